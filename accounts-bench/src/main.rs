@@ -7,6 +7,9 @@ use solana_runtime::{
 };
 use solana_sdk::{genesis_config::ClusterType, pubkey::Pubkey};
 use std::{collections::HashSet, env, fs, path::PathBuf};
+use workload::{AccountSizes, Workload};
+
+mod workload;
 
 fn main() {
     solana_logger::setup();
@@ -41,12 +44,44 @@ fn main() {
                 .takes_value(false)
                 .help("Run clean"),
         )
+        .arg(
+            Arg::with_name("workload")
+                .long("workload")
+                .takes_value(true)
+                .value_name("WORKLOAD")
+                .possible_values(Workload::NAMES)
+                .help("Run a workload and report its throughput and latency percentiles"),
+        )
+        .arg(
+            Arg::with_name("account_sizes")
+                .long("account_sizes")
+                .takes_value(true)
+                .value_name("mainnet|BYTES")
+                .default_value("mainnet")
+                .requires("workload")
+                .help(
+                    "Data size of the accounts stored by the workload: \"mainnet\" \
+                       samples the sizes from a mainnet-like distribution",
+                ),
+        )
         .get_matches();
 
     let num_slots = value_t!(matches, "num_slots", usize).unwrap_or(4);
     let num_accounts = value_t!(matches, "num_accounts", usize).unwrap_or(10_000);
     let iterations = value_t!(matches, "iterations", usize).unwrap_or(20);
     let clean = matches.is_present("clean");
+    let workload = value_t!(matches, "workload", Workload).ok();
+    let account_sizes = match matches
+        .value_of("account_sizes")
+        .unwrap()
+        .parse::<AccountSizes>()
+    {
+        Ok(account_sizes) => account_sizes,
+        Err(err) => {
+            eprintln!("Invalid --account_sizes: {}", err);
+            std::process::exit(1);
+        }
+    };
     println!("clean: {:?}", clean);
 
     let path = PathBuf::from(env::var("FARF_DIR").unwrap_or_else(|_| "farf".to_owned()))
@@ -56,6 +91,18 @@ fn main() {
     }
     let accounts =
         Accounts::new_with_config(vec![path], &ClusterType::Testnet, HashSet::new(), false);
+    if let Some(workload) = workload {
+        println!("Running {:?} workload", workload);
+        workload::run(
+            &accounts,
+            workload,
+            &account_sizes,
+            num_accounts,
+            num_slots,
+            iterations,
+        );
+        return;
+    }
     println!("Creating {} accounts", num_accounts);
     let mut create_time = Measure::start("create accounts");
     let pubkeys: Vec<_> = (0..num_slots)
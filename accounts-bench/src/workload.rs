@@ -0,0 +1,388 @@
+//! Workloads exercising the accounts-db write, read, clean and shrink paths, with account data
+//! sizes sampled from a distribution approximating mainnet-beta
+
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    seq::SliceRandom,
+    thread_rng, Rng,
+};
+use solana_measure::measure::Measure;
+use solana_runtime::{accounts::Accounts, accounts_index::Ancestors};
+use solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey};
+use std::{fmt, str::FromStr};
+
+/// Account data sizes and their share of the accounts of mainnet-beta: system accounts, token
+/// accounts, stake accounts, token mints, nonce accounts, vote accounts and larger program data
+const MAINNET_ACCOUNT_SIZES: &[(usize, u32)] = &[
+    (0, 540),
+    (165, 310),
+    (200, 90),
+    (82, 20),
+    (80, 10),
+    (3731, 20),
+    (10 * 1024, 8),
+    (100 * 1024, 2),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Workload {
+    Write,
+    Read,
+    Clean,
+    Shrink,
+}
+
+impl Workload {
+    pub const NAMES: &'static [&'static str] = &["write", "read", "clean", "shrink"];
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "write" => Ok(Self::Write),
+            "read" => Ok(Self::Read),
+            "clean" => Ok(Self::Clean),
+            "shrink" => Ok(Self::Shrink),
+            _ => Err(format!("unknown workload: {}", s)),
+        }
+    }
+}
+
+pub enum AccountSizes {
+    Mainnet(WeightedIndex<u32>),
+    Fixed(usize),
+}
+
+impl AccountSizes {
+    pub fn mainnet() -> Self {
+        Self::Mainnet(
+            WeightedIndex::new(MAINNET_ACCOUNT_SIZES.iter().map(|(_, weight)| *weight)).unwrap(),
+        )
+    }
+
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> usize {
+        match self {
+            Self::Mainnet(weights) => MAINNET_ACCOUNT_SIZES[weights.sample(rng)].0,
+            Self::Fixed(size) => *size,
+        }
+    }
+}
+
+impl FromStr for AccountSizes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "mainnet" {
+            Ok(Self::mainnet())
+        } else {
+            s.parse::<usize>()
+                .map(Self::Fixed)
+                .map_err(|_| format!("expected \"mainnet\" or a size in bytes: {}", s))
+        }
+    }
+}
+
+/// Latencies of the individual operations of a workload, in microseconds
+#[derive(Default)]
+pub struct LatencyStats {
+    name: &'static str,
+    latencies_us: Vec<u64>,
+    total_us: u64,
+}
+
+impl LatencyStats {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            ..Self::default()
+        }
+    }
+
+    pub fn record(&mut self, measure: &Measure) {
+        self.latencies_us.push(measure.as_us());
+    }
+
+    pub fn set_total(&mut self, measure: &Measure) {
+        self.total_us = measure.as_us();
+    }
+
+    fn percentile(&self, sorted_latencies_us: &[u64], percentile: usize) -> u64 {
+        if sorted_latencies_us.is_empty() {
+            return 0;
+        }
+        let index =
+            (sorted_latencies_us.len() * percentile / 100).min(sorted_latencies_us.len() - 1);
+        sorted_latencies_us[index]
+    }
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut sorted_latencies_us = self.latencies_us.clone();
+        sorted_latencies_us.sort_unstable();
+        let ops = sorted_latencies_us.len();
+        let ops_per_second = if self.total_us == 0 {
+            0.
+        } else {
+            ops as f64 * 1_000_000. / self.total_us as f64
+        };
+        write!(
+            f,
+            "{}: {} ops in {}us, {:.0} ops/s, latency us p50 {} p90 {} p99 {} max {}",
+            self.name,
+            ops,
+            self.total_us,
+            ops_per_second,
+            self.percentile(&sorted_latencies_us, 50),
+            self.percentile(&sorted_latencies_us, 90),
+            self.percentile(&sorted_latencies_us, 99),
+            sorted_latencies_us.last().cloned().unwrap_or_default(),
+        )
+    }
+}
+
+fn new_account<R: Rng>(rng: &mut R, account_sizes: &AccountSizes) -> Account {
+    Account::new(
+        rng.gen_range(1, 1_000_000),
+        account_sizes.sample(rng),
+        &Pubkey::default(),
+    )
+}
+
+// Stores an account for each of `pubkeys`, spread evenly over the slots starting at `first_slot`
+fn store_accounts(
+    accounts: &Accounts,
+    pubkeys: &[Pubkey],
+    account_sizes: &AccountSizes,
+    first_slot: Slot,
+    num_slots: usize,
+    stats: &mut LatencyStats,
+) {
+    let mut rng = thread_rng();
+    let accounts_per_slot = (pubkeys.len() + num_slots - 1) / num_slots;
+    for (i, pubkey) in pubkeys.iter().enumerate() {
+        let slot = first_slot + (i / accounts_per_slot.max(1)) as Slot;
+        let account = new_account(&mut rng, account_sizes);
+        let mut time = Measure::start("store");
+        accounts.store_slow_uncached(slot, pubkey, &account);
+        time.stop();
+        stats.record(&time);
+    }
+}
+
+fn add_roots(accounts: &Accounts, first_slot: Slot, num_slots: usize) {
+    for slot in first_slot..first_slot + num_slots as Slot {
+        accounts.add_root(slot);
+    }
+}
+
+/// Runs `workload` for `iterations` iterations over `num_accounts` accounts spread across
+/// `num_slots` slots per iteration, and prints the throughput and latency of each phase
+pub fn run(
+    accounts: &Accounts,
+    workload: Workload,
+    account_sizes: &AccountSizes,
+    num_accounts: usize,
+    num_slots: usize,
+    iterations: usize,
+) {
+    let num_slots = num_slots.max(1);
+    let pubkeys: Vec<_> = (0..num_accounts)
+        .map(|_| solana_sdk::pubkey::new_rand())
+        .collect();
+
+    let mut write_stats = LatencyStats::new("write");
+    let mut time = Measure::start("write");
+    store_accounts(
+        accounts,
+        &pubkeys,
+        account_sizes,
+        0,
+        num_slots,
+        &mut write_stats,
+    );
+    time.stop();
+    write_stats.set_total(&time);
+    add_roots(accounts, 0, num_slots);
+    println!("initial {}", write_stats);
+
+    let mut rng = thread_rng();
+    let ancestors = Ancestors::default();
+    for iteration in 1..=iterations {
+        let first_slot = (iteration * num_slots) as Slot;
+        match workload {
+            Workload::Write => {
+                let mut stats = LatencyStats::new("write");
+                let mut time = Measure::start("write");
+                store_accounts(
+                    accounts,
+                    &pubkeys,
+                    account_sizes,
+                    first_slot,
+                    num_slots,
+                    &mut stats,
+                );
+                time.stop();
+                stats.set_total(&time);
+                add_roots(accounts, first_slot, num_slots);
+                println!("iteration {} {}", iteration, stats);
+            }
+            Workload::Read => {
+                let mut stats = LatencyStats::new("read");
+                let mut time = Measure::start("read");
+                for _ in 0..num_accounts {
+                    let pubkey = pubkeys.choose(&mut rng).unwrap();
+                    let mut load_time = Measure::start("load");
+                    let account = accounts.load_slow(&ancestors, pubkey);
+                    load_time.stop();
+                    assert!(account.is_some());
+                    stats.record(&load_time);
+                }
+                time.stop();
+                stats.set_total(&time);
+                println!("iteration {} {}", iteration, stats);
+            }
+            Workload::Clean | Workload::Shrink => {
+                // Overwrite a random half of the accounts so the older slots hold dead accounts
+                let updated: Vec<_> = pubkeys
+                    .choose_multiple(&mut rng, num_accounts / 2)
+                    .cloned()
+                    .collect();
+                let mut write_stats = LatencyStats::new("write");
+                let mut time = Measure::start("write");
+                store_accounts(
+                    accounts,
+                    &updated,
+                    account_sizes,
+                    first_slot,
+                    num_slots,
+                    &mut write_stats,
+                );
+                time.stop();
+                write_stats.set_total(&time);
+                add_roots(accounts, first_slot, num_slots);
+
+                let mut clean_stats = LatencyStats::new("clean");
+                let mut time = Measure::start("clean");
+                accounts.accounts_db.clean_accounts(None);
+                time.stop();
+                clean_stats.record(&time);
+                clean_stats.set_total(&time);
+                println!("iteration {} {}", iteration, write_stats);
+                println!("iteration {} {}", iteration, clean_stats);
+
+                if workload == Workload::Shrink {
+                    let mut shrink_stats = LatencyStats::new("shrink");
+                    let mut time = Measure::start("shrink");
+                    accounts.accounts_db.shrink_all_slots();
+                    time.stop();
+                    shrink_stats.record(&time);
+                    shrink_stats.set_total(&time);
+                    println!("iteration {} {}", iteration, shrink_stats);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::genesis_config::ClusterType;
+    use std::collections::HashSet;
+
+    fn new_accounts() -> Accounts {
+        Accounts::new_with_config(Vec::new(), &ClusterType::Development, HashSet::new(), false)
+    }
+
+    #[test]
+    fn test_workload_from_str() {
+        let workloads: Vec<_> = Workload::NAMES
+            .iter()
+            .map(|name| name.parse::<Workload>().unwrap())
+            .collect();
+        assert_eq!(
+            workloads,
+            vec![
+                Workload::Write,
+                Workload::Read,
+                Workload::Clean,
+                Workload::Shrink
+            ]
+        );
+        assert!("compact".parse::<Workload>().is_err());
+    }
+
+    #[test]
+    fn test_account_sizes() {
+        let mut rng = thread_rng();
+
+        let sizes = "mainnet".parse::<AccountSizes>().unwrap();
+        for _ in 0..100 {
+            let size = sizes.sample(&mut rng);
+            assert!(MAINNET_ACCOUNT_SIZES.iter().any(|(s, _)| *s == size));
+        }
+
+        let sizes = "128".parse::<AccountSizes>().unwrap();
+        assert_eq!(sizes.sample(&mut rng), 128);
+
+        assert!("large".parse::<AccountSizes>().is_err());
+        assert!("-1".parse::<AccountSizes>().is_err());
+    }
+
+    #[test]
+    fn test_latency_percentile() {
+        let stats = LatencyStats::new("test");
+        assert_eq!(stats.percentile(&[], 50), 0);
+
+        let latencies_us: Vec<_> = (1..=100).collect();
+        assert_eq!(stats.percentile(&latencies_us, 50), 51);
+        assert_eq!(stats.percentile(&latencies_us, 99), 100);
+        assert_eq!(stats.percentile(&latencies_us, 100), 100);
+        assert_eq!(stats.percentile(&[7], 90), 7);
+    }
+
+    #[test]
+    fn test_store_accounts_spreads_over_slots() {
+        let accounts = new_accounts();
+        let pubkeys: Vec<_> = (0..10).map(|_| solana_sdk::pubkey::new_rand()).collect();
+        let mut stats = LatencyStats::new("write");
+        store_accounts(
+            &accounts,
+            &pubkeys,
+            &AccountSizes::Fixed(16),
+            5,
+            3,
+            &mut stats,
+        );
+        assert_eq!(stats.latencies_us.len(), pubkeys.len());
+
+        let ancestors: Ancestors = (5..8).map(|slot| (slot, 0)).collect();
+        let slots: Vec<_> = pubkeys
+            .iter()
+            .map(|pubkey| {
+                let (account, slot) = accounts.load_slow(&ancestors, pubkey).unwrap();
+                assert_eq!(account.data.len(), 16);
+                slot
+            })
+            .collect();
+        assert_eq!(slots, vec![5, 5, 5, 5, 6, 6, 6, 6, 7, 7]);
+    }
+
+    #[test]
+    fn test_run_workloads() {
+        for name in Workload::NAMES {
+            let accounts = new_accounts();
+            run(
+                &accounts,
+                name.parse().unwrap(),
+                &AccountSizes::mainnet(),
+                20,
+                2,
+                2,
+            );
+        }
+    }
+}
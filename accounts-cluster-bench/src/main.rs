@@ -0,0 +1,647 @@
+//! Generates sustained RPC load against a node, creating accounts, querying them with
+//! getAccountInfo and filtered getProgramAccounts and watching them with program subscriptions,
+//! and reports the latency percentiles of each method
+use clap::{crate_description, crate_name, value_t, value_t_or_exit, App, Arg};
+use log::*;
+use rand::{thread_rng, Rng};
+use solana_clap_utils::input_validators::{is_keypair, is_parsable, is_pubkey, is_url};
+use solana_client::{
+    pubsub_client::PubsubClient,
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    process::exit,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread::{sleep, spawn, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+// How long a blockhash is used before fetching a new one, well within its lifetime
+const BLOCKHASH_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Latencies of the successful calls of a method, with the count of failed ones
+#[derive(Clone, Debug, Default)]
+struct LatencyStats {
+    latencies_us: Vec<u64>,
+    errors: usize,
+}
+
+impl LatencyStats {
+    /// The latency below which `percentile` percent of the successful calls completed
+    fn percentile_us(&self, percentile: f64) -> Option<u64> {
+        if self.latencies_us.is_empty() {
+            return None;
+        }
+        let mut latencies_us = self.latencies_us.clone();
+        latencies_us.sort_unstable();
+        let rank = (percentile / 100.0 * latencies_us.len() as f64).ceil() as usize;
+        Some(latencies_us[rank.saturating_sub(1).min(latencies_us.len() - 1)])
+    }
+}
+
+#[derive(Clone, Default)]
+struct Stats(Arc<Mutex<BTreeMap<&'static str, LatencyStats>>>);
+
+impl Stats {
+    fn record_latency(&self, method: &'static str, latency: Duration) {
+        self.0
+            .lock()
+            .unwrap()
+            .entry(method)
+            .or_default()
+            .latencies_us
+            .push(latency.as_micros() as u64);
+    }
+
+    fn record_error(&self, method: &'static str) {
+        self.0.lock().unwrap().entry(method).or_default().errors += 1;
+    }
+
+    /// Calls `f`, recording how long it took or that it failed
+    fn measure<T, E: Display>(
+        &self,
+        method: &'static str,
+        f: impl FnOnce() -> Result<T, E>,
+    ) -> Option<T> {
+        let start = Instant::now();
+        match f() {
+            Ok(result) => {
+                self.record_latency(method, start.elapsed());
+                Some(result)
+            }
+            Err(err) => {
+                debug!("{} failed: {}", method, err);
+                self.record_error(method);
+                None
+            }
+        }
+    }
+
+    fn snapshot(&self) -> BTreeMap<&'static str, LatencyStats> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+fn print_stats(stats: &BTreeMap<&'static str, LatencyStats>, elapsed: Duration) {
+    let format_ms = |latency_us: Option<u64>| {
+        latency_us.map_or("-".to_string(), |latency_us| {
+            format!("{:.1}", latency_us as f64 / 1_000.0)
+        })
+    };
+    println!(
+        "{:<20} {:>8} {:>8} {:>8} {:>10} {:>10} {:>10} {:>10}",
+        "Method", "Calls", "Errors", "Calls/s", "p50 (ms)", "p90 (ms)", "p99 (ms)", "Max (ms)"
+    );
+    for (method, method_stats) in stats {
+        let calls = method_stats.latencies_us.len() + method_stats.errors;
+        println!(
+            "{:<20} {:>8} {:>8} {:>8.1} {:>10} {:>10} {:>10} {:>10}",
+            method,
+            calls,
+            method_stats.errors,
+            calls as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            format_ms(method_stats.percentile_us(50.0)),
+            format_ms(method_stats.percentile_us(90.0)),
+            format_ms(method_stats.percentile_us(99.0)),
+            format_ms(method_stats.percentile_us(100.0)),
+        );
+    }
+}
+
+struct BenchConfig {
+    json_rpc_url: String,
+    websocket_url: String,
+    payer: Arc<Keypair>,
+    /// Owner of the created accounts, their program accounts are queried and subscribed to
+    owner: Pubkey,
+    account_size: usize,
+    max_accounts: usize,
+    create_threads: usize,
+    account_info_threads: usize,
+    program_accounts_threads: usize,
+    subscriptions: usize,
+    duration: Duration,
+    report_interval: Duration,
+}
+
+/// Accounts created so far, with the time their creation was sent
+#[derive(Clone, Default)]
+struct CreatedAccounts {
+    pubkeys: Arc<RwLock<Vec<Pubkey>>>,
+    sent: Arc<RwLock<HashMap<Pubkey, Instant>>>,
+}
+
+impl CreatedAccounts {
+    fn len(&self) -> usize {
+        self.pubkeys.read().unwrap().len()
+    }
+
+    fn random(&self) -> Option<Pubkey> {
+        let pubkeys = self.pubkeys.read().unwrap();
+        if pubkeys.is_empty() {
+            None
+        } else {
+            Some(pubkeys[thread_rng().gen_range(0, pubkeys.len())])
+        }
+    }
+}
+
+fn program_accounts_filters(account_size: usize) -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::DataSize(account_size as u64),
+        // The created accounts are zeroed
+        RpcFilterType::Memcmp(Memcmp {
+            offset: 0,
+            bytes: MemcmpEncodedBytes::Binary(bs58_zeroes(account_size.min(4))),
+            encoding: None,
+        }),
+    ]
+}
+
+fn bs58_zeroes(len: usize) -> String {
+    // Base58 encodes each leading zero byte as a '1'
+    "1".repeat(len)
+}
+
+fn create_accounts(
+    config: &BenchConfig,
+    lamports: u64,
+    created_accounts: CreatedAccounts,
+    stats: Stats,
+    exit: Arc<AtomicBool>,
+) {
+    let rpc_client = RpcClient::new(config.json_rpc_url.clone());
+    let mut blockhash = None;
+    while !exit.load(Ordering::Relaxed) && created_accounts.len() < config.max_accounts {
+        let stale = blockhash.map_or(true, |(_, fetched): (_, Instant)| {
+            fetched.elapsed() >= BLOCKHASH_REFRESH_INTERVAL
+        });
+        if stale {
+            blockhash = stats
+                .measure("getRecentBlockhash", || rpc_client.get_recent_blockhash())
+                .map(|(blockhash, _fee_calculator)| (blockhash, Instant::now()));
+            if blockhash.is_none() {
+                sleep(Duration::from_secs(1));
+                continue;
+            }
+        }
+        let (recent_blockhash, _) = blockhash.unwrap();
+
+        let account = Keypair::new();
+        let instruction = system_instruction::create_account(
+            &config.payer.pubkey(),
+            &account.pubkey(),
+            lamports,
+            config.account_size as u64,
+            &config.owner,
+        );
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&config.payer.pubkey()),
+            &[config.payer.as_ref(), &account],
+            recent_blockhash,
+        );
+        created_accounts
+            .sent
+            .write()
+            .unwrap()
+            .insert(account.pubkey(), Instant::now());
+        if stats
+            .measure("sendTransaction", || {
+                rpc_client.send_transaction(&transaction)
+            })
+            .is_some()
+        {
+            created_accounts
+                .pubkeys
+                .write()
+                .unwrap()
+                .push(account.pubkey());
+        }
+    }
+}
+
+fn get_account_info(
+    config: &BenchConfig,
+    created_accounts: CreatedAccounts,
+    stats: Stats,
+    exit: Arc<AtomicBool>,
+) {
+    let rpc_client = RpcClient::new(config.json_rpc_url.clone());
+    while !exit.load(Ordering::Relaxed) {
+        match created_accounts.random() {
+            Some(pubkey) => {
+                stats.measure("getAccountInfo", || {
+                    rpc_client.get_account_with_commitment(&pubkey, CommitmentConfig::processed())
+                });
+            }
+            None => sleep(Duration::from_millis(100)),
+        }
+    }
+}
+
+fn get_program_accounts(config: &BenchConfig, stats: Stats, exit: Arc<AtomicBool>) {
+    let rpc_client = RpcClient::new(config.json_rpc_url.clone());
+    while !exit.load(Ordering::Relaxed) {
+        stats.measure("getProgramAccounts", || {
+            rpc_client.get_program_accounts_with_config(
+                &config.owner,
+                RpcProgramAccountsConfig {
+                    filters: Some(program_accounts_filters(config.account_size)),
+                    account_config: RpcAccountInfoConfig {
+                        commitment: Some(CommitmentConfig::processed()),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                },
+            )
+        });
+    }
+}
+
+/// Subscribes to the created accounts, measuring how long after its creation was sent each
+/// account is notified
+fn subscribe_program(
+    config: &BenchConfig,
+    created_accounts: CreatedAccounts,
+    stats: Stats,
+    exit: Arc<AtomicBool>,
+) {
+    let subscription = stats.measure("programSubscribe", || {
+        PubsubClient::program_subscribe(
+            &config.websocket_url,
+            &config.owner,
+            Some(RpcProgramAccountsConfig {
+                filters: Some(program_accounts_filters(config.account_size)),
+                account_config: RpcAccountInfoConfig {
+                    commitment: Some(CommitmentConfig::processed()),
+                    ..RpcAccountInfoConfig::default()
+                },
+            }),
+        )
+    });
+    let (subscription, receiver) = match subscription {
+        Some(subscription) => subscription,
+        None => return,
+    };
+    while !exit.load(Ordering::Relaxed) {
+        if let Ok(notification) = receiver.recv_timeout(Duration::from_millis(100)) {
+            let sent = notification
+                .value
+                .pubkey
+                .parse()
+                .ok()
+                .and_then(|pubkey| created_accounts.sent.read().unwrap().get(&pubkey).copied());
+            if let Some(sent) = sent {
+                stats.record_latency("programNotification", sent.elapsed());
+            }
+        }
+    }
+    // Dropping the subscription unsubscribes, which waits for its reader thread to release the
+    // web socket, and that only happens with the next notification.  Leave the web socket to be
+    // closed with the process instead
+    std::mem::forget(subscription);
+}
+
+fn spawn_workers(
+    name: &str,
+    count: usize,
+    config: &Arc<BenchConfig>,
+    worker: impl Fn(&BenchConfig) + Clone + Send + 'static,
+) -> Vec<JoinHandle<()>> {
+    (0..count)
+        .map(|i| {
+            let config = config.clone();
+            let worker = worker.clone();
+            Builder::new()
+                .name(format!("{}-{}", name, i))
+                .spawn(move || worker(&config))
+                .unwrap()
+        })
+        .collect()
+}
+
+fn run_bench(config: BenchConfig) -> BTreeMap<&'static str, LatencyStats> {
+    let rpc_client = RpcClient::new(config.json_rpc_url.clone());
+    let lamports = rpc_client
+        .get_minimum_balance_for_rent_exemption(config.account_size)
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to get the rent exempt minimum balance: {}", err);
+            exit(1);
+        });
+    let balance = rpc_client
+        .get_balance(&config.payer.pubkey())
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to get the balance of the payer: {}", err);
+            exit(1);
+        });
+    let max_affordable_accounts = (balance / lamports.max(1)) as usize;
+    if config.create_threads > 0 && max_affordable_accounts < config.max_accounts {
+        warn!(
+            "The payer's balance of {} lamports only funds {} accounts",
+            balance, max_affordable_accounts
+        );
+    }
+    info!(
+        "Creating up to {} accounts of {} bytes owned by {}",
+        config.max_accounts, config.account_size, config.owner
+    );
+
+    let config = Arc::new(config);
+    let stats = Stats::default();
+    let created_accounts = CreatedAccounts::default();
+    let exit = Arc::new(AtomicBool::new(false));
+
+    let mut workers = vec![];
+    workers.extend(spawn_workers(
+        "createAccounts",
+        config.create_threads,
+        &config,
+        {
+            let (created_accounts, stats, exit) =
+                (created_accounts.clone(), stats.clone(), exit.clone());
+            move |config: &BenchConfig| {
+                create_accounts(
+                    config,
+                    lamports,
+                    created_accounts.clone(),
+                    stats.clone(),
+                    exit.clone(),
+                )
+            }
+        },
+    ));
+    workers.extend(spawn_workers(
+        "getAccountInfo",
+        config.account_info_threads,
+        &config,
+        {
+            let (created_accounts, stats, exit) =
+                (created_accounts.clone(), stats.clone(), exit.clone());
+            move |config: &BenchConfig| {
+                get_account_info(
+                    config,
+                    created_accounts.clone(),
+                    stats.clone(),
+                    exit.clone(),
+                )
+            }
+        },
+    ));
+    workers.extend(spawn_workers(
+        "getProgramAccounts",
+        config.program_accounts_threads,
+        &config,
+        {
+            let (stats, exit) = (stats.clone(), exit.clone());
+            move |config: &BenchConfig| get_program_accounts(config, stats.clone(), exit.clone())
+        },
+    ));
+    workers.extend(spawn_workers(
+        "programSubscribe",
+        config.subscriptions,
+        &config,
+        {
+            let (created_accounts, stats, exit) =
+                (created_accounts.clone(), stats.clone(), exit.clone());
+            move |config: &BenchConfig| {
+                subscribe_program(
+                    config,
+                    created_accounts.clone(),
+                    stats.clone(),
+                    exit.clone(),
+                )
+            }
+        },
+    ));
+
+    let start = Instant::now();
+    let reporter = {
+        let (stats, exit, report_interval) = (stats.clone(), exit.clone(), config.report_interval);
+        spawn(move || {
+            let mut last_report = Instant::now();
+            while !exit.load(Ordering::Relaxed) {
+                sleep(Duration::from_millis(100));
+                if last_report.elapsed() >= report_interval {
+                    print_stats(&stats.snapshot(), start.elapsed());
+                    last_report = Instant::now();
+                }
+            }
+        })
+    };
+
+    sleep(config.duration);
+    exit.store(true, Ordering::Relaxed);
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    reporter.join().unwrap();
+
+    let stats = stats.snapshot();
+    println!(
+        "Created {} accounts in {:?}",
+        created_accounts.len(),
+        start.elapsed()
+    );
+    print_stats(&stats, start.elapsed());
+    stats
+}
+
+fn main() {
+    solana_logger::setup_with_default("solana=info");
+    let matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(solana_version::version!())
+        .arg(
+            Arg::with_name("json_rpc_url")
+                .long("url")
+                .short("u")
+                .value_name("URL")
+                .takes_value(true)
+                .default_value("http://127.0.0.1:8899")
+                .validator(is_url)
+                .help("JSON RPC URL of the node to load"),
+        )
+        .arg(
+            Arg::with_name("websocket_url")
+                .long("ws")
+                .value_name("URL")
+                .takes_value(true)
+                .validator(is_url)
+                .help("WebSocket URL of the node to load [default: derived from --url]"),
+        )
+        .arg(
+            Arg::with_name("identity")
+                .long("identity")
+                .value_name("KEYPAIR")
+                .takes_value(true)
+                .required(true)
+                .validator(is_keypair)
+                .help("Funded keypair paying for the created accounts"),
+        )
+        .arg(
+            Arg::with_name("owner")
+                .long("owner")
+                .value_name("PUBKEY")
+                .takes_value(true)
+                .validator(is_pubkey)
+                .help("Owner of the created accounts [default: a new address]"),
+        )
+        .arg(
+            Arg::with_name("account_size")
+                .long("account-size")
+                .value_name("BYTES")
+                .takes_value(true)
+                .default_value("165")
+                .validator(is_parsable::<usize>)
+                .help("Size of the created accounts"),
+        )
+        .arg(
+            Arg::with_name("max_accounts")
+                .long("max-accounts")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("10000")
+                .validator(is_parsable::<usize>)
+                .help("Stop creating accounts once this many were created"),
+        )
+        .arg(
+            Arg::with_name("create_threads")
+                .long("create-threads")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("2")
+                .validator(is_parsable::<usize>)
+                .help("Threads sending account creation transactions"),
+        )
+        .arg(
+            Arg::with_name("account_info_threads")
+                .long("account-info-threads")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("4")
+                .validator(is_parsable::<usize>)
+                .help("Threads calling getAccountInfo on the created accounts"),
+        )
+        .arg(
+            Arg::with_name("program_accounts_threads")
+                .long("program-accounts-threads")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("1")
+                .validator(is_parsable::<usize>)
+                .help("Threads calling getProgramAccounts with filters on the created accounts"),
+        )
+        .arg(
+            Arg::with_name("subscriptions")
+                .long("subscriptions")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value("4")
+                .validator(is_parsable::<usize>)
+                .help("Program subscriptions notified of the created accounts"),
+        )
+        .arg(
+            Arg::with_name("duration")
+                .long("duration")
+                .value_name("SECS")
+                .takes_value(true)
+                .default_value("60")
+                .validator(is_parsable::<u64>)
+                .help("How long to load the node"),
+        )
+        .arg(
+            Arg::with_name("report_interval")
+                .long("report-interval")
+                .value_name("SECS")
+                .takes_value(true)
+                .default_value("10")
+                .validator(is_parsable::<u64>)
+                .help("How often to report the latencies so far"),
+        )
+        .get_matches();
+
+    let json_rpc_url = value_t_or_exit!(matches, "json_rpc_url", String);
+    let websocket_url = value_t!(matches, "websocket_url", String)
+        .unwrap_or_else(|_| solana_cli_config::Config::compute_websocket_url(&json_rpc_url));
+    let payer = read_keypair_file(matches.value_of("identity").unwrap()).unwrap_or_else(|err| {
+        eprintln!("Failed to read the identity keypair: {}", err);
+        exit(1);
+    });
+    let owner = value_t!(matches, "owner", Pubkey).unwrap_or_else(|_| Pubkey::new_unique());
+
+    run_bench(BenchConfig {
+        json_rpc_url,
+        websocket_url,
+        payer: Arc::new(payer),
+        owner,
+        account_size: value_t_or_exit!(matches, "account_size", usize),
+        max_accounts: value_t_or_exit!(matches, "max_accounts", usize),
+        create_threads: value_t_or_exit!(matches, "create_threads", usize),
+        account_info_threads: value_t_or_exit!(matches, "account_info_threads", usize),
+        program_accounts_threads: value_t_or_exit!(matches, "program_accounts_threads", usize),
+        subscriptions: value_t_or_exit!(matches, "subscriptions", usize),
+        duration: Duration::from_secs(value_t_or_exit!(matches, "duration", u64)),
+        report_interval: Duration::from_secs(value_t_or_exit!(matches, "report_interval", u64)),
+    });
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use solana_core::test_validator::TestValidator;
+
+    #[test]
+    fn test_latency_stats_percentile() {
+        let stats = LatencyStats::default();
+        assert_eq!(stats.percentile_us(50.0), None);
+
+        let stats = LatencyStats {
+            latencies_us: (1..=100).rev().collect(),
+            errors: 0,
+        };
+        assert_eq!(stats.percentile_us(50.0), Some(50));
+        assert_eq!(stats.percentile_us(99.0), Some(99));
+        assert_eq!(stats.percentile_us(100.0), Some(100));
+        assert_eq!(stats.percentile_us(0.0), Some(1));
+    }
+
+    #[test]
+    fn test_accounts_cluster_bench() {
+        solana_logger::setup();
+        let mint_keypair = Keypair::new();
+        let test_validator = TestValidator::with_no_fees(mint_keypair.pubkey());
+
+        let stats = run_bench(BenchConfig {
+            json_rpc_url: test_validator.rpc_url(),
+            websocket_url: test_validator.rpc_pubsub_url(),
+            payer: Arc::new(mint_keypair),
+            owner: Pubkey::new_unique(),
+            account_size: 16,
+            max_accounts: 10,
+            create_threads: 1,
+            account_info_threads: 1,
+            program_accounts_threads: 1,
+            subscriptions: 1,
+            duration: Duration::from_secs(5),
+            report_interval: Duration::from_secs(1),
+        });
+        assert!(!stats["sendTransaction"].latencies_us.is_empty());
+        assert!(!stats["getAccountInfo"].latencies_us.is_empty());
+        assert!(!stats["getProgramAccounts"].latencies_us.is_empty());
+        assert_eq!(stats["programSubscribe"].latencies_us.len(), 1);
+    }
+}
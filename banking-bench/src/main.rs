@@ -1,4 +1,4 @@
-use clap::{crate_description, crate_name, value_t, App, Arg};
+use clap::{crate_description, crate_name, value_t, value_t_or_exit, App, Arg};
 use crossbeam_channel::unbounded;
 use log::*;
 use rand::{thread_rng, Rng};
@@ -29,11 +29,15 @@ use solana_sdk::{
     transaction::Transaction,
 };
 use std::{
+    path::PathBuf,
+    process::exit,
     sync::{atomic::Ordering, mpsc::Receiver, Arc, Mutex},
     thread::sleep,
     time::{Duration, Instant},
 };
 
+mod replay;
+
 fn check_txs(
     receiver: &Arc<Receiver<WorkingBankEntry>>,
     ref_tx_count: usize,
@@ -149,6 +153,33 @@ fn main() {
                 .takes_value(true)
                 .help("Number of iterations"),
         )
+        .arg(
+            Arg::with_name("replay_packets")
+                .long("replay-packets")
+                .takes_value(true)
+                .value_name("PATH")
+                .requires("ledger_path")
+                .help(
+                    "Replay the packets of a pcap capture or packet-batch file instead of \
+                       generating transfers",
+                ),
+        )
+        .arg(
+            Arg::with_name("replay_port")
+                .long("replay-port")
+                .takes_value(true)
+                .value_name("PORT")
+                .requires("replay_packets")
+                .help("Only replay the captured UDP datagrams sent to this port"),
+        )
+        .arg(
+            Arg::with_name("ledger_path")
+                .long("ledger")
+                .takes_value(true)
+                .value_name("DIR")
+                .requires("replay_packets")
+                .help("Ledger whose latest snapshot provides the bank to replay the packets on"),
+        )
         .get_matches();
 
     let num_threads =
@@ -158,6 +189,19 @@ fn main() {
     let packets_per_chunk = value_t!(matches, "packets_per_chunk", usize).unwrap_or(192);
     let iterations = value_t!(matches, "iterations", usize).unwrap_or(1000);
 
+    if let Some(replay_packets) = matches.value_of("replay_packets") {
+        let replay_port = value_t!(matches, "replay_port", u16).ok();
+        let packets = replay::read_packets(&PathBuf::from(replay_packets), replay_port)
+            .unwrap_or_else(|err| {
+                eprintln!("Unable to read {}: {}", replay_packets, err);
+                exit(1);
+            });
+        let ledger_path = PathBuf::from(value_t_or_exit!(matches, "ledger_path", String));
+        let bank_forks = replay::load_bank_forks(&ledger_path);
+        replay::run(bank_forks, packets, packets_per_chunk);
+        return;
+    }
+
     let total_num_transactions = num_chunks * num_threads * packets_per_chunk;
     let mint_total = 1_000_000_000_000;
     let GenesisConfigInfo {
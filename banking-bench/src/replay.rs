@@ -0,0 +1,453 @@
+//! Replays captured transaction packets through sigverify and the banking stage, on top of a bank
+//! loaded from a ledger snapshot.  Packets are read either from a pcap capture of the TPU traffic
+//! or from a packet-batch file, a sequence of packets each preceded by its length as a
+//! little-endian u32
+
+use crossbeam_channel::unbounded;
+use log::*;
+use solana_core::{
+    banking_stage::{create_test_recorder, BankingStage},
+    cluster_info::{ClusterInfo, Node},
+    sigverify::TransactionSigVerifier,
+    sigverify_stage::SigVerifier,
+};
+use solana_ledger::{
+    bank_forks_utils, blockstore::Blockstore, blockstore_db::AccessType,
+    blockstore_processor::ProcessOptions, get_tmp_ledger_path,
+};
+use solana_measure::measure::Measure;
+use solana_perf::packet::{Packet, Packets, PACKET_DATA_SIZE};
+use solana_runtime::{
+    bank::Bank,
+    bank_forks::{ArchiveFormat, BankForks, SnapshotConfig},
+    hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
+    snapshot_utils::SnapshotVersion,
+};
+use solana_sdk::{
+    program_utils::limited_deserialize, signature::Signature, timing::duration_as_us,
+    transaction::Transaction,
+};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    process::exit,
+    sync::{atomic::Ordering, Arc},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+// Stop waiting for the banking stage once it has not recorded anything for this long
+const IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IP_PROTOCOL_UDP: u8 = 17;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Reads the packets of a pcap capture or of a packet-batch file.  The UDP datagrams of a pcap
+/// capture can be restricted to the ones sent to `port`
+pub fn read_packets(path: &Path, port: Option<u16>) -> io::Result<Vec<Packet>> {
+    let data = fs::read(path)?;
+    let payloads = if pcap_endianness(&data).is_some() {
+        parse_pcap(&data, port)?
+    } else {
+        parse_packet_batch_file(&data)?
+    };
+
+    let mut skipped = 0;
+    let packets: Vec<_> = payloads
+        .into_iter()
+        .filter_map(|payload| {
+            if payload.is_empty() || payload.len() > PACKET_DATA_SIZE {
+                skipped += 1;
+                return None;
+            }
+            let mut packet = Packet::default();
+            packet.data[..payload.len()].copy_from_slice(payload);
+            packet.meta.size = payload.len();
+            Some(packet)
+        })
+        .collect();
+    if skipped > 0 {
+        warn!("Skipped {} packets of invalid size", skipped);
+    }
+    Ok(packets)
+}
+
+// Whether the pcap header is little endian, or None if the data is not a pcap capture
+fn pcap_endianness(data: &[u8]) -> Option<bool> {
+    let magic: [u8; 4] = data.get(..4)?.try_into().ok()?;
+    match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+        (PCAP_MAGIC_MICROS, _) | (PCAP_MAGIC_NANOS, _) => Some(true),
+        (_, PCAP_MAGIC_MICROS) | (_, PCAP_MAGIC_NANOS) => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_pcap(data: &[u8], port: Option<u16>) -> io::Result<Vec<&[u8]>> {
+    let little_endian = pcap_endianness(data).ok_or_else(|| invalid_data("not a pcap file"))?;
+    let read_u32 = |bytes: &[u8]| {
+        let bytes: [u8; 4] = bytes.try_into().unwrap();
+        if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    };
+    if data.len() < 24 {
+        return Err(invalid_data("truncated pcap header"));
+    }
+    let link_type = read_u32(&data[20..24]);
+    if ![LINKTYPE_ETHERNET, LINKTYPE_RAW, LINKTYPE_LINUX_SLL].contains(&link_type) {
+        return Err(invalid_data(&format!(
+            "unsupported pcap link type: {}",
+            link_type
+        )));
+    }
+
+    let mut payloads = vec![];
+    let mut offset = 24;
+    while offset < data.len() {
+        let record_header = data
+            .get(offset..offset + 16)
+            .ok_or_else(|| invalid_data("truncated pcap record header"))?;
+        let captured_len = read_u32(&record_header[8..12]) as usize;
+        offset += 16;
+        let frame = data
+            .get(offset..offset + captured_len)
+            .ok_or_else(|| invalid_data("truncated pcap record"))?;
+        offset += captured_len;
+        if let Some((dst_port, payload)) = udp_payload(link_type, frame) {
+            if port.map(|port| port == dst_port).unwrap_or(true) {
+                payloads.push(payload);
+            }
+        }
+    }
+    Ok(payloads)
+}
+
+// Destination port and payload of a captured UDP datagram, None for any other frame
+fn udp_payload(link_type: u32, frame: &[u8]) -> Option<(u16, &[u8])> {
+    let read_u16 = |bytes: &[u8], offset: usize| -> Option<u16> {
+        Some(u16::from_be_bytes(
+            bytes.get(offset..offset + 2)?.try_into().ok()?,
+        ))
+    };
+    let (ethertype, ip_packet) = match link_type {
+        LINKTYPE_ETHERNET => match read_u16(frame, 12)? {
+            ETHERTYPE_VLAN => (read_u16(frame, 16)?, frame.get(18..)?),
+            ethertype => (ethertype, frame.get(14..)?),
+        },
+        LINKTYPE_LINUX_SLL => (read_u16(frame, 14)?, frame.get(16..)?),
+        _ => match frame.first()? >> 4 {
+            4 => (ETHERTYPE_IPV4, frame),
+            6 => (ETHERTYPE_IPV6, frame),
+            _ => return None,
+        },
+    };
+    let udp_datagram = match ethertype {
+        ETHERTYPE_IPV4 => {
+            let header_len = (*ip_packet.first()? as usize & 0x0f) * 4;
+            let fragment = read_u16(ip_packet, 6)?;
+            // Reassembling fragmented datagrams is not supported
+            if *ip_packet.get(9)? != IP_PROTOCOL_UDP || fragment & 0x3fff != 0 {
+                return None;
+            }
+            ip_packet.get(header_len..)?
+        }
+        ETHERTYPE_IPV6 => {
+            if *ip_packet.get(6)? != IP_PROTOCOL_UDP {
+                return None;
+            }
+            ip_packet.get(40..)?
+        }
+        _ => return None,
+    };
+    let dst_port = read_u16(udp_datagram, 2)?;
+    let udp_len = read_u16(udp_datagram, 4)? as usize;
+    Some((dst_port, udp_datagram.get(8..udp_len.max(8))?))
+}
+
+fn parse_packet_batch_file(data: &[u8]) -> io::Result<Vec<&[u8]>> {
+    let mut payloads = vec![];
+    let mut offset = 0;
+    while offset < data.len() {
+        let len = data
+            .get(offset..offset + 4)
+            .ok_or_else(|| invalid_data("truncated packet length"))?;
+        let len = u32::from_le_bytes(len.try_into().unwrap()) as usize;
+        offset += 4;
+        payloads.push(
+            data.get(offset..offset + len)
+                .ok_or_else(|| invalid_data("truncated packet"))?,
+        );
+        offset += len;
+    }
+    Ok(payloads)
+}
+
+/// Loads the bank of the latest snapshot archive of the ledger at `ledger_path`, without
+/// replaying the ledger past it
+pub fn load_bank_forks(ledger_path: &Path) -> BankForks {
+    let genesis_config = open_genesis_config(ledger_path, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE);
+    let blockstore = Blockstore::open_with_access_type(
+        ledger_path,
+        AccessType::TryPrimaryThenSecondary,
+        None,
+        true,
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Failed to open ledger at {:?}: {:?}", ledger_path, err);
+        exit(1);
+    });
+    // Use dedicated directories, the snapshot path is wiped on load and the ledger may belong
+    // to a running validator
+    let snapshot_config = SnapshotConfig {
+        snapshot_interval_slots: 0, // Value doesn't matter
+        snapshot_package_output_path: ledger_path.to_path_buf(),
+        snapshot_path: ledger_path.join("snapshot.banking-bench"),
+        archive_format: ArchiveFormat::TarBzip2,
+        snapshot_version: SnapshotVersion::default(),
+    };
+    let process_options = ProcessOptions {
+        dev_halt_at_slot: Some(0),
+        poh_verify: false,
+        ..ProcessOptions::default()
+    };
+    match bank_forks_utils::load(
+        &genesis_config,
+        &blockstore,
+        vec![ledger_path.join("accounts.banking-bench")],
+        None,
+        Some(&snapshot_config),
+        process_options,
+        None,
+    ) {
+        Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => bank_forks,
+        Err(err) => {
+            eprintln!("Failed to load ledger: {:?}", err);
+            exit(1);
+        }
+    }
+}
+
+/// Sends `packets` through sigverify and the banking stage, on top of a child of the working
+/// bank of `bank_forks`, and reports the throughput of both stages and how far the banking stage
+/// reordered the transactions relative to their capture order
+pub fn run(mut bank_forks: BankForks, packets: Vec<Packet>, packets_per_batch: usize) {
+    // Capture order of each transaction, by signature
+    let mut capture_indexes = HashMap::new();
+    for packet in &packets {
+        if let Ok(tx) = limited_deserialize::<Transaction>(&packet.data[..packet.meta.size]) {
+            if let Some(signature) = tx.signatures.first() {
+                let capture_index = capture_indexes.len();
+                capture_indexes.entry(*signature).or_insert(capture_index);
+            }
+        }
+    }
+    let num_packets = packets.len();
+    let batches: Vec<_> = packets
+        .chunks(packets_per_batch.max(1))
+        .map(|chunk| Packets::new(chunk.to_vec()))
+        .collect();
+    info!(
+        "replaying {} packets, {} transactions, in {} batches",
+        num_packets,
+        capture_indexes.len(),
+        batches.len()
+    );
+
+    let verifier = TransactionSigVerifier::default();
+    let mut sigverify_time = Measure::start("sigverify");
+    let batches = verifier.verify_batch(batches);
+    sigverify_time.stop();
+    let num_verified = batches
+        .iter()
+        .flat_map(|batch| batch.packets.iter())
+        .filter(|packet| !packet.meta.discard)
+        .count();
+
+    let parent = bank_forks.working_bank();
+    let collector = solana_sdk::pubkey::new_rand();
+    let mut bank = bank_forks.insert(Bank::new_from_parent(
+        &parent,
+        &collector,
+        parent.slot() + 1,
+    ));
+    let base_tx_count = bank.transaction_count();
+    let first_slot = bank.slot();
+
+    let ledger_path = get_tmp_ledger_path!();
+    {
+        let blockstore = Arc::new(
+            Blockstore::open(&ledger_path).expect("Expected to be able to open database ledger"),
+        );
+        let (verified_sender, verified_receiver) = unbounded();
+        let (vote_sender, vote_receiver) = unbounded();
+        let (replay_vote_sender, _replay_vote_receiver) = unbounded();
+        let (exit, poh_recorder, poh_service, signal_receiver) =
+            create_test_recorder(&bank, &blockstore, None);
+        let cluster_info = ClusterInfo::new_with_invalid_keypair(Node::new_localhost().info);
+        let cluster_info = Arc::new(cluster_info);
+        let banking_stage = BankingStage::new(
+            &cluster_info,
+            &poh_recorder,
+            verified_receiver,
+            vote_receiver,
+            None,
+            replay_vote_sender,
+        );
+        poh_recorder.lock().unwrap().set_bank(&bank);
+
+        let now = Instant::now();
+        verified_sender.send(batches).unwrap();
+
+        let mut num_recorded = 0;
+        let mut num_reordered = 0;
+        let mut total_displacement = 0;
+        let mut max_capture_index = None;
+        let mut last_recorded = Instant::now();
+        while num_recorded < capture_indexes.len() && last_recorded.elapsed() < IDLE_TIMEOUT {
+            if let Ok((_bank, (entry, _tick_height))) =
+                signal_receiver.recv_timeout(Duration::from_millis(10))
+            {
+                for tx in &entry.transactions {
+                    let capture_index = match tx
+                        .signatures
+                        .first()
+                        .and_then(|signature: &Signature| capture_indexes.get(signature))
+                    {
+                        Some(capture_index) => *capture_index,
+                        None => continue,
+                    };
+                    // Recorded after a transaction captured later than it
+                    if max_capture_index.map_or(false, |max| capture_index < max) {
+                        num_reordered += 1;
+                    }
+                    max_capture_index = max_capture_index.max(Some(capture_index));
+                    total_displacement += (capture_index as i64 - num_recorded as i64).abs();
+                    num_recorded += 1;
+                    last_recorded = Instant::now();
+                }
+            }
+
+            if poh_recorder.lock().unwrap().bank().is_none() {
+                poh_recorder.lock().unwrap().reset(
+                    bank.last_blockhash(),
+                    bank.slot(),
+                    Some((bank.slot(), bank.slot() + 1)),
+                );
+                let new_bank = Bank::new_from_parent(&bank, &collector, bank.slot() + 1);
+                bank = bank_forks.insert(new_bank);
+                poh_recorder.lock().unwrap().set_bank(&bank);
+            }
+        }
+        let elapsed_us = duration_as_us(&now.elapsed());
+
+        let txs_processed = bank.transaction_count() - base_tx_count;
+        println!(
+            "sigverify: {} of {} packets verified in {}us, {:.2} packets/s",
+            num_verified,
+            num_packets,
+            sigverify_time.as_us(),
+            1_000_000. * num_packets as f64 / sigverify_time.as_us().max(1) as f64,
+        );
+        println!(
+            "banking: {} transactions recorded, {} processed successfully in {}us over {} slots, \
+             {:.2} recorded/s",
+            num_recorded,
+            txs_processed,
+            elapsed_us,
+            bank.slot() - first_slot + 1,
+            1_000_000. * num_recorded as f64 / elapsed_us.max(1) as f64,
+        );
+        println!(
+            "ordering: {} transactions recorded after a later captured one, \
+             mean displacement from capture order {:.2}",
+            num_reordered,
+            total_displacement as f64 / num_recorded.max(1) as f64,
+        );
+
+        drop(verified_sender);
+        drop(vote_sender);
+        exit.store(true, Ordering::Relaxed);
+        banking_stage.join().unwrap();
+        poh_service.join().unwrap();
+        sleep(Duration::from_secs(1));
+    }
+    let _unused = Blockstore::destroy(&ledger_path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_frame(dst_port: u16, payload: &[u8]) -> Vec<u8> {
+        let udp_len = (8 + payload.len()) as u16;
+        let mut frame = vec![0; 12];
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        let mut ipv4_header = vec![0x45, 0, 0, 0, 0, 0, 0, 0, 64, IP_PROTOCOL_UDP];
+        ipv4_header.resize(20, 0);
+        frame.extend_from_slice(&ipv4_header);
+        frame.extend_from_slice(&8000u16.to_be_bytes());
+        frame.extend_from_slice(&dst_port.to_be_bytes());
+        frame.extend_from_slice(&udp_len.to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_parse_pcap() {
+        let mut pcap = vec![];
+        pcap.extend_from_slice(&PCAP_MAGIC_MICROS.to_le_bytes());
+        pcap.extend_from_slice(&[2, 0, 4, 0]);
+        pcap.extend_from_slice(&[0; 12]);
+        pcap.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+        for (dst_port, payload) in &[(8003, &[1, 2, 3][..]), (8004, &[4][..]), (8003, &[5][..])] {
+            let frame = udp_frame(*dst_port, payload);
+            pcap.extend_from_slice(&[0; 8]);
+            pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            pcap.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            pcap.extend_from_slice(&frame);
+        }
+
+        assert_eq!(pcap_endianness(&pcap), Some(true));
+        assert_eq!(
+            parse_pcap(&pcap, None).unwrap(),
+            vec![&[1, 2, 3][..], &[4][..], &[5][..]]
+        );
+        assert_eq!(
+            parse_pcap(&pcap, Some(8003)).unwrap(),
+            vec![&[1, 2, 3][..], &[5][..]]
+        );
+        assert!(parse_pcap(&pcap[..pcap.len() - 1], None).is_err());
+    }
+
+    #[test]
+    fn test_parse_packet_batch_file() {
+        let mut data = vec![];
+        for payload in &[&[1, 2][..], &[3][..]] {
+            data.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            data.extend_from_slice(payload);
+        }
+        assert_eq!(pcap_endianness(&data), None);
+        assert_eq!(
+            parse_packet_batch_file(&data).unwrap(),
+            vec![&[1, 2][..], &[3][..]]
+        );
+        assert!(parse_packet_batch_file(&data[..data.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{input_validators, ArgConstant};
+use clap::Arg;
+
+pub const COMMITMENT_ARG: ArgConstant<'static> = ArgConstant {
+    name: "commitment",
+    long: "commitment",
+    help: "Return information at the selected commitment level \
+           [possible values: processed, confirmed, finalized]",
+};
+
+pub fn commitment_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(COMMITMENT_ARG.name)
+        .long(COMMITMENT_ARG.long)
+        .takes_value(true)
+        .value_name("COMMITMENT_LEVEL")
+        .validator(input_validators::is_commitment)
+        .help(COMMITMENT_ARG.help)
+}
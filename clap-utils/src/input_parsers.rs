@@ -9,11 +9,11 @@ use solana_sdk::{
     clock::UnixTimestamp,
     commitment_config::CommitmentConfig,
     genesis_config::ClusterType,
-    native_token::sol_to_lamports,
+    native_token::LAMPORTS_PER_SOL,
     pubkey::Pubkey,
     signature::{read_keypair_file, Keypair, Signature, Signer},
 };
-use std::{str::FromStr, sync::Arc};
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 // Return parsed values from matches at `name`
 pub fn values_of<T>(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<T>>
@@ -88,17 +88,25 @@ pub fn pubkey_of(matches: &ArgMatches<'_>, name: &str) -> Option<Pubkey> {
 pub fn pubkeys_of(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<Pubkey>> {
     matches.values_of(name).map(|values| {
         values
-            .map(|value| {
-                value.parse::<Pubkey>().unwrap_or_else(|_| {
-                    read_keypair_file(value)
-                        .expect("read_keypair_file failed")
-                        .pubkey()
-                })
-            })
+            .map(|value| pubkey_from_str_or_keypair_path(value).unwrap())
             .collect()
     })
 }
 
+// Return the pubkey of a string that is either a pubkey or the path of a keypair file
+pub fn pubkey_from_str_or_keypair_path(value: &str) -> Result<Pubkey, String> {
+    value.parse::<Pubkey>().or_else(|_| {
+        read_keypair_file(value)
+            .map(|keypair| keypair.pubkey())
+            .map_err(|err| {
+                format!(
+                    "Unable to parse '{}' as a pubkey or read it as a keypair file: {}",
+                    value, err
+                )
+            })
+    })
+}
+
 // Return pubkey/signature pairs for a string of the form pubkey=signature
 pub fn pubkeys_sigs_of(matches: &ArgMatches<'_>, name: &str) -> Option<Vec<(Pubkey, Signature)>> {
     matches.values_of(name).map(|values| {
@@ -176,7 +184,80 @@ pub fn resolve_signer(
 }
 
 pub fn lamports_of_sol(matches: &ArgMatches<'_>, name: &str) -> Option<u64> {
-    value_of(matches, name).map(sol_to_lamports)
+    matches
+        .value_of(name)
+        .and_then(|value| lamports_from_sol_str(value).ok())
+}
+
+// Convert a decimal SOL amount to lamports without rounding through a float, rejecting amounts
+// with more than 9 decimals or that overflow
+pub fn lamports_from_sol_str(value: &str) -> Result<u64, String> {
+    let (whole, fraction) = match value.find('.') {
+        Some(index) => (&value[..index], &value[index + 1..]),
+        None => (value, ""),
+    };
+    let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(format!("Unable to parse SOL amount, provided: {}", value));
+    }
+    if fraction.len() > 9 {
+        return Err(format!(
+            "SOL amounts have at most 9 decimals, provided: {}",
+            value
+        ));
+    }
+    let too_large = || format!("SOL amount is too large, provided: {}", value);
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<u64>().map_err(|_| too_large())?
+    };
+    let fraction = format!("{:0<9}", fraction).parse::<u64>().unwrap();
+    whole
+        .checked_mul(LAMPORTS_PER_SOL)
+        .and_then(|lamports| lamports.checked_add(fraction))
+        .ok_or_else(too_large)
+}
+
+// Return the duration at `name`, see `parse_duration()`
+pub fn duration_of(
+    matches: &ArgMatches<'_>,
+    name: &str,
+    bare_number_unit: Duration,
+) -> Option<Duration> {
+    matches
+        .value_of(name)
+        .and_then(|value| parse_duration(value, bare_number_unit).ok())
+}
+
+// Parse a duration with a `ms`, `s`, `m` or `h` unit, e.g. `100ms` or `5s`.  A number without unit
+// is a multiple of `bare_number_unit`, so that flags that used to take a plain number of seconds
+// or milliseconds still accept it
+pub fn parse_duration(value: &str, bare_number_unit: Duration) -> Result<Duration, String> {
+    let unit_index = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_index);
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| format!("Unable to parse duration, provided: {}", value))?;
+    let unit_ms = match unit {
+        "" => bare_number_unit.as_millis() as u64,
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => {
+            return Err(format!(
+                "Unknown duration unit '{}', expected one of ms, s, m or h",
+                unit
+            ))
+        }
+    };
+    number
+        .checked_mul(unit_ms)
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("Duration is too long, provided: {}", value))
 }
 
 pub fn cluster_type_of(matches: &ArgMatches<'_>, name: &str) -> Option<ClusterType> {
@@ -363,4 +444,42 @@ mod tests {
             .get_matches_from(vec!["test", "--single", "0.03"]);
         assert_eq!(lamports_of_sol(&matches, "single"), Some(30_000_000));
     }
+
+    #[test]
+    fn test_lamports_from_sol_str() {
+        assert_eq!(lamports_from_sol_str("1"), Ok(1_000_000_000));
+        assert_eq!(lamports_from_sol_str("1."), Ok(1_000_000_000));
+        assert_eq!(lamports_from_sol_str(".5"), Ok(500_000_000));
+        assert_eq!(lamports_from_sol_str("0.000000001"), Ok(1));
+        assert_eq!(lamports_from_sol_str("18446744073.709551615"), Ok(u64::MAX));
+        assert!(lamports_from_sol_str("18446744073.709551616").is_err());
+        assert!(lamports_from_sol_str("0.0000000001").is_err());
+        assert!(lamports_from_sol_str(".").is_err());
+        assert!(lamports_from_sol_str("").is_err());
+        assert!(lamports_from_sol_str("-1").is_err());
+        assert!(lamports_from_sol_str("1e3").is_err());
+        assert!(lamports_from_sol_str("1.2.3").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let secs = Duration::from_secs(1);
+        assert_eq!(
+            parse_duration("100ms", secs),
+            Ok(Duration::from_millis(100))
+        );
+        assert_eq!(parse_duration("5s", secs), Ok(Duration::from_secs(5)));
+        assert_eq!(parse_duration("2m", secs), Ok(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h", secs), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("7", secs), Ok(Duration::from_secs(7)));
+        assert_eq!(
+            parse_duration("7", Duration::from_millis(1)),
+            Ok(Duration::from_millis(7))
+        );
+        assert!(parse_duration("", secs).is_err());
+        assert!(parse_duration("s", secs).is_err());
+        assert!(parse_duration("5d", secs).is_err());
+        assert!(parse_duration("1.5s", secs).is_err());
+        assert!(parse_duration("18446744073709551615h", secs).is_err());
+    }
 }
@@ -1,13 +1,18 @@
-use crate::keypair::{parse_keypair_path, KeypairUrl, ASK_KEYWORD};
+use crate::{
+    input_parsers::{lamports_from_sol_str, parse_duration, pubkey_from_str_or_keypair_path},
+    keypair::{parse_keypair_path, KeypairUrl, ASK_KEYWORD},
+};
 use chrono::DateTime;
 use solana_sdk::{
     clock::{Epoch, Slot},
+    commitment_config::CommitmentConfig,
     hash::Hash,
     pubkey::Pubkey,
     signature::{read_keypair_file, Signature},
 };
 use std::fmt::Display;
 use std::str::FromStr;
+use std::time::Duration;
 
 fn is_parsable_generic<U, T>(string: T) -> Result<(), String>
 where
@@ -76,7 +81,7 @@ pub fn is_pubkey_or_keypair<T>(string: T) -> Result<(), String>
 where
     T: AsRef<str> + Display,
 {
-    is_pubkey(string.as_ref()).or_else(|_| is_keypair(string))
+    pubkey_from_str_or_keypair_path(string.as_ref()).map(|_| ())
 }
 
 // Return an error if string cannot be parsed as a pubkey string, or a valid Signer that can
@@ -252,6 +257,49 @@ where
     }
 }
 
+// Return an error if string is not a SOL amount with at most 9 decimals
+pub fn is_sol_amount<T>(amount: T) -> Result<(), String>
+where
+    T: AsRef<str> + Display,
+{
+    lamports_from_sol_str(amount.as_ref()).map(|_| ())
+}
+
+// Return an error if string is neither a SOL amount nor the ALL keyword
+pub fn is_sol_amount_or_all<T>(amount: T) -> Result<(), String>
+where
+    T: AsRef<str> + Display,
+{
+    if amount.as_ref() == "ALL" {
+        Ok(())
+    } else {
+        is_sol_amount(amount)
+    }
+}
+
+// Return an error if string is not a duration such as `100ms` or `5s`, or a bare number
+pub fn is_duration<T>(duration: T) -> Result<(), String>
+where
+    T: AsRef<str> + Display,
+{
+    parse_duration(duration.as_ref(), Duration::from_millis(1)).map(|_| ())
+}
+
+// Return an error if string is not a commitment level
+pub fn is_commitment<T>(commitment: T) -> Result<(), String>
+where
+    T: AsRef<str> + Display,
+{
+    CommitmentConfig::from_str(commitment.as_ref())
+        .map(|_| ())
+        .map_err(|_| {
+            format!(
+                "Unknown commitment level '{}', expected one of processed, confirmed or finalized",
+                commitment
+            )
+        })
+}
+
 pub fn is_rfc3339_datetime<T>(value: T) -> Result<(), String>
 where
     T: AsRef<str> + Display,
@@ -307,4 +355,20 @@ mod tests {
         assert!(is_derivation("a/b").is_err());
         assert!(is_derivation("0/4294967296").is_err());
     }
+
+    #[test]
+    fn test_is_sol_amount() {
+        assert_eq!(is_sol_amount("1.5"), Ok(()));
+        assert_eq!(is_sol_amount_or_all("ALL"), Ok(()));
+        assert_eq!(is_sol_amount_or_all("0.000000001"), Ok(()));
+        assert!(is_sol_amount("ALL").is_err());
+        assert!(is_sol_amount("0.0000000001").is_err());
+    }
+
+    #[test]
+    fn test_is_commitment() {
+        assert_eq!(is_commitment("confirmed"), Ok(()));
+        assert_eq!(is_commitment("singleGossip"), Ok(()));
+        assert!(is_commitment("confirmd").is_err());
+    }
 }
@@ -11,7 +11,7 @@ use solana_account_decoder::parse_token::UiTokenAccount;
 use solana_clap_utils::keypair::SignOnly;
 use solana_client::rpc_response::{
     RpcAccountBalance, RpcInflationGovernor, RpcInflationRate, RpcKeyedAccount, RpcSupply,
-    RpcVoteAccountInfo,
+    RpcValidatorDelegations, RpcVoteAccountInfo,
 };
 use solana_sdk::{
     clock::{self, Epoch, Slot, UnixTimestamp},
@@ -354,7 +354,23 @@ impl fmt::Display for CliValidators {
                 } else {
                     "-".into()
                 },
-            )
+            )?;
+            if let Some(delegations) = &validator.delegations {
+                writeln!(
+                    f,
+                    "    Self Stake: {} ({:.2}%)  Foreign Stake: {}  Stake Accounts: {}  Delegators: {}",
+                    build_balance_message(delegations.self_stake, use_lamports_unit, true),
+                    if delegations.active_stake > 0 {
+                        100. * delegations.self_stake as f64 / delegations.active_stake as f64
+                    } else {
+                        0.
+                    },
+                    build_balance_message(delegations.foreign_stake, use_lamports_unit, true),
+                    delegations.stake_account_count,
+                    delegations.delegator_count,
+                )?;
+            }
+            Ok(())
         }
         writeln_name_value(
             f,
@@ -457,6 +473,8 @@ pub struct CliValidator {
     pub credits: u64,
     pub activated_stake: u64,
     pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delegations: Option<RpcValidatorDelegations>,
 }
 
 impl CliValidator {
@@ -485,6 +503,7 @@ impl CliValidator {
                 .unwrap_or(0),
             activated_stake: vote_account.activated_stake,
             version,
+            delegations: None,
         }
     }
 }
@@ -1,11 +1,16 @@
 use console::style;
 use indicatif::{ProgressBar, ProgressStyle};
 use solana_sdk::{
-    hash::Hash, native_token::lamports_to_sol, program_utils::limited_deserialize,
-    transaction::Transaction,
+    hash::Hash, instruction::CompiledInstruction, native_token::lamports_to_sol,
+    program_utils::limited_deserialize, pubkey::Pubkey, transaction::Transaction,
+};
+use solana_transaction_status::{
+    parse_instruction, UiInstruction, UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, io,
 };
-use solana_transaction_status::UiTransactionStatusMeta;
-use std::{collections::HashMap, fmt, io};
 
 pub fn build_balance_message(lamports: u64, use_lamports_unit: bool, show_unit: bool) -> String {
     if use_lamports_unit {
@@ -80,6 +85,141 @@ pub fn println_signers(
     println!();
 }
 
+fn write_instruction<W: io::Write>(
+    w: &mut W,
+    instruction: &CompiledInstruction,
+    account_keys: &[Pubkey],
+    prefix: &str,
+) -> io::Result<()> {
+    let program_pubkey = account_keys[instruction.program_id_index as usize];
+    writeln!(
+        w,
+        "{}  Program: {} ({})",
+        prefix, program_pubkey, instruction.program_id_index
+    )?;
+    for (account_index, account) in instruction.accounts.iter().enumerate() {
+        let account_pubkey = account_keys[*account as usize];
+        writeln!(
+            w,
+            "{}  Account {}: {} ({})",
+            prefix, account_index, account_pubkey, account
+        )?;
+    }
+
+    let mut raw = true;
+    if program_pubkey == solana_vote_program::id() {
+        if let Ok(vote_instruction) = limited_deserialize::<
+            solana_vote_program::vote_instruction::VoteInstruction,
+        >(&instruction.data)
+        {
+            writeln!(w, "{}  {:?}", prefix, vote_instruction)?;
+            raw = false;
+        }
+    } else if program_pubkey == solana_stake_program::id() {
+        if let Ok(stake_instruction) = limited_deserialize::<
+            solana_stake_program::stake_instruction::StakeInstruction,
+        >(&instruction.data)
+        {
+            writeln!(w, "{}  {:?}", prefix, stake_instruction)?;
+            raw = false;
+        }
+    } else if program_pubkey == solana_sdk::system_program::id() {
+        if let Ok(system_instruction) = limited_deserialize::<
+            solana_sdk::system_instruction::SystemInstruction,
+        >(&instruction.data)
+        {
+            writeln!(w, "{}  {:?}", prefix, system_instruction)?;
+            raw = false;
+        }
+    } else if let Ok(parsed_instruction) =
+        parse_instruction::parse(&program_pubkey, instruction, account_keys)
+    {
+        writeln!(
+            w,
+            "{}  {}: {}",
+            prefix, parsed_instruction.program, parsed_instruction.parsed
+        )?;
+        raw = false;
+    }
+
+    if raw {
+        writeln!(w, "{}  Data: {:?}", prefix, instruction.data)?;
+    }
+    Ok(())
+}
+
+/// Sums the compute units consumed by the top level instructions, as logged by the BPF loader
+pub fn compute_units_consumed(log_messages: &[String]) -> Option<u64> {
+    let mut invoke_depth = 0;
+    let mut consumed = None;
+    for log_message in log_messages {
+        let words: Vec<_> = log_message.split_whitespace().collect();
+        match words.as_slice() {
+            ["Program", _, "invoke", depth] => {
+                invoke_depth = depth
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse()
+                    .unwrap_or(invoke_depth + 1);
+            }
+            ["Program", _, "success"] | ["Program", _, "failed:", ..] => {
+                invoke_depth = invoke_depth.saturating_sub(1);
+            }
+            ["Program", _, "consumed", units, "of", _, "compute", "units"] if invoke_depth == 1 => {
+                if let Ok(units) = units.parse::<u64>() {
+                    consumed = Some(consumed.unwrap_or(0) + units);
+                }
+            }
+            _ => {}
+        }
+    }
+    consumed
+}
+
+fn write_token_balances<W: io::Write>(
+    w: &mut W,
+    pre_token_balances: &[UiTransactionTokenBalance],
+    post_token_balances: &[UiTransactionTokenBalance],
+    prefix: &str,
+) -> io::Result<()> {
+    let mut balances: BTreeMap<u8, (Option<&UiTransactionTokenBalance>, Option<&_>)> =
+        BTreeMap::new();
+    for balance in pre_token_balances {
+        balances.entry(balance.account_index).or_default().0 = Some(balance);
+    }
+    for balance in post_token_balances {
+        balances.entry(balance.account_index).or_default().1 = Some(balance);
+    }
+    if balances.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(w, "{}Token Balances:", prefix)?;
+    for (account_index, (pre, post)) in balances {
+        let mint = pre.or(post).map(|balance| balance.mint.as_str()).unwrap();
+        let amount = |balance: Option<&UiTransactionTokenBalance>| {
+            balance
+                .map(|balance| balance.ui_token_amount.real_number_string_trimmed())
+                .unwrap_or_else(|| "-".to_string())
+        };
+        let (pre_amount, post_amount) = (amount(pre), amount(post));
+        if pre_amount == post_amount {
+            writeln!(
+                w,
+                "{}  Account {} (mint {}): {}",
+                prefix, account_index, mint, pre_amount
+            )?;
+        } else {
+            writeln!(
+                w,
+                "{}  Account {} (mint {}): {} -> {}",
+                prefix, account_index, mint, pre_amount, post_amount
+            )?;
+        }
+    }
+    Ok(())
+}
+
 pub fn write_transaction<W: io::Write>(
     w: &mut W,
     transaction: &Transaction,
@@ -104,52 +244,8 @@ pub fn write_transaction<W: io::Write>(
         writeln!(w, "{}Account {}: {:?}", prefix, account_index, account)?;
     }
     for (instruction_index, instruction) in message.instructions.iter().enumerate() {
-        let program_pubkey = message.account_keys[instruction.program_id_index as usize];
         writeln!(w, "{}Instruction {}", prefix, instruction_index)?;
-        writeln!(
-            w,
-            "{}  Program: {} ({})",
-            prefix, program_pubkey, instruction.program_id_index
-        )?;
-        for (account_index, account) in instruction.accounts.iter().enumerate() {
-            let account_pubkey = message.account_keys[*account as usize];
-            writeln!(
-                w,
-                "{}  Account {}: {} ({})",
-                prefix, account_index, account_pubkey, account
-            )?;
-        }
-
-        let mut raw = true;
-        if program_pubkey == solana_vote_program::id() {
-            if let Ok(vote_instruction) = limited_deserialize::<
-                solana_vote_program::vote_instruction::VoteInstruction,
-            >(&instruction.data)
-            {
-                writeln!(w, "{}  {:?}", prefix, vote_instruction)?;
-                raw = false;
-            }
-        } else if program_pubkey == solana_stake_program::id() {
-            if let Ok(stake_instruction) = limited_deserialize::<
-                solana_stake_program::stake_instruction::StakeInstruction,
-            >(&instruction.data)
-            {
-                writeln!(w, "{}  {:?}", prefix, stake_instruction)?;
-                raw = false;
-            }
-        } else if program_pubkey == solana_sdk::system_program::id() {
-            if let Ok(system_instruction) = limited_deserialize::<
-                solana_sdk::system_instruction::SystemInstruction,
-            >(&instruction.data)
-            {
-                writeln!(w, "{}  {:?}", prefix, system_instruction)?;
-                raw = false;
-            }
-        }
-
-        if raw {
-            writeln!(w, "{}  Data: {:?}", prefix, instruction.data)?;
-        }
+        write_instruction(w, instruction, &message.account_keys, prefix)?;
     }
 
     if let Some(transaction_status) = transaction_status {
@@ -162,6 +258,13 @@ pub fn write_transaction<W: io::Write>(
                 Err(err) => err.to_string(),
             }
         )?;
+        if let Some(failed_instruction) = &transaction_status.failed_instruction {
+            writeln!(
+                w,
+                "{}  Failed Instruction: {} ({})",
+                prefix, failed_instruction.index, failed_instruction
+            )?;
+        }
         writeln!(
             w,
             "{}  Fee: ◎{}",
@@ -198,6 +301,51 @@ pub fn write_transaction<W: io::Write>(
             }
         }
 
+        if let (Some(pre_token_balances), Some(post_token_balances)) = (
+            &transaction_status.pre_token_balances,
+            &transaction_status.post_token_balances,
+        ) {
+            write_token_balances(w, pre_token_balances, post_token_balances, prefix)?;
+        }
+
+        if let Some(inner_instructions) = &transaction_status.inner_instructions {
+            for inner_instructions in inner_instructions {
+                if inner_instructions.instructions.is_empty() {
+                    continue;
+                }
+                writeln!(
+                    w,
+                    "{}Inner Instructions of Instruction {}:",
+                    prefix, inner_instructions.index
+                )?;
+                let inner_prefix = format!("{}  ", prefix);
+                for (inner_index, instruction) in inner_instructions.instructions.iter().enumerate()
+                {
+                    writeln!(w, "{}Inner Instruction {}", inner_prefix, inner_index)?;
+                    match instruction {
+                        UiInstruction::Compiled(instruction) => {
+                            let instruction = CompiledInstruction {
+                                program_id_index: instruction.program_id_index,
+                                accounts: instruction.accounts.clone(),
+                                data: bs58::decode(&instruction.data)
+                                    .into_vec()
+                                    .unwrap_or_default(),
+                            };
+                            write_instruction(
+                                w,
+                                &instruction,
+                                &message.account_keys,
+                                &inner_prefix,
+                            )?;
+                        }
+                        UiInstruction::Parsed(instruction) => {
+                            writeln!(w, "{}  {:?}", inner_prefix, instruction)?;
+                        }
+                    }
+                }
+            }
+        }
+
         if let Some(log_messages) = &transaction_status.log_messages {
             if !log_messages.is_empty() {
                 writeln!(w, "{}Log Messages:", prefix,)?;
@@ -205,6 +353,9 @@ pub fn write_transaction<W: io::Write>(
                     writeln!(w, "{}  {}", prefix, log_message,)?;
                 }
             }
+            if let Some(compute_units) = compute_units_consumed(log_messages) {
+                writeln!(w, "{}Compute Units Consumed: {}", prefix, compute_units)?;
+            }
         }
     } else {
         writeln!(w, "{}Status: Unavailable", prefix)?;
@@ -240,6 +391,29 @@ mod test {
     use super::*;
     use solana_sdk::pubkey::Pubkey;
 
+    #[test]
+    fn test_compute_units_consumed() {
+        let log_messages: Vec<_> = [
+            "Program A invoke [1]",
+            "Program B invoke [2]",
+            "Program B consumed 100 of 150000 compute units",
+            "Program B success",
+            "Program A consumed 500 of 200000 compute units",
+            "Program A success",
+            "Program 11111111111111111111111111111111 invoke [1]",
+            "Program 11111111111111111111111111111111 success",
+            "Program C invoke [1]",
+            "Program log: Error",
+            "Program C consumed 50 of 200000 compute units",
+            "Program C failed: custom program error: 0x1",
+        ]
+        .iter()
+        .map(|log_message| log_message.to_string())
+        .collect();
+        assert_eq!(compute_units_consumed(&log_messages), Some(550));
+        assert_eq!(compute_units_consumed(&log_messages[6..8]), None);
+    }
+
     #[test]
     fn test_format_labeled_address() {
         let pubkey = Pubkey::default().to_string();
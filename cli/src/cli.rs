@@ -138,6 +138,7 @@ pub enum CliCommand {
     },
     ShowValidators {
         use_lamports_unit: bool,
+        detail: bool,
     },
     Supply {
         print_accounts: bool,
@@ -310,6 +311,12 @@ pub enum CliCommand {
         withdraw_authority: SignerIndex,
         withdraw_amount: SpendAmount,
     },
+    CloseVoteAccount {
+        vote_account_pubkey: Pubkey,
+        destination_account_pubkey: Pubkey,
+        withdraw_authority: SignerIndex,
+        force: bool,
+    },
     VoteAuthorize {
         vote_account_pubkey: Pubkey,
         new_authorized_pubkey: Pubkey,
@@ -733,6 +740,9 @@ pub fn parse_command(
         ("withdraw-from-vote-account", Some(matches)) => {
             parse_withdraw_from_vote_account(matches, default_signer, wallet_manager)
         }
+        ("close-vote-account", Some(matches)) => {
+            parse_close_vote_account(matches, default_signer, wallet_manager)
+        }
         // Wallet Commands
         ("address", Some(matches)) => Ok(CliCommandInfo {
             command: CliCommand::Address,
@@ -1276,9 +1286,10 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
         CliCommand::WaitForMaxStake { max_stake_percent } => {
             process_wait_for_max_stake(&rpc_client, config, *max_stake_percent)
         }
-        CliCommand::ShowValidators { use_lamports_unit } => {
-            process_show_validators(&rpc_client, config, *use_lamports_unit)
-        }
+        CliCommand::ShowValidators {
+            use_lamports_unit,
+            detail,
+        } => process_show_validators(&rpc_client, config, *use_lamports_unit, *detail),
         CliCommand::Supply { print_accounts } => {
             process_supply(&rpc_client, config, *print_accounts)
         }
@@ -1642,6 +1653,19 @@ pub fn process_command(config: &CliConfig) -> ProcessResult {
             *withdraw_amount,
             destination_account_pubkey,
         ),
+        CliCommand::CloseVoteAccount {
+            vote_account_pubkey,
+            destination_account_pubkey,
+            withdraw_authority,
+            force,
+        } => process_close_vote_account(
+            &rpc_client,
+            config,
+            vote_account_pubkey,
+            *withdraw_authority,
+            destination_account_pubkey,
+            *force,
+        ),
         CliCommand::VoteAuthorize {
             vote_account_pubkey,
             new_authorized_pubkey,
@@ -1891,7 +1915,7 @@ pub fn app<'ab, 'v>(name: &str, about: &'ab str, version: &'v str) -> App<'ab, '
                         .index(1)
                         .value_name("AMOUNT")
                         .takes_value(true)
-                        .validator(is_amount)
+                        .validator(is_sol_amount)
                         .required(true)
                         .help("The airdrop amount to request, in SOL"),
                 )
@@ -1920,7 +1944,7 @@ pub fn app<'ab, 'v>(name: &str, about: &'ab str, version: &'v str) -> App<'ab, '
         )
         .subcommand(
             SubCommand::with_name("confirm")
-                .about("Confirm transaction by signature")
+                .about("Confirm transaction by signature, with -v to show the executed transaction in detail")
                 .arg(
                     Arg::with_name("signature")
                         .index(1)
@@ -2030,7 +2054,7 @@ pub fn app<'ab, 'v>(name: &str, about: &'ab str, version: &'v str) -> App<'ab, '
                         .index(2)
                         .value_name("AMOUNT")
                         .takes_value(true)
-                        .validator(is_amount_or_all)
+                        .validator(is_sol_amount_or_all)
                         .required(true)
                         .help("The amount to send, in SOL; accepts keyword ALL"),
                 )
@@ -2065,7 +2089,7 @@ pub fn app<'ab, 'v>(name: &str, about: &'ab str, version: &'v str) -> App<'ab, '
                         .index(2)
                         .value_name("AMOUNT")
                         .takes_value(true)
-                        .validator(is_amount_or_all)
+                        .validator(is_sol_amount_or_all)
                         .required(true)
                         .help("The amount to send, in SOL; accepts keyword ALL"),
                 )
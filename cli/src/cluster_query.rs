@@ -334,6 +334,12 @@ impl ClusterQuerySubCommands for App<'_, '_> {
                         .long("lamports")
                         .takes_value(false)
                         .help("Display balance in lamports instead of SOL"),
+                )
+                .arg(
+                    Arg::with_name("detail")
+                        .long("detail")
+                        .takes_value(false)
+                        .help("Break down the stake of each validator between self stake and foreign stake"),
                 ),
         )
         .subcommand(
@@ -562,9 +568,13 @@ pub fn parse_show_stakes(
 
 pub fn parse_show_validators(matches: &ArgMatches<'_>) -> Result<CliCommandInfo, CliError> {
     let use_lamports_unit = matches.is_present("lamports");
+    let detail = matches.is_present("detail");
 
     Ok(CliCommandInfo {
-        command: CliCommand::ShowValidators { use_lamports_unit },
+        command: CliCommand::ShowValidators {
+            use_lamports_unit,
+            detail,
+        },
         signers: vec![],
     })
 }
@@ -1552,7 +1562,7 @@ pub fn process_show_gossip(rpc_client: &RpcClient, config: &CliConfig) -> Proces
         .into_iter()
         .map(|node| {
             format!(
-                "{:15} | {:44} | {:6} | {:5} | {:21} | {}",
+                "{:15} | {:44} | {:6} | {:5} | {:21} | {:21} | {}",
                 node.gossip
                     .map(|addr| addr.ip().to_string())
                     .unwrap_or_else(|| "none".to_string()),
@@ -1562,6 +1572,9 @@ pub fn process_show_gossip(rpc_client: &RpcClient, config: &CliConfig) -> Proces
                 node.rpc
                     .map(|addr| addr.to_string())
                     .unwrap_or_else(|| "none".to_string()),
+                node.pubsub
+                    .map(|addr| addr.to_string())
+                    .unwrap_or_else(|| "none".to_string()),
                 node.version.unwrap_or_else(|| "unknown".to_string()),
             )
         })
@@ -1569,9 +1582,9 @@ pub fn process_show_gossip(rpc_client: &RpcClient, config: &CliConfig) -> Proces
 
     Ok(format!(
         "IP Address      | Node identifier                              \
-         | Gossip | TPU   | RPC Address           | Version\n\
+         | Gossip | TPU   | RPC Address           | PubSub Address        | Version\n\
          ----------------+----------------------------------------------+\
-         --------+-------+-----------------------+----------------\n\
+         --------+-------+-----------------------+-----------------------+----------------\n\
          {}\n\
          Nodes: {}",
         s.join("\n"),
@@ -1698,9 +1711,20 @@ pub fn process_show_validators(
     rpc_client: &RpcClient,
     config: &CliConfig,
     use_lamports_unit: bool,
+    detail: bool,
 ) -> ProcessResult {
     let epoch_info = rpc_client.get_epoch_info()?;
     let vote_accounts = rpc_client.get_vote_accounts()?;
+    let mut validator_delegations: HashMap<_, _> = if detail {
+        rpc_client
+            .get_validator_delegations(None)?
+            .value
+            .into_iter()
+            .map(|delegations| (delegations.vote_pubkey.clone(), delegations))
+            .collect()
+    } else {
+        HashMap::new()
+    };
 
     let mut node_version = HashMap::new();
     let unknown_version = "unknown".to_string();
@@ -1732,7 +1756,7 @@ pub fn process_show_validators(
     let current_validators: Vec<CliValidator> = current
         .iter()
         .map(|vote_account| {
-            CliValidator::new(
+            let mut validator = CliValidator::new(
                 vote_account,
                 epoch_info.epoch,
                 node_version
@@ -1740,7 +1764,9 @@ pub fn process_show_validators(
                     .unwrap_or(&unknown_version)
                     .clone(),
                 &config.address_labels,
-            )
+            );
+            validator.delegations = validator_delegations.remove(&vote_account.vote_pubkey);
+            validator
         })
         .collect();
     let mut delinquent = vote_accounts.delinquent;
@@ -1748,7 +1774,7 @@ pub fn process_show_validators(
     let delinquent_validators: Vec<CliValidator> = delinquent
         .iter()
         .map(|vote_account| {
-            CliValidator::new(
+            let mut validator = CliValidator::new(
                 vote_account,
                 epoch_info.epoch,
                 node_version
@@ -1756,7 +1782,9 @@ pub fn process_show_validators(
                     .unwrap_or(&unknown_version)
                     .clone(),
                 &config.address_labels,
-            )
+            );
+            validator.delegations = validator_delegations.remove(&vote_account.vote_pubkey);
+            validator
         })
         .collect();
 
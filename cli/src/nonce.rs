@@ -74,7 +74,7 @@ impl NonceSubCommands for App<'_, '_> {
                         .value_name("AMOUNT")
                         .takes_value(true)
                         .required(true)
-                        .validator(is_amount_or_all)
+                        .validator(is_sol_amount_or_all)
                         .help("The amount to load the nonce account with, in SOL; accepts keyword ALL"),
                 )
                 .arg(
@@ -156,7 +156,7 @@ impl NonceSubCommands for App<'_, '_> {
                         .value_name("AMOUNT")
                         .takes_value(true)
                         .required(true)
-                        .validator(is_amount)
+                        .validator(is_sol_amount)
                         .help("The amount to withdraw from the nonce account, in SOL"),
                 )
                 .arg(nonce_authority_arg()),
@@ -27,6 +27,7 @@ use solana_sdk::{
     bpf_loader_upgradeable::{self, UpgradeableLoaderState},
     clock::Slot,
     commitment_config::CommitmentConfig,
+    hash::{hash, Hash},
     instruction::Instruction,
     loader_instruction,
     message::Message,
@@ -43,10 +44,11 @@ use std::{
     cmp::min,
     collections::HashMap,
     error,
-    fs::File,
+    fs::{self, File},
     io::Read,
     net::UdpSocket,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::Command,
     sync::Arc,
     thread::sleep,
     time::{Duration, Instant},
@@ -89,6 +91,14 @@ pub enum ProgramCliCommand {
     GetAuthority {
         account_pubkey: Option<Pubkey>,
     },
+    VerifyBuild {
+        program_pubkey: Pubkey,
+        repository: String,
+        commit: String,
+        manifest_path: String,
+        program_name: Option<String>,
+        docker_image: String,
+    },
 }
 
 pub trait ProgramSubCommands {
@@ -275,6 +285,61 @@ impl ProgramSubCommands for App<'_, '_> {
                                 .help("Public key of the account to query")
                         ),
                 )
+                .subcommand(
+                    SubCommand::with_name("verify-build")
+                        .about("Rebuilds a program from its source in a pinned container and \
+                                checks that the build matches the deployed program")
+                        .arg(
+                            Arg::with_name("program_id")
+                                .index(1)
+                                .value_name("PROGRAM_ID")
+                                .takes_value(true)
+                                .required(true)
+                                .validator(is_valid_pubkey)
+                                .help("Address of the deployed program")
+                        )
+                        .arg(
+                            Arg::with_name("repository")
+                                .long("repository")
+                                .value_name("URL")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Git repository of the program's source")
+                        )
+                        .arg(
+                            Arg::with_name("commit")
+                                .long("commit")
+                                .value_name("COMMIT")
+                                .takes_value(true)
+                                .required(true)
+                                .help("Commit of the repository the program was built from")
+                        )
+                        .arg(
+                            Arg::with_name("manifest_path")
+                                .long("manifest-path")
+                                .value_name("PATH")
+                                .takes_value(true)
+                                .default_value("Cargo.toml")
+                                .help("Path of the program's Cargo.toml, relative to the root of the repository")
+                        )
+                        .arg(
+                            Arg::with_name("program_name")
+                                .long("program-name")
+                                .value_name("NAME")
+                                .takes_value(true)
+                                .help("Name of the program's shared object, without the .so \
+                                       extension [default: the only program built]")
+                        )
+                        .arg(
+                            Arg::with_name("docker_image")
+                                .long("docker-image")
+                                .value_name("IMAGE")
+                                .takes_value(true)
+                                .help("Container image providing cargo-build-bpf, preferably \
+                                       pinned by digest \
+                                       [default: solanalabs/solana:v<CLI version>]")
+                        ),
+                )
         )
     }
 }
@@ -485,6 +550,22 @@ pub fn parse_program_subcommand(
             }),
             signers: vec![],
         },
+        ("verify-build", Some(matches)) => CliCommandInfo {
+            command: CliCommand::Program(ProgramCliCommand::VerifyBuild {
+                program_pubkey: pubkey_of(matches, "program_id").unwrap(),
+                repository: matches.value_of("repository").unwrap().to_string(),
+                commit: matches.value_of("commit").unwrap().to_string(),
+                manifest_path: matches.value_of("manifest_path").unwrap().to_string(),
+                program_name: matches
+                    .value_of("program_name")
+                    .map(|name| name.to_string()),
+                docker_image: matches
+                    .value_of("docker_image")
+                    .map(|image| image.to_string())
+                    .unwrap_or_else(default_verify_build_docker_image),
+            }),
+            signers: vec![],
+        },
         _ => unreachable!(),
     };
     Ok(response)
@@ -565,6 +646,23 @@ pub fn process_program_subcommand(
         ProgramCliCommand::GetAuthority { account_pubkey } => {
             process_get_authority(&rpc_client, config, *account_pubkey)
         }
+        ProgramCliCommand::VerifyBuild {
+            program_pubkey,
+            repository,
+            commit,
+            manifest_path,
+            program_name,
+            docker_image,
+        } => process_verify_build(
+            &rpc_client,
+            config,
+            program_pubkey,
+            repository,
+            commit,
+            manifest_path,
+            program_name,
+            docker_image,
+        ),
     }
 }
 
@@ -952,6 +1050,183 @@ fn process_get_authority(
     }
 }
 
+fn default_verify_build_docker_image() -> String {
+    format!("solanalabs/solana:v{}", solana_version::Version::default())
+}
+
+/// The deployed program's shared object, padded with zeroes if it is upgradeable
+fn get_program_data(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    program_pubkey: &Pubkey,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let account = rpc_client
+        .get_account_with_commitment(program_pubkey, config.commitment)?
+        .value
+        .ok_or_else(|| format!("Unable to find program {}", program_pubkey))?;
+    if account.owner == bpf_loader::id() || account.owner == bpf_loader_deprecated::id() {
+        return Ok(account.data);
+    }
+    if let Ok(UpgradeableLoaderState::Program {
+        programdata_address,
+    }) = account.state()
+    {
+        let programdata_account = rpc_client
+            .get_account_with_commitment(&programdata_address, config.commitment)?
+            .value
+            .ok_or("Failed to find associated ProgramData account for the provided program")?;
+        let offset = UpgradeableLoaderState::programdata_data_offset()?;
+        if programdata_account.data.len() < offset {
+            return Err("Invalid associated ProgramData account found for the program".into());
+        }
+        Ok(programdata_account.data[offset..].to_vec())
+    } else {
+        Err(format!("{} is not a program account", program_pubkey).into())
+    }
+}
+
+/// Hash of a program's shared object ignoring trailing zeroes, which the data of upgradeable
+/// programs is padded with up to its maximum length
+fn program_data_hash(program_data: &[u8]) -> Hash {
+    let len = program_data
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |position| position + 1);
+    hash(&program_data[..len])
+}
+
+fn run_verify_build_command(command: &mut Command) -> Result<(), Box<dyn error::Error>> {
+    debug!("Running {:?}", command);
+    let status = command
+        .status()
+        .map_err(|err| format!("Unable to run {:?}: {}", command, err))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("{:?} failed: {}", command, status).into())
+    }
+}
+
+/// Builds the program from a checkout of `repository` at `commit` inside `docker_image`,
+/// returning its shared object
+fn build_program_in_container(
+    work_dir: &Path,
+    repository: &str,
+    commit: &str,
+    manifest_path: &str,
+    program_name: Option<&str>,
+    docker_image: &str,
+) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let source_dir = work_dir.join("source");
+    run_verify_build_command(
+        Command::new("git")
+            .args(&["clone", "--quiet", repository])
+            .arg(&source_dir),
+    )?;
+    run_verify_build_command(
+        Command::new("git")
+            .arg("-C")
+            .arg(&source_dir)
+            .args(&["checkout", "--quiet", commit]),
+    )?;
+
+    // The build sees the source at the same path no matter where it was checked out, so that
+    // paths embedded in the shared object don't differ between machines
+    let out_dir = "verify-build-out";
+    run_verify_build_command(
+        Command::new("docker")
+            .args(&["run", "--rm", "--volume"])
+            .arg(format!("{}:/build", work_dir.display()))
+            .args(&[
+                "--workdir",
+                "/build/source",
+                "--entrypoint",
+                "cargo-build-bpf",
+            ])
+            .arg(docker_image)
+            .arg("--manifest-path")
+            .arg(format!("/build/source/{}", manifest_path))
+            .arg("--bpf-out-dir")
+            .arg(format!("/build/{}", out_dir)),
+    )?;
+
+    let mut programs: Vec<PathBuf> = fs::read_dir(work_dir.join(out_dir))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "so")
+        })
+        .collect();
+    let program_path = match program_name {
+        Some(program_name) => programs
+            .into_iter()
+            .find(|path| path.file_stem().map_or(false, |stem| stem == program_name))
+            .ok_or_else(|| format!("The build produced no program named {}", program_name))?,
+        None if programs.len() == 1 => programs.pop().unwrap(),
+        None => {
+            return Err(format!(
+                "The build produced {} programs, select one with --program-name",
+                programs.len()
+            )
+            .into())
+        }
+    };
+    Ok(fs::read(&program_path)?)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn process_verify_build(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    program_pubkey: &Pubkey,
+    repository: &str,
+    commit: &str,
+    manifest_path: &str,
+    program_name: &Option<String>,
+    docker_image: &str,
+) -> ProcessResult {
+    let program_data = get_program_data(rpc_client, config, program_pubkey)?;
+
+    let work_dir = std::env::temp_dir().join(format!("solana-verify-build-{}", program_pubkey));
+    if work_dir.exists() {
+        fs::remove_dir_all(&work_dir)
+            .map_err(|err| format!("Unable to remove {}: {}", work_dir.display(), err))?;
+    }
+    fs::create_dir_all(&work_dir)?;
+    let build = build_program_in_container(
+        &work_dir,
+        repository,
+        commit,
+        manifest_path,
+        program_name.as_deref(),
+        docker_image,
+    );
+    if let Err(err) = fs::remove_dir_all(&work_dir) {
+        // The container may leave files only its user can remove
+        warn!("Unable to remove {}: {}", work_dir.display(), err);
+    }
+    let build_data = build?;
+
+    let on_chain_hash = program_data_hash(&program_data);
+    let build_hash = program_data_hash(&build_data);
+    if on_chain_hash != build_hash {
+        return Err(format!(
+            "Program {} does not match the build of {} at {}: on-chain hash {}, build hash {}",
+            program_pubkey, repository, commit, on_chain_hash, build_hash
+        )
+        .into());
+    }
+    Ok(json!({
+        "ProgramId": program_pubkey.to_string(),
+        "Repository": repository,
+        "Commit": commit,
+        "DockerImage": docker_image,
+        "Hash": on_chain_hash.to_string(),
+        "Verified": true,
+    })
+    .to_string())
+}
+
 /// Deploy using non-upgradeable loader
 pub fn process_deploy(
     rpc_client: &RpcClient,
@@ -2387,6 +2662,92 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cli_parse_verify_build() {
+        let test_commands = app("test", "desc", "version");
+
+        let default_keypair = Keypair::new();
+        let keypair_file = make_tmp_path("keypair_file");
+        write_keypair_file(&default_keypair, &keypair_file).unwrap();
+        let default_signer = DefaultSigner {
+            path: keypair_file,
+            arg_name: "".to_string(),
+        };
+
+        let program_pubkey = Pubkey::new_unique();
+        let test_command = test_commands.clone().get_matches_from(vec![
+            "test",
+            "program",
+            "verify-build",
+            &program_pubkey.to_string(),
+            "--repository",
+            "https://github.com/example/program",
+            "--commit",
+            "0123abc",
+        ]);
+        assert_eq!(
+            parse_command(&test_command, &default_signer, &mut None).unwrap(),
+            CliCommandInfo {
+                command: CliCommand::Program(ProgramCliCommand::VerifyBuild {
+                    program_pubkey,
+                    repository: "https://github.com/example/program".to_string(),
+                    commit: "0123abc".to_string(),
+                    manifest_path: "Cargo.toml".to_string(),
+                    program_name: None,
+                    docker_image: default_verify_build_docker_image(),
+                }),
+                signers: vec![],
+            }
+        );
+
+        let test_command = test_commands.clone().get_matches_from(vec![
+            "test",
+            "program",
+            "verify-build",
+            &program_pubkey.to_string(),
+            "--repository",
+            "https://github.com/example/program",
+            "--commit",
+            "0123abc",
+            "--manifest-path",
+            "program/Cargo.toml",
+            "--program-name",
+            "example_program",
+            "--docker-image",
+            "example/image@sha256:0123",
+        ]);
+        assert_eq!(
+            parse_command(&test_command, &default_signer, &mut None).unwrap(),
+            CliCommandInfo {
+                command: CliCommand::Program(ProgramCliCommand::VerifyBuild {
+                    program_pubkey,
+                    repository: "https://github.com/example/program".to_string(),
+                    commit: "0123abc".to_string(),
+                    manifest_path: "program/Cargo.toml".to_string(),
+                    program_name: Some("example_program".to_string()),
+                    docker_image: "example/image@sha256:0123".to_string(),
+                }),
+                signers: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_program_data_hash() {
+        let program = vec![1, 0, 2, 3, 0];
+        let mut padded_program = program.clone();
+        padded_program.resize(64, 0);
+        assert_eq!(
+            program_data_hash(&program),
+            program_data_hash(&padded_program)
+        );
+        assert_ne!(
+            program_data_hash(&program),
+            program_data_hash(&[1, 0, 2, 4])
+        );
+        assert_eq!(program_data_hash(&[]), program_data_hash(&[0; 8]));
+    }
+
     #[test]
     fn test_cli_keypair_file() {
         solana_logger::setup();
@@ -120,7 +120,7 @@ impl StakeSubCommands for App<'_, '_> {
                         .index(2)
                         .value_name("AMOUNT")
                         .takes_value(true)
-                        .validator(is_amount_or_all)
+                        .validator(is_sol_amount_or_all)
                         .required(true)
                         .help("The amount to send to the stake account, in SOL; accepts keyword ALL")
                 )
@@ -279,7 +279,7 @@ impl StakeSubCommands for App<'_, '_> {
                         .index(3)
                         .value_name("AMOUNT")
                         .takes_value(true)
-                        .validator(is_amount)
+                        .validator(is_sol_amount)
                         .required(true)
                         .help("The amount to move into the new stake account, in SOL")
                 )
@@ -339,7 +339,7 @@ impl StakeSubCommands for App<'_, '_> {
                         .index(3)
                         .value_name("AMOUNT")
                         .takes_value(true)
-                        .validator(is_amount)
+                        .validator(is_sol_amount)
                         .required(true)
                         .help("The amount to withdraw from the stake account, in SOL")
                 )
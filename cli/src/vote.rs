@@ -232,7 +232,7 @@ impl VoteSubCommands for App<'_, '_> {
                         .value_name("AMOUNT")
                         .takes_value(true)
                         .required(true)
-                        .validator(is_amount_or_all)
+                        .validator(is_sol_amount_or_all)
                         .help("The amount to withdraw, in SOL; accepts keyword ALL"),
                 )
                 .arg(
@@ -244,6 +244,38 @@ impl VoteSubCommands for App<'_, '_> {
                         .help("Authorized withdrawer [default: cli config keypair]"),
                 )
         )
+        .subcommand(
+            SubCommand::with_name("close-vote-account")
+                .about("Close a vote account and withdraw all its lamports into a specified account")
+                .arg(
+                    pubkey!(Arg::with_name("vote_account_pubkey")
+                        .index(1)
+                        .value_name("VOTE_ACCOUNT_ADDRESS")
+                        .required(true),
+                        "Vote account to close. "),
+                )
+                .arg(
+                    pubkey!(Arg::with_name("destination_account_pubkey")
+                        .index(2)
+                        .value_name("RECIPIENT_ADDRESS")
+                        .required(true),
+                        "The recipient of all the withdrawn SOL. "),
+                )
+                .arg(
+                    Arg::with_name("authorized_withdrawer")
+                        .long("authorized-withdrawer")
+                        .value_name("AUTHORIZED_KEYPAIR")
+                        .takes_value(true)
+                        .validator(is_valid_signer)
+                        .help("Authorized withdrawer [default: cli config keypair]"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .takes_value(false)
+                        .help("Close the vote account even if stake is still delegated to it"),
+                )
+        )
     }
 }
 
@@ -414,6 +446,38 @@ pub fn parse_withdraw_from_vote_account(
     })
 }
 
+pub fn parse_close_vote_account(
+    matches: &ArgMatches<'_>,
+    default_signer: &DefaultSigner,
+    wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+) -> Result<CliCommandInfo, CliError> {
+    let vote_account_pubkey =
+        pubkey_of_signer(matches, "vote_account_pubkey", wallet_manager)?.unwrap();
+    let destination_account_pubkey =
+        pubkey_of_signer(matches, "destination_account_pubkey", wallet_manager)?.unwrap();
+    let force = matches.is_present("force");
+
+    let (withdraw_authority, withdraw_authority_pubkey) =
+        signer_of(matches, "authorized_withdrawer", wallet_manager)?;
+
+    let payer_provided = None;
+    let signer_info = default_signer.generate_unique_signers(
+        vec![payer_provided, withdraw_authority],
+        matches,
+        wallet_manager,
+    )?;
+
+    Ok(CliCommandInfo {
+        command: CliCommand::CloseVoteAccount {
+            vote_account_pubkey,
+            destination_account_pubkey,
+            withdraw_authority: signer_info.index_of(withdraw_authority_pubkey).unwrap(),
+            force,
+        },
+        signers: signer_info.signers,
+    })
+}
+
 pub fn process_create_vote_account(
     rpc_client: &RpcClient,
     config: &CliConfig,
@@ -747,6 +811,78 @@ pub fn process_withdraw_from_vote_account(
     log_instruction_custom_error::<VoteError>(result, &config)
 }
 
+pub fn process_close_vote_account(
+    rpc_client: &RpcClient,
+    config: &CliConfig,
+    vote_account_pubkey: &Pubkey,
+    withdraw_authority: SignerIndex,
+    destination_account_pubkey: &Pubkey,
+    force: bool,
+) -> ProcessResult {
+    check_unique_pubkeys(
+        (vote_account_pubkey, "vote_account_pubkey".to_string()),
+        (
+            destination_account_pubkey,
+            "destination_account_pubkey".to_string(),
+        ),
+    )?;
+    let withdraw_authority = config.signers[withdraw_authority];
+
+    let (vote_account, vote_state) =
+        get_vote_account(rpc_client, vote_account_pubkey, config.commitment)?;
+    if vote_state.authorized_withdrawer != withdraw_authority.pubkey() {
+        return Err(CliError::BadParameter(format!(
+            "{} is not the authorized withdrawer of vote account {}, {} is",
+            withdraw_authority.pubkey(),
+            vote_account_pubkey,
+            vote_state.authorized_withdrawer,
+        ))
+        .into());
+    }
+
+    // Closing the vote account of a staked validator makes all of its delegations stop earning
+    // rewards, only allow it when explicitly requested
+    let vote_account_pubkey_string = vote_account_pubkey.to_string();
+    let vote_accounts = rpc_client.get_vote_accounts_with_commitment(config.commitment)?;
+    let activated_stake = vote_accounts
+        .current
+        .iter()
+        .chain(vote_accounts.delinquent.iter())
+        .find(|vote_account| vote_account.vote_pubkey == vote_account_pubkey_string)
+        .map(|vote_account| vote_account.activated_stake)
+        .unwrap_or(0);
+    if activated_stake > 0 && !force {
+        return Err(CliError::BadParameter(format!(
+            "Vote account {} still has {} SOL of active stake delegated to it. \
+             Use --force to close it anyway",
+            vote_account_pubkey,
+            lamports_to_sol(activated_stake)
+        ))
+        .into());
+    }
+
+    let (recent_blockhash, fee_calculator) = rpc_client.get_recent_blockhash()?;
+    let ix = withdraw(
+        vote_account_pubkey,
+        &withdraw_authority.pubkey(),
+        vote_account.lamports,
+        destination_account_pubkey,
+    );
+
+    let message = Message::new(&[ix], Some(&config.signers[0].pubkey()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.try_sign(&config.signers, recent_blockhash)?;
+    check_account_for_fee_with_commitment(
+        rpc_client,
+        &config.signers[0].pubkey(),
+        &fee_calculator,
+        &transaction.message,
+        config.commitment,
+    )?;
+    let result = rpc_client.send_and_confirm_transaction_with_spinner(&transaction);
+    log_instruction_custom_error::<VoteError>(result, &config)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1064,5 +1200,51 @@ mod tests {
                 ],
             }
         );
+
+        // Test CloseVoteAccount subcommand
+        let test_close_vote_account = test_commands.clone().get_matches_from(vec![
+            "test",
+            "close-vote-account",
+            &keypair_file,
+            &pubkey_string,
+        ]);
+        assert_eq!(
+            parse_command(&test_close_vote_account, &default_signer, &mut None).unwrap(),
+            CliCommandInfo {
+                command: CliCommand::CloseVoteAccount {
+                    vote_account_pubkey: read_keypair_file(&keypair_file).unwrap().pubkey(),
+                    destination_account_pubkey: pubkey,
+                    withdraw_authority: 0,
+                    force: false,
+                },
+                signers: vec![read_keypair_file(&default_keypair_file).unwrap().into()],
+            }
+        );
+
+        // Test CloseVoteAccount subcommand with authority and force
+        let test_close_vote_account = test_commands.clone().get_matches_from(vec![
+            "test",
+            "close-vote-account",
+            &keypair_file,
+            &pubkey_string,
+            "--authorized-withdrawer",
+            &withdraw_authority_file,
+            "--force",
+        ]);
+        assert_eq!(
+            parse_command(&test_close_vote_account, &default_signer, &mut None).unwrap(),
+            CliCommandInfo {
+                command: CliCommand::CloseVoteAccount {
+                    vote_account_pubkey: read_keypair_file(&keypair_file).unwrap().pubkey(),
+                    destination_account_pubkey: pubkey,
+                    withdraw_authority: 1,
+                    force: true,
+                },
+                signers: vec![
+                    read_keypair_file(&default_keypair_file).unwrap().into(),
+                    read_keypair_file(&withdraw_authority_file).unwrap().into()
+                ],
+            }
+        );
     }
 }
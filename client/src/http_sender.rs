@@ -1,5 +1,6 @@
 use crate::{
     client_error::Result,
+    rpc_auth::RpcAuthToken,
     rpc_custom_error,
     rpc_request::{RpcError, RpcRequest, RpcResponseErrorData},
     rpc_response::RpcSimulateTransactionResult,
@@ -7,11 +8,13 @@ use crate::{
 };
 use log::*;
 use reqwest::{self, header::CONTENT_TYPE, StatusCode};
-use std::{thread::sleep, time::Duration};
+use solana_sdk::signature::Signer;
+use std::{sync::Arc, thread::sleep, time::Duration};
 
 pub struct HttpSender {
     client: reqwest::blocking::Client,
     url: String,
+    rpc_auth_identity: Option<Arc<dyn Signer + Send + Sync>>,
 }
 
 impl HttpSender {
@@ -25,7 +28,24 @@ impl HttpSender {
             .build()
             .expect("build rpc client");
 
-        Self { client, url }
+        Self {
+            client,
+            url,
+            rpc_auth_identity: None,
+        }
+    }
+
+    /// Authenticates every request with a fresh token of `identity`, for nodes restricting
+    /// some of their methods to the identities allowed to call them
+    pub fn new_with_rpc_auth(
+        url: String,
+        timeout: Duration,
+        identity: Arc<dyn Signer + Send + Sync>,
+    ) -> Self {
+        Self {
+            rpc_auth_identity: Some(identity),
+            ..Self::new_with_timeout(url, timeout)
+        }
     }
 }
 
@@ -45,13 +65,21 @@ impl RpcSender for HttpSender {
 
         let mut too_many_requests_retries = 5;
         loop {
-            match self
+            let mut request_builder = self
                 .client
                 .post(&self.url)
                 .header(CONTENT_TYPE, "application/json")
-                .body(request_json.to_string())
-                .send()
-            {
+                .body(request_json.to_string());
+            // Tokens are accepted once, so every attempt gets its own
+            if let Some(identity) = &self.rpc_auth_identity {
+                if let Some(token) = RpcAuthToken::new_for_request(identity.as_ref(), &request_json)
+                {
+                    for (name, value) in token.headers() {
+                        request_builder = request_builder.header(name, value);
+                    }
+                }
+            }
+            match request_builder.send() {
                 Ok(response) => {
                     if !response.status().is_success() {
                         if response.status() == StatusCode::TOO_MANY_REQUESTS
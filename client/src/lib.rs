@@ -8,6 +8,7 @@ pub mod mock_sender;
 pub mod nonce_utils;
 pub mod perf_utils;
 pub mod pubsub_client;
+pub mod rpc_auth;
 pub mod rpc_client;
 pub mod rpc_config;
 pub mod rpc_custom_error;
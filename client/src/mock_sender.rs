@@ -1,11 +1,12 @@
 use crate::{
     client_error::Result,
-    rpc_request::RpcRequest,
+    rpc_request::{RpcError, RpcRequest},
     rpc_response::{Response, RpcResponseContext, RpcVersionInfo},
     rpc_sender::RpcSender,
 };
 use serde_json::{json, Number, Value};
 use solana_sdk::{
+    clock::MAX_PROCESSING_AGE,
     epoch_info::EpochInfo,
     fee_calculator::{FeeCalculator, FeeRateGovernor},
     instruction::InstructionError,
@@ -14,14 +15,58 @@ use solana_sdk::{
 };
 use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
 use solana_version::Version;
-use std::{collections::HashMap, sync::RwLock};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::RwLock,
+    thread::sleep,
+    time::Duration,
+};
 
 pub const PUBKEY: &str = "7RoSF9fUmdphVCpabEoefH81WwrW7orsWonXWqTXkKV8";
 pub const SIGNATURE: &str =
     "43yNSFC6fYTuPgTNFFhF4axw7AfWxB2BPdurme8yrsWEYwm8299xh8n6TAHjGymiSub1XtyxTNyd9GBfY2hxoBw8";
 
 pub type Mocks = HashMap<RpcRequest, Value>;
+
+/// A programmed reply to a request, delivered after `latency`
+#[derive(Debug)]
+pub struct MockResponse {
+    pub result: std::result::Result<Value, RpcError>,
+    pub latency: Duration,
+}
+
+impl MockResponse {
+    pub fn value(value: Value) -> Self {
+        Self {
+            result: Ok(value),
+            latency: Duration::default(),
+        }
+    }
+
+    pub fn error(error: RpcError) -> Self {
+        Self {
+            result: Err(error),
+            latency: Duration::default(),
+        }
+    }
+
+    pub fn with_latency(self, latency: Duration) -> Self {
+        Self { latency, ..self }
+    }
+}
+
+/// A request received by a `MockSender`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockRequest {
+    pub request: RpcRequest,
+    pub params: Value,
+}
+
+/// An `RpcSender` replying from, in order of precedence, the responses programmed for each
+/// method, the one-shot `Mocks`, and canned responses selected by `url`
 pub struct MockSender {
+    responses: RwLock<HashMap<RpcRequest, VecDeque<MockResponse>>>,
+    requests: RwLock<Vec<MockRequest>>,
     mocks: RwLock<Mocks>,
     url: String,
 }
@@ -33,14 +78,82 @@ impl MockSender {
 
     pub fn new_with_mocks(url: String, mocks: Mocks) -> Self {
         Self {
+            responses: RwLock::default(),
+            requests: RwLock::default(),
             url,
             mocks: RwLock::new(mocks),
         }
     }
+
+    /// Queues a response to the next `request` not answered by an earlier queued response
+    pub fn push_response(&self, request: RpcRequest, response: MockResponse) {
+        self.responses
+            .write()
+            .unwrap()
+            .entry(request)
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Queues a sequence of responses to `request`, answered in order
+    pub fn push_responses<I>(&self, request: RpcRequest, responses: I)
+    where
+        I: IntoIterator<Item = MockResponse>,
+    {
+        self.responses
+            .write()
+            .unwrap()
+            .entry(request)
+            .or_default()
+            .extend(responses);
+    }
+
+    /// Number of queued responses to `request` that were not delivered yet
+    pub fn pending_responses(&self, request: RpcRequest) -> usize {
+        self.responses
+            .read()
+            .unwrap()
+            .get(&request)
+            .map_or(0, VecDeque::len)
+    }
+
+    /// All requests received so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.read().unwrap().clone()
+    }
+
+    /// The params of the `request`s received so far, oldest first
+    pub fn requests_for(&self, request: RpcRequest) -> Vec<Value> {
+        self.requests
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|mock_request| mock_request.request == request)
+            .map(|mock_request| mock_request.params.clone())
+            .collect()
+    }
+
+    pub fn clear_requests(&self) {
+        self.requests.write().unwrap().clear();
+    }
 }
 
 impl RpcSender for MockSender {
     fn send(&self, request: RpcRequest, params: serde_json::Value) -> Result<serde_json::Value> {
+        self.requests.write().unwrap().push(MockRequest {
+            request,
+            params: params.clone(),
+        });
+        let response = self
+            .responses
+            .write()
+            .unwrap()
+            .get_mut(&request)
+            .and_then(VecDeque::pop_front);
+        if let Some(MockResponse { result, latency }) = response {
+            sleep(latency);
+            return result.map_err(Into::into);
+        }
         if let Some(value) = self.mocks.write().unwrap().remove(&request) {
             return Ok(value);
         }
@@ -133,6 +246,7 @@ impl RpcSender for MockSender {
                 };
                 Value::String(signature)
             }
+            RpcRequest::GetMaxProcessingAge => json![MAX_PROCESSING_AGE],
             RpcRequest::GetMinimumBalanceForRentExemption => Value::Number(Number::from(20)),
             RpcRequest::GetVersion => {
                 let version = Version::default();
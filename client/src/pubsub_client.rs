@@ -1,15 +1,22 @@
 use crate::{
-    rpc_config::{RpcSignatureSubscribeConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
-    rpc_response::{Response as RpcResponse, RpcLogsResponse, RpcSignatureResult, SlotInfo},
+    rpc_config::{
+        RpcAccountInfoConfig, RpcBlockRewardsConfig, RpcProgramAccountsConfig,
+        RpcSignatureSubscribeConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+    },
+    rpc_response::{
+        EpochBoundaryInfo, Response as RpcResponse, RpcBlockRewards, RpcKeyedAccount,
+        RpcLogsResponse, RpcSignatureResult, SlotInfo,
+    },
 };
 use log::*;
-use serde::de::DeserializeOwned;
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{
     json,
     value::Value::{Number, Object},
     Map, Value,
 };
-use solana_sdk::signature::Signature;
+use solana_account_decoder::UiAccount;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
 use std::{
     marker::PhantomData,
     sync::{
@@ -20,8 +27,11 @@ use std::{
     thread::JoinHandle,
 };
 use thiserror::Error;
-use tungstenite::{client::AutoStream, connect, Message, WebSocket};
-use url::{ParseError, Url};
+use tungstenite::{
+    client::{AutoStream, IntoClientRequest},
+    connect, Message, WebSocket,
+};
+use url::ParseError;
 
 #[derive(Debug, Error)]
 pub enum PubsubClientError {
@@ -151,20 +161,122 @@ pub type LogsSubscription = (
     Receiver<RpcResponse<RpcLogsResponse>>,
 );
 pub type SlotsSubscription = (PubsubClientSubscription<SlotInfo>, Receiver<SlotInfo>);
+pub type EpochSubscription = (
+    PubsubClientSubscription<EpochBoundaryInfo>,
+    Receiver<EpochBoundaryInfo>,
+);
+pub type RewardsSubscription = (
+    PubsubClientSubscription<RpcBlockRewards>,
+    Receiver<RpcBlockRewards>,
+);
 pub type SignatureSubscription = (
     PubsubClientSubscription<RpcResponse<RpcSignatureResult>>,
     Receiver<RpcResponse<RpcSignatureResult>>,
 );
+pub type AccountSubscription = (
+    PubsubClientSubscription<RpcResponse<UiAccount>>,
+    Receiver<RpcResponse<UiAccount>>,
+);
+pub type ProgramSubscription = (
+    PubsubClientSubscription<RpcResponse<RpcKeyedAccount>>,
+    Receiver<RpcResponse<RpcKeyedAccount>>,
+);
 
+/// Subscriptions connect to a url, or to a request made with `rpc_auth_pubsub_request` to
+/// authenticate the connection with an identity keypair
 pub struct PubsubClient {}
 
 impl PubsubClient {
-    pub fn logs_subscribe(
-        url: &str,
+    /// Notifications carry the account in the encoding requested by `config`, the accounts of
+    /// every encoding but `jsonParsed` can be turned back into an `Account` with
+    /// `UiAccount::decode`
+    pub fn account_subscribe<R: IntoClientRequest>(
+        url: R,
+        pubkey: &Pubkey,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Result<AccountSubscription, PubsubClientError> {
+        Self::subscribe(url, "account", pubkey, config)
+    }
+
+    pub fn program_subscribe<R: IntoClientRequest>(
+        url: R,
+        program_id: &Pubkey,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> Result<ProgramSubscription, PubsubClientError> {
+        Self::subscribe(url, "program", program_id, config)
+    }
+
+    fn subscribe<R, T, C>(
+        url: R,
+        operation: &'static str,
+        pubkey: &Pubkey,
+        config: Option<C>,
+    ) -> Result<(PubsubClientSubscription<T>, Receiver<T>), PubsubClientError>
+    where
+        R: IntoClientRequest,
+        T: DeserializeOwned + Send + 'static,
+        C: Serialize,
+    {
+        let (socket, _response) = connect(url)?;
+        let (sender, receiver) = channel();
+
+        let socket = Arc::new(RwLock::new(socket));
+        let socket_clone = socket.clone();
+        let exit = Arc::new(AtomicBool::new(false));
+        let exit_clone = exit.clone();
+        let body = json!({
+            "jsonrpc":"2.0",
+            "id":1,
+            "method":format!("{}Subscribe", operation),
+            "params":[
+                pubkey.to_string(),
+                config
+            ]
+        })
+        .to_string();
+        let subscription_id = PubsubClientSubscription::<T>::send_subscribe(&socket_clone, body)?;
+
+        let t_cleanup = std::thread::spawn(move || {
+            loop {
+                if exit_clone.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                match PubsubClientSubscription::read_message(&socket_clone) {
+                    Ok(message) => match sender.send(message) {
+                        Ok(_) => (),
+                        Err(err) => {
+                            info!("receive error: {:?}", err);
+                            break;
+                        }
+                    },
+                    Err(err) => {
+                        info!("receive error: {:?}", err);
+                        break;
+                    }
+                }
+            }
+
+            info!("websocket - exited receive loop");
+        });
+
+        let result = PubsubClientSubscription {
+            message_type: PhantomData,
+            operation,
+            socket,
+            subscription_id,
+            t_cleanup: Some(t_cleanup),
+            exit,
+        };
+
+        Ok((result, receiver))
+    }
+
+    pub fn logs_subscribe<R: IntoClientRequest>(
+        url: R,
         filter: RpcTransactionLogsFilter,
         config: RpcTransactionLogsConfig,
     ) -> Result<LogsSubscription, PubsubClientError> {
-        let url = Url::parse(url)?;
         let (socket, _response) = connect(url)?;
         let (sender, receiver) = channel();
 
@@ -218,8 +330,9 @@ impl PubsubClient {
         Ok((result, receiver))
     }
 
-    pub fn slot_subscribe(url: &str) -> Result<SlotsSubscription, PubsubClientError> {
-        let url = Url::parse(url)?;
+    pub fn slot_subscribe<R: IntoClientRequest>(
+        url: R,
+    ) -> Result<SlotsSubscription, PubsubClientError> {
         let (socket, _response) = connect(url)?;
         let (sender, receiver) = channel::<SlotInfo>();
 
@@ -270,12 +383,118 @@ impl PubsubClient {
         Ok((result, receiver))
     }
 
-    pub fn signature_subscribe(
-        url: &str,
+    pub fn epoch_subscribe<R: IntoClientRequest>(
+        url: R,
+    ) -> Result<EpochSubscription, PubsubClientError> {
+        let (socket, _response) = connect(url)?;
+        let (sender, receiver) = channel::<EpochBoundaryInfo>();
+
+        let socket = Arc::new(RwLock::new(socket));
+        let socket_clone = socket.clone();
+        let exit = Arc::new(AtomicBool::new(false));
+        let exit_clone = exit.clone();
+        let subscription_id = PubsubClientSubscription::<EpochBoundaryInfo>::send_subscribe(
+            &socket_clone,
+            json!({
+                "jsonrpc":"2.0","id":1,"method":"epochSubscribe","params":[]
+            })
+            .to_string(),
+        )?;
+
+        let t_cleanup = std::thread::spawn(move || {
+            loop {
+                if exit_clone.load(Ordering::Relaxed) {
+                    break;
+                }
+                match PubsubClientSubscription::read_message(&socket_clone) {
+                    Ok(message) => match sender.send(message) {
+                        Ok(_) => (),
+                        Err(err) => {
+                            info!("receive error: {:?}", err);
+                            break;
+                        }
+                    },
+                    Err(err) => {
+                        info!("receive error: {:?}", err);
+                        break;
+                    }
+                }
+            }
+
+            info!("websocket - exited receive loop");
+        });
+
+        let result = PubsubClientSubscription {
+            message_type: PhantomData,
+            operation: "epoch",
+            socket,
+            subscription_id,
+            t_cleanup: Some(t_cleanup),
+            exit,
+        };
+
+        Ok((result, receiver))
+    }
+
+    pub fn rewards_subscribe<R: IntoClientRequest>(
+        url: R,
+        config: Option<RpcBlockRewardsConfig>,
+    ) -> Result<RewardsSubscription, PubsubClientError> {
+        let (socket, _response) = connect(url)?;
+        let (sender, receiver) = channel::<RpcBlockRewards>();
+
+        let socket = Arc::new(RwLock::new(socket));
+        let socket_clone = socket.clone();
+        let exit = Arc::new(AtomicBool::new(false));
+        let exit_clone = exit.clone();
+        let subscription_id = PubsubClientSubscription::<RpcBlockRewards>::send_subscribe(
+            &socket_clone,
+            json!({
+                "jsonrpc":"2.0","id":1,"method":"rewardsSubscribe","params":[config]
+            })
+            .to_string(),
+        )?;
+
+        let t_cleanup = std::thread::spawn(move || {
+            loop {
+                if exit_clone.load(Ordering::Relaxed) {
+                    break;
+                }
+                match PubsubClientSubscription::read_message(&socket_clone) {
+                    Ok(message) => match sender.send(message) {
+                        Ok(_) => (),
+                        Err(err) => {
+                            info!("receive error: {:?}", err);
+                            break;
+                        }
+                    },
+                    Err(err) => {
+                        info!("receive error: {:?}", err);
+                        break;
+                    }
+                }
+            }
+
+            info!("websocket - exited receive loop");
+        });
+
+        let result = PubsubClientSubscription {
+            message_type: PhantomData,
+            operation: "rewards",
+            socket,
+            subscription_id,
+            t_cleanup: Some(t_cleanup),
+            exit,
+        };
+
+        Ok((result, receiver))
+    }
+
+    pub fn signature_subscribe<R: IntoClientRequest>(
+        url: R,
         signature: &Signature,
         config: Option<RpcSignatureSubscribeConfig>,
     ) -> Result<SignatureSubscription, PubsubClientError> {
-        let url = Url::parse(url)?;
         let (socket, _response) = connect(url)?;
         let (sender, receiver) = channel();
 
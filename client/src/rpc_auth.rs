@@ -0,0 +1,237 @@
+//! Tokens authenticating JSON RPC requests and pubsub connections with an identity keypair, for
+//! the nodes restricting some of their methods to the identities allowed to call them.
+//!
+//! A token signs a unix timestamp, a nonce and the hash of the request it comes with, and is
+//! passed in the `solana-rpc-identity`, `solana-rpc-timestamp`, `solana-rpc-nonce` and
+//! `solana-rpc-signature` request headers.  Pubsub connections pass a token signed for pubsub
+//! as a `solana-rpc-auth.` websocket protocol when they are opened.
+
+use {
+    jsonrpc_core::Request,
+    solana_sdk::{
+        hash::{hash, Hash},
+        pubkey::Pubkey,
+        signature::{Signature, Signer},
+    },
+    std::{
+        str::FromStr,
+        time::{SystemTime, UNIX_EPOCH},
+    },
+    tungstenite::{
+        client::IntoClientRequest,
+        handshake::client::Request as PubsubRequest,
+        http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderValue},
+    },
+};
+
+pub const RPC_AUTH_IDENTITY_HEADER: &str = "solana-rpc-identity";
+pub const RPC_AUTH_TIMESTAMP_HEADER: &str = "solana-rpc-timestamp";
+pub const RPC_AUTH_NONCE_HEADER: &str = "solana-rpc-nonce";
+pub const RPC_AUTH_SIGNATURE_HEADER: &str = "solana-rpc-signature";
+
+/// Prefix of the websocket protocol carrying the token of a pubsub connection, followed by the
+/// identity, timestamp, nonce and signature separated by dots
+pub const RPC_AUTH_PUBSUB_PROTOCOL_PREFIX: &str = "solana-rpc-auth.";
+
+/// The message an identity signs to authenticate the request hashing to `request_hash`
+pub fn rpc_auth_message(timestamp: u64, nonce: u64, request_hash: &Hash) -> Vec<u8> {
+    format!("solana-rpc-auth:{}:{}:{}", timestamp, nonce, request_hash).into_bytes()
+}
+
+/// The hash a token authenticating `request` signs.  It is computed over the parsed request,
+/// whose objects serialize with sorted keys, so the formatting of the request body is irrelevant
+pub fn rpc_auth_request_hash(request: &Request) -> Hash {
+    let request = serde_json::to_value(request).unwrap_or_default();
+    hash(request.to_string().as_bytes())
+}
+
+/// The hash a token authenticating a pubsub connection signs
+pub fn rpc_auth_pubsub_hash() -> Hash {
+    hash(b"solana-rpc-auth-pubsub")
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// A token claiming to authenticate `identity`, still to be verified against the request or
+/// connection it came with
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcAuthToken {
+    pub identity: Pubkey,
+    pub timestamp: u64,
+    pub nonce: u64,
+    pub signature: Signature,
+}
+
+impl RpcAuthToken {
+    /// The token of `identity` for the request or connection hashing to `request_hash`
+    pub fn new(identity: &dyn Signer, timestamp: u64, nonce: u64, request_hash: &Hash) -> Self {
+        Self {
+            identity: identity.pubkey(),
+            timestamp,
+            nonce,
+            signature: identity.sign_message(&rpc_auth_message(timestamp, nonce, request_hash)),
+        }
+    }
+
+    /// A fresh token of `identity` for the JSON RPC request `request`, None if it isn't one
+    pub fn new_for_request(identity: &dyn Signer, request: &serde_json::Value) -> Option<Self> {
+        let request = serde_json::from_value::<Request>(request.clone()).ok()?;
+        Some(Self::new(
+            identity,
+            unix_timestamp(),
+            rand::random(),
+            &rpc_auth_request_hash(&request),
+        ))
+    }
+
+    /// A fresh token of `identity` for opening a pubsub connection
+    pub fn new_for_pubsub(identity: &dyn Signer) -> Self {
+        Self::new(
+            identity,
+            unix_timestamp(),
+            rand::random(),
+            &rpc_auth_pubsub_hash(),
+        )
+    }
+
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        vec![
+            (RPC_AUTH_IDENTITY_HEADER, self.identity.to_string()),
+            (RPC_AUTH_TIMESTAMP_HEADER, self.timestamp.to_string()),
+            (RPC_AUTH_NONCE_HEADER, self.nonce.to_string()),
+            (RPC_AUTH_SIGNATURE_HEADER, self.signature.to_string()),
+        ]
+    }
+
+    /// The token in the headers `header` looks up by name, None if there is none or it
+    /// doesn't parse
+    pub fn from_headers<'a, F>(header: F) -> Option<Self>
+    where
+        F: Fn(&str) -> Option<&'a str>,
+    {
+        Some(Self {
+            identity: Pubkey::from_str(header(RPC_AUTH_IDENTITY_HEADER)?).ok()?,
+            timestamp: u64::from_str(header(RPC_AUTH_TIMESTAMP_HEADER)?).ok()?,
+            nonce: u64::from_str(header(RPC_AUTH_NONCE_HEADER)?).ok()?,
+            signature: Signature::from_str(header(RPC_AUTH_SIGNATURE_HEADER)?).ok()?,
+        })
+    }
+
+    pub fn pubsub_protocol(&self) -> String {
+        format!(
+            "{}{}.{}.{}.{}",
+            RPC_AUTH_PUBSUB_PROTOCOL_PREFIX,
+            self.identity,
+            self.timestamp,
+            self.nonce,
+            self.signature
+        )
+    }
+
+    /// The token among the websocket protocols of a pubsub connection
+    pub fn from_pubsub_protocols(protocols: &[String]) -> Option<Self> {
+        let protocol = protocols
+            .iter()
+            .find_map(|protocol| protocol.strip_prefix(RPC_AUTH_PUBSUB_PROTOCOL_PREFIX))?;
+        let mut fields = protocol.split('.');
+        let token = Self {
+            identity: Pubkey::from_str(fields.next()?).ok()?,
+            timestamp: u64::from_str(fields.next()?).ok()?,
+            nonce: u64::from_str(fields.next()?).ok()?,
+            signature: Signature::from_str(fields.next()?).ok()?,
+        };
+        if fields.next().is_some() {
+            return None;
+        }
+        Some(token)
+    }
+}
+
+/// A request opening a pubsub connection to `url` authenticated as `identity`, to pass to the
+/// `PubsubClient` subscriptions in place of the url
+pub fn rpc_auth_pubsub_request(
+    url: &str,
+    identity: &dyn Signer,
+) -> tungstenite::Result<PubsubRequest> {
+    let mut request = url.into_client_request()?;
+    let protocol = RpcAuthToken::new_for_pubsub(identity).pubsub_protocol();
+    request.headers_mut().insert(
+        SEC_WEBSOCKET_PROTOCOL,
+        HeaderValue::from_str(&protocol).expect("token is a valid header value"),
+    );
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_sdk::signature::Keypair, std::collections::HashMap};
+
+    #[test]
+    fn test_rpc_auth_token_encoding() {
+        let token = RpcAuthToken::new(&Keypair::new(), 1_000_000, 42, &hash(b"request"));
+        let headers: HashMap<_, _> = token.headers().into_iter().collect();
+        assert_eq!(
+            RpcAuthToken::from_headers(|name| headers.get(name).map(String::as_str)),
+            Some(token.clone())
+        );
+        assert_eq!(RpcAuthToken::from_headers(|_| None), None);
+
+        let protocols = vec!["other".to_string(), token.pubsub_protocol()];
+        assert_eq!(
+            RpcAuthToken::from_pubsub_protocols(&protocols),
+            Some(token.clone())
+        );
+        assert_eq!(RpcAuthToken::from_pubsub_protocols(&protocols[..1]), None);
+        let protocols = vec![format!("{}.1", token.pubsub_protocol())];
+        assert_eq!(RpcAuthToken::from_pubsub_protocols(&protocols), None);
+    }
+
+    #[test]
+    fn test_rpc_auth_token_for_request() {
+        let identity = Keypair::new();
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "getBalance",
+            "params": [identity.pubkey().to_string()],
+        });
+        let token = RpcAuthToken::new_for_request(&identity, &request).unwrap();
+        let request_hash = rpc_auth_request_hash(
+            &serde_json::from_str(&format!(
+                r#"{{"params":["{}"],"method":"getBalance","id":1,"jsonrpc":"2.0"}}"#,
+                identity.pubkey()
+            ))
+            .unwrap(),
+        );
+        assert!(token.signature.verify(
+            identity.pubkey().as_ref(),
+            &rpc_auth_message(token.timestamp, token.nonce, &request_hash)
+        ));
+
+        // Every token gets its own nonce
+        let other = RpcAuthToken::new_for_request(&identity, &request).unwrap();
+        assert_ne!(token.nonce, other.nonce);
+        assert!(RpcAuthToken::new_for_request(&identity, &serde_json::json!(1)).is_none());
+    }
+
+    #[test]
+    fn test_rpc_auth_pubsub_request() {
+        let identity = Keypair::new();
+        let request = rpc_auth_pubsub_request("ws://127.0.0.1:8900", &identity).unwrap();
+        let protocols = vec![request.headers()[SEC_WEBSOCKET_PROTOCOL]
+            .to_str()
+            .unwrap()
+            .to_string()];
+        let token = RpcAuthToken::from_pubsub_protocols(&protocols).unwrap();
+        assert_eq!(token.identity, identity.pubkey());
+        assert!(token.signature.verify(
+            identity.pubkey().as_ref(),
+            &rpc_auth_message(token.timestamp, token.nonce, &rpc_auth_pubsub_hash())
+        ));
+    }
+}
@@ -4,9 +4,10 @@ use crate::{
     mock_sender::{MockSender, Mocks},
     rpc_config::RpcAccountInfoConfig,
     rpc_config::{
-        RpcGetConfirmedSignaturesForAddress2Config, RpcLargestAccountsConfig,
-        RpcProgramAccountsConfig, RpcSendTransactionConfig, RpcSimulateTransactionConfig,
-        RpcTokenAccountsFilter,
+        RpcBlockRewardsConfig, RpcFeeForMessageConfig, RpcGetConfirmedSignaturesForAddress2Config,
+        RpcGossipConfig, RpcLargestAccountsConfig, RpcProgramAccountsConfig,
+        RpcSendTransactionConfig, RpcSimulateTransactionConfig, RpcTokenAccountsFilter,
+        RpcValidatorDelegationsConfig,
     },
     rpc_request::{RpcError, RpcRequest, RpcResponseErrorData, TokenAccountsFilter},
     rpc_response::*,
@@ -22,14 +23,15 @@ use solana_account_decoder::{
 };
 use solana_sdk::{
     account::Account,
-    clock::{Slot, UnixTimestamp, DEFAULT_MS_PER_SLOT, MAX_HASH_AGE_IN_SECONDS},
+    clock::{Slot, UnixTimestamp, DEFAULT_MS_PER_SLOT, MAX_PROCESSING_AGE},
     commitment_config::{CommitmentConfig, CommitmentLevel},
     epoch_info::EpochInfo,
     epoch_schedule::EpochSchedule,
     fee_calculator::{FeeCalculator, FeeRateGovernor},
     hash::Hash,
+    message::Message,
     pubkey::Pubkey,
-    signature::Signature,
+    signature::{Signature, Signer},
     transaction::{self, uses_durable_nonce, Transaction},
 };
 use solana_transaction_status::{
@@ -39,7 +41,7 @@ use solana_vote_program::vote_state::MAX_LOCKOUT_HISTORY;
 use std::{
     cmp::min,
     net::SocketAddr,
-    sync::RwLock,
+    sync::{Arc, RwLock},
     thread::sleep,
     time::{Duration, Instant},
 };
@@ -54,6 +56,9 @@ fn serialize_encode_transaction(
     transaction: &Transaction,
     encoding: UiTransactionEncoding,
 ) -> ClientResult<String> {
+    transaction
+        .check_size()
+        .map_err(|e| ClientErrorKind::Custom(e.to_string()))?;
     let serialized = serialize(transaction)
         .map_err(|e| ClientErrorKind::Custom(format!("transaction serialization failed: {}", e)))?;
     let encoded = match encoding {
@@ -108,6 +113,20 @@ impl RpcClient {
         )
     }
 
+    /// Authenticates every request as `identity`, for nodes restricting some of their methods
+    /// to the identities allowed to call them
+    pub fn new_with_rpc_auth(
+        url: String,
+        timeout: Duration,
+        commitment_config: CommitmentConfig,
+        identity: Arc<dyn Signer + Send + Sync>,
+    ) -> Self {
+        Self::new_sender(
+            HttpSender::new_with_rpc_auth(url, timeout, identity),
+            commitment_config,
+        )
+    }
+
     pub fn new_mock(url: String) -> Self {
         Self::new_sender(MockSender::new(url), CommitmentConfig::default())
     }
@@ -119,6 +138,12 @@ impl RpcClient {
         )
     }
 
+    /// Creates a client sharing `mock_sender` with the caller, who can keep queueing responses
+    /// and inspect the requests made
+    pub fn new_with_mock_sender(mock_sender: Arc<MockSender>) -> Self {
+        Self::new_sender(mock_sender, CommitmentConfig::default())
+    }
+
     pub fn new_socket(addr: SocketAddr) -> Self {
         Self::new(get_rpc_request_str(addr, false))
     }
@@ -322,6 +347,10 @@ impl RpcClient {
         self.send(RpcRequest::GetSnapshotSlot, Value::Null)
     }
 
+    pub fn get_highest_snapshot_slot(&self) -> ClientResult<RpcSnapshotSlotInfo> {
+        self.send(RpcRequest::GetHighestSnapshotSlot, Value::Null)
+    }
+
     pub fn get_signature_status(
         &self,
         signature: &Signature,
@@ -397,6 +426,28 @@ impl RpcClient {
         )
     }
 
+    pub fn get_slot_execute_timings(
+        &self,
+        slot: Slot,
+    ) -> ClientResult<Option<RpcSlotExecuteTimings>> {
+        self.send(RpcRequest::GetSlotExecuteTimings, json!([slot]))
+    }
+
+    pub fn get_block_cost(&self, slot: Slot) -> ClientResult<Option<RpcBlockCost>> {
+        self.send(RpcRequest::GetBlockCost, json!([slot]))
+    }
+
+    pub fn get_repair_status(
+        &self,
+        limit: Option<usize>,
+    ) -> ClientResult<Vec<RpcSlotRepairStatus>> {
+        self.send(RpcRequest::GetRepairStatus, json!([limit]))
+    }
+
+    pub fn get_gossip_stats(&self) -> ClientResult<RpcGossipStats> {
+        self.send(RpcRequest::GetGossipStats, Value::Null)
+    }
+
     pub fn supply(&self) -> RpcResult<RpcSupply> {
         self.supply_with_commitment(self.commitment_config)
     }
@@ -452,6 +503,31 @@ impl RpcClient {
         )
     }
 
+    /// Returns the self and foreign stake breakdown of every staked vote account, or only of
+    /// `vote_pubkey`
+    pub fn get_validator_delegations(
+        &self,
+        vote_pubkey: Option<&Pubkey>,
+    ) -> RpcResult<Vec<RpcValidatorDelegations>> {
+        self.get_validator_delegations_with_config(RpcValidatorDelegationsConfig {
+            commitment: Some(self.commitment_config),
+            vote_pubkey: vote_pubkey.map(|vote_pubkey| vote_pubkey.to_string()),
+        })
+    }
+
+    pub fn get_validator_delegations_with_config(
+        &self,
+        config: RpcValidatorDelegationsConfig,
+    ) -> RpcResult<Vec<RpcValidatorDelegations>> {
+        let commitment = config.commitment.unwrap_or_default();
+        let commitment = self.maybe_map_commitment(commitment)?;
+        let config = RpcValidatorDelegationsConfig {
+            commitment: Some(commitment),
+            ..config
+        };
+        self.send(RpcRequest::GetValidatorDelegations, json!([config]))
+    }
+
     pub fn wait_for_max_stake(
         &self,
         commitment: CommitmentConfig,
@@ -500,6 +576,16 @@ impl RpcClient {
         self.send(RpcRequest::GetConfirmedBlock, json!([slot, encoding]))
     }
 
+    /// Fetches only the rewards of a confirmed block, optionally only those paid to
+    /// `config.addresses`
+    pub fn get_confirmed_block_rewards_with_config(
+        &self,
+        slot: Slot,
+        config: RpcBlockRewardsConfig,
+    ) -> ClientResult<Option<RpcBlockRewards>> {
+        self.send(RpcRequest::GetConfirmedBlockRewards, json!([slot, config]))
+    }
+
     pub fn get_confirmed_blocks(
         &self,
         start_slot: Slot,
@@ -727,7 +813,7 @@ impl RpcClient {
         commitment_config: CommitmentConfig,
     ) -> RpcResult<Option<Account>> {
         let config = RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
+            encoding: Some(UiAccountEncoding::Base64Zstd),
             commitment: Some(self.maybe_map_commitment(commitment_config)?),
             data_slice: None,
         };
@@ -774,7 +860,7 @@ impl RpcClient {
         commitment_config: CommitmentConfig,
     ) -> RpcResult<Vec<Option<Account>>> {
         let config = RpcAccountInfoConfig {
-            encoding: Some(UiAccountEncoding::Base64),
+            encoding: Some(UiAccountEncoding::Base64Zstd),
             commitment: Some(self.maybe_map_commitment(commitment_config)?),
             data_slice: None,
         };
@@ -836,13 +922,24 @@ impl RpcClient {
         )
     }
 
+    /// Returns the stats of every program invoked recently, or only those of `program_id`
+    pub fn get_program_stats(
+        &self,
+        program_id: Option<&Pubkey>,
+    ) -> ClientResult<RpcProgramStatsWindow> {
+        self.send(
+            RpcRequest::GetProgramStats,
+            json!([program_id.map(|program_id| program_id.to_string())]),
+        )
+    }
+
     pub fn get_program_accounts(&self, pubkey: &Pubkey) -> ClientResult<Vec<(Pubkey, Account)>> {
         self.get_program_accounts_with_config(
             pubkey,
             RpcProgramAccountsConfig {
                 filters: None,
                 account_config: RpcAccountInfoConfig {
-                    encoding: Some(UiAccountEncoding::Base64),
+                    encoding: Some(UiAccountEncoding::Base64Zstd),
                     commitment: Some(self.commitment_config),
                     ..RpcAccountInfoConfig::default()
                 },
@@ -971,6 +1068,31 @@ impl RpcClient {
         })
     }
 
+    /// The fee of `message`, whether its fee payer can pay it and whether its blockhash is still
+    /// valid, to check a transaction before signing it
+    pub fn get_fee_for_message(&self, message: &Message) -> RpcResult<RpcFeeForMessage> {
+        self.get_fee_for_message_with_commitment(message, self.commitment_config)
+    }
+
+    pub fn get_fee_for_message_with_commitment(
+        &self,
+        message: &Message,
+        commitment_config: CommitmentConfig,
+    ) -> RpcResult<RpcFeeForMessage> {
+        let serialized = serialize(message)
+            .map_err(|e| ClientErrorKind::Custom(format!("message serialization failed: {}", e)))?;
+        self.send(
+            RpcRequest::GetFeeForMessage,
+            json!([
+                base64::encode(serialized),
+                RpcFeeForMessageConfig {
+                    commitment: Some(self.maybe_map_commitment(commitment_config)?),
+                    encoding: Some(UiTransactionEncoding::Base64),
+                }
+            ]),
+        )
+    }
+
     pub fn get_fee_rate_governor(&self) -> RpcResult<FeeRateGovernor> {
         let Response {
             context,
@@ -984,6 +1106,20 @@ impl RpcClient {
         })
     }
 
+    pub fn get_max_processing_age(&self) -> ClientResult<usize> {
+        self.get_max_processing_age_with_commitment(self.commitment_config)
+    }
+
+    pub fn get_max_processing_age_with_commitment(
+        &self,
+        commitment_config: CommitmentConfig,
+    ) -> ClientResult<usize> {
+        self.send(
+            RpcRequest::GetMaxProcessingAge,
+            json!([self.maybe_map_commitment(commitment_config)?]),
+        )
+    }
+
     pub fn get_new_blockhash(&self, blockhash: &Hash) -> ClientResult<(Hash, FeeCalculator)> {
         let mut num_retries = 0;
         let start = Instant::now();
@@ -1465,6 +1601,9 @@ impl RpcClient {
             )
             .into());
         }
+        // Nodes predating getMaxProcessingAge process blockhashes up to the default age
+        let max_processing_age = self.get_max_processing_age().unwrap_or(MAX_PROCESSING_AGE);
+        let timeout = Duration::from_millis(max_processing_age as u64 * DEFAULT_MS_PER_SLOT);
         let now = Instant::now();
         loop {
             // Return when specified commitment is reached
@@ -1488,7 +1627,7 @@ impl RpcClient {
             confirmations = self
                 .get_num_blocks_since_signature_confirmation(&signature)
                 .unwrap_or(confirmations);
-            if now.elapsed().as_secs() >= MAX_HASH_AGE_IN_SECONDS as u64 {
+            if now.elapsed() >= timeout {
                 return Err(
                     RpcError::ForUser("transaction not finalized. \
                                       This can happen when a transaction lands in an abandoned fork. \
@@ -1502,6 +1641,12 @@ impl RpcClient {
         self.send(RpcRequest::ValidatorExit, Value::Null)
     }
 
+    /// Adjusts the gossip intervals and bandwidth budget of the node, returns false if the node
+    /// was not started with `--enable-rpc-set-gossip-config`
+    pub fn set_gossip_config(&self, config: RpcGossipConfig) -> ClientResult<bool> {
+        self.send(RpcRequest::SetGossipConfig, json!([config]))
+    }
+
     pub fn send<T>(&self, request: RpcRequest, params: Value) -> ClientResult<T>
     where
         T: serde::de::DeserializeOwned,
@@ -1567,7 +1712,10 @@ fn parse_keyed_accounts(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{client_error::ClientErrorKind, mock_sender::PUBKEY};
+    use crate::{
+        client_error::ClientErrorKind,
+        mock_sender::{MockResponse, PUBKEY},
+    };
     use assert_matches::assert_matches;
     use jsonrpc_core::{Error, IoHandler, Params};
     use jsonrpc_http_server::{AccessControlAllowOrigin, DomainsValidation, ServerBuilder};
@@ -1717,6 +1865,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mock_sender_programmed_responses() {
+        let mock_sender = Arc::new(MockSender::new("succeeds".to_string()));
+        let rpc_client = RpcClient::new_with_mock_sender(mock_sender.clone());
+
+        mock_sender.push_responses(
+            RpcRequest::GetSlot,
+            vec![
+                MockResponse::value(json!(5)),
+                MockResponse::error(RpcError::ForUser("slot unavailable".to_string())),
+                MockResponse::value(json!(7)).with_latency(Duration::from_millis(10)),
+            ],
+        );
+        assert_eq!(mock_sender.pending_responses(RpcRequest::GetSlot), 3);
+
+        assert_eq!(rpc_client.get_slot().unwrap(), 5);
+        assert_matches!(
+            rpc_client.get_slot().unwrap_err().kind(),
+            ClientErrorKind::RpcError(RpcError::ForUser(_))
+        );
+        let start = Instant::now();
+        assert_eq!(rpc_client.get_slot().unwrap(), 7);
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        assert_eq!(mock_sender.pending_responses(RpcRequest::GetSlot), 0);
+
+        // Falls back to the canned responses once the programmed ones are exhausted
+        assert_eq!(rpc_client.get_slot().unwrap(), 0);
+
+        let requests = mock_sender.requests_for(RpcRequest::GetSlot);
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0], json!([CommitmentConfig::default()]));
+
+        mock_sender.clear_requests();
+        assert!(mock_sender.requests().is_empty());
+    }
+
     #[test]
     fn test_rpc_client_thread() {
         let rpc_client = RpcClient::new_mock("succeeds".to_string());
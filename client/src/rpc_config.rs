@@ -12,6 +12,24 @@ pub struct RpcSignatureStatusConfig {
     pub search_transaction_history: bool,
 }
 
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcGossipConfig {
+    /// Milliseconds between gossip rounds, unchanged if absent
+    pub gossip_interval_ms: Option<u64>,
+    /// Milliseconds between gossip pull requests, unchanged if absent
+    pub pull_request_interval_ms: Option<u64>,
+    /// Bytes per second spent on pull responses, 0 = only bounded by stake, unchanged if absent
+    pub max_bytes_per_second: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBlockRewardsConfig {
+    /// Only include the rewards paid to these addresses
+    pub addresses: Option<Vec<String>>,
+}
+
 #[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcSendTransactionConfig {
@@ -29,9 +47,19 @@ pub struct RpcSimulateTransactionConfig {
     #[serde(flatten)]
     pub commitment: Option<CommitmentConfig>,
     pub encoding: Option<UiTransactionEncoding>,
+    #[serde(default)]
+    pub inner_instructions: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcFeeForMessageConfig {
+    #[serde(flatten)]
+    pub commitment: Option<CommitmentConfig>,
+    pub encoding: Option<UiTransactionEncoding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum RpcLargestAccountsFilter {
     Circulating,
@@ -46,6 +74,15 @@ pub struct RpcLargestAccountsConfig {
     pub filter: Option<RpcLargestAccountsFilter>,
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcValidatorDelegationsConfig {
+    #[serde(flatten)]
+    pub commitment: Option<CommitmentConfig>,
+    /// Only return the delegations to this vote account, as base-58 encoded string
+    pub vote_pubkey: Option<String>,
+}
+
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcStakeConfig {
@@ -13,6 +13,7 @@ pub const JSON_RPC_SERVER_ERROR_TRANSACTION_PRECOMPILE_VERIFICATION_FAILURE: i64
 pub const JSON_RPC_SERVER_ERROR_SLOT_SKIPPED: i64 = -32007;
 pub const JSON_RPC_SERVER_ERROR_NO_SNAPSHOT: i64 = -32008;
 pub const JSON_RPC_SERVER_ERROR_LONG_TERM_STORAGE_SLOT_SKIPPED: i64 = -32009;
+pub const JSON_RPC_SERVER_ERROR_METHOD_NOT_AUTHORIZED: i64 = -32010;
 
 pub enum RpcCustomError {
     BlockCleanedUp {
@@ -38,6 +39,9 @@ pub enum RpcCustomError {
     LongTermStorageSlotSkipped {
         slot: Slot,
     },
+    MethodNotAuthorized {
+        method: String,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -115,6 +119,11 @@ impl From<RpcCustomError> for Error {
                 message: format!("Slot {} was skipped, or missing in long-term storage", slot),
                 data: None,
             },
+            RpcCustomError::MethodNotAuthorized { method } => Self {
+                code: ErrorCode::ServerError(JSON_RPC_SERVER_ERROR_METHOD_NOT_AUTHORIZED),
+                message: format!("Method {} not authorized", method),
+                data: None,
+            },
         }
     }
 }
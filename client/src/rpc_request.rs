@@ -10,9 +10,11 @@ pub enum RpcRequest {
     ValidatorExit,
     GetAccountInfo,
     GetBalance,
+    GetBlockCost,
     GetBlockTime,
     GetClusterNodes,
     GetConfirmedBlock,
+    GetConfirmedBlockRewards,
     GetConfirmedBlocks,
     GetConfirmedBlocksWithLimit,
     GetConfirmedSignaturesForAddress,
@@ -21,23 +23,30 @@ pub enum RpcRequest {
     GetEpochInfo,
     GetEpochSchedule,
     GetFeeCalculatorForBlockhash,
+    GetFeeForMessage,
     GetFeeRateGovernor,
     GetFees,
     GetFirstAvailableBlock,
     GetGenesisHash,
+    GetGossipStats,
     GetHealth,
+    GetHighestSnapshotSlot,
     GetIdentity,
     GetInflationGovernor,
     GetInflationRate,
     GetLargestAccounts,
     GetLeaderSchedule,
+    GetMaxProcessingAge,
     GetMinimumBalanceForRentExemption,
     GetMultipleAccounts,
     GetProgramAccounts,
+    GetProgramStats,
     GetRecentBlockhash,
+    GetRepairStatus,
     GetSnapshotSlot,
     GetSignatureStatuses,
     GetSlot,
+    GetSlotExecuteTimings,
     GetSlotLeader,
     GetStorageTurn,
     GetStorageTurnRate,
@@ -50,12 +59,14 @@ pub enum RpcRequest {
     GetTokenSupply,
     GetTotalSupply,
     GetTransactionCount,
+    GetValidatorDelegations,
     GetVersion,
     GetVoteAccounts,
     MinimumLedgerSlot,
     RegisterNode,
     RequestAirdrop,
     SendTransaction,
+    SetGossipConfig,
     SimulateTransaction,
     SignVote,
 }
@@ -67,9 +78,11 @@ impl fmt::Display for RpcRequest {
             RpcRequest::ValidatorExit => "validatorExit",
             RpcRequest::GetAccountInfo => "getAccountInfo",
             RpcRequest::GetBalance => "getBalance",
+            RpcRequest::GetBlockCost => "getBlockCost",
             RpcRequest::GetBlockTime => "getBlockTime",
             RpcRequest::GetClusterNodes => "getClusterNodes",
             RpcRequest::GetConfirmedBlock => "getConfirmedBlock",
+            RpcRequest::GetConfirmedBlockRewards => "getConfirmedBlockRewards",
             RpcRequest::GetConfirmedBlocks => "getConfirmedBlocks",
             RpcRequest::GetConfirmedBlocksWithLimit => "getConfirmedBlocksWithLimit",
             RpcRequest::GetConfirmedSignaturesForAddress => "getConfirmedSignaturesForAddress",
@@ -78,23 +91,30 @@ impl fmt::Display for RpcRequest {
             RpcRequest::GetEpochInfo => "getEpochInfo",
             RpcRequest::GetEpochSchedule => "getEpochSchedule",
             RpcRequest::GetFeeCalculatorForBlockhash => "getFeeCalculatorForBlockhash",
+            RpcRequest::GetFeeForMessage => "getFeeForMessage",
             RpcRequest::GetFeeRateGovernor => "getFeeRateGovernor",
             RpcRequest::GetFees => "getFees",
             RpcRequest::GetFirstAvailableBlock => "getFirstAvailableBlock",
             RpcRequest::GetGenesisHash => "getGenesisHash",
+            RpcRequest::GetGossipStats => "getGossipStats",
             RpcRequest::GetHealth => "getHealth",
+            RpcRequest::GetHighestSnapshotSlot => "getHighestSnapshotSlot",
             RpcRequest::GetIdentity => "getIdentity",
             RpcRequest::GetInflationGovernor => "getInflationGovernor",
             RpcRequest::GetInflationRate => "getInflationRate",
             RpcRequest::GetLargestAccounts => "getLargestAccounts",
             RpcRequest::GetLeaderSchedule => "getLeaderSchedule",
+            RpcRequest::GetMaxProcessingAge => "getMaxProcessingAge",
             RpcRequest::GetMinimumBalanceForRentExemption => "getMinimumBalanceForRentExemption",
             RpcRequest::GetMultipleAccounts => "getMultipleAccounts",
             RpcRequest::GetProgramAccounts => "getProgramAccounts",
+            RpcRequest::GetProgramStats => "getProgramStats",
             RpcRequest::GetRecentBlockhash => "getRecentBlockhash",
+            RpcRequest::GetRepairStatus => "getRepairStatus",
             RpcRequest::GetSnapshotSlot => "getSnapshotSlot",
             RpcRequest::GetSignatureStatuses => "getSignatureStatuses",
             RpcRequest::GetSlot => "getSlot",
+            RpcRequest::GetSlotExecuteTimings => "getSlotExecuteTimings",
             RpcRequest::GetSlotLeader => "getSlotLeader",
             RpcRequest::GetStorageTurn => "getStorageTurn",
             RpcRequest::GetStorageTurnRate => "getStorageTurnRate",
@@ -107,12 +127,14 @@ impl fmt::Display for RpcRequest {
             RpcRequest::GetTokenSupply => "getTokenSupply",
             RpcRequest::GetTotalSupply => "getTotalSupply",
             RpcRequest::GetTransactionCount => "getTransactionCount",
+            RpcRequest::GetValidatorDelegations => "getValidatorDelegations",
             RpcRequest::GetVersion => "getVersion",
             RpcRequest::GetVoteAccounts => "getVoteAccounts",
             RpcRequest::MinimumLedgerSlot => "minimumLedgerSlot",
             RpcRequest::RegisterNode => "registerNode",
             RpcRequest::RequestAirdrop => "requestAirdrop",
             RpcRequest::SendTransaction => "sendTransaction",
+            RpcRequest::SetGossipConfig => "setGossipConfig",
             RpcRequest::SimulateTransaction => "simulateTransaction",
             RpcRequest::SignVote => "signVote",
         };
@@ -126,7 +148,11 @@ pub const MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS_SLOT_RANGE: u64 = 10_000;
 pub const MAX_GET_CONFIRMED_BLOCKS_RANGE: u64 = 500_000;
 pub const MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT: usize = 1_000;
 pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+pub const MAX_BLOCK_REWARDS_ADDRESSES: usize = 256;
+pub const MAX_GET_REPAIR_STATUS_SLOTS: usize = 256;
+pub const MAX_GET_REPAIR_STATUS_MISSING_SHREDS: usize = 1_024;
 pub const NUM_LARGEST_ACCOUNTS: usize = 20;
+pub const NUM_TOP_DELEGATORS: usize = 10;
 
 // Validators that are this number of slots behind are considered delinquent
 pub const DELINQUENT_VALIDATOR_SLOT_DISTANCE: u64 = 128;
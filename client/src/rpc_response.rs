@@ -6,7 +6,9 @@ use solana_sdk::{
     inflation::Inflation,
     transaction::{Result, TransactionError},
 };
-use solana_transaction_status::ConfirmedTransactionStatusWithSignature;
+use solana_transaction_status::{
+    ConfirmedTransactionStatusWithSignature, Rewards, UiFailedInstruction, UiInnerInstructions,
+};
 use std::{collections::HashMap, fmt, net::SocketAddr};
 
 pub type RpcResult<T> = client_error::Result<Response<T>>;
@@ -50,6 +52,20 @@ pub struct RpcFeeCalculator {
     pub fee_calculator: FeeCalculator,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcFeeForMessage {
+    /// None if the message's blockhash has expired
+    pub fee: Option<u64>,
+    pub fee_payer: String,
+    pub fee_payer_balance: u64,
+    /// Whether the fee payer's balance covers the fee
+    pub fee_payer_can_pay: bool,
+    /// Whether a transaction of the message would still be processed
+    pub blockhash_valid: bool,
+    pub last_valid_slot: Option<Slot>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcFeeRateGovernor {
@@ -101,6 +117,77 @@ pub struct SlotInfo {
     pub root: Slot,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RpcSnapshotSlotInfo {
+    /// Slot of the highest full snapshot
+    pub full: Slot,
+
+    /// Slot of the highest incremental snapshot based on the full snapshot, if any
+    pub incremental: Option<Slot>,
+}
+
+/// The rewards paid out in a block, the first block of each epoch carries the epoch's staking
+/// rewards
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBlockRewards {
+    pub slot: Slot,
+    pub epoch: Epoch,
+    pub rewards: Rewards,
+}
+
+/// Emitted when the root crosses into a new epoch
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochBoundaryInfo {
+    /// The new epoch
+    pub epoch: Epoch,
+
+    /// The root which crossed the boundary
+    pub root: Slot,
+
+    /// The root before it
+    pub prior_root: Slot,
+
+    /// Total stake, in lamports, delegated to vote accounts in the new epoch
+    pub total_active_stake: u64,
+
+    /// Total stake, in lamports, delegated to vote accounts in the prior epoch
+    pub prior_total_active_stake: u64,
+
+    /// Vote accounts whose delegated stake changed between the epochs
+    pub stake_changes: Vec<RpcStakeChange>,
+
+    /// Features activated since the prior epoch
+    pub activated_features: Vec<RpcActivatedFeature>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcStakeChange {
+    /// Vote account pubkey as base-58 encoded string
+    pub vote_pubkey: String,
+
+    /// Stake, in lamports, delegated in the prior epoch
+    pub prior_stake: u64,
+
+    /// Stake, in lamports, delegated in the new epoch
+    pub stake: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcActivatedFeature {
+    /// Feature id as base-58 encoded string
+    pub id: String,
+
+    /// Description of the feature
+    pub name: String,
+
+    /// The slot the feature was activated in
+    pub activation_slot: Slot,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase", untagged)]
 pub enum RpcSignatureResult {
@@ -139,6 +226,8 @@ pub struct RpcContactInfo {
     pub tpu: Option<SocketAddr>,
     /// JSON RPC port
     pub rpc: Option<SocketAddr>,
+    /// RPC websocket port
+    pub pubsub: Option<SocketAddr>,
     /// Software version
     pub version: Option<String>,
     /// First 4 bytes of the FeatureSet identifier
@@ -217,6 +306,49 @@ pub struct RpcVoteAccountInfo {
     pub root_slot: Slot,
 }
 
+/// Breakdown of the stake delegated to a vote account
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcValidatorDelegations {
+    /// Vote account pubkey as base-58 encoded string
+    pub vote_pubkey: String,
+
+    /// The pubkey of the node that votes using this account
+    pub node_pubkey: String,
+
+    /// Active stake, in lamports, delegated to this vote account
+    pub active_stake: u64,
+
+    /// Active stake of the accounts whose staker or withdrawer is the validator identity or the
+    /// vote account withdraw authority
+    pub self_stake: u64,
+
+    /// Active stake of all the other accounts
+    pub foreign_stake: u64,
+
+    /// Number of stake accounts with active stake
+    pub stake_account_count: usize,
+
+    /// Number of distinct stake authorities with active stake
+    pub delegator_count: usize,
+
+    /// Delegators with the most active stake, largest first
+    pub top_delegators: Vec<RpcDelegator>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcDelegator {
+    /// Stake authority as base-58 encoded string
+    pub staker: String,
+
+    /// Active stake, in lamports, of all the stake accounts of this authority
+    pub active_stake: u64,
+
+    /// Number of stake accounts of this authority
+    pub stake_account_count: usize,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct RpcSignatureConfirmation {
@@ -229,6 +361,10 @@ pub struct RpcSignatureConfirmation {
 pub struct RpcSimulateTransactionResult {
     pub err: Option<TransactionError>,
     pub logs: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_instruction: Option<UiFailedInstruction>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_instructions: Option<Vec<UiInnerInstructions>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -298,6 +434,134 @@ pub struct RpcPerfSample {
     pub sample_period_secs: u16,
 }
 
+/// Transaction execution timings accumulated over all the batches processed in a slot
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSlotExecuteTimings {
+    pub slot: Slot,
+    /// Time spent acquiring account locks, in microseconds
+    pub lock_us: u64,
+    /// Time spent loading accounts, in microseconds
+    pub load_us: u64,
+    /// Time spent executing transactions, in microseconds
+    pub execute_us: u64,
+    /// Time spent storing accounts, in microseconds
+    pub store_us: u64,
+    /// Execution time of instructions by program id, slowest first
+    pub programs: Vec<RpcProgramTiming>,
+}
+
+/// Cost of the transactions of a block, in compute units
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBlockCost {
+    pub slot: Slot,
+    /// Units of the transactions committed so far
+    pub units: u64,
+    /// Most units the block may cost
+    pub max_units: u64,
+    /// Whether blocks costing more than `max_units` are rejected
+    pub limits_enabled: bool,
+}
+
+/// Composition of the gossip crds table and gossip traffic totals since the node started
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcGossipStats {
+    /// Number of values in the crds table
+    pub num_crds_values: usize,
+    /// Number of values in the crds table by type, e.g. "ContactInfo" or "Vote"
+    pub crds_values_per_type: HashMap<String, usize>,
+    /// Number of origins by the age of their most recently received value, youngest first
+    pub origin_age_histogram: Vec<RpcOriginAgeBucket>,
+    pub push_values_received: u64,
+    pub push_values_inserted: u64,
+    /// Share of the pushed values that were new to the crds table, None if none were received
+    pub push_success_rate: Option<f64>,
+    pub pull_response_values_received: u64,
+    pub pull_response_values_inserted: u64,
+    /// Share of the pulled values that were new to the crds table, None if none were received
+    pub pull_success_rate: Option<f64>,
+    pub prune_messages_sent: u64,
+    pub prune_messages_received: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcOriginAgeBucket {
+    /// Upper bound of the bucket in milliseconds, None for the last one
+    pub max_age_ms: Option<u64>,
+    /// Number of origins in the bucket
+    pub origins: usize,
+}
+
+/// Missing shreds and repair activity of a recent slot
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcSlotRepairStatus {
+    pub slot: Slot,
+    /// Whether every shred of the slot has been received
+    pub is_full: bool,
+    /// Index of the last shred of the slot, if it has been received
+    pub last_index: Option<u64>,
+    /// Indexes of the data shreds that have not been received, possibly truncated
+    pub missing_shred_indexes: Vec<u64>,
+    /// Repair requests sent for the slot
+    pub repair_requests: u64,
+    /// Repair requests that have not been answered
+    pub outstanding_repairs: u64,
+    /// Repaired shreds by serving peer, most first
+    pub repaired_from: Vec<RpcRepairSource>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcRepairSource {
+    /// Address the repaired shreds were received from
+    pub address: String,
+    /// Number of repaired shreds received from the address
+    pub shreds: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcProgramTiming {
+    /// Program id as base-58 encoded string
+    pub program_id: String,
+    /// Time spent executing the program's instructions, in microseconds
+    pub execute_us: u64,
+    /// Number of instructions executed
+    pub count: u32,
+}
+
+/// Per-program statistics aggregated over the most recently processed slots
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcProgramStatsWindow {
+    /// First slot of the window, None if no transaction has been processed yet
+    pub first_slot: Option<Slot>,
+    /// Last slot of the window
+    pub last_slot: Option<Slot>,
+    /// Programs invoked within the window, most invoked first
+    pub programs: Vec<RpcProgramStats>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcProgramStats {
+    /// Program id as base-58 encoded string
+    pub program_id: String,
+    /// Number of top-level instructions that invoked the program
+    pub invocations: u64,
+    /// Number of invocations that failed their transaction
+    pub failures: u64,
+    /// Ratio of failures to invocations
+    pub error_rate: f64,
+    /// Average compute units consumed per invocation, None for programs that don't report
+    /// the compute units they consume
+    pub average_compute_units: Option<u64>,
+}
+
 impl From<ConfirmedTransactionStatusWithSignature> for RpcConfirmedTransactionStatusWithSignature {
     fn from(value: ConfirmedTransactionStatusWithSignature) -> Self {
         let ConfirmedTransactionStatusWithSignature {
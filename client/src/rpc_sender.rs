@@ -1,5 +1,14 @@
 use crate::{client_error::Result, rpc_request::RpcRequest};
+use std::sync::Arc;
 
 pub trait RpcSender {
     fn send(&self, request: RpcRequest, params: serde_json::Value) -> Result<serde_json::Value>;
 }
+
+/// Allows a sender to be shared with the `RpcClient`, e.g. to keep programming a `MockSender`
+/// and inspecting the requests it received
+impl<T: RpcSender + ?Sized> RpcSender for Arc<T> {
+    fn send(&self, request: RpcRequest, params: serde_json::Value) -> Result<serde_json::Value> {
+        (**self).send(request, params)
+    }
+}
@@ -211,12 +211,17 @@ impl ThinClient {
         tries: usize,
         pending_confirmations: usize,
     ) -> TransportResult<Signature> {
+        // Nodes predating getMaxProcessingAge process blockhashes up to the default age
+        let max_processing_age = self
+            .rpc_client()
+            .get_max_processing_age()
+            .unwrap_or(MAX_PROCESSING_AGE);
         for x in 0..tries {
             let now = Instant::now();
             let mut buf = vec![0; serialized_size(&transaction).unwrap() as usize];
             let mut wr = std::io::Cursor::new(&mut buf[..]);
             let mut num_confirmed = 0;
-            let mut wait_time = MAX_PROCESSING_AGE;
+            let mut wait_time = max_processing_age;
             serialize_into(&mut wr, &transaction)
                 .expect("serialize Transaction in pub fn transfer_signed");
             // resend the same transaction until the transaction has no chance of succeeding
@@ -239,7 +244,7 @@ impl ThinClient {
                     // all pending confirmations. Resending the transaction could result into
                     // extra transaction fees
                     wait_time = wait_time.max(
-                        MAX_PROCESSING_AGE * pending_confirmations.saturating_sub(num_confirmed),
+                        max_processing_age * pending_confirmations.saturating_sub(num_confirmed),
                     );
                 }
             }
@@ -92,6 +92,7 @@ fn bench_retransmitter(bencher: &mut Bencher) {
         &leader_schedule_cache,
         cluster_info,
         packet_receiver,
+        None,
     );
 
     let mut index = 0;
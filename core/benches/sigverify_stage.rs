@@ -3,9 +3,11 @@
 extern crate solana_core;
 extern crate test;
 
-use crossbeam_channel::unbounded;
 use log::*;
 use rand::{thread_rng, Rng};
+use solana_core::packet_channel::{
+    bounded_channel, ChannelConfig, DropPolicy, DEFAULT_PACKET_CHANNEL_CAPACITY,
+};
 use solana_core::sigverify::TransactionSigVerifier;
 use solana_core::sigverify_stage::SigVerifyStage;
 use solana_perf::packet::to_packets_chunked;
@@ -14,15 +16,15 @@ use solana_sdk::hash::Hash;
 use solana_sdk::signature::{Keypair, Signer};
 use solana_sdk::system_transaction;
 use solana_sdk::timing::duration_as_ms;
-use std::sync::mpsc::channel;
 use std::time::{Duration, Instant};
 use test::Bencher;
 
 #[bench]
 fn bench_sigverify_stage(bencher: &mut Bencher) {
     solana_logger::setup();
-    let (packet_s, packet_r) = channel();
-    let (verified_s, verified_r) = unbounded();
+    let channel_config = ChannelConfig::new(DEFAULT_PACKET_CHANNEL_CAPACITY, DropPolicy::Block);
+    let (packet_s, packet_r) = bounded_channel("bench-packets", &channel_config);
+    let (verified_s, verified_r) = bounded_channel("bench-verified-packets", &channel_config);
     let verifier = TransactionSigVerifier::default();
     let stage = SigVerifyStage::new(packet_r, verified_s, verifier);
 
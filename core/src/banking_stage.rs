@@ -21,6 +21,7 @@ use solana_perf::{
     packet::{limited_deserialize, Packet, Packets, PACKETS_PER_BATCH},
     perf_libs,
 };
+use solana_rayon_threadlimit::thread_manager;
 use solana_runtime::{
     accounts_db::ErrorCounters,
     bank::{
@@ -33,7 +34,7 @@ use solana_runtime::{
 };
 use solana_sdk::{
     clock::{
-        Slot, DEFAULT_TICKS_PER_SLOT, MAX_PROCESSING_AGE, MAX_TRANSACTION_FORWARDING_DELAY,
+        Slot, DEFAULT_TICKS_PER_SLOT, MAX_TRANSACTION_FORWARDING_DELAY,
         MAX_TRANSACTION_FORWARDING_DELAY_GPU,
     },
     poh_config::PohConfig,
@@ -136,6 +137,7 @@ impl BankingStage {
                 Builder::new()
                     .name("solana-banking-stage-tx".to_string())
                     .spawn(move || {
+                        thread_manager::pin_current_thread("solana-banking", i as usize);
                         thread_mem_usage::datapoint("solana-banking-stage-tx");
                         Self::process_loop(
                             my_pubkey,
@@ -559,7 +561,7 @@ impl BankingStage {
             signature_count,
         ) = bank.load_and_execute_transactions(
             batch,
-            MAX_PROCESSING_AGE,
+            bank.max_processing_age(),
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
             &mut execute_timings,
@@ -610,6 +612,7 @@ impl BankingStage {
             }
         }
         commit_time.stop();
+        bank.accumulate_execute_timings(&execute_timings);
 
         drop(freeze_lock);
 
@@ -633,11 +636,21 @@ impl BankingStage {
         transaction_status_sender: Option<TransactionStatusSender>,
         gossip_vote_sender: &ReplayVoteSender,
     ) -> (Result<usize, PohRecorderError>, Vec<usize>) {
+        // Leave out the transactions that would make the block cost more than validators accept
+        // in replay, see `blockstore_processor::confirm_slot`.  They are retried once the
+        // transactions being processed are committed, or in another block
+        let (num_fitting, reserved_block_units) = Self::reserve_block_units(bank, txs);
+        let (txs, cost_limited_txs) = txs.split_at(num_fitting);
+
         let mut lock_time = Measure::start("lock_time");
         // Once accounts are locked, other threads cannot encode transactions that will modify the
         // same account state
         let batch = bank.prepare_batch(txs, None);
         lock_time.stop();
+        bank.accumulate_execute_timings(&ExecuteTimings {
+            lock_us: lock_time.as_us(),
+            ..ExecuteTimings::default()
+        });
 
         let (result, mut retryable_txs) = Self::process_and_record_transactions_locked(
             bank,
@@ -646,6 +659,9 @@ impl BankingStage {
             transaction_status_sender,
             gossip_vote_sender,
         );
+        // The transactions committed count in the bank's block units by now
+        bank.release_block_units(reserved_block_units);
+        retryable_txs.extend(num_fitting..num_fitting + cost_limited_txs.len());
         retryable_txs.iter_mut().for_each(|x| *x += chunk_offset);
 
         let mut unlock_time = Measure::start("unlock_time");
@@ -664,6 +680,23 @@ impl BankingStage {
         (result, retryable_txs)
     }
 
+    /// Reserves the block units of the longest prefix of `txs` that fits in what's left of the
+    /// bank's max block units, returning the length of the prefix and the units reserved
+    fn reserve_block_units(bank: &Bank, txs: &[Transaction]) -> (usize, u64) {
+        if !bank.block_cost_limits_enabled() {
+            return (txs.len(), 0);
+        }
+        let mut reserved_block_units = 0;
+        for (i, tx) in txs.iter().enumerate() {
+            let units = bank.transaction_cost(tx);
+            if !bank.try_reserve_block_units(units) {
+                return (i, reserved_block_units);
+            }
+            reserved_block_units += units;
+        }
+        (txs.len(), reserved_block_units)
+    }
+
     /// Sends transactions to the bank.
     ///
     /// Returns the number of transactions successfully processed by the bank, which may be less
@@ -822,7 +855,7 @@ impl BankingStage {
             transactions,
             None,
             &filter,
-            (MAX_PROCESSING_AGE)
+            bank.max_processing_age()
                 .saturating_sub(max_tx_fwd_delay)
                 .saturating_sub(FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET as usize),
             &mut error_counters,
@@ -1123,7 +1156,9 @@ mod tests {
     };
     use solana_perf::packet::to_packets_chunked;
     use solana_sdk::{
-        instruction::InstructionError,
+        instruction::{Instruction, InstructionError},
+        message::Message,
+        process_instruction::BpfComputeBudget,
         signature::{Keypair, Signer},
         system_instruction::SystemError,
         system_transaction,
@@ -1906,6 +1941,77 @@ mod tests {
         Blockstore::destroy(&ledger_path).unwrap();
     }
 
+    #[test]
+    fn test_bank_process_and_record_transactions_max_block_units() {
+        solana_logger::setup();
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let mut bank = Bank::new(&genesis_config);
+        // Transactions of a BPF instruction cost more than half of the max block units
+        bank.set_bpf_compute_budget(Some(BpfComputeBudget {
+            max_units: bank.max_block_units() / 2,
+            ..BpfComputeBudget::default()
+        }));
+        let bank = Arc::new(bank);
+        let pubkey = solana_sdk::pubkey::new_rand();
+
+        let transactions: Vec<_> = (0..2)
+            .map(|_| {
+                let instruction = Instruction::new(Pubkey::new_unique(), &(), vec![]);
+                let message = Message::new(&[instruction], Some(&mint_keypair.pubkey()));
+                Transaction::new(&[&mint_keypair], message, genesis_config.hash())
+            })
+            .collect();
+        assert!(bank.transaction_cost(&transactions[0]) > bank.max_block_units() / 2);
+
+        let working_bank = WorkingBank {
+            bank: bank.clone(),
+            min_tick_height: bank.tick_height(),
+            max_tick_height: bank.tick_height() + 1,
+        };
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&ledger_path)
+                .expect("Expected to be able to open database ledger");
+            let (poh_recorder, _entry_receiver) = PohRecorder::new(
+                bank.tick_height(),
+                bank.last_blockhash(),
+                bank.slot(),
+                Some((4, 4)),
+                bank.ticks_per_slot(),
+                &pubkey,
+                &Arc::new(blockstore),
+                &Arc::new(LeaderScheduleCache::new_from_bank(&bank)),
+                &Arc::new(PohConfig::default()),
+            );
+            let poh_recorder = Arc::new(Mutex::new(poh_recorder));
+
+            poh_recorder.lock().unwrap().set_working_bank(working_bank);
+
+            let (gossip_vote_sender, _gossip_vote_receiver) = unbounded();
+
+            // The second transaction is left for later, and the programs of the first one don't
+            // exist, so it isn't committed and its units are given back
+            for _ in 0..2 {
+                let (result, unprocessed) = BankingStage::process_and_record_transactions(
+                    &bank,
+                    &transactions,
+                    &poh_recorder,
+                    2,
+                    None,
+                    &gossip_vote_sender,
+                );
+                assert_eq!(result.unwrap(), 0);
+                assert_eq!(unprocessed, vec![3]);
+                assert_eq!(bank.block_units(), 0);
+            }
+        }
+        Blockstore::destroy(&ledger_path).unwrap();
+    }
+
     #[test]
     fn test_filter_valid_packets() {
         solana_logger::setup();
@@ -2064,6 +2170,7 @@ mod tests {
             let transaction_status_service = TransactionStatusService::new(
                 transaction_status_receiver,
                 blockstore.clone(),
+                None,
                 &Arc::new(AtomicBool::new(false)),
             );
 
@@ -74,6 +74,7 @@ impl BigTableUploadService {
                 start_slot,
                 Some(end_slot),
                 true,
+                false,
                 exit.clone(),
             ));
 
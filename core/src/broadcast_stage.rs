@@ -17,7 +17,10 @@ use crossbeam_channel::{
     Receiver as CrossbeamReceiver, RecvTimeoutError as CrossbeamRecvTimeoutError,
     Sender as CrossbeamSender,
 };
-use solana_ledger::{blockstore::Blockstore, shred::Shred};
+use solana_ledger::{
+    blockstore::Blockstore, shred::Shred, shred_encryption::ShredCipher,
+    signing_journal::SigningJournal,
+};
 use solana_measure::measure::Measure;
 use solana_metrics::{inc_new_counter_error, inc_new_counter_info};
 use solana_runtime::bank::Bank;
@@ -60,6 +63,7 @@ pub enum BroadcastStageType {
 }
 
 impl BroadcastStageType {
+    #[allow(clippy::too_many_arguments)]
     pub fn new_broadcast_stage(
         &self,
         sock: Vec<UdpSocket>,
@@ -69,6 +73,7 @@ impl BroadcastStageType {
         exit_sender: &Arc<AtomicBool>,
         blockstore: &Arc<Blockstore>,
         shred_version: u16,
+        signing_journal: Option<Arc<SigningJournal>>,
     ) -> BroadcastStage {
         let keypair = cluster_info.keypair.clone();
         match self {
@@ -79,7 +84,7 @@ impl BroadcastStageType {
                 retransmit_slots_receiver,
                 exit_sender,
                 blockstore,
-                StandardBroadcastRun::new(keypair, shred_version),
+                StandardBroadcastRun::new(keypair, shred_version, signing_journal),
             ),
 
             BroadcastStageType::FailEntryVerification => BroadcastStage::new(
@@ -378,19 +383,36 @@ pub fn broadcast_shreds(
     peers: &[ContactInfo],
     last_datapoint_submit: &Arc<AtomicU64>,
     transmit_stats: &mut TransmitShredsStats,
+    shred_cipher: Option<&ShredCipher>,
 ) -> Result<()> {
     let broadcast_len = peers_and_stakes.len();
     if broadcast_len == 0 {
         update_peer_stats(1, 1, last_datapoint_submit);
         return Ok(());
     }
+    // The shreds themselves are inserted into the blockstore in the clear
+    let encrypted_payloads: Option<Vec<_>> = shred_cipher.map(|shred_cipher| {
+        shreds
+            .iter()
+            .map(|shred| {
+                let mut payload = shred.payload.clone();
+                shred_cipher.apply(&mut payload);
+                payload
+            })
+            .collect()
+    });
     let mut shred_select = Measure::start("shred_select");
     let packets: Vec<_> = shreds
         .iter()
-        .map(|shred| {
+        .enumerate()
+        .map(|(i, shred)| {
             let broadcast_index = weighted_best(&peers_and_stakes, shred.seed());
+            let payload = match &encrypted_payloads {
+                Some(encrypted_payloads) => &encrypted_payloads[i],
+                None => &shred.payload,
+            };
 
-            (&shred.payload, &peers[broadcast_index].tvu)
+            (payload, &peers[broadcast_index].tvu)
         })
         .collect();
     shred_select.stop();
@@ -622,7 +644,7 @@ pub mod test {
             retransmit_slots_receiver,
             &exit_sender,
             &blockstore,
-            StandardBroadcastRun::new(leader_keypair, 0),
+            StandardBroadcastRun::new(leader_keypair, 0, None),
         );
 
         MockBroadcastStage {
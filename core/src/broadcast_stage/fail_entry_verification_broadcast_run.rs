@@ -144,6 +144,7 @@ impl BroadcastRun for FailEntryVerificationBroadcastRun {
             &peers,
             &Arc::new(AtomicU64::new(0)),
             &mut TransmitShredsStats::default(),
+            cluster_info.shred_cipher().as_deref(),
         )?;
 
         Ok(())
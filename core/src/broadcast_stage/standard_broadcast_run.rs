@@ -8,8 +8,14 @@ use crate::broadcast_stage::broadcast_utils::UnfinishedSlotInfo;
 use solana_ledger::{
     entry::Entry,
     shred::{ProcessShredsStats, Shred, Shredder, RECOMMENDED_FEC_RATE, SHRED_TICK_REFERENCE_MASK},
+    signing_journal::SigningJournal,
+};
+use solana_sdk::{
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    timing::duration_as_us,
 };
-use solana_sdk::{pubkey::Pubkey, signature::Keypair, timing::duration_as_us};
 use std::collections::HashMap;
 use std::sync::RwLock;
 use std::time::Duration;
@@ -28,6 +34,7 @@ pub struct StandardBroadcastRun {
     num_batches: usize,
     broadcast_peer_cache: Arc<RwLock<BroadcastPeerCache>>,
     last_peer_update: Arc<AtomicU64>,
+    signing_journal: Option<Arc<SigningJournal>>,
 }
 
 #[derive(Default)]
@@ -37,7 +44,11 @@ struct BroadcastPeerCache {
 }
 
 impl StandardBroadcastRun {
-    pub(super) fn new(keypair: Arc<Keypair>, shred_version: u16) -> Self {
+    pub(super) fn new(
+        keypair: Arc<Keypair>,
+        shred_version: u16,
+        signing_journal: Option<Arc<SigningJournal>>,
+    ) -> Self {
         Self {
             process_shreds_stats: ProcessShredsStats::default(),
             transmit_shreds_stats: Arc::new(Mutex::new(SlotBroadcastStats::default())),
@@ -51,6 +62,7 @@ impl StandardBroadcastRun {
             num_batches: 0,
             broadcast_peer_cache: Arc::new(RwLock::new(BroadcastPeerCache::default())),
             last_peer_update: Arc::new(AtomicU64::new(0)),
+            signing_journal,
         }
     }
 
@@ -82,10 +94,23 @@ impl StandardBroadcastRun {
         if let Some(ref mut shred) = last_unfinished_slot_shred {
             Shredder::sign_shred(&self.keypair, shred);
             self.unfinished_slot = None;
+            // The slot never got its last entry
+            self.record_signed_slot(shred.slot(), Hash::default(), shred);
         }
 
         last_unfinished_slot_shred
     }
+    fn record_signed_slot(&self, slot: Slot, last_entry_hash: Hash, last_shred: &Shred) {
+        if let Some(signing_journal) = &self.signing_journal {
+            signing_journal.record_shreds(
+                slot,
+                last_entry_hash,
+                last_shred.signature(),
+                self.keypair.pubkey(),
+            );
+        }
+    }
+
     fn init_shredder(&self, blockstore: &Blockstore, reference_tick: u8) -> (Shredder, u32) {
         let (slot, parent_slot) = self.current_slot_and_parent.unwrap();
         let next_shred_index = self
@@ -209,6 +234,13 @@ impl StandardBroadcastRun {
                 .expect("Failed to insert shreds in blockstore");
         }
         let last_data_shred = data_shreds.len();
+        if is_last_in_slot {
+            if let (Some(last_entry), Some(last_shred)) =
+                (receive_results.entries.last(), data_shreds.last())
+            {
+                self.record_signed_slot(bank.slot(), last_entry.hash, last_shred);
+            }
+        }
         to_shreds_time.stop();
 
         let mut get_leader_schedule_time = Measure::start("broadcast_get_leader_schedule");
@@ -354,6 +386,7 @@ impl StandardBroadcastRun {
             &r_broadcast_peer_cache.peers,
             &self.last_datapoint_submit,
             &mut transmit_stats,
+            cluster_info.shred_cipher().as_deref(),
         )?;
         drop(r_broadcast_peer_cache);
         transmit_time.stop();
@@ -498,7 +531,7 @@ mod test {
     #[test]
     fn test_interrupted_slot_last_shred() {
         let keypair = Arc::new(Keypair::new());
-        let mut run = StandardBroadcastRun::new(keypair.clone(), 0);
+        let mut run = StandardBroadcastRun::new(keypair.clone(), 0, None);
 
         // Set up the slot to be interrupted
         let next_shred_index = 10;
@@ -544,7 +577,7 @@ mod test {
         };
 
         // Step 1: Make an incomplete transmission for slot 0
-        let mut standard_broadcast_run = StandardBroadcastRun::new(leader_keypair.clone(), 0);
+        let mut standard_broadcast_run = StandardBroadcastRun::new(leader_keypair.clone(), 0, None);
         standard_broadcast_run
             .test_process_receive_results(&cluster_info, &socket, &blockstore, receive_results)
             .unwrap();
@@ -658,7 +691,7 @@ mod test {
             last_tick_height: ticks.len() as u64,
         };
 
-        let mut standard_broadcast_run = StandardBroadcastRun::new(leader_keypair, 0);
+        let mut standard_broadcast_run = StandardBroadcastRun::new(leader_keypair, 0, None);
         standard_broadcast_run
             .test_process_receive_results(&cluster_info, &socket, &blockstore, receive_results)
             .unwrap();
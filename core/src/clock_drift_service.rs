@@ -0,0 +1,193 @@
+//! The `clock_drift_service` compares the local wall clock against the cluster time the
+//! working bank estimates from stake-weighted vote timestamps. A leader with a skewed clock
+//! skews the timestamps of its own votes and thus the cluster's block time estimates, so the
+//! service reports the drift and can pause block production while it exceeds a threshold.
+
+use solana_ledger::blockstore::Blockstore;
+use solana_runtime::{bank::Bank, bank_forks::BankForks};
+use solana_sdk::clock::{Slot, UnixTimestamp};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+pub const DEFAULT_MAX_CLOCK_DRIFT_SECS: u64 = 10;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const SLEEP_INTERVAL: Duration = Duration::from_millis(500);
+
+// The cluster time of a bank only tells the current time if the bank is recent, so no
+// samples are taken while the working bank trails the highest slot received by more than this
+const MAX_CATCHUP_SLOTS: Slot = 64;
+
+#[derive(Clone, Debug)]
+pub struct ClockDriftConfig {
+    /// Drift, in seconds, above which the local clock is considered skewed
+    pub max_drift_secs: u64,
+    /// Skip leader slots while the local clock is skewed
+    pub pause_block_production: bool,
+}
+
+impl Default for ClockDriftConfig {
+    fn default() -> Self {
+        Self {
+            max_drift_secs: DEFAULT_MAX_CLOCK_DRIFT_SECS,
+            pause_block_production: false,
+        }
+    }
+}
+
+pub struct ClockDriftService {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl ClockDriftService {
+    pub fn new(
+        bank_forks: &Arc<RwLock<BankForks>>,
+        blockstore: &Arc<Blockstore>,
+        config: ClockDriftConfig,
+        block_production_paused: &Arc<AtomicBool>,
+        exit: &Arc<AtomicBool>,
+    ) -> Self {
+        let bank_forks = bank_forks.clone();
+        let blockstore = blockstore.clone();
+        let block_production_paused = block_production_paused.clone();
+        let exit = exit.clone();
+
+        info!("Starting ClockDrift service: {:?}", config);
+        let thread_hdl = Builder::new()
+            .name("solana-clock-drift".to_string())
+            .spawn(move || {
+                Self::run(
+                    &bank_forks,
+                    &blockstore,
+                    &config,
+                    &block_production_paused,
+                    &exit,
+                );
+            })
+            .unwrap();
+
+        Self { thread_hdl }
+    }
+
+    fn run(
+        bank_forks: &RwLock<BankForks>,
+        blockstore: &Blockstore,
+        config: &ClockDriftConfig,
+        block_production_paused: &AtomicBool,
+        exit: &AtomicBool,
+    ) {
+        let mut last_check = Instant::now();
+        let mut last_slot = None;
+        let mut drift_exceeded = false;
+        loop {
+            if exit.load(Ordering::Relaxed) {
+                break;
+            }
+            sleep(SLEEP_INTERVAL);
+            if last_check.elapsed() < CHECK_INTERVAL {
+                continue;
+            }
+            last_check = Instant::now();
+
+            let bank = bank_forks.read().unwrap().working_bank();
+            if last_slot == Some(bank.slot()) {
+                // Replay is stalled, the stale bank says nothing about the cluster time
+                continue;
+            }
+            last_slot = Some(bank.slot());
+            match blockstore.highest_slot() {
+                Ok(Some(highest_slot)) if highest_slot > bank.slot() + MAX_CATCHUP_SLOTS => {
+                    debug!(
+                        "working bank {} is catching up to slot {}, skipping clock drift check",
+                        bank.slot(),
+                        highest_slot
+                    );
+                    continue;
+                }
+                Err(err) => {
+                    warn!("Unable to get the highest slot: {:?}", err);
+                    continue;
+                }
+                _ => {}
+            }
+
+            let drift_secs = clock_drift_secs(&bank, local_timestamp());
+            let exceeded = drift_secs.abs() as u64 > config.max_drift_secs;
+            if exceeded {
+                warn!(
+                    "local clock is {}s {} the cluster time at slot {}, exceeding the maximum drift of {}s",
+                    drift_secs.abs(),
+                    if drift_secs > 0 { "ahead of" } else { "behind" },
+                    bank.slot(),
+                    config.max_drift_secs,
+                );
+            } else if drift_exceeded {
+                info!(
+                    "local clock is back within {}s of the cluster time",
+                    config.max_drift_secs
+                );
+            }
+            drift_exceeded = exceeded;
+            if config.pause_block_production
+                && block_production_paused.swap(exceeded, Ordering::Relaxed) != exceeded
+            {
+                if exceeded {
+                    warn!("pausing block production until the local clock is corrected");
+                } else {
+                    info!("resuming block production");
+                }
+            }
+
+            datapoint_info!(
+                "clock_drift",
+                ("slot", bank.slot(), i64),
+                ("drift_secs", drift_secs, i64),
+                ("exceeded", exceeded, bool),
+                (
+                    "block_production_paused",
+                    block_production_paused.load(Ordering::Relaxed),
+                    bool
+                ),
+            );
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
+
+fn local_timestamp() -> UnixTimestamp {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as UnixTimestamp
+}
+
+/// Seconds the local clock at `local_timestamp` is ahead of the cluster time estimated for
+/// `bank`'s slot, negative if it is behind
+pub fn clock_drift_secs(bank: &Bank, local_timestamp: UnixTimestamp) -> i64 {
+    local_timestamp.saturating_sub(bank.clock().unix_timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_runtime::genesis_utils::create_genesis_config;
+
+    #[test]
+    fn test_clock_drift_secs() {
+        let genesis_config = create_genesis_config(10_000).genesis_config;
+        let bank = Bank::new(&genesis_config);
+        let cluster_timestamp = bank.clock().unix_timestamp;
+        assert_eq!(clock_drift_secs(&bank, cluster_timestamp), 0);
+        assert_eq!(clock_drift_secs(&bank, cluster_timestamp + 42), 42);
+        assert_eq!(clock_drift_secs(&bank, cluster_timestamp - 42), -42);
+    }
+}
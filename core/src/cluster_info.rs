@@ -14,6 +14,7 @@
 //! Bank needs to provide an interface for us to query the stake weight
 use crate::{
     contact_info::ContactInfo,
+    crds::CrdsComposition,
     crds_gossip::CrdsGossip,
     crds_gossip_error::CrdsGossipError,
     crds_gossip_pull::{CrdsFilter, ProcessPullStats, CRDS_GOSSIP_PULL_CRDS_TIMEOUT_MS},
@@ -30,14 +31,14 @@ use crate::{
 use rand::distributions::{Distribution, WeightedIndex};
 use rand::{CryptoRng, Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
-use solana_ledger::shred::Shred;
+use solana_ledger::{shred::Shred, shred_encryption::ShredCipher, signing_journal::SigningJournal};
 use solana_sdk::sanitize::{Sanitize, SanitizeError};
 
 use bincode::{serialize, serialized_size};
 use itertools::Itertools;
 use rand::thread_rng;
 use rayon::prelude::*;
-use rayon::{ThreadPool, ThreadPoolBuilder};
+use rayon::ThreadPool;
 use serde::ser::Serialize;
 use solana_measure::measure::Measure;
 use solana_measure::thread_mem_usage;
@@ -50,7 +51,7 @@ use solana_perf::packet::{
     limited_deserialize, to_packets_with_destination, Packet, Packets, PacketsRecycler,
     PACKET_DATA_SIZE,
 };
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_runtime::bank_forks::BankForks;
 use solana_sdk::{
     clock::{Slot, DEFAULT_MS_PER_SLOT, DEFAULT_SLOTS_PER_EPOCH},
@@ -114,6 +115,72 @@ pub const DEFAULT_CONTACT_DEBUG_INTERVAL_MILLIS: u64 = 10_000;
 pub const DEFAULT_CONTACT_SAVE_INTERVAL_MILLIS: u64 = 60_000;
 /// Minimum serialized size of a Protocol::PullResponse packet.
 const PULL_RESPONSE_MIN_SERIALIZED_SIZE: usize = 167;
+/// Upper bounds of the gossip timing parameters, beyond which crds values
+/// start timing out before they are refreshed
+pub const MAX_GOSSIP_INTERVAL_MILLIS: u64 = 1_000;
+pub const MAX_PULL_REQUEST_INTERVAL_MILLIS: u64 = 5_000;
+/// Lower bound of the outbound bytes per second budget, the default budget of
+/// a cluster with two staked nodes
+pub const MIN_GOSSIP_BYTES_PER_SECOND: u64 = 100_000;
+
+/// Gossip timing and bandwidth parameters, adjustable at runtime.  The
+/// defaults are also the lower bounds of the timing parameters, nodes may
+/// only gossip less often
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GossipConfig {
+    /// Milliseconds between gossip rounds, each of which pushes new crds values
+    pub gossip_interval_ms: u64,
+    /// Milliseconds between pull requests, rounded to a whole number of rounds
+    pub pull_request_interval_ms: u64,
+    /// Bytes per second spent on pull responses, 0 = only bounded by stake
+    pub max_bytes_per_second: u64,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        Self {
+            gossip_interval_ms: GOSSIP_SLEEP_MILLIS,
+            pull_request_interval_ms: 2 * GOSSIP_SLEEP_MILLIS,
+            max_bytes_per_second: 0,
+        }
+    }
+}
+
+impl GossipConfig {
+    pub fn verify(&self) -> Result<(), String> {
+        let default = Self::default();
+        if self.gossip_interval_ms < default.gossip_interval_ms
+            || self.gossip_interval_ms > MAX_GOSSIP_INTERVAL_MILLIS
+        {
+            return Err(format!(
+                "gossip interval must be between {}ms and {}ms",
+                default.gossip_interval_ms, MAX_GOSSIP_INTERVAL_MILLIS
+            ));
+        }
+        if self.pull_request_interval_ms < default.pull_request_interval_ms
+            || self.pull_request_interval_ms > MAX_PULL_REQUEST_INTERVAL_MILLIS
+        {
+            return Err(format!(
+                "pull request interval must be between {}ms and {}ms",
+                default.pull_request_interval_ms, MAX_PULL_REQUEST_INTERVAL_MILLIS
+            ));
+        }
+        if self.max_bytes_per_second != 0 && self.max_bytes_per_second < MIN_GOSSIP_BYTES_PER_SECOND
+        {
+            return Err(format!(
+                "gossip bandwidth budget must be at least {} bytes per second",
+                MIN_GOSSIP_BYTES_PER_SECOND
+            ));
+        }
+        Ok(())
+    }
+
+    /// Number of gossip rounds between pull requests
+    fn pull_request_rounds(&self) -> u64 {
+        ((self.pull_request_interval_ms + self.gossip_interval_ms / 2) / self.gossip_interval_ms)
+            .max(1)
+    }
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum ClusterInfoError {
@@ -209,6 +276,9 @@ impl Counter {
     fn clear(&self) -> u64 {
         self.0.swap(0, Ordering::Relaxed)
     }
+    fn load(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
 }
 
 struct ScopedTimer<'a> {
@@ -287,6 +357,31 @@ struct GossipStats {
     pull_requests_count: Counter,
 }
 
+/// Gossip traffic counted since the node started, unlike `GossipStats` which are cleared each
+/// time they are reported
+#[derive(Default)]
+struct GossipTotalCounters {
+    push_values_received: Counter,
+    push_values_inserted: Counter,
+    pull_response_values_received: Counter,
+    pull_response_values_inserted: Counter,
+    prune_messages_sent: Counter,
+    prune_messages_received: Counter,
+}
+
+/// A snapshot of the gossip traffic totals since the node started
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GossipTotals {
+    pub push_values_received: u64,
+    /// Pushed values that were new to the crds table
+    pub push_values_inserted: u64,
+    pub pull_response_values_received: u64,
+    /// Pulled values that were new to the crds table
+    pub pull_response_values_inserted: u64,
+    pub prune_messages_sent: u64,
+    pub prune_messages_received: u64,
+}
+
 pub struct ClusterInfo {
     /// The network
     pub gossip: RwLock<CrdsGossip>,
@@ -295,16 +390,25 @@ pub struct ClusterInfo {
     /// Network entrypoints
     entrypoints: RwLock<Vec<ContactInfo>>,
     outbound_budget: DataBudget,
+    gossip_config: RwLock<GossipConfig>,
     my_contact_info: RwLock<ContactInfo>,
     ping_cache: RwLock<PingCache>,
     id: Pubkey,
     stats: GossipStats,
+    totals: GossipTotalCounters,
     socket: UdpSocket,
     local_message_pending_push_queue: RwLock<Vec<(CrdsValue, u64)>>,
     contact_debug_interval: u64, // milliseconds, 0 = disabled
     contact_save_interval: u64,  // milliseconds, 0 = disabled
     instance: NodeInstance,
     contact_info_path: PathBuf,
+    /// Cipher applied to the shreds sent and received by this node, if the cluster encrypts them
+    shred_cipher: Option<Arc<ShredCipher>>,
+    /// Whether the identity interlock halts this node while another instance of it is running,
+    /// in place of exiting when a newer instance shows up in gossip
+    identity_interlock: bool,
+    /// Records the gossip values and prune messages signed with the identity
+    signing_journal: Option<Arc<SigningJournal>>,
 }
 
 impl Default for ClusterInfo {
@@ -408,7 +512,7 @@ type Ping = ping_pong::Ping<[u8; GOSSIP_PING_TOKEN_SIZE]>;
 #[frozen_abi(digest = "DdTxrwwnbe571Di4rLtrAQorFDE58vYnmzzbaeQ7sQMC")]
 #[derive(Serialize, Deserialize, Debug, AbiEnumVisitor, AbiExample)]
 #[allow(clippy::large_enum_variant)]
-enum Protocol {
+pub enum Protocol {
     /// Gossip protocol messages
     PullRequest(CrdsFilter, CrdsValue),
     PullResponse(Pubkey, Vec<CrdsValue>),
@@ -419,6 +523,19 @@ enum Protocol {
 }
 
 impl Protocol {
+    /// Returns true if every signature carried by the message verifies
+    pub fn verify_signatures(&self) -> bool {
+        match self {
+            Protocol::PullRequest(_, caller) => caller.verify(),
+            Protocol::PullResponse(_, data) | Protocol::PushMessage(_, data) => {
+                data.iter().all(Signable::verify)
+            }
+            Protocol::PruneMessage(_, data) => data.verify(),
+            Protocol::PingMessage(ping) => ping.verify(),
+            Protocol::PongMessage(pong) => pong.verify(),
+        }
+    }
+
     fn par_verify(self) -> Option<Self> {
         match self {
             Protocol::PullRequest(_, ref caller) => {
@@ -527,6 +644,7 @@ impl ClusterInfo {
             keypair,
             entrypoints: RwLock::new(vec![]),
             outbound_budget: DataBudget::default(),
+            gossip_config: RwLock::new(GossipConfig::default()),
             my_contact_info: RwLock::new(contact_info),
             ping_cache: RwLock::new(PingCache::new(
                 GOSSIP_PING_CACHE_TTL,
@@ -534,12 +652,16 @@ impl ClusterInfo {
             )),
             id,
             stats: GossipStats::default(),
+            totals: GossipTotalCounters::default(),
             socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
             local_message_pending_push_queue: RwLock::new(vec![]),
             contact_debug_interval: DEFAULT_CONTACT_DEBUG_INTERVAL_MILLIS,
             instance: NodeInstance::new(&mut thread_rng(), id, timestamp()),
             contact_info_path: PathBuf::default(),
             contact_save_interval: 0, // disabled
+            shred_cipher: None,
+            identity_interlock: false,
+            signing_journal: None,
         };
         {
             let mut gossip = me.gossip.write().unwrap();
@@ -562,10 +684,12 @@ impl ClusterInfo {
             keypair: self.keypair.clone(),
             entrypoints: RwLock::new(self.entrypoints.read().unwrap().clone()),
             outbound_budget: self.outbound_budget.clone_non_atomic(),
+            gossip_config: RwLock::new(self.gossip_config()),
             my_contact_info: RwLock::new(my_contact_info),
             ping_cache: RwLock::new(self.ping_cache.read().unwrap().mock_clone()),
             id: *new_id,
             stats: GossipStats::default(),
+            totals: GossipTotalCounters::default(),
             socket: UdpSocket::bind("0.0.0.0:0").unwrap(),
             local_message_pending_push_queue: RwLock::new(
                 self.local_message_pending_push_queue
@@ -577,6 +701,9 @@ impl ClusterInfo {
             instance: NodeInstance::new(&mut thread_rng(), *new_id, timestamp()),
             contact_info_path: PathBuf::default(),
             contact_save_interval: 0, // disabled
+            shred_cipher: self.shred_cipher.clone(),
+            identity_interlock: self.identity_interlock,
+            signing_journal: self.signing_journal.clone(),
         }
     }
 
@@ -584,6 +711,66 @@ impl ClusterInfo {
         self.contact_debug_interval = new;
     }
 
+    pub fn gossip_config(&self) -> GossipConfig {
+        *self.gossip_config.read().unwrap()
+    }
+
+    /// Takes effect from the next gossip round
+    pub fn set_gossip_config(&self, gossip_config: GossipConfig) -> Result<(), String> {
+        gossip_config.verify()?;
+        info!("gossip config: {:?}", gossip_config);
+        *self.gossip_config.write().unwrap() = gossip_config;
+        Ok(())
+    }
+
+    pub fn gossip_totals(&self) -> GossipTotals {
+        GossipTotals {
+            push_values_received: self.totals.push_values_received.load(),
+            push_values_inserted: self.totals.push_values_inserted.load(),
+            pull_response_values_received: self.totals.pull_response_values_received.load(),
+            pull_response_values_inserted: self.totals.pull_response_values_inserted.load(),
+            prune_messages_sent: self.totals.prune_messages_sent.load(),
+            prune_messages_received: self.totals.prune_messages_received.load(),
+        }
+    }
+
+    /// The values in the crds table per type, and the origins per age of their latest value
+    pub fn crds_composition(&self) -> CrdsComposition {
+        self.gossip.read().unwrap().crds.composition(timestamp())
+    }
+
+    /// Shreds of clusters using encryption can only be exchanged with nodes holding the same key.
+    /// Gossip needs no explicit negotiation: the key fingerprint is part of genesis, so such
+    /// nodes advertise a shred version that other nodes already filter their peers on
+    pub fn set_shred_cipher(&mut self, shred_cipher: Option<Arc<ShredCipher>>) {
+        self.shred_cipher = shred_cipher;
+    }
+
+    pub fn shred_cipher(&self) -> Option<Arc<ShredCipher>> {
+        self.shred_cipher.clone()
+    }
+
+    pub fn set_identity_interlock(&mut self, identity_interlock: bool) {
+        self.identity_interlock = identity_interlock;
+    }
+
+    pub fn set_signing_journal(&mut self, signing_journal: Option<Arc<SigningJournal>>) {
+        self.signing_journal = signing_journal;
+    }
+
+    /// Signs `data` with the identity, recording the value in the signing journal
+    fn new_signed_value(&self, data: CrdsData) -> CrdsValue {
+        let value = CrdsValue::new_signed(data, &self.keypair);
+        self.journal_signed(&value);
+        value
+    }
+
+    fn journal_signed<T: Signable>(&self, signed: &T) {
+        if let Some(signing_journal) = &self.signing_journal {
+            signing_journal.record_gossip(&signed.signable_data(), signed.get_signature(), self.id);
+        }
+    }
+
     pub fn update_contact_info<F>(&self, modify: F)
     where
         F: FnOnce(&mut ContactInfo),
@@ -606,7 +793,7 @@ impl ClusterInfo {
             CrdsData::NodeInstance(self.instance.with_wallclock(now)),
         ]
         .into_iter()
-        .map(|v| CrdsValue::new_signed(v, &self.keypair))
+        .map(|v| self.new_signed_value(v))
         .collect();
         {
             let mut local_message_pending_push_queue =
@@ -623,7 +810,7 @@ impl ClusterInfo {
 
     // TODO kill insert_info, only used by tests
     pub fn insert_info(&self, contact_info: ContactInfo) {
-        let value = CrdsValue::new_signed(CrdsData::ContactInfo(contact_info), &self.keypair);
+        let value = self.new_signed_value(CrdsData::ContactInfo(contact_info));
         let _ = self.gossip.write().unwrap().crds.insert(value, timestamp());
     }
 
@@ -777,6 +964,24 @@ impl ClusterInfo {
             .cloned()
     }
 
+    /// Returns the wallclock of the most recent gossip value signed with this node's identity
+    /// by another running instance of it: a node instance other than this one, or a contact
+    /// info advertising another gossip address
+    pub fn duplicate_identity_last_seen(&self) -> Option<u64> {
+        let gossip_addr = self.my_contact_info.read().unwrap().gossip;
+        let gossip = self.gossip.read().unwrap();
+        gossip
+            .crds
+            .get_records(&self.id())
+            .filter(|value| match &value.value.data {
+                CrdsData::NodeInstance(instance) => self.instance.is_other_instance(instance),
+                CrdsData::ContactInfo(contact_info) => contact_info.gossip != gossip_addr,
+                _ => false,
+            })
+            .map(|value| value.value.wallclock())
+            .max()
+    }
+
     pub fn my_contact_info(&self) -> ContactInfo {
         self.my_contact_info.read().unwrap().clone()
     }
@@ -955,10 +1160,8 @@ impl ClusterInfo {
             .map(|x| x.lowest)
             .unwrap_or(0);
         if min > last {
-            let entry = CrdsValue::new_signed(
-                CrdsData::LowestSlot(0, LowestSlot::new(id, min, now)),
-                &self.keypair,
-            );
+            let entry =
+                self.new_signed_value(CrdsData::LowestSlot(0, LowestSlot::new(id, min, now)));
             self.local_message_pending_push_queue
                 .write()
                 .unwrap()
@@ -1014,7 +1217,7 @@ impl ClusterInfo {
             };
             let n = slots.fill(&update[num..], now);
             if n > 0 {
-                let entry = CrdsValue::new_signed(CrdsData::EpochSlots(ix, slots), &self.keypair);
+                let entry = self.new_signed_value(CrdsData::EpochSlots(ix, slots));
                 self.local_message_pending_push_queue
                     .write()
                     .unwrap()
@@ -1062,7 +1265,7 @@ impl ClusterInfo {
         }
 
         let message = CrdsData::AccountsHashes(SnapshotHash::new(self.id(), accounts_hashes));
-        self.push_message(CrdsValue::new_signed(message, &self.keypair));
+        self.push_message(self.new_signed_value(message));
     }
 
     pub fn push_snapshot_hashes(&self, snapshot_hashes: Vec<(Slot, Hash)>) {
@@ -1075,7 +1278,7 @@ impl ClusterInfo {
         }
 
         let message = CrdsData::SnapshotHashes(SnapshotHash::new(self.id(), snapshot_hashes));
-        self.push_message(CrdsValue::new_signed(message, &self.keypair));
+        self.push_message(self.new_signed_value(message));
     }
 
     pub fn push_vote(&self, tower: &[Slot], vote: Transaction) {
@@ -1127,7 +1330,7 @@ impl ClusterInfo {
         let vote = Vote::new(self_pubkey, vote, now);
         debug_assert_eq!(vote.slot().unwrap(), *tower.last().unwrap());
         let vote = CrdsData::Vote(vote_index, vote);
-        let vote = CrdsValue::new_signed(vote, &self.keypair);
+        let vote = self.new_signed_value(vote);
         self.gossip
             .write()
             .unwrap()
@@ -1167,13 +1370,16 @@ impl ClusterInfo {
     }
 
     pub(crate) fn push_duplicate_shred(&self, shred: &Shred, other_payload: &[u8]) -> Result<()> {
-        self.gossip.write().unwrap().push_duplicate_shred(
+        let values = self.gossip.write().unwrap().push_duplicate_shred(
             &self.keypair,
             shred,
             other_payload,
             None::<fn(Slot) -> Option<Pubkey>>, // Leader schedule
             DUPLICATE_SHRED_MAX_PAYLOAD_SIZE,
         )?;
+        for value in &values {
+            self.journal_signed(value);
+        }
         Ok(())
     }
 
@@ -1496,8 +1702,7 @@ impl ClusterInfo {
     }
 
     fn insert_self(&self) {
-        let value =
-            CrdsValue::new_signed(CrdsData::ContactInfo(self.my_contact_info()), &self.keypair);
+        let value = self.new_signed_value(CrdsData::ContactInfo(self.my_contact_info()));
         let _ = self.gossip.write().unwrap().crds.insert(value, timestamp());
     }
 
@@ -1814,11 +2019,10 @@ impl ClusterInfo {
         exit: &Arc<AtomicBool>,
     ) -> JoinHandle<()> {
         let exit = exit.clone();
-        let thread_pool = ThreadPoolBuilder::new()
-            .num_threads(std::cmp::min(get_thread_count(), 8))
-            .thread_name(|i| format!("ClusterInfo::gossip-{}", i))
-            .build()
-            .unwrap();
+        let thread_pool = thread_manager::build_thread_pool(
+            "solana-gossip-work",
+            std::cmp::min(get_thread_count(), 8),
+        );
         Builder::new()
             .name("solana-gossip".to_string())
             .spawn(move || {
@@ -1832,12 +2036,14 @@ impl ClusterInfo {
                     CrdsData::NodeInstance(self.instance.with_wallclock(timestamp())),
                 ];
                 for value in crds_data {
-                    let value = CrdsValue::new_signed(value, &self.keypair);
+                    let value = self.new_signed_value(value);
                     self.push_message(value);
                 }
-                let mut generate_pull_requests = true;
+                let mut round: u64 = 0;
                 loop {
                     let start = timestamp();
+                    let gossip_config = self.gossip_config();
+                    let generate_pull_requests = round % gossip_config.pull_request_rounds() == 0;
                     thread_mem_usage::datapoint("solana-gossip");
                     if self.contact_debug_interval != 0
                         && start - last_contact_info_trace > self.contact_debug_interval
@@ -1888,11 +2094,11 @@ impl ClusterInfo {
                         last_push = timestamp();
                     }
                     let elapsed = timestamp() - start;
-                    if GOSSIP_SLEEP_MILLIS > elapsed {
-                        let time_left = GOSSIP_SLEEP_MILLIS - elapsed;
+                    if gossip_config.gossip_interval_ms > elapsed {
+                        let time_left = gossip_config.gossip_interval_ms - elapsed;
                         sleep(Duration::from_millis(time_left));
                     }
-                    generate_pull_requests = !generate_pull_requests;
+                    round = round.wrapping_add(1);
                 }
             })
             .unwrap()
@@ -1906,6 +2112,9 @@ impl ClusterInfo {
         self.stats
             .prune_message_count
             .add_relaxed(messages.len() as u64);
+        self.totals
+            .prune_messages_received
+            .add_relaxed(messages.len() as u64);
         self.stats.prune_message_len.add_relaxed(
             messages
                 .iter()
@@ -2006,10 +2215,16 @@ impl ClusterInfo {
         const BYTES_PER_INTERVAL: usize = 5000;
         const MAX_BUDGET_MULTIPLE: usize = 5; // allow budget build-up to 5x the interval default
         let num_staked = num_staked.max(2);
+        let mut bytes_per_interval = num_staked * BYTES_PER_INTERVAL;
+        let max_bytes_per_second = self.gossip_config().max_bytes_per_second;
+        if max_bytes_per_second != 0 {
+            bytes_per_interval =
+                bytes_per_interval.min((max_bytes_per_second * INTERVAL_MS / 1000) as usize);
+        }
         self.outbound_budget.update(INTERVAL_MS, |bytes| {
             std::cmp::min(
-                bytes + num_staked * BYTES_PER_INTERVAL,
-                MAX_BUDGET_MULTIPLE * num_staked * BYTES_PER_INTERVAL,
+                bytes + bytes_per_interval,
+                MAX_BUDGET_MULTIPLE * bytes_per_interval,
             )
         })
     }
@@ -2299,6 +2514,12 @@ impl ClusterInfo {
         self.stats
             .process_pull_response_success
             .add_relaxed(pull_stats.success as u64);
+        self.totals
+            .pull_response_values_received
+            .add_relaxed(len as u64);
+        self.totals
+            .pull_response_values_inserted
+            .add_relaxed(pull_stats.success as u64);
 
         (
             pull_stats.failed_insert + pull_stats.failed_timeout,
@@ -2408,6 +2629,9 @@ impl ClusterInfo {
         // Filter out data if the origin has different shred version.
         let self_shred_version = self.my_shred_version();
         let num_crds_values: u64 = messages.iter().map(|(_, data)| data.len() as u64).sum();
+        self.totals
+            .push_values_received
+            .add_relaxed(num_crds_values);
         let messages: Vec<_> = messages
             .into_iter()
             .zip(shred_versions)
@@ -2445,6 +2669,9 @@ impl ClusterInfo {
                 .map(|v| v.value.label())
                 .collect()
         };
+        self.totals
+            .push_values_inserted
+            .add_relaxed(updated_labels.len() as u64);
         // Generate prune messages.
         let prunes = self
             .time_gossip_write_lock("prune_received_cache", &self.stats.prune_received_cache)
@@ -2481,6 +2708,7 @@ impl ClusterInfo {
                             wallclock,
                         };
                         prune_data.sign(&self.keypair);
+                        self.journal_signed(&prune_data);
                         let prune_message = Protocol::PruneMessage(self_pubkey, prune_data);
                         Some((peer.gossip, prune_message))
                     })
@@ -2490,6 +2718,9 @@ impl ClusterInfo {
         if prune_messages.is_empty() {
             return;
         }
+        self.totals
+            .prune_messages_sent
+            .add_relaxed(prune_messages.len() as u64);
         let mut packets = to_packets_with_destination(recycler.clone(), &prune_messages);
         self.stats
             .push_response_count
@@ -2556,7 +2787,12 @@ impl ClusterInfo {
         });
         // Check if there is a duplicate instance of
         // this node with more recent timestamp.
+        // With the identity interlock, the values of the other instance
+        // are kept instead, and halt this node until it goes away.
         let check_duplicate_instance = |values: &[CrdsValue]| {
+            if self.identity_interlock {
+                return Ok(());
+            }
             for value in values {
                 if self.instance.check_duplicate(value) {
                     return Err(Error::DuplicateNodeInstance);
@@ -2907,11 +3143,10 @@ impl ClusterInfo {
         Builder::new()
             .name("solana-listen".to_string())
             .spawn(move || {
-                let thread_pool = ThreadPoolBuilder::new()
-                    .num_threads(std::cmp::min(get_thread_count(), 8))
-                    .thread_name(|i| format!("sol-gossip-work-{}", i))
-                    .build()
-                    .unwrap();
+                let thread_pool = thread_manager::build_thread_pool(
+                    "solana-gossip-listen",
+                    std::cmp::min(get_thread_count(), 8),
+                );
                 let mut last_print = Instant::now();
                 while !exit.load(Ordering::Relaxed) {
                     if let Err(err) = self.run_listen(
@@ -3181,6 +3416,33 @@ impl Node {
             },
         }
     }
+
+    /// Returns the port of every service of this node, with the advertised ports first followed
+    /// by the locally bound broadcast and retransmit ports
+    pub fn port_map(&self) -> Vec<(&'static str, u16)> {
+        let local_port = |socket: Option<&UdpSocket>| {
+            socket
+                .and_then(|socket| socket.local_addr().ok())
+                .map(|addr| addr.port())
+                .unwrap_or_default()
+        };
+        vec![
+            ("gossip", self.info.gossip.port()),
+            ("tvu", self.info.tvu.port()),
+            ("tvu_forwards", self.info.tvu_forwards.port()),
+            ("repair", self.info.repair.port()),
+            ("tpu", self.info.tpu.port()),
+            ("tpu_forwards", self.info.tpu_forwards.port()),
+            ("serve_repair", self.info.serve_repair.port()),
+            ("rpc", self.info.rpc.port()),
+            ("rpc_pubsub", self.info.rpc_pubsub.port()),
+            ("broadcast", local_port(self.sockets.broadcast.first())),
+            (
+                "retransmit",
+                local_port(self.sockets.retransmit_sockets.first()),
+            ),
+        ]
+    }
 }
 
 pub fn stake_weight_peers(
@@ -3200,8 +3462,15 @@ mod tests {
     };
     use itertools::izip;
     use rand::seq::SliceRandom;
-    use solana_ledger::shred::Shredder;
-    use solana_sdk::signature::{Keypair, Signer};
+    use rayon::ThreadPoolBuilder;
+    use solana_ledger::{
+        shred::Shredder,
+        signing_journal::{read_signing_journal, SigningEventKind},
+    };
+    use solana_sdk::{
+        hash::hash,
+        signature::{Keypair, Signer},
+    };
     use solana_vote_program::{vote_instruction, vote_state::Vote};
     use std::iter::repeat_with;
     use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddrV4};
@@ -3220,6 +3489,63 @@ mod tests {
         assert!(ClusterInfo::is_spy_node(&node));
     }
 
+    #[test]
+    fn test_gossip_config() {
+        let default = GossipConfig::default();
+        assert_eq!(default.verify(), Ok(()));
+        assert_eq!(default.pull_request_rounds(), 2);
+
+        let gossip_config = GossipConfig {
+            gossip_interval_ms: 300,
+            pull_request_interval_ms: 1_000,
+            max_bytes_per_second: MIN_GOSSIP_BYTES_PER_SECOND,
+        };
+        assert_eq!(gossip_config.verify(), Ok(()));
+        assert_eq!(gossip_config.pull_request_rounds(), 3);
+
+        // Nodes may only gossip less often than the defaults
+        for gossip_config in &[
+            GossipConfig {
+                gossip_interval_ms: GOSSIP_SLEEP_MILLIS - 1,
+                ..default
+            },
+            GossipConfig {
+                gossip_interval_ms: MAX_GOSSIP_INTERVAL_MILLIS + 1,
+                ..default
+            },
+            GossipConfig {
+                pull_request_interval_ms: GOSSIP_SLEEP_MILLIS,
+                ..default
+            },
+            GossipConfig {
+                pull_request_interval_ms: MAX_PULL_REQUEST_INTERVAL_MILLIS + 1,
+                ..default
+            },
+            GossipConfig {
+                max_bytes_per_second: MIN_GOSSIP_BYTES_PER_SECOND - 1,
+                ..default
+            },
+        ] {
+            assert!(gossip_config.verify().is_err());
+        }
+
+        let cluster_info = ClusterInfo::new_with_invalid_keypair(ContactInfo::default());
+        assert!(cluster_info
+            .set_gossip_config(GossipConfig {
+                gossip_interval_ms: 0,
+                ..default
+            })
+            .is_err());
+        assert_eq!(cluster_info.gossip_config(), default);
+        assert_eq!(cluster_info.set_gossip_config(gossip_config), Ok(()));
+        assert_eq!(cluster_info.gossip_config(), gossip_config);
+        // The budget is capped at the configured rate rather than 50kBps per staked node
+        assert_eq!(
+            cluster_info.update_data_budget(100),
+            (MIN_GOSSIP_BYTES_PER_SECOND / 10) as usize
+        );
+    }
+
     #[test]
     fn test_handle_pull() {
         solana_logger::setup();
@@ -3575,6 +3901,94 @@ mod tests {
         assert_eq!(d.id, cluster_info.id());
     }
 
+    #[test]
+    fn test_duplicate_identity_last_seen() {
+        let keypair = Arc::new(Keypair::new());
+        let contact_info = ContactInfo::new_localhost(&keypair.pubkey(), 0);
+        let cluster_info = ClusterInfo::new(contact_info.clone(), keypair.clone());
+        assert_eq!(cluster_info.duplicate_identity_last_seen(), None);
+
+        // Another instance of this node, started earlier
+        let now = timestamp();
+        let instance = NodeInstance::new(&mut thread_rng(), keypair.pubkey(), now - 1000);
+        let value = CrdsValue::new_signed(
+            CrdsData::NodeInstance(instance.with_wallclock(now)),
+            &keypair,
+        );
+        cluster_info
+            .gossip
+            .write()
+            .unwrap()
+            .crds
+            .insert(value, now)
+            .unwrap();
+        assert_eq!(cluster_info.duplicate_identity_last_seen(), Some(now));
+
+        // Another instance advertising a different gossip address
+        let mut other_contact_info = contact_info;
+        other_contact_info.gossip = "127.0.0.2:1234".parse().unwrap();
+        other_contact_info.wallclock = now + 10;
+        let value = CrdsValue::new_signed(CrdsData::ContactInfo(other_contact_info), &keypair);
+        cluster_info
+            .gossip
+            .write()
+            .unwrap()
+            .crds
+            .insert(value, now)
+            .unwrap();
+        assert_eq!(cluster_info.duplicate_identity_last_seen(), Some(now + 10));
+    }
+
+    #[test]
+    fn test_identity_interlock_other_instance() {
+        let keypair = Arc::new(Keypair::new());
+        let contact_info = ContactInfo::new_localhost(&keypair.pubkey(), 0);
+        let new_cluster_info = |identity_interlock| {
+            let mut cluster_info = ClusterInfo::new(contact_info.clone(), keypair.clone());
+            cluster_info.set_identity_interlock(identity_interlock);
+            cluster_info
+        };
+        let cluster_info = new_cluster_info(false);
+        let interlocked_cluster_info = new_cluster_info(true);
+
+        // Another instance of the same node, started later
+        let other_cluster_info = new_cluster_info(true);
+        let value = CrdsValue::new_signed(
+            CrdsData::NodeInstance(other_cluster_info.instance.with_wallclock(timestamp())),
+            &keypair,
+        );
+        let protocol = Protocol::PushMessage(keypair.pubkey(), vec![value]);
+        let addr = "127.0.0.1:1234".parse().unwrap();
+        let packets = || VecDeque::from(vec![Packet::from_data(&addr, &protocol).unwrap()]);
+        let thread_pool = ThreadPoolBuilder::new().build().unwrap();
+        let recycler = PacketsRecycler::default();
+        let (response_sender, _response_receiver) = std::sync::mpsc::channel();
+        let process_packets = |cluster_info: &ClusterInfo| {
+            cluster_info.process_packets(
+                packets(),
+                &thread_pool,
+                &recycler,
+                &response_sender,
+                HashMap::new(),
+                None,
+                CRDS_GOSSIP_PULL_CRDS_TIMEOUT_MS,
+            )
+        };
+
+        // Without the interlock the older instance exits
+        assert!(matches!(
+            process_packets(&cluster_info),
+            Err(Error::DuplicateNodeInstance)
+        ));
+        assert_eq!(cluster_info.duplicate_identity_last_seen(), None);
+
+        // With it both instances keep running, and the older one sees the other
+        assert!(process_packets(&interlocked_cluster_info).is_ok());
+        assert!(interlocked_cluster_info
+            .duplicate_identity_last_seen()
+            .is_some());
+    }
+
     #[test]
     fn insert_info_test() {
         let d = ContactInfo::new_localhost(&solana_sdk::pubkey::new_rand(), timestamp());
@@ -3686,6 +4100,27 @@ mod tests {
         assert_eq!(node.sockets.gossip.local_addr().unwrap().port(), port);
     }
 
+    #[test]
+    fn new_with_external_ip_test_port_map() {
+        let port_range = (VALIDATOR_PORT_RANGE.1 + 30, VALIDATOR_PORT_RANGE.1 + 50);
+        let ip = IpAddr::V4(Ipv4Addr::from(0));
+        let node = Node::new_with_external_ip(
+            &solana_sdk::pubkey::new_rand(),
+            &socketaddr!(0, 0),
+            port_range,
+            ip,
+        );
+
+        let port_map = node.port_map();
+        assert_eq!(solana_net_utils::find_port_conflict(&port_map), None);
+        for (name, port) in port_map {
+            match name {
+                "rpc" | "rpc_pubsub" => assert_eq!(port, 0),
+                _ => assert!((port_range.0..port_range.1).contains(&port)),
+            }
+        }
+    }
+
     //test that all cluster_info objects only generate signed messages
     //when constructed with keypairs
     #[test]
@@ -3731,6 +4166,40 @@ mod tests {
         assert!(val.verify());
     }
 
+    #[test]
+    fn test_signing_journal_records_gossip() {
+        let journal_dir = tempfile::TempDir::new().unwrap();
+        let journal_path = journal_dir.path().join("signing-journal");
+        let keypair = Arc::new(Keypair::new());
+        let contact_info = ContactInfo::new_localhost(&keypair.pubkey(), 0);
+        let mut cluster_info = ClusterInfo::new(contact_info, keypair.clone());
+        cluster_info
+            .set_signing_journal(Some(Arc::new(SigningJournal::open(&journal_path).unwrap())));
+
+        cluster_info.push_lowest_slot(keypair.pubkey(), 5);
+        cluster_info.push_snapshot_hashes(vec![(7, Hash::default())]);
+        let values: Vec<_> = cluster_info
+            .local_message_pending_push_queue
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(value, _)| value.clone())
+            .collect();
+        assert_eq!(values.len(), 2);
+
+        let events = read_signing_journal(&journal_path).unwrap();
+        assert_eq!(events.len(), 2);
+        for (event, value) in events.iter().zip(&values) {
+            assert_eq!(event.kind, SigningEventKind::Gossip);
+            assert_eq!(event.signature, value.signature);
+            assert_eq!(event.hash, hash(&value.signable_data()));
+            assert_eq!(event.signers, vec![keypair.pubkey()]);
+            assert!(event
+                .signature
+                .verify(keypair.pubkey().as_ref(), &value.signable_data()));
+        }
+    }
+
     #[test]
     fn test_push_vote() {
         let mut rng = rand::thread_rng();
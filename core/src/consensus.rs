@@ -811,7 +811,7 @@ impl Tower {
         }
     }
 
-    fn voted_slots(&self) -> Vec<Slot> {
+    pub(crate) fn voted_slots(&self) -> Vec<Slot> {
         self.lockouts
             .votes
             .iter()
@@ -1075,7 +1075,8 @@ impl Tower {
         path.with_extension("bin.new")
     }
 
-    pub fn save(&self, node_keypair: &Arc<Keypair>) -> Result<()> {
+    /// Saves the tower signed with `node_keypair`, returning the signature
+    pub fn save(&self, node_keypair: &Arc<Keypair>) -> Result<Signature> {
         let mut measure = Measure::start("tower_save-ms");
 
         if self.node_pubkey != node_keypair.pubkey() {
@@ -1088,13 +1089,14 @@ impl Tower {
 
         let filename = &self.path;
         let new_filename = &self.tmp_path;
-        {
+        let signature = {
             // overwrite anything if exists
             let mut file = File::create(&new_filename)?;
             let saved_tower = SavedTower::new(self, node_keypair)?;
             bincode::serialize_into(&mut file, &saved_tower)?;
             // file.sync_all() hurts performance; pipeline sync-ing and submitting votes to the cluster!
-        }
+            saved_tower.signature
+        };
         trace!("persisted votes: {:?}", self.voted_slots());
         fs::rename(&new_filename, &filename)?;
         // self.path.parent().sync_all() hurts performance same as the above sync
@@ -1102,7 +1104,7 @@ impl Tower {
         measure.stop();
         inc_new_counter_info!("tower_save-ms", measure.as_ms() as usize);
 
-        Ok(())
+        Ok(signature)
     }
 
     pub fn restore(path: &Path, node_pubkey: &Pubkey) -> Result<Self> {
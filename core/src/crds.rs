@@ -36,7 +36,7 @@ use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Keypair;
 use solana_sdk::timing::timestamp;
 use std::cmp;
-use std::collections::{hash_map, HashMap};
+use std::collections::{hash_map, BTreeMap, HashMap};
 use std::ops::{Index, IndexMut};
 
 const CRDS_SHARDS_BITS: u32 = 8;
@@ -44,6 +44,19 @@ const CRDS_SHARDS_BITS: u32 = 8;
 // excludes crds values which by label design are limited per each pubkey.
 const MAX_CRDS_VALUES_PER_PUBKEY: usize = 32;
 
+/// Upper bounds of the buckets origins are counted in by the age of their newest value, the last
+/// bucket holds the origins not heard from for longer
+pub const CRDS_ORIGIN_AGE_BUCKETS_MS: [u64; 4] = [15_000, 60_000, 300_000, 3_600_000];
+
+/// How the values in the table break down, see `Crds::composition`
+#[derive(Debug, Default, PartialEq)]
+pub struct CrdsComposition {
+    /// Number of values of each type, by `CrdsData::name`
+    pub values_per_type: BTreeMap<&'static str, usize>,
+    /// Number of origins by the age of their newest value, bucketed by CRDS_ORIGIN_AGE_BUCKETS_MS
+    pub origin_ages: [usize; CRDS_ORIGIN_AGE_BUCKETS_MS.len() + 1],
+}
+
 #[derive(Clone)]
 pub struct Crds {
     /// Stores the map of labels and values
@@ -255,6 +268,32 @@ impl Crds {
         self.table.par_values()
     }
 
+    /// Breaks the table down by value type and by how long ago, as of `now`, each origin's values
+    /// were last updated
+    pub fn composition(&self, now: u64) -> CrdsComposition {
+        let mut composition = CrdsComposition::default();
+        for value in self.table.values() {
+            *composition
+                .values_per_type
+                .entry(value.value.data.name())
+                .or_default() += 1;
+        }
+        for indices in self.records.values() {
+            let last_updated = indices
+                .iter()
+                .map(|index| self.table.index(*index).local_timestamp)
+                .max()
+                .unwrap_or_default();
+            let age = now.saturating_sub(last_updated);
+            let bucket = CRDS_ORIGIN_AGE_BUCKETS_MS
+                .iter()
+                .position(|bound| age < *bound)
+                .unwrap_or(CRDS_ORIGIN_AGE_BUCKETS_MS.len());
+            composition.origin_ages[bucket] += 1;
+        }
+        composition
+    }
+
     /// Returns all crds values which the first 'mask_bits'
     /// of their hash value is equal to 'mask'.
     pub fn filter_bitmask(
@@ -745,6 +784,39 @@ mod test {
         assert!(crds.records.is_empty());
     }
 
+    #[test]
+    fn test_crds_composition() {
+        let mut crds = Crds::default();
+        let now = 1_000_000;
+        assert_eq!(crds.composition(now), CrdsComposition::default());
+
+        let fresh = Pubkey::new_unique();
+        let stale = Pubkey::new_unique();
+        let contact_info = |pubkey| {
+            CrdsValue::new_unsigned(CrdsData::ContactInfo(ContactInfo::new_localhost(
+                &pubkey, 0,
+            )))
+        };
+        let node_instance = |pubkey| {
+            CrdsValue::new_unsigned(CrdsData::NodeInstance(NodeInstance::new(
+                &mut thread_rng(),
+                pubkey,
+                0,
+            )))
+        };
+        crds.insert(contact_info(fresh), now - 20_000).unwrap();
+        // The newest value of an origin decides its age
+        crds.insert(node_instance(fresh), now - 1_000).unwrap();
+        crds.insert(contact_info(stale), now - 7_200_000).unwrap();
+
+        let composition = crds.composition(now);
+        assert_eq!(
+            composition.values_per_type.into_iter().collect::<Vec<_>>(),
+            vec![("ContactInfo", 2), ("NodeInstance", 1)]
+        );
+        assert_eq!(composition.origin_ages, [1, 0, 0, 0, 1]);
+    }
+
     #[test]
     fn test_remove_staked() {
         let thread_pool = ThreadPoolBuilder::new().build().unwrap();
@@ -112,6 +112,8 @@ impl CrdsGossip {
         (self.id, push_messages)
     }
 
+    /// Signs and pushes the proof that `shred` and `other_payload` are duplicates, returning the
+    /// values pushed, none if a proof for the slot was already pushed
     pub(crate) fn push_duplicate_shred(
         &mut self,
         keypair: &Keypair,
@@ -120,7 +122,7 @@ impl CrdsGossip {
         leader_schedule: Option<impl LeaderScheduleFn>,
         // Maximum serialized size of each DuplicateShred chunk payload.
         max_payload_size: usize,
-    ) -> Result<(), duplicate_shred::Error> {
+    ) -> Result<Vec<CrdsValue>, duplicate_shred::Error> {
         let pubkey = keypair.pubkey();
         // Skip if there are already records of duplicate shreds for this slot.
         let shred_slot = shred.slot();
@@ -132,7 +134,7 @@ impl CrdsGossip {
                 _ => false,
             })
         {
-            return Ok(());
+            return Ok(vec![]);
         }
         let chunks = duplicate_shred::from_shred(
             shred.clone(),
@@ -162,7 +164,7 @@ impl CrdsGossip {
         } else {
             offset
         };
-        let entries = chunks
+        let entries: Vec<_> = chunks
             .enumerate()
             .map(|(k, chunk)| {
                 let index = (offset + k as DuplicateShredIndex) % MAX_DUPLICATE_SHREDS;
@@ -170,8 +172,8 @@ impl CrdsGossip {
                 CrdsValue::new_signed(data, keypair)
             })
             .collect();
-        self.process_push_message(&pubkey, entries, timestamp());
-        Ok(())
+        self.process_push_message(&pubkey, entries.clone(), timestamp());
+        Ok(entries)
     }
 
     /// add the `from` to the peer's filter of nodes
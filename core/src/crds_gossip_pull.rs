@@ -8,6 +8,11 @@
 //! Bloom filters have a false positive rate.  Each requests uses a different bloom filter
 //! with random hash functions.  So each subsequent request will have a different distribution
 //! of false positives.
+//!
+//! The filters are sharded by the leading bits of the value hashes, and sized for the table
+//! including its recent growth so that their false positive rate stays near `FALSE_RATE`.
+//! A single pull request carries at most `MAX_CRDS_FILTERS_PER_PULL` shards; successive
+//! requests rotate through the shards so the whole table is covered every few rounds.
 
 use crate::contact_info::ContactInfo;
 use crate::crds::{Crds, VersionedCrdsValue};
@@ -25,6 +30,8 @@ use std::cmp;
 use std::collections::VecDeque;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 pub const CRDS_GOSSIP_PULL_CRDS_TIMEOUT_MS: u64 = 15000;
 // The maximum age of a value received over pull responses
@@ -35,6 +42,8 @@ const FAILED_INSERTS_RETENTION_MS: u64 = 20_000;
 const PULL_ACTIVE_TIMEOUT_MS: u64 = 60_000;
 pub const FALSE_RATE: f64 = 0.1f64;
 pub const KEYS: f64 = 8f64;
+// Upper bound on the number of filter shards sent in a single pull request.
+pub const MAX_CRDS_FILTERS_PER_PULL: usize = 32;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, AbiExample)]
 pub struct CrdsFilter {
@@ -118,30 +127,63 @@ impl CrdsFilter {
     }
 }
 
-/// A vector of crds filters that together hold a complete set of Hashes.
+/// A vector of crds filters for a subset of the shards of the hash space; with all shards
+/// selected they together hold a complete set of Hashes.
 struct CrdsFilterSet {
     filters: Vec<AtomicBloom<Hash>>,
+    // Shard index of each filter, and the position of each shard in `filters` if selected.
+    seeds: Vec<u64>,
+    positions: Vec<Option<usize>>,
     mask_bits: u32,
 }
 
 impl CrdsFilterSet {
     fn new(num_items: usize, max_bytes: usize) -> Self {
+        Self::new_with_shards(num_items, max_bytes, usize::MAX, 0)
+    }
+
+    /// Selects `max_filters` consecutive shards starting at `offset`, wrapping around.
+    fn new_with_shards(
+        num_items: usize,
+        max_bytes: usize,
+        max_filters: usize,
+        offset: usize,
+    ) -> Self {
         let max_bits = (max_bytes * 8) as f64;
         let max_items = CrdsFilter::max_items(max_bits, FALSE_RATE, KEYS);
         let mask_bits = CrdsFilter::mask_bits(num_items as f64, max_items as f64);
+        let num_shards = 1usize << mask_bits;
+        let seeds: Vec<u64> = (0..cmp::min(max_filters, num_shards))
+            .map(|k| (offset.wrapping_add(k) % num_shards) as u64)
+            .collect();
+        let mut positions = vec![None; num_shards];
+        for (position, seed) in seeds.iter().enumerate() {
+            positions[*seed as usize] = Some(position);
+        }
         let filters = std::iter::repeat_with(|| {
             Bloom::random(max_items as usize, FALSE_RATE, max_bits as usize).into()
         })
-        .take(1 << mask_bits)
+        .take(seeds.len())
         .collect();
-        Self { filters, mask_bits }
+        Self {
+            filters,
+            seeds,
+            positions,
+            mask_bits,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.seeds.len() == self.positions.len()
     }
 
     fn add(&self, hash_value: Hash) {
         let index = CrdsFilter::hash_as_u64(&hash_value)
             .checked_shr(64 - self.mask_bits)
             .unwrap_or(0);
-        self.filters[index as usize].add(&hash_value);
+        if let Some(position) = self.positions[index as usize] {
+            self.filters[position].add(&hash_value);
+        }
     }
 }
 
@@ -150,10 +192,10 @@ impl From<CrdsFilterSet> for Vec<CrdsFilter> {
         let mask_bits = cfs.mask_bits;
         cfs.filters
             .into_iter()
-            .enumerate()
-            .map(|(seed, filter)| CrdsFilter {
+            .zip(cfs.seeds)
+            .map(|(filter, seed)| CrdsFilter {
                 filter: filter.into(),
-                mask: CrdsFilter::compute_mask(seed as u64, mask_bits),
+                mask: CrdsFilter::compute_mask(seed, mask_bits),
                 mask_bits,
             })
             .collect()
@@ -182,6 +224,10 @@ pub struct CrdsGossipPull {
     pub crds_timeout: u64,
     pub msg_timeout: u64,
     pub num_pulls: usize,
+    // Number of items the previous set of filters was sized for.
+    num_filter_items: Arc<AtomicUsize>,
+    // First shard of the next partial set of filters.
+    filter_shard_offset: Arc<AtomicUsize>,
 }
 
 impl Default for CrdsGossipPull {
@@ -193,6 +239,8 @@ impl Default for CrdsGossipPull {
             crds_timeout: CRDS_GOSSIP_PULL_CRDS_TIMEOUT_MS,
             msg_timeout: CRDS_GOSSIP_PULL_MSG_TIMEOUT_MS,
             num_pulls: 0,
+            num_filter_items: Arc::default(),
+            filter_shard_offset: Arc::default(),
         }
     }
 }
@@ -220,7 +268,16 @@ impl CrdsGossipPull {
         if options.is_empty() {
             return Err(CrdsGossipError::NoPeers);
         }
-        let filters = self.build_crds_filters(thread_pool, crds, bloom_size);
+        let offset = self
+            .filter_shard_offset
+            .fetch_add(MAX_CRDS_FILTERS_PER_PULL, Ordering::Relaxed);
+        let filters = self.build_crds_filter_shards(
+            thread_pool,
+            crds,
+            bloom_size,
+            MAX_CRDS_FILTERS_PER_PULL,
+            offset,
+        );
         let index = WeightedIndex::new(options.iter().map(|weighted| weighted.0)).unwrap();
         let random = index.sample(&mut rand::thread_rng());
         let self_info = crds
@@ -436,6 +493,20 @@ impl CrdsGossipPull {
         }
     }
 
+    // Number of items to size the filters for: the current number of hashes plus however much
+    // the table grew since the previous filters were built, so that the filters don't saturate
+    // while the table is growing.
+    fn num_filter_items(&self, crds: &Crds) -> usize {
+        let num_items = crds.len() + self.purged_values.len() + self.failed_inserts.len();
+        let prev_num_items = self.num_filter_items.swap(num_items, Ordering::Relaxed);
+        let growth = if prev_num_items == 0 {
+            0
+        } else {
+            num_items.saturating_sub(prev_num_items)
+        };
+        cmp::max(CRDS_GOSSIP_DEFAULT_BLOOM_ITEMS, num_items + growth)
+    }
+
     // build a set of filters of the current crds table
     // num_filters - used to increase the likelyhood of a value in crds being added to some filter
     pub fn build_crds_filters(
@@ -444,31 +515,93 @@ impl CrdsGossipPull {
         crds: &Crds,
         bloom_size: usize,
     ) -> Vec<CrdsFilter> {
-        const PAR_MIN_LENGTH: usize = 512;
-        let num = cmp::max(
-            CRDS_GOSSIP_DEFAULT_BLOOM_ITEMS,
-            crds.len() + self.purged_values.len() + self.failed_inserts.len(),
+        let filters = CrdsFilterSet::new(self.num_filter_items(crds), bloom_size);
+        self.fill_crds_filters(thread_pool, crds, filters)
+    }
+
+    /// Builds filters for at most `max_filters` shards of the crds table, starting at shard
+    /// `offset`. Only the crds values in the selected shards are hashed into the filters.
+    pub fn build_crds_filter_shards(
+        &self,
+        thread_pool: &ThreadPool,
+        crds: &Crds,
+        bloom_size: usize,
+        max_filters: usize,
+        offset: usize,
+    ) -> Vec<CrdsFilter> {
+        let filters = CrdsFilterSet::new_with_shards(
+            self.num_filter_items(crds),
+            bloom_size,
+            max_filters,
+            offset,
         );
-        let filters = CrdsFilterSet::new(num, bloom_size);
+        self.fill_crds_filters(thread_pool, crds, filters)
+    }
+
+    fn fill_crds_filters(
+        &self,
+        thread_pool: &ThreadPool,
+        crds: &Crds,
+        filters: CrdsFilterSet,
+    ) -> Vec<CrdsFilter> {
+        const PAR_MIN_LENGTH: usize = 512;
         thread_pool.install(|| {
-            crds.par_values()
+            let old_values = self
+                .purged_values
+                .par_iter()
                 .with_min_len(PAR_MIN_LENGTH)
-                .map(|v| v.value_hash)
-                .chain(
-                    self.purged_values
-                        .par_iter()
-                        .with_min_len(PAR_MIN_LENGTH)
-                        .map(|(v, _)| *v),
-                )
+                .map(|(v, _)| *v)
                 .chain(
                     self.failed_inserts
                         .par_iter()
                         .with_min_len(PAR_MIN_LENGTH)
                         .map(|(v, _)| *v),
-                )
-                .for_each(|v| filters.add(v));
+                );
+            if filters.is_complete() {
+                crds.par_values()
+                    .with_min_len(PAR_MIN_LENGTH)
+                    .map(|v| v.value_hash)
+                    .chain(old_values)
+                    .for_each(|v| filters.add(v));
+            } else {
+                let mask_bits = filters.mask_bits;
+                filters.seeds.par_iter().for_each(|seed| {
+                    let mask = CrdsFilter::compute_mask(*seed, mask_bits);
+                    crds.filter_bitmask(mask, mask_bits)
+                        .for_each(|v| filters.add(v.value_hash));
+                });
+                old_values.for_each(|v| filters.add(v));
+            }
         });
-        filters.into()
+        let filters: Vec<CrdsFilter> = filters.into();
+        Self::report_filter_stats(&filters);
+        filters
+    }
+
+    fn report_filter_stats(filters: &[CrdsFilter]) {
+        if filters.is_empty() {
+            return;
+        }
+        let (num_bits_set, false_positive_rate) =
+            filters
+                .iter()
+                .fold((0, 0.0), |(num_bits_set, rate), filter| {
+                    (
+                        num_bits_set + filter.filter.num_bits_set(),
+                        rate + filter.filter.false_positive_rate(),
+                    )
+                });
+        datapoint_debug!(
+            "crds_gossip_pull-filters",
+            ("num_filters", filters.len(), i64),
+            ("mask_bits", filters[0].mask_bits, i64),
+            ("avg_num_bits_set", num_bits_set / filters.len() as u64, i64),
+            (
+                "avg_false_positive_rate",
+                false_positive_rate / filters.len() as f64,
+                f64
+            ),
+        );
     }
 
     /// filter values that fail the bloom filter up to max_bytes
@@ -904,6 +1037,46 @@ mod test {
         assert!(false_positives < 50_000, "fp: {}", false_positives);
     }
 
+    #[test]
+    fn test_build_crds_filter_shards() {
+        let mut rng = thread_rng();
+        let thread_pool = ThreadPoolBuilder::new().build().unwrap();
+        let crds_gossip_pull = CrdsGossipPull::default();
+        let mut crds = Crds::default();
+        for _ in 0..30_000 {
+            crds.insert(CrdsValue::new_rand(&mut rng, None), rng.gen())
+                .unwrap();
+        }
+        let hash_values: Vec<_> = crds.values().map(|v| v.value_hash).collect();
+        // Rotating through the shards covers every value exactly once.
+        let mut num_hits = vec![0; hash_values.len()];
+        let mut offset = 0;
+        for _ in 0..4 {
+            let filters = crds_gossip_pull.build_crds_filter_shards(
+                &thread_pool,
+                &crds,
+                MAX_BLOOM_SIZE,
+                /*max_filters:*/ 8,
+                offset,
+            );
+            assert_eq!(filters.len(), 8);
+            offset += filters.len();
+            for (hash_value, num_hits) in hash_values.iter().zip(num_hits.iter_mut()) {
+                for filter in &filters {
+                    if filter.test_mask(hash_value) {
+                        *num_hits += 1;
+                        assert!(filter.filter.contains(hash_value));
+                    }
+                }
+            }
+        }
+        assert!(num_hits.iter().all(|num_hits| *num_hits == 1));
+        // The table did not grow, so the shards stay the same size.
+        let filters =
+            crds_gossip_pull.build_crds_filter_shards(&thread_pool, &crds, MAX_BLOOM_SIZE, 64, 0);
+        assert_eq!(filters.len(), 32);
+    }
+
     #[test]
     fn test_new_pull_request() {
         let thread_pool = ThreadPoolBuilder::new().build().unwrap();
@@ -135,6 +135,22 @@ pub(crate) fn new_rand_timestamp<R: Rng>(rng: &mut R) -> u64 {
 }
 
 impl CrdsData {
+    /// Name of the variant, to break the crds table down by type
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrdsData::ContactInfo(_) => "ContactInfo",
+            CrdsData::Vote(_, _) => "Vote",
+            CrdsData::LowestSlot(_, _) => "LowestSlot",
+            CrdsData::SnapshotHashes(_) => "SnapshotHashes",
+            CrdsData::AccountsHashes(_) => "AccountsHashes",
+            CrdsData::EpochSlots(_, _) => "EpochSlots",
+            CrdsData::LegacyVersion(_) => "LegacyVersion",
+            CrdsData::Version(_) => "Version",
+            CrdsData::NodeInstance(_) => "NodeInstance",
+            CrdsData::DuplicateShred(_, _) => "DuplicateShred",
+        }
+    }
+
     /// New random CrdsData for tests and benchmarks.
     fn new_rand<R: Rng>(rng: &mut R, pubkey: Option<Pubkey>) -> CrdsData {
         let kind = rng.gen_range(0, 6);
@@ -408,6 +424,12 @@ impl NodeInstance {
         }
     }
 
+    // Returns true if the other instance runs with the same identity
+    // as this one, regardless of which was started first.
+    pub fn is_other_instance(&self, other: &NodeInstance) -> bool {
+        self.from == other.from && self.token != other.token
+    }
+
     // Returns true if the crds-value is a duplicate instance
     // of this node, with a more recent timestamp.
     pub fn check_duplicate(&self, other: &CrdsValue) -> bool {
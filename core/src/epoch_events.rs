@@ -0,0 +1,168 @@
+//! The `epoch_events` module describes the epoch boundaries ReplayStage crosses as it roots
+//! banks, for the `epochSubscribe` and `rewardsSubscribe` pubsub notifications: the new
+//! epoch's stake set and the features activated since the prior root.
+
+use solana_client::rpc_response::{
+    EpochBoundaryInfo, RpcActivatedFeature, RpcBlockRewards, RpcStakeChange,
+};
+use solana_runtime::bank::Bank;
+use solana_sdk::{clock::Epoch, feature_set::FEATURE_NAMES, pubkey::Pubkey};
+use solana_transaction_status::Reward;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
+
+/// Describes the epoch boundary crossed by rooting `root_bank` over `prior_root_bank`, or
+/// returns None if both are in the same epoch. If the new root skips past several epochs,
+/// the changes are reported against the epoch of the prior root.
+pub fn epoch_boundary_info(prior_root_bank: &Bank, root_bank: &Bank) -> Option<EpochBoundaryInfo> {
+    let prior_epoch = prior_root_bank.epoch();
+    let epoch = root_bank.epoch();
+    if epoch <= prior_epoch {
+        return None;
+    }
+
+    let prior_epoch_stakes = prior_root_bank.epoch_stakes(prior_epoch);
+    let epoch_stakes = root_bank.epoch_stakes(epoch);
+    let prior_vote_accounts = prior_root_bank.epoch_vote_accounts(prior_epoch);
+    let vote_accounts = root_bank.epoch_vote_accounts(epoch);
+    let stake = |vote_accounts: Option<&HashMap<Pubkey, (u64, _)>>, vote_pubkey| {
+        vote_accounts
+            .and_then(|vote_accounts| vote_accounts.get(vote_pubkey))
+            .map(|(stake, _)| *stake)
+            .unwrap_or_default()
+    };
+    let vote_pubkeys: BTreeSet<&Pubkey> = prior_vote_accounts
+        .into_iter()
+        .chain(vote_accounts)
+        .flat_map(|vote_accounts| vote_accounts.keys())
+        .collect();
+    let stake_changes = vote_pubkeys
+        .into_iter()
+        .filter_map(|vote_pubkey| {
+            let prior_stake = stake(prior_vote_accounts, vote_pubkey);
+            let stake = stake(vote_accounts, vote_pubkey);
+            if prior_stake == stake {
+                return None;
+            }
+            Some(RpcStakeChange {
+                vote_pubkey: vote_pubkey.to_string(),
+                prior_stake,
+                stake,
+            })
+        })
+        .collect();
+
+    let mut activated_features: Vec<_> = root_bank
+        .feature_set
+        .active
+        .iter()
+        .filter(|(id, _)| !prior_root_bank.feature_set.active.contains_key(id))
+        .map(|(id, activation_slot)| RpcActivatedFeature {
+            id: id.to_string(),
+            name: FEATURE_NAMES
+                .get(id)
+                .map(|name| name.to_string())
+                .unwrap_or_default(),
+            activation_slot: *activation_slot,
+        })
+        .collect();
+    activated_features.sort_by(|a, b| (a.activation_slot, &a.id).cmp(&(b.activation_slot, &b.id)));
+
+    Some(EpochBoundaryInfo {
+        epoch,
+        root: root_bank.slot(),
+        prior_root: prior_root_bank.slot(),
+        total_active_stake: epoch_stakes
+            .map(|epoch_stakes| epoch_stakes.total_stake())
+            .unwrap_or_default(),
+        prior_total_active_stake: prior_epoch_stakes
+            .map(|epoch_stakes| epoch_stakes.total_stake())
+            .unwrap_or_default(),
+        stake_changes,
+        activated_features,
+    })
+}
+
+/// Returns the rewards of the first bank of `epoch` among `rooted_banks`, which carries the
+/// staking rewards of the epoch
+pub fn epoch_rewards(rooted_banks: &[Arc<Bank>], epoch: Epoch) -> Option<RpcBlockRewards> {
+    let bank = rooted_banks.iter().find(|bank| {
+        bank.epoch() == epoch && bank.epoch_schedule().get_epoch(bank.parent_slot()) < epoch
+    })?;
+    let rewards = bank
+        .rewards
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(pubkey, reward_info)| Reward {
+            pubkey: pubkey.to_string(),
+            lamports: reward_info.lamports,
+            post_balance: reward_info.post_balance,
+            reward_type: Some(reward_info.reward_type),
+        })
+        .collect();
+    Some(RpcBlockRewards {
+        slot: bank.slot(),
+        epoch,
+        rewards,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_runtime::genesis_utils::{create_genesis_config_with_leader, GenesisConfigInfo};
+
+    #[test]
+    fn test_epoch_boundary_info() {
+        let GenesisConfigInfo { genesis_config, .. } =
+            create_genesis_config_with_leader(10_000, &Pubkey::new_unique(), 100);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let epoch_schedule = bank0.epoch_schedule();
+        let first_slot_in_epoch_1 = epoch_schedule.get_first_slot_in_epoch(1);
+        let bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        assert!(epoch_boundary_info(&bank0, &bank1).is_none());
+
+        let bank2 = Bank::new_from_parent(&bank0, &Pubkey::default(), first_slot_in_epoch_1);
+        let epoch_boundary = epoch_boundary_info(&bank1, &bank2).unwrap();
+        assert_eq!(epoch_boundary.epoch, 1);
+        assert_eq!(epoch_boundary.root, first_slot_in_epoch_1);
+        assert_eq!(epoch_boundary.prior_root, 1);
+        assert_eq!(epoch_boundary.total_active_stake, 100);
+        assert_eq!(epoch_boundary.prior_total_active_stake, 100);
+        assert!(epoch_boundary.stake_changes.is_empty());
+        assert!(epoch_boundary.activated_features.is_empty());
+    }
+
+    #[test]
+    fn test_epoch_rewards() {
+        let GenesisConfigInfo { genesis_config, .. } =
+            create_genesis_config_with_leader(10_000, &Pubkey::new_unique(), 100);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let first_slot_in_epoch_1 = bank0.epoch_schedule().get_first_slot_in_epoch(1);
+        let bank1 = Arc::new(Bank::new_from_parent(&bank0, &Pubkey::default(), 1));
+        let bank2 = Arc::new(Bank::new_from_parent(
+            &bank1,
+            &Pubkey::default(),
+            first_slot_in_epoch_1 + 1,
+        ));
+        let bank3 = Arc::new(Bank::new_from_parent(
+            &bank2,
+            &Pubkey::default(),
+            first_slot_in_epoch_1 + 2,
+        ));
+        let rooted_banks = vec![bank1, bank2.clone(), bank3];
+
+        // The first slot of the epoch was skipped, its first bank comes after
+        let block_rewards = epoch_rewards(&rooted_banks, 1).unwrap();
+        assert_eq!(block_rewards.slot, bank2.slot());
+        assert_eq!(block_rewards.epoch, 1);
+        assert_eq!(
+            block_rewards.rewards.len(),
+            bank2.rewards.read().unwrap().len()
+        );
+        assert!(epoch_rewards(&rooted_banks, 2).is_none());
+    }
+}
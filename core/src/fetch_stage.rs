@@ -1,14 +1,16 @@
 //! The `fetch_stage` batches input from a UDP socket and sends it to a channel.
 
 use crate::banking_stage::FORWARD_TRANSACTIONS_TO_LEADER_AT_SLOT_OFFSET;
+use crate::packet_channel::{bounded_channel, BoundedSender, ChannelConfig};
 use crate::poh_recorder::PohRecorder;
 use crate::result::{Error, Result};
+use crossbeam_channel::Receiver as CrossbeamReceiver;
 use solana_measure::thread_mem_usage;
 use solana_metrics::{inc_new_counter_debug, inc_new_counter_info};
-use solana_perf::packet::PacketsRecycler;
+use solana_perf::packet::{Packets, PacketsRecycler};
 use solana_perf::recycler::Recycler;
 use solana_sdk::clock::DEFAULT_TICKS_PER_SLOT;
-use solana_streamer::streamer::{self, PacketReceiver, PacketSender};
+use solana_streamer::streamer::{self, PacketReceiver};
 use std::net::UdpSocket;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::{channel, RecvTimeoutError};
@@ -26,8 +28,9 @@ impl FetchStage {
         tpu_forwards_sockets: Vec<UdpSocket>,
         exit: &Arc<AtomicBool>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
-    ) -> (Self, PacketReceiver) {
-        let (sender, receiver) = channel();
+        config: &ChannelConfig,
+    ) -> (Self, CrossbeamReceiver<Packets>) {
+        let (sender, receiver) = bounded_channel("fetch-stage-packets-channel", config);
         (
             Self::new_with_sender(sockets, tpu_forwards_sockets, exit, &sender, &poh_recorder),
             receiver,
@@ -37,7 +40,7 @@ impl FetchStage {
         sockets: Vec<UdpSocket>,
         tpu_forwards_sockets: Vec<UdpSocket>,
         exit: &Arc<AtomicBool>,
-        sender: &PacketSender,
+        sender: &BoundedSender<Packets>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
     ) -> Self {
         let tx_sockets = sockets.into_iter().map(Arc::new).collect();
@@ -53,7 +56,7 @@ impl FetchStage {
 
     fn handle_forwarded_packets(
         recvr: &PacketReceiver,
-        sendr: &PacketSender,
+        sendr: &BoundedSender<Packets>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
     ) -> Result<()> {
         let msgs = recvr.recv()?;
@@ -90,16 +93,19 @@ impl FetchStage {
         sockets: Vec<Arc<UdpSocket>>,
         tpu_forwards_sockets: Vec<Arc<UdpSocket>>,
         exit: &Arc<AtomicBool>,
-        sender: &PacketSender,
+        sender: &BoundedSender<Packets>,
         poh_recorder: &Arc<Mutex<PohRecorder>>,
     ) -> Self {
         let recycler: PacketsRecycler = Recycler::warmed(1000, 1024);
 
+        // The streamers feed a std channel, which is relayed to the bounded channel so that it
+        // sheds load by its drop policy rather than queueing without limit
+        let (packet_sender, packet_receiver) = channel();
         let tpu_threads = sockets.into_iter().map(|socket| {
             streamer::receiver(
                 socket,
                 &exit,
-                sender.clone(),
+                packet_sender.clone(),
                 recycler.clone(),
                 "fetch_stage",
             )
@@ -116,6 +122,18 @@ impl FetchStage {
             )
         });
 
+        let relay_sender = sender.clone();
+        let relay_thread_hdl = Builder::new()
+            .name("solana-fetch-stage-relay".to_string())
+            .spawn(move || {
+                for packets in packet_receiver.iter() {
+                    if relay_sender.send(packets).is_err() {
+                        break;
+                    }
+                }
+            })
+            .unwrap();
+
         let sender = sender.clone();
         let poh_recorder = poh_recorder.clone();
 
@@ -138,6 +156,7 @@ impl FetchStage {
             .unwrap();
 
         let mut thread_hdls: Vec<_> = tpu_threads.chain(tpu_forwards_threads).collect();
+        thread_hdls.push(relay_thread_hdl);
         thread_hdls.push(fwd_thread_hdl);
         Self { thread_hdls }
     }
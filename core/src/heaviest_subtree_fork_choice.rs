@@ -190,6 +190,29 @@ impl HeaviestSubtreeForkChoice {
         self.last_root_time = Instant::now();
     }
 
+    /// Removes the slots of forks BankForks pruned, which no stake voted on, and points the
+    /// ancestors that considered one of them their `best_slot` at another slot
+    pub fn purge_slots(&mut self, slots: &[Slot]) {
+        let mut update_operations = BTreeMap::new();
+        for slot in slots {
+            assert_ne!(*slot, self.root);
+            if let Some(fork_info) = self.fork_infos.remove(slot) {
+                assert_eq!(fork_info.stake_voted_subtree, 0);
+                if let Some(parent) = fork_info.parent {
+                    if let Some(parent_info) = self.fork_infos.get_mut(&parent) {
+                        parent_info.children.retain(|child| child != slot);
+                        update_operations
+                            .insert((parent, UpdateLabel::Aggregate), UpdateOperation::Aggregate);
+                        self.insert_aggregate_operations(&mut update_operations, parent);
+                    }
+                }
+            }
+        }
+        self.latest_votes
+            .retain(|_, latest_vote_slot| !slots.contains(latest_vote_slot));
+        self.process_update_operations(update_operations);
+    }
+
     pub fn add_root_parent(&mut self, root_parent: Slot) {
         assert!(root_parent < self.root);
         assert!(self.fork_infos.get(&root_parent).is_none());
@@ -793,6 +816,20 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_purge_slots() {
+        let mut heaviest_subtree_fork_choice = setup_forks();
+        // Without votes the earlier fork is the best
+        assert_eq!(heaviest_subtree_fork_choice.best_overall_slot(), 4);
+
+        heaviest_subtree_fork_choice.purge_slots(&[2, 4]);
+        assert!(!heaviest_subtree_fork_choice.contains_slot(2));
+        assert!(!heaviest_subtree_fork_choice.contains_slot(4));
+        assert_eq!(heaviest_subtree_fork_choice.children(1).unwrap(), &[3]);
+        assert_eq!(heaviest_subtree_fork_choice.best_overall_slot(), 6);
+        assert_eq!(heaviest_subtree_fork_choice.best_slot(1), Some(6));
+    }
+
     #[test]
     fn test_set_root_and_add_votes() {
         let mut heaviest_subtree_fork_choice = setup_forks();
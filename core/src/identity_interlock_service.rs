@@ -0,0 +1,143 @@
+//! The `identity_interlock_service` guards primary/backup validator pairs sharing an identity
+//! against double signing. It watches gossip for values signed with this node's identity by
+//! another running instance and, as soon as one shows up, halts local block production and
+//! voting until that instance has been silent for the configured hold-down period. The node also
+//! starts halted for the hold-down period, so that it doesn't sign anything before it has had a
+//! chance to see an instance that is already running.
+
+use crate::cluster_info::ClusterInfo;
+use solana_sdk::timing::timestamp;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_IDENTITY_INTERLOCK_HOLD_DOWN_SECS: u64 = 60;
+
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+const METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Debug)]
+pub struct IdentityInterlockConfig {
+    /// Seconds the other instance must have been silent in gossip before resuming block
+    /// production and voting
+    pub hold_down_secs: u64,
+}
+
+impl Default for IdentityInterlockConfig {
+    fn default() -> Self {
+        Self {
+            hold_down_secs: DEFAULT_IDENTITY_INTERLOCK_HOLD_DOWN_SECS,
+        }
+    }
+}
+
+pub struct IdentityInterlockService {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl IdentityInterlockService {
+    pub fn new(
+        cluster_info: &Arc<ClusterInfo>,
+        config: IdentityInterlockConfig,
+        identity_conflict: &Arc<AtomicBool>,
+        exit: &Arc<AtomicBool>,
+    ) -> Self {
+        let cluster_info = cluster_info.clone();
+        let identity_conflict = identity_conflict.clone();
+        let exit = exit.clone();
+
+        info!("Starting IdentityInterlock service: {:?}", config);
+        let thread_hdl = Builder::new()
+            .name("solana-identity-interlock".to_string())
+            .spawn(move || {
+                Self::run(&cluster_info, &config, &identity_conflict, &exit);
+            })
+            .unwrap();
+
+        Self { thread_hdl }
+    }
+
+    fn run(
+        cluster_info: &ClusterInfo,
+        config: &IdentityInterlockConfig,
+        identity_conflict: &AtomicBool,
+        exit: &AtomicBool,
+    ) {
+        let hold_down_ms = config.hold_down_secs.saturating_mul(1000);
+        let started = timestamp();
+        let mut last_metrics = Instant::now();
+        loop {
+            if exit.load(Ordering::Relaxed) {
+                break;
+            }
+            sleep(CHECK_INTERVAL);
+
+            let last_seen = cluster_info.duplicate_identity_last_seen();
+            // Starting up counts as seeing another instance
+            let conflict = is_conflict(last_seen.max(Some(started)), timestamp(), hold_down_ms);
+            if identity_conflict.swap(conflict, Ordering::Relaxed) != conflict {
+                if conflict {
+                    error!(
+                        "another instance of {} is running, halting block production and voting",
+                        cluster_info.id()
+                    );
+                    datapoint_error!(
+                        "identity_interlock-halted",
+                        ("id", cluster_info.id().to_string(), String),
+                        ("last_seen", last_seen.unwrap_or_default(), i64),
+                    );
+                } else {
+                    warn!(
+                        "no other instance of {} seen for {}s, resuming block production and voting",
+                        cluster_info.id(),
+                        config.hold_down_secs
+                    );
+                    datapoint_warn!(
+                        "identity_interlock-resumed",
+                        ("id", cluster_info.id().to_string(), String),
+                    );
+                }
+            }
+
+            if last_metrics.elapsed() >= METRICS_INTERVAL {
+                last_metrics = Instant::now();
+                datapoint_info!(
+                    "identity_interlock",
+                    ("halted", conflict, bool),
+                    ("last_seen", last_seen.unwrap_or_default(), i64),
+                );
+            }
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
+
+/// Whether another instance last seen at `last_seen`, if ever, is still considered running at
+/// `now`. Timestamps are in milliseconds; values from the future count as running
+fn is_conflict(last_seen: Option<u64>, now: u64, hold_down_ms: u64) -> bool {
+    last_seen
+        .map(|last_seen| now.saturating_sub(last_seen) < hold_down_ms)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_conflict() {
+        assert!(!is_conflict(None, 100_000, 60_000));
+        assert!(is_conflict(Some(100_000), 100_000, 60_000));
+        assert!(is_conflict(Some(50_000), 100_000, 60_000));
+        assert!(!is_conflict(Some(40_000), 100_000, 60_000));
+        assert!(is_conflict(Some(110_000), 100_000, 60_000));
+    }
+}
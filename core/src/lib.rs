@@ -11,6 +11,7 @@ pub mod banking_stage;
 pub mod bigtable_upload_service;
 pub mod broadcast_stage;
 pub mod cache_block_time_service;
+pub mod clock_drift_service;
 pub mod cluster_info_vote_listener;
 pub mod commitment_service;
 pub mod completed_data_sets_service;
@@ -32,35 +33,46 @@ pub mod crds_shards;
 pub mod crds_value;
 pub mod data_budget;
 pub mod duplicate_shred;
+pub mod epoch_events;
 pub mod epoch_slots;
 pub mod fetch_stage;
 pub mod fork_choice;
 pub mod gen_keys;
 pub mod gossip_service;
 pub mod heaviest_subtree_fork_choice;
+pub mod identity_interlock_service;
 pub mod ledger_cleanup_service;
 pub mod non_circulating_supply;
+pub mod object_archive_service;
 pub mod optimistic_confirmation_verifier;
 pub mod optimistically_confirmed_bank_tracker;
+pub mod packet_channel;
 pub mod packet_hasher;
 pub mod ping_pong;
 pub mod poh_recorder;
 pub mod poh_service;
+pub mod program_stats;
 pub mod progress_map;
 pub mod pubkey_references;
 pub mod repair_response;
 pub mod repair_service;
+pub mod repair_status;
 pub mod repair_weight;
 pub mod repair_weighted_traversal;
 pub mod replay_stage;
+pub mod replica_sync;
+pub mod replica_sync_service;
 mod result;
 pub mod retransmit_stage;
 pub mod rewards_recorder_service;
 pub mod rpc;
+pub mod rpc_auth;
+pub mod rpc_cache;
 pub mod rpc_health;
 pub mod rpc_pubsub;
 pub mod rpc_pubsub_service;
 pub mod rpc_service;
+pub mod rpc_subscription_resume;
 pub mod rpc_subscriptions;
 pub mod send_transaction_service;
 pub mod serve_repair;
@@ -70,6 +82,7 @@ pub mod sigverify_shreds;
 pub mod sigverify_stage;
 pub mod snapshot_packager_service;
 pub mod test_validator;
+pub mod thread_pool_metrics_service;
 pub mod tpu;
 pub mod transaction_status_service;
 pub mod tree_diff;
@@ -0,0 +1,212 @@
+//! The `object_archive_service` streams rooted blocks from the blockstore to an object storage
+//! ledger archive, so that RPC can keep serving them after they are purged from the blockstore
+
+use solana_ledger::blockstore::{Blockstore, BlockstoreError};
+use solana_runtime::commitment::BlockCommitmentCache;
+use solana_sdk::clock::Slot;
+use solana_storage_object::LedgerArchive;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    sync::{Arc, RwLock},
+    thread::{self, sleep, Builder, JoinHandle},
+    time::Duration,
+};
+use tokio::runtime;
+
+// Delay archiving the largest confirmed root for this many slots, giving the
+// `CacheBlockTimeService` time to add the block time for the root
+const LARGEST_CONFIRMED_ROOT_ARCHIVE_DELAY: Slot = 100;
+
+// Number of blocks uploaded before the manifests are updated
+const ARCHIVE_BATCH_SIZE: usize = 100;
+
+pub struct ObjectArchiveService {
+    thread: JoinHandle<()>,
+}
+
+impl ObjectArchiveService {
+    pub fn new(
+        runtime_handle: runtime::Handle,
+        ledger_archive: LedgerArchive,
+        blockstore: Arc<Blockstore>,
+        block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
+        exit: Arc<AtomicBool>,
+    ) -> Self {
+        info!("Starting object archive service");
+        let thread = Builder::new()
+            .name("object-archive".to_string())
+            .spawn(move || {
+                Self::run(
+                    runtime_handle,
+                    ledger_archive,
+                    blockstore,
+                    block_commitment_cache,
+                    exit,
+                )
+            })
+            .unwrap();
+
+        Self { thread }
+    }
+
+    fn run(
+        runtime: runtime::Handle,
+        ledger_archive: LedgerArchive,
+        blockstore: Arc<Blockstore>,
+        block_commitment_cache: Arc<RwLock<BlockCommitmentCache>>,
+        exit: Arc<AtomicBool>,
+    ) {
+        // Resume after the last archived slot
+        let mut start_slot = loop {
+            if exit.load(Ordering::Relaxed) {
+                return;
+            }
+            match runtime.block_on(ledger_archive.get_state()) {
+                Ok(state) => break state.map(|state| state.last_slot + 1).unwrap_or(0),
+                Err(err) => {
+                    warn!("object archive: get_state: {}", err);
+                    sleep(Duration::from_secs(2));
+                }
+            }
+        };
+
+        while !exit.load(Ordering::Relaxed) {
+            let end_slot = block_commitment_cache
+                .read()
+                .unwrap()
+                .highest_confirmed_root()
+                .saturating_sub(LARGEST_CONFIRMED_ROOT_ARCHIVE_DELAY);
+
+            if end_slot < start_slot {
+                sleep(Duration::from_secs(1));
+                continue;
+            }
+
+            match Self::archive_slots(&runtime, &ledger_archive, &blockstore, start_slot, end_slot)
+            {
+                Ok(last_slot) => start_slot = last_slot + 1,
+                Err(err) => {
+                    warn!("object archive: {}", err);
+                    sleep(Duration::from_secs(2));
+                }
+            }
+        }
+    }
+
+    // Archives up to `ARCHIVE_BATCH_SIZE` rooted blocks in `start_slot..=end_slot`, returning
+    // the last slot covered. Stops before the first block that fails to load, so that it is
+    // retried by the next call instead of missing from the archive.
+    fn archive_slots(
+        runtime: &runtime::Handle,
+        ledger_archive: &LedgerArchive,
+        blockstore: &Blockstore,
+        start_slot: Slot,
+        end_slot: Slot,
+    ) -> Result<Slot, String> {
+        // The blockstore may have already been purged past `start_slot`
+        let start_slot = start_slot.max(blockstore.lowest_slot());
+        let slots: Vec<_> = blockstore
+            .rooted_slot_iterator(start_slot)
+            .map_err(|err| format!("rooted_slot_iterator: {:?}", err))?
+            .take_while(|slot| *slot <= end_slot)
+            .take(ARCHIVE_BATCH_SIZE)
+            .collect();
+
+        let mut blocks = Vec::with_capacity(slots.len());
+        let mut failed_slot = None;
+        for slot in &slots {
+            match blockstore.get_confirmed_block(*slot) {
+                Ok(block) => blocks.push((*slot, block)),
+                // Purged from the blockstore, retrying cannot recover it
+                Err(BlockstoreError::SlotCleanedUp) => {
+                    warn!("object archive: block {} was cleaned up, skipping", slot)
+                }
+                Err(err) => {
+                    warn!("object archive: failed to get block {}: {:?}", slot, err);
+                    failed_slot = Some(*slot);
+                    break;
+                }
+            }
+        }
+        let num_blocks = blocks.len();
+        runtime
+            .block_on(ledger_archive.upload_confirmed_blocks(blocks))
+            .map_err(|err| format!("upload_confirmed_blocks: {}", err))?;
+
+        let last_slot = match failed_slot {
+            Some(failed_slot) if failed_slot == start_slot => {
+                return Err(format!("failed to get block {}", failed_slot));
+            }
+            Some(failed_slot) => failed_slot - 1,
+            None if slots.len() < ARCHIVE_BATCH_SIZE => end_slot,
+            None => *slots.last().unwrap(),
+        };
+        datapoint_info!(
+            "object-archive",
+            ("num_blocks", num_blocks, i64),
+            ("last_slot", last_slot, i64)
+        );
+        Ok(last_slot)
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread.join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_ledger::{blockstore::make_slot_entries, get_tmp_ledger_path};
+    use solana_storage_object::ObjectStorage;
+
+    #[test]
+    fn test_archive_slots_retries_failed_block() {
+        let ledger_path = get_tmp_ledger_path!();
+        let blockstore = Blockstore::open(&ledger_path).unwrap();
+        let archive_dir = tempfile::tempdir().unwrap();
+        let ledger_archive =
+            LedgerArchive::new(ObjectStorage::Filesystem(archive_dir.path().to_path_buf()));
+        let runtime = runtime::Runtime::new().unwrap();
+        let runtime = runtime.handle().clone();
+
+        for (slot, parent_slot) in &[(1, 0), (3, 2)] {
+            let (shreds, _) = make_slot_entries(*slot, *parent_slot, 1);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+        }
+        // Slot 2 is missing its first shred, so its block can't be loaded yet
+        let (mut shreds, _) = make_slot_entries(2, 1, 100);
+        assert!(shreds.len() > 1);
+        let first_shred = shreds.remove(0);
+        blockstore.insert_shreds(shreds, None, false).unwrap();
+        blockstore.set_roots(&[1, 2, 3]).unwrap();
+
+        assert_eq!(
+            ObjectArchiveService::archive_slots(&runtime, &ledger_archive, &blockstore, 1, 3),
+            Ok(1)
+        );
+        let state = runtime.block_on(ledger_archive.get_state()).unwrap();
+        assert_eq!(state.unwrap().last_slot, 1);
+        assert!(
+            ObjectArchiveService::archive_slots(&runtime, &ledger_archive, &blockstore, 2, 3)
+                .is_err()
+        );
+
+        blockstore
+            .insert_shreds(vec![first_shred], None, false)
+            .unwrap();
+        assert_eq!(
+            ObjectArchiveService::archive_slots(&runtime, &ledger_archive, &blockstore, 2, 3),
+            Ok(3)
+        );
+        for slot in 1..=3 {
+            assert!(runtime
+                .block_on(ledger_archive.get_confirmed_block(slot))
+                .unwrap()
+                .is_some());
+        }
+
+        drop(blockstore);
+        Blockstore::destroy(&ledger_path).unwrap();
+    }
+}
@@ -0,0 +1,311 @@
+//! The `packet_channel` module implements the bounded channels between the packet
+//! processing stages of the TPU and TVU. When a channel fills up it either blocks the
+//! sending stage or sheds load according to its `DropPolicy`, and it periodically reports
+//! how saturated it is.
+
+use crossbeam_channel::{bounded, Receiver, SendError, Sender, TrySendError};
+use solana_sdk::timing::timestamp;
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+// Capacities are in number of messages; for the packet channels a message is one batch of
+// up to `PACKETS_PER_BATCH` packets.
+pub const DEFAULT_PACKET_CHANNEL_CAPACITY: usize = 4096;
+const STATS_REPORT_INTERVAL_MS: u64 = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Block the sender until the receiver catches up
+    Block,
+    /// Drop the message being sent
+    DropNewest,
+    /// Drop the oldest queued message to make room for the one being sent
+    DropOldest,
+}
+
+impl FromStr for DropPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(Self::Block),
+            "drop-newest" => Ok(Self::DropNewest),
+            "drop-oldest" => Ok(Self::DropOldest),
+            _ => Err(format!("invalid drop policy: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChannelConfig {
+    pub capacity: usize,
+    pub drop_policy: DropPolicy,
+}
+
+impl ChannelConfig {
+    pub fn new(capacity: usize, drop_policy: DropPolicy) -> Self {
+        Self {
+            capacity,
+            drop_policy,
+        }
+    }
+}
+
+/// Channels of the TPU and TVU pipelines. The `*_packets` channels carry packets from the
+/// fetch stages to signature verification, the `*_verified` channels carry verified packets
+/// to the banking stage and the window service respectively.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PacketChannelsConfig {
+    pub tpu_packets: ChannelConfig,
+    pub tpu_verified: ChannelConfig,
+    pub tvu_packets: ChannelConfig,
+    pub tvu_verified: ChannelConfig,
+}
+
+impl PacketChannelsConfig {
+    /// Rejects zero capacities, which would make every channel a rendezvous channel that
+    /// drops or blocks on every send
+    pub fn verify(&self) -> Result<(), String> {
+        let channels = [
+            ("tpu_packets", &self.tpu_packets),
+            ("tpu_verified", &self.tpu_verified),
+            ("tvu_packets", &self.tvu_packets),
+            ("tvu_verified", &self.tvu_verified),
+        ];
+        for (name, config) in channels.iter() {
+            if config.capacity == 0 {
+                return Err(format!("{} channel capacity must be at least 1", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for PacketChannelsConfig {
+    fn default() -> Self {
+        // Shed load where packets enter the pipeline; past signature verification the
+        // packets have already been paid for, so stall verification instead.
+        let packets = ChannelConfig::new(DEFAULT_PACKET_CHANNEL_CAPACITY, DropPolicy::DropOldest);
+        let verified = ChannelConfig::new(DEFAULT_PACKET_CHANNEL_CAPACITY, DropPolicy::Block);
+        Self {
+            tpu_packets: packets,
+            tpu_verified: verified,
+            tvu_packets: packets,
+            tvu_verified: verified,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ChannelStats {
+    num_sent: AtomicUsize,
+    num_dropped: AtomicUsize,
+    num_blocked: AtomicUsize,
+    max_len: AtomicUsize,
+    last_report: AtomicU64,
+}
+
+pub struct BoundedSender<T> {
+    name: &'static str,
+    sender: Sender<T>,
+    // Held only with `DropPolicy::DropOldest`, to evict queued messages. Otherwise the
+    // sender must not keep the channel connected once the receiving stage has exited.
+    receiver: Option<Receiver<T>>,
+    config: ChannelConfig,
+    stats: Arc<ChannelStats>,
+}
+
+impl<T> Clone for BoundedSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            sender: self.sender.clone(),
+            receiver: self.receiver.clone(),
+            config: self.config,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+/// Creates a bounded channel; `name` identifies the channel in metrics.
+pub fn bounded_channel<T>(
+    name: &'static str,
+    config: &ChannelConfig,
+) -> (BoundedSender<T>, Receiver<T>) {
+    let (sender, receiver) = bounded(config.capacity);
+    let sender = BoundedSender {
+        name,
+        sender,
+        receiver: if config.drop_policy == DropPolicy::DropOldest {
+            Some(receiver.clone())
+        } else {
+            None
+        },
+        config: *config,
+        stats: Arc::default(),
+    };
+    (sender, receiver)
+}
+
+impl<T> BoundedSender<T> {
+    /// Sends `msg`, applying the drop policy if the channel is full. Dropped messages are not
+    /// an error; the error is only returned once the receiving side has disconnected.
+    pub fn send(&self, msg: T) -> Result<(), SendError<T>> {
+        let len = self.sender.len();
+        self.stats.max_len.fetch_max(len + 1, Ordering::Relaxed);
+        let result = match self.sender.try_send(msg) {
+            Ok(()) => {
+                self.stats.num_sent.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(msg)) => Err(SendError(msg)),
+            Err(TrySendError::Full(msg)) => self.send_full(msg),
+        };
+        self.maybe_report_stats();
+        result
+    }
+
+    fn send_full(&self, msg: T) -> Result<(), SendError<T>> {
+        match (self.config.drop_policy, &self.receiver) {
+            (DropPolicy::DropOldest, Some(receiver)) => {
+                if receiver.try_recv().is_ok() {
+                    self.stats.num_dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                match self.sender.try_send(msg) {
+                    Ok(()) => {
+                        self.stats.num_sent.fetch_add(1, Ordering::Relaxed);
+                        Ok(())
+                    }
+                    Err(TrySendError::Disconnected(msg)) => Err(SendError(msg)),
+                    // Lost the race to another sender, so drop this message instead.
+                    Err(TrySendError::Full(_)) => {
+                        self.stats.num_dropped.fetch_add(1, Ordering::Relaxed);
+                        Ok(())
+                    }
+                }
+            }
+            (DropPolicy::DropNewest, _) | (DropPolicy::DropOldest, None) => {
+                self.stats.num_dropped.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            (DropPolicy::Block, _) => {
+                self.stats.num_blocked.fetch_add(1, Ordering::Relaxed);
+                self.sender.send(msg)?;
+                self.stats.num_sent.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+        }
+    }
+
+    fn maybe_report_stats(&self) {
+        let now = timestamp();
+        let last_report = self.stats.last_report.load(Ordering::Relaxed);
+        if now.saturating_sub(last_report) < STATS_REPORT_INTERVAL_MS
+            || self
+                .stats
+                .last_report
+                .compare_exchange(last_report, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return;
+        }
+        let max_len = self.stats.max_len.swap(0, Ordering::Relaxed);
+        datapoint_info!(
+            self.name,
+            ("len", self.sender.len(), i64),
+            ("max_len", max_len, i64),
+            ("capacity", self.config.capacity, i64),
+            (
+                "saturation_pct",
+                max_len.min(self.config.capacity) * 100 / self.config.capacity.max(1),
+                i64
+            ),
+            (
+                "num_sent",
+                self.stats.num_sent.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "num_dropped",
+                self.stats.num_dropped.swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "num_blocked",
+                self.stats.num_blocked.swap(0, Ordering::Relaxed),
+                i64
+            ),
+        );
+    }
+
+    pub fn len(&self) -> usize {
+        self.sender.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sender.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_policy_from_str() {
+        assert_eq!("block".parse(), Ok(DropPolicy::Block));
+        assert_eq!("drop-newest".parse(), Ok(DropPolicy::DropNewest));
+        assert_eq!("drop-oldest".parse(), Ok(DropPolicy::DropOldest));
+        assert!("drop".parse::<DropPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_packet_channels_config_verify() {
+        let mut config = PacketChannelsConfig::default();
+        assert_eq!(config.verify(), Ok(()));
+        config.tvu_packets.capacity = 0;
+        assert!(config.verify().is_err());
+    }
+
+    #[test]
+    fn test_bounded_channel_drop_newest() {
+        let config = ChannelConfig::new(2, DropPolicy::DropNewest);
+        let (sender, receiver) = bounded_channel("test-drop-newest", &config);
+        for i in 0..4 {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(sender.len(), 2);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![0, 1]);
+        drop(receiver);
+        assert!(sender.send(4).is_err());
+    }
+
+    #[test]
+    fn test_bounded_channel_drop_oldest() {
+        let config = ChannelConfig::new(2, DropPolicy::DropOldest);
+        let (sender, receiver) = bounded_channel("test-drop-oldest", &config);
+        for i in 0..4 {
+            sender.send(i).unwrap();
+        }
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_bounded_channel_block() {
+        let config = ChannelConfig::new(1, DropPolicy::Block);
+        let (sender, receiver) = bounded_channel("test-block", &config);
+        sender.send(0).unwrap();
+        let sender_ = sender.clone();
+        let t = std::thread::spawn(move || sender_.send(1));
+        assert_eq!(receiver.recv(), Ok(0));
+        assert_eq!(receiver.recv(), Ok(1));
+        t.join().unwrap().unwrap();
+        drop(receiver);
+        assert!(sender.send(2).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 //! The `poh_service` module implements a service that records the passing of
 //! "ticks", a measure of time in the PoH stream
 use crate::poh_recorder::PohRecorder;
+use solana_rayon_threadlimit::thread_manager;
 use solana_sdk::poh_config::PohConfig;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -50,9 +51,12 @@ impl PohService {
                 } else {
                     // PoH service runs in a tight loop, generating hashes as fast as possible.
                     // Let's dedicate one of the CPU cores to this thread so that it can gain
-                    // from cache performance.
-                    if let Some(cores) = core_affinity::get_core_ids() {
-                        core_affinity::set_for_current(cores[pinned_cpu_core]);
+                    // from cache performance. A core configured for the "solana-poh" thread
+                    // group takes precedence over `pinned_cpu_core`.
+                    if !thread_manager::pin_current_thread("solana-poh", 0) {
+                        if let Some(cores) = core_affinity::get_core_ids() {
+                            core_affinity::set_for_current(cores[pinned_cpu_core]);
+                        }
                     }
                     // Account for some extra time outside of PoH generation to account
                     // for processing time outside PoH.
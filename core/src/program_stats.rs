@@ -0,0 +1,257 @@
+//! The `program_stats` module keeps a rolling aggregate of per-program invocation counts,
+//! failures and compute units consumed, built from the transaction statuses of the most
+//! recently processed slots, for the `getProgramStats` RPC method.
+
+use solana_sdk::{
+    clock::Slot,
+    message::Message,
+    pubkey::Pubkey,
+    transaction::{Result, TransactionError},
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+/// Number of most recent slots aggregated, about 7 minutes worth of slots
+pub const DEFAULT_PROGRAM_STATS_WINDOW_SLOTS: Slot = 1_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProgramStatsEntry {
+    /// Top-level instructions that invoked the program
+    pub invocations: u64,
+    /// Invocations that failed the transaction
+    pub failures: u64,
+    /// Compute units consumed over all the invocations that reported them
+    pub compute_units: u64,
+    /// Number of invocations that reported the compute units they consumed
+    pub compute_samples: u64,
+}
+
+impl ProgramStatsEntry {
+    fn accumulate(&mut self, other: &ProgramStatsEntry) {
+        self.invocations += other.invocations;
+        self.failures += other.failures;
+        self.compute_units += other.compute_units;
+        self.compute_samples += other.compute_samples;
+    }
+
+    pub fn average_compute_units(&self) -> Option<u64> {
+        self.compute_units.checked_div(self.compute_samples)
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.invocations == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.invocations as f64
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ProgramStats {
+    window_slots: Slot,
+    slots: BTreeMap<Slot, HashMap<Pubkey, ProgramStatsEntry>>,
+}
+
+impl Default for ProgramStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROGRAM_STATS_WINDOW_SLOTS)
+    }
+}
+
+impl ProgramStats {
+    pub fn new(window_slots: Slot) -> Self {
+        Self {
+            window_slots: window_slots.max(1),
+            slots: BTreeMap::new(),
+        }
+    }
+
+    /// Accounts for a transaction processed in `slot` with the given status and log messages
+    pub fn record_transaction(
+        &mut self,
+        slot: Slot,
+        message: &Message,
+        status: &Result<()>,
+        log_messages: &[String],
+    ) {
+        if let Some(first_slot) = self.first_slot() {
+            if slot < first_slot && self.slots.len() as u64 >= self.window_slots {
+                return;
+            }
+        }
+        let program_stats = self.slots.entry(slot).or_default();
+
+        // Instructions following the one that failed are never executed
+        let (num_executed, failed_index) = match status {
+            Err(TransactionError::InstructionError(index, _)) => {
+                (*index as usize + 1, Some(*index as usize))
+            }
+            _ => (message.instructions.len(), None),
+        };
+        for (index, instruction) in message.instructions.iter().take(num_executed).enumerate() {
+            let entry = program_stats
+                .entry(*instruction.program_id(&message.account_keys))
+                .or_default();
+            entry.invocations += 1;
+            if failed_index == Some(index) {
+                entry.failures += 1;
+            }
+        }
+
+        for (program_id, compute_units) in log_messages
+            .iter()
+            .filter_map(|log| parse_compute_units_consumed(log))
+        {
+            let entry = program_stats.entry(program_id).or_default();
+            entry.compute_units += compute_units;
+            entry.compute_samples += 1;
+        }
+
+        self.prune();
+    }
+
+    fn prune(&mut self) {
+        while self.slots.len() as u64 > self.window_slots {
+            let first_slot = *self.slots.keys().next().unwrap();
+            self.slots.remove(&first_slot);
+        }
+    }
+
+    pub fn first_slot(&self) -> Option<Slot> {
+        self.slots.keys().next().cloned()
+    }
+
+    pub fn last_slot(&self) -> Option<Slot> {
+        self.slots.keys().next_back().cloned()
+    }
+
+    /// Stats of every program invoked within the window
+    pub fn programs(&self) -> HashMap<Pubkey, ProgramStatsEntry> {
+        let mut programs: HashMap<Pubkey, ProgramStatsEntry> = HashMap::new();
+        for program_stats in self.slots.values() {
+            for (program_id, entry) in program_stats {
+                programs.entry(*program_id).or_default().accumulate(entry);
+            }
+        }
+        programs
+    }
+
+    pub fn program(&self, program_id: &Pubkey) -> Option<ProgramStatsEntry> {
+        let mut stats: Option<ProgramStatsEntry> = None;
+        for entry in self
+            .slots
+            .values()
+            .filter_map(|program_stats| program_stats.get(program_id))
+        {
+            stats
+                .get_or_insert_with(ProgramStatsEntry::default)
+                .accumulate(entry);
+        }
+        stats
+    }
+}
+
+/// Parses the "Program <id> consumed <n> of <m> compute units" log message the BPF loader
+/// emits at the end of each invocation
+fn parse_compute_units_consumed(log: &str) -> Option<(Pubkey, u64)> {
+    let mut words = log.split_whitespace();
+    if words.next()? != "Program" {
+        return None;
+    }
+    let program_id = Pubkey::from_str(words.next()?).ok()?;
+    if words.next()? != "consumed" {
+        return None;
+    }
+    let compute_units = words.next()?.parse().ok()?;
+    Some((program_id, compute_units))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::{Instruction, InstructionError};
+
+    fn message(program_ids: &[Pubkey]) -> Message {
+        let instructions: Vec<_> = program_ids
+            .iter()
+            .map(|program_id| Instruction::new(*program_id, &0, vec![]))
+            .collect();
+        Message::new(&instructions, Some(&Pubkey::new_unique()))
+    }
+
+    #[test]
+    fn test_parse_compute_units_consumed() {
+        let program_id = Pubkey::new_unique();
+        assert_eq!(
+            parse_compute_units_consumed(&format!(
+                "Program {} consumed 1234 of 200000 compute units",
+                program_id
+            )),
+            Some((program_id, 1234))
+        );
+        assert_eq!(
+            parse_compute_units_consumed(&format!("Program {} invoke [1]", program_id)),
+            None
+        );
+        assert_eq!(parse_compute_units_consumed("Program log: hello"), None);
+    }
+
+    #[test]
+    fn test_record_transaction() {
+        let program_a = Pubkey::new_unique();
+        let program_b = Pubkey::new_unique();
+        let mut program_stats = ProgramStats::new(2);
+
+        let logs = vec![
+            format!("Program {} invoke [1]", program_a),
+            format!("Program {} consumed 100 of 200000 compute units", program_a),
+            format!("Program {} success", program_a),
+        ];
+        program_stats.record_transaction(1, &message(&[program_a, program_b]), &Ok(()), &logs);
+
+        // program_a fails, so program_b is never invoked
+        let logs = vec![format!(
+            "Program {} consumed 300 of 200000 compute units",
+            program_a
+        )];
+        let status = Err(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(0),
+        ));
+        program_stats.record_transaction(2, &message(&[program_a, program_b]), &status, &logs);
+
+        let stats_a = program_stats.program(&program_a).unwrap();
+        assert_eq!(
+            stats_a,
+            ProgramStatsEntry {
+                invocations: 2,
+                failures: 1,
+                compute_units: 400,
+                compute_samples: 2,
+            }
+        );
+        assert_eq!(stats_a.average_compute_units(), Some(200));
+        assert!((stats_a.error_rate() - 0.5).abs() < f64::EPSILON);
+
+        let stats_b = program_stats.program(&program_b).unwrap();
+        assert_eq!(stats_b.invocations, 1);
+        assert_eq!(stats_b.failures, 0);
+        assert_eq!(stats_b.average_compute_units(), None);
+        assert_eq!(program_stats.programs().len(), 2);
+
+        // Slot 1 falls out of the window
+        program_stats.record_transaction(3, &message(&[program_b]), &Ok(()), &[]);
+        assert_eq!(program_stats.first_slot(), Some(2));
+        assert_eq!(program_stats.last_slot(), Some(3));
+        assert_eq!(program_stats.program(&program_a).unwrap().invocations, 1);
+        assert_eq!(program_stats.program(&program_b).unwrap().invocations, 1);
+
+        // Slots older than the window are ignored
+        program_stats.record_transaction(1, &message(&[program_b]), &Ok(()), &[]);
+        assert_eq!(program_stats.first_slot(), Some(2));
+        assert_eq!(program_stats.program(&program_b).unwrap().invocations, 1);
+    }
+}
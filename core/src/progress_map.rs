@@ -14,6 +14,9 @@ use std::{
     sync::{Arc, RwLock},
 };
 
+// Only the programs that took the longest to execute in a slot are reported
+const MAX_REPORTED_PROGRAM_TIMINGS: usize = 10;
+
 type VotedSlot = Slot;
 type ExpirationSlot = Slot;
 pub(crate) type LockoutIntervals = BTreeMap<ExpirationSlot, Vec<(VotedSlot, Rc<Pubkey>)>>;
@@ -61,10 +64,31 @@ impl ReplaySlotStats {
             ),
             ("total_entries", num_entries as i64, i64),
             ("total_shreds", num_shreds as i64, i64),
+            ("lock_us", self.execute_timings.lock_us, i64),
             ("load_us", self.execute_timings.load_us, i64),
             ("execute_us", self.execute_timings.execute_us, i64),
             ("store_us", self.execute_timings.store_us, i64),
         );
+
+        let mut per_program_timings: Vec<_> = self
+            .execute_timings
+            .details
+            .per_program_timings
+            .iter()
+            .collect();
+        per_program_timings.sort_by(|a, b| b.1.accumulated_us.cmp(&a.1.accumulated_us));
+        for (program_id, program_timing) in per_program_timings
+            .into_iter()
+            .take(MAX_REPORTED_PROGRAM_TIMINGS)
+        {
+            datapoint_info!(
+                "replay-slot-program-timings",
+                ("slot", slot as i64, i64),
+                ("program_id", program_id.to_string(), String),
+                ("execute_us", program_timing.accumulated_us, i64),
+                ("count", program_timing.count, i64),
+            );
+        }
     }
 }
 
@@ -4,6 +4,7 @@ use crate::{
     cluster_info::ClusterInfo,
     cluster_info_vote_listener::VerifiedVoteReceiver,
     cluster_slots::ClusterSlots,
+    repair_status::RepairStatus,
     repair_weight::RepairWeight,
     result::Result,
     serve_repair::{RepairType, ServeRepair, DEFAULT_NONCE},
@@ -110,6 +111,7 @@ pub struct RepairInfo {
     pub epoch_schedule: EpochSchedule,
     pub duplicate_slots_reset_sender: DuplicateSlotsResetSender,
     pub repair_validators: Option<HashSet<Pubkey>>,
+    pub repair_status: Arc<RwLock<RepairStatus>>,
 }
 
 pub struct RepairSlotRange {
@@ -261,7 +263,9 @@ impl RepairService {
 
             let mut cache = HashMap::new();
             let mut send_repairs_elapsed = Measure::start("send_repairs_elapsed");
+            let mut requested_slots = Vec::with_capacity(repairs.len());
             repairs.into_iter().for_each(|repair_request| {
+                let slot = repair_request.slot();
                 if let Ok((to, req)) = serve_repair.repair_request(
                     &cluster_slots,
                     repair_request,
@@ -269,12 +273,18 @@ impl RepairService {
                     &mut repair_stats,
                     &repair_info.repair_validators,
                 ) {
-                    repair_socket.send_to(&req, to).unwrap_or_else(|e| {
-                        info!("{} repair req send_to({}) error {:?}", id, to, e);
-                        0
-                    });
+                    match repair_socket.send_to(&req, to) {
+                        Ok(_) => requested_slots.push(slot),
+                        Err(e) => info!("{} repair req send_to({}) error {:?}", id, to, e),
+                    }
                 }
             });
+            if !requested_slots.is_empty() {
+                let mut repair_status = repair_info.repair_status.write().unwrap();
+                for slot in requested_slots {
+                    repair_status.record_request(slot);
+                }
+            }
             send_repairs_elapsed.stop();
             repair_timing.update(
                 set_root_elapsed.as_us(),
@@ -309,6 +319,11 @@ impl RepairService {
                     datapoint_info!(
                         "serve_repair-repair",
                         ("repair-total", repair_total, i64),
+                        (
+                            "repairs-per-second",
+                            repair_total as f64 / last_stats.elapsed().as_secs_f64(),
+                            f64
+                        ),
                         ("shred-count", repair_stats.shred.count, i64),
                         ("highest-shred-count", repair_stats.highest_shred.count, i64),
                         ("orphan-count", repair_stats.orphan.count, i64),
@@ -0,0 +1,150 @@
+//! The `repair_status` module keeps track of the repair requests sent for each of the most
+//! recently repaired slots and of the peers that served them, for the `getRepairStatus` RPC
+//! method.
+
+use solana_sdk::clock::Slot;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+};
+
+/// Number of most recent slots tracked
+pub const DEFAULT_REPAIR_STATUS_WINDOW_SLOTS: Slot = 256;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SlotRepairStatus {
+    /// Repair requests sent for the slot
+    pub requests: u64,
+    /// Repair responses received for the slot
+    pub responses: u64,
+    /// Number of repair responses received by serving peer address
+    pub served_from: HashMap<SocketAddr, u64>,
+}
+
+impl SlotRepairStatus {
+    /// Requests that have not been answered yet
+    pub fn outstanding(&self) -> u64 {
+        self.requests.saturating_sub(self.responses)
+    }
+}
+
+#[derive(Debug)]
+pub struct RepairStatus {
+    window_slots: Slot,
+    slots: BTreeMap<Slot, SlotRepairStatus>,
+}
+
+impl Default for RepairStatus {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPAIR_STATUS_WINDOW_SLOTS)
+    }
+}
+
+impl RepairStatus {
+    pub fn new(window_slots: Slot) -> Self {
+        Self {
+            window_slots: window_slots.max(1),
+            slots: BTreeMap::new(),
+        }
+    }
+
+    pub fn record_request(&mut self, slot: Slot) {
+        if let Some(status) = self.slot_mut(slot) {
+            status.requests += 1;
+        }
+    }
+
+    pub fn record_response(&mut self, slot: Slot, from: SocketAddr) {
+        if let Some(status) = self.slot_mut(slot) {
+            status.responses += 1;
+            *status.served_from.entry(from).or_default() += 1;
+        }
+    }
+
+    fn slot_mut(&mut self, slot: Slot) -> Option<&mut SlotRepairStatus> {
+        if let Some(first_slot) = self.first_slot() {
+            if slot < first_slot && self.slots.len() as u64 >= self.window_slots {
+                return None;
+            }
+        }
+        if !self.slots.contains_key(&slot) {
+            self.slots.insert(slot, SlotRepairStatus::default());
+            self.prune();
+        }
+        self.slots.get_mut(&slot)
+    }
+
+    fn prune(&mut self) {
+        while self.slots.len() as u64 > self.window_slots {
+            let first_slot = *self.slots.keys().next().unwrap();
+            self.slots.remove(&first_slot);
+        }
+    }
+
+    pub fn first_slot(&self) -> Option<Slot> {
+        self.slots.keys().next().cloned()
+    }
+
+    pub fn slot(&self, slot: Slot) -> Option<&SlotRepairStatus> {
+        self.slots.get(&slot)
+    }
+
+    /// Tracked slots, most recent first
+    pub fn slots(&self) -> impl Iterator<Item = (&Slot, &SlotRepairStatus)> {
+        self.slots.iter().rev()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_requests_and_responses() {
+        let mut repair_status = RepairStatus::default();
+        let peer = SocketAddr::from(([127, 0, 0, 1], 8008));
+        let other_peer = SocketAddr::from(([127, 0, 0, 2], 8008));
+        repair_status.record_request(5);
+        repair_status.record_request(5);
+        repair_status.record_request(5);
+        repair_status.record_response(5, peer);
+        repair_status.record_response(5, other_peer);
+        repair_status.record_response(5, peer);
+        repair_status.record_request(7);
+
+        let status = repair_status.slot(5).unwrap();
+        assert_eq!(status.requests, 3);
+        assert_eq!(status.responses, 3);
+        assert_eq!(status.outstanding(), 0);
+        assert_eq!(status.served_from[&peer], 2);
+        assert_eq!(status.served_from[&other_peer], 1);
+        assert_eq!(repair_status.slot(7).unwrap().outstanding(), 1);
+        assert_eq!(
+            repair_status
+                .slots()
+                .map(|(slot, _)| *slot)
+                .collect::<Vec<_>>(),
+            vec![7, 5]
+        );
+
+        // More responses than requests, e.g. from requests sent before the window moved
+        repair_status.record_response(7, peer);
+        repair_status.record_response(7, peer);
+        assert_eq!(repair_status.slot(7).unwrap().outstanding(), 0);
+    }
+
+    #[test]
+    fn test_window() {
+        let mut repair_status = RepairStatus::new(2);
+        repair_status.record_request(1);
+        repair_status.record_request(2);
+        repair_status.record_request(3);
+        assert_eq!(repair_status.first_slot(), Some(2));
+        assert!(repair_status.slot(1).is_none());
+
+        // Slots older than the window are ignored once it is full
+        repair_status.record_request(1);
+        assert!(repair_status.slot(1).is_none());
+        assert_eq!(repair_status.slots().count(), 2);
+    }
+}
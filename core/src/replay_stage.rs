@@ -8,6 +8,7 @@ use crate::{
     cluster_slots::ClusterSlots,
     commitment_service::{AggregateCommitmentService, CommitmentAggregationData},
     consensus::{ComputedBankState, Stake, SwitchForkDecision, Tower, VotedStakes},
+    epoch_events::{epoch_boundary_info, epoch_rewards},
     fork_choice::{ForkChoice, SelectVoteAndResetForkResult},
     heaviest_subtree_fork_choice::HeaviestSubtreeForkChoice,
     optimistically_confirmed_bank_tracker::{BankNotification, BankNotificationSender},
@@ -15,6 +16,7 @@ use crate::{
     progress_map::{ForkProgress, ProgressMap, PropagatedStats},
     pubkey_references::PubkeyReferences,
     repair_service::DuplicateSlotsResetReceiver,
+    replica_sync_service::{ReplicaSyncRoot, ReplicaSyncSender},
     result::Result,
     rewards_recorder_service::RewardsRecorderSender,
     rpc_subscriptions::RpcSubscriptions,
@@ -25,6 +27,7 @@ use solana_ledger::{
     blockstore_processor::{self, BlockstoreProcessorError, TransactionStatusSender},
     entry::VerifyRecyclers,
     leader_schedule_cache::LeaderScheduleCache,
+    signing_journal::SigningJournal,
 };
 use solana_measure::{measure::Measure, thread_mem_usage};
 use solana_metrics::inc_new_counter_info;
@@ -106,6 +109,16 @@ pub struct ReplayStageConfig {
     pub rewards_recorder_sender: Option<RewardsRecorderSender>,
     pub cache_block_time_sender: Option<CacheBlockTimeSender>,
     pub bank_notification_sender: Option<BankNotificationSender>,
+    pub block_production_disabled: bool,
+    pub replica_sync_sender: Option<ReplicaSyncSender>,
+    pub block_production_paused: Option<Arc<AtomicBool>>,
+    /// Set while another instance running with this node's identity is seen in gossip, halting
+    /// both block production and voting
+    pub identity_conflict: Option<Arc<AtomicBool>>,
+    /// Verify PoH on the GPU when the perf libs are loaded
+    pub poh_verify_gpu: bool,
+    /// Records every vote and tower signed
+    pub signing_journal: Option<Arc<SigningJournal>>,
 }
 
 #[derive(Default)]
@@ -266,6 +279,12 @@ impl ReplayStage {
             rewards_recorder_sender,
             cache_block_time_sender,
             bank_notification_sender,
+            block_production_disabled,
+            replica_sync_sender,
+            block_production_paused,
+            identity_conflict,
+            poh_verify_gpu,
+            signing_journal,
         } = config;
 
         trace!("replay stage");
@@ -331,6 +350,7 @@ impl ReplayStage {
                         &mut progress,
                         transaction_status_sender.clone(),
                         &verify_recyclers,
+                        poh_verify_gpu,
                         &mut heaviest_subtree_fork_choice,
                         &replay_vote_sender,
                         &bank_notification_sender,
@@ -339,6 +359,15 @@ impl ReplayStage {
                     replay_active_banks_time.stop();
                     Self::report_memory(&allocated, "replay_active_banks", start);
 
+                    let leader_bank = poh_recorder.lock().unwrap().bank();
+                    Self::prune_dead_forks(
+                        &bank_forks,
+                        &tower,
+                        &mut progress,
+                        &mut heaviest_subtree_fork_choice,
+                        leader_bank.as_ref(),
+                    );
+
                     let mut reset_duplicate_slots_time = Measure::start("reset_duplicate_slots");
                     let mut ancestors = bank_forks.read().unwrap().ancestors();
                     let mut descendants = bank_forks.read().unwrap().descendants();
@@ -452,6 +481,10 @@ impl ReplayStage {
                     let start = allocated.get();
 
                     let mut voting_time = Measure::start("voting_time");
+                    let duplicate_identity = identity_conflict
+                        .as_ref()
+                        .map(|conflict| conflict.load(Ordering::Relaxed))
+                        .unwrap_or(false);
                     // Vote on a fork
                     if let Some((ref vote_bank, ref switch_fork_decision)) = vote_bank {
                         if let Some(votable_leader) =
@@ -472,7 +505,12 @@ impl ReplayStage {
                             &mut tower,
                             &mut progress,
                             &vote_account,
-                            &authorized_voter_keypairs,
+                            // The tower still records the vote, only its transmission is halted
+                            if duplicate_identity {
+                                &[][..]
+                            } else {
+                                &authorized_voter_keypairs[..]
+                            },
                             &cluster_info,
                             &blockstore,
                             &leader_schedule_cache,
@@ -485,6 +523,8 @@ impl ReplayStage {
                             &mut heaviest_subtree_fork_choice,
                             &cache_block_time_sender,
                             &bank_notification_sender,
+                            &replica_sync_sender,
+                            &signing_journal,
                         );
                     };
                     voting_time.stop();
@@ -566,7 +606,7 @@ impl ReplayStage {
 
                     let start = allocated.get();
                     let mut start_leader_time = Measure::start("start_leader_time");
-                    if !tpu_has_bank {
+                    if !tpu_has_bank && !block_production_disabled {
                         Self::maybe_start_leader(
                             &my_pubkey,
                             &bank_forks,
@@ -576,6 +616,11 @@ impl ReplayStage {
                             &progress,
                             &retransmit_slots_sender,
                             &mut skipped_slots_info,
+                            duplicate_identity
+                                || block_production_paused
+                                    .as_ref()
+                                    .map(|paused| paused.load(Ordering::Relaxed))
+                                    .unwrap_or(false),
                         );
 
                         let poh_bank = poh_recorder.lock().unwrap().bank();
@@ -771,6 +816,49 @@ impl ReplayStage {
         }
     }
 
+    // Prune the forks off the heaviest fork that BankForks considers dead, along with their
+    // progress and fork choice state.  Forks voted on are kept, whether by this node's tower,
+    // by the latest votes of fork choice or by any vote still in the towers of their banks.
+    // So is the fork of the bank this node is producing, which fork choice may have moved off
+    fn prune_dead_forks(
+        bank_forks: &RwLock<BankForks>,
+        tower: &Tower,
+        progress: &mut ProgressMap,
+        heaviest_subtree_fork_choice: &mut HeaviestSubtreeForkChoice,
+        leader_bank: Option<&Arc<Bank>>,
+    ) {
+        let tower_slots: HashSet<Slot> = tower.voted_slots().into_iter().collect();
+        let best_slot = heaviest_subtree_fork_choice.best_overall_slot();
+        let pruned_slots = bank_forks
+            .write()
+            .unwrap()
+            .prune_dead_forks(best_slot, |fork| {
+                fork.iter().any(|slot| {
+                    leader_bank.map_or(false, |leader_bank| {
+                        leader_bank.slot() == *slot || leader_bank.ancestors.contains_key(slot)
+                    }) || tower_slots.contains(slot)
+                        || heaviest_subtree_fork_choice
+                            .stake_voted_at(*slot)
+                            .map_or(false, |stake| stake > 0)
+                        || progress.get_fork_stats(*slot).map_or(false, |fork_stats| {
+                            fork_stats
+                                .lockout_intervals
+                                .values()
+                                .flatten()
+                                .any(|(voted_slot, _)| fork.contains(voted_slot))
+                        })
+                })
+            });
+        if pruned_slots.is_empty() {
+            return;
+        }
+        warn!("pruned dead forks, slots: {:?}", pruned_slots);
+        for slot in &pruned_slots {
+            progress.remove(slot);
+        }
+        heaviest_subtree_fork_choice.purge_slots(&pruned_slots);
+    }
+
     // Purge given slot and all its descendants from the `ancestors` and
     // `descendants` structures so that they're consistent with `BankForks`
     // and the `progress` map.
@@ -882,6 +970,7 @@ impl ReplayStage {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn maybe_start_leader(
         my_pubkey: &Pubkey,
         bank_forks: &Arc<RwLock<BankForks>>,
@@ -891,6 +980,7 @@ impl ReplayStage {
         progress_map: &ProgressMap,
         retransmit_slots_sender: &RetransmitSlotsSender,
         skipped_slots_info: &mut SkippedSlotsInfo,
+        block_production_paused: bool,
     ) {
         // all the individual calls to poh_recorder.lock() are designed to
         // increase granularity, decrease contention
@@ -945,6 +1035,21 @@ impl ReplayStage {
                 ("leader", next_leader.to_string(), String),
             );
 
+            if block_production_paused {
+                if poh_slot != skipped_slots_info.last_skipped_slot {
+                    warn!(
+                        "{} skipping leader slot {}, block production is paused",
+                        my_pubkey, poh_slot
+                    );
+                    datapoint_info!(
+                        "replay_stage-skip_leader_slot_paused",
+                        ("slot", poh_slot, i64),
+                    );
+                    skipped_slots_info.last_skipped_slot = poh_slot;
+                }
+                return;
+            }
+
             if !Self::check_propagation_for_start_leader(poh_slot, parent_slot, progress_map) {
                 let latest_unconfirmed_leader_slot = progress_map.get_latest_leader_slot(parent_slot).expect("In order for propagated check to fail, latest leader must exist in progress map");
                 if poh_slot != skipped_slots_info.last_skipped_slot {
@@ -1003,6 +1108,7 @@ impl ReplayStage {
         transaction_status_sender: Option<TransactionStatusSender>,
         replay_vote_sender: &ReplayVoteSender,
         verify_recyclers: &VerifyRecyclers,
+        poh_verify_gpu: bool,
     ) -> result::Result<usize, BlockstoreProcessorError> {
         let tx_count_before = bank_progress.replay_progress.num_txs;
         let confirm_result = blockstore_processor::confirm_slot(
@@ -1011,6 +1117,7 @@ impl ReplayStage {
             &mut bank_progress.replay_stats,
             &mut bank_progress.replay_progress,
             false,
+            poh_verify_gpu,
             transaction_status_sender,
             Some(replay_vote_sender),
             None,
@@ -1083,6 +1190,8 @@ impl ReplayStage {
         heaviest_subtree_fork_choice: &mut HeaviestSubtreeForkChoice,
         cache_block_time_sender: &Option<CacheBlockTimeSender>,
         bank_notification_sender: &Option<BankNotificationSender>,
+        replica_sync_sender: &Option<ReplicaSyncSender>,
+        signing_journal: &Option<Arc<SigningJournal>>,
     ) {
         if bank.is_empty() {
             inc_new_counter_info!("replay_stage-voted_empty_bank", 1);
@@ -1092,9 +1201,21 @@ impl ReplayStage {
         let new_root = tower.record_bank_vote(vote);
         let last_vote = tower.last_vote_and_timestamp();
 
-        if let Err(err) = tower.save(&cluster_info.keypair) {
-            error!("Unable to save tower: {:?}", err);
-            std::process::exit(1);
+        match tower.save(&cluster_info.keypair) {
+            Ok(signature) => {
+                if let Some(signing_journal) = signing_journal {
+                    signing_journal.record_tower(
+                        bank.slot(),
+                        bank.hash(),
+                        signature,
+                        cluster_info.id(),
+                    );
+                }
+            }
+            Err(err) => {
+                error!("Unable to save tower: {:?}", err);
+                std::process::exit(1);
+            }
         }
 
         if let Some(new_root) = new_root {
@@ -1122,6 +1243,23 @@ impl ReplayStage {
                 &rooted_slots,
                 cache_block_time_sender,
             );
+            if let Some(sender) = replica_sync_sender {
+                // The replica sync service drops its replicas when it misses a root, rather than
+                // holding up replay
+                let prior_root = bank_forks.read().unwrap().root();
+                if let Err(err) = sender.try_send(ReplicaSyncRoot {
+                    rooted_banks: rooted_banks.clone(),
+                    prior_root,
+                }) {
+                    inc_new_counter_info!("replay_stage-replica_sync_root_dropped", 1);
+                    warn!("replica_sync_sender failed: {:?}", err);
+                }
+            }
+            let epoch_boundary =
+                epoch_boundary_info(&bank_forks.read().unwrap().root_bank(), &root_bank);
+            let block_rewards = epoch_boundary
+                .as_ref()
+                .and_then(|epoch_boundary| epoch_rewards(&rooted_banks, epoch_boundary.epoch));
             let highest_confirmed_root = Some(
                 block_commitment_cache
                     .read()
@@ -1138,6 +1276,16 @@ impl ReplayStage {
                 heaviest_subtree_fork_choice,
             );
             subscriptions.notify_roots(rooted_slots);
+            if let Some(epoch_boundary) = epoch_boundary {
+                info!(
+                    "new root {} crossed into epoch {}",
+                    new_root, epoch_boundary.epoch
+                );
+                subscriptions.notify_epoch_boundary(epoch_boundary);
+            }
+            if let Some(block_rewards) = block_rewards {
+                subscriptions.notify_epoch_rewards(block_rewards);
+            }
             if let Some(sender) = bank_notification_sender {
                 sender
                     .send(BankNotification::Root(root_bank))
@@ -1165,9 +1313,11 @@ impl ReplayStage {
             last_vote,
             &tower_slots,
             switch_fork_decision,
+            signing_journal,
         );
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn push_vote(
         cluster_info: &ClusterInfo,
         bank: &Arc<Bank>,
@@ -1176,6 +1326,7 @@ impl ReplayStage {
         vote: Vote,
         tower: &[Slot],
         switch_fork_decision: &SwitchForkDecision,
+        signing_journal: &Option<Arc<SigningJournal>>,
     ) {
         if authorized_voter_keypairs.is_empty() {
             return;
@@ -1248,6 +1399,15 @@ impl ReplayStage {
         let blockhash = bank.last_blockhash();
         vote_tx.partial_sign(&[node_keypair.as_ref()], blockhash);
         vote_tx.partial_sign(&[authorized_voter_keypair.as_ref()], blockhash);
+        if let Some(signing_journal) = signing_journal {
+            signing_journal.record_vote(
+                bank.slot(),
+                bank.hash(),
+                vote_tx.signatures[0],
+                node_keypair.pubkey(),
+                authorized_voter_pubkey,
+            );
+        }
         let _ = cluster_info.send_vote(&vote_tx);
         cluster_info.push_vote(tower, vote_tx);
     }
@@ -1308,6 +1468,7 @@ impl ReplayStage {
         progress: &mut ProgressMap,
         transaction_status_sender: Option<TransactionStatusSender>,
         verify_recyclers: &VerifyRecyclers,
+        poh_verify_gpu: bool,
         heaviest_subtree_fork_choice: &mut HeaviestSubtreeForkChoice,
         replay_vote_sender: &ReplayVoteSender,
         bank_notification_sender: &Option<BankNotificationSender>,
@@ -1359,6 +1520,7 @@ impl ReplayStage {
                     transaction_status_sender.clone(),
                     replay_vote_sender,
                     verify_recyclers,
+                    poh_verify_gpu,
                 );
                 match replay_result {
                     Ok(replay_tx_count) => tx_count += replay_tx_count,
@@ -1907,6 +2069,12 @@ impl ReplayStage {
                     trace!("child already active or frozen {}", child_slot);
                     continue;
                 }
+                if forks.is_pruned(child_slot)
+                    && !Self::is_pruned_fork_extended(blockstore, &forks, child_slot)
+                {
+                    trace!("child pruned as a dead fork {}", child_slot);
+                    continue;
+                }
                 let leader = leader_schedule_cache
                     .slot_leader_at(child_slot, Some(&parent_bank))
                     .unwrap();
@@ -1943,6 +2111,25 @@ impl ReplayStage {
         }
     }
 
+    // Whether a slot that isn't pruned chains to the pruned `slot` in blockstore, as when the
+    // cluster keeps building on a fork pruned as dead.  Votes on a slot only land in blocks
+    // descending from it, so this is also how new votes on the fork show up
+    fn is_pruned_fork_extended(blockstore: &Blockstore, forks: &BankForks, slot: Slot) -> bool {
+        let mut pruned_slots = vec![slot];
+        while let Some(pruned_slot) = pruned_slots.pop() {
+            let next_slots = blockstore
+                .get_slots_since(&[pruned_slot])
+                .expect("Db error");
+            for child_slot in next_slots.into_iter().flat_map(|(_, children)| children) {
+                if !forks.is_pruned(child_slot) {
+                    return true;
+                }
+                pruned_slots.push(child_slot);
+            }
+        }
+        false
+    }
+
     fn new_bank_from_parent_with_notify(
         parent: &Arc<Bank>,
         slot: u64,
@@ -2038,6 +2225,7 @@ pub(crate) mod tests {
     };
     use solana_runtime::{
         accounts_background_service::ABSRequestSender,
+        bank_forks::BankForksPruningConfig,
         commitment::BlockCommitment,
         genesis_utils::{self, GenesisConfigInfo, ValidatorVoteKeypairs},
     };
@@ -2152,6 +2340,111 @@ pub(crate) mod tests {
         }
     }
 
+    #[test]
+    fn test_generate_new_bank_forks_pruned_slots() {
+        let ReplayBlockstoreComponents {
+            blockstore,
+            mut progress,
+            bank_forks,
+            leader_schedule_cache,
+            rpc_subscriptions,
+            ..
+        } = replay_blockstore_components();
+        let generate_new_bank_forks = |progress: &mut ProgressMap| {
+            ReplayStage::generate_new_bank_forks(
+                &blockstore,
+                &bank_forks,
+                &leader_schedule_cache,
+                &rpc_subscriptions,
+                progress,
+                &mut PubkeyReferences::default(),
+            )
+        };
+
+        // Slots 1 and 2 both chain to the root
+        for slot in 1..3 {
+            let (shreds, _) = make_slot_entries(slot, 0, 8);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+        }
+        generate_new_bank_forks(&mut progress);
+        bank_forks
+            .write()
+            .unwrap()
+            .set_pruning_config(BankForksPruningConfig {
+                max_fork_depth: None,
+                max_bank_count: Some(1),
+            });
+        assert_eq!(
+            bank_forks.write().unwrap().prune_dead_forks(2, |_| false),
+            vec![1]
+        );
+
+        // Pruned slots are not created again...
+        generate_new_bank_forks(&mut progress);
+        assert!(bank_forks.read().unwrap().get(1).is_none());
+        assert!(bank_forks.read().unwrap().is_pruned(1));
+
+        // ...until the cluster builds on them
+        let (shreds, _) = make_slot_entries(3, 1, 8);
+        blockstore.insert_shreds(shreds, None, false).unwrap();
+        generate_new_bank_forks(&mut progress);
+        assert!(bank_forks.read().unwrap().get(1).is_some());
+        assert!(!bank_forks.read().unwrap().is_pruned(1));
+    }
+
+    #[test]
+    fn test_prune_dead_forks_keeps_leader_fork() {
+        let genesis_config = create_genesis_config(10_000).genesis_config;
+        let bank0 = Bank::new(&genesis_config);
+        let bank_forks = RwLock::new(BankForks::new(bank0));
+        let bank0 = bank_forks.read().unwrap().get(0).unwrap().clone();
+        bank_forks
+            .write()
+            .unwrap()
+            .set_pruning_config(BankForksPruningConfig {
+                max_fork_depth: None,
+                max_bank_count: Some(2),
+            });
+
+        // This node's leader slot 3 is built on the root while it is the best slot...
+        let leader_bank = bank_forks.write().unwrap().insert(Bank::new_from_parent(
+            &bank0,
+            &Pubkey::default(),
+            3,
+        ));
+        let mut progress = ProgressMap::default();
+        progress.insert(0, ForkProgress::new(Hash::default(), None, None, 0, 0));
+        let mut heaviest_subtree_fork_choice = HeaviestSubtreeForkChoice::new(0);
+
+        // ...until slots 1 and 2 replay during the leader slot and slot 1 becomes the best
+        for slot in 1..3 {
+            let bank = Bank::new_from_parent(&bank0, &Pubkey::default(), slot);
+            bank.freeze();
+            bank_forks.write().unwrap().insert(bank);
+            progress.insert(slot, ForkProgress::new(Hash::default(), None, None, 0, 0));
+            heaviest_subtree_fork_choice.add_new_leaf_slot(slot, Some(0));
+        }
+        assert_eq!(heaviest_subtree_fork_choice.best_overall_slot(), 1);
+
+        ReplayStage::prune_dead_forks(
+            &bank_forks,
+            &Tower::default(),
+            &mut progress,
+            &mut heaviest_subtree_fork_choice,
+            Some(&leader_bank),
+        );
+        // Only the fork off the best fork that isn't being built by this node is pruned
+        let bank_forks = bank_forks.read().unwrap();
+        assert!(bank_forks.get(3).is_some());
+        assert!(bank_forks.get(2).is_none());
+        assert!(bank_forks.is_pruned(2));
+        assert!(progress.get(&2).is_none());
+        assert!(heaviest_subtree_fork_choice
+            .stake_voted_subtree(2)
+            .is_none());
+        assert!(progress.get(&1).is_some());
+    }
+
     #[test]
     fn test_child_slots_of_same_parent() {
         let ReplayBlockstoreComponents {
@@ -2576,6 +2869,7 @@ pub(crate) mod tests {
                 None,
                 &replay_vote_sender,
                 &&VerifyRecyclers::default(),
+                false,
             );
 
             // Check that the erroring bank was marked as dead in the progress map
@@ -2737,6 +3031,7 @@ pub(crate) mod tests {
         let transaction_status_service = TransactionStatusService::new(
             transaction_status_receiver,
             blockstore,
+            None,
             &Arc::new(AtomicBool::new(false)),
         );
 
@@ -0,0 +1,587 @@
+//! The `replica_sync` module implements the protocol used by RPC replicas to follow a full
+//! validator without replaying shreds.
+//!
+//! The validator opens each connection with a random challenge, which the replica signs along
+//! with both identities, and answers by signing the replica's handshake signature, so both
+//! sides are authenticated and a captured handshake can't open another session. The validator
+//! then streams the accounts modified in each rooted slot, followed by the root itself, and signs
+//! every frame together with the session and the frame's position in it, so frames can't be
+//! forged, replayed, reordered or moved to another session. `ReplicaSyncReader` checks those
+//! signatures, and that slots chain and that each batch matches the hash computed by the
+//! validator.
+
+use solana_sdk::{
+    account::Account,
+    clock::Slot,
+    hash::{hash, Hash, Hasher},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    timing::timestamp,
+};
+use std::{
+    collections::HashSet,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
+use thiserror::Error;
+
+/// Handshakes older (or further in the future) than this are rejected
+pub const MAX_HANDSHAKE_AGE_MS: u64 = 30_000;
+/// Upper bound on a single frame; a slot's worth of account updates must fit in it
+pub const MAX_FRAME_SIZE: u64 = 1024 * 1024 * 1024;
+const MAX_HANDSHAKE_FRAME_SIZE: u64 = 1024;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+const SIGNATURE_SIZE: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum ReplicaSyncError {
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    #[error("serialization error: {0}")]
+    Serialize(#[from] Box<bincode::ErrorKind>),
+    #[error("frame of {0} bytes exceeds the size limit")]
+    FrameTooLarge(u64),
+    #[error("invalid handshake signature")]
+    InvalidSignature,
+    #[error("invalid signature on frame {0}")]
+    InvalidFrameSignature(u64),
+    #[error("handshake does not answer the challenge")]
+    ChallengeMismatch,
+    #[error("stale handshake")]
+    StaleHandshake,
+    #[error("unexpected peer identity {0}")]
+    UnexpectedIdentity(Pubkey),
+    #[error("slot {slot} does not chain to {expected_parent} (parent {parent})")]
+    SlotDoesNotChain {
+        slot: Slot,
+        parent: Slot,
+        expected_parent: Slot,
+    },
+    #[error("account updates for slot {0} do not match their hash")]
+    AccountsHashMismatch(Slot),
+    #[error("root {root} does not match the last applied slot {last_slot}")]
+    RootMismatch { root: Slot, last_slot: Slot },
+}
+
+pub type Result<T> = std::result::Result<T, ReplicaSyncError>;
+
+/// Sent by the validator as soon as a replica connects
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicaChallenge {
+    pub identity: Pubkey,
+    pub challenge: Hash,
+}
+
+impl ReplicaChallenge {
+    pub fn new(identity: Pubkey) -> Self {
+        Self {
+            identity,
+            challenge: Hash::new(&rand::random::<[u8; 32]>()),
+        }
+    }
+}
+
+/// Sent by the replica to open a stream. The signature covers the replica identity, the
+/// validator it expects to talk to, the wallclock and the validator's challenge, so a captured
+/// handshake can't be replayed against any validator.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicaHandshake {
+    pub identity: Pubkey,
+    pub validator: Pubkey,
+    pub wallclock: u64,
+    pub challenge: Hash,
+    pub signature: Signature,
+}
+
+impl ReplicaHandshake {
+    pub fn new(keypair: &Keypair, challenge: &ReplicaChallenge) -> Self {
+        let identity = keypair.pubkey();
+        let wallclock = timestamp();
+        let signature = keypair.sign_message(&Self::signable_data(
+            &identity,
+            &challenge.identity,
+            wallclock,
+            &challenge.challenge,
+        ));
+        Self {
+            identity,
+            validator: challenge.identity,
+            wallclock,
+            challenge: challenge.challenge,
+            signature,
+        }
+    }
+
+    fn signable_data(
+        identity: &Pubkey,
+        validator: &Pubkey,
+        wallclock: u64,
+        challenge: &Hash,
+    ) -> Vec<u8> {
+        bincode::serialize(&(identity, validator, wallclock, challenge)).unwrap()
+    }
+
+    /// Checks the handshake answers `challenge`, and its signature and freshness at `now`
+    pub fn verify(&self, challenge: &ReplicaChallenge, now: u64) -> Result<()> {
+        if self.validator != challenge.identity {
+            return Err(ReplicaSyncError::UnexpectedIdentity(self.validator));
+        }
+        if self.challenge != challenge.challenge {
+            return Err(ReplicaSyncError::ChallengeMismatch);
+        }
+        let data = Self::signable_data(
+            &self.identity,
+            &self.validator,
+            self.wallclock,
+            &self.challenge,
+        );
+        if !self.signature.verify(self.identity.as_ref(), &data) {
+            return Err(ReplicaSyncError::InvalidSignature);
+        }
+        let age = if now > self.wallclock {
+            now - self.wallclock
+        } else {
+            self.wallclock - now
+        };
+        if age > MAX_HANDSHAKE_AGE_MS {
+            return Err(ReplicaSyncError::StaleHandshake);
+        }
+        Ok(())
+    }
+}
+
+/// Sent by the validator in reply to a valid handshake; signs the replica's handshake signature
+/// so the replica can authenticate the validator in turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplicaHandshakeResponse {
+    pub identity: Pubkey,
+    pub signature: Signature,
+}
+
+impl ReplicaHandshakeResponse {
+    pub fn new(keypair: &Keypair, handshake: &ReplicaHandshake) -> Self {
+        Self {
+            identity: keypair.pubkey(),
+            signature: keypair.sign_message(handshake.signature.as_ref()),
+        }
+    }
+
+    pub fn verify(&self, validator: &Pubkey, handshake: &ReplicaHandshake) -> Result<()> {
+        if self.identity != *validator {
+            return Err(ReplicaSyncError::UnexpectedIdentity(self.identity));
+        }
+        if !self
+            .signature
+            .verify(self.identity.as_ref(), handshake.signature.as_ref())
+        {
+            return Err(ReplicaSyncError::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ReplicaSyncMessage {
+    /// Accounts modified in `slot`, which has been rooted and whose parent is `parent`
+    AccountUpdates {
+        slot: Slot,
+        parent: Slot,
+        accounts_hash: Hash,
+        accounts: Vec<(Pubkey, Account)>,
+    },
+    /// All account updates up to and including `slot` have been sent
+    Root(Slot),
+}
+
+impl ReplicaSyncMessage {
+    pub fn new_account_updates(slot: Slot, parent: Slot, accounts: Vec<(Pubkey, Account)>) -> Self {
+        Self::AccountUpdates {
+            slot,
+            parent,
+            accounts_hash: hash_account_updates(&accounts),
+            accounts,
+        }
+    }
+}
+
+pub fn hash_account_updates(accounts: &[(Pubkey, Account)]) -> Hash {
+    let mut hasher = Hasher::default();
+    for (pubkey, account) in accounts {
+        hasher.hash(pubkey.as_ref());
+        hasher.hash(&bincode::serialize(account).unwrap());
+    }
+    hasher.result()
+}
+
+/// A message serialized once, to be signed and sent to every replica
+#[derive(Debug)]
+pub struct ReplicaSyncFrame {
+    payload: Vec<u8>,
+    payload_hash: Hash,
+}
+
+impl ReplicaSyncFrame {
+    pub fn new(message: &ReplicaSyncMessage) -> Self {
+        let payload = bincode::serialize(message).unwrap();
+        Self {
+            payload_hash: hash(&payload),
+            payload,
+        }
+    }
+}
+
+/// The session of a stream is identified by the replica's handshake signature, which covers the
+/// validator's challenge
+fn frame_signable_data(session: &Signature, sequence: u64, payload_hash: &Hash) -> Vec<u8> {
+    bincode::serialize(&(session, sequence, payload_hash)).unwrap()
+}
+
+pub fn write_frame<T: serde::Serialize>(stream: &mut impl Write, value: &T) -> Result<()> {
+    let data = bincode::serialize(value)?;
+    stream.write_all(&(data.len() as u64).to_le_bytes())?;
+    stream.write_all(&data)?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// Writes `frame` as the `sequence`th frame of `session`, signed by the validator
+pub fn write_signed_frame(
+    stream: &mut impl Write,
+    keypair: &Keypair,
+    session: &Signature,
+    sequence: u64,
+    frame: &ReplicaSyncFrame,
+) -> Result<()> {
+    let signature =
+        keypair.sign_message(&frame_signable_data(session, sequence, &frame.payload_hash));
+    stream.write_all(&(frame.payload.len() as u64).to_le_bytes())?;
+    stream.write_all(&frame.payload)?;
+    stream.write_all(signature.as_ref())?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn read_payload(stream: &mut impl Read, max_size: u64) -> Result<Vec<u8>> {
+    let mut len = [0u8; 8];
+    stream.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > max_size {
+        return Err(ReplicaSyncError::FrameTooLarge(len));
+    }
+    let mut data = vec![0u8; len as usize];
+    stream.read_exact(&mut data)?;
+    Ok(data)
+}
+
+pub fn read_frame<T: serde::de::DeserializeOwned>(
+    stream: &mut impl Read,
+    max_size: u64,
+) -> Result<T> {
+    Ok(bincode::deserialize(&read_payload(stream, max_size)?)?)
+}
+
+/// Performs the validator side of the handshake on a freshly accepted connection, returning the
+/// replica identity and the session once both sides are authenticated.
+pub fn accept_handshake(
+    stream: &mut TcpStream,
+    keypair: &Keypair,
+    replica_validators: &Option<HashSet<Pubkey>>, // None = accept any authenticated replica
+) -> Result<(Pubkey, Signature)> {
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    stream.set_write_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let challenge = ReplicaChallenge::new(keypair.pubkey());
+    write_frame(stream, &challenge)?;
+    let handshake: ReplicaHandshake = read_frame(stream, MAX_HANDSHAKE_FRAME_SIZE)?;
+    handshake.verify(&challenge, timestamp())?;
+    if let Some(replica_validators) = replica_validators {
+        if !replica_validators.contains(&handshake.identity) {
+            return Err(ReplicaSyncError::UnexpectedIdentity(handshake.identity));
+        }
+    }
+    write_frame(stream, &ReplicaHandshakeResponse::new(keypair, &handshake))?;
+    stream.set_read_timeout(None)?;
+    Ok((handshake.identity, handshake.signature))
+}
+
+/// Performs the replica side of the handshake with the validator identified by `validator`,
+/// returning a reader of the stream it opens
+pub fn connect(
+    addr: &SocketAddr,
+    keypair: &Keypair,
+    validator: &Pubkey,
+) -> Result<ReplicaSyncReader<TcpStream>> {
+    let mut stream = TcpStream::connect_timeout(addr, HANDSHAKE_TIMEOUT)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let challenge: ReplicaChallenge = read_frame(&mut stream, MAX_HANDSHAKE_FRAME_SIZE)?;
+    if challenge.identity != *validator {
+        return Err(ReplicaSyncError::UnexpectedIdentity(challenge.identity));
+    }
+    let handshake = ReplicaHandshake::new(keypair, &challenge);
+    write_frame(&mut stream, &handshake)?;
+    let response: ReplicaHandshakeResponse = read_frame(&mut stream, MAX_HANDSHAKE_FRAME_SIZE)?;
+    response.verify(validator, &handshake)?;
+    stream.set_read_timeout(None)?;
+    Ok(ReplicaSyncReader::new(
+        stream,
+        *validator,
+        handshake.signature,
+    ))
+}
+
+/// Replica end of a stream, returning the messages whose signatures are valid and which are
+/// consistent with the ones read before
+pub struct ReplicaSyncReader<S> {
+    stream: S,
+    validator: Pubkey,
+    session: Signature,
+    sequence: u64,
+    /// The last slot read, None until the first batch of account updates
+    last_slot: Option<Slot>,
+}
+
+impl<S: Read> ReplicaSyncReader<S> {
+    pub fn new(stream: S, validator: Pubkey, session: Signature) -> Self {
+        Self {
+            stream,
+            validator,
+            session,
+            sequence: 0,
+            last_slot: None,
+        }
+    }
+
+    /// Reads the next message; errors leave the stream unusable
+    pub fn read(&mut self) -> Result<ReplicaSyncMessage> {
+        let payload = read_payload(&mut self.stream, MAX_FRAME_SIZE)?;
+        let mut signature = [0u8; SIGNATURE_SIZE];
+        self.stream.read_exact(&mut signature)?;
+        let data = frame_signable_data(&self.session, self.sequence, &hash(&payload));
+        if !Signature::new(&signature).verify(self.validator.as_ref(), &data) {
+            return Err(ReplicaSyncError::InvalidFrameSignature(self.sequence));
+        }
+        self.sequence += 1;
+
+        let message = bincode::deserialize(&payload)?;
+        match &message {
+            ReplicaSyncMessage::AccountUpdates {
+                slot,
+                parent,
+                accounts_hash,
+                accounts,
+            } => {
+                // The first batch is accepted as the chain head
+                if let Some(last_slot) = self.last_slot {
+                    if *parent != last_slot || slot <= parent {
+                        return Err(ReplicaSyncError::SlotDoesNotChain {
+                            slot: *slot,
+                            parent: *parent,
+                            expected_parent: last_slot,
+                        });
+                    }
+                }
+                if hash_account_updates(accounts) != *accounts_hash {
+                    return Err(ReplicaSyncError::AccountsHashMismatch(*slot));
+                }
+                self.last_slot = Some(*slot);
+            }
+            ReplicaSyncMessage::Root(root) => {
+                let last_slot = self.last_slot.unwrap_or(*root);
+                if *root != last_slot {
+                    return Err(ReplicaSyncError::RootMismatch {
+                        root: *root,
+                        last_slot,
+                    });
+                }
+                self.last_slot = Some(*root);
+            }
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_account(lamports: u64) -> Account {
+        Account::new(lamports, 0, &Pubkey::default())
+    }
+
+    #[test]
+    fn test_handshake_verify() {
+        let replica = Keypair::new();
+        let validator = Keypair::new();
+        let challenge = ReplicaChallenge::new(validator.pubkey());
+        let handshake = ReplicaHandshake::new(&replica, &challenge);
+        assert!(handshake.verify(&challenge, handshake.wallclock).is_ok());
+        assert!(matches!(
+            handshake.verify(
+                &ReplicaChallenge {
+                    identity: Pubkey::new_unique(),
+                    ..challenge.clone()
+                },
+                handshake.wallclock
+            ),
+            Err(ReplicaSyncError::UnexpectedIdentity(_))
+        ));
+        assert!(matches!(
+            handshake.verify(&challenge, handshake.wallclock + MAX_HANDSHAKE_AGE_MS + 1),
+            Err(ReplicaSyncError::StaleHandshake)
+        ));
+
+        // A handshake answers a single challenge
+        assert!(matches!(
+            handshake.verify(
+                &ReplicaChallenge::new(validator.pubkey()),
+                handshake.wallclock
+            ),
+            Err(ReplicaSyncError::ChallengeMismatch)
+        ));
+
+        let mut forged = handshake.clone();
+        forged.identity = Pubkey::new_unique();
+        assert!(matches!(
+            forged.verify(&challenge, forged.wallclock),
+            Err(ReplicaSyncError::InvalidSignature)
+        ));
+
+        let response = ReplicaHandshakeResponse::new(&validator, &handshake);
+        assert!(response.verify(&validator.pubkey(), &handshake).is_ok());
+        let response = ReplicaHandshakeResponse::new(&Keypair::new(), &handshake);
+        assert!(response.verify(&validator.pubkey(), &handshake).is_err());
+    }
+
+    #[test]
+    fn test_frame_size_limit() {
+        let mut data = vec![];
+        write_frame(&mut data, &ReplicaSyncMessage::Root(42)).unwrap();
+        assert_eq!(
+            read_frame::<ReplicaSyncMessage>(&mut data.as_slice(), MAX_FRAME_SIZE).unwrap(),
+            ReplicaSyncMessage::Root(42)
+        );
+        assert!(matches!(
+            read_frame::<ReplicaSyncMessage>(&mut data.as_slice(), 1),
+            Err(ReplicaSyncError::FrameTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_signed_frames() {
+        let validator = Keypair::new();
+        let session = Signature::new(&[1; 64]);
+        let frames: Vec<_> = [ReplicaSyncMessage::Root(1), ReplicaSyncMessage::Root(1)]
+            .iter()
+            .map(ReplicaSyncFrame::new)
+            .collect();
+        let write = |keypair: &Keypair, session: &Signature, sequences: &[usize]| {
+            let mut data = vec![];
+            for sequence in sequences {
+                write_signed_frame(
+                    &mut data,
+                    keypair,
+                    session,
+                    *sequence as u64,
+                    &frames[*sequence],
+                )
+                .unwrap();
+            }
+            data
+        };
+        let read = |data: Vec<u8>| {
+            let mut reader = ReplicaSyncReader::new(data.as_slice(), validator.pubkey(), session);
+            (reader.read(), reader.read())
+        };
+
+        let (first, second) = read(write(&validator, &session, &[0, 1]));
+        assert_eq!(first.unwrap(), ReplicaSyncMessage::Root(1));
+        assert_eq!(second.unwrap(), ReplicaSyncMessage::Root(1));
+
+        // Frames signed by another identity or for another session are rejected
+        let (first, _) = read(write(&Keypair::new(), &session, &[0]));
+        assert!(matches!(
+            first,
+            Err(ReplicaSyncError::InvalidFrameSignature(0))
+        ));
+        let (first, _) = read(write(&validator, &Signature::new(&[2; 64]), &[0]));
+        assert!(matches!(
+            first,
+            Err(ReplicaSyncError::InvalidFrameSignature(0))
+        ));
+
+        // And so are replayed frames
+        let (first, second) = read(write(&validator, &session, &[0, 0]));
+        assert!(first.is_ok());
+        assert!(matches!(
+            second,
+            Err(ReplicaSyncError::InvalidFrameSignature(1))
+        ));
+
+        // Or tampered ones
+        let mut data = write(&validator, &session, &[0]);
+        data[8] ^= 1;
+        let (first, _) = read(data);
+        assert!(matches!(
+            first,
+            Err(ReplicaSyncError::InvalidFrameSignature(0))
+        ));
+    }
+
+    #[test]
+    fn test_reader_consistency() {
+        let validator = Keypair::new();
+        let session = Signature::new(&[1; 64]);
+        let pubkey = Pubkey::new_unique();
+        let read = |messages: &[ReplicaSyncMessage]| {
+            let mut data = vec![];
+            for (sequence, message) in messages.iter().enumerate() {
+                let frame = ReplicaSyncFrame::new(message);
+                write_signed_frame(&mut data, &validator, &session, sequence as u64, &frame)
+                    .unwrap();
+            }
+            let mut reader = ReplicaSyncReader::new(data.as_slice(), validator.pubkey(), session);
+            (0..messages.len())
+                .map(|_| reader.read())
+                .collect::<Vec<_>>()
+        };
+
+        let results = read(&[
+            ReplicaSyncMessage::new_account_updates(11, 10, vec![(pubkey, new_account(5))]),
+            ReplicaSyncMessage::Root(11),
+            ReplicaSyncMessage::new_account_updates(12, 11, vec![]),
+            ReplicaSyncMessage::Root(12),
+        ]);
+        assert!(results.iter().all(Result::is_ok));
+
+        // Updates must chain to the last slot read
+        let results = read(&[
+            ReplicaSyncMessage::new_account_updates(11, 10, vec![]),
+            ReplicaSyncMessage::new_account_updates(13, 12, vec![]),
+        ]);
+        assert!(matches!(
+            results[1],
+            Err(ReplicaSyncError::SlotDoesNotChain { .. })
+        ));
+
+        // Updates must match their hash
+        let mut message =
+            ReplicaSyncMessage::new_account_updates(11, 10, vec![(pubkey, new_account(5))]);
+        if let ReplicaSyncMessage::AccountUpdates { accounts, .. } = &mut message {
+            accounts[0].1.lamports = 6;
+        }
+        assert!(matches!(
+            read(&[message])[0],
+            Err(ReplicaSyncError::AccountsHashMismatch(11))
+        ));
+
+        // Roots must match the last slot read
+        let results = read(&[
+            ReplicaSyncMessage::new_account_updates(11, 10, vec![]),
+            ReplicaSyncMessage::Root(12),
+        ]);
+        assert!(matches!(
+            results[1],
+            Err(ReplicaSyncError::RootMismatch { .. })
+        ));
+    }
+}
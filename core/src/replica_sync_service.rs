@@ -0,0 +1,384 @@
+//! The `replica_sync_service` streams rooted account updates to authenticated RPC replicas.
+//! See `replica_sync` for the protocol.
+
+use crate::replica_sync::{
+    accept_handshake, write_signed_frame, ReplicaSyncFrame, ReplicaSyncMessage,
+};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+use solana_runtime::bank::Bank;
+use solana_sdk::{
+    clock::Slot,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+use std::{
+    collections::HashSet,
+    io,
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::Duration,
+};
+
+/// Roots replay can queue for the service before it starts dropping them
+pub const MAX_PENDING_REPLICA_SYNC_ROOTS: usize = 16;
+/// Roots queued for a replica before it is considered too slow and dropped
+const MAX_REPLICA_QUEUED_ROOTS: usize = 16;
+const REPLICA_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The banks rooted along with a new root, sent by replay before they are squashed
+pub struct ReplicaSyncRoot {
+    pub rooted_banks: Vec<Arc<Bank>>,
+    pub prior_root: Slot,
+}
+
+pub type ReplicaSyncReceiver = Receiver<ReplicaSyncRoot>;
+pub type ReplicaSyncSender = Sender<ReplicaSyncRoot>;
+
+/// Builds the messages announcing a new root from the banks rooted along with it. Called by the
+/// service as the roots come in, well before accounts cleaning, which stops short of the last
+/// snapshot root, can purge the accounts those banks modified.
+pub fn rooted_bank_updates(
+    rooted_banks: &[Arc<Bank>],
+    prior_root: Slot,
+) -> Vec<ReplicaSyncMessage> {
+    let mut rooted_banks: Vec<_> = rooted_banks
+        .iter()
+        .filter(|bank| bank.slot() > prior_root)
+        .collect();
+    rooted_banks.sort_by_key(|bank| bank.slot());
+    let root = match rooted_banks.last() {
+        Some(bank) => bank.slot(),
+        None => return vec![],
+    };
+    rooted_banks
+        .into_iter()
+        .map(|bank| {
+            ReplicaSyncMessage::new_account_updates(
+                bank.slot(),
+                bank.parent_slot(),
+                bank.get_all_accounts_modified_since_parent(),
+            )
+        })
+        .chain(std::iter::once(ReplicaSyncMessage::Root(root)))
+        .collect()
+}
+
+/// A connected replica, fed by its own writer thread
+struct Replica {
+    identity: Pubkey,
+    sender: Sender<Arc<Vec<ReplicaSyncFrame>>>,
+    t_write: JoinHandle<()>,
+}
+
+impl Replica {
+    fn new(
+        identity: Pubkey,
+        session: Signature,
+        mut stream: TcpStream,
+        identity_keypair: Arc<Keypair>,
+        exit: &Arc<AtomicBool>,
+    ) -> io::Result<Self> {
+        stream.set_write_timeout(Some(REPLICA_WRITE_TIMEOUT))?;
+        let (sender, receiver) = bounded::<Arc<Vec<ReplicaSyncFrame>>>(MAX_REPLICA_QUEUED_ROOTS);
+        let exit = exit.clone();
+        let t_write = Builder::new()
+            .name("solana-replica-sync-write".to_string())
+            .spawn(move || {
+                let mut sequence = 0;
+                while !exit.load(Ordering::Relaxed) {
+                    let frames = match receiver.recv_timeout(Duration::from_secs(1)) {
+                        Ok(frames) => frames,
+                        Err(RecvTimeoutError::Timeout) => continue,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    };
+                    for frame in frames.iter() {
+                        if let Err(err) = write_signed_frame(
+                            &mut stream,
+                            &identity_keypair,
+                            &session,
+                            sequence,
+                            frame,
+                        ) {
+                            warn!("dropping replica {}: {}", identity, err);
+                            return;
+                        }
+                        sequence += 1;
+                    }
+                }
+            })?;
+        Ok(Self {
+            identity,
+            sender,
+            t_write,
+        })
+    }
+}
+
+pub struct ReplicaSyncService {
+    t_listen: JoinHandle<()>,
+    t_send: JoinHandle<()>,
+    replicas: Arc<Mutex<Vec<Replica>>>,
+}
+
+impl ReplicaSyncService {
+    pub fn new(
+        listener: TcpListener,
+        identity_keypair: Arc<Keypair>,
+        replica_validators: Option<HashSet<Pubkey>>, // None = accept any authenticated replica
+        replica_sync_receiver: ReplicaSyncReceiver,
+        exit: &Arc<AtomicBool>,
+    ) -> Self {
+        info!(
+            "replica sync listening on {:?} as {}",
+            listener.local_addr(),
+            identity_keypair.pubkey()
+        );
+        let replicas = Arc::new(Mutex::new(Vec::<Replica>::new()));
+
+        let exit_ = exit.clone();
+        let replicas_ = replicas.clone();
+        let t_listen = Builder::new()
+            .name("solana-replica-sync-listen".to_string())
+            .spawn(move || {
+                listener
+                    .set_nonblocking(true)
+                    .expect("replica sync listener set_nonblocking");
+                while !exit_.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, addr)) => Self::accept_replica(
+                            stream,
+                            &identity_keypair,
+                            &replica_validators,
+                            &replicas_,
+                            &exit_,
+                        )
+                        .unwrap_or_else(|err| {
+                            warn!("rejected replica sync connection from {}: {}", addr, err)
+                        }),
+                        Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                            sleep(Duration::from_millis(100));
+                        }
+                        Err(err) => warn!("replica sync accept failed: {}", err),
+                    }
+                }
+            })
+            .unwrap();
+
+        let exit = exit.clone();
+        let replicas_ = replicas.clone();
+        let t_send = Builder::new()
+            .name("solana-replica-sync-send".to_string())
+            .spawn(move || {
+                let mut last_root = None;
+                loop {
+                    if exit.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if let Err(RecvTimeoutError::Disconnected) =
+                        Self::send_updates(&replica_sync_receiver, &replicas_, &mut last_root)
+                    {
+                        break;
+                    }
+                }
+            })
+            .unwrap();
+
+        Self {
+            t_listen,
+            t_send,
+            replicas,
+        }
+    }
+
+    fn accept_replica(
+        mut stream: TcpStream,
+        identity_keypair: &Arc<Keypair>,
+        replica_validators: &Option<HashSet<Pubkey>>,
+        replicas: &Mutex<Vec<Replica>>,
+        exit: &Arc<AtomicBool>,
+    ) -> crate::replica_sync::Result<()> {
+        stream.set_nonblocking(false)?;
+        let (replica, session) =
+            accept_handshake(&mut stream, identity_keypair, replica_validators)?;
+        info!(
+            "replica {} connected from {:?}",
+            replica,
+            stream.peer_addr()
+        );
+        let replica = Replica::new(replica, session, stream, identity_keypair.clone(), exit)?;
+        replicas.lock().unwrap().push(replica);
+        Ok(())
+    }
+
+    fn send_updates(
+        replica_sync_receiver: &ReplicaSyncReceiver,
+        replicas: &Mutex<Vec<Replica>>,
+        last_root: &mut Option<Slot>,
+    ) -> Result<(), RecvTimeoutError> {
+        let ReplicaSyncRoot {
+            rooted_banks,
+            prior_root,
+        } = replica_sync_receiver.recv_timeout(Duration::from_secs(1))?;
+        let messages = rooted_bank_updates(&rooted_banks, prior_root);
+        let frames: Arc<Vec<_>> = Arc::new(messages.iter().map(ReplicaSyncFrame::new).collect());
+        // Replay drops roots while this thread is behind, which leaves the connected replicas
+        // with a gap they can't recover from
+        let missed_roots = last_root.map_or(false, |last_root| last_root != prior_root);
+        if let Some(ReplicaSyncMessage::Root(root)) = messages.last() {
+            *last_root = Some(*root);
+        }
+
+        let mut num_dropped_replicas = 0;
+        let mut replicas = replicas.lock().unwrap();
+        replicas.retain(|replica| {
+            let result = if missed_roots {
+                Err("missed roots")
+            } else {
+                match replica.sender.try_send(frames.clone()) {
+                    Ok(()) => Ok(()),
+                    Err(TrySendError::Full(_)) => Err("fell behind"),
+                    Err(TrySendError::Disconnected(_)) => Err("disconnected"),
+                }
+            };
+            if let Err(err) = result {
+                warn!("dropping replica {}: {}", replica.identity, err);
+                num_dropped_replicas += 1;
+            }
+            result.is_ok()
+        });
+        datapoint_info!(
+            "replica-sync-service",
+            ("num_replicas", replicas.len(), i64),
+            ("num_messages", messages.len(), i64),
+            ("num_dropped_replicas", num_dropped_replicas, i64)
+        );
+        Ok(())
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.t_listen.join()?;
+        self.t_send.join()?;
+        let replicas = std::mem::take(&mut *self.replicas.lock().unwrap());
+        for replica in replicas {
+            replica.t_write.join()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replica_sync::{connect, ReplicaSyncError};
+    use solana_runtime::genesis_utils::create_genesis_config;
+
+    #[test]
+    fn test_replica_sync_service() {
+        let (genesis_config, mint_keypair) = create_genesis_config(10_000);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let bank1 = Arc::new(Bank::new_from_parent(&bank0, &Pubkey::default(), 1));
+        let to = Pubkey::new_unique();
+        bank1.transfer(42, &mint_keypair, &to).unwrap();
+        bank1.freeze();
+
+        let exit = Arc::new(AtomicBool::new(false));
+        let validator_keypair = Arc::new(Keypair::new());
+        let replica_keypair = Keypair::new();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (sender, receiver) = bounded(MAX_PENDING_REPLICA_SYNC_ROOTS);
+        let service = ReplicaSyncService::new(
+            listener,
+            validator_keypair.clone(),
+            Some(vec![replica_keypair.pubkey()].into_iter().collect()),
+            receiver,
+            &exit,
+        );
+
+        // Replicas outside of the allowed set are rejected
+        assert!(connect(&addr, &Keypair::new(), &validator_keypair.pubkey()).is_err());
+        // Replicas must authenticate the validator they expect
+        assert!(matches!(
+            connect(&addr, &replica_keypair, &Pubkey::new_unique()),
+            Err(ReplicaSyncError::UnexpectedIdentity(_))
+        ));
+
+        let mut reader = connect(&addr, &replica_keypair, &validator_keypair.pubkey()).unwrap();
+        // Give the listener a moment to register the replica before sending updates
+        sleep(Duration::from_millis(200));
+        sender
+            .send(ReplicaSyncRoot {
+                rooted_banks: vec![bank1, bank0],
+                prior_root: 0,
+            })
+            .unwrap();
+
+        match reader.read().unwrap() {
+            ReplicaSyncMessage::AccountUpdates { slot, accounts, .. } => {
+                assert_eq!(slot, 1);
+                let (_, account) = accounts.iter().find(|(pubkey, _)| *pubkey == to).unwrap();
+                assert_eq!(account.lamports, 42);
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+        assert_eq!(reader.read().unwrap(), ReplicaSyncMessage::Root(1));
+
+        exit.store(true, Ordering::Relaxed);
+        drop(sender);
+        service.join().unwrap();
+    }
+
+    #[test]
+    fn test_send_updates_drops_slow_replicas() {
+        let (genesis_config, _mint_keypair) = create_genesis_config(10_000);
+        let bank0 = Arc::new(Bank::new(&genesis_config));
+        let bank1 = Arc::new(Bank::new_from_parent(&bank0, &Pubkey::default(), 1));
+        let bank2 = Arc::new(Bank::new_from_parent(&bank1, &Pubkey::default(), 2));
+        let bank3 = Arc::new(Bank::new_from_parent(&bank2, &Pubkey::default(), 3));
+        let bank4 = Arc::new(Bank::new_from_parent(&bank3, &Pubkey::default(), 4));
+        let new_replica = |queued_roots| {
+            let (sender, receiver) = bounded(queued_roots);
+            let replica = Replica {
+                identity: Pubkey::new_unique(),
+                sender,
+                t_write: Builder::new().spawn(|| ()).unwrap(),
+            };
+            (replica, receiver)
+        };
+        let (slow_replica, _slow_receiver) = new_replica(1);
+        let (replica, receiver) = new_replica(MAX_REPLICA_QUEUED_ROOTS);
+        let replicas = Mutex::new(vec![slow_replica, replica]);
+        let (sender, replica_sync_receiver) = bounded(MAX_PENDING_REPLICA_SYNC_ROOTS);
+        let mut last_root = None;
+
+        // The replica that can't queue the second root is dropped, the other one keeps up
+        for (bank, prior_root) in vec![(&bank1, 0), (&bank2, 1)] {
+            sender
+                .send(ReplicaSyncRoot {
+                    rooted_banks: vec![bank.clone()],
+                    prior_root,
+                })
+                .unwrap();
+            ReplicaSyncService::send_updates(&replica_sync_receiver, &replicas, &mut last_root)
+                .unwrap();
+        }
+        assert_eq!(last_root, Some(2));
+        assert_eq!(replicas.lock().unwrap().len(), 1);
+        assert_eq!(receiver.len(), 2);
+
+        // A root replay failed to queue leaves a gap, so every replica is dropped
+        sender
+            .send(ReplicaSyncRoot {
+                rooted_banks: vec![bank4],
+                prior_root: 3,
+            })
+            .unwrap();
+        ReplicaSyncService::send_updates(&replica_sync_receiver, &replicas, &mut last_root)
+            .unwrap();
+        assert!(replicas.lock().unwrap().is_empty());
+    }
+}
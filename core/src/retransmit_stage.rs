@@ -10,6 +10,7 @@ use crate::{
     contact_info::ContactInfo,
     repair_service::DuplicateSlotsResetSender,
     repair_service::RepairInfo,
+    repair_status::RepairStatus,
     result::{Error, Result},
     window_service::{should_retransmit_and_persist, WindowService},
 };
@@ -23,13 +24,14 @@ use solana_ledger::{
 use solana_measure::measure::Measure;
 use solana_metrics::inc_new_counter_error;
 use solana_perf::packet::{Packet, Packets};
+use solana_rayon_threadlimit::thread_manager;
 use solana_runtime::{bank::Bank, bank_forks::BankForks};
 use solana_sdk::{
     clock::Slot, epoch_schedule::EpochSchedule, feature_set, pubkey::Pubkey, timing::timestamp,
 };
 use solana_streamer::streamer::PacketReceiver;
 use std::{
-    cmp,
+    cmp::{self, Reverse},
     collections::hash_set::HashSet,
     collections::{BTreeMap, HashMap},
     net::UdpSocket,
@@ -40,7 +42,7 @@ use std::{
     sync::Mutex,
     sync::{Arc, RwLock},
     thread::{self, Builder, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 const MAX_DUPLICATE_COUNT: usize = 2;
@@ -50,6 +52,53 @@ const DEFAULT_LRU_SIZE: usize = 10_000;
 // it doesn't pull up too much work.
 const MAX_PACKET_BATCH_SIZE: usize = 100;
 
+// Once the egress budget runs out, shreds of slots this far behind the working bank are sent to
+// all of their peers or to none, while those nearer the tip still go to as many as fit
+const RETRANSMIT_BUDGET_TIP_SLOTS: Slot = 4;
+
+/// Bytes per second the retransmit threads may send in total.  It refills continuously and
+/// holds up to a second's worth, absorbing the bursts shreds arrive in
+struct RetransmitBudget {
+    bytes_per_second: u64,
+    // Bytes available, and when they were last refilled
+    available: Mutex<(u64, Instant)>,
+}
+
+impl RetransmitBudget {
+    fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second,
+            available: Mutex::new((bytes_per_second, Instant::now())),
+        }
+    }
+
+    /// Takes as many sends of `size` bytes out of the budget as fit, up to `count`, and returns
+    /// how many.  With `all_or_nothing` it takes all `count` of them or none
+    fn take(&self, size: usize, count: usize, all_or_nothing: bool) -> usize {
+        let mut available = self.available.lock().unwrap();
+        let (bytes, refilled) = &mut *available;
+        let now = Instant::now();
+        let refill = u128::from(self.bytes_per_second)
+            * now.saturating_duration_since(*refilled).as_nanos()
+            / 1_000_000_000;
+        *bytes = (u128::from(*bytes) + refill).min(u128::from(self.bytes_per_second)) as u64;
+        *refilled = cmp::max(*refilled, now);
+
+        let fits = if size == 0 {
+            count
+        } else {
+            cmp::min(count as u64, *bytes / size as u64) as usize
+        };
+        let taken = if all_or_nothing && fits < count {
+            0
+        } else {
+            fits
+        };
+        *bytes -= (taken * size) as u64;
+        taken
+    }
+}
+
 #[derive(Default)]
 struct RetransmitStats {
     total_packets: AtomicU64,
@@ -62,6 +111,9 @@ struct RetransmitStats {
     retransmit_total: AtomicU64,
     last_ts: AtomicU64,
     compute_turbine_peers_total: AtomicU64,
+    // Shreds the egress budget left no room for at all, and the sends to peers it skipped
+    deferred_shreds: AtomicU64,
+    deferred_sends: AtomicU64,
     packets_by_slot: Mutex<BTreeMap<Slot, usize>>,
     packets_by_source: Mutex<BTreeMap<String, usize>>,
 }
@@ -75,6 +127,8 @@ fn update_retransmit_stats(
     discard_total: u64,
     repair_total: u64,
     compute_turbine_peers_total: u64,
+    deferred_shreds: u64,
+    deferred_sends: u64,
     peers_len: usize,
     packets_by_slot: HashMap<Slot, usize>,
     packets_by_source: HashMap<String, usize>,
@@ -97,6 +151,12 @@ fn update_retransmit_stats(
     stats
         .compute_turbine_peers_total
         .fetch_add(compute_turbine_peers_total, Ordering::Relaxed);
+    stats
+        .deferred_shreds
+        .fetch_add(deferred_shreds, Ordering::Relaxed);
+    stats
+        .deferred_sends
+        .fetch_add(deferred_sends, Ordering::Relaxed);
     stats.total_batches.fetch_add(1, Ordering::Relaxed);
     stats.epoch_fetch.fetch_add(epoch_fetch, Ordering::Relaxed);
     stats
@@ -169,6 +229,16 @@ fn update_retransmit_stats(
                 stats.discard_total.swap(0, Ordering::Relaxed) as i64,
                 i64
             ),
+            (
+                "deferred_shreds",
+                stats.deferred_shreds.swap(0, Ordering::Relaxed) as i64,
+                i64
+            ),
+            (
+                "deferred_sends",
+                stats.deferred_sends.swap(0, Ordering::Relaxed) as i64,
+                i64
+            ),
         );
         let mut packets_by_slot = stats.packets_by_slot.lock().unwrap();
         info!("retransmit: packets_by_slot: {:?}", packets_by_slot);
@@ -200,6 +270,8 @@ fn update_retransmit_stats(
 struct EpochStakesCache {
     peers: Vec<ContactInfo>,
     stakes_and_index: Vec<(u64, usize)>,
+    // The stake of each of `peers`
+    stakes: Vec<u64>,
 }
 
 use crate::packet_hasher::PacketHasher;
@@ -264,6 +336,7 @@ fn retransmit(
     epoch_stakes_cache: &RwLock<EpochStakesCache>,
     last_peer_update: &AtomicU64,
     shreds_received: &Mutex<ShredFilterAndHasher>,
+    egress_budget: Option<&RetransmitBudget>,
 ) -> Result<()> {
     let timer = Duration::new(1, 0);
     let r_lock = r.lock().unwrap();
@@ -300,8 +373,13 @@ fn retransmit(
             cluster_info.sorted_retransmit_peers_and_stakes(epoch_staked_nodes.as_ref());
         {
             let mut epoch_stakes_cache = epoch_stakes_cache.write().unwrap();
+            let mut stakes = vec![0; peers.len()];
+            for (stake, index) in &stakes_and_index {
+                stakes[*index] = *stake;
+            }
             epoch_stakes_cache.peers = peers;
             epoch_stakes_cache.stakes_and_index = stakes_and_index;
+            epoch_stakes_cache.stakes = stakes;
         }
         {
             let mut sr = shreds_received.lock().unwrap();
@@ -318,8 +396,11 @@ fn retransmit(
     let mut repair_total = 0;
     let mut retransmit_total = 0;
     let mut compute_turbine_peers_total = 0;
+    let mut deferred_shreds = 0;
+    let mut deferred_sends = 0;
     let mut packets_by_slot: HashMap<Slot, usize> = HashMap::new();
     let mut packets_by_source: HashMap<String, usize> = HashMap::new();
+    let shred_cipher = cluster_info.shred_cipher();
     for mut packets in packet_v {
         for packet in packets.packets.iter_mut() {
             // skip discarded packets and repair packets
@@ -355,9 +436,9 @@ fn retransmit(
                 .map(|(_, index)| index)
                 .collect();
 
-            let (neighbors, children) =
+            let (neighbors, mut children) =
                 compute_retransmit_peers(DATA_PLANE_FANOUT, my_index, &indexes);
-            let neighbors: Vec<_> = neighbors
+            let mut neighbors: Vec<_> = neighbors
                 .into_iter()
                 .filter_map(|index| {
                     let peer = &r_epoch_stakes_cache.peers[index];
@@ -368,6 +449,26 @@ fn retransmit(
                     }
                 })
                 .collect();
+            if packet.meta.forward {
+                neighbors.clear();
+            }
+            if let Some(egress_budget) = egress_budget {
+                let sends = neighbors.len() + children.len();
+                let near_tip =
+                    shred_slot.saturating_add(RETRANSMIT_BUDGET_TIP_SLOTS) >= r_bank.slot();
+                let allowed = egress_budget.take(packet.meta.size, sends, !near_tip);
+                if allowed < sends {
+                    deferred_sends += (sends - allowed) as u64;
+                    if allowed == 0 {
+                        deferred_shreds += 1;
+                    }
+                    // Neighbors pass the shred on to their own children, so they come first,
+                    // then the children with the most stake
+                    neighbors.truncate(allowed);
+                    children.sort_by_key(|index| Reverse(r_epoch_stakes_cache.stakes[*index]));
+                    children.truncate(allowed - neighbors.len());
+                }
+            }
             let children: Vec<_> = children
                 .into_iter()
                 .map(|index| &r_epoch_stakes_cache.peers[index])
@@ -383,6 +484,10 @@ fn retransmit(
             let leader =
                 leader_schedule_cache.slot_leader_at(packet.meta.slot, Some(r_bank.as_ref()));
             let mut retransmit_time = Measure::start("retransmit_to");
+            // Shreds were decrypted on receipt, encrypt them again before sending them on
+            if let Some(shred_cipher) = &shred_cipher {
+                shred_cipher.apply(&mut packet.data[..packet.meta.size]);
+            }
             if !packet.meta.forward {
                 ClusterInfo::retransmit_to(&neighbors, packet, leader, sock, true)?;
                 ClusterInfo::retransmit_to(&children, packet, leader, sock, false)?;
@@ -409,6 +514,8 @@ fn retransmit(
         discard_total,
         repair_total,
         compute_turbine_peers_total,
+        deferred_shreds,
+        deferred_sends,
         peers_len,
         packets_by_slot,
         packets_by_source,
@@ -427,14 +534,23 @@ fn retransmit(
 /// * `leader_schedule_cache` - The leader schedule to verify shreds
 /// * `cluster_info` - This structure needs to be updated and populated by the bank and via gossip.
 /// * `r` - Receive channel for shreds to be retransmitted to all the layer 1 nodes.
+/// * `egress_budget` - Bytes per second to retransmit at most, `None` for no limit.
 pub fn retransmitter(
     sockets: Arc<Vec<UdpSocket>>,
     bank_forks: Arc<RwLock<BankForks>>,
     leader_schedule_cache: &Arc<LeaderScheduleCache>,
     cluster_info: Arc<ClusterInfo>,
     r: Arc<Mutex<PacketReceiver>>,
+    egress_budget: Option<u64>,
 ) -> Vec<JoinHandle<()>> {
     let stats = Arc::new(RetransmitStats::default());
+    let egress_budget = egress_budget.map(|bytes_per_second| {
+        info!(
+            "Retransmitting at most {} bytes per second",
+            bytes_per_second
+        );
+        Arc::new(RetransmitBudget::new(bytes_per_second))
+    });
     let shreds_received = Arc::new(Mutex::new((
         LruCache::new(DEFAULT_LRU_SIZE),
         PacketHasher::default(),
@@ -450,10 +566,12 @@ pub fn retransmitter(
             let epoch_stakes_cache = Arc::new(RwLock::new(EpochStakesCache::default()));
             let last_peer_update = Arc::new(AtomicU64::new(0));
             let shreds_received = shreds_received.clone();
+            let egress_budget = egress_budget.clone();
 
             Builder::new()
                 .name("solana-retransmitter".to_string())
                 .spawn(move || {
+                    thread_manager::pin_current_thread("solana-turbine", s);
                     trace!("retransmitter started");
                     loop {
                         if let Err(e) = retransmit(
@@ -467,6 +585,7 @@ pub fn retransmitter(
                             &epoch_stakes_cache,
                             &last_peer_update,
                             &shreds_received,
+                            egress_budget.as_deref(),
                         ) {
                             match e {
                                 Error::RecvTimeoutError(RecvTimeoutError::Disconnected) => break,
@@ -511,6 +630,8 @@ impl RetransmitStage {
         verified_vote_receiver: VerifiedVoteReceiver,
         repair_validators: Option<HashSet<Pubkey>>,
         completed_data_sets_sender: CompletedDataSetsSender,
+        repair_status: Arc<RwLock<RepairStatus>>,
+        retransmit_egress_budget: Option<u64>,
     ) -> Self {
         let (retransmit_sender, retransmit_receiver) = channel();
 
@@ -521,6 +642,7 @@ impl RetransmitStage {
             leader_schedule_cache,
             cluster_info.clone(),
             retransmit_receiver,
+            retransmit_egress_budget,
         );
 
         let leader_schedule_cache_clone = leader_schedule_cache.clone();
@@ -537,6 +659,7 @@ impl RetransmitStage {
             epoch_schedule,
             duplicate_slots_reset_sender,
             repair_validators,
+            repair_status,
         };
         let window_service = WindowService::new(
             blockstore,
@@ -638,6 +761,7 @@ mod tests {
             &leader_schedule_cache,
             cluster_info,
             Arc::new(Mutex::new(retransmit_receiver)),
+            None,
         );
         let _thread_hdls = vec![t_retransmit];
 
@@ -726,4 +850,23 @@ mod tests {
         assert_eq!(check_if_already_received(&packet, &shreds_received), None);
         assert_eq!(check_if_already_received(&packet, &shreds_received), None);
     }
+
+    #[test]
+    fn test_retransmit_budget() {
+        let budget = RetransmitBudget::new(1000);
+        // Keep it from refilling while the test runs
+        budget.available.lock().unwrap().1 = Instant::now() + Duration::from_secs(3600);
+
+        assert_eq!(budget.take(100, 5, false), 5);
+        // 500 bytes left, 10 sends don't fit all at once
+        assert_eq!(budget.take(100, 10, true), 0);
+        assert_eq!(budget.take(100, 10, false), 5);
+        assert_eq!(budget.take(100, 1, false), 0);
+
+        // Refills at the rate, up to a second's worth
+        budget.available.lock().unwrap().1 = Instant::now() - Duration::from_millis(500);
+        assert_eq!(budget.take(100, 10, false), 5);
+        budget.available.lock().unwrap().1 = Instant::now() - Duration::from_secs(10);
+        assert_eq!(budget.take(100, 20, false), 10);
+    }
 }
@@ -1,11 +1,17 @@
 //! The `rpc` module implements the Solana RPC interface.
 
 use crate::{
-    cluster_info::ClusterInfo,
+    cluster_info::{ClusterInfo, GossipConfig},
     contact_info::ContactInfo,
+    crds::CRDS_ORIGIN_AGE_BUCKETS_MS,
     non_circulating_supply::calculate_non_circulating_supply,
     optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
+    program_stats::ProgramStats,
+    repair_status::{RepairStatus, SlotRepairStatus},
+    rpc_auth::{RpcAuthConfig, RpcAuthMetadata, RpcAuthToken},
+    rpc_cache::{LargestAccountsCache, ValidatorDelegationsCache},
     rpc_health::*,
+    sample_performance_service::RecentPerfSamples,
     send_transaction_service::{SendTransactionService, TransactionInfo},
     validator::ValidatorExit,
 };
@@ -25,10 +31,11 @@ use solana_client::{
     rpc_custom_error::RpcCustomError,
     rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
     rpc_request::{
-        TokenAccountsFilter, DELINQUENT_VALIDATOR_SLOT_DISTANCE, MAX_GET_CONFIRMED_BLOCKS_RANGE,
-        MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT,
-        MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS_SLOT_RANGE,
-        MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS, MAX_MULTIPLE_ACCOUNTS, NUM_LARGEST_ACCOUNTS,
+        TokenAccountsFilter, DELINQUENT_VALIDATOR_SLOT_DISTANCE, MAX_BLOCK_REWARDS_ADDRESSES,
+        MAX_GET_CONFIRMED_BLOCKS_RANGE, MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS2_LIMIT,
+        MAX_GET_CONFIRMED_SIGNATURES_FOR_ADDRESS_SLOT_RANGE, MAX_GET_REPAIR_STATUS_MISSING_SHREDS,
+        MAX_GET_REPAIR_STATUS_SLOTS, MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS, MAX_MULTIPLE_ACCOUNTS,
+        NUM_LARGEST_ACCOUNTS, NUM_TOP_DELEGATORS,
     },
     rpc_response::Response as RpcResponse,
     rpc_response::*,
@@ -49,11 +56,12 @@ use solana_runtime::{
 use solana_sdk::{
     account::Account,
     account_utils::StateMut,
-    clock::{Slot, UnixTimestamp, MAX_RECENT_BLOCKHASHES},
+    clock::{Slot, UnixTimestamp},
     commitment_config::{CommitmentConfig, CommitmentLevel},
     epoch_info::EpochInfo,
     epoch_schedule::EpochSchedule,
     hash::Hash,
+    message::Message,
     pubkey::Pubkey,
     sanitize::Sanitize,
     signature::Signature,
@@ -64,8 +72,9 @@ use solana_sdk::{
 };
 use solana_stake_program::stake_state::StakeState;
 use solana_transaction_status::{
-    EncodedConfirmedBlock, EncodedConfirmedTransaction, TransactionConfirmationStatus,
-    TransactionStatus, UiTransactionEncoding,
+    map_inner_instructions, ConfirmedBlock, EncodedConfirmedBlock, EncodedConfirmedTransaction,
+    Rewards, TransactionConfirmationStatus, TransactionStatus, UiFailedInstruction,
+    UiInnerInstructions, UiTransactionEncoding,
 };
 use solana_vote_program::vote_state::{VoteState, MAX_LOCKOUT_HISTORY};
 use spl_token_v2_0::{
@@ -74,7 +83,7 @@ use spl_token_v2_0::{
 };
 use std::{
     cmp::{max, min},
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     net::SocketAddr,
     str::FromStr,
     sync::{
@@ -88,6 +97,7 @@ use tokio::runtime;
 
 pub const MAX_REQUEST_PAYLOAD_SIZE: usize = 50 * (1 << 10); // 50kB
 pub const PERFORMANCE_SAMPLES_LIMIT: usize = 720;
+const VALIDATOR_DELEGATIONS_CACHE_DURATION: u64 = 60;
 
 fn new_response<T>(bank: &Bank, value: T) -> RpcResponse<T> {
     let context = RpcResponseContext { slot: bank.slot() };
@@ -108,6 +118,7 @@ pub fn is_confirmed_rooted(
 pub struct JsonRpcConfig {
     pub enable_validator_exit: bool,
     pub enable_set_log_filter: bool,
+    pub enable_set_gossip_config: bool,
     pub enable_rpc_transaction_history: bool,
     pub identity_pubkey: Pubkey,
     pub faucet_addr: Option<SocketAddr>,
@@ -118,6 +129,10 @@ pub struct JsonRpcConfig {
     pub account_indexes: HashSet<AccountIndex>,
     pub rpc_threads: usize,
     pub rpc_bigtable_timeout: Option<Duration>,
+    pub object_storage_url: Option<String>,
+    pub enable_object_archive_upload: bool,
+    pub default_preflight_commitment: Option<CommitmentLevel>,
+    pub rpc_auth: Option<RpcAuthConfig>,
 }
 
 #[derive(Clone)]
@@ -134,10 +149,35 @@ pub struct JsonRpcRequestProcessor {
     transaction_sender: Arc<Mutex<Sender<TransactionInfo>>>,
     runtime_handle: runtime::Handle,
     bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
+    ledger_archive: Option<solana_storage_object::LedgerArchive>,
     optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
+    largest_accounts_cache: Arc<RwLock<LargestAccountsCache>>,
+    validator_delegations_cache: Arc<RwLock<ValidatorDelegationsCache>>,
+    program_stats: Arc<RwLock<ProgramStats>>,
+    repair_status: Arc<RwLock<RepairStatus>>,
+    recent_perf_samples: Arc<RwLock<RecentPerfSamples>>,
+    rpc_auth_token: Option<RpcAuthToken>,
+    authenticated_identity: Option<Pubkey>,
 }
 impl Metadata for JsonRpcRequestProcessor {}
 
+impl RpcAuthMetadata for JsonRpcRequestProcessor {
+    fn authenticated_identity(&self) -> Option<&Pubkey> {
+        self.authenticated_identity.as_ref()
+    }
+
+    fn rpc_auth_token(&self) -> Option<&RpcAuthToken> {
+        self.rpc_auth_token.as_ref()
+    }
+
+    fn with_authenticated_identity(&self, identity: Option<Pubkey>) -> Self {
+        Self {
+            authenticated_identity: identity,
+            ..self.clone()
+        }
+    }
+}
+
 impl JsonRpcRequestProcessor {
     #[allow(deprecated)]
     fn bank(&self, commitment: Option<CommitmentConfig>) -> Arc<Bank> {
@@ -216,7 +256,12 @@ impl JsonRpcRequestProcessor {
         genesis_hash: Hash,
         runtime: &runtime::Runtime,
         bigtable_ledger_storage: Option<solana_storage_bigtable::LedgerStorage>,
+        ledger_archive: Option<solana_storage_object::LedgerArchive>,
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
+        largest_accounts_cache: Arc<RwLock<LargestAccountsCache>>,
+        program_stats: Arc<RwLock<ProgramStats>>,
+        repair_status: Arc<RwLock<RepairStatus>>,
+        recent_perf_samples: Arc<RwLock<RecentPerfSamples>>,
     ) -> (Self, Receiver<TransactionInfo>) {
         let (sender, receiver) = channel();
         (
@@ -233,7 +278,17 @@ impl JsonRpcRequestProcessor {
                 transaction_sender: Arc::new(Mutex::new(sender)),
                 runtime_handle: runtime.handle().clone(),
                 bigtable_ledger_storage,
+                ledger_archive,
                 optimistically_confirmed_bank,
+                largest_accounts_cache,
+                validator_delegations_cache: Arc::new(RwLock::new(ValidatorDelegationsCache::new(
+                    VALIDATOR_DELEGATIONS_CACHE_DURATION,
+                ))),
+                program_stats,
+                repair_status,
+                recent_perf_samples,
+                rpc_auth_token: None,
+                authenticated_identity: None,
             },
             receiver,
         )
@@ -270,9 +325,29 @@ impl JsonRpcRequestProcessor {
             transaction_sender: Arc::new(Mutex::new(sender)),
             runtime_handle: runtime::Runtime::new().unwrap().handle().clone(),
             bigtable_ledger_storage: None,
+            ledger_archive: None,
             optimistically_confirmed_bank: Arc::new(RwLock::new(OptimisticallyConfirmedBank {
                 bank: bank.clone(),
             })),
+            largest_accounts_cache: Arc::new(RwLock::new(LargestAccountsCache::new(30))),
+            validator_delegations_cache: Arc::new(RwLock::new(ValidatorDelegationsCache::new(
+                VALIDATOR_DELEGATIONS_CACHE_DURATION,
+            ))),
+            program_stats: Arc::new(RwLock::new(ProgramStats::default())),
+            repair_status: Arc::new(RwLock::new(RepairStatus::default())),
+            recent_perf_samples: Arc::new(RwLock::new(RecentPerfSamples::default())),
+            rpc_auth_token: None,
+            authenticated_identity: None,
+        }
+    }
+
+    /// The processor of a request that came with `token`, not authenticated until the token is
+    /// verified against the request
+    pub fn with_rpc_auth_token(&self, token: Option<RpcAuthToken>) -> Self {
+        Self {
+            rpc_auth_token: token,
+            authenticated_identity: None,
+            ..self.clone()
         }
     }
 
@@ -439,6 +514,38 @@ impl JsonRpcRequestProcessor {
         )
     }
 
+    fn get_fee_for_message(
+        &self,
+        message: &Message,
+        commitment: Option<CommitmentConfig>,
+    ) -> Result<RpcResponse<RpcFeeForMessage>> {
+        let fee_payer = message
+            .account_keys
+            .first()
+            .ok_or_else(|| Error::invalid_params("message has no fee payer"))?;
+        let bank = self.bank(commitment);
+        let fee = bank.get_fee_for_message(message);
+        let fee_payer_balance = bank.get_balance(fee_payer);
+        let blockhash_valid = bank
+            .check_hash_age(&message.recent_blockhash, bank.max_processing_age())
+            .unwrap_or(false);
+        Ok(new_response(
+            &bank,
+            RpcFeeForMessage {
+                fee,
+                fee_payer: fee_payer.to_string(),
+                fee_payer_balance,
+                fee_payer_can_pay: fee.map_or(false, |fee| fee_payer_balance >= fee),
+                blockhash_valid,
+                last_valid_slot: bank.get_blockhash_last_valid_slot(&message.recent_blockhash),
+            },
+        ))
+    }
+
+    fn get_max_processing_age(&self, commitment: Option<CommitmentConfig>) -> usize {
+        self.bank(commitment).max_processing_age()
+    }
+
     fn get_fee_rate_governor(&self) -> RpcResponse<RpcFeeRateGovernor> {
         let bank = self.bank(None);
         let fee_rate_governor = bank.get_fee_rate_governor();
@@ -477,6 +584,184 @@ impl JsonRpcRequestProcessor {
         self.bank(commitment).slot()
     }
 
+    fn get_program_stats(&self, program_id: Option<Pubkey>) -> RpcProgramStatsWindow {
+        let program_stats = self.program_stats.read().unwrap();
+        let programs = match program_id {
+            Some(program_id) => program_stats
+                .program(&program_id)
+                .map(|entry| vec![(program_id, entry)])
+                .unwrap_or_default(),
+            None => program_stats.programs().into_iter().collect(),
+        };
+        let mut programs: Vec<_> = programs
+            .into_iter()
+            .map(|(program_id, entry)| RpcProgramStats {
+                program_id: program_id.to_string(),
+                invocations: entry.invocations,
+                failures: entry.failures,
+                error_rate: entry.error_rate(),
+                average_compute_units: entry.average_compute_units(),
+            })
+            .collect();
+        programs.sort_by(|a, b| {
+            b.invocations
+                .cmp(&a.invocations)
+                .then_with(|| a.program_id.cmp(&b.program_id))
+        });
+        RpcProgramStatsWindow {
+            first_slot: program_stats.first_slot(),
+            last_slot: program_stats.last_slot(),
+            programs,
+        }
+    }
+
+    fn get_slot_execute_timings(&self, slot: Slot) -> Option<RpcSlotExecuteTimings> {
+        let bank = self.bank_forks.read().unwrap().get(slot).cloned()?;
+        let timings = bank.execute_timings();
+        let mut programs: Vec<_> = timings
+            .details
+            .per_program_timings
+            .iter()
+            .map(|(program_id, program_timing)| RpcProgramTiming {
+                program_id: program_id.to_string(),
+                execute_us: program_timing.accumulated_us,
+                count: program_timing.count,
+            })
+            .collect();
+        programs.sort_by(|a, b| b.execute_us.cmp(&a.execute_us));
+        Some(RpcSlotExecuteTimings {
+            slot,
+            lock_us: timings.lock_us,
+            load_us: timings.load_us,
+            execute_us: timings.execute_us,
+            store_us: timings.store_us,
+            programs,
+        })
+    }
+
+    fn get_block_cost(&self, slot: Slot) -> Option<RpcBlockCost> {
+        let bank = self.bank_forks.read().unwrap().get(slot).cloned()?;
+        Some(RpcBlockCost {
+            slot,
+            units: bank.block_units(),
+            max_units: bank.max_block_units(),
+            limits_enabled: bank.block_cost_limits_enabled(),
+        })
+    }
+
+    fn get_repair_status(&self, limit: usize) -> Vec<RpcSlotRepairStatus> {
+        // Slots with repair activity plus the incomplete ones above the root, most recent first
+        let mut slots: BTreeSet<Slot> = {
+            let repair_status = self.repair_status.read().unwrap();
+            repair_status.slots().map(|(slot, _)| *slot).collect()
+        };
+        if let Ok(slot_metas) = self
+            .blockstore
+            .slot_meta_iterator(self.blockstore.last_root() + 1)
+        {
+            slots.extend(
+                slot_metas
+                    .filter(|(_, slot_meta)| !slot_meta.is_full())
+                    .map(|(slot, _)| slot),
+            );
+        }
+        let slots: Vec<(Slot, SlotRepairStatus)> = {
+            let repair_status = self.repair_status.read().unwrap();
+            slots
+                .into_iter()
+                .rev()
+                .take(limit)
+                .map(|slot| (slot, repair_status.slot(slot).cloned().unwrap_or_default()))
+                .collect()
+        };
+
+        slots
+            .into_iter()
+            .map(|(slot, status)| {
+                let slot_meta = self.blockstore.meta(slot).ok().flatten();
+                let mut repaired_from: Vec<_> = status
+                    .served_from
+                    .iter()
+                    .map(|(address, shreds)| RpcRepairSource {
+                        address: address.to_string(),
+                        shreds: *shreds,
+                    })
+                    .collect();
+                repaired_from.sort_by(|a, b| {
+                    b.shreds
+                        .cmp(&a.shreds)
+                        .then_with(|| a.address.cmp(&b.address))
+                });
+                RpcSlotRepairStatus {
+                    slot,
+                    is_full: slot_meta
+                        .as_ref()
+                        .map(|slot_meta| slot_meta.is_full())
+                        .unwrap_or(false),
+                    last_index: slot_meta
+                        .as_ref()
+                        .map(|slot_meta| slot_meta.last_index)
+                        .filter(|last_index| *last_index != std::u64::MAX),
+                    missing_shred_indexes: slot_meta
+                        .as_ref()
+                        .map(|slot_meta| {
+                            self.blockstore.find_missing_data_indexes_in_slot(
+                                slot_meta,
+                                MAX_GET_REPAIR_STATUS_MISSING_SHREDS,
+                            )
+                        })
+                        .unwrap_or_default(),
+                    repair_requests: status.requests,
+                    outstanding_repairs: status.outstanding(),
+                    repaired_from,
+                }
+            })
+            .collect()
+    }
+
+    fn get_gossip_stats(&self) -> RpcGossipStats {
+        let composition = self.cluster_info.crds_composition();
+        let totals = self.cluster_info.gossip_totals();
+        let success_rate = |inserted: u64, received: u64| {
+            if received == 0 {
+                None
+            } else {
+                Some(inserted as f64 / received as f64)
+            }
+        };
+        RpcGossipStats {
+            num_crds_values: composition.values_per_type.values().sum(),
+            crds_values_per_type: composition
+                .values_per_type
+                .iter()
+                .map(|(name, count)| (name.to_string(), *count))
+                .collect(),
+            origin_age_histogram: composition
+                .origin_ages
+                .iter()
+                .enumerate()
+                .map(|(i, origins)| RpcOriginAgeBucket {
+                    max_age_ms: CRDS_ORIGIN_AGE_BUCKETS_MS.get(i).copied(),
+                    origins: *origins,
+                })
+                .collect(),
+            push_values_received: totals.push_values_received,
+            push_values_inserted: totals.push_values_inserted,
+            push_success_rate: success_rate(
+                totals.push_values_inserted,
+                totals.push_values_received,
+            ),
+            pull_response_values_received: totals.pull_response_values_received,
+            pull_response_values_inserted: totals.pull_response_values_inserted,
+            pull_success_rate: success_rate(
+                totals.pull_response_values_inserted,
+                totals.pull_response_values_received,
+            ),
+            prune_messages_sent: totals.prune_messages_sent,
+            prune_messages_received: totals.prune_messages_received,
+        }
+    }
+
     fn get_slot_leader(&self, commitment: Option<CommitmentConfig>) -> String {
         self.bank(commitment).collector_id().to_string()
     }
@@ -508,7 +793,17 @@ impl JsonRpcRequestProcessor {
     ) -> RpcResponse<Vec<RpcAccountBalance>> {
         let config = config.unwrap_or_default();
         let bank = self.bank(config.commitment);
-        let (addresses, address_filter) = if let Some(filter) = config.filter {
+        let commitment = config.commitment.unwrap_or_default().commitment;
+
+        if let Some((slot, accounts)) = self.get_cached_largest_accounts(&config.filter, commitment)
+        {
+            return Response {
+                context: RpcResponseContext { slot },
+                value: accounts,
+            };
+        }
+
+        let (addresses, address_filter) = if let Some(filter) = config.filter.clone() {
             let non_circulating_supply = calculate_non_circulating_supply(&bank);
             let addresses = non_circulating_supply.accounts.into_iter().collect();
             let address_filter = match filter {
@@ -519,16 +814,37 @@ impl JsonRpcRequestProcessor {
         } else {
             (HashSet::new(), AccountAddressFilter::Exclude)
         };
-        new_response(
-            &bank,
-            bank.get_largest_accounts(NUM_LARGEST_ACCOUNTS, &addresses, address_filter)
-                .into_iter()
-                .map(|(address, lamports)| RpcAccountBalance {
-                    address: address.to_string(),
-                    lamports,
-                })
-                .collect(),
-        )
+        let accounts = bank
+            .get_largest_accounts(NUM_LARGEST_ACCOUNTS, &addresses, address_filter)
+            .into_iter()
+            .map(|(address, lamports)| RpcAccountBalance {
+                address: address.to_string(),
+                lamports,
+            })
+            .collect::<Vec<RpcAccountBalance>>();
+
+        self.set_cached_largest_accounts(&config.filter, commitment, bank.slot(), &accounts);
+        new_response(&bank, accounts)
+    }
+
+    fn get_cached_largest_accounts(
+        &self,
+        filter: &Option<RpcLargestAccountsFilter>,
+        commitment: CommitmentLevel,
+    ) -> Option<(Slot, Vec<RpcAccountBalance>)> {
+        let largest_accounts_cache = self.largest_accounts_cache.read().unwrap();
+        largest_accounts_cache.get_largest_accounts(filter, commitment)
+    }
+
+    fn set_cached_largest_accounts(
+        &self,
+        filter: &Option<RpcLargestAccountsFilter>,
+        commitment: CommitmentLevel,
+        slot: Slot,
+        accounts: &[RpcAccountBalance],
+    ) {
+        let mut largest_accounts_cache = self.largest_accounts_cache.write().unwrap();
+        largest_accounts_cache.set_largest_accounts(filter, commitment, slot, accounts)
     }
 
     fn get_supply(&self, commitment: Option<CommitmentConfig>) -> RpcResponse<RpcSupply> {
@@ -604,12 +920,76 @@ impl JsonRpcRequestProcessor {
         })
     }
 
+    fn get_validator_delegations(
+        &self,
+        config: Option<RpcValidatorDelegationsConfig>,
+    ) -> Result<RpcResponse<Vec<RpcValidatorDelegations>>> {
+        let config = config.unwrap_or_default();
+        let vote_pubkey = config.vote_pubkey.map(verify_pubkey).transpose()?;
+
+        let cached = self
+            .validator_delegations_cache
+            .read()
+            .unwrap()
+            .get_validator_delegations();
+        let (slot, mut delegations) = match cached {
+            Some(cached) => cached,
+            None => {
+                let bank = self.bank(config.commitment);
+                let delegations = calculate_validator_delegations(&bank)?;
+                self.validator_delegations_cache
+                    .write()
+                    .unwrap()
+                    .set_validator_delegations(bank.slot(), &delegations);
+                (bank.slot(), delegations)
+            }
+        };
+        if let Some(vote_pubkey) = vote_pubkey {
+            let vote_pubkey = vote_pubkey.to_string();
+            delegations.retain(|delegations| delegations.vote_pubkey == vote_pubkey);
+        }
+        Ok(Response {
+            context: RpcResponseContext { slot },
+            value: delegations,
+        })
+    }
+
     pub fn set_log_filter(&self, filter: String) {
         if self.config.enable_set_log_filter {
             solana_logger::setup_with(&filter);
         }
     }
 
+    pub fn set_gossip_config(&self, config: RpcGossipConfig) -> Result<bool> {
+        if !self.config.enable_set_gossip_config {
+            debug!("set_gossip_config ignored");
+            return Ok(false);
+        }
+        // Anyone reaching the RPC port could otherwise silence this node's gossip
+        let restricted = self.config.rpc_auth.as_ref().map_or(false, |rpc_auth| {
+            rpc_auth.restricted_methods.contains("setGossipConfig")
+        });
+        if !restricted {
+            warn!("set_gossip_config ignored, the method is not restricted by rpc auth");
+            return Ok(false);
+        }
+        let gossip_config = self.cluster_info.gossip_config();
+        self.cluster_info
+            .set_gossip_config(GossipConfig {
+                gossip_interval_ms: config
+                    .gossip_interval_ms
+                    .unwrap_or(gossip_config.gossip_interval_ms),
+                pull_request_interval_ms: config
+                    .pull_request_interval_ms
+                    .unwrap_or(gossip_config.pull_request_interval_ms),
+                max_bytes_per_second: config
+                    .max_bytes_per_second
+                    .unwrap_or(gossip_config.max_bytes_per_second),
+            })
+            .map_err(Error::invalid_params)?;
+        Ok(true)
+    }
+
     pub fn validator_exit(&self) -> bool {
         if self.config.enable_validator_exit {
             warn!("validator_exit request...");
@@ -688,12 +1068,44 @@ impl JsonRpcRequestProcessor {
         Ok(())
     }
 
+    // Fetches a block purged from the blockstore from the object storage ledger archive
+    fn get_archived_block(&self, slot: Slot) -> Option<ConfirmedBlock> {
+        let ledger_archive = self.ledger_archive.as_ref()?;
+        self.runtime_handle
+            .block_on(ledger_archive.get_confirmed_block(slot))
+            .unwrap_or_else(|err| {
+                warn!("ledger archive: get_confirmed_block({}): {}", slot, err);
+                None
+            })
+    }
+
     pub fn get_confirmed_block(
         &self,
         slot: Slot,
         encoding: Option<UiTransactionEncoding>,
     ) -> Result<Option<EncodedConfirmedBlock>> {
         let encoding = encoding.unwrap_or(UiTransactionEncoding::Json);
+        Ok(self
+            .get_unencoded_confirmed_block(slot)?
+            .map(|confirmed_block| confirmed_block.encode(encoding)))
+    }
+
+    pub fn get_confirmed_block_rewards(
+        &self,
+        slot: Slot,
+        addresses: Option<HashSet<String>>,
+    ) -> Result<Option<RpcBlockRewards>> {
+        let epoch = self.bank(None).epoch_schedule().get_epoch(slot);
+        Ok(self
+            .get_unencoded_confirmed_block(slot)?
+            .map(|confirmed_block| RpcBlockRewards {
+                slot,
+                epoch,
+                rewards: filter_block_rewards(confirmed_block.rewards, addresses.as_ref()),
+            }))
+    }
+
+    fn get_unencoded_confirmed_block(&self, slot: Slot) -> Result<Option<ConfirmedBlock>> {
         if self.config.enable_rpc_transaction_history
             && slot
                 <= self
@@ -710,15 +1122,14 @@ impl JsonRpcRequestProcessor {
                         .runtime_handle
                         .block_on(bigtable_ledger_storage.get_confirmed_block(slot));
                     self.check_bigtable_result(&bigtable_result)?;
-                    return Ok(bigtable_result
-                        .ok()
-                        .map(|confirmed_block| confirmed_block.encode(encoding)));
+                    return Ok(bigtable_result.ok());
+                }
+                if let Some(confirmed_block) = self.get_archived_block(slot) {
+                    return Ok(Some(confirmed_block));
                 }
             }
             self.check_slot_cleaned_up(&result, slot)?;
-            Ok(result
-                .ok()
-                .map(|confirmed_block| confirmed_block.encode(encoding)))
+            Ok(result.ok())
         } else {
             Err(RpcCustomError::BlockNotAvailable { slot }.into())
         }
@@ -768,6 +1179,25 @@ impl JsonRpcRequestProcessor {
                         )
                     });
             }
+            if let Some(ledger_archive) = &self.ledger_archive {
+                let mut archived_blocks = self
+                    .runtime_handle
+                    .block_on(
+                        ledger_archive
+                            .get_confirmed_blocks(start_slot, (end_slot - start_slot) as usize + 1),
+                    )
+                    .map_err(|_| {
+                        Error::invalid_params("Ledger archive query failed".to_string())
+                    })?;
+                archived_blocks.retain(|&slot| slot <= end_slot && slot < lowest_blockstore_slot);
+                archived_blocks.extend(
+                    self.blockstore
+                        .rooted_slot_iterator(lowest_blockstore_slot)
+                        .map_err(|_| Error::internal_error())?
+                        .filter(|&slot| slot <= end_slot),
+                );
+                return Ok(archived_blocks);
+            }
         }
 
         Ok(self
@@ -801,6 +1231,21 @@ impl JsonRpcRequestProcessor {
                     .block_on(bigtable_ledger_storage.get_confirmed_blocks(start_slot, limit))
                     .unwrap_or_else(|_| vec![]));
             }
+            if let Some(ledger_archive) = &self.ledger_archive {
+                let mut archived_blocks = self
+                    .runtime_handle
+                    .block_on(ledger_archive.get_confirmed_blocks(start_slot, limit))
+                    .unwrap_or_else(|_| vec![]);
+                archived_blocks.retain(|&slot| slot < lowest_blockstore_slot);
+                let remaining = limit - archived_blocks.len();
+                archived_blocks.extend(
+                    self.blockstore
+                        .rooted_slot_iterator(lowest_blockstore_slot)
+                        .map_err(|_| Error::internal_error())?
+                        .take(remaining),
+                );
+                return Ok(archived_blocks);
+            }
         }
 
         Ok(self
@@ -831,6 +1276,9 @@ impl JsonRpcRequestProcessor {
                         .ok()
                         .and_then(|confirmed_block| confirmed_block.block_time));
                 }
+                if let Some(confirmed_block) = self.get_archived_block(slot) {
+                    return Ok(confirmed_block.block_time);
+                }
             }
             self.check_slot_cleaned_up(&result, slot)?;
             Ok(result.ok().unwrap_or(None))
@@ -1091,6 +1539,18 @@ impl JsonRpcRequestProcessor {
                 return bigtable_slot;
             }
         }
+
+        if let Some(ledger_archive) = &self.ledger_archive {
+            let archived_slot = self
+                .runtime_handle
+                .block_on(ledger_archive.get_first_available_block())
+                .unwrap_or(None)
+                .unwrap_or(slot);
+
+            if archived_slot < slot {
+                return archived_slot;
+            }
+        }
         slot
     }
 
@@ -1476,12 +1936,154 @@ fn verify_filter(input: &RpcFilterType) -> Result<()> {
         .map_err(|e| Error::invalid_params(format!("Invalid param: {:?}", e)))
 }
 
+/// Scans the stake accounts of `bank` to break down the active stake of each vote account
+/// between the validator's own stake and the stake of other delegators
+fn calculate_validator_delegations(bank: &Bank) -> Result<Vec<RpcValidatorDelegations>> {
+    #[derive(Default)]
+    struct Delegator {
+        active_stake: u64,
+        stake_account_count: usize,
+    }
+
+    let stake_history_account = bank
+        .get_account(&stake_history::id())
+        .ok_or_else(Error::internal_error)?;
+    let stake_history = solana_sdk::account::from_account::<StakeHistory>(&stake_history_account)
+        .ok_or_else(Error::internal_error)?;
+    let epoch = bank.epoch();
+    let fix_stake_deactivate = bank.stake_program_v2_enabled();
+
+    let mut delegators: HashMap<Pubkey, HashMap<Pubkey, Delegator>> = HashMap::new();
+    let mut self_stake: HashMap<Pubkey, u64> = HashMap::new();
+    let vote_accounts: HashMap<_, _> = bank.vote_accounts().into_iter().collect();
+    for (_, account) in bank.get_program_accounts(&solana_stake_program::id()) {
+        let (meta, stake) = match account.state() {
+            Ok(StakeState::Stake(meta, stake)) => (meta, stake),
+            _ => continue,
+        };
+        let active_stake = stake.stake(epoch, Some(&stake_history), fix_stake_deactivate);
+        if active_stake == 0 {
+            continue;
+        }
+        let vote_pubkey = stake.delegation.voter_pubkey;
+        let vote_state = match vote_accounts.get(&vote_pubkey) {
+            Some((_, vote_account)) => vote_account.vote_state(),
+            None => continue,
+        };
+        if let Ok(vote_state) = vote_state.as_ref() {
+            let validator_authorities = [vote_state.node_pubkey, vote_state.authorized_withdrawer];
+            if validator_authorities.contains(&meta.authorized.staker)
+                || validator_authorities.contains(&meta.authorized.withdrawer)
+            {
+                *self_stake.entry(vote_pubkey).or_default() += active_stake;
+            }
+        }
+        let delegator = delegators
+            .entry(vote_pubkey)
+            .or_default()
+            .entry(meta.authorized.staker)
+            .or_default();
+        delegator.active_stake += active_stake;
+        delegator.stake_account_count += 1;
+    }
+
+    let default_vote_state = VoteState::default();
+    let mut validator_delegations: Vec<_> = delegators
+        .into_iter()
+        .map(|(vote_pubkey, delegators)| {
+            let vote_state = vote_accounts
+                .get(&vote_pubkey)
+                .map(|(_, vote_account)| vote_account.vote_state());
+            let node_pubkey = vote_state
+                .as_ref()
+                .and_then(|vote_state| vote_state.as_ref().ok())
+                .unwrap_or(&default_vote_state)
+                .node_pubkey;
+            let active_stake: u64 = delegators
+                .values()
+                .map(|delegator| delegator.active_stake)
+                .sum();
+            let self_stake = self_stake.get(&vote_pubkey).copied().unwrap_or_default();
+            let stake_account_count: usize = delegators
+                .values()
+                .map(|delegator| delegator.stake_account_count)
+                .sum();
+            let delegator_count = delegators.len();
+            let mut top_delegators: Vec<_> = delegators
+                .into_iter()
+                .map(|(staker, delegator)| RpcDelegator {
+                    staker: staker.to_string(),
+                    active_stake: delegator.active_stake,
+                    stake_account_count: delegator.stake_account_count,
+                })
+                .collect();
+            top_delegators.sort_by(|a, b| {
+                b.active_stake
+                    .cmp(&a.active_stake)
+                    .then_with(|| a.staker.cmp(&b.staker))
+            });
+            top_delegators.truncate(NUM_TOP_DELEGATORS);
+            RpcValidatorDelegations {
+                vote_pubkey: vote_pubkey.to_string(),
+                node_pubkey: node_pubkey.to_string(),
+                active_stake,
+                self_stake,
+                foreign_stake: active_stake - self_stake,
+                stake_account_count,
+                delegator_count,
+                top_delegators,
+            }
+        })
+        .collect();
+    validator_delegations.sort_by(|a, b| {
+        b.active_stake
+            .cmp(&a.active_stake)
+            .then_with(|| a.vote_pubkey.cmp(&b.vote_pubkey))
+    });
+    Ok(validator_delegations)
+}
+
 fn verify_pubkey(input: String) -> Result<Pubkey> {
     input
         .parse()
         .map_err(|e| Error::invalid_params(format!("Invalid param: {:?}", e)))
 }
 
+/// Parses the addresses an `RpcBlockRewardsConfig` filters rewards on, `None` when rewards are
+/// not filtered
+pub(crate) fn verify_block_rewards_addresses(
+    config: Option<RpcBlockRewardsConfig>,
+) -> Result<Option<HashSet<String>>> {
+    let addresses = match config.and_then(|config| config.addresses) {
+        Some(addresses) => addresses,
+        None => return Ok(None),
+    };
+    if addresses.len() > MAX_BLOCK_REWARDS_ADDRESSES {
+        return Err(Error::invalid_params(format!(
+            "Too many addresses provided; max {}",
+            MAX_BLOCK_REWARDS_ADDRESSES
+        )));
+    }
+    addresses
+        .into_iter()
+        .map(|address| verify_pubkey(address).map(|pubkey| pubkey.to_string()))
+        .collect::<Result<HashSet<_>>>()
+        .map(Some)
+}
+
+pub(crate) fn filter_block_rewards(
+    rewards: Rewards,
+    addresses: Option<&HashSet<String>>,
+) -> Rewards {
+    match addresses {
+        Some(addresses) => rewards
+            .into_iter()
+            .filter(|reward| addresses.contains(&reward.pubkey))
+            .collect(),
+        None => rewards,
+    }
+}
+
 fn verify_signature(input: &str) -> Result<Signature> {
     input
         .parse()
@@ -1832,15 +2434,33 @@ pub trait RpcSol {
         commitment: Option<CommitmentConfig>,
     ) -> Result<RpcResponse<Option<RpcFeeCalculator>>>;
 
+    #[rpc(meta, name = "getFeeForMessage")]
+    fn get_fee_for_message(
+        &self,
+        meta: Self::Metadata,
+        data: String,
+        config: Option<RpcFeeForMessageConfig>,
+    ) -> Result<RpcResponse<RpcFeeForMessage>>;
+
     #[rpc(meta, name = "getFeeRateGovernor")]
     fn get_fee_rate_governor(
         &self,
         meta: Self::Metadata,
     ) -> Result<RpcResponse<RpcFeeRateGovernor>>;
 
+    #[rpc(meta, name = "getMaxProcessingAge")]
+    fn get_max_processing_age(
+        &self,
+        meta: Self::Metadata,
+        commitment: Option<CommitmentConfig>,
+    ) -> Result<usize>;
+
     #[rpc(meta, name = "getSnapshotSlot")]
     fn get_snapshot_slot(&self, meta: Self::Metadata) -> Result<Slot>;
 
+    #[rpc(meta, name = "getHighestSnapshotSlot")]
+    fn get_highest_snapshot_slot(&self, meta: Self::Metadata) -> Result<RpcSnapshotSlotInfo>;
+
     #[rpc(meta, name = "getSignatureStatuses")]
     fn get_signature_statuses(
         &self,
@@ -1923,6 +2543,13 @@ pub trait RpcSol {
         commitment: Option<CommitmentConfig>,
     ) -> Result<RpcVoteAccountStatus>;
 
+    #[rpc(meta, name = "getValidatorDelegations")]
+    fn get_validator_delegations(
+        &self,
+        meta: Self::Metadata,
+        config: Option<RpcValidatorDelegationsConfig>,
+    ) -> Result<RpcResponse<Vec<RpcValidatorDelegations>>>;
+
     #[rpc(meta, name = "validatorExit")]
     fn validator_exit(&self, meta: Self::Metadata) -> Result<bool>;
 
@@ -1935,6 +2562,9 @@ pub trait RpcSol {
     #[rpc(meta, name = "setLogFilter")]
     fn set_log_filter(&self, _meta: Self::Metadata, filter: String) -> Result<()>;
 
+    #[rpc(meta, name = "setGossipConfig")]
+    fn set_gossip_config(&self, meta: Self::Metadata, config: RpcGossipConfig) -> Result<bool>;
+
     #[rpc(meta, name = "getConfirmedBlock")]
     fn get_confirmed_block(
         &self,
@@ -1943,6 +2573,41 @@ pub trait RpcSol {
         encoding: Option<UiTransactionEncoding>,
     ) -> Result<Option<EncodedConfirmedBlock>>;
 
+    #[rpc(meta, name = "getConfirmedBlockRewards")]
+    fn get_confirmed_block_rewards(
+        &self,
+        meta: Self::Metadata,
+        slot: Slot,
+        config: Option<RpcBlockRewardsConfig>,
+    ) -> Result<Option<RpcBlockRewards>>;
+
+    #[rpc(meta, name = "getProgramStats")]
+    fn get_program_stats(
+        &self,
+        meta: Self::Metadata,
+        program_id_str: Option<String>,
+    ) -> Result<RpcProgramStatsWindow>;
+
+    #[rpc(meta, name = "getRepairStatus")]
+    fn get_repair_status(
+        &self,
+        meta: Self::Metadata,
+        limit: Option<usize>,
+    ) -> Result<Vec<RpcSlotRepairStatus>>;
+
+    #[rpc(meta, name = "getGossipStats")]
+    fn get_gossip_stats(&self, meta: Self::Metadata) -> Result<RpcGossipStats>;
+
+    #[rpc(meta, name = "getSlotExecuteTimings")]
+    fn get_slot_execute_timings(
+        &self,
+        meta: Self::Metadata,
+        slot: Slot,
+    ) -> Result<Option<RpcSlotExecuteTimings>>;
+
+    #[rpc(meta, name = "getBlockCost")]
+    fn get_block_cost(&self, meta: Self::Metadata, slot: Slot) -> Result<Option<RpcBlockCost>>;
+
     #[rpc(meta, name = "getBlockTime")]
     fn get_block_time(&self, meta: Self::Metadata, slot: Slot) -> Result<Option<UnixTimestamp>>;
 
@@ -2213,12 +2878,10 @@ impl RpcSol for RpcSolImpl {
         }
 
         Ok(meta
-            .blockstore
-            .get_recent_perf_samples(limit)
-            .map_err(|err| {
-                warn!("get_recent_performance_samples failed: {:?}", err);
-                Error::invalid_request()
-            })?
+            .recent_perf_samples
+            .read()
+            .unwrap()
+            .recent(limit)
             .iter()
             .map(|(slot, sample)| RpcPerfSample {
                 slot: *slot,
@@ -2258,6 +2921,7 @@ impl RpcSol for RpcSolImpl {
                         gossip: Some(contact_info.gossip),
                         tpu: valid_address_or_none(&contact_info.tpu),
                         rpc: valid_address_or_none(&contact_info.rpc),
+                        pubsub: valid_address_or_none(&contact_info.rpc_pubsub),
                         version,
                         feature_set,
                     })
@@ -2361,6 +3025,19 @@ impl RpcSol for RpcSolImpl {
         Ok(meta.get_fee_calculator_for_blockhash(&blockhash, commitment))
     }
 
+    fn get_fee_for_message(
+        &self,
+        meta: Self::Metadata,
+        data: String,
+        config: Option<RpcFeeForMessageConfig>,
+    ) -> Result<RpcResponse<RpcFeeForMessage>> {
+        debug!("get_fee_for_message rpc request received");
+        let config = config.unwrap_or_default();
+        let encoding = config.encoding.unwrap_or(UiTransactionEncoding::Base58);
+        let message = deserialize_message(data, encoding)?;
+        meta.get_fee_for_message(&message, config.commitment)
+    }
+
     fn get_fee_rate_governor(
         &self,
         meta: Self::Metadata,
@@ -2369,6 +3046,15 @@ impl RpcSol for RpcSolImpl {
         Ok(meta.get_fee_rate_governor())
     }
 
+    fn get_max_processing_age(
+        &self,
+        meta: Self::Metadata,
+        commitment: Option<CommitmentConfig>,
+    ) -> Result<usize> {
+        debug!("get_max_processing_age rpc request received");
+        Ok(meta.get_max_processing_age(commitment))
+    }
+
     fn get_signature_confirmation(
         &self,
         meta: Self::Metadata,
@@ -2408,6 +3094,21 @@ impl RpcSol for RpcSolImpl {
             .ok_or_else(|| RpcCustomError::NoSnapshot.into())
     }
 
+    fn get_highest_snapshot_slot(&self, meta: Self::Metadata) -> Result<RpcSnapshotSlotInfo> {
+        debug!("get_highest_snapshot_slot rpc request received");
+
+        // This node only produces full snapshots, so there is never an incremental one
+        meta.snapshot_config
+            .and_then(|snapshot_config| {
+                get_highest_snapshot_archive_path(&snapshot_config.snapshot_package_output_path)
+            })
+            .map(|(_, (slot, _, _))| RpcSnapshotSlotInfo {
+                full: slot,
+                incremental: None,
+            })
+            .ok_or_else(|| RpcCustomError::NoSnapshot.into())
+    }
+
     fn get_signature_statuses(
         &self,
         meta: Self::Metadata,
@@ -2527,6 +3228,7 @@ impl RpcSol for RpcSolImpl {
 
         let preflight_commitment = config
             .preflight_commitment
+            .or(meta.config.default_preflight_commitment)
             .map(|commitment| CommitmentConfig { commitment });
         let preflight_bank = &*meta.bank(preflight_commitment);
 
@@ -2540,11 +3242,11 @@ impl RpcSol for RpcSolImpl {
             })
             .map(|&pubkey| (pubkey, transaction.message.recent_blockhash));
         if durable_nonce_info.is_some() {
-            // While it uses a defined constant, this last_valid_slot value is chosen arbitrarily.
-            // It provides a fallback timeout for durable-nonce transaction retries in case of
-            // malicious packing of the retry queue. Durable-nonce transactions are otherwise
-            // retried until the nonce is advanced.
-            last_valid_slot = preflight_bank.slot() + MAX_RECENT_BLOCKHASHES as u64;
+            // While it uses the max processing age, this last_valid_slot value is chosen
+            // arbitrarily. It provides a fallback timeout for durable-nonce transaction retries in
+            // case of malicious packing of the retry queue. Durable-nonce transactions are
+            // otherwise retried until the nonce is advanced.
+            last_valid_slot = preflight_bank.slot() + preflight_bank.max_processing_age() as u64;
         }
 
         if !config.skip_preflight {
@@ -2562,12 +3264,22 @@ impl RpcSol for RpcSolImpl {
                 }
             }
 
-            if let (Err(err), logs) = preflight_bank.simulate_transaction(transaction.clone()) {
+            if let (Err(err), logs, _) = preflight_bank.simulate_transaction(transaction.clone()) {
+                let failed_instruction = UiFailedInstruction::new(&err, &transaction.message);
+                let message = match &failed_instruction {
+                    Some(failed_instruction) => format!(
+                        "Transaction simulation failed: {} ({})",
+                        err, failed_instruction
+                    ),
+                    None => format!("Transaction simulation failed: {}", err),
+                };
                 return Err(RpcCustomError::SendTransactionPreflightFailure {
-                    message: format!("Transaction simulation failed: {}", err),
+                    message,
                     result: RpcSimulateTransactionResult {
                         err: Some(err),
                         logs: Some(logs),
+                        failed_instruction,
+                        inner_instructions: None,
                     },
                 }
                 .into());
@@ -2601,13 +3313,28 @@ impl RpcSol for RpcSolImpl {
         }
 
         let bank = &*meta.bank(config.commitment);
-        let (result, logs) = bank.simulate_transaction(transaction);
+        let (result, logs, inner_instructions) = bank.simulate_transaction(transaction.clone());
+        let err = result.err();
+        let failed_instruction = err
+            .as_ref()
+            .and_then(|err| UiFailedInstruction::new(err, &transaction.message));
+        let inner_instructions = if config.inner_instructions {
+            inner_instructions.map(|inner_instructions| {
+                map_inner_instructions(inner_instructions)
+                    .map(|ix| UiInnerInstructions::parse(ix, &transaction.message))
+                    .collect()
+            })
+        } else {
+            None
+        };
 
         Ok(new_response(
             &bank,
             RpcSimulateTransactionResult {
-                err: result.err(),
+                err,
                 logs: Some(logs),
+                failed_instruction,
+                inner_instructions,
             },
         ))
     }
@@ -2635,6 +3362,15 @@ impl RpcSol for RpcSolImpl {
         meta.get_vote_accounts(commitment)
     }
 
+    fn get_validator_delegations(
+        &self,
+        meta: Self::Metadata,
+        config: Option<RpcValidatorDelegationsConfig>,
+    ) -> Result<RpcResponse<Vec<RpcValidatorDelegations>>> {
+        debug!("get_validator_delegations rpc request received");
+        meta.get_validator_delegations(config)
+    }
+
     fn validator_exit(&self, meta: Self::Metadata) -> Result<bool> {
         debug!("validator_exit rpc request received");
         Ok(meta.validator_exit())
@@ -2662,6 +3398,11 @@ impl RpcSol for RpcSolImpl {
         Ok(())
     }
 
+    fn set_gossip_config(&self, meta: Self::Metadata, config: RpcGossipConfig) -> Result<bool> {
+        debug!("set_gossip_config rpc request received: {:?}", config);
+        meta.set_gossip_config(config)
+    }
+
     fn get_confirmed_block(
         &self,
         meta: Self::Metadata,
@@ -2672,6 +3413,20 @@ impl RpcSol for RpcSolImpl {
         meta.get_confirmed_block(slot, encoding)
     }
 
+    fn get_confirmed_block_rewards(
+        &self,
+        meta: Self::Metadata,
+        slot: Slot,
+        config: Option<RpcBlockRewardsConfig>,
+    ) -> Result<Option<RpcBlockRewards>> {
+        debug!(
+            "get_confirmed_block_rewards rpc request received: {:?}",
+            slot
+        );
+        let addresses = verify_block_rewards_addresses(config)?;
+        meta.get_confirmed_block_rewards(slot, addresses)
+    }
+
     fn get_confirmed_blocks(
         &self,
         meta: Self::Metadata,
@@ -2702,6 +3457,54 @@ impl RpcSol for RpcSolImpl {
         meta.get_block_time(slot)
     }
 
+    fn get_program_stats(
+        &self,
+        meta: Self::Metadata,
+        program_id_str: Option<String>,
+    ) -> Result<RpcProgramStatsWindow> {
+        debug!(
+            "get_program_stats rpc request received: {:?}",
+            program_id_str
+        );
+        let program_id = program_id_str.map(verify_pubkey).transpose()?;
+        Ok(meta.get_program_stats(program_id))
+    }
+
+    fn get_repair_status(
+        &self,
+        meta: Self::Metadata,
+        limit: Option<usize>,
+    ) -> Result<Vec<RpcSlotRepairStatus>> {
+        debug!("get_repair_status rpc request received: {:?}", limit);
+        let limit = limit.unwrap_or(MAX_GET_REPAIR_STATUS_SLOTS);
+        if limit == 0 || limit > MAX_GET_REPAIR_STATUS_SLOTS {
+            return Err(Error::invalid_params(format!(
+                "Invalid limit; max {}",
+                MAX_GET_REPAIR_STATUS_SLOTS
+            )));
+        }
+        Ok(meta.get_repair_status(limit))
+    }
+
+    fn get_gossip_stats(&self, meta: Self::Metadata) -> Result<RpcGossipStats> {
+        debug!("get_gossip_stats rpc request received");
+        Ok(meta.get_gossip_stats())
+    }
+
+    fn get_slot_execute_timings(
+        &self,
+        meta: Self::Metadata,
+        slot: Slot,
+    ) -> Result<Option<RpcSlotExecuteTimings>> {
+        debug!("get_slot_execute_timings rpc request received: {}", slot);
+        Ok(meta.get_slot_execute_timings(slot))
+    }
+
+    fn get_block_cost(&self, meta: Self::Metadata, slot: Slot) -> Result<Option<RpcBlockCost>> {
+        debug!("get_block_cost rpc request received: {}", slot);
+        Ok(meta.get_block_cost(slot))
+    }
+
     fn get_confirmed_transaction(
         &self,
         meta: Self::Metadata,
@@ -2873,10 +3676,10 @@ impl RpcSol for RpcSolImpl {
 
 const WORST_CASE_BASE58_TX: usize = 1683; // Golden, bump if PACKET_DATA_SIZE changes
 const WORST_CASE_BASE64_TX: usize = 1644; // Golden, bump if PACKET_DATA_SIZE changes
-fn deserialize_transaction(
+fn decode_wire_data(
     encoded_transaction: String,
     encoding: UiTransactionEncoding,
-) -> Result<(Vec<u8>, Transaction)> {
+) -> Result<Vec<u8>> {
     let wire_transaction = match encoding {
         UiTransactionEncoding::Base58 => {
             inc_new_counter_info!("rpc-base58_encoded_tx", 1);
@@ -2921,6 +3724,14 @@ fn deserialize_transaction(
         info!("{}", err);
         return Err(Error::invalid_params(&err));
     }
+    Ok(wire_transaction)
+}
+
+fn deserialize_transaction(
+    encoded_transaction: String,
+    encoding: UiTransactionEncoding,
+) -> Result<(Vec<u8>, Transaction)> {
+    let wire_transaction = decode_wire_data(encoded_transaction, encoding)?;
     bincode::options()
         .with_limit(PACKET_DATA_SIZE as u64)
         .with_fixint_encoding()
@@ -2943,6 +3754,29 @@ fn deserialize_transaction(
         .map(|transaction| (wire_transaction, transaction))
 }
 
+fn deserialize_message(
+    encoded_message: String,
+    encoding: UiTransactionEncoding,
+) -> Result<Message> {
+    let wire_message = decode_wire_data(encoded_message, encoding)?;
+    bincode::options()
+        .with_limit(PACKET_DATA_SIZE as u64)
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .deserialize_from(&wire_message[..])
+        .map_err(|err| {
+            info!("message deserialize error: {:?}", err);
+            Error::invalid_params(&err.to_string())
+        })
+        .and_then(|message: Message| {
+            if let Err(err) = message.sanitize() {
+                Err(Error::invalid_params(format!("invalid message: {}", err)))
+            } else {
+                Ok(message)
+            }
+        })
+}
+
 pub(crate) fn create_validator_exit(exit: &Arc<AtomicBool>) -> Arc<RwLock<Option<ValidatorExit>>> {
     let mut validator_exit = ValidatorExit::default();
     let exit_ = exit.clone();
@@ -2960,6 +3794,7 @@ pub mod tests {
             BankNotification, OptimisticallyConfirmedBankTracker,
         },
         replay_stage::tests::create_test_transactions_and_populate_blockstore,
+        rpc_auth::DEFAULT_RPC_AUTH_MAX_TOKEN_AGE_SECS,
         rpc_subscriptions::RpcSubscriptions,
     };
     use bincode::deserialize;
@@ -2971,16 +3806,22 @@ pub mod tests {
     use solana_ledger::{
         blockstore_meta::PerfSample,
         blockstore_processor::fill_blockstore_slot_with_ticks,
-        genesis_utils::{create_genesis_config, GenesisConfigInfo},
+        genesis_utils::{
+            bootstrap_validator_stake_lamports, create_genesis_config,
+            create_genesis_config_with_leader, GenesisConfigInfo,
+        },
     };
     use solana_runtime::{
-        accounts_background_service::ABSRequestSender, commitment::BlockCommitment,
+        accounts_background_service::ABSRequestSender,
+        bank::{goto_end_of_slot, ExecuteTimings},
+        bank_forks::ArchiveFormat,
+        commitment::BlockCommitment,
+        snapshot_utils::{self, SnapshotVersion},
     };
     use solana_sdk::{
-        clock::MAX_RECENT_BLOCKHASHES,
         fee_calculator::DEFAULT_BURN_PERCENT,
         hash::{hash, Hash},
-        instruction::InstructionError,
+        instruction::{Instruction, InstructionError},
         message::Message,
         nonce, rpc_port,
         signature::{Keypair, Signer},
@@ -2988,8 +3829,9 @@ pub mod tests {
         timing::slot_duration_from_slots_per_year,
         transaction::{self, TransactionError},
     };
+    use solana_stake_program::stake_state;
     use solana_transaction_status::{
-        EncodedTransaction, EncodedTransactionWithStatusMeta, UiMessage,
+        EncodedTransaction, EncodedTransactionWithStatusMeta, Reward, RewardType, UiMessage,
     };
     use solana_vote_program::{
         vote_instruction,
@@ -3138,6 +3980,9 @@ pub mod tests {
         blockstore
             .write_perf_sample(0, &sample1)
             .expect("write to blockstore");
+        let recent_perf_samples = Arc::new(RwLock::new(RecentPerfSamples::new_from_blockstore(
+            &blockstore,
+        )));
 
         let (meta, receiver) = JsonRpcRequestProcessor::new(
             JsonRpcConfig {
@@ -3155,7 +4000,12 @@ pub mod tests {
             Hash::default(),
             &runtime::Runtime::new().unwrap(),
             None,
+            None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(RwLock::new(LargestAccountsCache::new(30))),
+            Arc::new(RwLock::new(ProgramStats::default())),
+            Arc::new(RwLock::new(RepairStatus::default())),
+            recent_perf_samples,
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
 
@@ -3258,9 +4108,10 @@ pub mod tests {
             .expect("actual response deserialization");
 
         let expected = format!(
-            r#"{{"jsonrpc":"2.0","result":[{{"pubkey": "{}", "gossip": "127.0.0.1:1235", "tpu": "127.0.0.1:1234", "rpc": "127.0.0.1:{}", "version": null, "featureSet": null}}],"id":1}}"#,
+            r#"{{"jsonrpc":"2.0","result":[{{"pubkey": "{}", "gossip": "127.0.0.1:1235", "tpu": "127.0.0.1:1234", "rpc": "127.0.0.1:{}", "pubsub": "127.0.0.1:{}", "version": null, "featureSet": null}}],"id":1}}"#,
             leader_pubkey,
-            rpc_port::DEFAULT_RPC_PORT
+            rpc_port::DEFAULT_RPC_PORT,
+            rpc_port::DEFAULT_RPC_PUBSUB_PORT
         );
 
         let expected: Response =
@@ -3313,31 +4164,297 @@ pub mod tests {
             "jsonrpc": "2.0",
             "error": {
                 "code": -32602,
-                "message": "Invalid limit; max 720"
+                "message": "Invalid limit; max 720"
+            },
+            "id": 1
+        });
+
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_rpc_get_slot_leader() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler {
+            io,
+            meta,
+            leader_pubkey,
+            ..
+        } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getSlotLeader"}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let expected = format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, leader_pubkey);
+        let expected: Response =
+            serde_json::from_str(&expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_rpc_get_program_stats() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getProgramStats"}"#;
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "firstSlot": null,
+                "lastSlot": null,
+                "programs": []
+            },
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+
+        let program_id = Pubkey::new_unique();
+        let message = Message::new(
+            &[
+                system_instruction::transfer(&bob_pubkey, &Pubkey::new_unique(), 1),
+                Instruction::new(program_id, &0, vec![]),
+            ],
+            Some(&bob_pubkey),
+        );
+        let logs = vec![format!(
+            "Program {} consumed 1000 of 200000 compute units",
+            program_id
+        )];
+        {
+            let mut program_stats = meta.program_stats.write().unwrap();
+            program_stats.record_transaction(3, &message, &Ok(()), &logs);
+            program_stats.record_transaction(
+                4,
+                &message,
+                &Err(TransactionError::InstructionError(
+                    1,
+                    InstructionError::Custom(0),
+                )),
+                &[],
+            );
+        }
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getProgramStats","params":["{}"]}}"#,
+            program_id
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "firstSlot": 3,
+                "lastSlot": 4,
+                "programs": [
+                    {
+                        "programId": program_id.to_string(),
+                        "invocations": 2,
+                        "failures": 1,
+                        "errorRate": 0.5,
+                        "averageComputeUnits": 1000
+                    }
+                ]
+            },
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getProgramStats"}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let programs = result["result"]["programs"].as_array().unwrap();
+        assert_eq!(programs.len(), 2);
+        let system_program_stats = programs
+            .iter()
+            .find(|program| program["programId"] == json!(system_program::id().to_string()))
+            .unwrap();
+        assert_eq!(system_program_stats["invocations"], json!(2));
+        assert_eq!(system_program_stats["failures"], json!(0));
+        assert_eq!(system_program_stats["averageComputeUnits"], json!(null));
+    }
+
+    #[test]
+    fn test_rpc_get_slot_execute_timings() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, bank, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let slow_program_id = Pubkey::new_unique();
+        let fast_program_id = Pubkey::new_unique();
+        let mut timings = ExecuteTimings {
+            lock_us: 1,
+            load_us: 2,
+            execute_us: 3,
+            store_us: 4,
+            ..ExecuteTimings::default()
+        };
+        timings.details.accumulate_program(&fast_program_id, 5);
+        timings.details.accumulate_program(&slow_program_id, 10);
+        timings.details.accumulate_program(&slow_program_id, 20);
+        bank.accumulate_execute_timings(&timings);
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getSlotExecuteTimings","params":[{}]}}"#,
+            bank.slot()
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "slot": bank.slot(),
+                "lockUs": 1,
+                "loadUs": 2,
+                "executeUs": 3,
+                "storeUs": 4,
+                "programs": [
+                    {
+                        "programId": slow_program_id.to_string(),
+                        "executeUs": 30,
+                        "count": 2
+                    },
+                    {
+                        "programId": fast_program_id.to_string(),
+                        "executeUs": 5,
+                        "count": 1
+                    }
+                ]
+            },
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getSlotExecuteTimings","params":[42]}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": null,
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_rpc_get_block_cost() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, bank, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+        assert!(bank.block_units() > 0);
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getBlockCost","params":[{}]}}"#,
+            bank.slot()
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "slot": bank.slot(),
+                "units": bank.block_units(),
+                "maxUnits": bank.max_block_units(),
+                "limitsEnabled": bank.block_cost_limits_enabled()
+            },
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getBlockCost","params":[42]}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": null,
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+    }
+
+    #[test]
+    fn test_rpc_get_repair_status() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let slot = 1_000;
+        let peer = SocketAddr::from(([127, 0, 0, 1], 8008));
+        let other_peer = SocketAddr::from(([127, 0, 0, 2], 8008));
+        {
+            let mut repair_status = meta.repair_status.write().unwrap();
+            for _ in 0..4 {
+                repair_status.record_request(slot);
+            }
+            repair_status.record_response(slot, other_peer);
+            repair_status.record_response(slot, peer);
+            repair_status.record_response(slot, peer);
+        }
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getRepairStatus","params":[1]}"#;
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": [
+                {
+                    "slot": slot,
+                    "isFull": false,
+                    "lastIndex": null,
+                    "missingShredIndexes": [],
+                    "repairRequests": 4,
+                    "outstandingRepairs": 1,
+                    "repairedFrom": [
+                        {
+                            "address": peer.to_string(),
+                            "shreds": 2
+                        },
+                        {
+                            "address": other_peer.to_string(),
+                            "shreds": 1
+                        }
+                    ]
+                }
+            ],
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getRepairStatus","params":[0]}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "error": {
+                "code": -32602,
+                "message": format!("Invalid limit; max {}", MAX_GET_REPAIR_STATUS_SLOTS)
             },
             "id": 1
         });
-
         let expected: Response =
             serde_json::from_value(expected).expect("expected response deserialization");
-        assert_eq!(expected, result);
-    }
-
-    #[test]
-    fn test_rpc_get_slot_leader() {
-        let bob_pubkey = solana_sdk::pubkey::new_rand();
-        let RpcHandler {
-            io,
-            meta,
-            leader_pubkey,
-            ..
-        } = start_rpc_handler_with_tx(&bob_pubkey);
-
-        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getSlotLeader"}"#;
-        let res = io.handle_request_sync(&req, meta);
-        let expected = format!(r#"{{"jsonrpc":"2.0","result":"{}","id":1}}"#, leader_pubkey);
-        let expected: Response =
-            serde_json::from_str(&expected).expect("expected response deserialization");
         let result: Response = serde_json::from_str(&res.expect("actual response"))
             .expect("actual response deserialization");
         assert_eq!(expected, result);
@@ -3597,6 +4714,38 @@ pub mod tests {
         assert_eq!(epoch_schedule, *bank.epoch_schedule());
     }
 
+    #[test]
+    fn test_rpc_get_epoch_schedule_custom() {
+        let GenesisConfigInfo {
+            mut genesis_config, ..
+        } = create_genesis_config(100);
+        genesis_config.epoch_schedule = EpochSchedule::custom(1024, 512, true);
+        let bank = Arc::new(Bank::new(&genesis_config));
+        let meta = JsonRpcRequestProcessor::new_from_bank(&bank);
+
+        let mut io = MetaIoHandler::default();
+        io.extend_with(RpcSolImpl.to_delegate());
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getEpochSchedule"}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "slotsPerEpoch": 1024,
+                "leaderScheduleSlotOffset": 512,
+                "warmup": true,
+                "firstNormalEpoch": 5,
+                "firstNormalSlot": 992
+            },
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn test_rpc_get_leader_schedule() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -4138,6 +5287,29 @@ pub mod tests {
             .expect("actual response deserialization");
         assert_eq!(expected, result);
 
+        // Inner instructions are returned on request, a system transfer invokes no programs
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransaction","params":["{}", {{"innerInstructions": true}}]}}"#,
+            tx_serialized_encoded,
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "context":{"slot":0},
+                "value":{"err":null, "logs":[
+                    "Program 11111111111111111111111111111111 invoke [1]",
+                    "Program 11111111111111111111111111111111 success"
+                ], "innerInstructions": []}
+            },
+            "id": 1,
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+
         // Bad signature with default sigVerify setting (false)
         let req = format!(
             r#"{{"jsonrpc":"2.0","id":1,"method":"simulateTransaction","params":["{}"]}}"#,
@@ -4384,6 +5556,7 @@ pub mod tests {
         let RpcHandler {
             io,
             meta,
+            bank,
             blockhash,
             ..
         } = start_rpc_handler_with_tx(&bob_pubkey);
@@ -4399,7 +5572,7 @@ pub mod tests {
                 "feeCalculator": {
                     "lamportsPerSignature": 0,
                 },
-                "lastValidSlot": MAX_RECENT_BLOCKHASHES,
+                "lastValidSlot": bank.max_processing_age(),
             }},
             "id": 1
         });
@@ -4458,6 +5631,78 @@ pub mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_rpc_get_fee_for_message() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler {
+            io,
+            meta,
+            bank,
+            alice,
+            ..
+        } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let get_fee_for_message = |message: &Message| {
+            let req = format!(
+                r#"{{"jsonrpc":"2.0","id":1,"method":"getFeeForMessage","params":["{}",{{"encoding":"base64"}}]}}"#,
+                base64::encode(serialize(message).unwrap())
+            );
+            let res = io.handle_request_sync(&req, meta.clone());
+            let json: Value = serde_json::from_str(&res.expect("actual response"))
+                .expect("actual response deserialization");
+            serde_json::from_value::<RpcFeeForMessage>(json["result"]["value"].clone()).unwrap()
+        };
+
+        let (blockhash, fee_calculator) = bank.last_blockhash_with_fee_calculator();
+        let last_valid_slot = bank.get_blockhash_last_valid_slot(&blockhash);
+        let alice_balance = bank.get_balance(&alice.pubkey());
+        let mut message = Message::new(
+            &[system_instruction::transfer(
+                &alice.pubkey(),
+                &bob_pubkey,
+                1,
+            )],
+            Some(&alice.pubkey()),
+        );
+        message.recent_blockhash = blockhash;
+        assert_eq!(
+            get_fee_for_message(&message),
+            RpcFeeForMessage {
+                fee: Some(fee_calculator.lamports_per_signature),
+                fee_payer: alice.pubkey().to_string(),
+                fee_payer_balance: alice_balance,
+                fee_payer_can_pay: alice_balance >= fee_calculator.lamports_per_signature,
+                blockhash_valid: true,
+                last_valid_slot,
+            }
+        );
+
+        // Unfunded fee payer
+        let mut message = Message::new(
+            &[system_instruction::transfer(
+                &bob_pubkey,
+                &alice.pubkey(),
+                1,
+            )],
+            Some(&solana_sdk::pubkey::new_rand()),
+        );
+        message.recent_blockhash = blockhash;
+        let fee_for_message = get_fee_for_message(&message);
+        assert_eq!(fee_for_message.fee_payer_balance, 0);
+        assert_eq!(
+            fee_for_message.fee_payer_can_pay,
+            fee_calculator.lamports_per_signature == 0
+        );
+
+        // Expired (non-existent) blockhash
+        message.recent_blockhash = Hash::default();
+        let fee_for_message = get_fee_for_message(&message);
+        assert_eq!(fee_for_message.fee, None);
+        assert!(!fee_for_message.fee_payer_can_pay);
+        assert!(!fee_for_message.blockhash_valid);
+        assert_eq!(fee_for_message.last_valid_slot, None);
+    }
+
     #[test]
     fn test_rpc_get_fee_rate_governor() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -4487,6 +5732,25 @@ pub mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_rpc_get_max_processing_age() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, bank, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getMaxProcessingAge"}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": bank.max_processing_age(),
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn test_rpc_fail_request_airdrop() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -4556,7 +5820,12 @@ pub mod tests {
             Hash::default(),
             &runtime::Runtime::new().unwrap(),
             None,
+            None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(RwLock::new(LargestAccountsCache::new(30))),
+            Arc::new(RwLock::new(ProgramStats::default())),
+            Arc::new(RwLock::new(RepairStatus::default())),
+            Arc::new(RwLock::new(RecentPerfSamples::default())),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
 
@@ -4661,6 +5930,92 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn test_rpc_send_transaction_default_preflight_commitment() {
+        let (bank_forks, mint_keypair, ..) = new_bank_forks();
+        let bank0 = bank_forks.read().unwrap().get(0).unwrap().clone();
+        bank0.freeze();
+        let mut bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        goto_end_of_slot(&mut bank1);
+        let processed_blockhash = bank1.last_blockhash();
+        assert_ne!(processed_blockhash, bank0.last_blockhash());
+        bank_forks.write().unwrap().insert(bank1);
+
+        // Slot 1 is processed but not rooted, so its blockhash is only visible to preflight
+        // checks run against the processed bank
+        let transaction = system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            42,
+            processed_blockhash,
+        );
+
+        let mut io = MetaIoHandler::default();
+        let rpc = RpcSolImpl;
+        io.extend_with(rpc.to_delegate());
+
+        let send_transaction = |default_preflight_commitment: Option<CommitmentLevel>,
+                                request_config: &str| {
+            let exit = Arc::new(AtomicBool::new(false));
+            let validator_exit = create_validator_exit(&exit);
+            let ledger_path = get_tmp_ledger_path!();
+            let blockstore = Arc::new(Blockstore::open(&ledger_path).unwrap());
+            let block_commitment_cache = Arc::new(RwLock::new(
+                BlockCommitmentCache::new_for_tests_with_slots(1, 0),
+            ));
+            let cluster_info = Arc::new(ClusterInfo::new_with_invalid_keypair(
+                ContactInfo::new_with_socketaddr(&socketaddr!("127.0.0.1:1234")),
+            ));
+            let tpu_address = cluster_info.my_contact_info().tpu;
+            let (meta, receiver) = JsonRpcRequestProcessor::new(
+                JsonRpcConfig {
+                    default_preflight_commitment,
+                    ..JsonRpcConfig::default()
+                },
+                None,
+                bank_forks.clone(),
+                block_commitment_cache,
+                blockstore,
+                validator_exit,
+                RpcHealth::stub(),
+                cluster_info,
+                Hash::default(),
+                &runtime::Runtime::new().unwrap(),
+                None,
+                None,
+                OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+                Arc::new(RwLock::new(LargestAccountsCache::new(30))),
+                Arc::new(RwLock::new(ProgramStats::default())),
+                Arc::new(RwLock::new(RepairStatus::default())),
+                Arc::new(RwLock::new(RecentPerfSamples::default())),
+            );
+            SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
+            let req = format!(
+                r#"{{"jsonrpc":"2.0","id":1,"method":"sendTransaction","params":["{}", {}]}}"#,
+                bs58::encode(serialize(&transaction).unwrap()).into_string(),
+                request_config,
+            );
+            let res = io.handle_request_sync(&req, meta);
+            Blockstore::destroy(&ledger_path).unwrap();
+            serde_json::from_str::<Value>(&res.unwrap()).unwrap()
+        };
+
+        // Without a node default, preflight runs against the finalized bank
+        let json = send_transaction(None, "{}");
+        assert_eq!(json["error"]["data"]["err"], "BlockhashNotFound");
+
+        // With a processed node default, preflight runs against the processed bank
+        let json = send_transaction(Some(CommitmentLevel::Processed), "{}");
+        assert_eq!(json["result"], transaction.signatures[0].to_string());
+
+        // A preflight commitment in the request overrides the node default
+        let json = send_transaction(
+            Some(CommitmentLevel::Processed),
+            r#"{"preflightCommitment": "finalized"}"#,
+        );
+        assert_eq!(json["error"]["data"]["err"], "BlockhashNotFound");
+    }
+
     #[test]
     fn test_rpc_verify_filter() {
         let filter = RpcFilterType::Memcmp(Memcmp {
@@ -4752,7 +6107,12 @@ pub mod tests {
             Hash::default(),
             &runtime::Runtime::new().unwrap(),
             None,
+            None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(RwLock::new(LargestAccountsCache::new(30))),
+            Arc::new(RwLock::new(ProgramStats::default())),
+            Arc::new(RwLock::new(RepairStatus::default())),
+            Arc::new(RwLock::new(RecentPerfSamples::default())),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
         assert_eq!(request_processor.validator_exit(), false);
@@ -4785,13 +6145,103 @@ pub mod tests {
             Hash::default(),
             &runtime::Runtime::new().unwrap(),
             None,
+            None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(RwLock::new(LargestAccountsCache::new(30))),
+            Arc::new(RwLock::new(ProgramStats::default())),
+            Arc::new(RwLock::new(RepairStatus::default())),
+            Arc::new(RwLock::new(RecentPerfSamples::default())),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
         assert_eq!(request_processor.validator_exit(), true);
         assert_eq!(exit.load(Ordering::Relaxed), true);
     }
 
+    #[test]
+    fn test_rpc_get_gossip_stats() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getGossipStats"}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let stats: RpcGossipStats = serde_json::from_value(result["result"].clone())
+            .expect("actual response deserialization");
+
+        // The node's own contact info is in the table, and just got there
+        assert!(stats.crds_values_per_type["ContactInfo"] >= 1);
+        assert_eq!(
+            stats.num_crds_values,
+            stats.crds_values_per_type.values().sum::<usize>()
+        );
+        assert_eq!(
+            stats.origin_age_histogram.len(),
+            CRDS_ORIGIN_AGE_BUCKETS_MS.len() + 1
+        );
+        assert!(stats.origin_age_histogram[0].origins >= 1);
+        assert_eq!(
+            stats.origin_age_histogram[0].max_age_ms,
+            Some(CRDS_ORIGIN_AGE_BUCKETS_MS[0])
+        );
+        assert_eq!(stats.origin_age_histogram.last().unwrap().max_age_ms, None);
+        // No gossip traffic without gossip service
+        assert_eq!(stats.push_values_received, 0);
+        assert_eq!(stats.push_success_rate, None);
+        assert_eq!(stats.pull_success_rate, None);
+        assert_eq!(stats.prune_messages_sent, 0);
+    }
+
+    #[test]
+    fn test_rpc_set_gossip_config() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+        let default = GossipConfig::default();
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"setGossipConfig","params":[{"gossipIntervalMs":500}]}"#;
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({"jsonrpc": "2.0", "result": false, "id": 1});
+        let expected: Response = serde_json::from_value(expected).unwrap();
+        let result: Response = serde_json::from_str(&res.unwrap()).unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(meta.cluster_info.gossip_config(), default);
+
+        // Enabled but callable without rpc auth
+        let mut meta = meta;
+        meta.config.enable_set_gossip_config = true;
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({"jsonrpc": "2.0", "result": false, "id": 1});
+        let expected: Response = serde_json::from_value(expected).unwrap();
+        let result: Response = serde_json::from_str(&res.unwrap()).unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(meta.cluster_info.gossip_config(), default);
+
+        meta.config.rpc_auth = Some(RpcAuthConfig {
+            restricted_methods: vec!["setGossipConfig".to_string()].into_iter().collect(),
+            allowed_methods: HashMap::new(),
+            max_token_age_secs: DEFAULT_RPC_AUTH_MAX_TOKEN_AGE_SECS,
+        });
+        let res = io.handle_request_sync(&req, meta.clone());
+        let expected = json!({"jsonrpc": "2.0", "result": true, "id": 1});
+        let expected: Response = serde_json::from_value(expected).unwrap();
+        let result: Response = serde_json::from_str(&res.unwrap()).unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(
+            meta.cluster_info.gossip_config(),
+            GossipConfig {
+                gossip_interval_ms: 500,
+                ..default
+            }
+        );
+
+        // Out of bounds values are rejected and leave the config as is
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"setGossipConfig","params":[{"pullRequestIntervalMs":1}]}"#;
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.unwrap()).unwrap();
+        assert_eq!(result["error"]["code"], ErrorCode::InvalidParams.code());
+        assert_eq!(meta.cluster_info.gossip_config().gossip_interval_ms, 500);
+    }
+
     #[test]
     fn test_rpc_get_identity() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -4813,6 +6263,52 @@ pub mod tests {
         assert_eq!(expected, result);
     }
 
+    #[test]
+    fn test_rpc_get_highest_snapshot_slot() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, mut meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getHighestSnapshotSlot"}"#;
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(
+            result["error"]["code"],
+            json!(solana_client::rpc_custom_error::JSON_RPC_SERVER_ERROR_NO_SNAPSHOT)
+        );
+
+        let snapshot_output_dir = tempfile::tempdir().unwrap();
+        for slot in &[10, 42] {
+            let archive_path = snapshot_utils::get_snapshot_archive_path(
+                snapshot_output_dir.path(),
+                &(*slot, Hash::default()),
+                ArchiveFormat::TarBzip2,
+            );
+            std::fs::File::create(archive_path).unwrap();
+        }
+        meta.snapshot_config = Some(SnapshotConfig {
+            snapshot_interval_slots: 100,
+            snapshot_package_output_path: snapshot_output_dir.path().to_path_buf(),
+            snapshot_path: snapshot_output_dir.path().to_path_buf(),
+            archive_format: ArchiveFormat::TarBzip2,
+            snapshot_version: SnapshotVersion::default(),
+        });
+        let res = io.handle_request_sync(&req, meta);
+        let expected = json!({
+            "jsonrpc": "2.0",
+            "result": {
+                "full": 42,
+                "incremental": null
+            },
+            "id": 1
+        });
+        let expected: Response =
+            serde_json::from_value(expected).expect("expected response deserialization");
+        let result: Response = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(expected, result);
+    }
+
     #[test]
     fn test_rpc_get_version() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -4877,7 +6373,12 @@ pub mod tests {
             Hash::default(),
             &runtime::Runtime::new().unwrap(),
             None,
+            None,
             OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            Arc::new(RwLock::new(LargestAccountsCache::new(30))),
+            Arc::new(RwLock::new(ProgramStats::default())),
+            Arc::new(RwLock::new(RepairStatus::default())),
+            Arc::new(RwLock::new(RecentPerfSamples::default())),
         );
         SendTransactionService::new(tpu_address, &bank_forks, None, receiver, 1000, 1);
         assert_eq!(
@@ -5006,6 +6507,14 @@ pub mod tests {
                             InstructionError::Custom(1)
                         ))
                     );
+                    assert_eq!(
+                        meta.failed_instruction,
+                        Some(UiFailedInstruction {
+                            index: 0,
+                            program_id: system_program::id().to_string(),
+                            custom_code: Some(1),
+                        })
+                    );
                 } else {
                     assert_eq!(meta, None);
                 }
@@ -5048,6 +6557,14 @@ pub mod tests {
                             InstructionError::Custom(1)
                         ))
                     );
+                    assert_eq!(
+                        meta.failed_instruction,
+                        Some(UiFailedInstruction {
+                            index: 0,
+                            program_id: system_program::id().to_string(),
+                            custom_code: Some(1),
+                        })
+                    );
                 } else {
                     assert_eq!(meta, None);
                 }
@@ -5055,6 +6572,56 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_get_confirmed_block_rewards() {
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let RpcHandler { io, meta, .. } = start_rpc_handler_with_tx(&bob_pubkey);
+
+        let staker = solana_sdk::pubkey::new_rand();
+        let rewards: Rewards = vec![&bob_pubkey, &staker]
+            .into_iter()
+            .map(|pubkey| Reward {
+                pubkey: pubkey.to_string(),
+                lamports: 42,
+                post_balance: 1_042,
+                reward_type: Some(RewardType::Staking),
+            })
+            .collect();
+        meta.blockstore.write_rewards(0, rewards.clone()).unwrap();
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getConfirmedBlockRewards","params":[0]}"#;
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let block_rewards: Option<RpcBlockRewards> =
+            serde_json::from_value(result["result"].clone()).unwrap();
+        assert_eq!(
+            block_rewards,
+            Some(RpcBlockRewards {
+                slot: 0,
+                epoch: 0,
+                rewards: rewards.clone(),
+            })
+        );
+
+        let req = format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"getConfirmedBlockRewards","params":[0, {{"addresses": ["{}"]}}]}}"#,
+            staker
+        );
+        let res = io.handle_request_sync(&req, meta.clone());
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        let block_rewards: RpcBlockRewards =
+            serde_json::from_value(result["result"].clone()).unwrap();
+        assert_eq!(block_rewards.rewards, rewards[1..].to_vec());
+
+        let req = r#"{"jsonrpc":"2.0","id":1,"method":"getConfirmedBlockRewards","params":[0, {"addresses": ["not a pubkey"]}]}"#;
+        let res = io.handle_request_sync(&req, meta);
+        let result: Value = serde_json::from_str(&res.expect("actual response"))
+            .expect("actual response deserialization");
+        assert_eq!(result["error"]["code"], ErrorCode::InvalidParams.code());
+    }
+
     #[test]
     fn test_get_confirmed_blocks() {
         let bob_pubkey = solana_sdk::pubkey::new_rand();
@@ -5445,6 +7012,108 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_get_validator_delegations() {
+        let validator_pubkey = solana_sdk::pubkey::new_rand();
+        let GenesisConfigInfo {
+            genesis_config,
+            voting_keypair,
+            ..
+        } = create_genesis_config_with_leader(
+            TEST_MINT_LAMPORTS,
+            &validator_pubkey,
+            bootstrap_validator_stake_lamports() + 1_000,
+        );
+        let bank = Arc::new(Bank::new(&genesis_config));
+        let vote_pubkey = voting_keypair.pubkey();
+        let vote_account = bank.get_account(&vote_pubkey).unwrap();
+        let rent = genesis_config.rent;
+        let genesis_stake: u64 = bank
+            .vote_accounts()
+            .iter()
+            .map(|(_, (stake, _))| *stake)
+            .sum();
+        let stake_reserve = StakeState::get_rent_exempt_reserve(&rent);
+
+        // Self stake, authorized by the validator identity
+        let self_stake_account = stake_state::create_account(
+            &validator_pubkey,
+            &vote_pubkey,
+            &vote_account,
+            &rent,
+            stake_reserve + 50,
+        );
+        bank.store_account(&Pubkey::new_unique(), &self_stake_account);
+        let self_stake = StakeState::stake_from(&self_stake_account)
+            .unwrap()
+            .delegation
+            .stake;
+
+        // Foreign stake of a delegator with two stake accounts
+        let delegator = Pubkey::new_unique();
+        let delegator_stake_account = stake_state::create_account(
+            &delegator,
+            &vote_pubkey,
+            &vote_account,
+            &rent,
+            stake_reserve + 100,
+        );
+        bank.store_account(&Pubkey::new_unique(), &delegator_stake_account);
+        bank.store_account(&Pubkey::new_unique(), &delegator_stake_account);
+        let delegator_stake = 2 * StakeState::stake_from(&delegator_stake_account)
+            .unwrap()
+            .delegation
+            .stake;
+
+        let meta = JsonRpcRequestProcessor::new_from_bank(&bank);
+        let RpcResponse { context, value } = meta.get_validator_delegations(None).unwrap();
+        assert_eq!(context.slot, bank.slot());
+        assert_eq!(value.len(), 1);
+        let delegations = &value[0];
+        assert_eq!(delegations.vote_pubkey, vote_pubkey.to_string());
+        assert_eq!(delegations.node_pubkey, validator_pubkey.to_string());
+        assert_eq!(
+            delegations.active_stake,
+            genesis_stake + self_stake + delegator_stake
+        );
+        assert_eq!(delegations.self_stake, self_stake);
+        assert_eq!(delegations.foreign_stake, genesis_stake + delegator_stake);
+        assert_eq!(delegations.stake_account_count, 4);
+        assert_eq!(delegations.delegator_count, 3);
+        assert_eq!(delegations.top_delegators.len(), 3);
+        assert_eq!(
+            delegations
+                .top_delegators
+                .iter()
+                .find(|top_delegator| top_delegator.staker == delegator.to_string()),
+            Some(&RpcDelegator {
+                staker: delegator.to_string(),
+                active_stake: delegator_stake,
+                stake_account_count: 2,
+            })
+        );
+
+        // Filtering by vote account is served from the cache
+        bank.store_account(&Pubkey::new_unique(), &delegator_stake_account);
+        let value = meta
+            .get_validator_delegations(Some(RpcValidatorDelegationsConfig {
+                vote_pubkey: Some(vote_pubkey.to_string()),
+                ..RpcValidatorDelegationsConfig::default()
+            }))
+            .unwrap()
+            .value;
+        assert_eq!(value.len(), 1);
+        assert_eq!(value[0].stake_account_count, 4);
+        let value = meta
+            .get_validator_delegations(Some(RpcValidatorDelegationsConfig {
+                vote_pubkey: Some(Pubkey::new_unique().to_string()),
+                ..RpcValidatorDelegationsConfig::default()
+            }))
+            .unwrap()
+            .value;
+        assert!(value.is_empty());
+    }
+
     #[test]
     fn test_is_confirmed_rooted() {
         let bank = Arc::new(Bank::default());
@@ -6106,7 +7775,12 @@ pub mod tests {
             Hash::default(),
             &runtime::Runtime::new().unwrap(),
             None,
+            None,
             optimistically_confirmed_bank.clone(),
+            Arc::new(RwLock::new(LargestAccountsCache::new(30))),
+            Arc::new(RwLock::new(ProgramStats::default())),
+            Arc::new(RwLock::new(RepairStatus::default())),
+            Arc::new(RwLock::new(RecentPerfSamples::default())),
         );
 
         let mut io = MetaIoHandler::default();
@@ -0,0 +1,490 @@
+//! The `rpc_auth` module authenticates JSON RPC clients by a token signed with their identity
+//! keypair, and restricts the sensitive methods to the identities allowed to call them.
+//!
+//! A token signs a unix timestamp, a nonce and the hash of the request it comes with, and is
+//! passed in the `solana-rpc-identity`, `solana-rpc-timestamp`, `solana-rpc-nonce` and
+//! `solana-rpc-signature` request headers.  Pubsub connections pass a token signed for pubsub
+//! as a `solana-rpc-auth.` websocket protocol and are authenticated for as long as they are
+//! open.  Each token is accepted once, until it expires, so a captured token can't be replayed,
+//! but requests and connections are still not encrypted: serve restricted methods behind a TLS
+//! terminating proxy unless the network is trusted.
+//!
+//! Clients mint tokens with `solana_client::rpc_auth`: `RpcClient::new_with_rpc_auth` attaches
+//! one to every request, and `rpc_auth_pubsub_request` opens authenticated pubsub connections.
+
+use {
+    jsonrpc_core::{
+        futures::future::{self, Either},
+        Call, Failure, Metadata, Middleware, Output, Request, Response,
+    },
+    solana_client::{
+        rpc_auth::{rpc_auth_message, rpc_auth_pubsub_hash, rpc_auth_request_hash, unix_timestamp},
+        rpc_custom_error::RpcCustomError,
+    },
+    solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature},
+    std::{
+        collections::{HashMap, HashSet},
+        fs,
+        path::Path,
+        str::FromStr,
+        sync::{Arc, Mutex},
+    },
+};
+
+pub use solana_client::rpc_auth::RpcAuthToken;
+
+pub const DEFAULT_RPC_AUTH_MAX_TOKEN_AGE_SECS: u64 = 30;
+
+// Bounds the tokens remembered to reject replays, only configured identities can mint them
+const MAX_ACCEPTED_RPC_AUTH_TOKENS: usize = 100_000;
+
+// Allows an identity to call every restricted method
+const ALL_METHODS: &str = "*";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RpcAuthConfigFile {
+    restricted_methods: Vec<String>,
+    #[serde(default)]
+    identities: HashMap<String, Vec<String>>,
+    max_token_age_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcAuthConfig {
+    /// Methods only the identities allowed to call them may call
+    pub restricted_methods: HashSet<String>,
+    /// The restricted methods each identity may call, "*" standing for all of them
+    pub allowed_methods: HashMap<Pubkey, HashSet<String>>,
+    /// Seconds a token is accepted for, either side of its timestamp
+    pub max_token_age_secs: u64,
+}
+
+impl RpcAuthConfig {
+    /// Loads the config from a JSON file of the form
+    /// `{"restrictedMethods": ["setLogFilter"], "identities": {"<pubkey>": ["setLogFilter"]}}`
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+        let config_file: RpcAuthConfigFile = serde_json::from_str(&contents)
+            .map_err(|err| format!("Unable to parse {}: {}", path.display(), err))?;
+
+        let allowed_methods = config_file
+            .identities
+            .into_iter()
+            .map(|(identity, methods)| {
+                let identity = Pubkey::from_str(&identity)
+                    .map_err(|_| format!("Invalid identity in {}: {}", path.display(), identity))?;
+                Ok((identity, methods.into_iter().collect()))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            restricted_methods: config_file.restricted_methods.into_iter().collect(),
+            allowed_methods,
+            max_token_age_secs: config_file
+                .max_token_age_secs
+                .unwrap_or(DEFAULT_RPC_AUTH_MAX_TOKEN_AGE_SECS),
+        })
+    }
+
+    /// The identity `token` authenticates for the request or connection hashing to
+    /// `request_hash`, None if the identity isn't configured, the token is expired at `now` or
+    /// its signature is invalid
+    pub fn authenticate(
+        &self,
+        token: &RpcAuthToken,
+        request_hash: &Hash,
+        now: u64,
+    ) -> Option<Pubkey> {
+        // Other identities can't call any restricted method, so there is nothing to verify
+        if !self.allowed_methods.contains_key(&token.identity) {
+            debug!("rpc auth token of unknown identity {}", token.identity);
+            return None;
+        }
+        let age = if now > token.timestamp {
+            now - token.timestamp
+        } else {
+            token.timestamp - now
+        };
+        if age > self.max_token_age_secs {
+            debug!("rpc auth token of {} expired", token.identity);
+            return None;
+        }
+        let message = rpc_auth_message(token.timestamp, token.nonce, request_hash);
+        if !token.signature.verify(token.identity.as_ref(), &message) {
+            debug!(
+                "rpc auth token of {} has an invalid signature",
+                token.identity
+            );
+            return None;
+        }
+        Some(token.identity)
+    }
+
+    pub fn is_allowed(&self, method: &str, identity: Option<&Pubkey>) -> bool {
+        if !self.restricted_methods.contains(method) {
+            return true;
+        }
+        identity
+            .and_then(|identity| self.allowed_methods.get(identity))
+            .map(|methods| methods.contains(method) || methods.contains(ALL_METHODS))
+            .unwrap_or(false)
+    }
+}
+
+/// Request metadata carrying the identity the request was authenticated as
+pub trait RpcAuthMetadata: Metadata {
+    fn authenticated_identity(&self) -> Option<&Pubkey>;
+
+    /// The token that came with the request, which `RpcAuthMiddleware` verifies against it
+    fn rpc_auth_token(&self) -> Option<&RpcAuthToken>;
+
+    fn with_authenticated_identity(&self, identity: Option<Pubkey>) -> Self;
+}
+
+/// Fails the calls of restricted methods not made by an identity allowed to call them
+#[derive(Clone, Default)]
+pub struct RpcAuthMiddleware {
+    config: Option<Arc<RpcAuthConfig>>,
+    /// Timestamps of the tokens accepted and not expired yet, by signature
+    accepted_tokens: Arc<Mutex<HashMap<Signature, u64>>>,
+}
+
+impl RpcAuthMiddleware {
+    pub fn new(config: Option<RpcAuthConfig>) -> Self {
+        Self {
+            config: config.map(Arc::new),
+            accepted_tokens: Arc::default(),
+        }
+    }
+
+    /// The identity `token` authenticates for the request or connection hashing to
+    /// `request_hash` at `now`, None if it is invalid or was already accepted.  Only tokens of
+    /// configured identities with valid signatures are remembered, and only until they expire
+    fn accept(&self, token: &RpcAuthToken, request_hash: &Hash, now: u64) -> Option<Pubkey> {
+        let config = self.config.as_ref()?;
+        let identity = config.authenticate(token, request_hash, now)?;
+        let mut accepted_tokens = self.accepted_tokens.lock().unwrap();
+        accepted_tokens.retain(|_, timestamp| *timestamp + config.max_token_age_secs >= now);
+        if accepted_tokens.contains_key(&token.signature) {
+            debug!("rpc auth token of {} replayed", identity);
+            return None;
+        }
+        // Forgetting an unexpired token would let it be replayed, so refuse new ones instead
+        if accepted_tokens.len() >= MAX_ACCEPTED_RPC_AUTH_TOKENS {
+            warn!(
+                "too many rpc auth tokens accepted, rejecting the token of {}",
+                identity
+            );
+            return None;
+        }
+        accepted_tokens.insert(token.signature, token.timestamp);
+        Some(identity)
+    }
+
+    /// The identity of a pubsub connection opened now with the given websocket protocols
+    pub fn authenticate_pubsub(&self, protocols: &[String]) -> Option<Pubkey> {
+        let token = RpcAuthToken::from_pubsub_protocols(protocols)?;
+        self.accept(&token, &rpc_auth_pubsub_hash(), unix_timestamp())
+    }
+}
+
+impl<M: RpcAuthMetadata> Middleware<M> for RpcAuthMiddleware {
+    type Future = future::FutureResult<Option<Response>, ()>;
+    type CallFuture = future::FutureResult<Option<Output>, ()>;
+
+    fn on_request<F, X>(&self, request: Request, meta: M, next: F) -> Either<Self::Future, X>
+    where
+        F: Fn(Request, M) -> X + Send + Sync,
+        X: future::Future<Item = Option<Response>, Error = ()> + Send + 'static,
+    {
+        // The token is only valid for the request it came with
+        let meta = match meta.rpc_auth_token() {
+            Some(token) => {
+                let identity =
+                    self.accept(token, &rpc_auth_request_hash(&request), unix_timestamp());
+                meta.with_authenticated_identity(identity)
+            }
+            None => meta,
+        };
+        Either::B(next(request, meta))
+    }
+
+    fn on_call<F, X>(&self, call: Call, meta: M, next: F) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, M) -> X + Send + Sync,
+        X: future::Future<Item = Option<Output>, Error = ()> + Send + 'static,
+    {
+        let config = match &self.config {
+            Some(config) => config,
+            None => return Either::B(next(call, meta)),
+        };
+        let identity = meta.authenticated_identity().copied();
+        let identity = identity.as_ref();
+        match &call {
+            Call::MethodCall(method_call) if !config.is_allowed(&method_call.method, identity) => {
+                warn!("rpc method {} denied to {:?}", method_call.method, identity);
+                Either::A(future::ok(Some(Output::Failure(Failure {
+                    jsonrpc: method_call.jsonrpc,
+                    error: RpcCustomError::MethodNotAuthorized {
+                        method: method_call.method.clone(),
+                    }
+                    .into(),
+                    id: method_call.id.clone(),
+                }))))
+            }
+            // Notifications get no response, so a denied notification is just dropped
+            Call::Notification(notification)
+                if !config.is_allowed(&notification.method, identity) =>
+            {
+                warn!(
+                    "rpc notification {} denied to {:?}",
+                    notification.method, identity
+                );
+                Either::A(future::ok(None))
+            }
+            _ => Either::B(next(call, meta)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::rpc::{JsonRpcRequestProcessor, RpcSol, RpcSolImpl},
+        jsonrpc_core::MetaIoHandler,
+        solana_client::rpc_custom_error::JSON_RPC_SERVER_ERROR_METHOD_NOT_AUTHORIZED,
+        solana_runtime::bank::Bank,
+        solana_sdk::{
+            genesis_config::create_genesis_config,
+            hash::hash,
+            signature::{Keypair, Signer},
+        },
+    };
+
+    fn config(identity: &Pubkey) -> RpcAuthConfig {
+        RpcAuthConfig {
+            restricted_methods: ["getBalance", "getSlot"]
+                .iter()
+                .map(|method| method.to_string())
+                .collect(),
+            allowed_methods: vec![(
+                *identity,
+                vec!["getBalance".to_string()].into_iter().collect(),
+            )]
+            .into_iter()
+            .collect(),
+            max_token_age_secs: DEFAULT_RPC_AUTH_MAX_TOKEN_AGE_SECS,
+        }
+    }
+
+    #[test]
+    fn test_rpc_auth_authenticate() {
+        let identity = Keypair::new();
+        let config = config(&identity.pubkey());
+        let request_hash = hash(b"request");
+        let now = 1_000_000;
+
+        let token = RpcAuthToken::new(&identity, now, 42, &request_hash);
+        let authenticate =
+            |token: &RpcAuthToken, now| config.authenticate(token, &request_hash, now);
+        assert_eq!(authenticate(&token, now), Some(identity.pubkey()));
+        assert_eq!(authenticate(&token, now + 30), Some(identity.pubkey()));
+        assert_eq!(authenticate(&token, now - 30), Some(identity.pubkey()));
+        assert_eq!(authenticate(&token, now + 31), None);
+        assert_eq!(authenticate(&token, now - 31), None);
+
+        // The signature covers the timestamp, the nonce and the request
+        let mut tampered = token.clone();
+        tampered.timestamp += 1;
+        assert_eq!(authenticate(&tampered, now), None);
+        let mut tampered = token.clone();
+        tampered.nonce += 1;
+        assert_eq!(authenticate(&tampered, now), None);
+        assert_eq!(
+            config.authenticate(&token, &hash(b"other request"), now),
+            None
+        );
+
+        // And is made by the identity
+        let mut impersonated = RpcAuthToken::new(&Keypair::new(), now, 42, &request_hash);
+        impersonated.identity = identity.pubkey();
+        assert_eq!(authenticate(&impersonated, now), None);
+
+        // Which must be configured
+        let unknown = RpcAuthToken::new(&Keypair::new(), now, 42, &request_hash);
+        assert_eq!(authenticate(&unknown, now), None);
+    }
+
+    #[test]
+    fn test_rpc_auth_accept() {
+        let identity = Keypair::new();
+        let middleware = RpcAuthMiddleware::new(Some(config(&identity.pubkey())));
+        let request_hash = hash(b"request");
+        let now = 1_000_000;
+
+        // Tokens are accepted once
+        let token = RpcAuthToken::new(&identity, now, 42, &request_hash);
+        assert_eq!(
+            middleware.accept(&token, &request_hash, now),
+            Some(identity.pubkey())
+        );
+        assert_eq!(middleware.accept(&token, &request_hash, now + 1), None);
+        let token = RpcAuthToken::new(&identity, now, 43, &request_hash);
+        assert_eq!(
+            middleware.accept(&token, &request_hash, now + 1),
+            Some(identity.pubkey())
+        );
+
+        // And forgotten once expired
+        assert_eq!(middleware.accepted_tokens.lock().unwrap().len(), 2);
+        let token = RpcAuthToken::new(&identity, now + 31, 44, &request_hash);
+        assert!(middleware.accept(&token, &request_hash, now + 31).is_some());
+        assert_eq!(middleware.accepted_tokens.lock().unwrap().len(), 1);
+
+        // Invalid tokens are rejected before they are remembered
+        let unknown = RpcAuthToken::new(&Keypair::new(), now + 31, 45, &request_hash);
+        assert_eq!(middleware.accept(&unknown, &request_hash, now + 31), None);
+        let forged = RpcAuthToken::new(&identity, now + 31, 46, &hash(b"other request"));
+        assert_eq!(middleware.accept(&forged, &request_hash, now + 31), None);
+        let expired = RpcAuthToken::new(&identity, now, 47, &request_hash);
+        assert_eq!(middleware.accept(&expired, &request_hash, now + 31), None);
+        assert_eq!(middleware.accepted_tokens.lock().unwrap().len(), 1);
+
+        // Once full, new tokens are refused rather than unexpired ones forgotten
+        middleware.accepted_tokens.lock().unwrap().extend(
+            (0..MAX_ACCEPTED_RPC_AUTH_TOKENS as u64).map(|i| {
+                (
+                    Signature::new(&[&i.to_le_bytes()[..], &[0; 56]].concat()),
+                    now + 31,
+                )
+            }),
+        );
+        let token = RpcAuthToken::new(&identity, now + 31, 48, &request_hash);
+        assert_eq!(middleware.accept(&token, &request_hash, now + 31), None);
+        middleware.accepted_tokens.lock().unwrap().clear();
+        assert!(middleware.accept(&token, &request_hash, now + 31).is_some());
+
+        // Nothing is accepted without a config
+        let token = RpcAuthToken::new(&identity, now, 49, &request_hash);
+        assert_eq!(
+            RpcAuthMiddleware::default().accept(&token, &request_hash, now),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rpc_auth_authenticate_pubsub() {
+        let identity = Keypair::new();
+        let middleware = RpcAuthMiddleware::new(Some(config(&identity.pubkey())));
+        let now = unix_timestamp();
+
+        let token = RpcAuthToken::new(&identity, now, 42, &rpc_auth_pubsub_hash());
+        let protocols = vec!["other".to_string(), token.pubsub_protocol()];
+        assert_eq!(
+            middleware.authenticate_pubsub(&protocols),
+            Some(identity.pubkey())
+        );
+        assert_eq!(middleware.authenticate_pubsub(&protocols), None);
+        assert_eq!(middleware.authenticate_pubsub(&["other".to_string()]), None);
+
+        // A token signed for a request can't open a connection
+        let token = RpcAuthToken::new(&identity, now, 43, &hash(b"request"));
+        assert_eq!(
+            middleware.authenticate_pubsub(&[token.pubsub_protocol()]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rpc_auth_is_allowed() {
+        let identity = solana_sdk::pubkey::new_rand();
+        let mut config = config(&identity);
+
+        assert!(config.is_allowed("getVersion", None));
+        assert!(config.is_allowed("getBalance", Some(&identity)));
+        assert!(!config.is_allowed("getBalance", None));
+        assert!(!config.is_allowed("getBalance", Some(&solana_sdk::pubkey::new_rand())));
+        assert!(!config.is_allowed("getSlot", Some(&identity)));
+
+        config.allowed_methods.insert(
+            identity,
+            vec![ALL_METHODS.to_string()].into_iter().collect(),
+        );
+        assert!(config.is_allowed("getSlot", Some(&identity)));
+    }
+
+    #[test]
+    fn test_rpc_auth_load() {
+        let identity = solana_sdk::pubkey::new_rand();
+        let path = std::env::temp_dir().join(format!("rpc-auth-{}.json", identity));
+
+        fs::write(
+            &path,
+            format!(
+                r#"{{"restrictedMethods":["getBalance","getSlot"],"identities":{{"{}":["getBalance"]}}}}"#,
+                identity
+            ),
+        )
+        .unwrap();
+        assert_eq!(RpcAuthConfig::load(&path).unwrap(), config(&identity));
+
+        fs::write(
+            &path,
+            r#"{"restrictedMethods":[],"identities":{"not-a-pubkey":[]}}"#,
+        )
+        .unwrap();
+        assert!(RpcAuthConfig::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rpc_auth_middleware() {
+        let identity = Keypair::new();
+        let bank = Arc::new(Bank::new(&create_genesis_config(10_000).0));
+        let meta = JsonRpcRequestProcessor::new_from_bank(&bank);
+        let mut io = MetaIoHandler::with_middleware(RpcAuthMiddleware::new(Some(config(
+            &identity.pubkey(),
+        ))));
+        io.extend_with(RpcSolImpl.to_delegate());
+
+        let request = format!(
+            r#"[{{"jsonrpc":"2.0","id":1,"method":"getBalance","params":["{}"]}},
+                {{"jsonrpc":"2.0","id":2,"method":"getVersion"}}]"#,
+            identity.pubkey()
+        );
+        let response = |meta| -> Vec<serde_json::Value> {
+            serde_json::from_str(&io.handle_request_sync(&request, meta).unwrap()).unwrap()
+        };
+
+        let anonymous = response(meta.clone());
+        assert_eq!(
+            anonymous[0]["error"]["code"],
+            JSON_RPC_SERVER_ERROR_METHOD_NOT_AUTHORIZED
+        );
+        assert!(anonymous[1]["result"].is_object());
+
+        // A token signed for another request doesn't authenticate this one
+        let now = unix_timestamp();
+        let token = RpcAuthToken::new(&identity, now, 1, &hash(b"other request"));
+        let forged = response(meta.with_rpc_auth_token(Some(token)));
+        assert_eq!(
+            forged[0]["error"]["code"],
+            JSON_RPC_SERVER_ERROR_METHOD_NOT_AUTHORIZED
+        );
+
+        let request_hash = rpc_auth_request_hash(&serde_json::from_str(&request).unwrap());
+        let token = RpcAuthToken::new(&identity, now, 2, &request_hash);
+        let authenticated = response(meta.with_rpc_auth_token(Some(token.clone())));
+        assert_eq!(authenticated[0]["result"]["value"], 0);
+        assert!(authenticated[1]["result"].is_object());
+
+        // Nor can it be replayed
+        let replayed = response(meta.with_rpc_auth_token(Some(token)));
+        assert_eq!(
+            replayed[0]["error"]["code"],
+            JSON_RPC_SERVER_ERROR_METHOD_NOT_AUTHORIZED
+        );
+    }
+}
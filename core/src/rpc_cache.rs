@@ -0,0 +1,205 @@
+use {
+    solana_client::{
+        rpc_config::RpcLargestAccountsFilter,
+        rpc_response::{RpcAccountBalance, RpcValidatorDelegations},
+    },
+    solana_sdk::{clock::Slot, commitment_config::CommitmentLevel},
+    std::{
+        collections::HashMap,
+        time::{Duration, SystemTime},
+    },
+};
+
+/// Caches the results of `getLargestAccounts`, which requires a full accounts scan, so that
+/// the scan is performed at most once per `duration` for each filter and commitment level
+#[derive(Debug, Clone)]
+pub struct LargestAccountsCache {
+    duration: u64,
+    cache: HashMap<(Option<RpcLargestAccountsFilter>, CommitmentLevel), LargestAccountsCacheValue>,
+}
+
+#[derive(Debug, Clone)]
+struct LargestAccountsCacheValue {
+    accounts: Vec<RpcAccountBalance>,
+    slot: Slot,
+    cached_time: SystemTime,
+}
+
+impl LargestAccountsCache {
+    /// Create a new cache whose entries expire `duration` seconds after they are set
+    pub fn new(duration: u64) -> Self {
+        Self {
+            duration,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached slot and accounts for `filter` at `commitment`, if present and not yet
+    /// expired
+    pub fn get_largest_accounts(
+        &self,
+        filter: &Option<RpcLargestAccountsFilter>,
+        commitment: CommitmentLevel,
+    ) -> Option<(Slot, Vec<RpcAccountBalance>)> {
+        self.cache
+            .get(&(filter.clone(), commitment))
+            .and_then(|value| {
+                let elapsed = SystemTime::now()
+                    .duration_since(value.cached_time)
+                    .unwrap_or_default();
+                if elapsed < Duration::from_secs(self.duration) {
+                    Some((value.slot, value.accounts.clone()))
+                } else {
+                    None
+                }
+            })
+    }
+
+    pub fn set_largest_accounts(
+        &mut self,
+        filter: &Option<RpcLargestAccountsFilter>,
+        commitment: CommitmentLevel,
+        slot: Slot,
+        accounts: &[RpcAccountBalance],
+    ) {
+        self.cache.insert(
+            (filter.clone(), commitment),
+            LargestAccountsCacheValue {
+                accounts: accounts.to_owned(),
+                slot,
+                cached_time: SystemTime::now(),
+            },
+        );
+    }
+}
+
+/// Caches the results of `getValidatorDelegations`, which requires a scan of all the stake
+/// accounts, so that the scan is performed at most once per `duration`
+#[derive(Debug, Clone)]
+pub struct ValidatorDelegationsCache {
+    duration: u64,
+    cache: Option<ValidatorDelegationsCacheValue>,
+}
+
+#[derive(Debug, Clone)]
+struct ValidatorDelegationsCacheValue {
+    delegations: Vec<RpcValidatorDelegations>,
+    slot: Slot,
+    cached_time: SystemTime,
+}
+
+impl ValidatorDelegationsCache {
+    /// Create a new cache whose entry expires `duration` seconds after it is set
+    pub fn new(duration: u64) -> Self {
+        Self {
+            duration,
+            cache: None,
+        }
+    }
+
+    /// Returns the cached slot and delegations, if present and not yet expired
+    pub fn get_validator_delegations(&self) -> Option<(Slot, Vec<RpcValidatorDelegations>)> {
+        self.cache.as_ref().and_then(|value| {
+            let elapsed = SystemTime::now()
+                .duration_since(value.cached_time)
+                .unwrap_or_default();
+            if elapsed < Duration::from_secs(self.duration) {
+                Some((value.slot, value.delegations.clone()))
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn set_validator_delegations(
+        &mut self,
+        slot: Slot,
+        delegations: &[RpcValidatorDelegations],
+    ) {
+        self.cache = Some(ValidatorDelegationsCacheValue {
+            delegations: delegations.to_owned(),
+            slot,
+            cached_time: SystemTime::now(),
+        });
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    #[test]
+    fn test_old_entries_expire() {
+        let mut cache = LargestAccountsCache::new(1);
+
+        let filter = Some(RpcLargestAccountsFilter::Circulating);
+
+        let accounts: Vec<RpcAccountBalance> = Vec::new();
+
+        cache.set_largest_accounts(&filter, CommitmentLevel::Finalized, 1000, &accounts);
+        std::thread::sleep(Duration::from_secs(1));
+        assert_eq!(
+            cache.get_largest_accounts(&filter, CommitmentLevel::Finalized),
+            None
+        );
+    }
+
+    #[test]
+    fn test_entries_are_keyed_by_filter_and_commitment() {
+        let mut cache = LargestAccountsCache::new(60);
+
+        let accounts = vec![RpcAccountBalance {
+            address: "address".to_string(),
+            lamports: 42,
+        }];
+
+        cache.set_largest_accounts(&None, CommitmentLevel::Finalized, 7, &accounts);
+        assert_eq!(
+            cache.get_largest_accounts(&None, CommitmentLevel::Finalized),
+            Some((7, accounts.clone()))
+        );
+        assert_eq!(
+            cache.get_largest_accounts(
+                &Some(RpcLargestAccountsFilter::NonCirculating),
+                CommitmentLevel::Finalized
+            ),
+            None
+        );
+        assert_eq!(
+            cache.get_largest_accounts(&None, CommitmentLevel::Processed),
+            None
+        );
+
+        // A processed result doesn't replace the finalized one
+        cache.set_largest_accounts(&None, CommitmentLevel::Processed, 9, &[]);
+        assert_eq!(
+            cache.get_largest_accounts(&None, CommitmentLevel::Processed),
+            Some((9, vec![]))
+        );
+        assert_eq!(
+            cache.get_largest_accounts(&None, CommitmentLevel::Finalized),
+            Some((7, accounts))
+        );
+    }
+
+    #[test]
+    fn test_validator_delegations_expire() {
+        let mut cache = ValidatorDelegationsCache::new(1);
+        assert_eq!(cache.get_validator_delegations(), None);
+
+        let delegations = vec![RpcValidatorDelegations {
+            vote_pubkey: "vote".to_string(),
+            node_pubkey: "node".to_string(),
+            active_stake: 42,
+            self_stake: 2,
+            foreign_stake: 40,
+            stake_account_count: 2,
+            delegator_count: 2,
+            top_delegators: vec![],
+        }];
+        cache.set_validator_delegations(7, &delegations);
+        assert_eq!(cache.get_validator_delegations(), Some((7, delegations)));
+        std::thread::sleep(Duration::from_secs(1));
+        assert_eq!(cache.get_validator_delegations(), None);
+    }
+}
@@ -1,17 +1,24 @@
 //! The `pubsub` module implements a threaded subscription service on client RPC request
 
-use crate::rpc_subscriptions::{RpcSubscriptions, RpcVote};
+use crate::{
+    rpc::verify_block_rewards_addresses,
+    rpc_auth::{RpcAuthMetadata, RpcAuthToken},
+    rpc_subscription_resume::MAX_RESUMABLE_SUBSCRIPTION_ID,
+    rpc_subscriptions::{RpcSubscriptions, RpcVote},
+};
 use jsonrpc_core::{Error, ErrorCode, Result};
 use jsonrpc_derive::rpc;
-use jsonrpc_pubsub::{typed::Subscriber, Session, SubscriptionId};
+use jsonrpc_pubsub::{typed::Subscriber, PubSubMetadata, Session, SubscriptionId};
+use rand::Rng;
 use solana_account_decoder::UiAccount;
 use solana_client::{
     rpc_config::{
-        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSignatureSubscribeConfig,
-        RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+        RpcAccountInfoConfig, RpcBlockRewardsConfig, RpcProgramAccountsConfig,
+        RpcSignatureSubscribeConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
     },
     rpc_response::{
-        Response as RpcResponse, RpcKeyedAccount, RpcLogsResponse, RpcSignatureResult, SlotInfo,
+        EpochBoundaryInfo, Response as RpcResponse, RpcBlockRewards, RpcKeyedAccount,
+        RpcLogsResponse, RpcSignatureResult, SlotInfo,
     },
 };
 #[cfg(test)]
@@ -20,6 +27,7 @@ use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
 #[cfg(test)]
 use std::sync::RwLock;
 use std::{
+    ops::Deref,
     str::FromStr,
     sync::{atomic, Arc},
 };
@@ -162,24 +170,120 @@ pub trait RpcSolPubSub {
         name = "rootUnsubscribe"
     )]
     fn root_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+
+    // Get notification when a new root crosses into a new epoch
+    #[pubsub(subscription = "epochNotification", subscribe, name = "epochSubscribe")]
+    fn epoch_subscribe(&self, meta: Self::Metadata, subscriber: Subscriber<EpochBoundaryInfo>);
+
+    // Unsubscribe from epoch notification subscription.
+    #[pubsub(
+        subscription = "epochNotification",
+        unsubscribe,
+        name = "epochUnsubscribe"
+    )]
+    fn epoch_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool>;
+
+    // Get the rewards of the first block of each epoch once it is rooted, optionally only those
+    // paid to the given addresses
+    #[pubsub(
+        subscription = "rewardsNotification",
+        subscribe,
+        name = "rewardsSubscribe"
+    )]
+    fn rewards_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: Subscriber<RpcBlockRewards>,
+        config: Option<RpcBlockRewardsConfig>,
+    );
+
+    // Unsubscribe from rewards notification subscription.
+    #[pubsub(
+        subscription = "rewardsNotification",
+        unsubscribe,
+        name = "rewardsUnsubscribe"
+    )]
+    fn rewards_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId)
+        -> Result<bool>;
+
+    // Move a subscription whose connection dropped onto this connection, replaying the
+    // notifications it missed in the meantime. Returns the number of replayed notifications
+    #[rpc(meta, name = "subscriptionResume")]
+    fn subscription_resume(&self, meta: Self::Metadata, id: u64) -> Result<usize>;
 }
 
 pub struct RpcSolPubSubImpl {
     uid: Arc<atomic::AtomicUsize>,
     subscriptions: Arc<RpcSubscriptions>,
+    enable_subscription_resume: bool,
 }
 
 impl RpcSolPubSubImpl {
-    pub fn new(subscriptions: Arc<RpcSubscriptions>) -> Self {
+    pub fn new(subscriptions: Arc<RpcSubscriptions>, enable_subscription_resume: bool) -> Self {
         let uid = Arc::new(atomic::AtomicUsize::default());
-        Self { uid, subscriptions }
+        Self {
+            uid,
+            subscriptions,
+            enable_subscription_resume,
+        }
     }
 
     #[cfg(test)]
     fn default_with_bank_forks(bank_forks: Arc<RwLock<BankForks>>) -> Self {
         let uid = Arc::new(atomic::AtomicUsize::default());
         let subscriptions = Arc::new(RpcSubscriptions::default_with_bank_forks(bank_forks));
-        Self { uid, subscriptions }
+        Self {
+            uid,
+            subscriptions,
+            enable_subscription_resume: false,
+        }
+    }
+
+    // Resumable subscriptions get random ids, so that a subscription can't be resumed by
+    // guessing its id
+    fn new_subscription_id(&self, notification: &'static str) -> SubscriptionId {
+        if self.enable_subscription_resume {
+            let mut rng = rand::thread_rng();
+            loop {
+                let sub_id =
+                    SubscriptionId::Number(rng.gen_range(0, MAX_RESUMABLE_SUBSCRIPTION_ID + 1));
+                if !self.subscriptions.is_resumable_subscription(&sub_id) {
+                    self.subscriptions
+                        .register_resumable_subscription(&sub_id, notification);
+                    return sub_id;
+                }
+            }
+        } else {
+            let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
+            SubscriptionId::Number(id as u64)
+        }
+    }
+
+    fn unsubscribe<F>(
+        &self,
+        meta: Option<PubSubSession>,
+        id: SubscriptionId,
+        remove_subscription: F,
+    ) -> Result<bool>
+    where
+        F: FnOnce(&SubscriptionId) -> bool,
+    {
+        // No session means the connection dropped, resumable subscriptions are kept around
+        // until they are resumed or expire
+        if meta.is_none() && self.subscriptions.detach_subscription(&id) {
+            info!("detached subscription: id={:?}", id);
+            return Ok(true);
+        }
+        self.subscriptions.forget_resumable_subscription(&id);
+        if remove_subscription(&id) {
+            Ok(true)
+        } else {
+            Err(Error {
+                code: ErrorCode::InvalidParams,
+                message: "Invalid Request: Subscription id does not exist".into(),
+                data: None,
+            })
+        }
     }
 
     fn check_subscription_count(&self) -> Result<()> {
@@ -207,8 +311,58 @@ fn param<T: FromStr>(param_str: &str, thing: &str) -> Result<T> {
     })
 }
 
+/// The session of a pubsub connection and the identity it authenticated as when it was opened
+#[derive(Clone)]
+pub struct PubSubSession {
+    session: Arc<Session>,
+    authenticated_identity: Option<Pubkey>,
+}
+
+impl PubSubSession {
+    pub fn new(session: Arc<Session>, authenticated_identity: Option<Pubkey>) -> Self {
+        Self {
+            session,
+            authenticated_identity,
+        }
+    }
+}
+
+impl Deref for PubSubSession {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        &self.session
+    }
+}
+
+impl jsonrpc_core::Metadata for PubSubSession {}
+
+impl PubSubMetadata for PubSubSession {
+    fn session(&self) -> Option<Arc<Session>> {
+        Some(self.session.clone())
+    }
+}
+
+impl RpcAuthMetadata for PubSubSession {
+    fn authenticated_identity(&self) -> Option<&Pubkey> {
+        self.authenticated_identity.as_ref()
+    }
+
+    // Connections authenticate once, when they are opened
+    fn rpc_auth_token(&self) -> Option<&RpcAuthToken> {
+        None
+    }
+
+    fn with_authenticated_identity(&self, identity: Option<Pubkey>) -> Self {
+        Self {
+            authenticated_identity: identity,
+            ..self.clone()
+        }
+    }
+}
+
 impl RpcSolPubSub for RpcSolPubSubImpl {
-    type Metadata = Arc<Session>;
+    type Metadata = PubSubSession;
 
     fn account_subscribe(
         &self,
@@ -223,8 +377,7 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
         }
         match param::<Pubkey>(&pubkey_str, "pubkey") {
             Ok(pubkey) => {
-                let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
-                let sub_id = SubscriptionId::Number(id as u64);
+                let sub_id = self.new_subscription_id("accountNotification");
                 info!("account_subscribe: account={:?} id={:?}", pubkey, sub_id);
                 self.subscriptions
                     .add_account_subscription(pubkey, config, sub_id, subscriber)
@@ -235,19 +388,13 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
 
     fn account_unsubscribe(
         &self,
-        _meta: Option<Self::Metadata>,
+        meta: Option<Self::Metadata>,
         id: SubscriptionId,
     ) -> Result<bool> {
         info!("account_unsubscribe: id={:?}", id);
-        if self.subscriptions.remove_account_subscription(&id) {
-            Ok(true)
-        } else {
-            Err(Error {
-                code: ErrorCode::InvalidParams,
-                message: "Invalid Request: Subscription id does not exist".into(),
-                data: None,
-            })
-        }
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_account_subscription(id)
+        })
     }
 
     fn program_subscribe(
@@ -263,8 +410,7 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
         }
         match param::<Pubkey>(&pubkey_str, "pubkey") {
             Ok(pubkey) => {
-                let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
-                let sub_id = SubscriptionId::Number(id as u64);
+                let sub_id = self.new_subscription_id("programNotification");
                 info!("program_subscribe: account={:?} id={:?}", pubkey, sub_id);
                 self.subscriptions
                     .add_program_subscription(pubkey, config, sub_id, subscriber)
@@ -275,19 +421,13 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
 
     fn program_unsubscribe(
         &self,
-        _meta: Option<Self::Metadata>,
+        meta: Option<Self::Metadata>,
         id: SubscriptionId,
     ) -> Result<bool> {
         info!("program_unsubscribe: id={:?}", id);
-        if self.subscriptions.remove_program_subscription(&id) {
-            Ok(true)
-        } else {
-            Err(Error {
-                code: ErrorCode::InvalidParams,
-                message: "Invalid Request: Subscription id does not exist".into(),
-                data: None,
-            })
-        }
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_program_subscription(id)
+        })
     }
 
     fn logs_subscribe(
@@ -331,8 +471,7 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
             }
         };
 
-        let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
-        let sub_id = SubscriptionId::Number(id as u64);
+        let sub_id = self.new_subscription_id("logsNotification");
         self.subscriptions.add_logs_subscription(
             address,
             include_votes,
@@ -342,17 +481,11 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
         )
     }
 
-    fn logs_unsubscribe(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+    fn logs_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
         info!("logs_unsubscribe: id={:?}", id);
-        if self.subscriptions.remove_logs_subscription(&id) {
-            Ok(true)
-        } else {
-            Err(Error {
-                code: ErrorCode::InvalidParams,
-                message: "Invalid Request: Subscription id does not exist".into(),
-                data: None,
-            })
-        }
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_logs_subscription(id)
+        })
     }
 
     fn signature_subscribe(
@@ -369,8 +502,7 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
         }
         match param::<Signature>(&signature_str, "signature") {
             Ok(signature) => {
-                let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
-                let sub_id = SubscriptionId::Number(id as u64);
+                let sub_id = self.new_subscription_id("signatureNotification");
                 info!(
                     "signature_subscribe: signature={:?} id={:?}",
                     signature, sub_id
@@ -388,19 +520,13 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
 
     fn signature_unsubscribe(
         &self,
-        _meta: Option<Self::Metadata>,
+        meta: Option<Self::Metadata>,
         id: SubscriptionId,
     ) -> Result<bool> {
         info!("signature_unsubscribe");
-        if self.subscriptions.remove_signature_subscription(&id) {
-            Ok(true)
-        } else {
-            Err(Error {
-                code: ErrorCode::InvalidParams,
-                message: "Invalid Request: Subscription id does not exist".into(),
-                data: None,
-            })
-        }
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_signature_subscription(id)
+        })
     }
 
     fn slot_subscribe(&self, _meta: Self::Metadata, subscriber: Subscriber<SlotInfo>) {
@@ -409,23 +535,16 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
             subscriber.reject(err).unwrap_or_default();
             return;
         }
-        let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
-        let sub_id = SubscriptionId::Number(id as u64);
+        let sub_id = self.new_subscription_id("slotNotification");
         info!("slot_subscribe: id={:?}", sub_id);
         self.subscriptions.add_slot_subscription(sub_id, subscriber);
     }
 
-    fn slot_unsubscribe(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+    fn slot_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
         info!("slot_unsubscribe");
-        if self.subscriptions.remove_slot_subscription(&id) {
-            Ok(true)
-        } else {
-            Err(Error {
-                code: ErrorCode::InvalidParams,
-                message: "Invalid Request: Subscription id does not exist".into(),
-                data: None,
-            })
-        }
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_slot_subscription(id)
+        })
     }
 
     fn vote_subscribe(&self, _meta: Self::Metadata, subscriber: Subscriber<RpcVote>) {
@@ -434,23 +553,16 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
             subscriber.reject(err).unwrap_or_default();
             return;
         }
-        let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
-        let sub_id = SubscriptionId::Number(id as u64);
+        let sub_id = self.new_subscription_id("voteNotification");
         info!("vote_subscribe: id={:?}", sub_id);
         self.subscriptions.add_vote_subscription(sub_id, subscriber);
     }
 
-    fn vote_unsubscribe(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+    fn vote_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
         info!("vote_unsubscribe");
-        if self.subscriptions.remove_vote_subscription(&id) {
-            Ok(true)
-        } else {
-            Err(Error {
-                code: ErrorCode::InvalidParams,
-                message: "Invalid Request: Subscription id does not exist".into(),
-                data: None,
-            })
-        }
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_vote_subscription(id)
+        })
     }
 
     fn root_subscribe(&self, _meta: Self::Metadata, subscriber: Subscriber<Slot>) {
@@ -459,22 +571,92 @@ impl RpcSolPubSub for RpcSolPubSubImpl {
             subscriber.reject(err).unwrap_or_default();
             return;
         }
-        let id = self.uid.fetch_add(1, atomic::Ordering::Relaxed);
-        let sub_id = SubscriptionId::Number(id as u64);
+        let sub_id = self.new_subscription_id("rootNotification");
         info!("root_subscribe: id={:?}", sub_id);
         self.subscriptions.add_root_subscription(sub_id, subscriber);
     }
 
-    fn root_unsubscribe(&self, _meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+    fn root_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
         info!("root_unsubscribe");
-        if self.subscriptions.remove_root_subscription(&id) {
-            Ok(true)
-        } else {
-            Err(Error {
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_root_subscription(id)
+        })
+    }
+
+    fn epoch_subscribe(&self, _meta: Self::Metadata, subscriber: Subscriber<EpochBoundaryInfo>) {
+        info!("epoch_subscribe");
+        if let Err(err) = self.check_subscription_count() {
+            subscriber.reject(err).unwrap_or_default();
+            return;
+        }
+        let sub_id = self.new_subscription_id("epochNotification");
+        info!("epoch_subscribe: id={:?}", sub_id);
+        self.subscriptions
+            .add_epoch_subscription(sub_id, subscriber);
+    }
+
+    fn epoch_unsubscribe(&self, meta: Option<Self::Metadata>, id: SubscriptionId) -> Result<bool> {
+        info!("epoch_unsubscribe");
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_epoch_subscription(id)
+        })
+    }
+
+    fn rewards_subscribe(
+        &self,
+        _meta: Self::Metadata,
+        subscriber: Subscriber<RpcBlockRewards>,
+        config: Option<RpcBlockRewardsConfig>,
+    ) {
+        info!("rewards_subscribe");
+        if let Err(err) = self.check_subscription_count() {
+            subscriber.reject(err).unwrap_or_default();
+            return;
+        }
+        let addresses = match verify_block_rewards_addresses(config) {
+            Ok(addresses) => addresses,
+            Err(err) => {
+                subscriber.reject(err).unwrap_or_default();
+                return;
+            }
+        };
+        let sub_id = self.new_subscription_id("rewardsNotification");
+        info!("rewards_subscribe: id={:?}", sub_id);
+        self.subscriptions
+            .add_rewards_subscription(addresses, sub_id, subscriber);
+    }
+
+    fn rewards_unsubscribe(
+        &self,
+        meta: Option<Self::Metadata>,
+        id: SubscriptionId,
+    ) -> Result<bool> {
+        info!("rewards_unsubscribe");
+        self.unsubscribe(meta, id, |id| {
+            self.subscriptions.remove_rewards_subscription(id)
+        })
+    }
+
+    fn subscription_resume(&self, meta: Self::Metadata, id: u64) -> Result<usize> {
+        info!("subscription_resume: id={}", id);
+        let sub_id = SubscriptionId::Number(id);
+        match self
+            .subscriptions
+            .resume_subscription(&sub_id, meta.sender())
+        {
+            Some((generation, num_missed_notifications)) => {
+                let subscriptions = self.subscriptions.clone();
+                meta.on_drop(move || {
+                    subscriptions.detach_resumed_subscription(&sub_id, generation);
+                });
+                Ok(num_missed_notifications)
+            }
+            None => Err(Error {
                 code: ErrorCode::InvalidParams,
-                message: "Invalid Request: Subscription id does not exist".into(),
+                message: "Invalid Request: Subscription id does not exist or is not resumable"
+                    .into(),
                 data: None,
-            })
+            }),
         }
     }
 }
@@ -542,8 +724,8 @@ mod tests {
         Ok(())
     }
 
-    fn create_session() -> Arc<Session> {
-        Arc::new(Session::new(mpsc::channel(1).0))
+    fn create_session() -> PubSubSession {
+        PubSubSession::new(Arc::new(Session::new(mpsc::channel(1).0)), None)
     }
 
     #[test]
@@ -567,6 +749,7 @@ mod tests {
                 OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
             )),
             uid: Arc::new(atomic::AtomicUsize::default()),
+            enable_subscription_resume: false,
         };
 
         // Test signature subscriptions
@@ -637,6 +820,80 @@ mod tests {
         assert_eq!(serde_json::to_string(&expected).unwrap(), response);
     }
 
+    #[test]
+    #[serial]
+    fn test_signature_subscription_resume() {
+        use jsonrpc_core::futures::Future;
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair: alice,
+            ..
+        } = create_genesis_config(10_000);
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+        let bank = Bank::new(&genesis_config);
+        let blockhash = bank.last_blockhash();
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let rpc = RpcSolPubSubImpl {
+            subscriptions: Arc::new(RpcSubscriptions::new(
+                &Arc::new(AtomicBool::new(false)),
+                bank_forks.clone(),
+                Arc::new(RwLock::new(BlockCommitmentCache::new_for_tests())),
+                OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+            )),
+            uid: Arc::new(atomic::AtomicUsize::default()),
+            enable_subscription_resume: true,
+        };
+        let tx = system_transaction::transfer(&alice, &bob_pubkey, 20, blockhash);
+
+        let (subscriber, id_receiver, _receiver) = Subscriber::new_test("signatureNotification");
+        rpc.signature_subscribe(
+            create_session(),
+            subscriber,
+            tx.signatures[0].to_string(),
+            Some(RpcSignatureSubscribeConfig {
+                commitment: Some(CommitmentConfig::finalized()),
+                ..RpcSignatureSubscribeConfig::default()
+            }),
+        );
+        let sub_id = match id_receiver.wait().unwrap().unwrap() {
+            SubscriptionId::Number(sub_id) => sub_id,
+            SubscriptionId::String(_) => panic!("expected a numeric subscription id"),
+        };
+        assert!(sub_id <= MAX_RESUMABLE_SUBSCRIPTION_ID);
+
+        // The connection drops before the transaction is processed
+        assert_eq!(
+            rpc.signature_unsubscribe(None, SubscriptionId::Number(sub_id)),
+            Ok(true)
+        );
+        process_transaction_and_notify(&bank_forks, &tx, &rpc.subscriptions, 0).unwrap();
+
+        // The notification is delivered on the connection that resumes the subscription
+        let (sender, receiver) = mpsc::channel(1);
+        let session = PubSubSession::new(Arc::new(Session::new(sender)), None);
+        assert!(rpc.subscription_resume(session.clone(), sub_id).is_ok());
+        let (response, _) = robust_poll_or_panic(receiver);
+        let expected = json!({
+           "jsonrpc": "2.0",
+           "method": "signatureNotification",
+           "params": {
+               "result": {
+                   "context": { "slot": 0 },
+                   "value": RpcSignatureResult::ProcessedSignature(
+                       ProcessedSignatureResult { err: None }
+                   ),
+               },
+               "subscription": sub_id,
+           }
+        });
+        assert_eq!(serde_json::to_string(&expected).unwrap(), response);
+
+        // Once the notification is delivered, the subscription can't be resumed again
+        assert!(rpc.subscription_resume(session, sub_id).is_err());
+        assert!(rpc.subscription_resume(create_session(), 42).is_err());
+    }
+
     #[test]
     #[serial]
     fn test_signature_unsubscribe() {
@@ -713,6 +970,7 @@ mod tests {
                 OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
             )),
             uid: Arc::new(atomic::AtomicUsize::default()),
+            enable_subscription_resume: false,
         };
         let session = create_session();
         let (subscriber, _id_receiver, receiver) = Subscriber::new_test("accountNotification");
@@ -823,6 +1081,7 @@ mod tests {
                 OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
             )),
             uid: Arc::new(atomic::AtomicUsize::default()),
+            enable_subscription_resume: false,
         };
         let session = create_session();
         let (subscriber, _id_receiver, receiver) = Subscriber::new_test("accountNotification");
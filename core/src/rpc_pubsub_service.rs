@@ -1,9 +1,11 @@
 //! The `pubsub` module implements a threaded subscription service on client RPC request
 
 use crate::{
-    rpc_pubsub::{RpcSolPubSub, RpcSolPubSubImpl},
+    rpc_auth::{RpcAuthConfig, RpcAuthMiddleware},
+    rpc_pubsub::{PubSubSession, RpcSolPubSub, RpcSolPubSubImpl},
     rpc_subscriptions::RpcSubscriptions,
 };
+use jsonrpc_core::MetaIoHandler;
 use jsonrpc_pubsub::{PubSubHandler, Session};
 use jsonrpc_ws_server::{RequestContext, ServerBuilder};
 use std::{
@@ -19,6 +21,11 @@ use std::{
 #[derive(Debug, Clone)]
 pub struct PubSubConfig {
     pub enable_vote_subscription: bool,
+    // Keep subscriptions alive for a while after their connection drops, so clients can
+    // resume them with `subscriptionResume` without missing notifications
+    pub enable_subscription_resume: bool,
+    // Connections authenticate with a token passed as a websocket protocol when they are opened
+    pub rpc_auth: Option<RpcAuthConfig>,
 
     // See the corresponding fields in
     // https://github.com/paritytech/ws-rs/blob/be4d47575bae55c60d9f51b47480d355492a94fc/src/lib.rs#L131
@@ -33,6 +40,8 @@ impl Default for PubSubConfig {
     fn default() -> Self {
         Self {
             enable_vote_subscription: false,
+            enable_subscription_resume: false,
+            rpc_auth: None,
             max_connections: 1000, // Arbitrary, default of 100 is too low
             max_fragment_size: 50 * 1024, // 50KB
             max_in_buffer_capacity: 50 * 1024, // 50KB
@@ -53,7 +62,11 @@ impl PubSubService {
         exit: &Arc<AtomicBool>,
     ) -> Self {
         info!("rpc_pubsub bound to {:?}", pubsub_addr);
-        let rpc = RpcSolPubSubImpl::new(subscriptions.clone());
+        let rpc = RpcSolPubSubImpl::new(
+            subscriptions.clone(),
+            pubsub_config.enable_subscription_resume,
+        );
+        let rpc_auth_middleware = RpcAuthMiddleware::new(pubsub_config.rpc_auth.clone());
         let exit_ = exit.clone();
 
         // TODO: Once https://github.com/paritytech/jsonrpc/pull/594 lands, use
@@ -72,20 +85,23 @@ impl PubSubService {
         let thread_hdl = Builder::new()
             .name("solana-pubsub".to_string())
             .spawn(move || {
-                let mut io = PubSubHandler::default();
+                let mut io =
+                    PubSubHandler::new(MetaIoHandler::with_middleware(rpc_auth_middleware.clone()));
                 io.extend_with(rpc.to_delegate());
 
-                let server = ServerBuilder::with_meta_extractor(io, |context: &RequestContext| {
-                    info!("New pubsub connection");
-                    let session = Arc::new(Session::new(context.sender()));
-                    session.on_drop(|| {
-                        info!("Pubsub connection dropped");
-                    });
-                    session
-                })
-                .max_connections(pubsub_config.max_connections)
-                .max_payload(max_payload)
-                .start(&pubsub_addr);
+                let server =
+                    ServerBuilder::with_meta_extractor(io, move |context: &RequestContext| {
+                        let identity = rpc_auth_middleware.authenticate_pubsub(&context.protocols);
+                        info!("New pubsub connection, authenticated as {:?}", identity);
+                        let session = Arc::new(Session::new(context.sender()));
+                        session.on_drop(|| {
+                            info!("Pubsub connection dropped");
+                        });
+                        PubSubSession::new(session, identity)
+                    })
+                    .max_connections(pubsub_config.max_connections)
+                    .max_payload(max_payload)
+                    .start(&pubsub_addr);
 
                 if let Err(e) = server {
                     warn!(
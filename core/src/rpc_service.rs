@@ -3,10 +3,16 @@
 use crate::{
     bigtable_upload_service::BigTableUploadService,
     cluster_info::ClusterInfo,
+    object_archive_service::ObjectArchiveService,
     optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
     poh_recorder::PohRecorder,
+    program_stats::ProgramStats,
+    repair_status::RepairStatus,
     rpc::*,
+    rpc_auth::{RpcAuthMiddleware, RpcAuthToken},
+    rpc_cache::LargestAccountsCache,
     rpc_health::*,
+    sample_performance_service::RecentPerfSamples,
     send_transaction_service::{LeaderInfo, SendTransactionService},
     validator::ValidatorExit,
 };
@@ -18,6 +24,7 @@ use jsonrpc_http_server::{
 use regex::Regex;
 use solana_ledger::blockstore::Blockstore;
 use solana_metrics::inc_new_counter_info;
+use solana_rayon_threadlimit::thread_manager;
 use solana_runtime::{
     bank_forks::{BankForks, SnapshotConfig},
     commitment::BlockCommitmentCache,
@@ -34,6 +41,8 @@ use std::{
 };
 use tokio::runtime;
 
+const LARGEST_ACCOUNTS_CACHE_DURATION: u64 = 60 * 60 * 2;
+
 pub struct JsonRpcService {
     thread_hdl: JoinHandle<()>,
 
@@ -270,6 +279,9 @@ impl JsonRpcService {
         optimistically_confirmed_bank: Arc<RwLock<OptimisticallyConfirmedBank>>,
         send_transaction_retry_ms: u64,
         send_transaction_leader_forward_count: u64,
+        program_stats: Arc<RwLock<ProgramStats>>,
+        repair_status: Arc<RwLock<RepairStatus>>,
+        recent_perf_samples: Arc<RwLock<RecentPerfSamples>>,
     ) -> Self {
         info!("rpc bound to {:?}", rpc_addr);
         info!("rpc configuration: {:?}", config);
@@ -285,6 +297,10 @@ impl JsonRpcService {
         let tpu_address = cluster_info.my_contact_info().tpu;
         let mut runtime = runtime::Builder::new()
             .threaded_scheduler()
+            .core_threads(thread_manager::register_pool(
+                "rpc-runtime",
+                num_cpus::get(),
+            ))
             .thread_name("rpc-runtime")
             .enable_all()
             .build()
@@ -328,6 +344,47 @@ impl JsonRpcService {
                 (None, None)
             };
 
+        let exit_object_archive_service = Arc::new(AtomicBool::new(false));
+
+        let (ledger_archive, _object_archive_service) =
+            if let Some(object_storage_url) = &config.object_storage_url {
+                solana_storage_object::LedgerArchive::from_url(
+                    object_storage_url,
+                    config.rpc_bigtable_timeout,
+                )
+                .map(|ledger_archive| {
+                    info!("Object storage ledger archive initialized");
+
+                    let object_archive_service = if config.enable_object_archive_upload {
+                        Some(Arc::new(ObjectArchiveService::new(
+                            runtime.handle().clone(),
+                            ledger_archive.clone(),
+                            blockstore.clone(),
+                            block_commitment_cache.clone(),
+                            exit_object_archive_service.clone(),
+                        )))
+                    } else {
+                        None
+                    };
+
+                    (Some(ledger_archive), object_archive_service)
+                })
+                .unwrap_or_else(|err| {
+                    error!(
+                        "Failed to initialize object storage ledger archive: {:?}",
+                        err
+                    );
+                    (None, None)
+                })
+            } else {
+                (None, None)
+            };
+
+        let largest_accounts_cache = Arc::new(RwLock::new(LargestAccountsCache::new(
+            LARGEST_ACCOUNTS_CACHE_DURATION,
+        )));
+
+        let rpc_auth_middleware = RpcAuthMiddleware::new(config.rpc_auth.clone());
         let (request_processor, receiver) = JsonRpcRequestProcessor::new(
             config,
             snapshot_config.clone(),
@@ -340,7 +397,12 @@ impl JsonRpcService {
             genesis_hash,
             &runtime,
             bigtable_ledger_storage,
+            ledger_archive,
             optimistically_confirmed_bank,
+            largest_accounts_cache,
+            program_stats,
+            repair_status,
+            recent_perf_samples,
         );
 
         let leader_info =
@@ -377,7 +439,7 @@ impl JsonRpcService {
         let thread_hdl = Builder::new()
             .name("solana-jsonrpc".to_string())
             .spawn(move || {
-                let mut io = MetaIoHandler::default();
+                let mut io = MetaIoHandler::with_middleware(rpc_auth_middleware);
                 let rpc = RpcSolImpl;
                 io.extend_with(rpc.to_delegate());
 
@@ -389,7 +451,13 @@ impl JsonRpcService {
                 );
                 let server = ServerBuilder::with_meta_extractor(
                     io,
-                    move |_req: &hyper::Request<hyper::Body>| request_processor.clone(),
+                    move |req: &hyper::Request<hyper::Body>| {
+                        request_processor.with_rpc_auth_token(RpcAuthToken::from_headers(|name| {
+                            req.headers()
+                                .get(name)
+                                .and_then(|value| value.to_str().ok())
+                        }))
+                    },
                 )
                 .event_loop_executor(event_loop.executor())
                 .threads(1)
@@ -415,6 +483,7 @@ impl JsonRpcService {
                 close_handle_sender.send(server.close_handle()).unwrap();
                 server.wait();
                 exit_bigtable_ledger_upload_service.store(true, Ordering::Relaxed);
+                exit_object_archive_service.store(true, Ordering::Relaxed);
             })
             .unwrap();
 
@@ -500,6 +569,9 @@ mod tests {
             optimistically_confirmed_bank,
             1000,
             1,
+            Arc::new(RwLock::new(ProgramStats::default())),
+            Arc::new(RwLock::new(RepairStatus::default())),
+            Arc::new(RwLock::new(RecentPerfSamples::default())),
         );
         let thread = rpc_service.thread_hdl.thread();
         assert_eq!(thread.name().unwrap(), "solana-jsonrpc");
@@ -0,0 +1,505 @@
+//! The `rpc_subscription_resume` module keeps pubsub subscriptions alive for a short window after
+//! their connection drops, buffering the notifications they would have received, so that a
+//! client reconnecting with `subscriptionResume` doesn't miss any of them.
+
+use jsonrpc_core::futures::sync::mpsc;
+use jsonrpc_pubsub::SubscriptionId;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+/// How long a subscription survives after its connection drops
+pub const DEFAULT_SUBSCRIPTION_RESUME_WINDOW: Duration = Duration::from_secs(30);
+
+/// Notifications buffered per detached subscription, the oldest are dropped first
+pub const MAX_BUFFERED_NOTIFICATIONS: usize = 256;
+
+/// Bytes of notifications buffered per detached subscription, the oldest are dropped first
+pub const MAX_BUFFERED_BYTES_PER_SUBSCRIPTION: usize = 1024 * 1024;
+
+/// Bytes of notifications buffered across all detached subscriptions. Past it, the
+/// subscriptions detached the longest are evicted as if they had expired
+pub const MAX_BUFFERED_BYTES: usize = 64 * 1024 * 1024;
+
+/// Raw write stream of a pubsub connection, as returned by `Session::sender()`
+pub type TransportSender = mpsc::Sender<String>;
+
+// Ids of resumable subscriptions are random, but kept within the range of integers a javascript
+// number represents exactly
+pub const MAX_RESUMABLE_SUBSCRIPTION_ID: u64 = (1 << 53) - 1;
+
+enum Route {
+    /// Notifications go to the sink of the connection that subscribed
+    Attached,
+    /// The connection dropped, notifications are buffered until the subscription is resumed
+    Detached {
+        since: Instant,
+        buffer: VecDeque<String>,
+        buffered_bytes: usize,
+    },
+    /// The subscription was resumed on another connection
+    Resumed {
+        transport: TransportSender,
+        generation: u64,
+    },
+}
+
+struct ResumableSubscription {
+    notification: &'static str,
+    route: Route,
+    // The subscription won't send more notifications, it is only kept for its buffer
+    finished: bool,
+}
+
+pub(crate) enum Delivery {
+    /// Not resumable or still attached, notify through the subscription sink
+    Sink,
+    /// Buffered for a later resume
+    Buffered,
+    /// Send this serialized notification to the connection that resumed the subscription
+    Transport(TransportSender, String),
+}
+
+pub struct ResumedSubscription {
+    pub transport: TransportSender,
+    pub generation: u64,
+    /// Notifications missed while the subscription was detached, oldest first
+    pub missed_notifications: Vec<String>,
+}
+
+pub struct ResumableSubscriptions {
+    window: Duration,
+    max_buffered_bytes: usize,
+    subscriptions: RwLock<HashMap<SubscriptionId, ResumableSubscription>>,
+    next_generation: AtomicU64,
+    // Bytes buffered across all detached subscriptions, only updated with the subscriptions
+    // write-locked
+    buffered_bytes: AtomicUsize,
+    // Subscriptions evicted to keep within `max_buffered_bytes`, until `expire` returns them
+    evicted: Mutex<Vec<SubscriptionId>>,
+}
+
+impl Default for ResumableSubscriptions {
+    fn default() -> Self {
+        Self::new(DEFAULT_SUBSCRIPTION_RESUME_WINDOW)
+    }
+}
+
+impl ResumableSubscriptions {
+    pub fn new(window: Duration) -> Self {
+        Self::new_with_max_buffered_bytes(window, MAX_BUFFERED_BYTES)
+    }
+
+    pub fn new_with_max_buffered_bytes(window: Duration, max_buffered_bytes: usize) -> Self {
+        Self {
+            window,
+            max_buffered_bytes,
+            subscriptions: RwLock::default(),
+            next_generation: AtomicU64::default(),
+            buffered_bytes: AtomicUsize::default(),
+            evicted: Mutex::default(),
+        }
+    }
+
+    fn new_detached_route() -> Route {
+        Route::Detached {
+            since: Instant::now(),
+            buffer: VecDeque::new(),
+            buffered_bytes: 0,
+        }
+    }
+
+    // Accounts for the notifications buffered in `route` being dropped
+    fn release(&self, route: &Route) {
+        if let Route::Detached { buffered_bytes, .. } = route {
+            self.buffered_bytes
+                .fetch_sub(*buffered_bytes, Ordering::Relaxed);
+        }
+    }
+
+    /// Makes subscription `id`, sending `notification` messages, resumable
+    pub fn register(&self, id: SubscriptionId, notification: &'static str) {
+        self.subscriptions.write().unwrap().insert(
+            id,
+            ResumableSubscription {
+                notification,
+                route: Route::Attached,
+                finished: false,
+            },
+        );
+    }
+
+    pub fn contains(&self, id: &SubscriptionId) -> bool {
+        self.subscriptions.read().unwrap().contains_key(id)
+    }
+
+    /// Stops tracking subscription `id`, once unsubscribed
+    pub fn forget(&self, id: &SubscriptionId) {
+        if let Some(subscription) = self.subscriptions.write().unwrap().remove(id) {
+            self.release(&subscription.route);
+        }
+    }
+
+    /// Starts buffering the notifications of subscription `id` after its connection dropped.
+    /// Returns false if the subscription is not resumable
+    pub fn detach(&self, id: &SubscriptionId) -> bool {
+        match self.subscriptions.write().unwrap().get_mut(id) {
+            Some(subscription) => {
+                if !matches!(subscription.route, Route::Detached { .. }) {
+                    subscription.route = Self::new_detached_route();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Detaches subscription `id` if it is still routed to the connection that resumed it as
+    /// `generation`
+    pub fn detach_resumed(&self, id: &SubscriptionId, generation: u64) {
+        let mut subscriptions = self.subscriptions.write().unwrap();
+        if let Some(subscription) = subscriptions.get_mut(id) {
+            match subscription.route {
+                Route::Resumed {
+                    generation: resumed_generation,
+                    ..
+                } if resumed_generation == generation => {
+                    if subscription.finished {
+                        subscriptions.remove(id);
+                    } else {
+                        subscription.route = Self::new_detached_route();
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Marks subscription `id` as done sending notifications, like signature subscriptions
+    /// after their notification. It's forgotten unless notifications are waiting for a resume
+    pub fn finish(&self, id: &SubscriptionId) {
+        let mut subscriptions = self.subscriptions.write().unwrap();
+        if let Some(subscription) = subscriptions.get_mut(id) {
+            if matches!(subscription.route, Route::Detached { .. }) {
+                subscription.finished = true;
+            } else {
+                subscriptions.remove(id);
+            }
+        }
+    }
+
+    /// Routes subscription `id` to `transport`, taking the notifications missed since it
+    /// was detached. Only detached subscriptions can be resumed, so that a live connection
+    /// can't have its subscriptions taken over
+    pub fn resume(
+        &self,
+        id: &SubscriptionId,
+        transport: TransportSender,
+    ) -> Option<ResumedSubscription> {
+        let mut subscriptions = self.subscriptions.write().unwrap();
+        let subscription = subscriptions
+            .get_mut(id)
+            .filter(|subscription| matches!(subscription.route, Route::Detached { .. }))?;
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        let route = std::mem::replace(
+            &mut subscription.route,
+            Route::Resumed {
+                transport: transport.clone(),
+                generation,
+            },
+        );
+        self.release(&route);
+        let missed_notifications = match route {
+            Route::Detached { buffer, .. } => buffer.into_iter().collect(),
+            _ => vec![],
+        };
+        if subscription.finished {
+            subscriptions.remove(id);
+        }
+        Some(ResumedSubscription {
+            transport,
+            generation,
+            missed_notifications,
+        })
+    }
+
+    /// Decides how a notification of subscription `id` is delivered
+    pub(crate) fn delivery<T: Serialize>(&self, id: &SubscriptionId, value: &T) -> Delivery {
+        {
+            let subscriptions = self.subscriptions.read().unwrap();
+            match subscriptions
+                .get(id)
+                .map(|subscription| &subscription.route)
+            {
+                None | Some(Route::Attached) => return Delivery::Sink,
+                Some(Route::Resumed { transport, .. }) => {
+                    let notification = subscriptions[id].notification;
+                    return Delivery::Transport(
+                        transport.clone(),
+                        serialize_notification(notification, id, value),
+                    );
+                }
+                Some(Route::Detached { .. }) => (),
+            }
+        }
+
+        let mut subscriptions = self.subscriptions.write().unwrap();
+        let delivery = match subscriptions.get_mut(id) {
+            Some(ResumableSubscription {
+                notification,
+                route:
+                    Route::Detached {
+                        buffer,
+                        buffered_bytes,
+                        ..
+                    },
+                ..
+            }) => {
+                let serialized_notification = serialize_notification(notification, id, value);
+                while !buffer.is_empty()
+                    && (buffer.len() >= MAX_BUFFERED_NOTIFICATIONS
+                        || *buffered_bytes + serialized_notification.len()
+                            > MAX_BUFFERED_BYTES_PER_SUBSCRIPTION)
+                {
+                    let dropped_bytes = buffer.pop_front().unwrap().len();
+                    *buffered_bytes -= dropped_bytes;
+                    self.buffered_bytes
+                        .fetch_sub(dropped_bytes, Ordering::Relaxed);
+                    inc_new_counter_info!("rpc-subscription-resume-buffer-overflow", 1);
+                }
+                *buffered_bytes += serialized_notification.len();
+                self.buffered_bytes
+                    .fetch_add(serialized_notification.len(), Ordering::Relaxed);
+                buffer.push_back(serialized_notification);
+                Delivery::Buffered
+            }
+            // Resumed or forgotten while waiting for the write lock
+            _ => return Delivery::Sink,
+        };
+        self.evict(&mut subscriptions);
+        delivery
+    }
+
+    // Evicts the subscriptions detached the longest until the buffered notifications fit in
+    // `max_buffered_bytes`
+    fn evict(&self, subscriptions: &mut HashMap<SubscriptionId, ResumableSubscription>) {
+        while self.buffered_bytes.load(Ordering::Relaxed) > self.max_buffered_bytes {
+            let oldest = subscriptions
+                .iter()
+                .filter_map(|(id, subscription)| match subscription.route {
+                    Route::Detached { since, .. } => Some((since, id)),
+                    _ => None,
+                })
+                .min_by_key(|(since, _)| *since)
+                .map(|(_, id)| id.clone());
+            let id = match oldest {
+                Some(id) => id,
+                None => break,
+            };
+            let subscription = subscriptions.remove(&id).unwrap();
+            self.release(&subscription.route);
+            inc_new_counter_info!("rpc-subscription-resume-evicted", 1);
+            self.evicted.lock().unwrap().push(id);
+        }
+    }
+
+    /// Drops the subscriptions detached for longer than the resume window, returning their ids
+    /// along with the ids of evicted subscriptions, so they can be unsubscribed
+    pub fn expire(&self) -> Vec<SubscriptionId> {
+        let mut expired = std::mem::take(&mut *self.evicted.lock().unwrap());
+        let window = self.window;
+        let mut subscriptions = self.subscriptions.write().unwrap();
+        let expired_ids: Vec<_> = subscriptions
+            .iter()
+            .filter_map(|(id, subscription)| match subscription.route {
+                Route::Detached { since, .. } if since.elapsed() > window => Some(id.clone()),
+                _ => None,
+            })
+            .collect();
+        for id in expired_ids {
+            let subscription = subscriptions.remove(&id).unwrap();
+            self.release(&subscription.route);
+            expired.push(id);
+        }
+        expired
+    }
+
+    /// Bytes of notifications buffered across all detached subscriptions
+    pub fn buffered_bytes(&self) -> usize {
+        self.buffered_bytes.load(Ordering::Relaxed)
+    }
+}
+
+fn serialize_notification<T: Serialize>(
+    notification: &str,
+    id: &SubscriptionId,
+    value: &T,
+) -> String {
+    let id = match id {
+        SubscriptionId::Number(id) => json!(id),
+        SubscriptionId::String(id) => json!(id),
+    };
+    json!({
+        "jsonrpc": "2.0",
+        "method": notification,
+        "params": {
+            "result": value,
+            "subscription": id,
+        },
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detach_and_resume() {
+        let resumable = ResumableSubscriptions::default();
+        let id = SubscriptionId::Number(42);
+        assert!(matches!(resumable.delivery(&id, &1), Delivery::Sink));
+        assert!(!resumable.detach(&id));
+
+        resumable.register(id.clone(), "slotNotification");
+        assert!(matches!(resumable.delivery(&id, &1), Delivery::Sink));
+
+        assert!(resumable.detach(&id));
+        assert!(matches!(resumable.delivery(&id, &2), Delivery::Buffered));
+        assert!(matches!(resumable.delivery(&id, &3), Delivery::Buffered));
+
+        let (transport, _receiver) = mpsc::channel(1);
+        let resumed = resumable.resume(&id, transport).unwrap();
+        assert_eq!(
+            resumed.missed_notifications,
+            vec![
+                r#"{"jsonrpc":"2.0","method":"slotNotification","params":{"result":2,"subscription":42}}"#,
+                r#"{"jsonrpc":"2.0","method":"slotNotification","params":{"result":3,"subscription":42}}"#,
+            ]
+        );
+        match resumable.delivery(&id, &4) {
+            Delivery::Transport(_, notification) => assert_eq!(
+                notification,
+                r#"{"jsonrpc":"2.0","method":"slotNotification","params":{"result":4,"subscription":42}}"#
+            ),
+            _ => panic!("expected delivery to the resumed connection"),
+        }
+
+        // Subscriptions that aren't detached can't be taken over by another connection
+        let (transport, _other_receiver) = mpsc::channel(1);
+        assert!(resumable.resume(&id, transport).is_none());
+        let attached = SubscriptionId::Number(43);
+        resumable.register(attached.clone(), "slotNotification");
+        let (transport, _other_receiver) = mpsc::channel(1);
+        assert!(resumable.resume(&attached, transport).is_none());
+
+        // A stale connection doesn't detach the subscription resumed on a newer one
+        resumable.detach_resumed(&id, resumed.generation);
+        let (transport, _newer_receiver) = mpsc::channel(1);
+        let newer = resumable.resume(&id, transport).unwrap();
+        resumable.detach_resumed(&id, resumed.generation);
+        assert!(matches!(
+            resumable.delivery(&id, &5),
+            Delivery::Transport(..)
+        ));
+        resumable.detach_resumed(&id, newer.generation);
+        assert!(matches!(resumable.delivery(&id, &6), Delivery::Buffered));
+    }
+
+    #[test]
+    fn test_finish_and_expire() {
+        let resumable = ResumableSubscriptions::new(Duration::from_millis(0));
+        let attached = SubscriptionId::Number(1);
+        let detached = SubscriptionId::Number(2);
+        resumable.register(attached.clone(), "signatureNotification");
+        resumable.register(detached.clone(), "signatureNotification");
+
+        // Attached subscriptions are forgotten as soon as they finish
+        resumable.finish(&attached);
+        assert!(!resumable.contains(&attached));
+
+        // Detached ones are kept until their notification is picked up
+        resumable.detach(&detached);
+        assert!(matches!(
+            resumable.delivery(&detached, &"confirmed"),
+            Delivery::Buffered
+        ));
+        resumable.finish(&detached);
+        assert!(resumable.contains(&detached));
+        let (transport, _receiver) = mpsc::channel(1);
+        let resumed = resumable.resume(&detached, transport).unwrap();
+        assert_eq!(resumed.missed_notifications.len(), 1);
+        assert!(!resumable.contains(&detached));
+
+        // Overflowing buffers keep the most recent notifications
+        resumable.register(detached.clone(), "slotNotification");
+        resumable.detach(&detached);
+        for slot in 0..MAX_BUFFERED_NOTIFICATIONS + 1 {
+            resumable.delivery(&detached, &slot);
+        }
+        let (transport, _receiver) = mpsc::channel(1);
+        let resumed = resumable.resume(&detached, transport).unwrap();
+        assert_eq!(
+            resumed.missed_notifications.len(),
+            MAX_BUFFERED_NOTIFICATIONS
+        );
+        assert!(resumed.missed_notifications[0].contains(r#""result":1,"#));
+
+        // Subscriptions detached for longer than the window expire
+        let expiring = SubscriptionId::Number(3);
+        resumable.register(expiring.clone(), "slotNotification");
+        resumable.detach(&expiring);
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(resumable.expire(), vec![expiring.clone()]);
+        assert!(resumable.resume(&expiring, mpsc::channel(1).0).is_none());
+    }
+
+    #[test]
+    fn test_buffered_bytes_budget() {
+        let resumable = ResumableSubscriptions::new_with_max_buffered_bytes(
+            DEFAULT_SUBSCRIPTION_RESUME_WINDOW,
+            3 * MAX_BUFFERED_BYTES_PER_SUBSCRIPTION / 2,
+        );
+        let data = "x".repeat(MAX_BUFFERED_BYTES_PER_SUBSCRIPTION / 4);
+
+        // Each subscription keeps its most recent notifications within its own budget
+        let first = SubscriptionId::Number(1);
+        resumable.register(first.clone(), "accountNotification");
+        resumable.detach(&first);
+        for _ in 0..5 {
+            resumable.delivery(&first, &data);
+        }
+        assert!(resumable.buffered_bytes() <= MAX_BUFFERED_BYTES_PER_SUBSCRIPTION);
+        assert!(resumable.buffered_bytes() > MAX_BUFFERED_BYTES_PER_SUBSCRIPTION / 2);
+
+        // Past the global budget, the subscription detached the longest is evicted
+        let second = SubscriptionId::Number(2);
+        resumable.register(second.clone(), "accountNotification");
+        std::thread::sleep(Duration::from_millis(1));
+        resumable.detach(&second);
+        for _ in 0..3 {
+            resumable.delivery(&second, &data);
+        }
+        assert!(!resumable.contains(&first));
+        assert!(resumable.contains(&second));
+        assert!(resumable.buffered_bytes() <= MAX_BUFFERED_BYTES_PER_SUBSCRIPTION);
+        assert_eq!(resumable.expire(), vec![first.clone()]);
+        assert!(resumable.resume(&first, mpsc::channel(1).0).is_none());
+
+        // Resuming or forgetting subscriptions releases their buffers
+        let (transport, _receiver) = mpsc::channel(1);
+        let resumed = resumable.resume(&second, transport).unwrap();
+        assert_eq!(resumed.missed_notifications.len(), 3);
+        assert_eq!(resumable.buffered_bytes(), 0);
+        resumable.detach_resumed(&second, resumed.generation);
+        resumable.delivery(&second, &data);
+        resumable.forget(&second);
+        assert_eq!(resumable.buffered_bytes(), 0);
+    }
+}
@@ -2,13 +2,16 @@
 
 use crate::{
     optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
-    rpc::{get_parsed_token_account, get_parsed_token_accounts},
+    rpc::{filter_block_rewards, get_parsed_token_account, get_parsed_token_accounts},
+    rpc_subscription_resume::{
+        Delivery, ResumableSubscriptions, ResumedSubscription, TransportSender,
+    },
 };
 use core::hash::Hash;
-use jsonrpc_core::futures::Future;
+use jsonrpc_core::futures::{stream, Future, Sink as FuturesSink};
 use jsonrpc_pubsub::{
     typed::{Sink, Subscriber},
-    SubscriptionId,
+    SubscriptionId, TransportError,
 };
 use serde::Serialize;
 use solana_account_decoder::{parse_token::spl_token_id_v2_0, UiAccount, UiAccountEncoding};
@@ -16,8 +19,9 @@ use solana_client::{
     rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSignatureSubscribeConfig},
     rpc_filter::RpcFilterType,
     rpc_response::{
-        ProcessedSignatureResult, ReceivedSignatureResult, Response, RpcKeyedAccount,
-        RpcLogsResponse, RpcResponseContext, RpcSignatureResult, SlotInfo,
+        EpochBoundaryInfo, ProcessedSignatureResult, ReceivedSignatureResult, Response,
+        RpcBlockRewards, RpcKeyedAccount, RpcLogsResponse, RpcResponseContext, RpcSignatureResult,
+        SlotInfo,
     },
 };
 use solana_measure::measure::Measure;
@@ -46,13 +50,14 @@ use std::{
     },
     sync::{Arc, Mutex, RwLock},
     thread::{Builder, JoinHandle},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 // Stuck on tokio 0.1 until the jsonrpc-pubsub crate upgrades to tokio 0.2
 use tokio_01::runtime::{Builder as RuntimeBuilder, Runtime, TaskExecutor};
 
 const RECEIVE_DELAY_MILLIS: u64 = 100;
+const EXPIRE_DETACHED_SUBSCRIPTIONS_INTERVAL: Duration = Duration::from_secs(1);
 
 trait BankGetTransactionLogsAdapter {
     fn get_transaction_logs_adapter(
@@ -88,6 +93,8 @@ enum NotificationEntry {
     Slot(SlotInfo),
     Vote(Vote),
     Root(Slot),
+    EpochBoundary(EpochBoundaryInfo),
+    Rewards(RpcBlockRewards),
     Bank(CommitmentSlots),
     Gossip(Slot),
     SignaturesReceived((Slot, Vec<Signature>)),
@@ -97,6 +104,12 @@ impl std::fmt::Debug for NotificationEntry {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             NotificationEntry::Root(root) => write!(f, "Root({})", root),
+            NotificationEntry::EpochBoundary(epoch_boundary) => {
+                write!(f, "EpochBoundary({})", epoch_boundary.epoch)
+            }
+            NotificationEntry::Rewards(block_rewards) => {
+                write!(f, "Rewards({})", block_rewards.slot)
+            }
             NotificationEntry::Vote(vote) => write!(f, "Vote({:?})", vote),
             NotificationEntry::Slot(slot_info) => write!(f, "Slot({:?})", slot_info),
             NotificationEntry::Bank(commitment_slots) => {
@@ -148,6 +161,9 @@ type RpcSignatureSubscriptions = RwLock<
 type RpcSlotSubscriptions = RwLock<HashMap<SubscriptionId, Sink<SlotInfo>>>;
 type RpcVoteSubscriptions = RwLock<HashMap<SubscriptionId, Sink<RpcVote>>>;
 type RpcRootSubscriptions = RwLock<HashMap<SubscriptionId, Sink<Slot>>>;
+type RpcEpochSubscriptions = RwLock<HashMap<SubscriptionId, Sink<EpochBoundaryInfo>>>;
+type RpcRewardsSubscriptions =
+    RwLock<HashMap<SubscriptionId, (Sink<RpcBlockRewards>, Option<HashSet<String>>)>>;
 
 fn add_subscription<K, S, T>(
     subscriptions: &mut HashMap<K, HashMap<SubscriptionId, SubscriptionData<S, T>>>,
@@ -252,6 +268,7 @@ where
                             value: result,
                         },
                         sink,
+                        sub_id,
                     );
                     notified_set.insert(sub_id.clone());
                     *w_last_notified_slot = result_slot;
@@ -262,15 +279,25 @@ where
     notified_set
 }
 
-struct RpcNotifier(TaskExecutor);
+struct RpcNotifier {
+    executor: TaskExecutor,
+    resumable_subscriptions: Arc<ResumableSubscriptions>,
+}
 
 impl RpcNotifier {
-    fn notify<T>(&self, value: T, sink: &Sink<T>)
+    fn notify<T>(&self, value: T, sink: &Sink<T>, sub_id: &SubscriptionId)
     where
         T: serde::Serialize,
     {
-        self.0
-            .spawn(sink.notify(Ok(value)).map(|_| ()).map_err(|_| ()));
+        match self.resumable_subscriptions.delivery(sub_id, &value) {
+            Delivery::Sink => self
+                .executor
+                .spawn(sink.notify(Ok(value)).map(|_| ()).map_err(|_| ())),
+            Delivery::Buffered => (),
+            Delivery::Transport(transport, notification) => self
+                .executor
+                .spawn(transport.send(notification).map(|_| ()).map_err(|_| ())),
+        }
     }
 }
 
@@ -394,6 +421,9 @@ struct Subscriptions {
     slot_subscriptions: Arc<RpcSlotSubscriptions>,
     vote_subscriptions: Arc<RpcVoteSubscriptions>,
     root_subscriptions: Arc<RpcRootSubscriptions>,
+    epoch_subscriptions: Arc<RpcEpochSubscriptions>,
+    rewards_subscriptions: Arc<RpcRewardsSubscriptions>,
+    resumable_subscriptions: Arc<ResumableSubscriptions>,
 }
 
 impl Subscriptions {
@@ -410,6 +440,8 @@ impl Subscriptions {
         total += self.slot_subscriptions.read().unwrap().len();
         total += self.vote_subscriptions.read().unwrap().len();
         total += self.root_subscriptions.read().unwrap().len();
+        total += self.epoch_subscriptions.read().unwrap().len();
+        total += self.rewards_subscriptions.read().unwrap().len();
         total
     }
 }
@@ -473,6 +505,9 @@ impl RpcSubscriptions {
         let slot_subscriptions = Arc::new(RpcSlotSubscriptions::default());
         let vote_subscriptions = Arc::new(RpcVoteSubscriptions::default());
         let root_subscriptions = Arc::new(RpcRootSubscriptions::default());
+        let epoch_subscriptions = Arc::new(RpcEpochSubscriptions::default());
+        let rewards_subscriptions = Arc::new(RpcRewardsSubscriptions::default());
+        let resumable_subscriptions = Arc::new(ResumableSubscriptions::default());
         let notification_sender = Arc::new(Mutex::new(notification_sender));
 
         let _bank_forks = bank_forks.clone();
@@ -490,6 +525,9 @@ impl RpcSubscriptions {
             slot_subscriptions,
             vote_subscriptions,
             root_subscriptions,
+            epoch_subscriptions,
+            rewards_subscriptions,
+            resumable_subscriptions: resumable_subscriptions.clone(),
         };
         let _subscriptions = subscriptions.clone();
 
@@ -499,7 +537,10 @@ impl RpcSubscriptions {
             .build()
             .unwrap();
 
-        let notifier = RpcNotifier(notifier_runtime.executor());
+        let notifier = RpcNotifier {
+            executor: notifier_runtime.executor(),
+            resumable_subscriptions,
+        };
         let t_cleanup = Builder::new()
             .name("solana-rpc-notifications".to_string())
             .spawn(move || {
@@ -613,6 +654,9 @@ impl RpcSubscriptions {
             filter_signature_result,
             notifier,
         );
+        for sub_id in &notified_ids {
+            notifier.resumable_subscriptions.finish(sub_id);
+        }
         if let Some(subscription_ids) = subscriptions.get_mut(signature) {
             subscription_ids.retain(|k, _| !notified_ids.contains(k));
             if subscription_ids.is_empty() {
@@ -805,14 +849,17 @@ impl RpcSubscriptions {
     }
 
     fn update_bank_transaction_log_keys(&self) {
+        Self::update_bank_transaction_log_keys_for(&self.subscriptions, &self.bank_forks);
+    }
+
+    fn update_bank_transaction_log_keys_for(
+        subscriptions: &Subscriptions,
+        bank_forks: &Arc<RwLock<BankForks>>,
+    ) {
         // Grab a write lock for both `logs_subscriptions` and `gossip_logs_subscriptions`, to
         // ensure `Bank::transaction_log_collector_config` is updated atomically.
-        let logs_subscriptions = self.subscriptions.logs_subscriptions.write().unwrap();
-        let gossip_logs_subscriptions = self
-            .subscriptions
-            .gossip_logs_subscriptions
-            .write()
-            .unwrap();
+        let logs_subscriptions = subscriptions.logs_subscriptions.write().unwrap();
+        let gossip_logs_subscriptions = subscriptions.gossip_logs_subscriptions.write().unwrap();
 
         let mut config = TransactionLogCollectorConfig::default();
 
@@ -847,8 +894,7 @@ impl RpcSubscriptions {
             TransactionLogCollectorFilter::None
         };
 
-        *self
-            .bank_forks
+        *bank_forks
             .read()
             .unwrap()
             .root_bank()
@@ -857,6 +903,144 @@ impl RpcSubscriptions {
             .unwrap() = config;
     }
 
+    /// Keeps subscription `sub_id` alive for a while after its connection drops, so it can be
+    /// resumed from another connection
+    pub fn register_resumable_subscription(
+        &self,
+        sub_id: &SubscriptionId,
+        notification: &'static str,
+    ) {
+        self.subscriptions
+            .resumable_subscriptions
+            .register(sub_id.clone(), notification);
+    }
+
+    pub fn is_resumable_subscription(&self, sub_id: &SubscriptionId) -> bool {
+        self.subscriptions.resumable_subscriptions.contains(sub_id)
+    }
+
+    /// Starts buffering the notifications of a resumable subscription whose connection dropped.
+    /// Returns false if the subscription is not resumable and should be removed
+    pub fn detach_subscription(&self, sub_id: &SubscriptionId) -> bool {
+        self.subscriptions.resumable_subscriptions.detach(sub_id)
+    }
+
+    pub fn forget_resumable_subscription(&self, sub_id: &SubscriptionId) {
+        self.subscriptions.resumable_subscriptions.forget(sub_id);
+    }
+
+    /// Routes the notifications of subscription `sub_id` to `transport`, first replaying the
+    /// ones missed since it was detached. Returns the generation of the new route, to detach it
+    /// once `transport` closes, and the number of replayed notifications
+    pub fn resume_subscription(
+        &self,
+        sub_id: &SubscriptionId,
+        transport: TransportSender,
+    ) -> Option<(u64, usize)> {
+        let ResumedSubscription {
+            transport,
+            generation,
+            missed_notifications,
+        } = self
+            .subscriptions
+            .resumable_subscriptions
+            .resume(sub_id, transport)?;
+        let num_missed_notifications = missed_notifications.len();
+        if let Some(runtime) = &self.notifier_runtime {
+            runtime.executor().spawn(
+                transport
+                    .send_all(stream::iter_ok::<_, TransportError>(missed_notifications))
+                    .map(|_| ())
+                    .map_err(|_| ()),
+            );
+        }
+        Some((generation, num_missed_notifications))
+    }
+
+    pub fn detach_resumed_subscription(&self, sub_id: &SubscriptionId, generation: u64) {
+        self.subscriptions
+            .resumable_subscriptions
+            .detach_resumed(sub_id, generation);
+    }
+
+    fn expire_detached_subscriptions(
+        subscriptions: &Subscriptions,
+        bank_forks: &Arc<RwLock<BankForks>>,
+    ) {
+        let expired = subscriptions.resumable_subscriptions.expire();
+        if expired.is_empty() {
+            return;
+        }
+        debug!("expiring {} detached subscriptions", expired.len());
+        let mut removed_logs_subscription = false;
+        for sub_id in &expired {
+            // The subscription is in one of these, according to its kind and commitment
+            remove_subscription(
+                &mut subscriptions.account_subscriptions.write().unwrap(),
+                sub_id,
+            );
+            remove_subscription(
+                &mut subscriptions.program_subscriptions.write().unwrap(),
+                sub_id,
+            );
+            remove_subscription(
+                &mut subscriptions.signature_subscriptions.write().unwrap(),
+                sub_id,
+            );
+            remove_subscription(
+                &mut subscriptions.gossip_account_subscriptions.write().unwrap(),
+                sub_id,
+            );
+            remove_subscription(
+                &mut subscriptions.gossip_program_subscriptions.write().unwrap(),
+                sub_id,
+            );
+            remove_subscription(
+                &mut subscriptions
+                    .gossip_signature_subscriptions
+                    .write()
+                    .unwrap(),
+                sub_id,
+            );
+            removed_logs_subscription |= remove_subscription(
+                &mut subscriptions.logs_subscriptions.write().unwrap(),
+                sub_id,
+            );
+            removed_logs_subscription |= remove_subscription(
+                &mut subscriptions.gossip_logs_subscriptions.write().unwrap(),
+                sub_id,
+            );
+            subscriptions
+                .slot_subscriptions
+                .write()
+                .unwrap()
+                .remove(sub_id);
+            subscriptions
+                .vote_subscriptions
+                .write()
+                .unwrap()
+                .remove(sub_id);
+            subscriptions
+                .root_subscriptions
+                .write()
+                .unwrap()
+                .remove(sub_id);
+            subscriptions
+                .epoch_subscriptions
+                .write()
+                .unwrap()
+                .remove(sub_id);
+            subscriptions
+                .rewards_subscriptions
+                .write()
+                .unwrap()
+                .remove(sub_id);
+        }
+        if removed_logs_subscription {
+            Self::update_bank_transaction_log_keys_for(subscriptions, bank_forks);
+        }
+    }
+
     pub fn add_signature_subscription(
         &self,
         signature: Signature,
@@ -941,6 +1125,7 @@ impl RpcSubscriptions {
             let mut subscriptions = self.subscriptions.vote_subscriptions.write().unwrap();
             subscriptions.insert(sub_id, sink);
         } else {
+            self.forget_resumable_subscription(&sub_id);
             let _ = subscriber.reject(jsonrpc_core::Error::new(
                 jsonrpc_core::ErrorCode::MethodNotFound,
             ));
@@ -974,6 +1159,46 @@ impl RpcSubscriptions {
         });
     }
 
+    pub fn add_epoch_subscription(
+        &self,
+        sub_id: SubscriptionId,
+        subscriber: Subscriber<EpochBoundaryInfo>,
+    ) {
+        let sink = subscriber.assign_id(sub_id.clone()).unwrap();
+        let mut subscriptions = self.subscriptions.epoch_subscriptions.write().unwrap();
+        subscriptions.insert(sub_id, sink);
+    }
+
+    pub fn remove_epoch_subscription(&self, id: &SubscriptionId) -> bool {
+        let mut subscriptions = self.subscriptions.epoch_subscriptions.write().unwrap();
+        subscriptions.remove(id).is_some()
+    }
+
+    pub fn notify_epoch_boundary(&self, epoch_boundary: EpochBoundaryInfo) {
+        self.enqueue_notification(NotificationEntry::EpochBoundary(epoch_boundary));
+    }
+
+    pub fn add_rewards_subscription(
+        &self,
+        addresses: Option<HashSet<String>>,
+        sub_id: SubscriptionId,
+        subscriber: Subscriber<RpcBlockRewards>,
+    ) {
+        let sink = subscriber.assign_id(sub_id.clone()).unwrap();
+        let mut subscriptions = self.subscriptions.rewards_subscriptions.write().unwrap();
+        subscriptions.insert(sub_id, (sink, addresses));
+    }
+
+    pub fn remove_rewards_subscription(&self, id: &SubscriptionId) -> bool {
+        let mut subscriptions = self.subscriptions.rewards_subscriptions.write().unwrap();
+        subscriptions.remove(id).is_some()
+    }
+
+    /// Notify rewards subscribers of the rewards of the first block of an epoch, once rooted
+    pub fn notify_epoch_rewards(&self, block_rewards: RpcBlockRewards) {
+        self.enqueue_notification(NotificationEntry::Rewards(block_rewards));
+    }
+
     fn enqueue_notification(&self, notification_entry: NotificationEntry) {
         match self
             .notification_sender
@@ -998,10 +1223,15 @@ impl RpcSubscriptions {
         subscriptions: Subscriptions,
         bank_forks: Arc<RwLock<BankForks>>,
     ) {
+        let mut last_expire = Instant::now();
         loop {
             if exit.load(Ordering::Relaxed) {
                 break;
             }
+            if last_expire.elapsed() > EXPIRE_DETACHED_SUBSCRIPTIONS_INTERVAL {
+                Self::expire_detached_subscriptions(&subscriptions, &bank_forks);
+                last_expire = Instant::now();
+            }
             match notification_receiver.recv_timeout(Duration::from_millis(RECEIVE_DELAY_MILLIS)) {
                 Ok(notification_entry) => match notification_entry {
                     NotificationEntry::Slot(slot_info) => {
@@ -1013,9 +1243,9 @@ impl RpcSubscriptions {
                                 slot_info, num_subscriptions
                             );
                         }
-                        for (_, sink) in subscriptions.iter() {
+                        for (sub_id, sink) in subscriptions.iter() {
                             inc_new_counter_info!("rpc-subscription-notify-slot", 1);
-                            notifier.notify(slot_info, sink);
+                            notifier.notify(slot_info, sink, sub_id);
                         }
                     }
                     // These notifications are only triggered by votes observed on gossip,
@@ -1030,7 +1260,7 @@ impl RpcSubscriptions {
                                 vote_info, num_subscriptions
                             );
                         }
-                        for (_, sink) in subscriptions.iter() {
+                        for (sub_id, sink) in subscriptions.iter() {
                             inc_new_counter_info!("rpc-subscription-notify-vote", 1);
                             notifier.notify(
                                 RpcVote {
@@ -1039,6 +1269,7 @@ impl RpcSubscriptions {
                                     timestamp: vote_info.timestamp,
                                 },
                                 sink,
+                                sub_id,
                             );
                         }
                     }
@@ -1051,9 +1282,44 @@ impl RpcSubscriptions {
                                 root, num_subscriptions
                             );
                         }
-                        for (_, sink) in subscriptions.iter() {
+                        for (sub_id, sink) in subscriptions.iter() {
                             inc_new_counter_info!("rpc-subscription-notify-root", 1);
-                            notifier.notify(root, sink);
+                            notifier.notify(root, sink, sub_id);
+                        }
+                    }
+                    NotificationEntry::EpochBoundary(epoch_boundary) => {
+                        let subscriptions = subscriptions.epoch_subscriptions.read().unwrap();
+                        let num_subscriptions = subscriptions.len();
+                        if num_subscriptions > 0 {
+                            debug!(
+                                "epoch boundary notify: {:?}, num_subscriptions: {:?}",
+                                epoch_boundary.epoch, num_subscriptions
+                            );
+                        }
+                        for (sub_id, sink) in subscriptions.iter() {
+                            inc_new_counter_info!("rpc-subscription-notify-epoch", 1);
+                            notifier.notify(epoch_boundary.clone(), sink, sub_id);
+                        }
+                    }
+                    NotificationEntry::Rewards(block_rewards) => {
+                        let subscriptions = subscriptions.rewards_subscriptions.read().unwrap();
+                        let num_subscriptions = subscriptions.len();
+                        if num_subscriptions > 0 {
+                            debug!(
+                                "rewards notify: {:?}, num_subscriptions: {:?}",
+                                block_rewards.slot, num_subscriptions
+                            );
+                        }
+                        for (sub_id, (sink, addresses)) in subscriptions.iter() {
+                            inc_new_counter_info!("rpc-subscription-notify-rewards", 1);
+                            let filtered_rewards = RpcBlockRewards {
+                                rewards: filter_block_rewards(
+                                    block_rewards.rewards.clone(),
+                                    addresses.as_ref(),
+                                ),
+                                ..block_rewards
+                            };
+                            notifier.notify(filtered_rewards, sink, sub_id);
                         }
                     }
                     NotificationEntry::Bank(commitment_slots) => {
@@ -1242,7 +1508,7 @@ impl RpcSubscriptions {
         for signature in signatures {
             if let Some(hashmap) = signature_subscriptions.read().unwrap().get(signature) {
                 for (
-                    _,
+                    sub_id,
                     SubscriptionData {
                         sink,
                         config: is_received_notification_enabled,
@@ -1261,6 +1527,7 @@ impl RpcSubscriptions {
                                 ),
                             },
                             &sink,
+                            sub_id,
                         );
                     }
                 }
@@ -1307,6 +1574,7 @@ pub(crate) mod tests {
         system_instruction, system_program, system_transaction,
         transaction::Transaction,
     };
+    use solana_transaction_status::{Reward, RewardType};
     use std::{fmt::Debug, sync::mpsc::channel, time::Instant};
     use tokio_01::{prelude::FutureExt, runtime::Runtime, timer::Delay};
 
@@ -1882,6 +2150,102 @@ pub(crate) mod tests {
             .contains_key(&sub_id));
     }
 
+    #[test]
+    #[serial]
+    fn test_check_epoch_subscribe() {
+        let (subscriber, _id_receiver, transport_receiver) =
+            Subscriber::new_test("epochNotification");
+        let sub_id = SubscriptionId::Number(0);
+        let exit = Arc::new(AtomicBool::new(false));
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+        let bank = Bank::new(&genesis_config);
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let optimistically_confirmed_bank =
+            OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks);
+        let subscriptions = RpcSubscriptions::new(
+            &exit,
+            bank_forks,
+            Arc::new(RwLock::new(BlockCommitmentCache::new_for_tests())),
+            optimistically_confirmed_bank,
+        );
+        subscriptions.add_epoch_subscription(sub_id.clone(), subscriber);
+
+        let epoch_boundary = EpochBoundaryInfo {
+            epoch: 1,
+            root: 32,
+            prior_root: 30,
+            total_active_stake: 10,
+            prior_total_active_stake: 0,
+            stake_changes: vec![],
+            activated_features: vec![],
+        };
+        subscriptions.notify_epoch_boundary(epoch_boundary.clone());
+        let (response, _) = robust_poll_or_panic(transport_receiver);
+        let expected_res_str =
+            serde_json::to_string(&serde_json::to_value(epoch_boundary).unwrap()).unwrap();
+        let expected = format!(
+            r#"{{"jsonrpc":"2.0","method":"epochNotification","params":{{"result":{},"subscription":0}}}}"#,
+            expected_res_str
+        );
+        assert_eq!(expected, response);
+
+        assert!(subscriptions.remove_epoch_subscription(&sub_id));
+        assert!(!subscriptions.remove_epoch_subscription(&sub_id));
+    }
+
+    #[test]
+    #[serial]
+    fn test_check_rewards_subscribe() {
+        let (subscriber, _id_receiver, transport_receiver) =
+            Subscriber::new_test("rewardsNotification");
+        let sub_id = SubscriptionId::Number(0);
+        let exit = Arc::new(AtomicBool::new(false));
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+        let bank = Bank::new(&genesis_config);
+        let bank_forks = Arc::new(RwLock::new(BankForks::new(bank)));
+        let optimistically_confirmed_bank =
+            OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks);
+        let subscriptions = RpcSubscriptions::new(
+            &exit,
+            bank_forks,
+            Arc::new(RwLock::new(BlockCommitmentCache::new_for_tests())),
+            optimistically_confirmed_bank,
+        );
+        let staker = Pubkey::new_unique();
+        let addresses: HashSet<_> = vec![staker.to_string()].into_iter().collect();
+        subscriptions.add_rewards_subscription(Some(addresses), sub_id.clone(), subscriber);
+
+        let rewards: Vec<_> = vec![Pubkey::new_unique(), staker]
+            .into_iter()
+            .map(|pubkey| Reward {
+                pubkey: pubkey.to_string(),
+                lamports: 42,
+                post_balance: 1_042,
+                reward_type: Some(RewardType::Staking),
+            })
+            .collect();
+        subscriptions.notify_epoch_rewards(RpcBlockRewards {
+            slot: 32,
+            epoch: 1,
+            rewards: rewards.clone(),
+        });
+        let (response, _) = robust_poll_or_panic(transport_receiver);
+        // Only the subscribed address's reward is sent
+        let expected_res = RpcBlockRewards {
+            slot: 32,
+            epoch: 1,
+            rewards: rewards[1..].to_vec(),
+        };
+        let expected = format!(
+            r#"{{"jsonrpc":"2.0","method":"rewardsNotification","params":{{"result":{},"subscription":0}}}}"#,
+            serde_json::to_string(&expected_res).unwrap()
+        );
+        assert_eq!(expected, response);
+
+        assert!(subscriptions.remove_rewards_subscription(&sub_id));
+        assert!(!subscriptions.remove_rewards_subscription(&sub_id));
+    }
+
     #[test]
     #[serial]
     fn test_add_and_remove_subscription() {
@@ -1,6 +1,9 @@
+use crate::rpc::PERFORMANCE_SAMPLES_LIMIT;
 use solana_ledger::{blockstore::Blockstore, blockstore_meta::PerfSample};
 use solana_runtime::bank_forks::BankForks;
+use solana_sdk::clock::Slot;
 use std::{
+    collections::VecDeque,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc, RwLock,
@@ -17,6 +20,41 @@ pub struct SamplePerformanceSnapshot {
     pub num_slots: u64,
 }
 
+/// Ring buffer of the most recent performance samples, keyed by the highest slot at the time of
+/// the sample, for the `getRecentPerformanceSamples` RPC method
+#[derive(Debug, Default)]
+pub struct RecentPerfSamples {
+    samples: VecDeque<(Slot, PerfSample)>,
+}
+
+impl RecentPerfSamples {
+    /// Seeds the buffer with the samples persisted by a previous run, if any
+    pub fn new_from_blockstore(blockstore: &Blockstore) -> Self {
+        let mut recent_perf_samples = Self::default();
+        match blockstore.get_recent_perf_samples(PERFORMANCE_SAMPLES_LIMIT) {
+            Ok(samples) => {
+                for (slot, sample) in samples.into_iter().rev() {
+                    recent_perf_samples.push(slot, sample);
+                }
+            }
+            Err(err) => warn!("get_recent_perf_samples failed: {:?}", err),
+        }
+        recent_perf_samples
+    }
+
+    pub fn push(&mut self, slot: Slot, sample: PerfSample) {
+        if self.samples.len() >= PERFORMANCE_SAMPLES_LIMIT {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((slot, sample));
+    }
+
+    /// Returns up to `limit` samples, most recent first
+    pub fn recent(&self, limit: usize) -> Vec<(Slot, PerfSample)> {
+        self.samples.iter().rev().take(limit).cloned().collect()
+    }
+}
+
 pub struct SamplePerformanceService {
     thread_hdl: JoinHandle<()>,
 }
@@ -25,18 +63,20 @@ impl SamplePerformanceService {
     #[allow(clippy::new_ret_no_self)]
     pub fn new(
         bank_forks: &Arc<RwLock<BankForks>>,
-        blockstore: &Arc<Blockstore>,
+        blockstore: Option<&Arc<Blockstore>>,
+        recent_perf_samples: &Arc<RwLock<RecentPerfSamples>>,
         exit: &Arc<AtomicBool>,
     ) -> Self {
         let exit = exit.clone();
-        let blockstore = blockstore.clone();
+        let blockstore = blockstore.cloned();
+        let recent_perf_samples = recent_perf_samples.clone();
         let bank_forks = bank_forks.clone();
 
         info!("Starting SamplePerformance service");
         let thread_hdl = Builder::new()
             .name("sample-performance".to_string())
             .spawn(move || {
-                Self::run(bank_forks, &blockstore, exit);
+                Self::run(bank_forks, blockstore.as_ref(), &recent_perf_samples, exit);
             })
             .unwrap();
 
@@ -45,7 +85,8 @@ impl SamplePerformanceService {
 
     pub fn run(
         bank_forks: Arc<RwLock<BankForks>>,
-        blockstore: &Arc<Blockstore>,
+        blockstore: Option<&Arc<Blockstore>>,
+        recent_perf_samples: &RwLock<RecentPerfSamples>,
         exit: Arc<AtomicBool>,
     ) {
         let forks = bank_forks.read().unwrap();
@@ -84,9 +125,15 @@ impl SamplePerformanceService {
                     sample_period_secs: elapsed.as_secs() as u16,
                 };
 
-                if let Err(e) = blockstore.write_perf_sample(highest_slot, &perf_sample) {
-                    error!("write_perf_sample failed: slot {:?} {:?}", highest_slot, e);
+                if let Some(blockstore) = blockstore {
+                    if let Err(e) = blockstore.write_perf_sample(highest_slot, &perf_sample) {
+                        error!("write_perf_sample failed: slot {:?} {:?}", highest_slot, e);
+                    }
                 }
+                recent_perf_samples
+                    .write()
+                    .unwrap()
+                    .push(highest_slot, perf_sample);
 
                 sample_snapshot = SamplePerformanceSnapshot {
                     num_transactions: bank.transaction_count(),
@@ -102,3 +149,36 @@ impl SamplePerformanceService {
         self.thread_hdl.join()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_perf_samples() {
+        let sample = |num_slots| PerfSample {
+            num_transactions: 1000,
+            num_slots,
+            sample_period_secs: 60,
+        };
+        let mut recent_perf_samples = RecentPerfSamples::default();
+        assert!(recent_perf_samples.recent(10).is_empty());
+
+        for slot in 0..PERFORMANCE_SAMPLES_LIMIT as Slot + 10 {
+            recent_perf_samples.push(slot, sample(slot));
+        }
+        let recent = recent_perf_samples.recent(3);
+        let last_slot = PERFORMANCE_SAMPLES_LIMIT as Slot + 9;
+        assert_eq!(
+            recent,
+            vec![
+                (last_slot, sample(last_slot)),
+                (last_slot - 1, sample(last_slot - 1)),
+                (last_slot - 2, sample(last_slot - 2)),
+            ]
+        );
+        let all = recent_perf_samples.recent(usize::MAX);
+        assert_eq!(all.len(), PERFORMANCE_SAMPLES_LIMIT);
+        assert_eq!(all.last().unwrap().0, 10);
+    }
+}
@@ -323,6 +323,7 @@ impl ServeRepair {
     ) {
         // iter over the packets
         let allocated = thread_mem_usage::Allocatedp::default();
+        let shred_cipher = me.read().unwrap().cluster_info.shred_cipher();
         packets.packets.iter().for_each(|packet| {
             let start = allocated.get();
             let from_addr = packet.meta.addr();
@@ -332,7 +333,12 @@ impl ServeRepair {
                     stats.processed += 1;
                     let rsp =
                         Self::handle_repair(me, recycler, &from_addr, blockstore, request, stats);
-                    if let Some(rsp) = rsp {
+                    if let Some(mut rsp) = rsp {
+                        if let Some(shred_cipher) = &shred_cipher {
+                            for packet in rsp.packets.iter_mut() {
+                                shred_cipher.apply(&mut packet.data[..packet.meta.size]);
+                            }
+                        }
                         let _ignore_disconnect = response_sender.send(rsp);
                     }
                 });
@@ -1,14 +1,17 @@
 //! The `shred_fetch_stage` pulls shreds from UDP sockets and sends it to a channel.
 
-use crate::packet_hasher::PacketHasher;
+use crate::{packet_channel::BoundedSender, packet_hasher::PacketHasher};
 use lru::LruCache;
-use solana_ledger::shred::{get_shred_slot_index_type, ShredFetchStats};
+use solana_ledger::{
+    shred::{get_shred_slot_index_type, ShredFetchStats},
+    shred_encryption::ShredCipher,
+};
 use solana_perf::cuda_runtime::PinnedVec;
-use solana_perf::packet::{Packet, PacketsRecycler};
+use solana_perf::packet::{Packet, Packets, PacketsRecycler};
 use solana_perf::recycler::Recycler;
 use solana_runtime::bank_forks::BankForks;
 use solana_sdk::clock::{Slot, DEFAULT_MS_PER_SLOT};
-use solana_streamer::streamer::{self, PacketReceiver, PacketSender};
+use solana_streamer::streamer::{self, PacketReceiver};
 use std::net::UdpSocket;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc::channel;
@@ -58,10 +61,11 @@ impl ShredFetchStage {
         }
     }
 
-    // updates packets received on a channel and sends them on another channel
+    // updates packets received on a channel and sends them on another channel, which sheds
+    // them according to its drop policy once full
     fn modify_packets<F>(
         recvr: PacketReceiver,
-        sendr: PacketSender,
+        sendr: BoundedSender<Packets>,
         bank_forks: Option<Arc<RwLock<BankForks>>>,
         name: &'static str,
         modify: F,
@@ -130,7 +134,7 @@ impl ShredFetchStage {
     fn packet_modifier<F>(
         sockets: Vec<Arc<UdpSocket>>,
         exit: &Arc<AtomicBool>,
-        sender: PacketSender,
+        sender: BoundedSender<Packets>,
         recycler: Recycler<PinnedVec<Packet>>,
         bank_forks: Option<Arc<RwLock<BankForks>>>,
         name: &'static str,
@@ -164,11 +168,18 @@ impl ShredFetchStage {
         sockets: Vec<Arc<UdpSocket>>,
         forward_sockets: Vec<Arc<UdpSocket>>,
         repair_socket: Arc<UdpSocket>,
-        sender: &PacketSender,
+        sender: &BoundedSender<Packets>,
         bank_forks: Option<Arc<RwLock<BankForks>>>,
+        shred_cipher: Option<Arc<ShredCipher>>,
         exit: &Arc<AtomicBool>,
     ) -> Self {
         let recycler: PacketsRecycler = Recycler::warmed(100, 1024);
+        // Shreds are decrypted once deduplicated, before anything reads past their common header
+        let decrypt = move |p: &mut Packet| {
+            if let Some(shred_cipher) = &shred_cipher {
+                shred_cipher.apply(&mut p.data[..p.meta.size]);
+            }
+        };
 
         let (mut tvu_threads, tvu_filter) = Self::packet_modifier(
             sockets,
@@ -177,7 +188,10 @@ impl ShredFetchStage {
             recycler.clone(),
             bank_forks.clone(),
             "shred_fetch",
-            |_| {},
+            {
+                let decrypt = decrypt.clone();
+                move |p| decrypt(p)
+            },
         );
 
         let (tvu_forwards_threads, fwd_thread_hdl) = Self::packet_modifier(
@@ -187,7 +201,13 @@ impl ShredFetchStage {
             recycler.clone(),
             bank_forks.clone(),
             "shred_fetch_tvu_forwards",
-            |p| p.meta.forward = true,
+            {
+                let decrypt = decrypt.clone();
+                move |p| {
+                    decrypt(p);
+                    p.meta.forward = true;
+                }
+            },
         );
 
         let (repair_receiver, repair_handler) = Self::packet_modifier(
@@ -197,7 +217,10 @@ impl ShredFetchStage {
             recycler,
             bank_forks,
             "shred_fetch_repair",
-            |p| p.meta.repair = true,
+            move |p| {
+                decrypt(p);
+                p.meta.repair = true;
+            },
         );
 
         tvu_threads.extend(tvu_forwards_threads.into_iter());
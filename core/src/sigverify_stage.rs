@@ -4,18 +4,21 @@
 //! signature in that packet is valid. It assumes each packet contains one
 //! transaction. All processing is done on the CPU by default and on a GPU
 //! if perf-libs are available
+//!
+//! Incoming packets arrive on a bounded channel, so that a backlog is shed according to the
+//! channel's drop policy instead of growing without bound.
 
+use crate::packet_channel::BoundedSender;
 use crate::sigverify;
-use crossbeam_channel::{SendError, Sender as CrossbeamSender};
+use crossbeam_channel::{Receiver as CrossbeamReceiver, RecvTimeoutError, SendError};
 use solana_measure::measure::Measure;
 use solana_metrics::datapoint_debug;
 use solana_perf::packet::Packets;
 use solana_perf::perf_libs;
-use solana_sdk::timing;
-use solana_streamer::streamer::{self, PacketReceiver, StreamerError};
-use std::sync::mpsc::{Receiver, RecvTimeoutError};
-use std::sync::{Arc, Mutex};
+use solana_rayon_threadlimit::thread_manager;
+use solana_sdk::timing::{self, duration_as_ms};
 use std::thread::{self, Builder, JoinHandle};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 const RECV_BATCH_MAX_CPU: usize = 1_000;
@@ -26,8 +29,8 @@ pub enum SigVerifyServiceError {
     #[error("send packets batch error")]
     SendError(#[from] SendError<Vec<Packets>>),
 
-    #[error("streamer error")]
-    StreamerError(#[from] StreamerError),
+    #[error("receive timeout error")]
+    RecvTimeoutError(#[from] RecvTimeoutError),
 }
 
 type Result<T> = std::result::Result<T, SigVerifyServiceError>;
@@ -52,24 +55,43 @@ impl SigVerifier for DisabledSigVerifier {
 }
 
 impl SigVerifyStage {
+    /// `packet_receiver` is the bounded channel the fetch stage fills, see `packet_channel`.
     #[allow(clippy::new_ret_no_self)]
     pub fn new<T: SigVerifier + 'static + Send + Clone>(
-        packet_receiver: Receiver<Packets>,
-        verified_sender: CrossbeamSender<Vec<Packets>>,
+        packet_receiver: CrossbeamReceiver<Packets>,
+        verified_sender: BoundedSender<Vec<Packets>>,
         verifier: T,
     ) -> Self {
         let thread_hdls = Self::verifier_services(packet_receiver, verified_sender, verifier);
         Self { thread_hdls }
     }
 
+    fn recv_batch(
+        recvr: &CrossbeamReceiver<Packets>,
+        max_batch: usize,
+    ) -> Result<(Vec<Packets>, usize, u64)> {
+        let msgs = recvr.recv_timeout(Duration::new(1, 0))?;
+        let recv_start = Instant::now();
+        let mut len = msgs.packets.len();
+        let mut batch = vec![msgs];
+        while let Ok(more) = recvr.try_recv() {
+            len += more.packets.len();
+            batch.push(more);
+            if len > max_batch {
+                break;
+            }
+        }
+        Ok((batch, len, duration_as_ms(&recv_start.elapsed())))
+    }
+
     fn verifier<T: SigVerifier>(
-        recvr: &Arc<Mutex<PacketReceiver>>,
-        sendr: &CrossbeamSender<Vec<Packets>>,
+        recvr: &CrossbeamReceiver<Packets>,
+        sendr: &BoundedSender<Vec<Packets>>,
         id: usize,
         verifier: &T,
     ) -> Result<()> {
-        let (batch, len, recv_time) = streamer::recv_batch(
-            &recvr.lock().expect("'recvr' lock in fn verifier"),
+        let (batch, len, recv_time) = Self::recv_batch(
+            recvr,
             if perf_libs::api().is_some() {
                 RECV_BATCH_MAX_GPU
             } else {
@@ -116,27 +138,27 @@ impl SigVerifyStage {
     }
 
     fn verifier_service<T: SigVerifier + 'static + Send + Clone>(
-        packet_receiver: Arc<Mutex<PacketReceiver>>,
-        verified_sender: CrossbeamSender<Vec<Packets>>,
+        packet_receiver: CrossbeamReceiver<Packets>,
+        verified_sender: BoundedSender<Vec<Packets>>,
         id: usize,
         verifier: &T,
     ) -> JoinHandle<()> {
         let verifier = verifier.clone();
         Builder::new()
             .name(format!("solana-verifier-{}", id))
-            .spawn(move || loop {
-                if let Err(e) = Self::verifier(&packet_receiver, &verified_sender, id, &verifier) {
-                    match e {
-                        SigVerifyServiceError::StreamerError(StreamerError::RecvTimeoutError(
-                            RecvTimeoutError::Disconnected,
-                        )) => break,
-                        SigVerifyServiceError::StreamerError(StreamerError::RecvTimeoutError(
-                            RecvTimeoutError::Timeout,
-                        )) => (),
-                        SigVerifyServiceError::SendError(_) => {
-                            break;
+            .spawn(move || {
+                thread_manager::pin_current_thread("solana-sigverify", id);
+                loop {
+                    if let Err(e) =
+                        Self::verifier(&packet_receiver, &verified_sender, id, &verifier)
+                    {
+                        match e {
+                            SigVerifyServiceError::RecvTimeoutError(
+                                RecvTimeoutError::Disconnected,
+                            ) => break,
+                            SigVerifyServiceError::RecvTimeoutError(RecvTimeoutError::Timeout) => {}
+                            SigVerifyServiceError::SendError(_) => break,
                         }
-                        _ => error!("{:?}", e),
                     }
                 }
             })
@@ -144,14 +166,18 @@ impl SigVerifyStage {
     }
 
     fn verifier_services<T: SigVerifier + 'static + Send + Clone>(
-        packet_receiver: PacketReceiver,
-        verified_sender: CrossbeamSender<Vec<Packets>>,
+        packet_receiver: CrossbeamReceiver<Packets>,
+        verified_sender: BoundedSender<Vec<Packets>>,
         verifier: T,
     ) -> Vec<JoinHandle<()>> {
-        let receiver = Arc::new(Mutex::new(packet_receiver));
         (0..4)
             .map(|id| {
-                Self::verifier_service(receiver.clone(), verified_sender.clone(), id, &verifier)
+                Self::verifier_service(
+                    packet_receiver.clone(),
+                    verified_sender.clone(),
+                    id,
+                    &verifier,
+                )
             })
             .collect()
     }
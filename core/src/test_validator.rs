@@ -6,7 +6,9 @@ use {
         validator::{Validator, ValidatorConfig},
     },
     solana_client::rpc_client::RpcClient,
-    solana_ledger::{blockstore::create_new_ledger, create_new_tmp_ledger},
+    solana_ledger::{
+        blockstore::create_new_ledger, create_new_tmp_ledger, leader_schedule::FixedSchedule,
+    },
     solana_runtime::{
         bank_forks::{ArchiveFormat, SnapshotConfig, SnapshotVersion},
         genesis_utils::create_genesis_config_with_leader_ex,
@@ -49,6 +51,7 @@ pub struct TestValidatorGenesis {
     rpc_config: JsonRpcConfig,
     rpc_ports: Option<(u16, u16)>, // (JsonRpc, JsonRpcPubSub), None == random ports
     warp_slot: Option<Slot>,
+    fixed_leader_schedule: Option<FixedSchedule>,
     accounts: HashMap<Pubkey, Account>,
     programs: Vec<ProgramInfo>,
 }
@@ -84,6 +87,12 @@ impl TestValidatorGenesis {
         self
     }
 
+    /// Follow `fixed_leader_schedule` instead of the stake-weighted leader schedule
+    pub fn fixed_leader_schedule(&mut self, fixed_leader_schedule: FixedSchedule) -> &mut Self {
+        self.fixed_leader_schedule = Some(fixed_leader_schedule);
+        self
+    }
+
     /// Add an account to the test environment
     pub fn add_account(&mut self, address: Pubkey, account: Account) -> &mut Self {
         self.accounts.insert(address, account);
@@ -394,6 +403,7 @@ impl TestValidator {
             }),
             enforce_ulimit_nofile: false,
             warp_slot: config.warp_slot,
+            fixed_leader_schedule: config.fixed_leader_schedule.clone(),
             ..ValidatorConfig::default()
         };
 
@@ -0,0 +1,57 @@
+//! The `thread_pool_metrics_service` periodically reports the size of the named thread pools
+//! registered with `solana_rayon_threadlimit::thread_manager`.
+
+use solana_rayon_threadlimit::thread_manager::thread_pool_stats;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, sleep, Builder, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const REPORT_INTERVAL: Duration = Duration::from_secs(10);
+const SLEEP_INTERVAL: Duration = Duration::from_millis(500);
+
+pub struct ThreadPoolMetricsService {
+    thread_hdl: JoinHandle<()>,
+}
+
+impl ThreadPoolMetricsService {
+    pub fn new(exit: &Arc<AtomicBool>) -> Self {
+        let exit = exit.clone();
+        let thread_hdl = Builder::new()
+            .name("solana-thread-pool-metrics".to_string())
+            .spawn(move || Self::run(exit))
+            .unwrap();
+        Self { thread_hdl }
+    }
+
+    fn run(exit: Arc<AtomicBool>) {
+        let mut last_report: Option<Instant> = None;
+        while !exit.load(Ordering::Relaxed) {
+            if last_report.map_or(true, |last_report| last_report.elapsed() >= REPORT_INTERVAL) {
+                Self::report();
+                last_report = Some(Instant::now());
+            }
+            sleep(SLEEP_INTERVAL);
+        }
+    }
+
+    fn report() {
+        for stats in thread_pool_stats() {
+            datapoint_info!(
+                "thread-pool",
+                ("name", stats.name, String),
+                ("num_threads", stats.num_threads, i64),
+                ("num_pools", stats.num_pools, i64),
+                ("num_live_threads", stats.num_live_threads, i64),
+            );
+        }
+    }
+
+    pub fn join(self) -> thread::Result<()> {
+        self.thread_hdl.join()
+    }
+}
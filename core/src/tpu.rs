@@ -1,5 +1,8 @@
 //! The `tpu` module implements the Transaction Processing Unit, a
 //! multi-stage transaction processing pipeline in software.
+//!
+//! In RPC-only mode the node never produces blocks, so only the vote listener
+//! is started; the fetch, sigverify, banking and broadcast stages are skipped.
 
 use crate::{
     banking_stage::BankingStage,
@@ -8,35 +11,39 @@ use crate::{
     cluster_info_vote_listener::{ClusterInfoVoteListener, VerifiedVoteSender, VoteTracker},
     fetch_stage::FetchStage,
     optimistically_confirmed_bank_tracker::BankNotificationSender,
+    packet_channel::{bounded_channel, PacketChannelsConfig},
     poh_recorder::{PohRecorder, WorkingBankEntry},
     rpc_subscriptions::RpcSubscriptions,
     sigverify::TransactionSigVerifier,
     sigverify_stage::SigVerifyStage,
 };
 use crossbeam_channel::unbounded;
-use solana_ledger::{blockstore::Blockstore, blockstore_processor::TransactionStatusSender};
+use solana_ledger::{
+    blockstore::Blockstore, blockstore_processor::TransactionStatusSender,
+    signing_journal::SigningJournal,
+};
 use solana_runtime::{
     bank_forks::BankForks,
     vote_sender_types::{ReplayVoteReceiver, ReplayVoteSender},
 };
 use std::{
     net::UdpSocket,
-    sync::{
-        atomic::AtomicBool,
-        mpsc::{channel, Receiver},
-        Arc, Mutex, RwLock,
-    },
+    sync::{atomic::AtomicBool, mpsc::Receiver, Arc, Mutex, RwLock},
     thread,
 };
 
-pub struct Tpu {
+struct TransactionPipeline {
     fetch_stage: FetchStage,
     sigverify_stage: SigVerifyStage,
     banking_stage: BankingStage,
-    cluster_info_vote_listener: ClusterInfoVoteListener,
     broadcast_stage: BroadcastStage,
 }
 
+pub struct Tpu {
+    cluster_info_vote_listener: ClusterInfoVoteListener,
+    pipeline: Option<TransactionPipeline>,
+}
+
 impl Tpu {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
@@ -59,22 +66,10 @@ impl Tpu {
         replay_vote_receiver: ReplayVoteReceiver,
         replay_vote_sender: ReplayVoteSender,
         bank_notification_sender: Option<BankNotificationSender>,
+        rpc_only: bool,
+        packet_channels: &PacketChannelsConfig,
+        signing_journal: Option<Arc<SigningJournal>>,
     ) -> Self {
-        let (packet_sender, packet_receiver) = channel();
-        let fetch_stage = FetchStage::new_with_sender(
-            transactions_sockets,
-            tpu_forwards_sockets,
-            &exit,
-            &packet_sender,
-            &poh_recorder,
-        );
-        let (verified_sender, verified_receiver) = unbounded();
-
-        let sigverify_stage = {
-            let verifier = TransactionSigVerifier::default();
-            SigVerifyStage::new(packet_receiver, verified_sender, verifier)
-        };
-
         let (verified_vote_packets_sender, verified_vote_packets_receiver) = unbounded();
         let cluster_info_vote_listener = ClusterInfoVoteListener::new(
             &exit,
@@ -90,6 +85,33 @@ impl Tpu {
             bank_notification_sender,
         );
 
+        if rpc_only {
+            info!("rpc-only mode: skipping fetch, sigverify, banking and broadcast stages");
+            return Self {
+                cluster_info_vote_listener,
+                pipeline: None,
+            };
+        }
+
+        let (packet_sender, packet_receiver) =
+            bounded_channel("tpu-packets-channel", &packet_channels.tpu_packets);
+        let fetch_stage = FetchStage::new_with_sender(
+            transactions_sockets,
+            tpu_forwards_sockets,
+            &exit,
+            &packet_sender,
+            &poh_recorder,
+        );
+        let (verified_sender, verified_receiver) = bounded_channel(
+            "tpu-verified-packets-channel",
+            &packet_channels.tpu_verified,
+        );
+
+        let sigverify_stage = {
+            let verifier = TransactionSigVerifier::default();
+            SigVerifyStage::new(packet_receiver, verified_sender, verifier)
+        };
+
         let banking_stage = BankingStage::new(
             &cluster_info,
             poh_recorder,
@@ -107,29 +129,34 @@ impl Tpu {
             &exit,
             blockstore,
             shred_version,
+            signing_journal,
         );
 
         Self {
-            fetch_stage,
-            sigverify_stage,
-            banking_stage,
             cluster_info_vote_listener,
-            broadcast_stage,
+            pipeline: Some(TransactionPipeline {
+                fetch_stage,
+                sigverify_stage,
+                banking_stage,
+                broadcast_stage,
+            }),
         }
     }
 
     pub fn join(self) -> thread::Result<()> {
-        let results = vec![
-            self.fetch_stage.join(),
-            self.sigverify_stage.join(),
-            self.cluster_info_vote_listener.join(),
-            self.banking_stage.join(),
-        ];
-        let broadcast_result = self.broadcast_stage.join();
-        for result in results {
-            result?;
+        let vote_listener_result = self.cluster_info_vote_listener.join();
+        if let Some(pipeline) = self.pipeline {
+            let results = vec![
+                pipeline.fetch_stage.join(),
+                pipeline.sigverify_stage.join(),
+                pipeline.banking_stage.join(),
+            ];
+            let broadcast_result = pipeline.broadcast_stage.join();
+            for result in results {
+                result?;
+            }
+            let _ = broadcast_result?;
         }
-        let _ = broadcast_result?;
-        Ok(())
+        vote_listener_result
     }
 }
@@ -1,3 +1,4 @@
+use crate::program_stats::ProgramStats;
 use crossbeam_channel::{Receiver, RecvTimeoutError};
 use itertools::izip;
 use solana_ledger::{blockstore::Blockstore, blockstore_processor::TransactionStatusBatch};
@@ -5,11 +6,13 @@ use solana_runtime::{
     bank::{Bank, NonceRollbackInfo},
     transaction_utils::OrderedIterator,
 };
-use solana_transaction_status::{InnerInstructions, TransactionStatusMeta};
+use solana_transaction_status::{
+    extract_memos::extract_and_fmt_memos, map_inner_instructions, TransactionStatusMeta,
+};
 use std::{
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, RwLock,
     },
     thread::{self, Builder, JoinHandle},
     time::Duration,
@@ -24,6 +27,7 @@ impl TransactionStatusService {
     pub fn new(
         write_transaction_status_receiver: Receiver<TransactionStatusBatch>,
         blockstore: Arc<Blockstore>,
+        program_stats: Option<Arc<RwLock<ProgramStats>>>,
         exit: &Arc<AtomicBool>,
     ) -> Self {
         let exit = exit.clone();
@@ -36,6 +40,7 @@ impl TransactionStatusService {
                 if let Err(RecvTimeoutError::Disconnected) = Self::write_transaction_status_batch(
                     &write_transaction_status_receiver,
                     &blockstore,
+                    program_stats.as_deref(),
                 ) {
                     break;
                 }
@@ -47,6 +52,7 @@ impl TransactionStatusService {
     fn write_transaction_status_batch(
         write_transaction_status_receiver: &Receiver<TransactionStatusBatch>,
         blockstore: &Arc<Blockstore>,
+        program_stats: Option<&RwLock<ProgramStats>>,
     ) -> Result<(), RecvTimeoutError> {
         let TransactionStatusBatch {
             bank,
@@ -60,6 +66,7 @@ impl TransactionStatusService {
         } = write_transaction_status_receiver.recv_timeout(Duration::from_secs(1))?;
 
         let slot = bank.slot();
+        let mut program_stats = program_stats.map(|program_stats| program_stats.write().unwrap());
         for (
             (_, transaction),
             (status, nonce_rollback),
@@ -80,6 +87,15 @@ impl TransactionStatusService {
             transaction_logs
         ) {
             if Bank::can_commit(&status) && !transaction.signatures.is_empty() {
+                if let Some(program_stats) = program_stats.as_mut() {
+                    program_stats.record_transaction(
+                        slot,
+                        transaction.message(),
+                        &status,
+                        &log_messages,
+                    );
+                }
+
                 let fee_calculator = nonce_rollback
                     .map(|nonce_rollback| nonce_rollback.fee_calculator())
                     .unwrap_or_else(|| {
@@ -90,17 +106,14 @@ impl TransactionStatusService {
                 let (writable_keys, readonly_keys) =
                     transaction.message.get_account_keys_by_lock_type();
 
-                let inner_instructions = inner_instructions.map(|inner_instructions| {
-                    inner_instructions
-                        .into_iter()
-                        .enumerate()
-                        .map(|(index, instructions)| InnerInstructions {
-                            index: index as u8,
-                            instructions,
-                        })
-                        .filter(|i| !i.instructions.is_empty())
-                        .collect()
-                });
+                let inner_instructions = inner_instructions
+                    .map(|inner_instructions| map_inner_instructions(inner_instructions).collect());
+
+                if let Some(memos) = extract_and_fmt_memos(transaction.message()) {
+                    blockstore
+                        .write_transaction_memos(slot, &transaction.signatures[0], memos)
+                        .expect("Expect database write to succeed: TransactionMemos");
+                }
 
                 let log_messages = Some(log_messages);
                 let pre_token_balances = Some(pre_token_balances);
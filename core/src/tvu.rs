@@ -12,8 +12,11 @@ use crate::{
     consensus::Tower,
     ledger_cleanup_service::LedgerCleanupService,
     optimistically_confirmed_bank_tracker::BankNotificationSender,
+    packet_channel::{bounded_channel, PacketChannelsConfig},
     poh_recorder::PohRecorder,
+    repair_status::RepairStatus,
     replay_stage::{ReplayStage, ReplayStageConfig},
+    replica_sync_service::ReplicaSyncSender,
     retransmit_stage::RetransmitStage,
     rewards_recorder_service::RewardsRecorderSender,
     rpc_subscriptions::RpcSubscriptions,
@@ -27,6 +30,7 @@ use solana_ledger::{
     blockstore::{Blockstore, CompletedSlotsReceiver},
     blockstore_processor::TransactionStatusSender,
     leader_schedule_cache::LeaderScheduleCache,
+    signing_journal::SigningJournal,
 };
 use solana_runtime::{
     accounts_background_service::{
@@ -79,6 +83,13 @@ pub struct TvuConfig {
     pub repair_validators: Option<HashSet<Pubkey>>,
     pub accounts_hash_fault_injection_slots: u64,
     pub accounts_db_caching_enabled: bool,
+    pub rpc_only: bool,
+    pub packet_channels: PacketChannelsConfig,
+    pub block_production_paused: Option<Arc<AtomicBool>>,
+    pub identity_conflict: Option<Arc<AtomicBool>>,
+    pub retransmit_egress_budget: Option<u64>,
+    pub poh_verify_gpu: bool,
+    pub signing_journal: Option<Arc<SigningJournal>>,
 }
 
 impl Tvu {
@@ -115,6 +126,8 @@ impl Tvu {
         replay_vote_sender: ReplayVoteSender,
         completed_data_sets_sender: CompletedDataSetsSender,
         bank_notification_sender: Option<BankNotificationSender>,
+        replica_sync_sender: Option<ReplicaSyncSender>,
+        repair_status: Arc<RwLock<RepairStatus>>,
         tvu_config: TvuConfig,
     ) -> Self {
         let keypair: Arc<Keypair> = cluster_info.keypair.clone();
@@ -126,7 +139,10 @@ impl Tvu {
             forwards: tvu_forward_sockets,
         } = sockets;
 
-        let (fetch_sender, fetch_receiver) = channel();
+        let (fetch_sender, fetch_receiver) = bounded_channel(
+            "tvu-packets-channel",
+            &tvu_config.packet_channels.tvu_packets,
+        );
 
         let repair_socket = Arc::new(repair_socket);
         let fetch_sockets: Vec<Arc<UdpSocket>> = fetch_sockets.into_iter().map(Arc::new).collect();
@@ -138,10 +154,14 @@ impl Tvu {
             repair_socket.clone(),
             &fetch_sender,
             Some(bank_forks.clone()),
+            cluster_info.shred_cipher(),
             &exit,
         );
 
-        let (verified_sender, verified_receiver) = unbounded();
+        let (verified_sender, verified_receiver) = bounded_channel(
+            "tvu-verified-packets-channel",
+            &tvu_config.packet_channels.tvu_verified,
+        );
         let sigverify_stage = SigVerifyStage::new(
             fetch_receiver,
             verified_sender,
@@ -168,6 +188,8 @@ impl Tvu {
             verified_vote_receiver,
             tvu_config.repair_validators,
             completed_data_sets_sender,
+            repair_status,
+            tvu_config.retransmit_egress_budget,
         );
 
         let (ledger_cleanup_slot_sender, ledger_cleanup_slot_receiver) = channel();
@@ -243,6 +265,12 @@ impl Tvu {
             rewards_recorder_sender,
             cache_block_time_sender,
             bank_notification_sender,
+            block_production_disabled: tvu_config.rpc_only,
+            replica_sync_sender,
+            block_production_paused: tvu_config.block_production_paused.clone(),
+            identity_conflict: tvu_config.identity_conflict.clone(),
+            poh_verify_gpu: tvu_config.poh_verify_gpu,
+            signing_journal: tvu_config.signing_journal.clone(),
         };
 
         let replay_stage = ReplayStage::new(
@@ -396,6 +424,8 @@ pub mod tests {
             replay_vote_sender,
             completed_data_sets_sender,
             None,
+            None,
+            Arc::new(RwLock::new(RepairStatus::default())),
             TvuConfig::default(),
         );
         exit.store(true, Ordering::Relaxed);
@@ -3,8 +3,9 @@
 use crate::{
     broadcast_stage::BroadcastStageType,
     cache_block_time_service::{CacheBlockTimeSender, CacheBlockTimeService},
+    clock_drift_service::{ClockDriftConfig, ClockDriftService},
     cluster_info::{
-        ClusterInfo, Node, DEFAULT_CONTACT_DEBUG_INTERVAL_MILLIS,
+        ClusterInfo, GossipConfig, Node, DEFAULT_CONTACT_DEBUG_INTERVAL_MILLIS,
         DEFAULT_CONTACT_SAVE_INTERVAL_MILLIS,
     },
     cluster_info_vote_listener::VoteTracker,
@@ -12,21 +13,27 @@ use crate::{
     consensus::{reconcile_blockstore_roots_with_tower, Tower},
     contact_info::ContactInfo,
     gossip_service::GossipService,
+    identity_interlock_service::{IdentityInterlockConfig, IdentityInterlockService},
     optimistically_confirmed_bank_tracker::{
         OptimisticallyConfirmedBank, OptimisticallyConfirmedBankTracker,
     },
+    packet_channel::PacketChannelsConfig,
     poh_recorder::{PohRecorder, GRACE_TICKS_FACTOR, MAX_GRACE_SLOTS},
     poh_service::{self, PohService},
+    program_stats::ProgramStats,
+    repair_status::RepairStatus,
+    replica_sync_service::{ReplicaSyncService, MAX_PENDING_REPLICA_SYNC_ROOTS},
     rewards_recorder_service::{RewardsRecorderSender, RewardsRecorderService},
     rpc::JsonRpcConfig,
     rpc_pubsub_service::{PubSubConfig, PubSubService},
     rpc_service::JsonRpcService,
     rpc_subscriptions::RpcSubscriptions,
-    sample_performance_service::SamplePerformanceService,
+    sample_performance_service::{RecentPerfSamples, SamplePerformanceService},
     serve_repair::ServeRepair,
     serve_repair_service::ServeRepairService,
     sigverify,
     snapshot_packager_service::{PendingSnapshotPackage, SnapshotPackagerService},
+    thread_pool_metrics_service::ThreadPoolMetricsService,
     tpu::Tpu,
     transaction_status_service::TransactionStatusService,
     tvu::{Sockets, Tvu, TvuConfig},
@@ -36,18 +43,21 @@ use rand::{thread_rng, Rng};
 use solana_ledger::{
     bank_forks_utils,
     blockstore::{Blockstore, BlockstoreSignals, CompletedSlotsReceiver, PurgeType},
-    blockstore_db::BlockstoreRecoveryMode,
+    blockstore_db::{BlockstoreRecoveryMode, BlockstoreRocksOptions},
     blockstore_processor::{self, TransactionStatusSender},
     leader_schedule::FixedSchedule,
     leader_schedule_cache::LeaderScheduleCache,
     poh::compute_hash_time_ns,
+    shred_encryption::{self, ShredCipher, ShredEncryptionKey},
+    signing_journal::SigningJournal,
 };
 use solana_measure::measure::Measure;
 use solana_metrics::datapoint_info;
+use solana_net_utils::ClusterIdentity;
 use solana_runtime::{
     accounts_index::AccountIndex,
     bank::Bank,
-    bank_forks::{BankForks, SnapshotConfig},
+    bank_forks::{BankForks, BankForksPruningConfig, SnapshotConfig},
     commitment::BlockCommitmentCache,
     hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
 };
@@ -65,7 +75,7 @@ use solana_vote_program::vote_state::VoteState;
 use std::time::Instant;
 use std::{
     collections::HashSet,
-    net::SocketAddr,
+    net::{SocketAddr, TcpListener},
     ops::Deref,
     path::{Path, PathBuf},
     sync::atomic::{AtomicBool, Ordering},
@@ -77,6 +87,10 @@ use std::{
 
 const MAX_COMPLETED_DATA_SETS_IN_CHANNEL: usize = 100_000;
 
+// An rpc-only node only runs programs to replay blocks and simulate transactions, so it keeps
+// a fraction of the executors a leader caches
+const RPC_ONLY_MAX_CACHED_EXECUTORS: usize = 20;
+
 #[derive(Clone, Debug)]
 pub struct ValidatorConfig {
     pub dev_halt_at_slot: Option<Slot>,
@@ -84,10 +98,13 @@ pub struct ValidatorConfig {
     pub expected_bank_hash: Option<Hash>,
     pub expected_shred_version: Option<u16>,
     pub voting_disabled: bool,
+    pub rpc_only: bool, // Implies voting_disabled; no block production, no TPU pipeline
     pub account_paths: Vec<PathBuf>,
     pub account_shrink_paths: Option<Vec<PathBuf>>,
     pub rpc_config: JsonRpcConfig,
     pub rpc_addrs: Option<(SocketAddr, SocketAddr)>, // (JsonRpc, JsonRpcPubSub)
+    pub json_rpc_disabled: bool,                     // Only serve RPC pubsub from rpc_addrs
+    pub rpc_pubsub_disabled: bool,                   // Only serve JSON RPC from rpc_addrs
     pub pubsub_config: PubSubConfig,
     pub snapshot_config: Option<SnapshotConfig>,
     pub max_ledger_shreds: Option<u64>,
@@ -100,19 +117,25 @@ pub struct ValidatorConfig {
     pub trusted_validators: Option<HashSet<Pubkey>>, // None = trust all
     pub repair_validators: Option<HashSet<Pubkey>>,  // None = repair from all
     pub gossip_validators: Option<HashSet<Pubkey>>,  // None = gossip with all
+    pub replica_sync_addr: Option<SocketAddr>,
+    pub replica_validators: Option<HashSet<Pubkey>>, // None = stream to any authenticated replica
     pub halt_on_trusted_validators_accounts_hash_mismatch: bool,
     pub accounts_hash_fault_injection_slots: u64, // 0 = no fault injection
     pub frozen_accounts: Vec<Pubkey>,
     pub no_rocksdb_compaction: bool,
+    pub blockstore_integrity_repair: bool,
     pub accounts_hash_interval_slots: u64,
+    pub bank_forks_pruning_config: BankForksPruningConfig,
     pub max_genesis_archive_unpacked_size: u64,
     pub wal_recovery_mode: Option<BlockstoreRecoveryMode>,
+    pub blockstore_rocks_options: BlockstoreRocksOptions,
     pub poh_verify: bool, // Perform PoH verification during blockstore processing at boo
     pub cuda: bool,
     pub require_tower: bool,
     pub debug_keys: Option<Arc<HashSet<Pubkey>>>,
     pub contact_debug_interval: u64,
     pub contact_save_interval: u64,
+    pub gossip_config: GossipConfig,
     pub bpf_jit: bool,
     pub send_transaction_retry_ms: u64,
     pub send_transaction_leader_forward_count: u64,
@@ -121,6 +144,12 @@ pub struct ValidatorConfig {
     pub account_indexes: HashSet<AccountIndex>,
     pub accounts_db_caching_enabled: bool,
     pub warp_slot: Option<Slot>,
+    pub packet_channels: PacketChannelsConfig,
+    pub clock_drift_config: ClockDriftConfig,
+    pub identity_interlock_config: Option<IdentityInterlockConfig>,
+    pub shred_encryption_key: Option<ShredEncryptionKey>,
+    pub retransmit_egress_budget: Option<u64>, // Bytes per second, None = unlimited
+    pub signing_journal_path: Option<PathBuf>,
 }
 
 impl Default for ValidatorConfig {
@@ -131,11 +160,14 @@ impl Default for ValidatorConfig {
             expected_bank_hash: None,
             expected_shred_version: None,
             voting_disabled: false,
+            rpc_only: false,
             max_ledger_shreds: None,
             account_paths: Vec::new(),
             account_shrink_paths: None,
             rpc_config: JsonRpcConfig::default(),
             rpc_addrs: None,
+            json_rpc_disabled: false,
+            rpc_pubsub_disabled: false,
             pubsub_config: PubSubConfig::default(),
             snapshot_config: None,
             broadcast_stage_type: BroadcastStageType::Standard,
@@ -147,19 +179,25 @@ impl Default for ValidatorConfig {
             trusted_validators: None,
             repair_validators: None,
             gossip_validators: None,
+            replica_sync_addr: None,
+            replica_validators: None,
             halt_on_trusted_validators_accounts_hash_mismatch: false,
             accounts_hash_fault_injection_slots: 0,
             frozen_accounts: vec![],
             no_rocksdb_compaction: false,
+            blockstore_integrity_repair: false,
             accounts_hash_interval_slots: std::u64::MAX,
+            bank_forks_pruning_config: BankForksPruningConfig::default(),
             max_genesis_archive_unpacked_size: MAX_GENESIS_ARCHIVE_UNPACKED_SIZE,
             wal_recovery_mode: None,
+            blockstore_rocks_options: BlockstoreRocksOptions::default(),
             poh_verify: true,
             cuda: false,
             require_tower: false,
             debug_keys: None,
             contact_debug_interval: DEFAULT_CONTACT_DEBUG_INTERVAL_MILLIS,
             contact_save_interval: DEFAULT_CONTACT_SAVE_INTERVAL_MILLIS,
+            gossip_config: GossipConfig::default(),
             bpf_jit: false,
             send_transaction_retry_ms: 2000,
             send_transaction_leader_forward_count: 2,
@@ -168,6 +206,12 @@ impl Default for ValidatorConfig {
             account_indexes: HashSet::new(),
             accounts_db_caching_enabled: false,
             warp_slot: None,
+            packet_channels: PacketChannelsConfig::default(),
+            clock_drift_config: ClockDriftConfig::default(),
+            identity_interlock_config: None,
+            shred_encryption_key: None,
+            retransmit_egress_budget: None,
+            signing_journal_path: None,
         }
     }
 }
@@ -200,8 +244,8 @@ struct TransactionHistoryServices {
 }
 
 struct RpcServices {
-    json_rpc_service: JsonRpcService,
-    pubsub_service: PubSubService,
+    json_rpc_service: Option<JsonRpcService>,
+    pubsub_service: Option<PubSubService>,
     optimistically_confirmed_bank_tracker: OptimisticallyConfirmedBankTracker,
 }
 
@@ -213,12 +257,16 @@ pub struct Validator {
     rewards_recorder_service: Option<RewardsRecorderService>,
     cache_block_time_service: Option<CacheBlockTimeService>,
     sample_performance_service: Option<SamplePerformanceService>,
+    clock_drift_service: ClockDriftService,
+    identity_interlock_service: Option<IdentityInterlockService>,
+    replica_sync_service: Option<ReplicaSyncService>,
+    thread_pool_metrics_service: ThreadPoolMetricsService,
     gossip_service: GossipService,
     serve_repair_service: ServeRepairService,
     completed_data_sets_service: CompletedDataSetsService,
     snapshot_packager_service: Option<SnapshotPackagerService>,
     poh_recorder: Arc<Mutex<PohRecorder>>,
-    poh_service: PohService,
+    poh_service: Option<PohService>,
     tpu: Tpu,
     tvu: Tvu,
     ip_echo_server: solana_net_utils::IpEchoServer,
@@ -254,7 +302,10 @@ impl Validator {
         warn!("identity: {}", id);
         warn!("vote account: {}", vote_account);
 
-        if config.voting_disabled {
+        if config.rpc_only {
+            warn!("rpc-only mode: voting and block production disabled");
+            authorized_voter_keypairs.clear();
+        } else if config.voting_disabled {
             warn!("voting disabled");
             authorized_voter_keypairs.clear();
         } else {
@@ -313,6 +364,8 @@ impl Validator {
         validator_exit.register_exit(Box::new(move || exit_.store(true, Ordering::Relaxed)));
         let validator_exit = Arc::new(RwLock::new(Some(validator_exit)));
 
+        let program_stats = Arc::new(RwLock::new(ProgramStats::default()));
+        let repair_status = Arc::new(RwLock::new(RepairStatus::default()));
         let (replay_vote_sender, replay_vote_receiver) = unbounded();
         let (
             genesis_config,
@@ -337,6 +390,7 @@ impl Validator {
             config,
             ledger_path,
             config.poh_verify,
+            &program_stats,
             &exit,
             config.enforce_ulimit_nofile,
         );
@@ -346,18 +400,42 @@ impl Validator {
         if let Some(ref shrink_paths) = config.account_shrink_paths {
             bank.set_shrink_paths(shrink_paths.clone());
         }
+        if config.rpc_only {
+            for bank in bank_forks.frozen_banks().values() {
+                bank.set_max_cached_executors(RPC_ONLY_MAX_CACHED_EXECUTORS);
+            }
+            bank.set_max_cached_executors(RPC_ONLY_MAX_CACHED_EXECUTORS);
+        }
         let bank_forks = Arc::new(RwLock::new(bank_forks));
 
-        let sample_performance_service =
-            if config.rpc_addrs.is_some() && config.rpc_config.enable_rpc_transaction_history {
-                Some(SamplePerformanceService::new(
-                    &bank_forks,
-                    &blockstore,
-                    &exit,
-                ))
-            } else {
-                None
-            };
+        let thread_pool_metrics_service = ThreadPoolMetricsService::new(&exit);
+
+        let recent_perf_samples = Arc::new(RwLock::new(RecentPerfSamples::new_from_blockstore(
+            &blockstore,
+        )));
+        let sample_performance_service = if config.rpc_addrs.is_some() {
+            Some(SamplePerformanceService::new(
+                &bank_forks,
+                if config.rpc_config.enable_rpc_transaction_history {
+                    Some(&blockstore)
+                } else {
+                    None
+                },
+                &recent_perf_samples,
+                &exit,
+            ))
+        } else {
+            None
+        };
+
+        let block_production_paused = Arc::new(AtomicBool::new(false));
+        let clock_drift_service = ClockDriftService::new(
+            &bank_forks,
+            &blockstore,
+            config.clock_drift_config.clone(),
+            &block_production_paused,
+            &exit,
+        );
 
         info!("Starting validator with working bank slot {}", bank.slot());
         {
@@ -385,11 +463,85 @@ impl Validator {
             }
         }
 
+        let shred_cipher = match (
+            shred_encryption::genesis_key_fingerprint(&genesis_config),
+            &config.shred_encryption_key,
+        ) {
+            (None, None) => None,
+            (Some(fingerprint), Some(key)) if key.fingerprint() == fingerprint => {
+                info!("Shred encryption enabled, key fingerprint {}", fingerprint);
+                Some(Arc::new(ShredCipher::new(key.clone())))
+            }
+            (Some(fingerprint), Some(key)) => {
+                error!(
+                    "shred encryption key mismatch: genesis expects fingerprint {} found: {}",
+                    fingerprint,
+                    key.fingerprint()
+                );
+                abort();
+            }
+            (Some(fingerprint), None) => {
+                error!(
+                    "cluster encrypts shreds with the key of fingerprint {}, \
+                     a shred encryption key is required",
+                    fingerprint
+                );
+                abort();
+            }
+            (None, Some(_)) => {
+                error!("cluster does not encrypt shreds, remove the shred encryption key");
+                abort();
+            }
+        };
+
+        let signing_journal = config.signing_journal_path.as_ref().map(|path| {
+            let signing_journal = SigningJournal::open(path).unwrap_or_else(|err| {
+                error!("Unable to open signing journal {:?}: {}", path, err);
+                abort();
+            });
+            let root_bank = bank_forks.read().unwrap().root_bank();
+            let mut signers = vec![id];
+            signers.extend(
+                authorized_voter_keypairs
+                    .iter()
+                    .map(|keypair| keypair.pubkey()),
+            );
+            signing_journal.record_startup(root_bank.slot(), root_bank.hash(), signers);
+            info!("Journaling signing events to {:?}", path);
+            Arc::new(signing_journal)
+        });
+
         let mut cluster_info = ClusterInfo::new(node.info.clone(), identity_keypair.clone());
         cluster_info.set_contact_debug_interval(config.contact_debug_interval);
+        if let Err(err) = cluster_info.set_gossip_config(config.gossip_config) {
+            error!("Invalid gossip config: {}", err);
+            abort();
+        }
+        if let Err(err) = config.packet_channels.verify() {
+            error!("Invalid packet channel config: {}", err);
+            abort();
+        }
+        cluster_info.set_shred_cipher(shred_cipher);
+        cluster_info.set_identity_interlock(config.identity_interlock_config.is_some());
+        cluster_info.set_signing_journal(signing_journal.clone());
         cluster_info.set_entrypoints(cluster_entrypoints);
         cluster_info.restore_contact_info(ledger_path, config.contact_save_interval);
         let cluster_info = Arc::new(cluster_info);
+        let (identity_interlock_service, identity_conflict) =
+            match config.identity_interlock_config.clone() {
+                Some(identity_interlock_config) => {
+                    // Halted until the interlock has watched gossip for the hold-down period
+                    let identity_conflict = Arc::new(AtomicBool::new(true));
+                    let identity_interlock_service = IdentityInterlockService::new(
+                        &cluster_info,
+                        identity_interlock_config,
+                        &identity_conflict,
+                        &exit,
+                    );
+                    (Some(identity_interlock_service), Some(identity_conflict))
+                }
+                None => (None, None),
+            };
         let mut block_commitment_cache = BlockCommitmentCache::default();
         block_commitment_cache.initialize_slots(bank.slot());
         let block_commitment_cache = Arc::new(RwLock::new(block_commitment_cache));
@@ -405,6 +557,31 @@ impl Validator {
             config.pubsub_config.enable_vote_subscription,
         ));
 
+        let (replica_sync_sender, replica_sync_service) = match config.replica_sync_addr {
+            Some(replica_sync_addr) => {
+                let listener = TcpListener::bind(replica_sync_addr).unwrap_or_else(|err| {
+                    error!(
+                        "Unable to bind replica sync to {}: {}",
+                        replica_sync_addr, err
+                    );
+                    abort();
+                });
+                let (replica_sync_sender, replica_sync_receiver) =
+                    bounded(MAX_PENDING_REPLICA_SYNC_ROOTS);
+                (
+                    Some(replica_sync_sender),
+                    Some(ReplicaSyncService::new(
+                        listener,
+                        identity_keypair.clone(),
+                        config.replica_validators.clone(),
+                        replica_sync_receiver,
+                        &exit,
+                    )),
+                )
+            }
+            None => (None, None),
+        };
+
         let (completed_data_sets_sender, completed_data_sets_receiver) =
             bounded(MAX_COMPLETED_DATA_SETS_IN_CHANNEL);
         let completed_data_sets_service = CompletedDataSetsService::new(
@@ -451,38 +628,50 @@ impl Validator {
         let (rpc_service, bank_notification_sender) = if let Some((rpc_addr, rpc_pubsub_addr)) =
             config.rpc_addrs
         {
-            if ContactInfo::is_valid_address(&node.info.rpc) {
-                assert!(ContactInfo::is_valid_address(&node.info.rpc_pubsub));
-            } else {
+            if config.json_rpc_disabled {
+                assert!(!ContactInfo::is_valid_address(&node.info.rpc));
+            }
+            if config.rpc_pubsub_disabled {
                 assert!(!ContactInfo::is_valid_address(&node.info.rpc_pubsub));
             }
             let (bank_notification_sender, bank_notification_receiver) = unbounded();
             (
                 Some(RpcServices {
-                    json_rpc_service: JsonRpcService::new(
-                        rpc_addr,
-                        config.rpc_config.clone(),
-                        config.snapshot_config.clone(),
-                        bank_forks.clone(),
-                        block_commitment_cache.clone(),
-                        blockstore.clone(),
-                        cluster_info.clone(),
-                        Some(poh_recorder.clone()),
-                        genesis_config.hash(),
-                        ledger_path,
-                        validator_exit.clone(),
-                        config.trusted_validators.clone(),
-                        rpc_override_health_check.clone(),
-                        optimistically_confirmed_bank.clone(),
-                        config.send_transaction_retry_ms,
-                        config.send_transaction_leader_forward_count,
-                    ),
-                    pubsub_service: PubSubService::new(
-                        config.pubsub_config.clone(),
-                        &subscriptions,
-                        rpc_pubsub_addr,
-                        &exit,
-                    ),
+                    json_rpc_service: if config.json_rpc_disabled {
+                        None
+                    } else {
+                        Some(JsonRpcService::new(
+                            rpc_addr,
+                            config.rpc_config.clone(),
+                            config.snapshot_config.clone(),
+                            bank_forks.clone(),
+                            block_commitment_cache.clone(),
+                            blockstore.clone(),
+                            cluster_info.clone(),
+                            Some(poh_recorder.clone()),
+                            genesis_config.hash(),
+                            ledger_path,
+                            validator_exit.clone(),
+                            config.trusted_validators.clone(),
+                            rpc_override_health_check.clone(),
+                            optimistically_confirmed_bank.clone(),
+                            config.send_transaction_retry_ms,
+                            config.send_transaction_leader_forward_count,
+                            program_stats,
+                            repair_status.clone(),
+                            recent_perf_samples,
+                        ))
+                    },
+                    pubsub_service: if config.rpc_pubsub_disabled {
+                        None
+                    } else {
+                        Some(PubSubService::new(
+                            config.pubsub_config.clone(),
+                            &subscriptions,
+                            rpc_pubsub_addr,
+                            &exit,
+                        ))
+                    },
                     optimistically_confirmed_bank_tracker: OptimisticallyConfirmedBankTracker::new(
                         bank_notification_receiver,
                         &exit,
@@ -510,7 +699,15 @@ impl Validator {
             std::thread::park();
         }
 
-        let ip_echo_server = solana_net_utils::ip_echo_server(node.sockets.ip_echo.unwrap());
+        let ip_echo_server = solana_net_utils::ip_echo_server_for_cluster(
+            node.sockets.ip_echo.unwrap(),
+            ClusterIdentity {
+                shred_version: node.info.shred_version,
+                genesis_hash: genesis_config.hash().to_bytes(),
+            },
+            identity_keypair.clone(),
+            Some(node.info.rpc).filter(ContactInfo::is_valid_address),
+        );
 
         let gossip_service = GossipService::new(
             &cluster_info,
@@ -553,7 +750,7 @@ impl Validator {
                 (None, None)
             };
 
-        if !config.no_poh_speed_test {
+        if !config.no_poh_speed_test && !config.rpc_only {
             check_poh_speed(&genesis_config, None);
         }
 
@@ -561,13 +758,18 @@ impl Validator {
             abort();
         }
 
-        let poh_service = PohService::new(
-            poh_recorder.clone(),
-            &poh_config,
-            &exit,
-            bank.ticks_per_slot(),
-            config.poh_pinned_cpu_core,
-        );
+        // PoH only needs to tick while this node is leader, which never happens in rpc-only mode
+        let poh_service = if config.rpc_only {
+            None
+        } else {
+            Some(PohService::new(
+                poh_recorder.clone(),
+                &poh_config,
+                &exit,
+                bank.ticks_per_slot(),
+                config.poh_pinned_cpu_core,
+            ))
+        };
         assert_eq!(
             blockstore.new_shreds_signals.len(),
             1,
@@ -630,6 +832,8 @@ impl Validator {
             replay_vote_sender.clone(),
             completed_data_sets_sender,
             bank_notification_sender.clone(),
+            replica_sync_sender,
+            repair_status,
             TvuConfig {
                 max_ledger_shreds: config.max_ledger_shreds,
                 halt_on_trusted_validators_accounts_hash_mismatch: config
@@ -639,6 +843,13 @@ impl Validator {
                 repair_validators: config.repair_validators.clone(),
                 accounts_hash_fault_injection_slots: config.accounts_hash_fault_injection_slots,
                 accounts_db_caching_enabled: config.accounts_db_caching_enabled,
+                rpc_only: config.rpc_only,
+                packet_channels: config.packet_channels.clone(),
+                block_production_paused: Some(block_production_paused),
+                identity_conflict,
+                retransmit_egress_budget: config.retransmit_egress_budget,
+                poh_verify_gpu: config.cuda,
+                signing_journal: signing_journal.clone(),
             },
         );
 
@@ -662,6 +873,9 @@ impl Validator {
             replay_vote_receiver,
             replay_vote_sender,
             bank_notification_sender,
+            config.rpc_only,
+            &config.packet_channels,
+            signing_journal,
         );
 
         datapoint_info!("validator-new", ("id", id.to_string(), String));
@@ -674,6 +888,10 @@ impl Validator {
             rewards_recorder_service,
             cache_block_time_service,
             sample_performance_service,
+            clock_drift_service,
+            identity_interlock_service,
+            thread_pool_metrics_service,
+            replica_sync_service,
             snapshot_packager_service,
             completed_data_sets_service,
             tpu,
@@ -723,7 +941,9 @@ impl Validator {
     }
 
     pub fn join(self) {
-        self.poh_service.join().expect("poh_service");
+        if let Some(poh_service) = self.poh_service {
+            poh_service.join().expect("poh_service");
+        }
         drop(self.poh_recorder);
         if let Some(RpcServices {
             json_rpc_service,
@@ -731,8 +951,12 @@ impl Validator {
             optimistically_confirmed_bank_tracker,
         }) = self.rpc_service
         {
-            json_rpc_service.join().expect("rpc_service");
-            pubsub_service.join().expect("pubsub_service");
+            if let Some(json_rpc_service) = json_rpc_service {
+                json_rpc_service.join().expect("rpc_service");
+            }
+            if let Some(pubsub_service) = pubsub_service {
+                pubsub_service.join().expect("pubsub_service");
+            }
             optimistically_confirmed_bank_tracker
                 .join()
                 .expect("optimistically_confirmed_bank_tracker");
@@ -761,6 +985,24 @@ impl Validator {
                 .expect("sample_performance_service");
         }
 
+        self.clock_drift_service
+            .join()
+            .expect("clock_drift_service");
+
+        if let Some(identity_interlock_service) = self.identity_interlock_service {
+            identity_interlock_service
+                .join()
+                .expect("identity_interlock_service");
+        }
+
+        self.thread_pool_metrics_service
+            .join()
+            .expect("thread_pool_metrics_service");
+
+        if let Some(replica_sync_service) = self.replica_sync_service {
+            replica_sync_service.join().expect("replica_sync_service");
+        }
+
         if let Some(s) = self.snapshot_packager_service {
             s.join().expect("snapshot_packager_service");
         }
@@ -907,13 +1149,50 @@ fn post_process_restored_tower(
         })
 }
 
-#[allow(clippy::type_complexity)]
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn check_blockstore_integrity(blockstore: &Blockstore, repair: bool) {
+    let start_slot = blockstore.last_root().saturating_add(1);
+    let report = blockstore
+        .check_integrity(start_slot)
+        .unwrap_or_else(|err| {
+            error!("Failed to check blockstore integrity: {:?}", err);
+            abort()
+        });
+    if report.is_ok() {
+        info!(
+            "Blockstore integrity check passed ({} slots checked)",
+            report.slots_checked
+        );
+        return;
+    }
+
+    for issue in &report.issues {
+        warn!("Blockstore integrity issue: {}", issue);
+    }
+    if repair {
+        let dropped_slots = blockstore.repair_integrity(&report).unwrap_or_else(|err| {
+            error!("Failed to repair blockstore: {:?}", err);
+            abort()
+        });
+        info!(
+            "Blockstore repaired, dropped slots {:?} will be repaired from the cluster",
+            dropped_slots
+        );
+    } else {
+        warn!(
+            "Found {} blockstore integrity issues; restart with --blockstore-integrity-repair to drop the affected slots",
+            report.issues.len()
+        );
+    }
+}
+
 fn new_banks_from_ledger(
     validator_identity: &Pubkey,
     vote_account: &Pubkey,
     config: &ValidatorConfig,
     ledger_path: &Path,
     poh_verify: bool,
+    program_stats: &Arc<RwLock<ProgramStats>>,
     exit: &Arc<AtomicBool>,
     enforce_ulimit_nofile: bool,
 ) -> (
@@ -953,10 +1232,11 @@ fn new_banks_from_ledger(
         ledger_signal_receiver,
         completed_slots_receiver,
         ..
-    } = Blockstore::open_with_signal(
+    } = Blockstore::open_with_signal_and_options(
         ledger_path,
         config.wal_recovery_mode.clone(),
         enforce_ulimit_nofile,
+        &config.blockstore_rocks_options,
     )
     .expect("Failed to open ledger database");
     blockstore.set_no_compaction(config.no_rocksdb_compaction);
@@ -969,9 +1249,12 @@ fn new_banks_from_ledger(
         });
     }
 
+    check_blockstore_integrity(&blockstore, config.blockstore_integrity_repair);
+
     let process_options = blockstore_processor::ProcessOptions {
         bpf_jit: config.bpf_jit,
         poh_verify,
+        poh_verify_gpu: config.cuda,
         dev_halt_at_slot: config.dev_halt_at_slot,
         new_hard_forks: config.new_hard_forks.clone(),
         frozen_accounts: config.frozen_accounts.clone(),
@@ -984,7 +1267,7 @@ fn new_banks_from_ledger(
     let blockstore = Arc::new(blockstore);
     let transaction_history_services =
         if config.rpc_addrs.is_some() && config.rpc_config.enable_rpc_transaction_history {
-            initialize_rpc_transaction_history_services(blockstore.clone(), exit)
+            initialize_rpc_transaction_history_services(blockstore.clone(), program_stats, exit)
         } else {
             TransactionHistoryServices::default()
         };
@@ -1064,6 +1347,7 @@ fn new_banks_from_ledger(
 
     bank_forks.set_snapshot_config(config.snapshot_config.clone());
     bank_forks.set_accounts_hash_interval_slots(config.accounts_hash_interval_slots);
+    bank_forks.set_pruning_config(config.bank_forks_pruning_config.clone());
 
     (
         genesis_config,
@@ -1152,6 +1436,7 @@ fn backup_and_clear_blockstore(ledger_path: &Path, start_slot: Slot, shred_versi
 
 fn initialize_rpc_transaction_history_services(
     blockstore: Arc<Blockstore>,
+    program_stats: &Arc<RwLock<ProgramStats>>,
     exit: &Arc<AtomicBool>,
 ) -> TransactionHistoryServices {
     let (transaction_status_sender, transaction_status_receiver) = unbounded();
@@ -1159,6 +1444,7 @@ fn initialize_rpc_transaction_history_services(
     let transaction_status_service = Some(TransactionStatusService::new(
         transaction_status_receiver,
         blockstore.clone(),
+        Some(program_stats.clone()),
         exit,
     ));
 
@@ -1416,6 +1702,61 @@ mod tests {
         remove_dir_all(validator_ledger_path).unwrap();
     }
 
+    #[test]
+    fn validator_with_rpc_service_disabled() {
+        solana_logger::setup();
+        let leader_keypair = Keypair::new();
+        let leader_node = Node::new_localhost_with_pubkey(&leader_keypair.pubkey());
+
+        for (json_rpc_disabled, rpc_pubsub_disabled) in &[(true, false), (false, true)] {
+            let validator_keypair = Keypair::new();
+            let mut validator_node = Node::new_localhost_with_pubkey(&validator_keypair.pubkey());
+            let rpc_addrs = (validator_node.info.rpc, validator_node.info.rpc_pubsub);
+            // A disabled service is not advertised in gossip
+            if *json_rpc_disabled {
+                validator_node.info.rpc = socketaddr_any!();
+            }
+            if *rpc_pubsub_disabled {
+                validator_node.info.rpc_pubsub = socketaddr_any!();
+            }
+            let genesis_config =
+                create_genesis_config_with_leader(10_000, &leader_keypair.pubkey(), 1000)
+                    .genesis_config;
+            let (validator_ledger_path, _blockhash) = create_new_tmp_ledger!(&genesis_config);
+
+            let voting_keypair = Arc::new(Keypair::new());
+            let config = ValidatorConfig {
+                rpc_addrs: Some(rpc_addrs),
+                json_rpc_disabled: *json_rpc_disabled,
+                rpc_pubsub_disabled: *rpc_pubsub_disabled,
+                ..ValidatorConfig::default()
+            };
+            let validator = Validator::new(
+                validator_node,
+                &Arc::new(validator_keypair),
+                &validator_ledger_path,
+                &voting_keypair.pubkey(),
+                vec![voting_keypair.clone()],
+                vec![leader_node.info.clone()],
+                &config,
+            );
+            let rpc_services = validator.rpc_service.as_ref().unwrap();
+            assert_eq!(rpc_services.json_rpc_service.is_none(), *json_rpc_disabled);
+            assert_eq!(rpc_services.pubsub_service.is_none(), *rpc_pubsub_disabled);
+
+            // The port of the disabled service is left unbound
+            let disabled_addr = if *json_rpc_disabled {
+                rpc_addrs.0
+            } else {
+                rpc_addrs.1
+            };
+            assert!(TcpListener::bind(disabled_addr).is_ok());
+
+            validator.close();
+            remove_dir_all(validator_ledger_path).unwrap();
+        }
+    }
+
     #[test]
     fn test_backup_and_clear_blockstore() {
         use std::time::Instant;
@@ -8,6 +8,7 @@ use crate::{
     completed_data_sets_service::CompletedDataSetsSender,
     repair_response,
     repair_service::{RepairInfo, RepairService},
+    repair_status::RepairStatus,
     result::{Error, Result},
     serve_repair::DEFAULT_NONCE,
 };
@@ -24,7 +25,7 @@ use solana_ledger::{
 };
 use solana_metrics::{inc_new_counter_debug, inc_new_counter_error};
 use solana_perf::packet::Packets;
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_runtime::{bank::Bank, bank_forks::BankForks};
 use solana_sdk::{packet::PACKET_DATA_SIZE, pubkey::Pubkey, timing::duration_as_ms};
 use solana_streamer::streamer::PacketSender;
@@ -130,6 +131,7 @@ fn run_insert<F>(
     handle_duplicate: F,
     metrics: &mut BlockstoreInsertionMetrics,
     completed_data_sets_sender: &CompletedDataSetsSender,
+    repair_status: &RwLock<RepairStatus>,
 ) -> Result<()>
 where
     F: Fn(Shred),
@@ -147,6 +149,15 @@ where
     repair_infos.retain(|repair_info| verify_repair(&repair_info));
     assert_eq!(shreds.len(), repair_infos.len());
 
+    if repair_infos.iter().any(Option::is_some) {
+        let mut repair_status = repair_status.write().unwrap();
+        for (shred, repair_info) in shreds.iter().zip(repair_infos.iter()) {
+            if let Some(repair_info) = repair_info {
+                repair_status.record_response(shred.slot(), repair_info.from_addr);
+            }
+        }
+    }
+
     let (completed_data_sets, inserted_indices) = blockstore.insert_shreds_handle_duplicate(
         shreds,
         Some(leader_schedule_cache),
@@ -215,7 +226,7 @@ where
                                     if packet.meta.repair {
                                         if let Some(nonce) = repair_response::nonce(&packet.data) {
                                             let repair_info = RepairMeta {
-                                                _from_addr: packet.meta.addr(),
+                                                from_addr: packet.meta.addr(),
                                                 nonce,
                                             };
                                             Some(repair_info)
@@ -275,7 +286,7 @@ where
 }
 
 struct RepairMeta {
-    _from_addr: SocketAddr,
+    from_addr: SocketAddr,
     nonce: Nonce,
 }
 
@@ -327,6 +338,7 @@ impl WindowService {
             + std::marker::Sync,
     {
         let bank_forks = Some(repair_info.bank_forks.clone());
+        let repair_status = repair_info.repair_status.clone();
 
         let repair_service = RepairService::new(
             blockstore.clone(),
@@ -355,6 +367,7 @@ impl WindowService {
             insert_receiver,
             duplicate_sender,
             completed_data_sets_sender,
+            repair_status,
         );
 
         let t_window = Self::start_recv_window_thread(
@@ -410,6 +423,7 @@ impl WindowService {
         insert_receiver: CrossbeamReceiver<(Vec<Shred>, Vec<Option<RepairMeta>>)>,
         duplicate_sender: CrossbeamSender<Shred>,
         completed_data_sets_sender: CompletedDataSetsSender,
+        repair_status: Arc<RwLock<RepairStatus>>,
     ) -> JoinHandle<()> {
         let exit = exit.clone();
         let blockstore = blockstore.clone();
@@ -439,6 +453,7 @@ impl WindowService {
                         &handle_duplicate,
                         &mut metrics,
                         &completed_data_sets_sender,
+                        &repair_status,
                     ) {
                         if Self::should_exit_on_error(e, &mut handle_timeout, &handle_error) {
                             break;
@@ -478,10 +493,8 @@ impl WindowService {
             .spawn(move || {
                 let _exit = Finalizer::new(exit.clone());
                 trace!("{}: RECV_WINDOW started", id);
-                let thread_pool = rayon::ThreadPoolBuilder::new()
-                    .num_threads(get_thread_count())
-                    .build()
-                    .unwrap();
+                let thread_pool =
+                    thread_manager::build_thread_pool("solana-window-insert", get_thread_count());
                 let mut now = Instant::now();
                 let handle_error = || {
                     inc_new_counter_error!("solana-window-error", 1, 1);
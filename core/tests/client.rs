@@ -1,4 +1,8 @@
-use solana_client::{pubsub_client::PubsubClient, rpc_client::RpcClient, rpc_response::SlotInfo};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    pubsub_client::PubsubClient, rpc_client::RpcClient, rpc_config::RpcAccountInfoConfig,
+    rpc_response::SlotInfo,
+};
 use solana_core::{
     optimistically_confirmed_bank_tracker::OptimisticallyConfirmedBank,
     rpc_pubsub_service::{PubSubConfig, PubSubService},
@@ -82,6 +86,44 @@ fn test_rpc_client() {
         client.get_balance(&alice.pubkey()).unwrap(),
         original_alice_balance - sol_to_lamports(20.0)
     );
+    // Accounts are fetched with the base64+zstd encoding
+    assert_eq!(
+        client.get_account(&bob_pubkey).unwrap().lamports,
+        sol_to_lamports(20.0)
+    );
+}
+
+#[test]
+fn test_account_subscription() {
+    solana_logger::setup();
+
+    let alice = Keypair::new();
+    let test_validator = TestValidator::with_no_fees(alice.pubkey());
+    let client = RpcClient::new(test_validator.rpc_url());
+    let bob_pubkey = solana_sdk::pubkey::new_rand();
+
+    let (mut subscription, receiver) = PubsubClient::account_subscribe(
+        &test_validator.rpc_pubsub_url(),
+        &bob_pubkey,
+        Some(RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64Zstd),
+            commitment: Some(CommitmentConfig::confirmed()),
+            data_slice: None,
+        }),
+    )
+    .unwrap();
+
+    let (blockhash, _fee_calculator) = client.get_recent_blockhash().unwrap();
+    let tx = system_transaction::transfer(&alice, &bob_pubkey, sol_to_lamports(1.0), blockhash);
+    client.send_transaction(&tx).unwrap();
+
+    let response = receiver.recv_timeout(Duration::from_secs(20)).unwrap();
+    let account = response.value.decode().unwrap();
+    assert_eq!(account.lamports, sol_to_lamports(1.0));
+
+    // The subscription thread only returns once the validator closes the web socket
+    drop(test_validator);
+    subscription.shutdown().unwrap();
 }
 
 #[test]
@@ -1,5 +1,8 @@
 use clap::{crate_description, crate_name, App, Arg};
-use solana_clap_utils::input_parsers::{lamports_of_sol, value_of};
+use solana_clap_utils::{
+    input_parsers::{duration_of, lamports_of_sol},
+    input_validators::{is_duration, is_sol_amount},
+};
 use solana_faucet::{
     faucet::{run_faucet, Faucet, FAUCET_PORT},
     socketaddr,
@@ -9,6 +12,7 @@ use std::{
     net::{Ipv4Addr, SocketAddr},
     sync::{Arc, Mutex},
     thread,
+    time::Duration,
 };
 
 #[tokio::main]
@@ -33,9 +37,10 @@ async fn main() {
         .arg(
             Arg::with_name("slice")
                 .long("slice")
-                .value_name("SECS")
+                .value_name("DURATION")
                 .takes_value(true)
-                .help("Time slice over which to limit requests to faucet"),
+                .validator(is_duration)
+                .help("Time slice over which to limit requests to faucet, e.g. 10m; a bare number is in seconds"),
         )
         .arg(
             Arg::with_name("per_time_cap")
@@ -43,6 +48,7 @@ async fn main() {
                 .alias("cap")
                 .value_name("NUM")
                 .takes_value(true)
+                .validator(is_sol_amount)
                 .help("Request limit for time slice, in SOL"),
         )
         .arg(
@@ -50,6 +56,7 @@ async fn main() {
                 .long("per-request-cap")
                 .value_name("NUM")
                 .takes_value(true)
+                .validator(is_sol_amount)
                 .help("Request limit for a single request, in SOL"),
         )
         .get_matches();
@@ -57,7 +64,8 @@ async fn main() {
     let faucet_keypair = read_keypair_file(matches.value_of("keypair").unwrap())
         .expect("failed to read client keypair");
 
-    let time_slice = value_of(&matches, "slice");
+    let time_slice =
+        duration_of(&matches, "slice", Duration::from_secs(1)).map(|slice| slice.as_secs());
     let per_time_cap = lamports_of_sol(&matches, "per_time_cap");
     let per_request_cap = lamports_of_sol(&matches, "per_request_cap");
 
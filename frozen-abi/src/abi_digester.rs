@@ -1,4 +1,4 @@
-use crate::abi_example::{normalize_type_name, AbiEnumVisitor};
+use crate::abi_example::{normalize_type_name, AbiEnumVisitor, AbiExample};
 use crate::hash::{Hash, Hasher};
 
 use log::*;
@@ -515,6 +515,15 @@ impl SerializeStructVariant for AbiDigester {
     }
 }
 
+/// Digests the ABI of `T` the same way `#[frozen_abi]` does, so that tools can report the
+/// digests of a build outside of its tests
+pub fn digest_abi<T: AbiExample + Serialize>() -> Result<Hash, DigestError> {
+    let mut digester = AbiDigester::create();
+    let example = T::example();
+    <&T>::visit_for_abi(&&example, &mut digester)?;
+    Ok(digester.finalize())
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
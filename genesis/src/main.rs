@@ -10,17 +10,22 @@ extern crate solana_vest_program;
 use clap::{crate_description, crate_name, value_t, value_t_or_exit, App, Arg, ArgMatches};
 use solana_clap_utils::{
     input_parsers::{cluster_type_of, pubkey_of, pubkeys_of, unix_timestamp_from_rfc3339_datetime},
-    input_validators::{is_pubkey_or_keypair, is_rfc3339_datetime, is_valid_percentage},
+    input_validators::{
+        is_parsable, is_pubkey_or_keypair, is_rfc3339_datetime, is_valid_percentage,
+    },
 };
 use solana_genesis::{genesis_accounts::add_genesis_accounts, Base64Account};
 use solana_ledger::{
-    blockstore::create_new_ledger, blockstore_db::AccessType, poh::compute_hashes_per_tick,
+    blockstore::create_new_ledger,
+    blockstore_db::AccessType,
+    poh::compute_hashes_per_tick,
+    shred_encryption::{self, ShredEncryptionKey},
 };
 use solana_runtime::hardened_unpack::MAX_GENESIS_ARCHIVE_UNPACKED_SIZE;
 use solana_sdk::{
     account::Account,
     clock,
-    epoch_schedule::EpochSchedule,
+    epoch_schedule::{EpochSchedule, MAX_LEADER_SCHEDULE_EPOCH_OFFSET, MINIMUM_SLOTS_PER_EPOCH},
     fee_calculator::FeeRateGovernor,
     genesis_config::{ClusterType, GenesisConfig},
     inflation::Inflation,
@@ -38,7 +43,7 @@ use std::{
     error,
     fs::File,
     io::{self, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
     process,
     str::FromStr,
     time::Duration,
@@ -98,6 +103,66 @@ pub fn load_genesis_accounts(file: &str, genesis_config: &mut GenesisConfig) ->
     Ok(lamports)
 }
 
+// Rates are fractions, e.g. 0.08 for 8%
+fn check_inflation(inflation: &Inflation) -> Result<(), String> {
+    for (name, rate) in &[
+        ("initial", inflation.initial),
+        ("terminal", inflation.terminal),
+        ("taper", inflation.taper),
+        ("foundation", inflation.foundation),
+    ] {
+        if !(0.0..=1.0).contains(rate) {
+            return Err(format!("{} rate must be between 0 and 1: {}", name, rate));
+        }
+    }
+    if inflation.terminal > inflation.initial {
+        return Err(format!(
+            "terminal rate {} is greater than initial rate {}",
+            inflation.terminal, inflation.initial
+        ));
+    }
+    if inflation.foundation_term < 0.0 {
+        return Err(format!(
+            "foundation term must not be negative: {}",
+            inflation.foundation_term
+        ));
+    }
+    Ok(())
+}
+
+fn check_epoch_schedule(
+    slots_per_epoch: u64,
+    leader_schedule_slot_offset: u64,
+    warmup: bool,
+) -> Result<(), String> {
+    if slots_per_epoch < MINIMUM_SLOTS_PER_EPOCH {
+        return Err(format!(
+            "slots per epoch must be at least {}: {}",
+            MINIMUM_SLOTS_PER_EPOCH, slots_per_epoch
+        ));
+    }
+    let max_leader_schedule_slot_offset =
+        slots_per_epoch.saturating_mul(MAX_LEADER_SCHEDULE_EPOCH_OFFSET);
+    if leader_schedule_slot_offset == 0
+        || leader_schedule_slot_offset > max_leader_schedule_slot_offset
+    {
+        return Err(format!(
+            "leader schedule slot offset must be between 1 and {}: {}",
+            max_leader_schedule_slot_offset, leader_schedule_slot_offset
+        ));
+    }
+    // Warmup epochs only compute the leader schedule of the next epoch, a larger offset would
+    // skip epochs when the first normal epoch starts
+    if warmup && leader_schedule_slot_offset > slots_per_epoch {
+        return Err(format!(
+            "leader schedule slot offset must not exceed the slots per epoch when warmup epochs \
+             are enabled: {}",
+            leader_schedule_slot_offset
+        ));
+    }
+    Ok(())
+}
+
 #[allow(clippy::cognitive_complexity)]
 fn main() -> Result<(), Box<dyn error::Error>> {
     let default_faucet_pubkey = solana_cli_config::Config::default().keypair_path;
@@ -328,6 +393,17 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                 .takes_value(true)
                 .help("The number of slots in an epoch"),
         )
+        .arg(
+            Arg::with_name("leader_schedule_slot_offset")
+                .long("leader-schedule-slot-offset")
+                .value_name("SLOTS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help(
+                    "The number of slots before the beginning of an epoch at which its \
+                     leader schedule is computed [default: the number of slots in an epoch]"
+                ),
+        )
         .arg(
             Arg::with_name("enable_warmup_epochs")
                 .long("enable-warmup-epochs")
@@ -344,6 +420,17 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                 .multiple(true)
                 .help("The location of pubkey for primordial accounts and balance"),
         )
+        .arg(
+            Arg::with_name("shred_encryption_key")
+                .long("shred-encryption-key")
+                .value_name("PATH")
+                .takes_value(true)
+                .help(
+                    "Encrypt the shreds of the cluster with the key in this file, \
+                     a new key is written to it if it does not exist. \
+                     Every validator needs a copy of the key",
+                ),
+        )
         .arg(
             Arg::with_name("cluster_type")
                 .long("cluster-type")
@@ -381,6 +468,46 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                 .possible_values(&["pico", "full", "none"])
                 .help("Selects inflation"),
         )
+        .arg(
+            Arg::with_name("inflation_initial")
+                .long("inflation-initial")
+                .value_name("RATE")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .help("Initial yearly inflation rate, as a fraction [default: 0.08]"),
+        )
+        .arg(
+            Arg::with_name("inflation_terminal")
+                .long("inflation-terminal")
+                .value_name("RATE")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .help("Yearly inflation rate reached once tapering ends, as a fraction [default: 0.015]"),
+        )
+        .arg(
+            Arg::with_name("inflation_taper")
+                .long("inflation-taper")
+                .value_name("RATE")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .help("Yearly rate at which inflation is lowered until reaching the terminal rate, as a fraction [default: 0.15]"),
+        )
+        .arg(
+            Arg::with_name("inflation_foundation")
+                .long("inflation-foundation")
+                .value_name("RATE")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .help("Share of the inflation allocated to the foundation, as a fraction [default: 0.05]"),
+        )
+        .arg(
+            Arg::with_name("inflation_foundation_term")
+                .long("inflation-foundation-term")
+                .value_name("YEARS")
+                .takes_value(true)
+                .validator(is_parsable::<f64>)
+                .help("Number of years the foundation receives its share of the inflation [default: 7]"),
+        )
         .get_matches();
 
     let ledger_path = PathBuf::from(matches.value_of("ledger_path").unwrap());
@@ -483,11 +610,15 @@ fn main() -> Result<(), Box<dyn error::Error>> {
             }
         }
     };
-    let epoch_schedule = EpochSchedule::custom(
-        slots_per_epoch,
-        slots_per_epoch,
-        matches.is_present("enable_warmup_epochs"),
-    );
+    let leader_schedule_slot_offset =
+        value_t!(matches, "leader_schedule_slot_offset", u64).unwrap_or(slots_per_epoch);
+    let warmup = matches.is_present("enable_warmup_epochs");
+    if let Err(err) = check_epoch_schedule(slots_per_epoch, leader_schedule_slot_offset, warmup) {
+        eprintln!("Error: invalid epoch schedule: {}", err);
+        process::exit(1);
+    }
+    let epoch_schedule =
+        EpochSchedule::custom(slots_per_epoch, leader_schedule_slot_offset, warmup);
 
     let native_instruction_processors = if cluster_type == ClusterType::Development {
         vec![
@@ -519,6 +650,26 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         };
         genesis_config.inflation = inflation;
     }
+    // Individual parameters override the ones of the selected inflation
+    if let Ok(initial) = value_t!(matches, "inflation_initial", f64) {
+        genesis_config.inflation.initial = initial;
+    }
+    if let Ok(terminal) = value_t!(matches, "inflation_terminal", f64) {
+        genesis_config.inflation.terminal = terminal;
+    }
+    if let Ok(taper) = value_t!(matches, "inflation_taper", f64) {
+        genesis_config.inflation.taper = taper;
+    }
+    if let Ok(foundation) = value_t!(matches, "inflation_foundation", f64) {
+        genesis_config.inflation.foundation = foundation;
+    }
+    if let Ok(foundation_term) = value_t!(matches, "inflation_foundation_term", f64) {
+        genesis_config.inflation.foundation_term = foundation_term;
+    }
+    if let Err(err) = check_inflation(&genesis_config.inflation) {
+        eprintln!("Error: invalid inflation: {}", err);
+        process::exit(1);
+    }
 
     let commission = value_t_or_exit!(matches, "vote_commission_percentage", u8);
 
@@ -576,6 +727,33 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         solana_runtime::genesis_utils::activate_all_features(&mut genesis_config);
     }
 
+    if let Some(path) = matches.value_of("shred_encryption_key") {
+        let path = Path::new(path);
+        let key = if path.exists() {
+            ShredEncryptionKey::read(path)
+        } else {
+            let key = ShredEncryptionKey::new_rand();
+            key.write(path).map(|_| key)
+        }
+        .unwrap_or_else(|err| {
+            eprintln!(
+                "Error: failed to load shred encryption key {}: {}",
+                path.display(),
+                err
+            );
+            process::exit(1);
+        });
+        let fingerprint = key.fingerprint().as_ref().to_vec();
+        genesis_config.add_account(
+            shred_encryption::fingerprint_account::id(),
+            Account {
+                lamports: genesis_config.rent.minimum_balance(fingerprint.len()),
+                data: fingerprint,
+                ..Account::default()
+            },
+        );
+    }
+
     if let Some(files) = matches.values_of("primordial_accounts_file") {
         for file in files {
             load_genesis_accounts(file, &mut genesis_config)?;
@@ -652,6 +830,45 @@ mod tests {
     use std::io::Write;
     use std::path::Path;
 
+    #[test]
+    fn test_check_inflation() {
+        assert!(check_inflation(&Inflation::default()).is_ok());
+        assert!(check_inflation(&Inflation::new_disabled()).is_ok());
+        assert!(check_inflation(&Inflation::pico()).is_ok());
+
+        let mut inflation = Inflation::full();
+        inflation.terminal = inflation.initial + 0.01;
+        assert!(check_inflation(&inflation).is_err());
+
+        let mut inflation = Inflation::full();
+        inflation.taper = 1.5;
+        assert!(check_inflation(&inflation).is_err());
+
+        let mut inflation = Inflation::full();
+        inflation.foundation = -0.1;
+        assert!(check_inflation(&inflation).is_err());
+
+        let mut inflation = Inflation::full();
+        inflation.foundation_term = -1.0;
+        assert!(check_inflation(&inflation).is_err());
+    }
+
+    #[test]
+    fn test_check_epoch_schedule() {
+        assert!(
+            check_epoch_schedule(MINIMUM_SLOTS_PER_EPOCH, MINIMUM_SLOTS_PER_EPOCH, true).is_ok()
+        );
+        assert!(check_epoch_schedule(8192, 8192, true).is_ok());
+        assert!(check_epoch_schedule(1000, 1000, true).is_ok());
+        assert!(check_epoch_schedule(1000, 1000, false).is_ok());
+        assert!(check_epoch_schedule(1000, 3000, false).is_ok());
+
+        assert!(check_epoch_schedule(MINIMUM_SLOTS_PER_EPOCH - 1, 32, false).is_err());
+        assert!(check_epoch_schedule(1000, 0, false).is_err());
+        assert!(check_epoch_schedule(1000, 3001, false).is_err());
+        assert!(check_epoch_schedule(1000, 3000, true).is_err());
+    }
+
     #[test]
     fn test_append_primordial_accounts_to_genesis() {
         // Test invalid file returns error
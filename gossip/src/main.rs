@@ -1,23 +1,31 @@
 //! A command-line executable for monitoring a cluster's gossip plane.
 
 use clap::{
-    crate_description, crate_name, value_t, value_t_or_exit, App, AppSettings, Arg, ArgMatches,
-    SubCommand,
+    crate_description, crate_name, value_t, value_t_or_exit, values_t_or_exit, App, AppSettings,
+    Arg, ArgMatches, SubCommand,
 };
+use packet_decoder::{decode_packet, PacketKind};
+use pcap::PcapReader;
 use solana_clap_utils::{
     input_parsers::keypair_of,
     input_validators::{is_keypair_or_ask_keyword, is_port, is_pubkey},
 };
 use solana_client::rpc_client::RpcClient;
 use solana_core::{contact_info::ContactInfo, gossip_service::discover};
+use solana_perf::packet::PACKET_DATA_SIZE;
 use solana_sdk::pubkey::Pubkey;
 use std::{
     error,
-    net::{IpAddr, Ipv4Addr, SocketAddr},
+    fs::File,
+    io::BufReader,
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
     process::exit,
     sync::Arc,
 };
 
+mod packet_decoder;
+mod pcap;
+
 fn parse_matches() -> ArgMatches<'static> {
     let shred_version_arg = Arg::with_name("shred_version")
         .long("shred-version")
@@ -164,6 +172,63 @@ fn parse_matches() -> ArgMatches<'static> {
                         .help("Public key of a specific node to stop"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("decode")
+                .about("Decode and print gossip, repair and turbine packets")
+                .setting(AppSettings::DisableVersion)
+                .arg(
+                    Arg::with_name("pcap")
+                        .long("pcap")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required_unless("listen")
+                        .conflicts_with("listen")
+                        .help("Decode the UDP packets of this libpcap capture file"),
+                )
+                .arg(
+                    Arg::with_name("listen")
+                        .long("listen")
+                        .value_name("HOST:PORT")
+                        .takes_value(true)
+                        .validator(solana_net_utils::is_host_port)
+                        .help("Capture and decode the packets sent to this address"),
+                )
+                .arg(
+                    Arg::with_name("kind")
+                        .long("kind")
+                        .value_name("KIND")
+                        .takes_value(true)
+                        .multiple(true)
+                        .possible_values(&["gossip", "repair", "turbine"])
+                        .use_delimiter(true)
+                        .help(
+                            "Packet kinds to try decoding packets as, in order \
+                             [default: gossip,repair,turbine]",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("leader")
+                        .long("leader")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .validator(is_pubkey)
+                        .help("Check shred signatures against this slot leader"),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .long("count")
+                        .value_name("NUM")
+                        .takes_value(true)
+                        .help("Stop after decoding NUM packets"),
+                )
+                .arg(
+                    Arg::with_name("verbose")
+                        .short("v")
+                        .long("verbose")
+                        .takes_value(false)
+                        .help("Also print the full contents of each message"),
+                ),
+        )
         .get_matches()
 }
 
@@ -370,6 +435,57 @@ fn process_stop(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
     Ok(())
 }
 
+fn process_decode(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+    let kinds = if matches.is_present("kind") {
+        values_t_or_exit!(matches, "kind", PacketKind)
+    } else {
+        vec![PacketKind::Gossip, PacketKind::Repair, PacketKind::Turbine]
+    };
+    let leader = value_t!(matches, "leader", Pubkey).ok();
+    let count = value_t!(matches, "count", usize).ok();
+    let verbose = matches.is_present("verbose");
+
+    let print_packet = |src: SocketAddr, dst: Option<SocketAddr>, data: &[u8]| {
+        let dst = dst.map(|dst| format!(" -> {}", dst)).unwrap_or_default();
+        match decode_packet(data, &kinds, leader.as_ref(), verbose) {
+            Some(description) => print!("{}{} ({} bytes): {}", src, dst, data.len(), description),
+            None => println!("{}{} ({} bytes): undecodable", src, dst, data.len()),
+        }
+    };
+
+    if let Some(path) = matches.value_of("pcap") {
+        let mut reader = PcapReader::new(BufReader::new(File::open(path)?))?;
+        let mut decoded = 0;
+        while count.map(|count| decoded < count).unwrap_or(true) {
+            match reader.next_datagram()? {
+                Some(datagram) => {
+                    print!(
+                        "{}.{:06} ",
+                        datagram.timestamp_us / 1_000_000,
+                        datagram.timestamp_us % 1_000_000
+                    );
+                    print_packet(datagram.src, Some(datagram.dst), &datagram.payload);
+                    decoded += 1;
+                }
+                None => break,
+            }
+        }
+    } else {
+        let listen_addr = solana_net_utils::parse_host_port(matches.value_of("listen").unwrap())?;
+        let socket = UdpSocket::bind(listen_addr)?;
+        eprintln!("Listening on {}", socket.local_addr()?);
+        let mut data = [0u8; PACKET_DATA_SIZE];
+        let mut decoded = 0;
+        while count.map(|count| decoded < count).unwrap_or(true) {
+            let (size, src) = socket.recv_from(&mut data)?;
+            print_packet(src, None, &data[..size]);
+            decoded += 1;
+        }
+    }
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn error::Error>> {
     solana_logger::setup_with_default("solana=info");
 
@@ -385,6 +501,9 @@ fn main() -> Result<(), Box<dyn error::Error>> {
         ("stop", Some(matches)) => {
             process_stop(matches)?;
         }
+        ("decode", Some(matches)) => {
+            process_decode(matches)?;
+        }
         _ => unreachable!(),
     }
 
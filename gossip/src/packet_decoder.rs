@@ -0,0 +1,268 @@
+//! Decodes gossip, repair and turbine packets into human readable descriptions
+
+use solana_core::{
+    cluster_info::Protocol,
+    crds_value::{CrdsValue, Signable},
+    serve_repair::RepairProtocol,
+};
+use solana_ledger::shred::{Nonce, Shred, SHRED_PAYLOAD_SIZE, SIZE_OF_NONCE};
+use solana_perf::packet::{limited_deserialize, PACKET_DATA_SIZE};
+use solana_sdk::{pubkey::Pubkey, sanitize::Sanitize};
+use std::{fmt::Write, str::FromStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketKind {
+    Gossip,
+    Repair,
+    Turbine,
+}
+
+impl FromStr for PacketKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gossip" => Ok(PacketKind::Gossip),
+            "repair" => Ok(PacketKind::Repair),
+            "turbine" => Ok(PacketKind::Turbine),
+            _ => Err(format!("unknown packet kind: {}", s)),
+        }
+    }
+}
+
+/// Describes `data`, trying each of `kinds` in turn.  Shred signatures are checked against
+/// `leader` when given, since the slot leader is not known from the packet alone.  Returns
+/// None if the packet does not decode as any of the kinds
+pub fn decode_packet(
+    data: &[u8],
+    kinds: &[PacketKind],
+    leader: Option<&Pubkey>,
+    verbose: bool,
+) -> Option<String> {
+    kinds.iter().find_map(|kind| match kind {
+        PacketKind::Gossip => decode_gossip(data, verbose),
+        PacketKind::Repair => decode_repair(data, leader, verbose),
+        PacketKind::Turbine => decode_shred(data, None, leader, verbose),
+    })
+}
+
+fn signature_status(valid: bool) -> &'static str {
+    if valid {
+        "valid signature"
+    } else {
+        "INVALID SIGNATURE"
+    }
+}
+
+fn describe_crds_value(out: &mut String, value: &CrdsValue) {
+    let _ = writeln!(
+        out,
+        "    {:?} wallclock {}: {}",
+        value.label(),
+        value.wallclock(),
+        signature_status(value.verify())
+    );
+}
+
+fn decode_gossip(data: &[u8], verbose: bool) -> Option<String> {
+    let protocol: Protocol = limited_deserialize(data).ok()?;
+    protocol.sanitize().ok()?;
+
+    let mut out = String::new();
+    match &protocol {
+        Protocol::PullRequest(_, caller) => {
+            let _ = writeln!(
+                out,
+                "gossip pull request from {}: {}",
+                caller.pubkey(),
+                signature_status(caller.verify())
+            );
+        }
+        Protocol::PullResponse(from, values) | Protocol::PushMessage(from, values) => {
+            let kind = if let Protocol::PullResponse(..) = protocol {
+                "pull response"
+            } else {
+                "push message"
+            };
+            let _ = writeln!(
+                out,
+                "gossip {} from {} with {} values:",
+                kind,
+                from,
+                values.len()
+            );
+            for value in values {
+                describe_crds_value(&mut out, value);
+            }
+        }
+        Protocol::PruneMessage(from, prune_data) => {
+            let _ = writeln!(
+                out,
+                "gossip prune message from {} to {} pruning {} origins: {}",
+                from,
+                prune_data.destination,
+                prune_data.prunes.len(),
+                signature_status(prune_data.verify())
+            );
+        }
+        Protocol::PingMessage(ping) => {
+            let _ = writeln!(
+                out,
+                "gossip ping from {}: {}",
+                ping.pubkey(),
+                signature_status(ping.verify())
+            );
+        }
+        Protocol::PongMessage(pong) => {
+            let _ = writeln!(
+                out,
+                "gossip pong from {}: {}",
+                pong.pubkey(),
+                signature_status(pong.verify())
+            );
+        }
+    }
+    if verbose {
+        let _ = writeln!(out, "{:#?}", protocol);
+    }
+    Some(out)
+}
+
+fn decode_repair(data: &[u8], leader: Option<&Pubkey>, verbose: bool) -> Option<String> {
+    // Repair responses are shreds followed by the nonce of the request
+    if data.len() == SHRED_PAYLOAD_SIZE + SIZE_OF_NONCE {
+        let nonce: Nonce = limited_deserialize(&data[SHRED_PAYLOAD_SIZE..]).ok()?;
+        if let Some(out) = decode_shred(&data[..SHRED_PAYLOAD_SIZE], Some(nonce), leader, verbose) {
+            return Some(out);
+        }
+    }
+
+    let request: RepairProtocol = limited_deserialize(data).ok()?;
+    let mut out = match &request {
+        RepairProtocol::WindowIndex(from, slot, index) => format!(
+            "repair window index request from {} for slot {} shred {}",
+            from.id, slot, index
+        ),
+        RepairProtocol::HighestWindowIndex(from, slot, index) => format!(
+            "repair highest window index request from {} for slot {} from shred {}",
+            from.id, slot, index
+        ),
+        RepairProtocol::Orphan(from, slot) => {
+            format!("repair orphan request from {} for slot {}", from.id, slot)
+        }
+        RepairProtocol::WindowIndexWithNonce(from, slot, index, nonce) => format!(
+            "repair window index request from {} for slot {} shred {} (nonce {})",
+            from.id, slot, index, nonce
+        ),
+        RepairProtocol::HighestWindowIndexWithNonce(from, slot, index, nonce) => format!(
+            "repair highest window index request from {} for slot {} from shred {} (nonce {})",
+            from.id, slot, index, nonce
+        ),
+        RepairProtocol::OrphanWithNonce(from, slot, nonce) => format!(
+            "repair orphan request from {} for slot {} (nonce {})",
+            from.id, slot, nonce
+        ),
+    };
+    // Repair requests are not signed
+    out.push_str(": unsigned\n");
+    if verbose {
+        let _ = writeln!(out, "{:#?}", request);
+    }
+    Some(out)
+}
+
+fn decode_shred(
+    data: &[u8],
+    nonce: Option<Nonce>,
+    leader: Option<&Pubkey>,
+    verbose: bool,
+) -> Option<String> {
+    if data.len() < SHRED_PAYLOAD_SIZE || data.len() > PACKET_DATA_SIZE {
+        return None;
+    }
+    let shred = Shred::new_from_serialized_shred(data.to_vec()).ok()?;
+
+    let mut out = String::new();
+    if let Some(nonce) = nonce {
+        let _ = write!(out, "repair response (nonce {}): ", nonce);
+    }
+    if shred.is_data() {
+        let _ = write!(
+            out,
+            "data shred slot {} index {} parent {} version {}{}{}",
+            shred.slot(),
+            shred.index(),
+            shred.parent(),
+            shred.version(),
+            if shred.data_complete() {
+                ", data complete"
+            } else {
+                ""
+            },
+            if shred.last_in_slot() {
+                ", last in slot"
+            } else {
+                ""
+            },
+        );
+    } else {
+        let _ = write!(
+            out,
+            "coding shred slot {} index {} version {}",
+            shred.slot(),
+            shred.index(),
+            shred.version(),
+        );
+    }
+    match leader {
+        Some(leader) => {
+            let _ = writeln!(out, ": {}", signature_status(shred.verify(leader)));
+        }
+        None => out.push_str(": signature not checked, pass --leader to check it\n"),
+    }
+    if verbose {
+        let _ = writeln!(out, "signature {}", shred.signature());
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_core::ping_pong::Ping;
+    use solana_ledger::shred::Shredder;
+    use solana_sdk::signature::{Keypair, Signer};
+
+    #[test]
+    fn test_decode_packet() {
+        let all_kinds = [PacketKind::Gossip, PacketKind::Repair, PacketKind::Turbine];
+        let keypair = Keypair::new();
+
+        let ping = Ping::new([7u8; 32], &keypair).unwrap();
+        let mut data = bincode::serialize(&Protocol::PingMessage(ping)).unwrap();
+        let out = decode_packet(&data, &all_kinds, None, false).unwrap();
+        assert_eq!(
+            out,
+            format!("gossip ping from {}: valid signature\n", keypair.pubkey())
+        );
+        // Corrupt the token
+        data[36] ^= 1;
+        let out = decode_packet(&data, &[PacketKind::Gossip], None, false).unwrap();
+        assert!(out.ends_with("INVALID SIGNATURE\n"));
+
+        let leader = Keypair::new();
+        let mut shred = Shred::new_from_data(5, 0, 1, Some(&[1, 2, 3]), true, true, 0, 0, 0);
+        Shredder::sign_shred(&leader, &mut shred);
+        let out = decode_packet(&shred.payload, &all_kinds, Some(&leader.pubkey()), false).unwrap();
+        assert_eq!(
+            out,
+            "data shred slot 5 index 0 parent 4 version 0, data complete, last in slot: valid signature\n"
+        );
+        assert!(decode_packet(&shred.payload, &[PacketKind::Gossip], None, false).is_none());
+
+        let mut response = shred.payload.clone();
+        response.extend_from_slice(&bincode::serialize(&42u32).unwrap());
+        let out = decode_packet(&response, &[PacketKind::Repair], None, false).unwrap();
+        assert!(out.starts_with("repair response (nonce 42): data shred slot 5 index 0"));
+    }
+}
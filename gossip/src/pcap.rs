@@ -0,0 +1,281 @@
+//! A minimal reader of the UDP datagrams in a libpcap capture file
+
+use std::{
+    convert::TryInto,
+    io::{self, Read},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const GLOBAL_HEADER_SIZE: usize = 24;
+const RECORD_HEADER_SIZE: usize = 16;
+// Guards against allocating for the length of a corrupt record
+const MAX_RECORD_SIZE: usize = 256 * 1024;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IPPROTO_UDP: u8 = 17;
+const UDP_HEADER_SIZE: usize = 8;
+
+/// A UDP datagram read from a capture
+#[derive(Debug, PartialEq)]
+pub struct Datagram {
+    /// Capture time in microseconds since the epoch
+    pub timestamp_us: u64,
+    pub src: SocketAddr,
+    pub dst: SocketAddr,
+    pub payload: Vec<u8>,
+}
+
+pub struct PcapReader<R> {
+    reader: R,
+    big_endian: bool,
+    nanos: bool,
+    link_type: u32,
+}
+
+impl<R: Read> PcapReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; GLOBAL_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        let (big_endian, nanos) = if magic == PCAP_MAGIC_MICROS {
+            (false, false)
+        } else if magic == PCAP_MAGIC_NANOS {
+            (false, true)
+        } else if magic.swap_bytes() == PCAP_MAGIC_MICROS {
+            (true, false)
+        } else if magic.swap_bytes() == PCAP_MAGIC_NANOS {
+            (true, true)
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "not a pcap file (magic {:#x}), pcapng is not supported",
+                    magic
+                ),
+            ));
+        };
+        let mut pcap_reader = Self {
+            reader,
+            big_endian,
+            nanos,
+            link_type: 0,
+        };
+        pcap_reader.link_type = pcap_reader.read_u32(&header[20..24]);
+        Ok(pcap_reader)
+    }
+
+    fn read_u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes.try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Returns the next UDP datagram of the capture, skipping over any other traffic
+    pub fn next_datagram(&mut self) -> io::Result<Option<Datagram>> {
+        loop {
+            let mut header = [0u8; RECORD_HEADER_SIZE];
+            match self.reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(err) => return Err(err),
+            }
+            let seconds = u64::from(self.read_u32(&header[0..4]));
+            let fraction = u64::from(self.read_u32(&header[4..8]));
+            let captured_len = self.read_u32(&header[8..12]) as usize;
+            if captured_len > MAX_RECORD_SIZE {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("record of {} bytes is too large", captured_len),
+                ));
+            }
+            let mut record = vec![0u8; captured_len];
+            self.reader.read_exact(&mut record)?;
+
+            let timestamp_us = seconds * 1_000_000
+                + if self.nanos {
+                    fraction / 1_000
+                } else {
+                    fraction
+                };
+            if let Some((src, dst, payload)) = parse_link_layer(self.link_type, &record) {
+                return Ok(Some(Datagram {
+                    timestamp_us,
+                    src,
+                    dst,
+                    payload: payload.to_vec(),
+                }));
+            }
+        }
+    }
+}
+
+fn read_u16_be(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        bytes.get(offset..offset + 2)?.try_into().unwrap(),
+    ))
+}
+
+fn parse_link_layer(link_type: u32, frame: &[u8]) -> Option<(SocketAddr, SocketAddr, &[u8])> {
+    match link_type {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ethertype = read_u16_be(frame, offset)?;
+            while ethertype == ETHERTYPE_VLAN {
+                offset += 4;
+                ethertype = read_u16_be(frame, offset)?;
+            }
+            parse_network_layer(Some(ethertype), frame.get(offset + 2..)?)
+        }
+        LINKTYPE_LINUX_SLL => parse_network_layer(read_u16_be(frame, 14), frame.get(16..)?),
+        // The BSD loopback header is the address family in host byte order
+        LINKTYPE_NULL => parse_network_layer(None, frame.get(4..)?),
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => parse_network_layer(None, frame),
+        _ => None,
+    }
+}
+
+fn parse_network_layer(
+    ethertype: Option<u16>,
+    packet: &[u8],
+) -> Option<(SocketAddr, SocketAddr, &[u8])> {
+    let version = packet.first()? >> 4;
+    match (ethertype, version) {
+        (Some(ETHERTYPE_IPV4), 4) | (None, 4) => {
+            let header_len = usize::from(packet[0] & 0x0f) * 4;
+            let total_len = usize::from(read_u16_be(packet, 2)?);
+            let fragment = read_u16_be(packet, 6)?;
+            // Only the first fragment carries the UDP header, and reassembly is not supported
+            if *packet.get(9)? != IPPROTO_UDP || fragment & 0x3fff != 0 {
+                return None;
+            }
+            let src: [u8; 4] = packet.get(12..16)?.try_into().unwrap();
+            let dst: [u8; 4] = packet.get(16..20)?.try_into().unwrap();
+            parse_udp(
+                IpAddr::V4(Ipv4Addr::from(src)),
+                IpAddr::V4(Ipv4Addr::from(dst)),
+                packet.get(header_len..total_len.min(packet.len()))?,
+            )
+        }
+        (Some(ETHERTYPE_IPV6), 6) | (None, 6) => {
+            const IPV6_HEADER_SIZE: usize = 40;
+            // Extension headers are not supported
+            if *packet.get(6)? != IPPROTO_UDP {
+                return None;
+            }
+            let payload_len = usize::from(read_u16_be(packet, 4)?);
+            let src: [u8; 16] = packet.get(8..24)?.try_into().unwrap();
+            let dst: [u8; 16] = packet.get(24..40)?.try_into().unwrap();
+            parse_udp(
+                IpAddr::V6(Ipv6Addr::from(src)),
+                IpAddr::V6(Ipv6Addr::from(dst)),
+                packet.get(IPV6_HEADER_SIZE..(IPV6_HEADER_SIZE + payload_len).min(packet.len()))?,
+            )
+        }
+        _ => None,
+    }
+}
+
+fn parse_udp(
+    src_ip: IpAddr,
+    dst_ip: IpAddr,
+    segment: &[u8],
+) -> Option<(SocketAddr, SocketAddr, &[u8])> {
+    let src_port = read_u16_be(segment, 0)?;
+    let dst_port = read_u16_be(segment, 2)?;
+    let len = usize::from(read_u16_be(segment, 4)?);
+    if len < UDP_HEADER_SIZE {
+        return None;
+    }
+    Some((
+        SocketAddr::new(src_ip, src_port),
+        SocketAddr::new(dst_ip, dst_port),
+        segment.get(UDP_HEADER_SIZE..len.min(segment.len()))?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4_udp_frame(payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+        let total_len = (20 + UDP_HEADER_SIZE + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&total_len.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 64, IPPROTO_UDP, 0, 0]);
+        frame.extend_from_slice(&[10, 0, 0, 1, 10, 0, 0, 2]);
+        frame.extend_from_slice(&8001u16.to_be_bytes());
+        frame.extend_from_slice(&8002u16.to_be_bytes());
+        frame.extend_from_slice(&((UDP_HEADER_SIZE + payload.len()) as u16).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_pcap_reader() {
+        let mut capture = vec![];
+        capture.extend_from_slice(&PCAP_MAGIC_MICROS.to_le_bytes());
+        capture.extend_from_slice(&[2, 0, 4, 0]);
+        capture.extend_from_slice(&[0u8; 12]);
+        capture.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+        let frames = vec![
+            ipv4_udp_frame(b"gossip"),
+            // An ARP frame is skipped
+            {
+                let mut frame = vec![0u8; 12];
+                frame.extend_from_slice(&[0x08, 0x06]);
+                frame.extend_from_slice(&[0u8; 28]);
+                frame
+            },
+            ipv4_udp_frame(b"repair"),
+        ];
+        for (i, frame) in frames.iter().enumerate() {
+            capture.extend_from_slice(&(i as u32).to_le_bytes());
+            capture.extend_from_slice(&5u32.to_le_bytes());
+            capture.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            capture.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            capture.extend_from_slice(frame);
+        }
+
+        let mut reader = PcapReader::new(&capture[..]).unwrap();
+        let src: SocketAddr = "10.0.0.1:8001".parse().unwrap();
+        let dst: SocketAddr = "10.0.0.2:8002".parse().unwrap();
+        assert_eq!(
+            reader.next_datagram().unwrap(),
+            Some(Datagram {
+                timestamp_us: 5,
+                src,
+                dst,
+                payload: b"gossip".to_vec(),
+            })
+        );
+        assert_eq!(
+            reader.next_datagram().unwrap(),
+            Some(Datagram {
+                timestamp_us: 2_000_005,
+                src,
+                dst,
+                payload: b"repair".to_vec(),
+            })
+        );
+        assert_eq!(reader.next_datagram().unwrap(), None);
+
+        assert!(PcapReader::new(&[0u8; GLOBAL_HEADER_SIZE][..]).is_err());
+    }
+}
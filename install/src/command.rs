@@ -1,5 +1,5 @@
 use crate::{
-    config::{Config, ExplicitRelease},
+    config::{Config, ExplicitRelease, ReleaseChannel},
     stop_process::stop_process,
     update_manifest::{SignedUpdateManifest, UpdateManifest},
 };
@@ -556,22 +556,80 @@ fn github_release_download_url(release_semver: &str) -> String {
     )
 }
 
-fn release_channel_download_url(release_channel: &str) -> String {
+fn release_channel_download_url(release_channel: &ReleaseChannel) -> String {
     format!(
-        "http://release.solana.com/{}/solana-release-{}.tar.bz2",
-        release_channel,
+        "{}/solana-release-{}.tar.bz2",
+        release_channel.url,
         crate::build_env::TARGET
     )
 }
 
-fn release_channel_version_url(release_channel: &str) -> String {
+fn release_channel_version_url(release_channel: &ReleaseChannel) -> String {
     format!(
-        "http://release.solana.com/{}/solana-release-{}.yml",
-        release_channel,
+        "{}/solana-release-{}.yml",
+        release_channel.url,
         crate::build_env::TARGET
     )
 }
 
+fn release_channel_manifest_url(release_channel: &ReleaseChannel) -> String {
+    format!(
+        "{}/solana-release-{}.manifest",
+        release_channel.url,
+        crate::build_env::TARGET
+    )
+}
+
+fn get_release_channel(config: &Config, release_channel: &str) -> Result<ReleaseChannel, String> {
+    config.release_channel(release_channel).ok_or_else(|| {
+        format!(
+            "Unknown release channel {}, see `solana-install channel add`",
+            release_channel
+        )
+    })
+}
+
+/// Downloads the update manifest of a signed release channel, and verifies it was signed by
+/// the channel's signer and is newer than `last_update_manifest`, the last one installed from
+/// the channel
+fn get_release_channel_manifest(
+    release_channel: &ReleaseChannel,
+    signer: &Pubkey,
+    last_update_manifest: Option<&UpdateManifest>,
+) -> Result<UpdateManifest, String> {
+    let manifest_url = release_channel_manifest_url(release_channel);
+    let (_temp_dir, temp_file, _temp_file_sha256) = download_to_temp(&manifest_url, None)
+        .map_err(|err| format!("Unable to download {}: {}", manifest_url, err))?;
+    let data =
+        fs::read(&temp_file).map_err(|err| format!("Unable to read {:?}: {}", temp_file, err))?;
+    verify_release_channel_manifest(&data, signer, last_update_manifest)
+        .map_err(|err| format!("Unable to verify {}: {}", manifest_url, err))
+}
+
+/// Deserializes the update manifest of a signed release channel, rejecting it unless it was
+/// signed by `signer` and is strictly newer than `last_update_manifest`. The last manifest
+/// itself is accepted again, so that its release can be reinstalled after a rollback
+fn verify_release_channel_manifest(
+    data: &[u8],
+    signer: &Pubkey,
+    last_update_manifest: Option<&UpdateManifest>,
+) -> Result<UpdateManifest, String> {
+    let update_manifest = SignedUpdateManifest::deserialize(signer, data)
+        .map_err(|err| err.to_string())?
+        .manifest;
+    if let Some(last_update_manifest) = last_update_manifest {
+        if update_manifest != *last_update_manifest
+            && update_manifest.timestamp_secs <= last_update_manifest.timestamp_secs
+        {
+            return Err(format!(
+                "manifest from {} is not newer than the installed one from {}",
+                update_manifest.timestamp_secs, last_update_manifest.timestamp_secs
+            ));
+        }
+    }
+    Ok(update_manifest)
+}
+
 pub fn info(
     config_file: &str,
     local_info_only: bool,
@@ -579,13 +637,16 @@ pub fn info(
 ) -> Result<Option<UpdateManifest>, String> {
     let config = Config::load(config_file)?;
 
+    let pinned_release = config.pinned_release();
+
     if eval {
         println!(
             "SOLANA_INSTALL_ACTIVE_RELEASE={}",
             &config.active_release_dir().to_str().unwrap_or("")
         );
-        config
-            .explicit_release
+        pinned_release
+            .map(ExplicitRelease::Semver)
+            .or(config.explicit_release)
             .map(|er| match er {
                 ExplicitRelease::Semver(semver) => semver,
                 ExplicitRelease::Channel(channel) => channel,
@@ -602,6 +663,21 @@ pub fn info(
         "Active release directory:",
         &config.active_release_dir().to_str().unwrap_or("?"),
     );
+    if let Ok(previous_release_dir) = fs::read_link(config.previous_release_dir()) {
+        println_name_value(
+            "Previous release directory:",
+            &previous_release_dir.to_str().unwrap_or("?"),
+        );
+    }
+
+    if let Some(release_semver) = &pinned_release {
+        println_name_value("Pinned release version:", &release_semver);
+        println_name_value(
+            &format!("{}Release URL:", BULLET),
+            &github_release_download_url(release_semver),
+        );
+        return Ok(None);
+    }
 
     if let Some(explicit_release) = &config.explicit_release {
         match explicit_release {
@@ -612,12 +688,28 @@ pub fn info(
                     &github_release_download_url(release_semver),
                 );
             }
-            ExplicitRelease::Channel(release_channel) => {
-                println_name_value(&format!("{}Release channel:", BULLET), &release_channel);
+            ExplicitRelease::Channel(release_channel_name) => {
                 println_name_value(
-                    &format!("{}Release URL:", BULLET),
-                    &release_channel_download_url(release_channel),
+                    &format!("{}Release channel:", BULLET),
+                    &release_channel_name,
                 );
+                match config.release_channel(release_channel_name) {
+                    Some(release_channel) => {
+                        println_name_value(
+                            &format!("{}Release URL:", BULLET),
+                            &release_channel_download_url(&release_channel),
+                        );
+                        if let Some(signer) = release_channel.signer {
+                            println_name_value(
+                                &format!("{}Release signer:", BULLET),
+                                &signer.to_string(),
+                            );
+                        }
+                    }
+                    None => {
+                        println_name_value(&format!("{}Release URL:", BULLET), "Unknown channel");
+                    }
+                }
             }
         }
         return Ok(None);
@@ -777,73 +869,116 @@ fn symlink_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> std::io::Resul
     std::os::unix::fs::symlink(src, dst)
 }
 
+/// Points the `link` symlink at `target`, returning the previous target of `link` if any.
+/// The new link is created next to `link` and renamed over it, so that `link` always refers
+/// to a complete release.
+#[cfg(not(windows))]
+fn switch_symlink(target: &Path, link: &Path) -> Result<Option<PathBuf>, String> {
+    let previous_target = fs::read_link(link).ok();
+    let temp_link = link.with_extension("tmp");
+    let _ = fs::remove_file(&temp_link);
+    symlink_dir(target, &temp_link)
+        .map_err(|err| format!("Unable to symlink {:?} to {:?}: {}", target, temp_link, err))?;
+    fs::rename(&temp_link, link)
+        .map_err(|err| format!("Unable to rename {:?} to {:?}: {}", temp_link, link, err))?;
+    Ok(previous_target)
+}
+// Windows cannot rename over a directory symlink, so the switch is not atomic there
+#[cfg(windows)]
+fn switch_symlink(target: &Path, link: &Path) -> Result<Option<PathBuf>, String> {
+    let previous_target = fs::read_link(link).ok();
+    let _ = fs::remove_dir_all(link);
+    symlink_dir(target, link)
+        .map_err(|err| format!("Unable to symlink {:?} to {:?}: {}", target, link, err))?;
+    Ok(previous_target)
+}
+
+/// Downloads and extracts the release archive at `download_url` into `release_dir`, unless
+/// a previous download into `release_dir` completed
+fn download_release(
+    download_url: &str,
+    expected_sha256: Option<&Hash>,
+    release_dir: &Path,
+) -> Result<(), String> {
+    if release_dir.join(".ok").exists() {
+        println!("{:?} is present, no download required.", release_dir);
+        return Ok(());
+    }
+    let (_temp_dir, temp_archive, _temp_archive_sha256) =
+        download_to_temp(download_url, expected_sha256)
+            .map_err(|err| format!("Unable to download {}: {}", download_url, err))?;
+    extract_release_archive(&temp_archive, release_dir).map_err(|err| {
+        format!(
+            "Unable to extract {:?} to {:?}: {}",
+            temp_archive, release_dir, err
+        )
+    })?;
+    let _ = fs::create_dir_all(release_dir.join(".ok"));
+    Ok(())
+}
+
 pub fn update(config_file: &str) -> Result<bool, String> {
     let mut config = Config::load(config_file)?;
     let update_manifest = info(config_file, false, false)?;
+    let previous_update_manifest = config.current_update_manifest.clone();
 
-    let release_dir = if let Some(explicit_release) = &config.explicit_release {
-        let (download_url, release_dir) = match explicit_release {
+    let explicit_release = config
+        .pinned_release()
+        .map(ExplicitRelease::Semver)
+        .or_else(|| config.explicit_release.clone());
+    let release_dir = if let Some(explicit_release) = &explicit_release {
+        match explicit_release {
             ExplicitRelease::Semver(release_semver) => {
-                let download_url = github_release_download_url(release_semver);
                 let release_dir = config.release_dir(&release_semver);
-                let download_url = if release_dir.join(".ok").exists() {
-                    // If this release_semver has already been successfully downloaded, no update
-                    // needed
-                    println!("{} is present, no download required.", release_semver);
-                    None
-                } else {
-                    Some(download_url)
-                };
-                (download_url, release_dir)
+                download_release(
+                    &github_release_download_url(release_semver),
+                    None,
+                    &release_dir,
+                )?;
+                release_dir
             }
-            ExplicitRelease::Channel(release_channel) => {
-                let release_dir = config.release_dir(&release_channel);
-                let current_release_version_yml =
-                    release_dir.join("solana-release").join("version.yml");
-                let download_url = Some(release_channel_download_url(release_channel));
-
-                if !current_release_version_yml.exists() {
-                    (download_url, release_dir)
+            ExplicitRelease::Channel(release_channel_name) => {
+                let release_channel = get_release_channel(&config, release_channel_name)?;
+                if let Some(signer) = &release_channel.signer {
+                    let update_manifest = get_release_channel_manifest(
+                        &release_channel,
+                        signer,
+                        config.release_channel_manifests.get(release_channel_name),
+                    )?;
+                    let release_dir =
+                        config.release_dir(&update_manifest.download_sha256.to_string());
+                    download_release(
+                        &update_manifest.download_url,
+                        Some(&update_manifest.download_sha256),
+                        &release_dir,
+                    )?;
+                    config
+                        .release_channel_manifests
+                        .insert(release_channel_name.clone(), update_manifest.clone());
+                    config.current_update_manifest = Some(update_manifest);
+                    release_dir
                 } else {
-                    let version_url = release_channel_version_url(release_channel);
-
-                    let (_temp_dir, temp_file, _temp_archive_sha256) =
+                    // Each build of the channel is kept in its own directory, so that the
+                    // previous build remains available for rollback
+                    let version_url = release_channel_version_url(&release_channel);
+                    let (_temp_dir, temp_file, _temp_file_sha256) =
                         download_to_temp(&version_url, None).map_err(|err| {
                             format!("Unable to download {}: {}", version_url, err)
                         })?;
-
-                    let update_release_version = load_release_version(&temp_file)?;
-                    let current_release_version =
-                        load_release_version(&current_release_version_yml)?;
-
-                    if update_release_version.commit == current_release_version.commit {
-                        // Same commit, no update required
-                        println!(
-                            "Latest {} build is already present, no download required.",
-                            release_channel
-                        );
-                        (None, release_dir)
-                    } else {
-                        (download_url, release_dir)
-                    }
+                    let release_version = load_release_version(&temp_file)?;
+                    let release_dir = config.release_dir(&format!(
+                        "{}-{}",
+                        release_channel_name, release_version.commit
+                    ));
+                    download_release(
+                        &release_channel_download_url(&release_channel),
+                        None,
+                        &release_dir,
+                    )?;
+                    release_dir
                 }
             }
-        };
-
-        if let Some(download_url) = download_url {
-            let (_temp_dir, temp_archive, _temp_archive_sha256) =
-                download_to_temp(&download_url, None)
-                    .map_err(|err| format!("Unable to download {}: {}", download_url, err))?;
-            extract_release_archive(&temp_archive, &release_dir).map_err(|err| {
-                format!(
-                    "Unable to extract {:?} to {:?}: {}",
-                    temp_archive, release_dir, err
-                )
-            })?;
-            let _ = fs::create_dir_all(release_dir.join(".ok"));
         }
-
-        release_dir
     } else {
         if update_manifest.is_none() {
             return Ok(false);
@@ -894,19 +1029,19 @@ pub fn update(config_file: &str) -> Result<bool, String> {
         return Err(format!("Incompatible update target: {}", release_target));
     }
 
-    let _ = fs::remove_dir_all(config.active_release_dir());
-    symlink_dir(
-        release_dir.join("solana-release"),
-        config.active_release_dir(),
-    )
-    .map_err(|err| {
-        format!(
-            "Unable to symlink {:?} to {:?}: {}",
-            release_dir,
-            config.active_release_dir(),
-            err
-        )
-    })?;
+    let release_dir = release_dir.join("solana-release");
+    if fs::read_link(config.active_release_dir()).ok().as_ref() == Some(&release_dir) {
+        println!(
+            "  {}{}",
+            INFORMATION,
+            style("Release is already active").bold()
+        );
+        return Ok(false);
+    }
+    if let Some(previous_release_dir) = switch_symlink(&release_dir, config.active_release_dir())? {
+        switch_symlink(&previous_release_dir, &config.previous_release_dir())?;
+        config.previous_update_manifest = previous_update_manifest;
+    }
 
     config.save(config_file)?;
 
@@ -914,6 +1049,148 @@ pub fn update(config_file: &str) -> Result<bool, String> {
     Ok(true)
 }
 
+/// Switches back to the release that was active before the last update or rollback
+pub fn rollback(config_file: &str) -> Result<(), String> {
+    let mut config = Config::load(config_file)?;
+    let previous_release_dir = fs::read_link(config.previous_release_dir())
+        .map_err(|_| "No previous release to roll back to".to_string())?;
+    if !previous_release_dir.exists() {
+        return Err(format!(
+            "Previous release {:?} no longer exists",
+            previous_release_dir
+        ));
+    }
+
+    if let Some(release_dir) = switch_symlink(&previous_release_dir, config.active_release_dir())? {
+        switch_symlink(&release_dir, &config.previous_release_dir())?;
+    }
+    let current_update_manifest = config.current_update_manifest.take();
+    config.current_update_manifest = config.previous_update_manifest.take();
+    config.previous_update_manifest = current_update_manifest;
+    config.save(config_file)?;
+
+    println_name_value(
+        "Active release directory:",
+        &format!("{:?}", previous_release_dir),
+    );
+    println!("  {}{}", SPARKLE, style("Rollback successful").bold());
+    if config.pinned_release().is_none() {
+        println!(
+            "  {}{}",
+            INFORMATION,
+            style("Run `solana-install pin` to keep the next update from replacing this release")
+                .italic()
+        );
+    }
+    Ok(())
+}
+
+/// Pins the data directory to the given release and installs it
+pub fn pin(config_file: &str, release_semver: &str) -> Result<(), String> {
+    let config = Config::load(config_file)?;
+    config.pin_release(release_semver)?;
+    println_name_value("Pinned release version:", release_semver);
+    update(config_file).map(|_| ())
+}
+
+pub fn unpin(config_file: &str) -> Result<(), String> {
+    let config = Config::load(config_file)?;
+    if config.unpin_release()? {
+        println!("  {}{}", SPARKLE, style("Release unpinned").bold());
+    } else {
+        println!("  {}{}", INFORMATION, style("No release is pinned").bold());
+    }
+    Ok(())
+}
+
+pub fn channel_add(
+    config_file: &str,
+    name: &str,
+    url: &str,
+    signer: Option<Pubkey>,
+) -> Result<(), String> {
+    let mut config = Config::load(config_file)?;
+    config.channels.insert(
+        name.to_string(),
+        ReleaseChannel {
+            url: url.trim_end_matches('/').to_string(),
+            signer,
+        },
+    );
+    config.save(config_file)
+}
+
+pub fn channel_remove(config_file: &str, name: &str) -> Result<(), String> {
+    let mut config = Config::load(config_file)?;
+    if config.channels.remove(name).is_none() {
+        return Err(format!("Release channel {} was not added", name));
+    }
+    config.save(config_file)
+}
+
+pub fn channel_list(config_file: &str) -> Result<(), String> {
+    let config = Config::load(config_file)?;
+    let names = crate::defaults::RELEASE_CHANNELS
+        .iter()
+        .map(|name| name.to_string())
+        .chain(config.channels.keys().cloned())
+        .collect::<std::collections::BTreeSet<_>>();
+    for name in names {
+        let release_channel = config.release_channel(&name).unwrap();
+        println_name_value(&format!("{}:", name), &release_channel.url);
+        if let Some(signer) = release_channel.signer {
+            println_name_value(&format!("{}signer:", BULLET), &signer.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Writes an update manifest for the release archive at `download_url`, signed by
+/// `update_manifest_keypair_file`, to be published as the manifest of a signed release channel
+pub fn sign_manifest(
+    download_url: &str,
+    update_manifest_keypair_file: &str,
+    output_file: &str,
+) -> Result<(), String> {
+    let update_manifest_keypair = read_keypair_file(update_manifest_keypair_file)
+        .map_err(|err| format!("Unable to read {}: {}", update_manifest_keypair_file, err))?;
+
+    let (temp_dir, temp_archive, temp_archive_sha256) = download_to_temp(download_url, None)
+        .map_err(|err| format!("Unable to download {}: {}", download_url, err))?;
+    let temp_release_dir = temp_dir.path().join("archive");
+    extract_release_archive(&temp_archive, &temp_release_dir).map_err(|err| {
+        format!(
+            "Unable to extract {:?} into {:?}: {}",
+            temp_archive, temp_release_dir, err
+        )
+    })?;
+    let release_target = load_release_target(&temp_release_dir).map_err(|err| {
+        format!(
+            "Unable to load release target from {:?}: {}",
+            temp_release_dir, err
+        )
+    })?;
+    println_name_value("Release target:", &release_target);
+
+    let mut update_manifest = SignedUpdateManifest {
+        account_pubkey: update_manifest_keypair.pubkey(),
+        ..SignedUpdateManifest::default()
+    };
+    update_manifest.manifest.timestamp_secs = timestamp_secs();
+    update_manifest.manifest.download_url = download_url.to_string();
+    update_manifest.manifest.download_sha256 = temp_archive_sha256;
+    update_manifest.sign(&update_manifest_keypair);
+    assert!(update_manifest.verify());
+
+    let data = bincode::serialize(&update_manifest)
+        .map_err(|err| format!("Unable to serialize update manifest: {}", err))?;
+    fs::write(output_file, data)
+        .map_err(|err| format!("Unable to write {}: {}", output_file, err))?;
+    println_name_value("Signer:", &update_manifest_keypair.pubkey().to_string());
+    println!("  {}{}", SPARKLE, style("Manifest signed").bold());
+    Ok(())
+}
+
 pub fn run(
     config_file: &str,
     program_name: &str,
@@ -972,7 +1249,10 @@ pub fn run(
             }
         };
 
-        if config.explicit_release.is_none() && now.elapsed().as_secs() > config.update_poll_secs {
+        if config.explicit_release.is_none()
+            && config.pinned_release().is_none()
+            && now.elapsed().as_secs() > config.update_poll_secs
+        {
             match update(config_file) {
                 Ok(true) => {
                     // Update successful, kill current process so it will be restart
@@ -1001,3 +1281,108 @@ pub fn run(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_manifest_data(keypair: &Keypair, update_manifest: &UpdateManifest) -> Vec<u8> {
+        let mut signed_update_manifest = SignedUpdateManifest {
+            manifest: update_manifest.clone(),
+            account_pubkey: keypair.pubkey(),
+            ..SignedUpdateManifest::default()
+        };
+        signed_update_manifest.sign(keypair);
+        bincode::serialize(&signed_update_manifest).unwrap()
+    }
+
+    #[test]
+    fn test_verify_release_channel_manifest() {
+        let signer = Keypair::new();
+        let last_update_manifest = UpdateManifest {
+            timestamp_secs: 1_000,
+            download_url: "https://example.com/1.tar.bz2".to_string(),
+            download_sha256: Hash::default(),
+        };
+        let update_manifest = UpdateManifest {
+            timestamp_secs: 2_000,
+            download_url: "https://example.com/2.tar.bz2".to_string(),
+            ..last_update_manifest.clone()
+        };
+        let data = signed_manifest_data(&signer, &update_manifest);
+        assert_eq!(
+            verify_release_channel_manifest(&data, &signer.pubkey(), None),
+            Ok(update_manifest.clone())
+        );
+        assert_eq!(
+            verify_release_channel_manifest(&data, &signer.pubkey(), Some(&last_update_manifest)),
+            Ok(update_manifest.clone())
+        );
+        // The installed manifest is accepted again
+        assert_eq!(
+            verify_release_channel_manifest(&data, &signer.pubkey(), Some(&update_manifest)),
+            Ok(update_manifest.clone())
+        );
+    }
+
+    #[test]
+    fn test_verify_release_channel_manifest_downgrade() {
+        let signer = Keypair::new();
+        let last_update_manifest = UpdateManifest {
+            timestamp_secs: 2_000,
+            download_url: "https://example.com/2.tar.bz2".to_string(),
+            download_sha256: Hash::default(),
+        };
+
+        // Older manifests are rejected, even though the signer signed them
+        let older_update_manifest = UpdateManifest {
+            timestamp_secs: 1_000,
+            download_url: "https://example.com/1.tar.bz2".to_string(),
+            ..last_update_manifest.clone()
+        };
+        let data = signed_manifest_data(&signer, &older_update_manifest);
+        assert!(verify_release_channel_manifest(
+            &data,
+            &signer.pubkey(),
+            Some(&last_update_manifest)
+        )
+        .is_err());
+
+        // And so are other manifests with the same timestamp
+        let other_update_manifest = UpdateManifest {
+            download_url: "https://example.com/other.tar.bz2".to_string(),
+            ..last_update_manifest.clone()
+        };
+        let data = signed_manifest_data(&signer, &other_update_manifest);
+        assert!(verify_release_channel_manifest(
+            &data,
+            &signer.pubkey(),
+            Some(&last_update_manifest)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_verify_release_channel_manifest_bad_signature() {
+        let signer = Keypair::new();
+        let update_manifest = UpdateManifest {
+            timestamp_secs: 1_000,
+            download_url: "https://example.com/1.tar.bz2".to_string(),
+            download_sha256: Hash::default(),
+        };
+
+        // Signed by another key
+        let data = signed_manifest_data(&Keypair::new(), &update_manifest);
+        assert!(verify_release_channel_manifest(&data, &signer.pubkey(), None).is_err());
+
+        // Tampered with after signing
+        let mut signed_update_manifest: SignedUpdateManifest =
+            bincode::deserialize(&signed_manifest_data(&signer, &update_manifest)).unwrap();
+        signed_update_manifest.manifest.download_url = "https://example.com/evil.tar.bz2".into();
+        let data = bincode::serialize(&signed_update_manifest).unwrap();
+        assert!(verify_release_channel_manifest(&data, &signer.pubkey(), None).is_err());
+
+        // Not a manifest at all
+        assert!(verify_release_channel_manifest(&[1, 2, 3], &signer.pubkey(), None).is_err());
+    }
+}
@@ -1,16 +1,26 @@
-use crate::update_manifest::UpdateManifest;
+use crate::{defaults, update_manifest::UpdateManifest};
 use serde_derive::{Deserialize, Serialize};
 use solana_sdk::pubkey::Pubkey;
-use std::fs::{create_dir_all, File};
+use std::collections::BTreeMap;
+use std::fs::{self, create_dir_all, File};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum ExplicitRelease {
     Semver(String),
     Channel(String),
 }
 
+/// A named source of release builds
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct ReleaseChannel {
+    // Base URL of the channel's release archives and version files
+    pub url: String,
+    // If set, releases must come with an update manifest signed by this key
+    pub signer: Option<Pubkey>,
+}
+
 #[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
 pub struct Config {
     pub json_rpc_url: String,
@@ -18,6 +28,14 @@ pub struct Config {
     pub current_update_manifest: Option<UpdateManifest>,
     pub update_poll_secs: u64,
     pub explicit_release: Option<ExplicitRelease>,
+    #[serde(default)]
+    pub previous_update_manifest: Option<UpdateManifest>,
+    #[serde(default)]
+    pub channels: BTreeMap<String, ReleaseChannel>,
+    // The newest update manifest installed from each signed release channel, which the
+    // channel's later manifests must be newer than
+    #[serde(default)]
+    pub release_channel_manifests: BTreeMap<String, UpdateManifest>,
     releases_dir: PathBuf,
     active_release_dir: PathBuf,
 }
@@ -35,6 +53,9 @@ impl Config {
             current_update_manifest: None,
             update_poll_secs: 60 * 60, // check for updates once an hour
             explicit_release,
+            previous_update_manifest: None,
+            channels: BTreeMap::new(),
+            release_channel_manifests: BTreeMap::new(),
             releases_dir: PathBuf::from(data_dir).join("releases"),
             active_release_dir: PathBuf::from(data_dir).join("active_release"),
         }
@@ -77,7 +98,63 @@ impl Config {
         self.active_release_dir.join("bin")
     }
 
+    /// Looks up a release channel by name. Configured channels take precedence over the
+    /// builtin ones, so that a builtin channel can be given a signer.
+    pub fn release_channel(&self, name: &str) -> Option<ReleaseChannel> {
+        self.channels.get(name).cloned().or_else(|| {
+            if defaults::RELEASE_CHANNELS.contains(&name) {
+                Some(ReleaseChannel {
+                    url: format!("{}/{}", defaults::RELEASE_CHANNEL_URL, name),
+                    signer: None,
+                })
+            } else {
+                None
+            }
+        })
+    }
+
+    /// The release that was active before the last update, for rollback
+    pub fn previous_release_dir(&self) -> PathBuf {
+        self.active_release_dir.with_file_name("previous_release")
+    }
+
     pub fn release_dir(&self, release_id: &str) -> PathBuf {
         self.releases_dir.join(release_id)
     }
+
+    // The pinned release is kept in the data directory rather than the config file, so that it
+    // applies to every config sharing the data directory
+    fn pinned_release_file(&self) -> PathBuf {
+        self.active_release_dir.with_file_name("pinned_release")
+    }
+
+    pub fn pinned_release(&self) -> Option<String> {
+        fs::read_to_string(self.pinned_release_file())
+            .ok()
+            .map(|release_semver| release_semver.trim().to_string())
+            .filter(|release_semver| !release_semver.is_empty())
+    }
+
+    pub fn pin_release(&self, release_semver: &str) -> Result<(), String> {
+        let pinned_release_file = self.pinned_release_file();
+        if let Some(data_dir) = pinned_release_file.parent() {
+            create_dir_all(data_dir)
+                .map_err(|err| format!("Unable to create {:?}: {}", data_dir, err))?;
+        }
+        fs::write(&pinned_release_file, format!("{}\n", release_semver))
+            .map_err(|err| format!("Unable to write {:?}: {}", pinned_release_file, err))
+    }
+
+    /// Returns false if no release was pinned
+    pub fn unpin_release(&self) -> Result<bool, String> {
+        let pinned_release_file = self.pinned_release_file();
+        match fs::remove_file(&pinned_release_file) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(format!(
+                "Unable to remove {:?}: {}",
+                pinned_release_file, err
+            )),
+        }
+    }
 }
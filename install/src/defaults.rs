@@ -1,4 +1,6 @@
 pub const JSON_RPC_URL: &str = "http://devnet.solana.com";
+pub const RELEASE_CHANNEL_URL: &str = "http://release.solana.com";
+pub const RELEASE_CHANNELS: &[&str] = &["edge", "beta", "stable"];
 
 lazy_static! {
     pub static ref CONFIG_FILE: Option<String> = {
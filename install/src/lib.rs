@@ -21,10 +21,20 @@ pub fn is_semver(semver: &str) -> Result<(), String> {
     }
 }
 
+pub fn is_release_semver(string: String) -> Result<(), String> {
+    is_semver(string.trim_start_matches('v'))
+}
+
 pub fn is_release_channel(channel: &str) -> Result<(), String> {
-    match channel {
-        "edge" | "beta" | "stable" => Ok(()),
-        _ => Err(format!("Invalid release channel {}", channel)),
+    // Besides the builtin channels, any channel added with `solana-install channel add`
+    if !channel.is_empty()
+        && channel
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(())
+    } else {
+        Err(format!("Invalid release channel {}", channel))
     }
 }
 
@@ -213,6 +223,114 @@ pub fn main() -> Result<(), String> {
                 .about("checks for an update, and if available downloads and applies it")
                 .setting(AppSettings::DisableVersion),
         )
+        .subcommand(
+            SubCommand::with_name("rollback")
+                .about("switches back to the release that was active before the last update")
+                .after_help(
+                    "A later update replaces the rolled back release again, \
+                     unless a release is pinned",
+                )
+                .setting(AppSettings::DisableVersion),
+        )
+        .subcommand(
+            SubCommand::with_name("pin")
+                .about("pins the installation to a release version and installs it")
+                .after_help(
+                    "The pinned release applies to every configuration using the data \
+                     directory, and is not updated until unpinned",
+                )
+                .setting(AppSettings::DisableVersion)
+                .arg(
+                    Arg::with_name("release_semver")
+                        .value_name("release")
+                        .index(1)
+                        .required(true)
+                        .validator(is_release_semver)
+                        .help("The release version to pin"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("unpin")
+                .about("resumes updates of a pinned installation")
+                .setting(AppSettings::DisableVersion),
+        )
+        .subcommand(
+            SubCommand::with_name("channel")
+                .about("manages release channels")
+                .setting(AppSettings::DisableVersion)
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("adds a release channel, or replaces one with the same name")
+                        .arg(
+                            Arg::with_name("name")
+                                .index(1)
+                                .required(true)
+                                .validator(|name| is_release_channel(&name))
+                                .help("Name of the release channel"),
+                        )
+                        .arg(
+                            Arg::with_name("url")
+                                .index(2)
+                                .required(true)
+                                .validator(is_url)
+                                .help("Base URL of the channel's release archives"),
+                        )
+                        .arg(
+                            Arg::with_name("signer")
+                                .long("signer")
+                                .value_name("PUBKEY")
+                                .takes_value(true)
+                                .validator(is_pubkey)
+                                .help(
+                                    "Only install releases with an update manifest \
+                                     signed by this key",
+                                ),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("removes a release channel")
+                        .arg(
+                            Arg::with_name("name")
+                                .index(1)
+                                .required(true)
+                                .help("Name of the release channel"),
+                        ),
+                )
+                .subcommand(SubCommand::with_name("list").about("lists the release channels")),
+        )
+        .subcommand(
+            SubCommand::with_name("sign-manifest")
+                .about("signs the update manifest of a release for a signed release channel")
+                .after_help(
+                    "Publish the manifest as solana-release-<target>.manifest next to the \
+                     release archive",
+                )
+                .setting(AppSettings::DisableVersion)
+                .arg(
+                    Arg::with_name("download_url")
+                        .index(1)
+                        .required(true)
+                        .validator(is_url)
+                        .help("URL to the solana release archive"),
+                )
+                .arg(
+                    Arg::with_name("update_manifest_keypair_file")
+                        .index(2)
+                        .required(true)
+                        .help("Keypair file of the channel signer (/path/to/keypair.json)"),
+                )
+                .arg(
+                    Arg::with_name("output_file")
+                        .short("o")
+                        .long("output")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .required(true)
+                        .help("File to write the signed manifest to"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("run")
                 .about("Runs a program while periodically checking and applying software updates")
@@ -256,6 +374,33 @@ pub fn main() -> Result<(), String> {
             )
         }
         ("update", Some(_matches)) => command::update(config_file).map(|_| ()),
+        ("rollback", Some(_matches)) => command::rollback(config_file),
+        ("pin", Some(matches)) => {
+            let release_semver = matches.value_of("release_semver").unwrap();
+            command::pin(config_file, release_semver.trim_start_matches('v'))
+        }
+        ("unpin", Some(_matches)) => command::unpin(config_file),
+        ("channel", Some(matches)) => match matches.subcommand() {
+            ("add", Some(matches)) => {
+                let name = matches.value_of("name").unwrap();
+                let url = matches.value_of("url").unwrap();
+                let signer = pubkey_of(&matches, "signer");
+                command::channel_add(config_file, name, url, signer)
+            }
+            ("remove", Some(matches)) => {
+                let name = matches.value_of("name").unwrap();
+                command::channel_remove(config_file, name)
+            }
+            ("list", Some(_matches)) => command::channel_list(config_file),
+            _ => unreachable!(),
+        },
+        ("sign-manifest", Some(matches)) => {
+            let download_url = matches.value_of("download_url").unwrap();
+            let update_manifest_keypair_file =
+                matches.value_of("update_manifest_keypair_file").unwrap();
+            let output_file = matches.value_of("output_file").unwrap();
+            command::sign_manifest(download_url, update_manifest_keypair_file, output_file)
+        }
         ("run", Some(matches)) => {
             let program_name = matches.value_of("program_name").unwrap();
             let program_arguments = matches
@@ -0,0 +1,227 @@
+/// Digests of the types serialized into the ledger, snapshots and transactions, printed by
+/// `abi-check` so that the digests of two builds can be compared before a release.  The digests
+/// are the ones `#[frozen_abi]` checks, which can only be computed by a build with
+/// RUSTC_WITH_SPECIALIZATION; other builds print the digests pinned on the types instead
+use clap::{value_t, ArgMatches};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+/// Digest by type name
+pub type AbiDigests = BTreeMap<String, String>;
+
+/// The `#[frozen_abi]` digests pinned on the types `abi_digests()` covers
+const PINNED_ABI_DIGESTS: &[(&str, &str)] = &[
+    (
+        "solana_sdk::transaction::Transaction",
+        "EGPL8qfT6of8UDKUTPQfcCADiRrpVxKXmULWtNdfjbQ8",
+    ),
+    (
+        "solana_program::system_instruction::SystemInstruction",
+        "2xnDcizcPKKR7b624FeuuPd1zj5bmnkmVsBWgoKPTh4w",
+    ),
+    (
+        "solana_sdk::account::Account",
+        "727tKRcoDvPiAsXxfvfsUauvZ4tLSw2WSw4HQfRQJ9Xx",
+    ),
+    (
+        "solana_sdk::genesis_config::GenesisConfig",
+        "VxfEg5DXq5czYouMdcCbqDzUE8jGi3iSDSjzrrWp5iG",
+    ),
+    (
+        "solana_vote_program::vote_state::Vote",
+        "Ch2vVEwos2EjAVqSHCyJjnN2MNX1yrpapZTGhMSCjWUH",
+    ),
+    (
+        "solana_vote_program::vote_state::VoteState",
+        "331ZmXrmsUcwbKhzR3C1UEU6uNwZr48ExE54JDKGWA4w",
+    ),
+    (
+        "solana_runtime::stakes::Stakes",
+        "AQoDxRSevQk6E2AshnkHSNPu3mHop3a6rR9vpThY6e8q",
+    ),
+    (
+        "solana_runtime::epoch_stakes::EpochStakes",
+        "75bMnDQJZrYicefQ4JwNG1b5LviZKTt7WwFWfXALdfaq",
+    ),
+    (
+        "solana_ledger::entry::Entry",
+        "FwFd26hj71SmP4hfQqUWoH9WYpCaAeNXfzqcxbkxpoNn",
+    ),
+    (
+        "solana_ledger::shred::ShredType",
+        "DMgAH8XuCbyj8iajbog6fTGB5VsVHQR8EWMYBq5b2kRX",
+    ),
+    (
+        "solana_ledger::erasure::ErasureConfig",
+        "J51p5QyLtkkEqdchBW3RgRCEvqnZuxfn6VTEHzUEA9Wu",
+    ),
+    (
+        "solana_ledger::blockstore_meta::SlotMeta",
+        "DBRxERf9gS7cmKDc3sbtQVp7HUdsgAHfSRH5vw8JAEB1",
+    ),
+    (
+        "solana_ledger::blockstore_meta::Index",
+        "CPBXzFY54nWXSxcrLeSeJ3k8uwMXEmq68fLSorSEJUNc",
+    ),
+    (
+        "solana_ledger::blockstore_meta::ErasureMeta",
+        "5d68LeDAmhYF9Gp63KbymiEmaM4PhDSxMW4pncR1j3WQ",
+    ),
+    (
+        "solana_ledger::blockstore_meta::DuplicateSlotProof",
+        "HxnCeGiqGHSY6Z15qWfmfjQLdXdeR89PNedj23bW5urZ",
+    ),
+    (
+        "solana_ledger::blockstore_meta::TransactionStatusIndexMeta",
+        "4wBVck1woAWVfgzzsfEorgs5iGbbRyYUXa7z9UW7NrWT",
+    ),
+    (
+        "solana_ledger::blockstore_meta::AddressSignatureMeta",
+        "5BnczqCfocUJGXr3a8qgz5VpVqgz5WE3AEn6L3xwAYZr",
+    ),
+    (
+        "solana_ledger::blockstore_meta::PerfSample",
+        "5NLLYsxqBrJSvzL4eF13rpfcVisjzeMkazhddJ6A7JrJ",
+    ),
+];
+
+pub fn pinned_abi_digests() -> AbiDigests {
+    PINNED_ABI_DIGESTS
+        .iter()
+        .map(|(type_name, digest)| (type_name.to_string(), digest.to_string()))
+        .collect()
+}
+
+#[cfg(RUSTC_WITH_SPECIALIZATION)]
+pub fn abi_digests() -> Result<AbiDigests, String> {
+    use {
+        serde::Serialize,
+        solana_frozen_abi::{abi_digester::digest_abi, abi_example::AbiExample},
+        solana_ledger::{blockstore_meta, entry::Entry, erasure::ErasureConfig, shred::ShredType},
+        solana_runtime::{epoch_stakes::EpochStakes, stakes::Stakes},
+        solana_sdk::{
+            account::Account, genesis_config::GenesisConfig, system_instruction::SystemInstruction,
+            transaction::Transaction,
+        },
+        solana_vote_program::vote_state::{Vote, VoteState},
+    };
+
+    fn insert<T: AbiExample + Serialize>(digests: &mut AbiDigests) -> Result<(), String> {
+        let type_name = std::any::type_name::<T>();
+        let digest = digest_abi::<T>()
+            .map_err(|err| format!("Failed to digest {}: {:?}", type_name, err))?;
+        digests.insert(type_name.to_string(), digest.to_string());
+        Ok(())
+    }
+
+    let mut digests = AbiDigests::new();
+    // Transactions and accounts
+    insert::<Transaction>(&mut digests)?;
+    insert::<SystemInstruction>(&mut digests)?;
+    insert::<Account>(&mut digests)?;
+    insert::<GenesisConfig>(&mut digests)?;
+    insert::<Vote>(&mut digests)?;
+    insert::<VoteState>(&mut digests)?;
+    // Snapshots
+    insert::<Stakes>(&mut digests)?;
+    insert::<EpochStakes>(&mut digests)?;
+    // Ledger
+    insert::<Entry>(&mut digests)?;
+    insert::<ShredType>(&mut digests)?;
+    insert::<ErasureConfig>(&mut digests)?;
+    insert::<blockstore_meta::SlotMeta>(&mut digests)?;
+    insert::<blockstore_meta::Index>(&mut digests)?;
+    insert::<blockstore_meta::ErasureMeta>(&mut digests)?;
+    insert::<blockstore_meta::DuplicateSlotProof>(&mut digests)?;
+    insert::<blockstore_meta::TransactionStatusIndexMeta>(&mut digests)?;
+    insert::<blockstore_meta::AddressSignatureMeta>(&mut digests)?;
+    insert::<blockstore_meta::PerfSample>(&mut digests)?;
+    Ok(digests)
+}
+
+#[cfg(not(RUSTC_WITH_SPECIALIZATION))]
+pub fn abi_digests() -> Result<AbiDigests, String> {
+    // The frozen-abi tests of nightly builds keep the types at their pinned digests
+    Ok(pinned_abi_digests())
+}
+
+/// Reads digests in the format `abi-check` prints them, one "<type name> <digest>" per line
+pub fn read_abi_digests(path: &Path) -> Result<AbiDigests, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next(), fields.next()) {
+                (Some(type_name), Some(digest), None) => {
+                    Ok((type_name.to_string(), digest.to_string()))
+                }
+                _ => Err(format!("Invalid line in {}: {}", path.display(), line)),
+            }
+        })
+        .collect()
+}
+
+/// Describes each type whose digest differs between `expected` and `actual`
+pub fn compare_abi_digests(expected: &AbiDigests, actual: &AbiDigests) -> Vec<String> {
+    let mut differences = vec![];
+    for (type_name, expected_digest) in expected {
+        match actual.get(type_name) {
+            Some(digest) if digest == expected_digest => {}
+            Some(digest) => differences.push(format!(
+                "{}: digest changed from {} to {}",
+                type_name, expected_digest, digest
+            )),
+            None => differences.push(format!("{}: no longer digested", type_name)),
+        }
+    }
+    for type_name in actual.keys() {
+        if !expected.contains_key(type_name) {
+            differences.push(format!("{}: newly digested", type_name));
+        }
+    }
+    differences
+}
+
+pub fn abi_check_process_command(matches: &ArgMatches<'_>) {
+    let digests = abi_digests().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    });
+    for (type_name, digest) in &digests {
+        println!("{} {}", type_name, digest);
+    }
+    if cfg!(RUSTC_WITH_SPECIALIZATION) {
+        // Types whose pin was updated without updating PINNED_ABI_DIGESTS
+        let differences = compare_abi_digests(&pinned_abi_digests(), &digests);
+        if !differences.is_empty() {
+            for difference in differences {
+                eprintln!("Pinned {}", difference);
+            }
+            exit(1);
+        }
+    } else {
+        eprintln!("Printed the pinned digests, build with a nightly toolchain to compute them");
+    }
+
+    if let Ok(path) = value_t!(matches, "compare", PathBuf) {
+        let expected = read_abi_digests(&path).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        });
+        let differences = compare_abi_digests(&expected, &digests);
+        if !differences.is_empty() {
+            for difference in differences {
+                eprintln!("{}", difference);
+            }
+            exit(1);
+        }
+        eprintln!("All digests match {}", path.display());
+    }
+}
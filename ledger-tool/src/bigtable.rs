@@ -5,10 +5,15 @@ use solana_clap_utils::{
     input_validators::{is_slot, is_valid_pubkey},
 };
 use solana_cli_output::display::println_transaction;
-use solana_ledger::{blockstore::Blockstore, blockstore_db::AccessType};
+use solana_ledger::{
+    bigtable_upload::{get_bigtable_slots, upload_blocks},
+    blockstore::Blockstore,
+    blockstore_db::AccessType,
+};
 use solana_sdk::{clock::Slot, pubkey::Pubkey, signature::Signature};
 use solana_transaction_status::ConfirmedBlock;
 use std::{
+    collections::HashSet,
     path::Path,
     process::exit,
     result::Result,
@@ -20,6 +25,7 @@ async fn upload(
     starting_slot: Slot,
     ending_slot: Option<Slot>,
     allow_missing_metadata: bool,
+    force_reupload: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let bigtable = solana_storage_bigtable::LedgerStorage::new(false, None)
         .await
@@ -31,6 +37,242 @@ async fn upload(
         starting_slot,
         ending_slot,
         allow_missing_metadata,
+        force_reupload,
+        Arc::new(AtomicBool::new(false)),
+    )
+    .await
+}
+
+fn rooted_slots(
+    blockstore: &Blockstore,
+    starting_slot: Slot,
+    ending_slot: Slot,
+) -> Result<Vec<Slot>, Box<dyn std::error::Error>> {
+    Ok(blockstore
+        .rooted_slot_iterator(starting_slot)
+        .map_err(|err| {
+            format!(
+                "Failed to load roots starting from slot {}: {:?}",
+                starting_slot, err
+            )
+        })?
+        .take_while(|slot| *slot <= ending_slot)
+        .collect())
+}
+
+// Collapses sorted slots into ranges of consecutive slots, for display
+fn slot_ranges(slots: &[Slot]) -> Vec<String> {
+    let mut ranges: Vec<(Slot, Slot)> = vec![];
+    for slot in slots {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == *slot => *last = *slot,
+            _ => ranges.push((*slot, *slot)),
+        }
+    }
+    ranges
+        .into_iter()
+        .map(|(first, last)| {
+            if first == last {
+                first.to_string()
+            } else {
+                format!("{}-{}", first, last)
+            }
+        })
+        .collect()
+}
+
+// Returns a description of each difference between the local and the bigtable copy of a block
+fn compare_blocks(local: &ConfirmedBlock, bigtable: &ConfirmedBlock) -> Vec<String> {
+    let mut differences = vec![];
+    if local.parent_slot != bigtable.parent_slot {
+        differences.push(format!(
+            "parent slot: local {}, bigtable {}",
+            local.parent_slot, bigtable.parent_slot
+        ));
+    }
+    if local.blockhash != bigtable.blockhash {
+        differences.push(format!(
+            "blockhash: local {}, bigtable {}",
+            local.blockhash, bigtable.blockhash
+        ));
+    }
+    if local.previous_blockhash != bigtable.previous_blockhash {
+        differences.push(format!(
+            "previous blockhash: local {}, bigtable {}",
+            local.previous_blockhash, bigtable.previous_blockhash
+        ));
+    }
+    if let (Some(local_block_time), Some(bigtable_block_time)) =
+        (local.block_time, bigtable.block_time)
+    {
+        if local_block_time != bigtable_block_time {
+            differences.push(format!(
+                "block time: local {}, bigtable {}",
+                local_block_time, bigtable_block_time
+            ));
+        }
+    }
+    if local.rewards.len() != bigtable.rewards.len() {
+        differences.push(format!(
+            "rewards: local {}, bigtable {}",
+            local.rewards.len(),
+            bigtable.rewards.len()
+        ));
+    }
+    if local.transactions.len() != bigtable.transactions.len() {
+        differences.push(format!(
+            "transactions: local {}, bigtable {}",
+            local.transactions.len(),
+            bigtable.transactions.len()
+        ));
+    }
+    for (index, (local_transaction, bigtable_transaction)) in local
+        .transactions
+        .iter()
+        .zip(bigtable.transactions.iter())
+        .enumerate()
+    {
+        let local_signature = local_transaction.transaction.signatures.first();
+        let bigtable_signature = bigtable_transaction.transaction.signatures.first();
+        if local_signature != bigtable_signature {
+            differences.push(format!(
+                "transaction {} signature: local {:?}, bigtable {:?}",
+                index, local_signature, bigtable_signature
+            ));
+            continue;
+        }
+        let local_status = local_transaction.meta.as_ref().map(|meta| &meta.status);
+        let bigtable_status = bigtable_transaction.meta.as_ref().map(|meta| &meta.status);
+        if local_status != bigtable_status {
+            differences.push(format!(
+                "transaction {} status: local {:?}, bigtable {:?}",
+                index, local_status, bigtable_status
+            ));
+        }
+    }
+    differences
+}
+
+async fn compare(
+    blockstore: Blockstore,
+    starting_slot: Slot,
+    ending_slot: Option<Slot>,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = solana_storage_bigtable::LedgerStorage::new(true, None)
+        .await
+        .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
+
+    let ending_slot = ending_slot.unwrap_or_else(|| blockstore.max_root());
+    let local_slots = rooted_slots(&blockstore, starting_slot, ending_slot)?;
+    let bigtable_slots = get_bigtable_slots(&bigtable, starting_slot, ending_slot).await;
+
+    let local_slot_set: HashSet<_> = local_slots.iter().cloned().collect();
+    let bigtable_slot_set: HashSet<_> = bigtable_slots.iter().cloned().collect();
+    let missing_from_bigtable: Vec<_> = local_slots
+        .iter()
+        .filter(|slot| !bigtable_slot_set.contains(slot))
+        .cloned()
+        .collect();
+    let missing_locally: Vec<_> = bigtable_slots
+        .iter()
+        .filter(|slot| !local_slot_set.contains(slot))
+        .cloned()
+        .collect();
+
+    println!("Comparing slots {} to {}", starting_slot, ending_slot);
+    println!("Local roots: {}", local_slots.len());
+    println!("BigTable blocks: {}", bigtable_slots.len());
+    println!("Missing from BigTable: {}", missing_from_bigtable.len());
+    if verbose && !missing_from_bigtable.is_empty() {
+        println!("  {}", slot_ranges(&missing_from_bigtable).join(", "));
+    }
+    println!("Missing locally: {}", missing_locally.len());
+    if verbose && !missing_locally.is_empty() {
+        println!("  {}", slot_ranges(&missing_locally).join(", "));
+    }
+
+    let mut mismatched_slots = 0;
+    for slot in local_slots
+        .iter()
+        .filter(|slot| bigtable_slot_set.contains(slot))
+    {
+        let local_block = match blockstore.get_confirmed_block(*slot) {
+            Ok(block) => block,
+            Err(err) => {
+                println!("Slot {}: unable to load local block: {:?}", slot, err);
+                continue;
+            }
+        };
+        let bigtable_block = bigtable.get_confirmed_block(*slot).await?;
+        let differences = compare_blocks(&local_block, &bigtable_block);
+        if !differences.is_empty() {
+            mismatched_slots += 1;
+            println!("Slot {} differs:", slot);
+            for difference in differences {
+                println!("  {}", difference);
+            }
+        }
+    }
+    println!("Mismatched blocks: {}", mismatched_slots);
+
+    if mismatched_slots > 0 || !missing_from_bigtable.is_empty() {
+        Err(format!(
+            "BigTable differs from the local ledger: {} blocks missing, {} blocks mismatched",
+            missing_from_bigtable.len(),
+            mismatched_slots
+        )
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+async fn backfill(
+    blockstore: Blockstore,
+    starting_slot: Slot,
+    ending_slot: Option<Slot>,
+    allow_missing_metadata: bool,
+    dry_run: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let bigtable = solana_storage_bigtable::LedgerStorage::new(dry_run, None)
+        .await
+        .map_err(|err| format!("Failed to connect to storage: {:?}", err))?;
+
+    let ending_slot = ending_slot.unwrap_or_else(|| blockstore.max_root());
+    let local_slots = rooted_slots(&blockstore, starting_slot, ending_slot)?;
+    let bigtable_slots: HashSet<_> = get_bigtable_slots(&bigtable, starting_slot, ending_slot)
+        .await
+        .into_iter()
+        .collect();
+    let gaps: Vec<_> = local_slots
+        .into_iter()
+        .filter(|slot| !bigtable_slots.contains(slot))
+        .collect();
+
+    if gaps.is_empty() {
+        println!(
+            "No gaps in BigTable between slots {} and {}",
+            starting_slot, ending_slot
+        );
+        return Ok(());
+    }
+    println!(
+        "{} blocks missing from BigTable between slots {} and {}: {}",
+        gaps.len(),
+        starting_slot,
+        ending_slot,
+        slot_ranges(&gaps).join(", ")
+    );
+    if dry_run {
+        return Ok(());
+    }
+
+    upload_blocks(
+        Arc::new(blockstore),
+        bigtable,
+        gaps,
+        allow_missing_metadata,
         Arc::new(AtomicBool::new(false)),
     )
     .await
@@ -244,6 +486,81 @@ impl BigTableSubCommand for App<'_, '_> {
                                 .long("allow-missing-metadata")
                                 .takes_value(false)
                                 .help("Don't panic if transaction metadata is missing"),
+                        )
+                        .arg(
+                            Arg::with_name("force_reupload")
+                                .long("force")
+                                .takes_value(false)
+                                .help(
+                                    "Upload every block in the range, overwriting the blocks \
+                                     already in BigTable",
+                                ),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("compare")
+                        .about(
+                            "Compare the rooted blocks of the local ledger with the blocks in \
+                             BigTable, exiting with an error if any are missing or differ",
+                        )
+                        .arg(
+                            Arg::with_name("starting_slot")
+                                .long("starting-slot")
+                                .validator(is_slot)
+                                .value_name("SLOT")
+                                .takes_value(true)
+                                .default_value("0")
+                                .help("Start comparing at this slot"),
+                        )
+                        .arg(
+                            Arg::with_name("ending_slot")
+                                .long("ending-slot")
+                                .validator(is_slot)
+                                .value_name("SLOT")
+                                .takes_value(true)
+                                .help("Stop comparing at this slot [default: last local root]"),
+                        )
+                        .arg(
+                            Arg::with_name("verbose")
+                                .short("v")
+                                .long("verbose")
+                                .takes_value(false)
+                                .help("List the missing slots"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("backfill")
+                        .about("Upload the rooted blocks of the local ledger missing from BigTable")
+                        .arg(
+                            Arg::with_name("starting_slot")
+                                .long("starting-slot")
+                                .validator(is_slot)
+                                .value_name("SLOT")
+                                .takes_value(true)
+                                .default_value("0")
+                                .help("Start looking for gaps at this slot"),
+                        )
+                        .arg(
+                            Arg::with_name("ending_slot")
+                                .long("ending-slot")
+                                .validator(is_slot)
+                                .value_name("SLOT")
+                                .takes_value(true)
+                                .help(
+                                    "Stop looking for gaps at this slot [default: last local root]",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("allow_missing_metadata")
+                                .long("allow-missing-metadata")
+                                .takes_value(false)
+                                .help("Don't panic if transaction metadata is missing"),
+                        )
+                        .arg(
+                            Arg::with_name("dry_run")
+                                .long("dry-run")
+                                .takes_value(false)
+                                .help("Only list the gaps, don't upload anything"),
                         ),
                 )
                 .subcommand(
@@ -386,6 +703,7 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
             let starting_slot = value_t!(arg_matches, "starting_slot", Slot).unwrap_or(0);
             let ending_slot = value_t!(arg_matches, "ending_slot", Slot).ok();
             let allow_missing_metadata = arg_matches.is_present("allow_missing_metadata");
+            let force_reupload = arg_matches.is_present("force_reupload");
             let blockstore =
                 crate::open_blockstore(&ledger_path, AccessType::TryPrimaryThenSecondary, None);
 
@@ -394,6 +712,32 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
                 starting_slot,
                 ending_slot,
                 allow_missing_metadata,
+                force_reupload,
+            ))
+        }
+        ("compare", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).ok();
+            let verbose = arg_matches.is_present("verbose");
+            let blockstore =
+                crate::open_blockstore(&ledger_path, AccessType::TryPrimaryThenSecondary, None);
+
+            runtime.block_on(compare(blockstore, starting_slot, ending_slot, verbose))
+        }
+        ("backfill", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).ok();
+            let allow_missing_metadata = arg_matches.is_present("allow_missing_metadata");
+            let dry_run = arg_matches.is_present("dry_run");
+            let blockstore =
+                crate::open_blockstore(&ledger_path, AccessType::TryPrimaryThenSecondary, None);
+
+            runtime.block_on(backfill(
+                blockstore,
+                starting_slot,
+                ending_slot,
+                allow_missing_metadata,
+                dry_run,
             ))
         }
         ("first-available-block", Some(_arg_matches)) => runtime.block_on(first_available_block()),
@@ -448,3 +792,96 @@ pub fn bigtable_process_command(ledger_path: &Path, matches: &ArgMatches<'_>) {
         exit(1);
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_ledger::get_tmp_ledger_path;
+    use solana_sdk::transaction::{Transaction, TransactionError};
+    use solana_transaction_status::{TransactionStatusMeta, TransactionWithStatusMeta};
+
+    fn transaction(
+        signature: u8,
+        status: Result<(), TransactionError>,
+    ) -> TransactionWithStatusMeta {
+        TransactionWithStatusMeta {
+            transaction: Transaction {
+                signatures: vec![Signature::new(&[signature; 64])],
+                ..Transaction::default()
+            },
+            meta: Some(TransactionStatusMeta {
+                status,
+                fee: 5000,
+                pre_balances: vec![],
+                post_balances: vec![],
+                inner_instructions: None,
+                log_messages: None,
+                pre_token_balances: None,
+                post_token_balances: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_rooted_slots() {
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&ledger_path).unwrap();
+            blockstore.set_roots(&[1, 2, 3, 5, 8]).unwrap();
+            assert_eq!(rooted_slots(&blockstore, 2, 5).unwrap(), vec![2, 3, 5]);
+            assert_eq!(rooted_slots(&blockstore, 4, 7).unwrap(), vec![5]);
+            assert!(rooted_slots(&blockstore, 9, 10).unwrap().is_empty());
+        }
+        Blockstore::destroy(&ledger_path).unwrap();
+    }
+
+    #[test]
+    fn test_slot_ranges() {
+        assert!(slot_ranges(&[]).is_empty());
+        assert_eq!(slot_ranges(&[4]), vec!["4"]);
+        assert_eq!(slot_ranges(&[1, 3, 4, 5, 7, 8]), vec!["1", "3-5", "7-8"]);
+    }
+
+    #[test]
+    fn test_compare_blocks() {
+        let local = ConfirmedBlock {
+            previous_blockhash: "previous".to_string(),
+            blockhash: "blockhash".to_string(),
+            parent_slot: 6,
+            transactions: vec![transaction(1, Ok(())), transaction(2, Ok(()))],
+            rewards: vec![],
+            block_time: Some(1_614_556_800),
+        };
+        assert!(compare_blocks(&local, &local).is_empty());
+
+        // A block time missing from either copy isn't a difference
+        let bigtable = ConfirmedBlock {
+            block_time: None,
+            ..local.clone()
+        };
+        assert!(compare_blocks(&local, &bigtable).is_empty());
+
+        let bigtable = ConfirmedBlock {
+            blockhash: "other".to_string(),
+            parent_slot: 5,
+            block_time: Some(1_614_556_801),
+            transactions: vec![
+                transaction(1, Err(TransactionError::AccountNotFound)),
+                transaction(3, Ok(())),
+                transaction(4, Ok(())),
+            ],
+            ..local.clone()
+        };
+        let differences = compare_blocks(&local, &bigtable);
+        assert_eq!(differences.len(), 6);
+        assert_eq!(differences[0], "parent slot: local 6, bigtable 5");
+        assert_eq!(differences[1], "blockhash: local blockhash, bigtable other");
+        assert_eq!(
+            differences[2],
+            "block time: local 1614556800, bigtable 1614556801"
+        );
+        assert_eq!(differences[3], "transactions: local 2, bigtable 3");
+        assert!(differences[4].starts_with("transaction 0 status: "));
+        assert!(differences[5].starts_with("transaction 1 signature: "));
+    }
+}
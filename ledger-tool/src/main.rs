@@ -19,11 +19,12 @@ use solana_ledger::{
     bank_forks_utils,
     blockstore::{create_new_ledger, Blockstore, PurgeType},
     blockstore_db::{self, AccessType, BlockstoreRecoveryMode, Column, Database},
-    blockstore_processor::ProcessOptions,
+    blockstore_processor::{ProcessOptions, TransactionStatusSender},
     rooted_slot_iterator::RootedSlotIterator,
     shred::Shred,
 };
 use solana_runtime::{
+    accounts_index::AccountIndex,
     bank::{Bank, RewardCalculationEvent},
     bank_forks::{ArchiveFormat, BankForks, SnapshotConfig},
     hardened_unpack::{open_genesis_config, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
@@ -31,7 +32,9 @@ use solana_runtime::{
     snapshot_utils::SnapshotVersion,
 };
 use solana_sdk::{
-    account::Account,
+    account::{from_account, Account},
+    account_utils::StateMut,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
     clock::{Epoch, Slot},
     feature::{self, Feature},
     feature_set,
@@ -42,7 +45,8 @@ use solana_sdk::{
     pubkey::Pubkey,
     rent::Rent,
     shred_version::compute_shred_version,
-    system_program,
+    stake_history::StakeHistory,
+    system_program, sysvar,
 };
 use solana_stake_program::stake_state::{self, PointValue, StakeState};
 use solana_vote_program::{
@@ -59,11 +63,22 @@ use std::{
     process::{exit, Command, Stdio},
     str::FromStr,
     sync::Arc,
+    thread::sleep,
+    time::Duration,
 };
 
+mod abi_check;
+use abi_check::*;
+
 mod bigtable;
 use bigtable::*;
 
+mod replay_record;
+use replay_record::*;
+
+mod signing_journal;
+use signing_journal::*;
+
 #[derive(PartialEq)]
 enum LedgerOutputMethod {
     Print,
@@ -283,8 +298,18 @@ fn render_dot(dot: String, output_file: &str, output_format: &str) -> io::Result
     Ok(())
 }
 
+struct GraphConfig {
+    include_all_votes: bool,
+    // Banks below this slot are collapsed into the "..." node
+    starting_slot: Slot,
+}
+
 #[allow(clippy::cognitive_complexity)]
-fn graph_forks(bank_forks: &BankForks, include_all_votes: bool) -> String {
+fn graph_forks(
+    bank_forks: &BankForks,
+    rooted_slots: &HashSet<Slot>,
+    config: &GraphConfig,
+) -> String {
     let frozen_banks = bank_forks.frozen_banks();
     let mut fork_slots: HashSet<_> = frozen_banks.keys().cloned().collect();
     for (_, bank) in frozen_banks {
@@ -357,12 +382,15 @@ fn graph_forks(bank_forks: &BankForks, include_all_votes: bool) -> String {
             }
 
             if !styled_slots.contains(&bank.slot()) {
+                let rooted = rooted_slots.contains(&bank.slot());
                 dot.push(format!(
-                    r#"    "{}"[label="{} (epoch {})\nleader: {}{}{}",style="{}{}"];"#,
+                    r#"    "{}"[label="{} (epoch {}{})\nleader: {}\nhash: {}{}{}",style="{}{}"];"#,
                     bank.slot(),
                     bank.slot(),
                     bank.epoch(),
+                    if rooted { ", rooted" } else { "" },
                     bank.collector_id(),
+                    bank.hash(),
                     if let Some(parent) = bank.parent() {
                         format!(
                             "\ntransactions: {}",
@@ -384,13 +412,17 @@ fn graph_forks(bank_forks: &BankForks, include_all_votes: bool) -> String {
                         "".to_string()
                     },
                     if first { "filled," } else { "" },
-                    ""
+                    if rooted { "bold" } else { "" }
                 ));
                 styled_slots.insert(bank.slot());
             }
             first = false;
 
             match bank.parent() {
+                Some(parent) if parent.slot() < config.starting_slot => {
+                    dot.push(format!(r#"    "{}" -> "..." [dir=back]"#, bank.slot(),));
+                    break;
+                }
                 None => {
                     if bank.slot() > 0 {
                         dot.push(format!(r#"    "{}" -> "..." [dir=back]"#, bank.slot(),));
@@ -478,7 +510,7 @@ fn graph_forks(bank_forks: &BankForks, include_all_votes: bool) -> String {
     }
 
     // Add for vote information from all banks.
-    if include_all_votes {
+    if config.include_all_votes {
         for (node_pubkey, validator_votes) in &all_votes {
             for (vote_slot, vote_state) in validator_votes {
                 dot.push(format!(
@@ -651,6 +683,7 @@ fn load_bank_forks(
     access_type: AccessType,
     wal_recovery_mode: Option<BlockstoreRecoveryMode>,
     snapshot_archive_path: Option<PathBuf>,
+    transaction_status_sender: Option<TransactionStatusSender>,
 ) -> bank_forks_utils::LoadResult {
     let blockstore = open_blockstore(&ledger_path, access_type, wal_recovery_mode);
     let snapshot_path = ledger_path.join(if blockstore.is_primary_access() {
@@ -697,7 +730,7 @@ fn load_bank_forks(
         None,
         snapshot_config.as_ref(),
         process_options,
-        None,
+        transaction_status_sender,
     )
 }
 
@@ -707,6 +740,139 @@ fn open_genesis_config_by(ledger_path: &Path, matches: &ArgMatches<'_>) -> Genes
     open_genesis_config(ledger_path, max_genesis_archive_unpacked_size)
 }
 
+fn output_epoch_account_state(bank: &Bank, address: &Pubkey) {
+    println!("{}:", address);
+    let account = match bank.get_account(address) {
+        Some(account) => account,
+        None => {
+            println!("  - not found");
+            return;
+        }
+    };
+    println!("  - balance: {} SOL", lamports_to_sol(account.lamports));
+    println!("  - owner: '{}'", account.owner);
+
+    if account.owner == solana_stake_program::id() {
+        match StakeState::from(&account) {
+            Some(StakeState::Stake(_meta, stake)) => {
+                let stake_history = bank
+                    .get_account(&sysvar::stake_history::id())
+                    .and_then(|account| from_account::<StakeHistory>(&account))
+                    .unwrap_or_default();
+                let (effective, activating, deactivating) =
+                    stake.delegation.stake_activating_and_deactivating(
+                        bank.epoch(),
+                        Some(&stake_history),
+                        bank.stake_program_v2_enabled(),
+                    );
+                println!("  - voter: {}", stake.delegation.voter_pubkey);
+                println!(
+                    "  - delegated stake: {} SOL",
+                    lamports_to_sol(stake.delegation.stake)
+                );
+                println!("  - effective stake: {} SOL", lamports_to_sol(effective));
+                println!("  - activating stake: {} SOL", lamports_to_sol(activating));
+                println!(
+                    "  - deactivating stake: {} SOL",
+                    lamports_to_sol(deactivating)
+                );
+                println!(
+                    "  - activation epoch: {}",
+                    stake.delegation.activation_epoch
+                );
+                if stake.delegation.deactivation_epoch != std::u64::MAX {
+                    println!(
+                        "  - deactivation epoch: {}",
+                        stake.delegation.deactivation_epoch
+                    );
+                }
+                println!("  - credits observed: {}", stake.credits_observed);
+            }
+            Some(stake_state) => println!("  - stake state: {:?}", stake_state),
+            None => println!("  - stake state: invalid"),
+        }
+    } else if account.owner == solana_vote_program::id() {
+        match VoteState::from(&account) {
+            Some(vote_state) => {
+                println!("  - node: {}", vote_state.node_pubkey);
+                println!("  - commission: {}%", vote_state.commission);
+                println!("  - credits: {}", vote_state.credits());
+                if let Some((epoch, credits, prev_credits)) = vote_state.epoch_credits().last() {
+                    println!(
+                        "  - epoch {} credits: {}",
+                        epoch,
+                        credits.saturating_sub(*prev_credits)
+                    );
+                }
+                println!("  - last vote: {:?}", vote_state.last_voted_slot());
+                println!("  - root slot: {:?}", vote_state.root_slot);
+            }
+            None => println!("  - vote state: invalid"),
+        }
+    }
+
+    if let Some((_pubkey, reward)) = bank
+        .rewards
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(pubkey, _reward)| pubkey == address)
+    {
+        println!(
+            "  - {} reward: {}{} SOL",
+            reward.reward_type,
+            if reward.lamports < 0 { "-" } else { "" },
+            lamports_to_sol(reward.lamports.abs() as u64)
+        );
+    }
+}
+
+/// The accounts of `bank` a mini snapshot keeps: the sysvars, the feature accounts, each program
+/// along with its program data account and the accounts it owns, and the extra `addresses`
+fn mini_snapshot_accounts(
+    bank: &Bank,
+    program_ids: &[Pubkey],
+    addresses: &[Pubkey],
+) -> Result<BTreeMap<Pubkey, Account>, String> {
+    let mut accounts: BTreeMap<_, _> = bank
+        .get_program_accounts(&sysvar::id())
+        .into_iter()
+        .chain(bank.get_program_accounts(&feature::id()))
+        .collect();
+    for program_id in program_ids {
+        let program_account = bank
+            .get_account(program_id)
+            .ok_or_else(|| format!("Program {} not found", program_id))?;
+        if !program_account.executable {
+            return Err(format!("{} is not a program", program_id));
+        }
+        if program_account.owner == bpf_loader_upgradeable::id() {
+            if let Ok(UpgradeableLoaderState::Program {
+                programdata_address,
+            }) = program_account.state()
+            {
+                let programdata_account =
+                    bank.get_account(&programdata_address).ok_or_else(|| {
+                        format!(
+                            "Program data {} of program {} not found",
+                            programdata_address, program_id
+                        )
+                    })?;
+                accounts.insert(programdata_address, programdata_account);
+            }
+        }
+        accounts.insert(*program_id, program_account);
+        accounts.extend(bank.get_program_accounts(program_id));
+    }
+    for address in addresses {
+        let account = bank
+            .get_account(address)
+            .ok_or_else(|| format!("Account {} not found", address))?;
+        accounts.insert(*address, account);
+    }
+    Ok(accounts)
+}
+
 fn assert_capitalization(bank: &Bank) {
     assert!(bank.calculate_and_verify_capitalization());
 }
@@ -970,11 +1136,48 @@ fn main() {
             .arg(&starting_slot_arg)
             .arg(&ending_slot_arg)
         )
+        .subcommand(
+            SubCommand::with_name("missing-shreds")
+            .about("Prints the indexes of the data shreds missing from the incomplete slots")
+            .arg(&starting_slot_arg)
+            .arg(&ending_slot_arg)
+            .arg(
+                Arg::with_name("follow")
+                    .long("follow")
+                    .takes_value(false)
+                    .help("Keep printing the missing shreds of a live ledger until interrupted"),
+            )
+            .arg(
+                Arg::with_name("interval")
+                    .long("interval")
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .default_value("2")
+                    .validator(is_parsable::<u64>)
+                    .requires("follow")
+                    .help("Seconds to wait between updates in --follow mode"),
+            )
+        )
         .subcommand(
             SubCommand::with_name("bank-hash")
             .about("Prints the hash of the working bank after reading the ledger")
             .arg(&max_genesis_archive_unpacked_size_arg)
         )
+        .subcommand(
+            SubCommand::with_name("verify-accounts")
+            .about("Verify the consistency of the account storages against the accounts index")
+            .arg(&account_paths_arg)
+            .arg(&halt_at_slot_arg)
+            .arg(&hard_forks_arg)
+            .arg(&max_genesis_archive_unpacked_size_arg)
+            .arg(
+                Arg::with_name("quarantine_dir")
+                    .long("quarantine-dir")
+                    .value_name("DIR")
+                    .takes_value(true)
+                    .help("Copy corrupt account storages into this directory for inspection"),
+            )
+        )
         .subcommand(
             SubCommand::with_name("bounds")
             .about("Print lowest and highest non-empty slots. Note that there may be empty slots within the bounds")
@@ -1005,12 +1208,34 @@ fn main() {
                     .takes_value(false)
                     .help("Skip ledger PoH verification"),
             )
+            .arg(
+                Arg::with_name("bpf_jit")
+                    .long("bpf-jit")
+                    .takes_value(false)
+                    .help("Use the just-in-time compiler instead of the interpreter for BPF"),
+            )
             .arg(
                 Arg::with_name("print_accounts_stats")
                     .long("print-accounts-stats")
                     .takes_value(false)
                     .help("After verifying the ledger, print some information about the account stores."),
             )
+            .arg(
+                Arg::with_name("record_account_hashes")
+                    .long("record-account-hashes")
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .help("Record the bank hash of each replayed slot and the lamport changes \
+                           of each transaction to FILE"),
+            )
+            .arg(
+                Arg::with_name("compare")
+                    .long("compare")
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .help("Compare the replay against the one recorded to FILE with \
+                           --record-account-hashes, and fail if they diverge"),
+            )
         ).subcommand(
             SubCommand::with_name("graph")
             .about("Create a Graphviz rendering of the ledger")
@@ -1024,6 +1249,16 @@ fn main() {
                     .long("include-all-votes")
                     .help("Include all votes in the graph"),
             )
+            .arg(
+                Arg::with_name("starting_slot")
+                    .long("starting-slot")
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Collapse banks before this slot, use --halt-at-slot to bound the \
+                           end of the range"),
+            )
             .arg(
                 Arg::with_name("graph_filename")
                     .index(1)
@@ -1147,6 +1382,52 @@ fn main() {
                     .takes_value(false)
                     .help("Remove all existing stake accounts from the new snapshot.")
             )
+        ).subcommand(
+            SubCommand::with_name("mini-snapshot")
+            .about("Create a snapshot holding only the given programs, the accounts they own \
+                    and the sysvars and feature accounts, on top of the genesis accounts")
+            .arg(&no_snapshot_arg)
+            .arg(&account_paths_arg)
+            .arg(&hard_forks_arg)
+            .arg(&max_genesis_archive_unpacked_size_arg)
+            .arg(&snapshot_version_arg)
+            .arg(
+                Arg::with_name("snapshot_slot")
+                    .index(1)
+                    .value_name("SLOT")
+                    .validator(is_slot)
+                    .takes_value(true)
+                    .required(true)
+                    .help("Slot to take the accounts from, and of the mini snapshot"),
+            )
+            .arg(
+                Arg::with_name("output_directory")
+                    .index(2)
+                    .value_name("DIR")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Output directory for the mini snapshot"),
+            )
+            .arg(
+                Arg::with_name("programs")
+                    .long("program")
+                    .value_name("PROGRAM_ID")
+                    .takes_value(true)
+                    .multiple(true)
+                    .required(true)
+                    .validator(is_pubkey)
+                    .help("Program to keep along with its program data account and the \
+                           accounts it owns"),
+            )
+            .arg(
+                Arg::with_name("addresses")
+                    .long("account")
+                    .value_name("PUBKEY")
+                    .takes_value(true)
+                    .multiple(true)
+                    .validator(is_pubkey)
+                    .help("Additional account to keep"),
+            )
         ).subcommand(
             SubCommand::with_name("accounts")
             .about("Print account contents after processing in the ledger")
@@ -1167,6 +1448,33 @@ fn main() {
                     .help("Exclude account data (useful for large number of accounts)"),
             )
             .arg(&max_genesis_archive_unpacked_size_arg)
+        ).subcommand(
+            SubCommand::with_name("epoch-account-state")
+            .about("Print the stake and vote account state as of an epoch boundary, \
+                    replaying from the closest retained snapshot")
+            .arg(&no_snapshot_arg)
+            .arg(&account_paths_arg)
+            .arg(&hard_forks_arg)
+            .arg(
+                Arg::with_name("epoch")
+                    .index(1)
+                    .value_name("EPOCH")
+                    .takes_value(true)
+                    .validator(is_parsable::<Epoch>)
+                    .required(true)
+                    .help("Epoch whose first block to print the account state at"),
+            )
+            .arg(
+                Arg::with_name("addresses")
+                    .index(2)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .multiple(true)
+                    .validator(is_pubkey)
+                    .required(true)
+                    .help("Stake or vote account addresses"),
+            )
+            .arg(&max_genesis_archive_unpacked_size_arg)
         ).subcommand(
             SubCommand::with_name("capitalization")
             .about("Print capitalization (aka, total suppy) while checksumming it")
@@ -1262,6 +1570,17 @@ fn main() {
                     .help("Limit puring to dead slots only")
             )
         )
+        .subcommand(
+            SubCommand::with_name("fsck")
+            .about("Check the consistency of the ledger columns")
+            .arg(&starting_slot_arg)
+            .arg(
+                Arg::with_name("repair")
+                    .long("repair")
+                    .takes_value(false)
+                    .help("Drop the unrooted slots that fail the check, and their descendants")
+            )
+        )
         .subcommand(
             SubCommand::with_name("list-roots")
             .about("Output upto last <num-roots> root hashes and their heights starting at the given block height")
@@ -1295,10 +1614,71 @@ fn main() {
             SubCommand::with_name("analyze-storage")
                 .about("Output statistics in JSON format about all column families in the ledger rocksDB")
         )
+        .subcommand(
+            SubCommand::with_name("abi-check")
+                .about("Print the ABI digests of the types serialized into the ledger and snapshots")
+                .arg(
+                    Arg::with_name("compare")
+                        .long("compare")
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .help("Fail if the digests differ from the ones previously printed to FILE"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("signing-journal")
+                .about("Print what a validator signed with its identity and vote keys")
+                .arg(
+                    Arg::with_name("journal")
+                        .index(1)
+                        .value_name("FILE")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Journal written by solana-validator --signing-journal"),
+                )
+                .arg(
+                    Arg::with_name("kind")
+                        .long("kind")
+                        .value_name("KIND")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .possible_values(&[
+                            "startup",
+                            "vote",
+                            "authorized-voter-rotation",
+                            "shreds",
+                            "tower",
+                            "gossip",
+                        ])
+                        .help("Only print events of this kind, may be specified multiple times"),
+                )
+                .arg(
+                    Arg::with_name("signer")
+                        .long("signer")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .validator(is_pubkey)
+                        .help("Only print events signed by this key"),
+                )
+                .arg(&starting_slot_arg)
+                .arg(&ending_slot_arg)
+        )
         .get_matches();
 
     info!("{} {}", crate_name!(), solana_version::version!());
 
+    // The ABI digests are those of this build, no ledger is involved
+    if let ("abi-check", Some(arg_matches)) = matches.subcommand() {
+        abi_check_process_command(arg_matches);
+        return;
+    }
+    // The journal is kept apart from the ledger
+    if let ("signing-journal", Some(arg_matches)) = matches.subcommand() {
+        signing_journal_process_command(arg_matches);
+        return;
+    }
+
     let ledger_path = PathBuf::from(value_t!(matches, "ledger_path", String).unwrap_or_else(
         |_err| {
             eprintln!(
@@ -1416,6 +1796,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     println!(
@@ -1472,6 +1853,54 @@ fn main() {
                 }
             }
         }
+        ("missing-shreds", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let ending_slot = value_t!(arg_matches, "ending_slot", Slot).unwrap_or(Slot::MAX);
+            let follow = arg_matches.is_present("follow");
+            let interval = Duration::from_secs(value_t_or_exit!(arg_matches, "interval", u64));
+            loop {
+                // Reopen the ledger on every update to pick up the shreds inserted by a running
+                // validator, which holds the primary instance
+                let blockstore = open_blockstore(
+                    &ledger_path,
+                    AccessType::TryPrimaryThenSecondary,
+                    wal_recovery_mode,
+                );
+                // Slots below the root will not be repaired anymore
+                let starting_slot = if follow {
+                    starting_slot.max(blockstore.last_root())
+                } else {
+                    starting_slot
+                };
+                for (slot, meta) in blockstore
+                    .slot_meta_iterator(starting_slot)
+                    .unwrap()
+                    .take_while(|(slot, _)| *slot <= ending_slot)
+                    .filter(|(_, meta)| !meta.is_full())
+                {
+                    let missing = blockstore.find_missing_data_indexes_in_slot(&meta, usize::MAX);
+                    let last_index = if meta.last_index == std::u64::MAX {
+                        "unknown".to_string()
+                    } else {
+                        meta.last_index.to_string()
+                    };
+                    println!(
+                        "Slot {}: received {}, last index {}, missing {}: {:?}",
+                        slot,
+                        meta.received,
+                        last_index,
+                        missing.len(),
+                        missing,
+                    );
+                }
+                if !follow {
+                    break;
+                }
+                drop(blockstore);
+                println!();
+                sleep(interval);
+            }
+        }
         ("bank-hash", Some(arg_matches)) => {
             let process_options = ProcessOptions {
                 dev_halt_at_slot: Some(0),
@@ -1488,6 +1917,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     println!("{}", &bank_forks.working_bank().hash());
@@ -1498,6 +1928,74 @@ fn main() {
                 }
             }
         }
+        ("verify-accounts", Some(arg_matches)) => {
+            let process_options = ProcessOptions {
+                dev_halt_at_slot: Some(value_t!(arg_matches, "halt_at_slot", Slot).unwrap_or(0)),
+                new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+                poh_verify: false,
+                ..ProcessOptions::default()
+            };
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            let (bank_forks, _, _) = load_bank_forks(
+                arg_matches,
+                &ledger_path,
+                &genesis_config,
+                process_options,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+                snapshot_archive_path,
+                None,
+            )
+            .unwrap_or_else(|err| {
+                eprintln!("Failed to load ledger: {:?}", err);
+                exit(1);
+            });
+
+            let working_bank = bank_forks.working_bank();
+            let report = working_bank.verify_accounts_storages();
+            for corrupt_storage in &report.corrupt_storages {
+                println!(
+                    "Slot {} storage {} ({}):",
+                    corrupt_storage.slot,
+                    corrupt_storage.id,
+                    corrupt_storage.path.display()
+                );
+                for corruption in &corrupt_storage.corruptions {
+                    println!("  {}", corruption);
+                }
+            }
+            println!(
+                "{} accounts in {} storages checked at slot {}, {} corrupt storages",
+                report.accounts_checked,
+                report.storages_checked,
+                working_bank.slot(),
+                report.corrupt_storages.len()
+            );
+
+            if !report.is_ok() {
+                if let Some(quarantine_dir) = value_t!(arg_matches, "quarantine_dir", PathBuf).ok()
+                {
+                    fs::create_dir_all(&quarantine_dir).unwrap_or_else(|err| {
+                        eprintln!("Unable to create {}: {}", quarantine_dir.display(), err);
+                        exit(1);
+                    });
+                    for corrupt_storage in &report.corrupt_storages {
+                        let file_name = corrupt_storage.path.file_name().unwrap();
+                        let destination = quarantine_dir.join(file_name);
+                        if let Err(err) = fs::copy(&corrupt_storage.path, &destination) {
+                            eprintln!(
+                                "Unable to quarantine {}: {}",
+                                corrupt_storage.path.display(),
+                                err
+                            );
+                        } else {
+                            println!("Quarantined {}", destination.display());
+                        }
+                    }
+                }
+                exit(1);
+            }
+        }
         ("slot", Some(arg_matches)) => {
             let slots = values_t_or_exit!(arg_matches, "slots", Slot);
             let allow_dead_slots = arg_matches.is_present("allow_dead_slots");
@@ -1627,10 +2125,29 @@ fn main() {
             }
         }
         ("verify", Some(arg_matches)) => {
+            let record_path = value_t!(arg_matches, "record_account_hashes", String).ok();
+            let baseline = value_t!(arg_matches, "compare", String)
+                .ok()
+                .map(|compare_path| {
+                    read_replay_record(Path::new(&compare_path)).unwrap_or_else(|err| {
+                        eprintln!("{}", err);
+                        exit(1);
+                    })
+                });
+            let replay_recorder = if record_path.is_some() || baseline.is_some() {
+                Some(ReplayRecorder::new())
+            } else {
+                None
+            };
+
             let process_options = ProcessOptions {
                 dev_halt_at_slot: value_t!(arg_matches, "halt_at_slot", Slot).ok(),
                 new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
                 poh_verify: !arg_matches.is_present("skip_poh_verify"),
+                bpf_jit: arg_matches.is_present("bpf_jit"),
+                slot_callback: replay_recorder
+                    .as_ref()
+                    .map(|replay_recorder| replay_recorder.slot_callback()),
                 ..ProcessOptions::default()
             };
             let print_accounts_stats = arg_matches.is_present("print_accounts_stats");
@@ -1647,6 +2164,9 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                replay_recorder
+                    .as_ref()
+                    .map(|replay_recorder| replay_recorder.transaction_status_sender()),
             )
             .unwrap_or_else(|err| {
                 eprintln!("Ledger verification failed: {:?}", err);
@@ -1656,6 +2176,21 @@ fn main() {
                 let working_bank = bank_forks.working_bank();
                 working_bank.print_accounts_stats();
             }
+            if let Some(replay_recorder) = replay_recorder {
+                let slots = replay_recorder.finish();
+                if let Some(record_path) = record_path {
+                    write_replay_record(Path::new(&record_path), &slots).unwrap_or_else(|err| {
+                        eprintln!("{}", err);
+                        exit(1);
+                    });
+                    println!("Recorded {} slots to {}", slots.len(), record_path);
+                }
+                if let Some(baseline) = baseline {
+                    if compare_replay_records(&baseline, &slots).is_some() {
+                        exit(1);
+                    }
+                }
+            }
             println!("Ok");
         }
         ("graph", Some(arg_matches)) => {
@@ -1674,11 +2209,25 @@ fn main() {
                 &open_genesis_config_by(&ledger_path, arg_matches),
                 process_options,
                 AccessType::TryPrimaryThenSecondary,
-                wal_recovery_mode,
+                wal_recovery_mode.clone(),
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
-                    let dot = graph_forks(&bank_forks, arg_matches.is_present("include_all_votes"));
+                    let graph_config = GraphConfig {
+                        include_all_votes: arg_matches.is_present("include_all_votes"),
+                        starting_slot: value_t_or_exit!(arg_matches, "starting_slot", Slot),
+                    };
+                    let blockstore = open_blockstore(
+                        &ledger_path,
+                        AccessType::TryPrimaryThenSecondary,
+                        wal_recovery_mode,
+                    );
+                    let rooted_slots: HashSet<Slot> = blockstore
+                        .rooted_slot_iterator(graph_config.starting_slot)
+                        .map(|iter| iter.collect())
+                        .unwrap_or_default();
+                    let dot = graph_forks(&bank_forks, &rooted_slots, &graph_config);
 
                     let extension = Path::new(&output_file).extension();
                     let result = if extension == Some(OsStr::new("pdf")) {
@@ -1753,6 +2302,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     let mut bank = bank_forks
@@ -1945,6 +2495,108 @@ fn main() {
                 }
             }
         }
+        ("mini-snapshot", Some(arg_matches)) => {
+            let snapshot_slot = value_t_or_exit!(arg_matches, "snapshot_slot", Slot);
+            let output_directory = value_t_or_exit!(arg_matches, "output_directory", String);
+            let program_ids = pubkeys_of(arg_matches, "programs").unwrap();
+            let addresses = pubkeys_of(arg_matches, "addresses").unwrap_or_default();
+            let snapshot_version =
+                arg_matches
+                    .value_of("snapshot_version")
+                    .map_or(SnapshotVersion::default(), |s| {
+                        s.parse::<SnapshotVersion>().unwrap_or_else(|e| {
+                            eprintln!("Error: {}", e);
+                            exit(1)
+                        })
+                    });
+            if snapshot_slot == 0 {
+                eprintln!("Error: The mini snapshot slot must come after genesis");
+                exit(1);
+            }
+            let process_options = ProcessOptions {
+                dev_halt_at_slot: Some(snapshot_slot),
+                new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+                poh_verify: false,
+                ..ProcessOptions::default()
+            };
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            match load_bank_forks(
+                arg_matches,
+                &ledger_path,
+                &genesis_config,
+                process_options,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+                snapshot_archive_path,
+                None,
+            ) {
+                Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
+                    let bank = bank_forks.get(snapshot_slot).unwrap_or_else(|| {
+                        eprintln!("Error: Slot {} is not available", snapshot_slot);
+                        exit(1);
+                    });
+                    let accounts = mini_snapshot_accounts(bank, &program_ids, &addresses)
+                        .unwrap_or_else(|err| {
+                            eprintln!("Error: {}", err);
+                            exit(1);
+                        });
+
+                    // Start over from genesis with only the kept accounts, and warp to the
+                    // snapshot slot.  The feature accounts are in place by then, so the warp
+                    // activates the same features as the full bank has
+                    let accounts_dir = tempfile::tempdir_in(&ledger_path).unwrap_or_else(|err| {
+                        eprintln!("Unable to create a temporary accounts directory: {}", err);
+                        exit(1);
+                    });
+                    let mini_bank = Bank::new_with_paths(
+                        &genesis_config,
+                        vec![accounts_dir.path().to_path_buf()],
+                        &[],
+                        None,
+                        None,
+                        HashSet::<AccountIndex>::new(),
+                        false,
+                    );
+                    for (address, account) in &accounts {
+                        mini_bank.store_account(address, account);
+                    }
+                    mini_bank.set_capitalization();
+                    let mini_bank = Bank::warp_from_parent(
+                        &Arc::new(mini_bank),
+                        bank.collector_id(),
+                        snapshot_slot,
+                    );
+
+                    println!(
+                        "Creating a version {} mini snapshot of slot {} keeping {} accounts",
+                        snapshot_version,
+                        mini_bank.slot(),
+                        accounts.len(),
+                    );
+                    let archive_file = snapshot_utils::bank_to_snapshot_archive(
+                        &ledger_path,
+                        &mini_bank,
+                        Some(snapshot_version),
+                        output_directory,
+                        ArchiveFormat::TarZstd,
+                    )
+                    .unwrap_or_else(|err| {
+                        eprintln!("Unable to create mini snapshot: {}", err);
+                        exit(1);
+                    });
+                    println!(
+                        "Successfully created mini snapshot for slot {}, hash {}: {}",
+                        mini_bank.slot(),
+                        mini_bank.hash(),
+                        archive_file.display(),
+                    );
+                }
+                Err(err) => {
+                    eprintln!("Failed to load ledger: {:?}", err);
+                    exit(1);
+                }
+            }
+        }
         ("accounts", Some(arg_matches)) => {
             let dev_halt_at_slot = value_t!(arg_matches, "halt_at_slot", Slot).ok();
             let process_options = ProcessOptions {
@@ -1964,6 +2616,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     let slot = bank_forks.working_bank().slot();
@@ -2002,6 +2655,62 @@ fn main() {
                 }
             }
         }
+        ("epoch-account-state", Some(arg_matches)) => {
+            let epoch = value_t_or_exit!(arg_matches, "epoch", Epoch);
+            let addresses = pubkeys_of(arg_matches, "addresses").unwrap();
+            let genesis_config = open_genesis_config_by(&ledger_path, arg_matches);
+            let epoch_boundary_slot = genesis_config.epoch_schedule.get_first_slot_in_epoch(epoch);
+            let process_options = ProcessOptions {
+                dev_halt_at_slot: Some(epoch_boundary_slot),
+                snapshot_max_slot: Some(epoch_boundary_slot),
+                new_hard_forks: hardforks_of(arg_matches, "hard_forks"),
+                poh_verify: false,
+                ..ProcessOptions::default()
+            };
+            match load_bank_forks(
+                arg_matches,
+                &ledger_path,
+                &genesis_config,
+                process_options,
+                AccessType::TryPrimaryThenSecondary,
+                wal_recovery_mode,
+                snapshot_archive_path,
+                None,
+            ) {
+                Ok((bank_forks, _leader_schedule_cache, snapshot_hash)) => {
+                    let bank = bank_forks.working_bank();
+                    if bank.epoch() != epoch {
+                        eprintln!(
+                            "Error: Epoch {} is not available, ledger processing stopped at slot {}",
+                            epoch,
+                            bank.slot()
+                        );
+                        exit(1);
+                    }
+                    match snapshot_hash {
+                        Some((snapshot_slot, _hash)) => println!(
+                            "Epoch {} starts at slot {}, replayed from snapshot at slot {}",
+                            epoch,
+                            bank.slot(),
+                            snapshot_slot
+                        ),
+                        None => println!(
+                            "Epoch {} starts at slot {}, replayed from genesis",
+                            epoch,
+                            bank.slot()
+                        ),
+                    }
+                    println!("---");
+                    for address in addresses.iter() {
+                        output_epoch_account_state(&bank, address);
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Failed to load ledger: {:?}", err);
+                    exit(1);
+                }
+            }
+        }
         ("capitalization", Some(arg_matches)) => {
             let dev_halt_at_slot = value_t!(arg_matches, "halt_at_slot", Slot).ok();
             let process_options = ProcessOptions {
@@ -2019,6 +2728,7 @@ fn main() {
                 AccessType::TryPrimaryThenSecondary,
                 wal_recovery_mode,
                 snapshot_archive_path,
+                None,
             ) {
                 Ok((bank_forks, _leader_schedule_cache, _snapshot_hash)) => {
                     let slot = bank_forks.working_bank().slot();
@@ -2558,6 +3268,50 @@ fn main() {
                 }
             }
         }
+        ("fsck", Some(arg_matches)) => {
+            let starting_slot = value_t_or_exit!(arg_matches, "starting_slot", Slot);
+            let repair = arg_matches.is_present("repair");
+            let blockstore =
+                open_blockstore(&ledger_path, AccessType::PrimaryOnly, wal_recovery_mode);
+            let report = blockstore
+                .check_integrity(starting_slot)
+                .unwrap_or_else(|err| {
+                    eprintln!("Failed to check the ledger: {:?}", err);
+                    exit(1);
+                });
+            for issue in &report.issues {
+                println!("{}", issue);
+            }
+            println!(
+                "{} slots checked, {} issues found",
+                report.slots_checked,
+                report.issues.len()
+            );
+            if !report.is_ok() {
+                if !repair {
+                    exit(1);
+                }
+                let dropped_slots = blockstore.repair_integrity(&report).unwrap_or_else(|err| {
+                    eprintln!("Failed to repair the ledger: {:?}", err);
+                    exit(1);
+                });
+                println!("Dropped slots: {:?}", dropped_slots);
+                let remaining_issues = blockstore
+                    .check_integrity(starting_slot)
+                    .map(|report| report.issues.len())
+                    .unwrap_or_else(|err| {
+                        eprintln!("Failed to check the ledger: {:?}", err);
+                        exit(1);
+                    });
+                if remaining_issues > 0 {
+                    eprintln!(
+                        "{} issues remain in rooted slots and cannot be repaired",
+                        remaining_issues
+                    );
+                    exit(1);
+                }
+            }
+        }
         ("purge", Some(arg_matches)) => {
             let start_slot = value_t_or_exit!(arg_matches, "start_slot", Slot);
             let end_slot = value_t!(arg_matches, "end_slot", Slot).ok();
@@ -2743,3 +3497,84 @@ fn main() {
         _ => unreachable!(),
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_ledger::genesis_utils::create_genesis_config;
+    use solana_sdk::bpf_loader;
+
+    #[test]
+    fn test_mini_snapshot_accounts() {
+        let genesis_config = create_genesis_config(100).genesis_config;
+        let bank = Bank::new(&genesis_config);
+
+        let program_id = Pubkey::new_unique();
+        let mut program_account = Account::new(1, 0, &bpf_loader::id());
+        program_account.executable = true;
+        bank.store_account(&program_id, &program_account);
+        let owned_address = Pubkey::new_unique();
+        bank.store_account(&owned_address, &Account::new(1, 0, &program_id));
+
+        let upgradeable_program_id = Pubkey::new_unique();
+        let programdata_address = Pubkey::new_unique();
+        let mut upgradeable_program_account = Account::new_data(
+            1,
+            &UpgradeableLoaderState::Program {
+                programdata_address,
+            },
+            &bpf_loader_upgradeable::id(),
+        )
+        .unwrap();
+        upgradeable_program_account.executable = true;
+        bank.store_account(&upgradeable_program_id, &upgradeable_program_account);
+        let programdata_account = Account::new(1, 0, &bpf_loader_upgradeable::id());
+        bank.store_account(&programdata_address, &programdata_account);
+
+        let extra_address = Pubkey::new_unique();
+        bank.store_account(&extra_address, &Account::new(1, 0, &system_program::id()));
+        let unrelated_address = Pubkey::new_unique();
+        bank.store_account(
+            &unrelated_address,
+            &Account::new(1, 0, &system_program::id()),
+        );
+
+        let accounts = mini_snapshot_accounts(
+            &bank,
+            &[program_id, upgradeable_program_id],
+            &[extra_address],
+        )
+        .unwrap();
+        for address in &[
+            program_id,
+            owned_address,
+            upgradeable_program_id,
+            programdata_address,
+            extra_address,
+            sysvar::clock::id(),
+            sysvar::rent::id(),
+        ] {
+            assert!(accounts.contains_key(address), "{} not kept", address);
+        }
+        assert!(!accounts.contains_key(&unrelated_address));
+        assert_eq!(accounts[&programdata_address], programdata_account);
+
+        assert_eq!(
+            mini_snapshot_accounts(&bank, &[owned_address], &[]).unwrap_err(),
+            format!("{} is not a program", owned_address)
+        );
+        assert_eq!(
+            mini_snapshot_accounts(&bank, &[unrelated_address], &[]).unwrap_err(),
+            format!("{} is not a program", unrelated_address)
+        );
+        let missing_address = Pubkey::new_unique();
+        assert_eq!(
+            mini_snapshot_accounts(&bank, &[missing_address], &[]).unwrap_err(),
+            format!("Program {} not found", missing_address)
+        );
+        assert_eq!(
+            mini_snapshot_accounts(&bank, &[program_id], &[missing_address]).unwrap_err(),
+            format!("Account {} not found", missing_address)
+        );
+    }
+}
@@ -0,0 +1,280 @@
+/// Recording and comparison of replay results, used by `verify --record-account-hashes` and
+/// `verify --compare` to track down non-determinism between runs or releases
+use crossbeam_channel::{unbounded, Receiver, RecvTimeoutError};
+use itertools::izip;
+use serde::{Deserialize, Serialize};
+use solana_ledger::blockstore_processor::{
+    ProcessCallback, TransactionStatusBatch, TransactionStatusSender,
+};
+use solana_runtime::{bank::Bank, transaction_utils::OrderedIterator};
+use solana_sdk::clock::Slot;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{Builder, JoinHandle},
+    time::Duration,
+};
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionRecord {
+    pub signature: String,
+    pub error: Option<String>,
+    /// Lamport balance changes by account address, accounts without a change are omitted
+    pub lamport_deltas: BTreeMap<String, i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotRecord {
+    pub slot: Slot,
+    pub parent_slot: Slot,
+    pub bank_hash: String,
+    pub capitalization: u64,
+    /// Sorted by signature, so that records don't depend on the entry batching
+    pub transactions: Vec<TransactionRecord>,
+}
+
+#[derive(Default)]
+struct PendingSlot {
+    frozen: Option<(Slot, String, u64)>,
+    transactions: BTreeMap<String, TransactionRecord>,
+}
+
+pub struct ReplayRecorder {
+    slots: Arc<Mutex<BTreeMap<Slot, PendingSlot>>>,
+    transaction_status_sender: TransactionStatusSender,
+    exit: Arc<AtomicBool>,
+    thread_hdl: JoinHandle<()>,
+}
+
+impl ReplayRecorder {
+    pub fn new() -> Self {
+        let slots = Arc::new(Mutex::new(BTreeMap::new()));
+        let exit = Arc::new(AtomicBool::new(false));
+        let (transaction_status_sender, transaction_status_receiver) = unbounded();
+        let thread_hdl = {
+            let slots = slots.clone();
+            let exit = exit.clone();
+            Builder::new()
+                .name("replay-recorder".to_string())
+                .spawn(move || loop {
+                    match Self::record_transaction_status_batch(
+                        &transaction_status_receiver,
+                        &slots,
+                    ) {
+                        Ok(()) => {}
+                        Err(RecvTimeoutError::Timeout) if !exit.load(Ordering::Relaxed) => {}
+                        Err(_) => break,
+                    }
+                })
+                .unwrap()
+        };
+        Self {
+            slots,
+            transaction_status_sender,
+            exit,
+            thread_hdl,
+        }
+    }
+
+    pub fn transaction_status_sender(&self) -> TransactionStatusSender {
+        self.transaction_status_sender.clone()
+    }
+
+    /// Callback recording the hash of each bank once it is frozen
+    pub fn slot_callback(&self) -> ProcessCallback {
+        let slots = self.slots.clone();
+        Arc::new(move |bank: &Bank| {
+            slots.lock().unwrap().entry(bank.slot()).or_default().frozen = Some((
+                bank.parent_slot(),
+                bank.hash().to_string(),
+                bank.capitalization(),
+            ));
+        })
+    }
+
+    /// Waits for the pending transaction statuses and returns the records of the frozen slots
+    pub fn finish(self) -> Vec<SlotRecord> {
+        self.exit.store(true, Ordering::Relaxed);
+        drop(self.transaction_status_sender);
+        self.thread_hdl.join().unwrap();
+
+        let slots = std::mem::take(&mut *self.slots.lock().unwrap());
+        slots
+            .into_iter()
+            .filter_map(|(slot, pending_slot)| {
+                let (parent_slot, bank_hash, capitalization) = pending_slot.frozen?;
+                Some(SlotRecord {
+                    slot,
+                    parent_slot,
+                    bank_hash,
+                    capitalization,
+                    transactions: pending_slot
+                        .transactions
+                        .into_iter()
+                        .map(|(_, t)| t)
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+
+    fn record_transaction_status_batch(
+        transaction_status_receiver: &Receiver<TransactionStatusBatch>,
+        slots: &Mutex<BTreeMap<Slot, PendingSlot>>,
+    ) -> Result<(), RecvTimeoutError> {
+        let TransactionStatusBatch {
+            bank,
+            transactions,
+            iteration_order,
+            statuses,
+            balances,
+            ..
+        } = transaction_status_receiver.recv_timeout(Duration::from_millis(100))?;
+
+        let mut slots = slots.lock().unwrap();
+        let pending_slot = slots.entry(bank.slot()).or_default();
+        for ((_, transaction), (status, _), pre_balances, post_balances) in izip!(
+            OrderedIterator::new(&transactions, iteration_order.as_deref()),
+            statuses,
+            balances.pre_balances,
+            balances.post_balances
+        ) {
+            if !Bank::can_commit(&status) || transaction.signatures.is_empty() {
+                continue;
+            }
+            let lamport_deltas = izip!(
+                &transaction.message.account_keys,
+                pre_balances,
+                post_balances
+            )
+            .filter(|(_, pre_balance, post_balance)| pre_balance != post_balance)
+            .map(|(pubkey, pre_balance, post_balance)| {
+                (pubkey.to_string(), post_balance as i64 - pre_balance as i64)
+            })
+            .collect();
+            let signature = transaction.signatures[0].to_string();
+            pending_slot.transactions.insert(
+                signature.clone(),
+                TransactionRecord {
+                    signature,
+                    error: status.err().map(|err| err.to_string()),
+                    lamport_deltas,
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn write_replay_record(path: &Path, slots: &[SlotRecord]) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| format!("Unable to create {:?}: {}", path, err))?;
+    serde_json::to_writer(BufWriter::new(file), slots)
+        .map_err(|err| format!("Unable to write {:?}: {}", path, err))
+}
+
+pub fn read_replay_record(path: &Path) -> Result<Vec<SlotRecord>, String> {
+    let file = File::open(path).map_err(|err| format!("Unable to open {:?}: {}", path, err))?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|err| format!("Unable to read {:?}: {}", path, err))
+}
+
+/// Prints the differences between two replay records, returning the first divergent slot
+pub fn compare_replay_records(baseline: &[SlotRecord], current: &[SlotRecord]) -> Option<Slot> {
+    let baseline: BTreeMap<_, _> = baseline
+        .iter()
+        .map(|record| (record.slot, record))
+        .collect();
+    let current: BTreeMap<_, _> = current.iter().map(|record| (record.slot, record)).collect();
+
+    let mut first_divergent_slot = None;
+    let mut divergent_slots = 0;
+    let mut common_slots = 0;
+    for (slot, baseline_record) in &baseline {
+        let current_record = match current.get(slot) {
+            Some(current_record) => current_record,
+            None => continue,
+        };
+        common_slots += 1;
+        if baseline_record == current_record {
+            continue;
+        }
+        divergent_slots += 1;
+        first_divergent_slot.get_or_insert(*slot);
+
+        println!("Slot {} diverged:", slot);
+        if baseline_record.bank_hash != current_record.bank_hash {
+            println!(
+                "  bank hash: {} != {}",
+                baseline_record.bank_hash, current_record.bank_hash
+            );
+        }
+        if baseline_record.capitalization != current_record.capitalization {
+            println!(
+                "  capitalization: {} != {}",
+                baseline_record.capitalization, current_record.capitalization
+            );
+        }
+        print_transaction_differences(&baseline_record.transactions, &current_record.transactions);
+    }
+
+    let only_in = |records: &BTreeMap<Slot, &SlotRecord>, other: &BTreeMap<Slot, &SlotRecord>| {
+        records
+            .keys()
+            .filter(|slot| !other.contains_key(slot))
+            .count()
+    };
+    println!(
+        "Compared {} slots, {} diverged. {} slots only in the baseline, {} only in this run",
+        common_slots,
+        divergent_slots,
+        only_in(&baseline, &current),
+        only_in(&current, &baseline),
+    );
+    if let Some(slot) = first_divergent_slot {
+        println!("First divergent slot: {}", slot);
+    }
+    first_divergent_slot
+}
+
+fn print_transaction_differences(baseline: &[TransactionRecord], current: &[TransactionRecord]) {
+    let baseline: BTreeMap<_, _> = baseline.iter().map(|t| (&t.signature, t)).collect();
+    let current: BTreeMap<_, _> = current.iter().map(|t| (&t.signature, t)).collect();
+    let signatures: BTreeSet<_> = baseline.keys().chain(current.keys()).collect();
+
+    for signature in signatures {
+        match (baseline.get(signature), current.get(signature)) {
+            (Some(_), None) => println!("  transaction {} only in the baseline", signature),
+            (None, Some(_)) => println!("  transaction {} only in this run", signature),
+            (Some(baseline), Some(current)) if baseline != current => {
+                println!("  transaction {}:", signature);
+                if baseline.error != current.error {
+                    println!("    error: {:?} != {:?}", baseline.error, current.error);
+                }
+                let addresses: BTreeSet<_> = baseline
+                    .lamport_deltas
+                    .keys()
+                    .chain(current.lamport_deltas.keys())
+                    .collect();
+                for address in addresses {
+                    let baseline_delta = baseline.lamport_deltas.get(address).unwrap_or(&0);
+                    let current_delta = current.lamport_deltas.get(address).unwrap_or(&0);
+                    if baseline_delta != current_delta {
+                        println!(
+                            "    {} lamport delta: {} != {}",
+                            address, baseline_delta, current_delta
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,83 @@
+/// Audit of the signing journal a validator appends to with `--signing-journal`: prints the
+/// events matching the filters, then the slots for which different hashes were signed
+use chrono::NaiveDateTime;
+use clap::{value_t, value_t_or_exit, values_t, ArgMatches};
+use solana_clap_utils::input_parsers::pubkey_of;
+use solana_ledger::signing_journal::{
+    find_conflicting_hashes, read_signing_journal, SigningEvent, SigningEventKind,
+};
+use solana_sdk::clock::Slot;
+use std::{collections::BTreeMap, path::PathBuf, process::exit};
+
+fn format_timestamp(timestamp: u64) -> String {
+    NaiveDateTime::from_timestamp(
+        (timestamp / 1000) as i64,
+        (timestamp % 1000) as u32 * 1_000_000,
+    )
+    .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+    .to_string()
+}
+
+fn print_event(event: &SigningEvent) {
+    let signers: Vec<_> = event.signers.iter().map(|s| s.to_string()).collect();
+    println!(
+        "{} {:<25} slot {:<10} hash {:<44} signature {:<88} signers {}",
+        format_timestamp(event.timestamp),
+        event.kind.to_string(),
+        event.slot,
+        event.hash,
+        event.signature,
+        signers.join(",")
+    );
+}
+
+pub fn signing_journal_process_command(matches: &ArgMatches<'_>) {
+    let path = value_t_or_exit!(matches, "journal", PathBuf);
+    let kinds = values_t!(matches, "kind", SigningEventKind).ok();
+    let signer = pubkey_of(matches, "signer");
+    let starting_slot = value_t_or_exit!(matches, "starting_slot", Slot);
+    let ending_slot = value_t!(matches, "ending_slot", Slot).unwrap_or(Slot::MAX);
+
+    let events = read_signing_journal(&path).unwrap_or_else(|err| {
+        eprintln!("Unable to read {}: {}", path.display(), err);
+        exit(1);
+    });
+    let events: Vec<_> = events
+        .into_iter()
+        .filter(|event| {
+            kinds
+                .as_ref()
+                .map_or(true, |kinds| kinds.contains(&event.kind))
+                && signer.map_or(true, |signer| event.signers.contains(&signer))
+                && event.slot >= starting_slot
+                && event.slot <= ending_slot
+        })
+        .collect();
+
+    let mut counts = BTreeMap::new();
+    for event in &events {
+        print_event(event);
+        *counts.entry(event.kind).or_insert(0) += 1;
+    }
+    println!();
+    for (kind, count) in &counts {
+        println!("{} {} events", count, kind);
+    }
+
+    // Two blocks, or votes on two versions of a block, for one slot are what a key used by two
+    // validators at once signs
+    let conflicts = find_conflicting_hashes(&events);
+    if !conflicts.is_empty() {
+        for ((kind, slot), hashes) in &conflicts {
+            let hashes: Vec<_> = hashes.iter().map(|hash| hash.to_string()).collect();
+            eprintln!(
+                "Slot {}: {} events signed {} different hashes: {}",
+                slot,
+                kind,
+                hashes.len(),
+                hashes.join(", ")
+            );
+        }
+        exit(1);
+    }
+}
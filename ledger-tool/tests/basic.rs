@@ -47,3 +47,88 @@ fn nominal() {
     assert!(output.status.success());
     assert_eq!(count_newlines(&output.stdout), ticks + meta_lines);
 }
+
+#[test]
+fn abi_check() {
+    // No ledger is needed
+    let output = run_ledger_tool(&["abi-check"]);
+    assert!(output.status.success());
+    let digests = String::from_utf8(output.stdout).unwrap();
+    assert!(digests
+        .lines()
+        .all(|line| line.split_whitespace().count() == 2));
+    // Computed by nightly builds, pinned ones printed by the others
+    assert!(digests
+        .lines()
+        .any(|line| line
+            == "solana_sdk::account::Account 727tKRcoDvPiAsXxfvfsUauvZ4tLSw2WSw4HQfRQJ9Xx"));
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("digests");
+    std::fs::write(&path, &digests).unwrap();
+    let output = run_ledger_tool(&["abi-check", "--compare", path.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    std::fs::write(&path, "solana_sdk::account::Account 1\n").unwrap();
+    let output = run_ledger_tool(&["abi-check", "--compare", path.to_str().unwrap()]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn graph() {
+    let genesis_config = create_genesis_config(100).genesis_config;
+    let (ledger_path, _blockhash) = create_new_tmp_ledger!(&genesis_config);
+    let ledger_path = ledger_path.to_str().unwrap();
+    let dir = tempfile::TempDir::new().unwrap();
+    let graph_path = dir.path().join("graph.dot");
+    let graph_path = graph_path.to_str().unwrap();
+
+    // Slot 0 is the genesis root
+    let output = run_ledger_tool(&["-l", &ledger_path, "graph", graph_path]);
+    assert!(output.status.success());
+    let dot = std::fs::read_to_string(graph_path).unwrap();
+    let node = dot
+        .lines()
+        .find(|line| line.starts_with(r#"    "0"[label="#))
+        .unwrap();
+    assert!(node.contains("(epoch 0, rooted)"));
+    assert!(node.contains("\\nhash: "));
+    assert!(node.contains(r#"style="filled,bold""#));
+
+    let output = run_ledger_tool(&[
+        "-l",
+        &ledger_path,
+        "graph",
+        "--starting-slot",
+        "not-a-slot",
+        graph_path,
+    ]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn mini_snapshot() {
+    let genesis_config = create_genesis_config(100).genesis_config;
+    let (ledger_path, _blockhash) = create_new_tmp_ledger!(&genesis_config);
+    let ledger_path = ledger_path.to_str().unwrap();
+    let dir = tempfile::TempDir::new().unwrap();
+    let output_directory = dir.path().to_str().unwrap();
+    let program_id = solana_sdk::pubkey::new_rand().to_string();
+
+    // At least one program is required
+    let output = run_ledger_tool(&["-l", &ledger_path, "mini-snapshot", "1", output_directory]);
+    assert!(!output.status.success());
+
+    // The genesis slot can't be snapshotted
+    let output = run_ledger_tool(&[
+        "-l",
+        &ledger_path,
+        "mini-snapshot",
+        "0",
+        output_directory,
+        "--program",
+        &program_id,
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("must come after genesis"));
+}
@@ -47,9 +47,18 @@ pub fn load(
         fs::create_dir_all(&snapshot_config.snapshot_path)
             .expect("Couldn't create snapshot directory");
 
-        match snapshot_utils::get_highest_snapshot_archive_path(
-            &snapshot_config.snapshot_package_output_path,
-        ) {
+        let archive = match process_options.snapshot_max_slot {
+            Some(snapshot_max_slot) => {
+                snapshot_utils::get_highest_snapshot_archive_path_at_or_below(
+                    &snapshot_config.snapshot_package_output_path,
+                    snapshot_max_slot,
+                )
+            }
+            None => snapshot_utils::get_highest_snapshot_archive_path(
+                &snapshot_config.snapshot_package_output_path,
+            ),
+        };
+        match archive {
             Some((archive_filename, (archive_slot, archive_snapshot_hash, compression))) => {
                 info!("Loading snapshot package: {:?}", archive_filename);
                 // Fail hard here if snapshot fails to load, don't silently continue
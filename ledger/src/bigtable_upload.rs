@@ -25,6 +25,7 @@ pub async fn upload_confirmed_blocks(
     starting_slot: Slot,
     ending_slot: Option<Slot>,
     allow_missing_metadata: bool,
+    force_reupload: bool,
     exit: Arc<AtomicBool>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut measure = Measure::start("entire upload");
@@ -64,37 +65,15 @@ pub async fn upload_confirmed_blocks(
     );
 
     // Gather the blocks that are already present in bigtable, by slot
-    let bigtable_slots = {
-        let mut bigtable_slots = vec![];
-        let first_blockstore_slot = *blockstore_slots.first().unwrap();
-        let last_blockstore_slot = *blockstore_slots.last().unwrap();
-        info!(
-            "Loading list of bigtable blocks between slots {} and {}...",
-            first_blockstore_slot, last_blockstore_slot
-        );
-
-        let mut start_slot = *blockstore_slots.first().unwrap();
-        while start_slot <= last_blockstore_slot {
-            let mut next_bigtable_slots = loop {
-                match bigtable.get_confirmed_blocks(start_slot, 1000).await {
-                    Ok(slots) => break slots,
-                    Err(err) => {
-                        error!("get_confirmed_blocks for {} failed: {:?}", start_slot, err);
-                        // Consider exponential backoff...
-                        delay_for(Duration::from_secs(2)).await;
-                    }
-                }
-            };
-            if next_bigtable_slots.is_empty() {
-                break;
-            }
-            bigtable_slots.append(&mut next_bigtable_slots);
-            start_slot = bigtable_slots.last().unwrap() + 1;
-        }
-        bigtable_slots
-            .into_iter()
-            .filter(|slot| *slot <= last_blockstore_slot)
-            .collect::<Vec<_>>()
+    let bigtable_slots = if force_reupload {
+        vec![]
+    } else {
+        get_bigtable_slots(
+            &bigtable,
+            *blockstore_slots.first().unwrap(),
+            *blockstore_slots.last().unwrap(),
+        )
+        .await
     };
 
     // The blocks that still need to be uploaded is the difference between what's already in the
@@ -122,6 +101,66 @@ pub async fn upload_confirmed_blocks(
         blocks_to_upload.last().unwrap()
     );
 
+    let result = upload_blocks(
+        blockstore,
+        bigtable,
+        blocks_to_upload,
+        allow_missing_metadata,
+        exit,
+    )
+    .await;
+
+    measure.stop();
+    info!("{}", measure);
+    result
+}
+
+/// Returns the slots of the blocks present in bigtable from `first_slot` to `last_slot`,
+/// inclusive
+pub async fn get_bigtable_slots(
+    bigtable: &solana_storage_bigtable::LedgerStorage,
+    first_slot: Slot,
+    last_slot: Slot,
+) -> Vec<Slot> {
+    let mut bigtable_slots = vec![];
+    info!(
+        "Loading list of bigtable blocks between slots {} and {}...",
+        first_slot, last_slot
+    );
+
+    let mut start_slot = first_slot;
+    while start_slot <= last_slot {
+        let mut next_bigtable_slots = loop {
+            match bigtable.get_confirmed_blocks(start_slot, 1000).await {
+                Ok(slots) => break slots,
+                Err(err) => {
+                    error!("get_confirmed_blocks for {} failed: {:?}", start_slot, err);
+                    // Consider exponential backoff...
+                    delay_for(Duration::from_secs(2)).await;
+                }
+            }
+        };
+        if next_bigtable_slots.is_empty() {
+            break;
+        }
+        bigtable_slots.append(&mut next_bigtable_slots);
+        start_slot = bigtable_slots.last().unwrap() + 1;
+    }
+    bigtable_slots
+        .into_iter()
+        .filter(|slot| *slot <= last_slot)
+        .collect()
+}
+
+/// Uploads the blocks of `blocks_to_upload` from blockstore to bigtable, overwriting any
+/// already present
+pub async fn upload_blocks(
+    blockstore: Arc<Blockstore>,
+    bigtable: solana_storage_bigtable::LedgerStorage,
+    blocks_to_upload: Vec<Slot>,
+    allow_missing_metadata: bool,
+    exit: Arc<AtomicBool>,
+) -> Result<(), Box<dyn std::error::Error>> {
     // Load the blocks out of blockstore in a separate thread to allow for concurrent block uploading
     let (_loader_thread, receiver) = {
         let exit = exit.clone();
@@ -209,8 +248,6 @@ pub async fn upload_confirmed_blocks(
         info!("{} for {} blocks", measure_upload, num_blocks);
     }
 
-    measure.stop();
-    info!("{}", measure);
     if failures > 0 {
         Err(format!("Incomplete upload, {} operations failed", failures).into())
     } else {
@@ -29,4 +29,8 @@ pub enum BlockError {
 
     #[error("duplicate block")]
     DuplicateBlock,
+
+    /// Blocks can not cost more than the max block units, see `solana_runtime::block_cost`
+    #[error("max block units exceeded")]
+    MaxBlockUnitsExceeded,
 }
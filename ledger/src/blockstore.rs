@@ -4,8 +4,8 @@
 pub use crate::{blockstore_db::BlockstoreError, blockstore_meta::SlotMeta};
 use crate::{
     blockstore_db::{
-        columns as cf, AccessType, BlockstoreRecoveryMode, Column, Database, IteratorDirection,
-        IteratorMode, LedgerColumn, Result, WriteBatch,
+        columns as cf, AccessType, BlockstoreRecoveryMode, BlockstoreRocksOptions, Column,
+        Database, IteratorDirection, IteratorMode, LedgerColumn, Result, WriteBatch,
     },
     blockstore_meta::*,
     entry::{create_ticks, Entry},
@@ -23,7 +23,7 @@ use rayon::{
 use rocksdb::DBRawIterator;
 use solana_measure::measure::Measure;
 use solana_metrics::{datapoint_debug, datapoint_error};
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_runtime::{
     hardened_unpack::{unpack_genesis_archive, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     vote_account::ArcVoteAccount,
@@ -64,21 +64,16 @@ use std::{
 use thiserror::Error;
 use trees::{Tree, TreeWalk};
 
+pub mod blockstore_fsck;
 pub mod blockstore_purge;
 
 pub const BLOCKSTORE_DIRECTORY: &str = "rocksdb";
 
-thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
-                    .num_threads(get_thread_count())
-                    .thread_name(|ix| format!("blockstore_{}", ix))
-                    .build()
-                    .unwrap()));
+thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(
+    thread_manager::build_thread_pool("solana-blockstore", get_thread_count())));
 
-thread_local!(static PAR_THREAD_POOL_ALL_CPUS: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
-                    .num_threads(num_cpus::get())
-                    .thread_name(|ix| format!("blockstore_{}", ix))
-                    .build()
-                    .unwrap()));
+thread_local!(static PAR_THREAD_POOL_ALL_CPUS: RefCell<ThreadPool> = RefCell::new(
+    thread_manager::build_thread_pool("solana-blockstore-all-cpus", num_cpus::get())));
 
 pub const MAX_COMPLETED_SLOTS_IN_CHANNEL: usize = 100_000;
 pub const MAX_TURBINE_PROPAGATION_IN_MS: u64 = 100;
@@ -142,6 +137,7 @@ pub struct Blockstore {
     rewards_cf: LedgerColumn<cf::Rewards>,
     blocktime_cf: LedgerColumn<cf::Blocktime>,
     perf_samples_cf: LedgerColumn<cf::PerfSamples>,
+    transaction_memos_cf: LedgerColumn<cf::TransactionMemos>,
     last_root: Arc<RwLock<Slot>>,
     insert_shreds_lock: Arc<Mutex<()>>,
     pub new_shreds_signals: Vec<SyncSender<bool>>,
@@ -253,7 +249,13 @@ impl Blockstore {
 
     /// Opens a Ledger in directory, provides "infinite" window of shreds
     pub fn open(ledger_path: &Path) -> Result<Blockstore> {
-        Self::do_open(ledger_path, AccessType::PrimaryOnly, None, true)
+        Self::do_open(
+            ledger_path,
+            AccessType::PrimaryOnly,
+            None,
+            true,
+            &BlockstoreRocksOptions::default(),
+        )
     }
 
     pub fn open_with_access_type(
@@ -267,6 +269,7 @@ impl Blockstore {
             access_type,
             recovery_mode,
             enforce_ulimit_nofile,
+            &BlockstoreRocksOptions::default(),
         )
     }
 
@@ -275,6 +278,7 @@ impl Blockstore {
         access_type: AccessType,
         recovery_mode: Option<BlockstoreRecoveryMode>,
         enforce_ulimit_nofile: bool,
+        rocks_options: &BlockstoreRocksOptions,
     ) -> Result<Blockstore> {
         fs::create_dir_all(&ledger_path)?;
         let blockstore_path = ledger_path.join(BLOCKSTORE_DIRECTORY);
@@ -284,7 +288,12 @@ impl Blockstore {
         // Open the database
         let mut measure = Measure::start("open");
         info!("Opening database at {:?}", blockstore_path);
-        let db = Database::open(&blockstore_path, access_type, recovery_mode)?;
+        let db = Database::open_with_options(
+            &blockstore_path,
+            access_type,
+            recovery_mode,
+            rocks_options,
+        )?;
 
         // Create the metadata column family
         let meta_cf = db.column();
@@ -308,6 +317,7 @@ impl Blockstore {
         let rewards_cf = db.column();
         let blocktime_cf = db.column();
         let perf_samples_cf = db.column();
+        let transaction_memos_cf = db.column();
 
         let db = Arc::new(db);
 
@@ -354,6 +364,7 @@ impl Blockstore {
             rewards_cf,
             blocktime_cf,
             perf_samples_cf,
+            transaction_memos_cf,
             new_shreds_signals: vec![],
             completed_slots_senders: vec![],
             insert_shreds_lock: Arc::new(Mutex::new(())),
@@ -372,11 +383,26 @@ impl Blockstore {
         recovery_mode: Option<BlockstoreRecoveryMode>,
         enforce_ulimit_nofile: bool,
     ) -> Result<BlockstoreSignals> {
-        let mut blockstore = Self::open_with_access_type(
+        Self::open_with_signal_and_options(
+            ledger_path,
+            recovery_mode,
+            enforce_ulimit_nofile,
+            &BlockstoreRocksOptions::default(),
+        )
+    }
+
+    pub fn open_with_signal_and_options(
+        ledger_path: &Path,
+        recovery_mode: Option<BlockstoreRecoveryMode>,
+        enforce_ulimit_nofile: bool,
+        rocks_options: &BlockstoreRocksOptions,
+    ) -> Result<BlockstoreSignals> {
+        let mut blockstore = Self::do_open(
             ledger_path,
             AccessType::PrimaryOnly,
             recovery_mode,
             enforce_ulimit_nofile,
+            rocks_options,
         )?;
         let (ledger_signal_sender, ledger_signal_receiver) = sync_channel(1);
         let (completed_slots_sender, completed_slots_receiver) =
@@ -477,6 +503,15 @@ impl Blockstore {
             .unwrap_or(0)
     }
 
+    /// Returns the highest slot with shreds in the blockstore
+    pub fn highest_slot(&self) -> Result<Option<Slot>> {
+        Ok(self
+            .db
+            .iter::<cf::SlotMeta>(IteratorMode::End)?
+            .next()
+            .map(|(slot, _)| slot))
+    }
+
     pub fn slot_meta_iterator(
         &self,
         slot: Slot,
@@ -1667,6 +1702,32 @@ impl Blockstore {
         }
     }
 
+    /// Returns up to `max_missing` indexes of the data shreds of the slot described by
+    /// `slot_meta` that have not been received, including the ones that may still arrive through
+    /// turbine.  Only holes below the highest received shred are reported until the last shred
+    /// of the slot is known.
+    pub fn find_missing_data_indexes_in_slot(
+        &self,
+        slot_meta: &SlotMeta,
+        max_missing: usize,
+    ) -> Vec<u64> {
+        if slot_meta.is_full() {
+            return vec![];
+        }
+        let end_index = if slot_meta.last_index == std::u64::MAX {
+            slot_meta.received
+        } else {
+            slot_meta.last_index + 1
+        };
+        self.find_missing_data_indexes(
+            slot_meta.slot,
+            0,
+            slot_meta.consumed,
+            end_index,
+            max_missing,
+        )
+    }
+
     pub fn get_block_time(&self, slot: Slot) -> Result<Option<UnixTimestamp>> {
         datapoint_info!(
             "blockstore-rpc-api",
@@ -1971,6 +2032,30 @@ impl Blockstore {
         Ok(())
     }
 
+    pub fn read_transaction_memos(&self, index: (Signature, Slot)) -> Result<Option<String>> {
+        let (signature, slot) = index;
+        let result = self.transaction_memos_cf.get((0, signature, slot))?;
+        if result.is_none() {
+            Ok(self.transaction_memos_cf.get((1, signature, slot))?)
+        } else {
+            Ok(result)
+        }
+    }
+
+    pub fn write_transaction_memos(
+        &self,
+        slot: Slot,
+        signature: &Signature,
+        memos: String,
+    ) -> Result<()> {
+        // Memos share the primary index of transaction statuses, so they are purged with them
+        let mut w_active_transaction_status_index =
+            self.active_transaction_status_index.write().unwrap();
+        let primary_index = self.get_primary_index(slot, &mut w_active_transaction_status_index)?;
+        self.transaction_memos_cf
+            .put((primary_index, *signature, slot), &memos)
+    }
+
     // Returns a transaction status if it was processed in a root, as well as a loop counter for
     // unit testing
     fn get_transaction_status_with_counter(
@@ -2325,12 +2410,13 @@ impl Blockstore {
                 None => None,
                 Some((_slot, status)) => status.status.err(),
             };
+            let memo = self.read_transaction_memos((signature, slot))?;
             let block_time = self.get_block_time(slot)?;
             infos.push(ConfirmedTransactionStatusWithSignature {
                 signature,
                 slot,
                 err,
-                memo: None,
+                memo,
                 block_time,
             });
         }
@@ -3593,6 +3679,7 @@ fn adjust_ulimit_nofile(enforce_ulimit_nofile: bool) -> Result<()> {
 pub mod tests {
     use super::*;
     use crate::{
+        blockstore_db::BlockstoreCompressionType,
         blockstore_processor::fill_blockstore_slot_with_ticks,
         entry::{next_entry, next_entry_mut},
         genesis_utils::{create_genesis_config, GenesisConfigInfo},
@@ -3684,6 +3771,41 @@ pub mod tests {
         Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_open_with_rocks_options() {
+        let (shreds, entries) = make_slot_entries(0, 0, 100);
+
+        let mut column_compression = HashMap::new();
+        column_compression.insert("data_shred".to_string(), BlockstoreCompressionType::Lz4);
+        column_compression.insert("code_shred".to_string(), BlockstoreCompressionType::None);
+        // Unknown columns are ignored
+        column_compression.insert("not_a_column".to_string(), BlockstoreCompressionType::Lz4hc);
+        let rocks_options = BlockstoreRocksOptions {
+            write_buffer_size: 4 * 1024 * 1024,
+            max_write_buffer_number: 2,
+            max_background_jobs: Some(2),
+            column_compression,
+            pin_l0_filter_and_index_blocks: true,
+        };
+
+        let ledger_path = get_tmp_ledger_path!();
+        {
+            let ledger =
+                Blockstore::open_with_signal_and_options(&ledger_path, None, true, &rocks_options)
+                    .unwrap()
+                    .blockstore;
+            ledger.insert_shreds(shreds, None, false).unwrap();
+            assert_eq!(ledger.get_slot_entries(0, 0).unwrap(), entries);
+        }
+
+        // A ledger written with custom options can be reopened with the defaults
+        {
+            let ledger = Blockstore::open(&ledger_path).unwrap();
+            assert_eq!(ledger.get_slot_entries(0, 0).unwrap(), entries);
+        }
+        Blockstore::destroy(&ledger_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_write_entries() {
         solana_logger::setup();
@@ -4805,6 +4927,22 @@ pub mod tests {
             Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
         }
     */
+    #[test]
+    fn test_highest_slot() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            assert_eq!(blockstore.highest_slot().unwrap(), None);
+
+            for slot in &[5, 2, 9] {
+                let (shreds, _) = make_slot_entries(*slot, 0, 1);
+                blockstore.insert_shreds(shreds, None, false).unwrap();
+            }
+            assert_eq!(blockstore.highest_slot().unwrap(), Some(9));
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     pub fn test_get_slots_since() {
         let blockstore_path = get_tmp_ledger_path!();
@@ -4976,6 +5114,49 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_find_missing_data_indexes_in_slot() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let (shreds, _) = make_slot_entries(1, 0, max_ticks_per_n_shreds(8, None));
+            let num_shreds = shreds.len() as u64;
+            assert!(num_shreds > 5);
+            let missing = [1, 3, num_shreds - 2];
+            let received: Vec<_> = shreds
+                .into_iter()
+                .filter(|shred| !missing.contains(&u64::from(shred.index())))
+                .collect();
+
+            // Without the last shred of the slot, only the holes below the highest received
+            // shred are known to be missing
+            let (last_shred, received) = received.split_last().unwrap();
+            blockstore
+                .insert_shreds(received.to_vec(), None, false)
+                .unwrap();
+            let slot_meta = blockstore.meta(1).unwrap().unwrap();
+            assert_eq!(
+                blockstore.find_missing_data_indexes_in_slot(&slot_meta, 10),
+                vec![1, 3]
+            );
+            assert_eq!(
+                blockstore.find_missing_data_indexes_in_slot(&slot_meta, 1),
+                vec![1]
+            );
+
+            blockstore
+                .insert_shreds(vec![last_shred.clone()], None, false)
+                .unwrap();
+            let slot_meta = blockstore.meta(1).unwrap().unwrap();
+            assert_eq!(slot_meta.last_index, num_shreds - 1);
+            assert_eq!(
+                blockstore.find_missing_data_indexes_in_slot(&slot_meta, 10),
+                missing.to_vec()
+            );
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_find_missing_data_indexes() {
         let slot = 0;
@@ -7088,6 +7269,39 @@ pub mod tests {
         Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
     }
 
+    #[test]
+    fn test_write_read_transaction_memos() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            let signature = Signature::new(&[1u8; 64]);
+            assert_eq!(
+                blockstore.read_transaction_memos((signature, 0)).unwrap(),
+                None
+            );
+
+            let memos = "[9] Test memo".to_string();
+            blockstore
+                .write_transaction_memos(0, &signature, memos.clone())
+                .unwrap();
+            assert_eq!(
+                blockstore.read_transaction_memos((signature, 0)).unwrap(),
+                Some(memos)
+            );
+            assert_eq!(
+                blockstore.read_transaction_memos((signature, 1)).unwrap(),
+                None
+            );
+            assert_eq!(
+                blockstore
+                    .read_transaction_memos((Signature::new(&[2u8; 64]), 0))
+                    .unwrap(),
+                None
+            );
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+
     #[test]
     fn test_lowest_slot() {
         let blockstore_path = get_tmp_ledger_path!();
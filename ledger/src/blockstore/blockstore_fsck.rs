@@ -0,0 +1,260 @@
+//! Consistency checks across the blockstore columns, and the repair of ledgers left
+//! inconsistent by a crash.
+
+use super::*;
+use std::{collections::BTreeSet, fmt};
+
+/// An inconsistency between blockstore columns found by `Blockstore::check_integrity()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    /// The slot meta counts as consumed data shreds that are missing from the data column
+    MissingDataShreds { slot: Slot, count: u64 },
+    /// The slot has received shreds but has no index
+    MissingIndex { slot: Slot },
+    /// Data shreds are stored for a slot without a slot meta
+    ShredsWithoutMeta { slot: Slot },
+    /// An erasure set is stored for a slot without a slot meta
+    DanglingErasureMeta { slot: Slot, set_index: u64 },
+    /// The slot meta lists a child without a slot meta
+    DanglingNextSlot { slot: Slot, next_slot: Slot },
+}
+
+impl IntegrityIssue {
+    /// The slot to drop to resolve the issue
+    pub fn corrupt_slot(&self) -> Slot {
+        match self {
+            IntegrityIssue::MissingDataShreds { slot, .. }
+            | IntegrityIssue::MissingIndex { slot }
+            | IntegrityIssue::ShredsWithoutMeta { slot }
+            | IntegrityIssue::DanglingErasureMeta { slot, .. } => *slot,
+            IntegrityIssue::DanglingNextSlot { next_slot, .. } => *next_slot,
+        }
+    }
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntegrityIssue::MissingDataShreds { slot, count } => write!(
+                f,
+                "slot {}: {} consumed data shreds are missing",
+                slot, count
+            ),
+            IntegrityIssue::MissingIndex { slot } => {
+                write!(
+                    f,
+                    "slot {}: shreds were received but the index is missing",
+                    slot
+                )
+            }
+            IntegrityIssue::ShredsWithoutMeta { slot } => {
+                write!(
+                    f,
+                    "slot {}: data shreds are stored without a slot meta",
+                    slot
+                )
+            }
+            IntegrityIssue::DanglingErasureMeta { slot, set_index } => write!(
+                f,
+                "slot {}: erasure set {} is stored without a slot meta",
+                slot, set_index
+            ),
+            IntegrityIssue::DanglingNextSlot { slot, next_slot } => {
+                write!(f, "slot {}: next slot {} has no slot meta", slot, next_slot)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IntegrityReport {
+    pub slots_checked: u64,
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Blockstore {
+    /// Cross-checks the slot meta, index, data shred and erasure meta columns of the slots
+    /// from `start_slot` onward
+    pub fn check_integrity(&self, start_slot: Slot) -> Result<IntegrityReport> {
+        let mut report = IntegrityReport::default();
+
+        for (slot, meta) in self.slot_meta_iterator(start_slot)? {
+            report.slots_checked += 1;
+            if meta.received > 0 && self.get_index(slot)?.is_none() {
+                report.issues.push(IntegrityIssue::MissingIndex { slot });
+            }
+            let present = self
+                .slot_data_iterator(slot, 0)?
+                .take_while(|((_, index), _)| *index < meta.consumed)
+                .count() as u64;
+            if present < meta.consumed {
+                report.issues.push(IntegrityIssue::MissingDataShreds {
+                    slot,
+                    count: meta.consumed - present,
+                });
+            }
+            for next_slot in &meta.next_slots {
+                if self.meta(*next_slot)?.is_none() {
+                    report.issues.push(IntegrityIssue::DanglingNextSlot {
+                        slot,
+                        next_slot: *next_slot,
+                    });
+                }
+            }
+        }
+
+        // Seek from slot to slot rather than visiting every shred
+        let mut slot = start_slot;
+        while let Some(((shred_slot, _), _)) = self
+            .db
+            .iter::<cf::ShredData>(IteratorMode::From((slot, 0), IteratorDirection::Forward))?
+            .next()
+        {
+            if self.meta(shred_slot)?.is_none() {
+                report
+                    .issues
+                    .push(IntegrityIssue::ShredsWithoutMeta { slot: shred_slot });
+            }
+            match shred_slot.checked_add(1) {
+                Some(next_slot) => slot = next_slot,
+                None => break,
+            }
+        }
+
+        for ((slot, set_index), _) in self.db.iter::<cf::ErasureMeta>(IteratorMode::From(
+            (start_slot, 0),
+            IteratorDirection::Forward,
+        ))? {
+            if self.meta(slot)?.is_none() {
+                report
+                    .issues
+                    .push(IntegrityIssue::DanglingErasureMeta { slot, set_index });
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Drops the slots above the last root that `report` found corrupt, along with their
+    /// descendants, and removes them from the `next_slots` of their parents so that they are
+    /// repaired again.  Corrupt rooted slots are left in place.  Returns the dropped slots
+    pub fn repair_integrity(&self, report: &IntegrityReport) -> Result<Vec<Slot>> {
+        let _lock = self.insert_shreds_lock.lock().unwrap();
+        let last_root = self.last_root();
+
+        let mut dropped_slots = BTreeSet::new();
+        let mut pending_slots: Vec<_> = report
+            .issues
+            .iter()
+            .map(IntegrityIssue::corrupt_slot)
+            .filter(|slot| *slot > last_root)
+            .collect();
+        let mut parents = BTreeSet::new();
+        while let Some(slot) = pending_slots.pop() {
+            if !dropped_slots.insert(slot) {
+                continue;
+            }
+            if let Some(meta) = self.meta(slot)? {
+                parents.insert(meta.parent_slot);
+                pending_slots.extend(meta.next_slots);
+            }
+        }
+        for issue in &report.issues {
+            if let IntegrityIssue::DanglingNextSlot { slot, .. } = issue {
+                parents.insert(*slot);
+            }
+        }
+
+        for slot in &dropped_slots {
+            self.run_purge(*slot, *slot, PurgeType::Exact)?;
+        }
+        for parent in parents.difference(&dropped_slots) {
+            if let Some(mut meta) = self.meta(*parent)? {
+                let original_len = meta.next_slots.len();
+                meta.next_slots
+                    .retain(|next_slot| !dropped_slots.contains(next_slot));
+                if meta.next_slots.len() != original_len {
+                    self.meta_cf.put(*parent, &meta)?;
+                }
+            }
+        }
+
+        if !dropped_slots.is_empty() {
+            warn!(
+                "Dropped {} corrupt slots from the blockstore: {:?}",
+                dropped_slots.len(),
+                dropped_slots
+            );
+        }
+        Ok(dropped_slots.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::get_tmp_ledger_path;
+
+    #[test]
+    fn test_check_and_repair_integrity() {
+        let blockstore_path = get_tmp_ledger_path!();
+        {
+            let blockstore = Blockstore::open(&blockstore_path).unwrap();
+            // Chain slots 0 -> 1 -> 2 -> 3, rooting slot 1
+            let (shreds, _) = make_many_slot_entries(0, 4, 5);
+            blockstore.insert_shreds(shreds, None, false).unwrap();
+            blockstore.set_roots(&[0, 1]).unwrap();
+            let report = blockstore.check_integrity(0).unwrap();
+            assert!(report.is_ok());
+            assert_eq!(report.slots_checked, 4);
+
+            // Lose a data shred of the rooted slot 1 and of slot 2, and the slot meta of
+            // slot 3, leaving its shreds and erasure sets behind
+            let mut write_batch = blockstore.db.batch().unwrap();
+            for slot in 1..3 {
+                write_batch.delete::<cf::ShredData>((slot, 0)).unwrap();
+            }
+            write_batch.delete::<cf::SlotMeta>(3).unwrap();
+            blockstore.db.write(write_batch).unwrap();
+            blockstore
+                .erasure_meta_cf
+                .put((3, 0), &ErasureMeta::default())
+                .unwrap();
+
+            let report = blockstore.check_integrity(0).unwrap();
+            assert_eq!(
+                report.issues,
+                vec![
+                    IntegrityIssue::MissingDataShreds { slot: 1, count: 1 },
+                    IntegrityIssue::MissingDataShreds { slot: 2, count: 1 },
+                    IntegrityIssue::DanglingNextSlot {
+                        slot: 2,
+                        next_slot: 3
+                    },
+                    IntegrityIssue::ShredsWithoutMeta { slot: 3 },
+                    IntegrityIssue::DanglingErasureMeta {
+                        slot: 3,
+                        set_index: 0
+                    },
+                ]
+            );
+            assert_eq!(blockstore.check_integrity(2).unwrap().issues.len(), 4);
+
+            assert_eq!(blockstore.repair_integrity(&report).unwrap(), vec![2, 3]);
+            assert_eq!(blockstore.meta(1).unwrap().unwrap().next_slots, vec![]);
+            assert!(blockstore.meta(2).unwrap().is_none());
+            // The rooted slot is only reported
+            assert_eq!(
+                blockstore.check_integrity(0).unwrap().issues,
+                vec![IntegrityIssue::MissingDataShreds { slot: 1, count: 1 }]
+            );
+        }
+        Blockstore::destroy(&blockstore_path).expect("Expected successful database destruction");
+    }
+}
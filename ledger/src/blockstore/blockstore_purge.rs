@@ -232,6 +232,10 @@ impl Blockstore {
                 .transaction_status_index_cf
                 .compact_range(0, 2)
                 .unwrap_or(false)
+            && self
+                .transaction_memos_cf
+                .compact_range(0, 2)
+                .unwrap_or(false)
             && self
                 .rewards_cf
                 .compact_range(from_slot, to_slot)
@@ -276,6 +280,8 @@ impl Blockstore {
                 if let Some(&signature) = transaction.signatures.get(0) {
                     batch.delete::<cf::TransactionStatus>((0, signature, slot))?;
                     batch.delete::<cf::TransactionStatus>((1, signature, slot))?;
+                    batch.delete::<cf::TransactionMemos>((0, signature, slot))?;
+                    batch.delete::<cf::TransactionMemos>((1, signature, slot))?;
                     for pubkey in transaction.message.account_keys {
                         batch.delete::<cf::AddressSignatures>((0, pubkey, slot, signature))?;
                         batch.delete::<cf::AddressSignatures>((1, pubkey, slot, signature))?;
@@ -315,6 +321,10 @@ impl Blockstore {
                 & self
                     .db
                     .delete_range_cf::<cf::AddressSignatures>(write_batch, index, index + 1)
+                    .is_ok()
+                & self
+                    .db
+                    .delete_range_cf::<cf::TransactionMemos>(write_batch, index, index + 1)
                     .is_ok();
         }
         Ok(())
@@ -528,17 +538,23 @@ pub mod tests {
             let blockstore = Blockstore::open(&blockstore_path).unwrap();
             let transaction_status_index_cf = blockstore.db.column::<cf::TransactionStatusIndex>();
             let slot = 10;
+            let mut signatures = vec![];
             for _ in 0..5 {
                 let random_bytes: Vec<u8> = (0..64).map(|_| rand::random::<u8>()).collect();
+                let signature = Signature::new(&random_bytes);
                 blockstore
                     .write_transaction_status(
                         slot,
-                        Signature::new(&random_bytes),
+                        signature,
                         vec![&Pubkey::new(&random_bytes[0..32])],
                         vec![&Pubkey::new(&random_bytes[32..])],
                         &TransactionStatusMeta::default(),
                     )
                     .unwrap();
+                blockstore
+                    .write_transaction_memos(slot, &signature, "memo".to_string())
+                    .unwrap();
+                signatures.push(signature);
             }
             // Purge to freeze index 0
             blockstore.run_purge(0, 1, PurgeType::PrimaryIndex).unwrap();
@@ -608,6 +624,13 @@ pub mod tests {
                 }
             );
 
+            for signature in &signatures {
+                assert!(blockstore
+                    .read_transaction_memos((*signature, slot))
+                    .unwrap()
+                    .is_some());
+            }
+
             // Test boundary conditions: < slot should not purge statuses; <= slot should
             blockstore.run_purge(0, 9, PurgeType::PrimaryIndex).unwrap();
             let mut status_entry_iterator = blockstore
@@ -667,6 +690,14 @@ pub mod tests {
             assert_eq!(padding_entry.0, 2);
             assert_eq!(padding_entry.2, 0);
             assert!(address_transactions_iterator.next().is_none());
+            for signature in &signatures {
+                assert_eq!(
+                    blockstore
+                        .read_transaction_memos((*signature, slot))
+                        .unwrap(),
+                    None
+                );
+            }
             assert_eq!(
                 transaction_status_index_cf.get(0).unwrap().unwrap(),
                 TransactionStatusIndexMeta {
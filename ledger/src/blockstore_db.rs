@@ -5,8 +5,9 @@ use log::*;
 use prost::Message;
 pub use rocksdb::Direction as IteratorDirection;
 use rocksdb::{
-    self, ColumnFamily, ColumnFamilyDescriptor, DBIterator, DBRawIterator, DBRecoveryMode,
-    IteratorMode as RocksIteratorMode, Options, WriteBatch as RWriteBatch, DB,
+    self, BlockBasedOptions, ColumnFamily, ColumnFamilyDescriptor, DBCompressionType, DBIterator,
+    DBRawIterator, DBRecoveryMode, IteratorMode as RocksIteratorMode, Options,
+    WriteBatch as RWriteBatch, DB,
 };
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -18,7 +19,7 @@ use solana_sdk::{
 };
 use solana_storage_proto::convert::generated;
 use solana_transaction_status::TransactionStatusMeta;
-use std::{collections::HashMap, fs, marker::PhantomData, path::Path, sync::Arc};
+use std::{collections::HashMap, fs, marker::PhantomData, path::Path, str::FromStr, sync::Arc};
 use thiserror::Error;
 
 const MAX_WRITE_BUFFER_SIZE: u64 = 256 * 1024 * 1024; // 256MB
@@ -56,6 +57,8 @@ const REWARDS_CF: &str = "rewards";
 const BLOCKTIME_CF: &str = "blocktime";
 /// Column family for Performance Samples
 const PERF_SAMPLES_CF: &str = "perf_samples";
+/// Column family for Transaction Memos
+const TRANSACTION_MEMOS_CF: &str = "transaction_memos";
 
 #[derive(Error, Debug)]
 pub enum BlockstoreError {
@@ -150,6 +153,10 @@ pub mod columns {
     #[derive(Debug)]
     /// The performance samples column
     pub struct PerfSamples;
+
+    #[derive(Debug)]
+    /// The transaction memos column
+    pub struct TransactionMemos;
 }
 
 pub enum AccessType {
@@ -201,6 +208,67 @@ impl From<BlockstoreRecoveryMode> for DBRecoveryMode {
     }
 }
 
+/// Compression algorithms available for the blockstore columns
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockstoreCompressionType {
+    None,
+    Lz4,
+    Lz4hc,
+}
+
+impl FromStr for BlockstoreCompressionType {
+    type Err = String;
+
+    fn from_str(string: &str) -> std::result::Result<Self, Self::Err> {
+        match string {
+            "none" => Ok(BlockstoreCompressionType::None),
+            "lz4" => Ok(BlockstoreCompressionType::Lz4),
+            "lz4hc" => Ok(BlockstoreCompressionType::Lz4hc),
+            bad_type => Err(format!("Invalid compression type: {}", bad_type)),
+        }
+    }
+}
+
+impl From<BlockstoreCompressionType> for DBCompressionType {
+    fn from(compression_type: BlockstoreCompressionType) -> Self {
+        match compression_type {
+            BlockstoreCompressionType::None => DBCompressionType::None,
+            BlockstoreCompressionType::Lz4 => DBCompressionType::Lz4,
+            BlockstoreCompressionType::Lz4hc => DBCompressionType::Lz4hc,
+        }
+    }
+}
+
+/// RocksDB tunables of the blockstore. The defaults are tuned for NVMe drives; spinning disks
+/// usually benefit from larger write buffers, fewer background jobs and compression
+#[derive(Debug, Clone)]
+pub struct BlockstoreRocksOptions {
+    /// Size in bytes of a column's memtable
+    pub write_buffer_size: u64,
+    /// Maximum number of memtables per column, both active and waiting to be flushed
+    pub max_write_buffer_number: i32,
+    /// Maximum number of concurrent flushes and compactions. When `None`, RocksDB scales its
+    /// background threads to the number of cores
+    pub max_background_jobs: Option<i32>,
+    /// Compression of the SST files of each column, by column name. Columns which aren't listed
+    /// are not compressed
+    pub column_compression: HashMap<String, BlockstoreCompressionType>,
+    /// Keep the index and filter blocks of level 0 files pinned in the block cache
+    pub pin_l0_filter_and_index_blocks: bool,
+}
+
+impl Default for BlockstoreRocksOptions {
+    fn default() -> Self {
+        Self {
+            write_buffer_size: MAX_WRITE_BUFFER_SIZE,
+            max_write_buffer_number: 8,
+            max_background_jobs: None,
+            column_compression: HashMap::new(),
+            pin_l0_filter_and_index_blocks: false,
+        }
+    }
+}
+
 #[derive(Debug)]
 struct Rocks(rocksdb::DB, ActualAccessType);
 
@@ -209,11 +277,12 @@ impl Rocks {
         path: &Path,
         access_type: AccessType,
         recovery_mode: Option<BlockstoreRecoveryMode>,
+        rocks_options: &BlockstoreRocksOptions,
     ) -> Result<Rocks> {
         use columns::{
             AddressSignatures, Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index, Orphans,
-            PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
-            TransactionStatusIndex,
+            PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionMemos,
+            TransactionStatus, TransactionStatusIndex,
         };
 
         fs::create_dir_all(&path)?;
@@ -222,42 +291,77 @@ impl Rocks {
         if matches!(access_type, AccessType::PrimaryOnlyForMaintenance) {
             warn!("Disabling rocksdb's auto compaction for maintenance bulk ledger update...");
         }
-        let mut db_options = get_db_options(&access_type);
+        info!("Blockstore RocksDB options: {:?}", rocks_options);
+        let mut db_options = get_db_options(&access_type, rocks_options);
         if let Some(recovery_mode) = recovery_mode {
             db_options.set_wal_recovery_mode(recovery_mode.into());
         }
 
         // Column family names
-        let meta_cf_descriptor =
-            ColumnFamilyDescriptor::new(SlotMeta::NAME, get_cf_options(&access_type));
-        let dead_slots_cf_descriptor =
-            ColumnFamilyDescriptor::new(DeadSlots::NAME, get_cf_options(&access_type));
-        let duplicate_slots_cf_descriptor =
-            ColumnFamilyDescriptor::new(DuplicateSlots::NAME, get_cf_options(&access_type));
-        let erasure_meta_cf_descriptor =
-            ColumnFamilyDescriptor::new(ErasureMeta::NAME, get_cf_options(&access_type));
-        let orphans_cf_descriptor =
-            ColumnFamilyDescriptor::new(Orphans::NAME, get_cf_options(&access_type));
-        let root_cf_descriptor =
-            ColumnFamilyDescriptor::new(Root::NAME, get_cf_options(&access_type));
-        let index_cf_descriptor =
-            ColumnFamilyDescriptor::new(Index::NAME, get_cf_options(&access_type));
-        let shred_data_cf_descriptor =
-            ColumnFamilyDescriptor::new(ShredData::NAME, get_cf_options(&access_type));
-        let shred_code_cf_descriptor =
-            ColumnFamilyDescriptor::new(ShredCode::NAME, get_cf_options(&access_type));
-        let transaction_status_cf_descriptor =
-            ColumnFamilyDescriptor::new(TransactionStatus::NAME, get_cf_options(&access_type));
-        let address_signatures_cf_descriptor =
-            ColumnFamilyDescriptor::new(AddressSignatures::NAME, get_cf_options(&access_type));
-        let transaction_status_index_cf_descriptor =
-            ColumnFamilyDescriptor::new(TransactionStatusIndex::NAME, get_cf_options(&access_type));
-        let rewards_cf_descriptor =
-            ColumnFamilyDescriptor::new(Rewards::NAME, get_cf_options(&access_type));
-        let blocktime_cf_descriptor =
-            ColumnFamilyDescriptor::new(Blocktime::NAME, get_cf_options(&access_type));
-        let perf_samples_cf_descriptor =
-            ColumnFamilyDescriptor::new(PerfSamples::NAME, get_cf_options(&access_type));
+        let meta_cf_descriptor = ColumnFamilyDescriptor::new(
+            SlotMeta::NAME,
+            get_cf_options(SlotMeta::NAME, &access_type, rocks_options),
+        );
+        let dead_slots_cf_descriptor = ColumnFamilyDescriptor::new(
+            DeadSlots::NAME,
+            get_cf_options(DeadSlots::NAME, &access_type, rocks_options),
+        );
+        let duplicate_slots_cf_descriptor = ColumnFamilyDescriptor::new(
+            DuplicateSlots::NAME,
+            get_cf_options(DuplicateSlots::NAME, &access_type, rocks_options),
+        );
+        let erasure_meta_cf_descriptor = ColumnFamilyDescriptor::new(
+            ErasureMeta::NAME,
+            get_cf_options(ErasureMeta::NAME, &access_type, rocks_options),
+        );
+        let orphans_cf_descriptor = ColumnFamilyDescriptor::new(
+            Orphans::NAME,
+            get_cf_options(Orphans::NAME, &access_type, rocks_options),
+        );
+        let root_cf_descriptor = ColumnFamilyDescriptor::new(
+            Root::NAME,
+            get_cf_options(Root::NAME, &access_type, rocks_options),
+        );
+        let index_cf_descriptor = ColumnFamilyDescriptor::new(
+            Index::NAME,
+            get_cf_options(Index::NAME, &access_type, rocks_options),
+        );
+        let shred_data_cf_descriptor = ColumnFamilyDescriptor::new(
+            ShredData::NAME,
+            get_cf_options(ShredData::NAME, &access_type, rocks_options),
+        );
+        let shred_code_cf_descriptor = ColumnFamilyDescriptor::new(
+            ShredCode::NAME,
+            get_cf_options(ShredCode::NAME, &access_type, rocks_options),
+        );
+        let transaction_status_cf_descriptor = ColumnFamilyDescriptor::new(
+            TransactionStatus::NAME,
+            get_cf_options(TransactionStatus::NAME, &access_type, rocks_options),
+        );
+        let address_signatures_cf_descriptor = ColumnFamilyDescriptor::new(
+            AddressSignatures::NAME,
+            get_cf_options(AddressSignatures::NAME, &access_type, rocks_options),
+        );
+        let transaction_status_index_cf_descriptor = ColumnFamilyDescriptor::new(
+            TransactionStatusIndex::NAME,
+            get_cf_options(TransactionStatusIndex::NAME, &access_type, rocks_options),
+        );
+        let rewards_cf_descriptor = ColumnFamilyDescriptor::new(
+            Rewards::NAME,
+            get_cf_options(Rewards::NAME, &access_type, rocks_options),
+        );
+        let blocktime_cf_descriptor = ColumnFamilyDescriptor::new(
+            Blocktime::NAME,
+            get_cf_options(Blocktime::NAME, &access_type, rocks_options),
+        );
+        let perf_samples_cf_descriptor = ColumnFamilyDescriptor::new(
+            PerfSamples::NAME,
+            get_cf_options(PerfSamples::NAME, &access_type, rocks_options),
+        );
+        let transaction_memos_cf_descriptor = ColumnFamilyDescriptor::new(
+            TransactionMemos::NAME,
+            get_cf_options(TransactionMemos::NAME, &access_type, rocks_options),
+        );
 
         let cfs = vec![
             (SlotMeta::NAME, meta_cf_descriptor),
@@ -278,7 +382,13 @@ impl Rocks {
             (Rewards::NAME, rewards_cf_descriptor),
             (Blocktime::NAME, blocktime_cf_descriptor),
             (PerfSamples::NAME, perf_samples_cf_descriptor),
+            (TransactionMemos::NAME, transaction_memos_cf_descriptor),
         ];
+        for column in rocks_options.column_compression.keys() {
+            if !cfs.iter().any(|(name, _)| *name == column.as_str()) {
+                warn!("Ignoring the compression of unknown column {}", column);
+            }
+        }
 
         // Open the database
         let db = match access_type {
@@ -316,8 +426,8 @@ impl Rocks {
     fn columns(&self) -> Vec<&'static str> {
         use columns::{
             AddressSignatures, Blocktime, DeadSlots, DuplicateSlots, ErasureMeta, Index, Orphans,
-            PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionStatus,
-            TransactionStatusIndex,
+            PerfSamples, Rewards, Root, ShredCode, ShredData, SlotMeta, TransactionMemos,
+            TransactionStatus, TransactionStatusIndex,
         };
 
         vec![
@@ -336,6 +446,7 @@ impl Rocks {
             Rewards::NAME,
             Blocktime::NAME,
             PerfSamples::NAME,
+            TransactionMemos::NAME,
         ]
     }
 
@@ -551,6 +662,34 @@ impl ColumnName for columns::TransactionStatusIndex {
     const NAME: &'static str = TRANSACTION_STATUS_INDEX_CF;
 }
 
+impl Column for columns::TransactionMemos {
+    type Index = (u64, Signature, Slot);
+
+    fn key((index, signature, slot): (u64, Signature, Slot)) -> Vec<u8> {
+        columns::TransactionStatus::key((index, signature, slot))
+    }
+
+    fn index(key: &[u8]) -> (u64, Signature, Slot) {
+        columns::TransactionStatus::index(key)
+    }
+
+    fn primary_index(index: Self::Index) -> u64 {
+        index.0
+    }
+
+    fn as_index(index: u64) -> Self::Index {
+        (index, Signature::default(), 0)
+    }
+}
+
+impl ColumnName for columns::TransactionMemos {
+    const NAME: &'static str = TRANSACTION_MEMOS_CF;
+}
+
+impl TypedColumn for columns::TransactionMemos {
+    type Type = String;
+}
+
 impl SlotColumn for columns::Rewards {}
 impl ColumnName for columns::Rewards {
     const NAME: &'static str = REWARDS_CF;
@@ -734,7 +873,26 @@ impl Database {
         access_type: AccessType,
         recovery_mode: Option<BlockstoreRecoveryMode>,
     ) -> Result<Self> {
-        let backend = Arc::new(Rocks::open(path, access_type, recovery_mode)?);
+        Self::open_with_options(
+            path,
+            access_type,
+            recovery_mode,
+            &BlockstoreRocksOptions::default(),
+        )
+    }
+
+    pub fn open_with_options(
+        path: &Path,
+        access_type: AccessType,
+        recovery_mode: Option<BlockstoreRecoveryMode>,
+        rocks_options: &BlockstoreRocksOptions,
+    ) -> Result<Self> {
+        let backend = Arc::new(Rocks::open(
+            path,
+            access_type,
+            recovery_mode,
+            rocks_options,
+        )?);
 
         Ok(Database {
             backend,
@@ -1007,20 +1165,33 @@ impl<'a> WriteBatch<'a> {
     }
 }
 
-fn get_cf_options(access_type: &AccessType) -> Options {
+fn get_cf_options(
+    name: &'static str,
+    access_type: &AccessType,
+    rocks_options: &BlockstoreRocksOptions,
+) -> Options {
     let mut options = Options::default();
-    // 256 * 8 = 2GB. 6 of these columns should take at most 12GB of RAM
-    options.set_max_write_buffer_number(8);
-    options.set_write_buffer_size(MAX_WRITE_BUFFER_SIZE as usize);
+    // 256 * 8 = 2GB by default. 6 of these columns should take at most 12GB of RAM
+    options.set_max_write_buffer_number(rocks_options.max_write_buffer_number);
+    options.set_write_buffer_size(rocks_options.write_buffer_size as usize);
     let file_num_compaction_trigger = 4;
     // Recommend that this be around the size of level 0. Level 0 estimated size in stable state is
     // write_buffer_size * min_write_buffer_number_to_merge * level0_file_num_compaction_trigger
     // Source: https://docs.rs/rocksdb/0.6.0/rocksdb/struct.Options.html#method.set_level_zero_file_num_compaction_trigger
-    let total_size_base = MAX_WRITE_BUFFER_SIZE * file_num_compaction_trigger;
+    let total_size_base = rocks_options.write_buffer_size * file_num_compaction_trigger;
     let file_size_base = total_size_base / 10;
     options.set_level_zero_file_num_compaction_trigger(file_num_compaction_trigger as i32);
     options.set_max_bytes_for_level_base(total_size_base);
     options.set_target_file_size_base(file_size_base);
+    if let Some(compression_type) = rocks_options.column_compression.get(name) {
+        options.set_compression_type((*compression_type).into());
+    }
+    if rocks_options.pin_l0_filter_and_index_blocks {
+        let mut block_based_options = BlockBasedOptions::default();
+        block_based_options.set_cache_index_and_filter_blocks(true);
+        block_based_options.set_pin_l0_filter_and_index_blocks_in_cache(true);
+        options.set_block_based_table_factory(&block_based_options);
+    }
     if matches!(access_type, AccessType::PrimaryOnlyForMaintenance) {
         options.set_disable_auto_compactions(true);
     }
@@ -1028,12 +1199,15 @@ fn get_cf_options(access_type: &AccessType) -> Options {
     options
 }
 
-fn get_db_options(access_type: &AccessType) -> Options {
+fn get_db_options(access_type: &AccessType, rocks_options: &BlockstoreRocksOptions) -> Options {
     let mut options = Options::default();
     options.create_if_missing(true);
     options.create_missing_column_families(true);
     // A good value for this is the number of cores on the machine
     options.increase_parallelism(num_cpus::get() as i32);
+    if let Some(max_background_jobs) = rocks_options.max_background_jobs {
+        options.set_max_background_jobs(max_background_jobs);
+    }
 
     let mut env = rocksdb::Env::default().unwrap();
 
@@ -1052,3 +1226,45 @@ fn get_db_options(access_type: &AccessType) -> Options {
 
     options
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_type_from_str() {
+        assert_eq!(
+            "none".parse::<BlockstoreCompressionType>(),
+            Ok(BlockstoreCompressionType::None)
+        );
+        assert_eq!(
+            "lz4".parse::<BlockstoreCompressionType>(),
+            Ok(BlockstoreCompressionType::Lz4)
+        );
+        assert_eq!(
+            "lz4hc".parse::<BlockstoreCompressionType>(),
+            Ok(BlockstoreCompressionType::Lz4hc)
+        );
+        assert_eq!(
+            "zstd".parse::<BlockstoreCompressionType>(),
+            Err("Invalid compression type: zstd".to_string())
+        );
+        assert!("LZ4".parse::<BlockstoreCompressionType>().is_err());
+    }
+
+    #[test]
+    fn test_compression_type_into_rocks() {
+        assert!(matches!(
+            DBCompressionType::from(BlockstoreCompressionType::None),
+            DBCompressionType::None
+        ));
+        assert!(matches!(
+            DBCompressionType::from(BlockstoreCompressionType::Lz4),
+            DBCompressionType::Lz4
+        ));
+        assert!(matches!(
+            DBCompressionType::from(BlockstoreCompressionType::Lz4hc),
+            DBCompressionType::Lz4hc
+        ));
+    }
+}
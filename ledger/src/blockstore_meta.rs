@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 use solana_sdk::clock::Slot;
 use std::{collections::BTreeSet, ops::RangeBounds};
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[frozen_abi(digest = "DBRxERf9gS7cmKDc3sbtQVp7HUdsgAHfSRH5vw8JAEB1")]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq, AbiExample)]
 // The Meta column family
 pub struct SlotMeta {
     // The number of slots above the root (the genesis block). The first
@@ -32,7 +33,8 @@ pub struct SlotMeta {
     pub completed_data_indexes: Vec<u32>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[frozen_abi(digest = "CPBXzFY54nWXSxcrLeSeJ3k8uwMXEmq68fLSorSEJUNc")]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, AbiExample)]
 /// Index recording presence/absence of shreds
 pub struct Index {
     pub slot: Slot,
@@ -40,13 +42,14 @@ pub struct Index {
     coding: ShredIndex,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, AbiExample)]
 pub struct ShredIndex {
     /// Map representing presence/absence of shreds
     index: BTreeSet<u64>,
 }
 
-#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[frozen_abi(digest = "5d68LeDAmhYF9Gp63KbymiEmaM4PhDSxMW4pncR1j3WQ")]
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, Eq, PartialEq, AbiExample)]
 /// Erasure coding information
 pub struct ErasureMeta {
     /// Which erasure set in the slot this is
@@ -59,7 +62,8 @@ pub struct ErasureMeta {
     pub config: ErasureConfig,
 }
 
-#[derive(Deserialize, Serialize)]
+#[frozen_abi(digest = "HxnCeGiqGHSY6Z15qWfmfjQLdXdeR89PNedj23bW5urZ")]
+#[derive(Deserialize, Serialize, AbiExample)]
 pub struct DuplicateSlotProof {
     #[serde(with = "serde_bytes")]
     pub shred1: Vec<u8>,
@@ -232,18 +236,21 @@ impl DuplicateSlotProof {
     }
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+#[frozen_abi(digest = "4wBVck1woAWVfgzzsfEorgs5iGbbRyYUXa7z9UW7NrWT")]
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, AbiExample)]
 pub struct TransactionStatusIndexMeta {
     pub max_slot: Slot,
     pub frozen: bool,
 }
 
-#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+#[frozen_abi(digest = "5BnczqCfocUJGXr3a8qgz5VpVqgz5WE3AEn6L3xwAYZr")]
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, AbiExample)]
 pub struct AddressSignatureMeta {
     pub writeable: bool,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[frozen_abi(digest = "5NLLYsxqBrJSvzL4eF13rpfcVisjzeMkazhddJ6A7JrJ")]
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, AbiExample)]
 pub struct PerfSample {
     pub num_transactions: u64,
     pub num_slots: u64,
@@ -14,7 +14,7 @@ use rand::{seq::SliceRandom, thread_rng};
 use rayon::{prelude::*, ThreadPool};
 use solana_measure::{measure::Measure, thread_mem_usage};
 use solana_metrics::{datapoint_error, inc_new_counter_debug};
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_runtime::{
     accounts_index::AccountIndex,
     bank::{
@@ -30,7 +30,7 @@ use solana_runtime::{
     vote_sender_types::ReplayVoteSender,
 };
 use solana_sdk::{
-    clock::{Slot, MAX_PROCESSING_AGE},
+    clock::Slot,
     genesis_config::GenesisConfig,
     hash::Hash,
     pubkey::Pubkey,
@@ -54,11 +54,8 @@ use thiserror::Error;
 pub type BlockstoreProcessorResult =
     result::Result<(BankForks, LeaderScheduleCache), BlockstoreProcessorError>;
 
-thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
-                    .num_threads(get_thread_count())
-                    .thread_name(|ix| format!("blockstore_processor_{}", ix))
-                    .build()
-                    .unwrap())
+thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(
+    thread_manager::build_thread_pool("solana-blockstore-processor", get_thread_count()))
 );
 
 fn first_err(results: &[Result<()>]) -> Result<()> {
@@ -121,7 +118,7 @@ fn execute_batch(
     let (tx_results, balances, inner_instructions, transaction_logs) =
         batch.bank().load_execute_and_commit_transactions(
             batch,
-            MAX_PROCESSING_AGE,
+            batch.bank().max_processing_age(),
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
             transaction_status_sender.is_some(),
@@ -271,7 +268,10 @@ fn process_entries_with_callback(
             };
 
             // try to lock the accounts
+            let mut lock_time = Measure::start("lock_time");
             let batch = bank.prepare_batch(&entry.transactions, iteration_order);
+            lock_time.stop();
+            timings.lock_us += lock_time.as_us();
 
             let first_lock_err = first_err(batch.lock_results());
 
@@ -358,9 +358,16 @@ pub type ProcessCallback = Arc<dyn Fn(&Bank) + Sync + Send>;
 pub struct ProcessOptions {
     pub bpf_jit: bool,
     pub poh_verify: bool,
+    /// Verify PoH on the GPU when the perf libs are loaded, rather than in parallel batches on
+    /// the CPU
+    pub poh_verify_gpu: bool,
     pub full_leader_cache: bool,
     pub dev_halt_at_slot: Option<Slot>,
+    /// Only boot from a snapshot archive at or below this slot, if any
+    pub snapshot_max_slot: Option<Slot>,
     pub entry_callback: Option<ProcessCallback>,
+    /// Called with each replayed bank once it is frozen
+    pub slot_callback: Option<ProcessCallback>,
     pub override_num_threads: Option<usize>,
     pub new_hard_forks: Option<Vec<Slot>>,
     pub frozen_accounts: Vec<Pubkey>,
@@ -592,6 +599,7 @@ fn confirm_full_slot(
         &mut timing,
         progress,
         skip_verification,
+        opts.poh_verify_gpu,
         transaction_status_sender,
         replay_vote_sender,
         opts.entry_callback.as_ref(),
@@ -655,6 +663,7 @@ pub fn confirm_slot(
     timing: &mut ConfirmationTiming,
     progress: &mut ConfirmationProgress,
     skip_verification: bool,
+    poh_verify_gpu: bool,
     transaction_status_sender: Option<TransactionStatusSender>,
     replay_vote_sender: Option<&ReplayVoteSender>,
     entry_callback: Option<&ProcessCallback>,
@@ -705,12 +714,33 @@ pub fn confirm_slot(
         })?;
     }
 
+    // Blocks others can't replay in time are rejected before replaying any more of them
+    if bank.block_cost_limits_enabled() {
+        let block_units = bank.block_units()
+            + entries
+                .iter()
+                .flat_map(|entry| &entry.transactions)
+                .map(|tx| bank.transaction_cost(tx))
+                .sum::<u64>();
+        if block_units > bank.max_block_units() {
+            warn!(
+                "slot {} costs at least {} units, more than the max of {}",
+                slot,
+                block_units,
+                bank.max_block_units()
+            );
+            return Err(BlockError::MaxBlockUnitsExceeded.into());
+        }
+    }
+
     let verifier = if !skip_verification {
         datapoint_debug!("verify-batch-size", ("size", num_entries as i64, i64));
-        let entry_state = entries.start_verify(
+        // The PoH is verified while the entries are replayed, and checked once they are
+        let entry_state = entries.start_verify_in_background(
             &progress.last_entry,
             recyclers.clone(),
             bank.secp256k1_program_enabled(),
+            poh_verify_gpu,
         );
         if entry_state.status() == EntryVerificationStatus::Failure {
             warn!("Ledger proof of history failed at slot: {}", slot);
@@ -737,6 +767,7 @@ pub fn confirm_slot(
     timing.replay_elapsed += replay_elapsed.as_us();
 
     timing.execute_timings.accumulate(&execute_timings);
+    bank.accumulate_execute_timings(&execute_timings);
 
     if let Some(mut verifier) = verifier {
         let verified = verifier.finish_verify(&entries);
@@ -1082,6 +1113,9 @@ fn process_single_slot(
     })?;
 
     bank.freeze(); // all banks handled by this routine are created from complete slots
+    if let Some(slot_callback) = &opts.slot_callback {
+        slot_callback(bank);
+    }
 
     Ok(())
 }
@@ -1172,13 +1206,18 @@ pub mod tests {
     use crossbeam_channel::unbounded;
     use matches::assert_matches;
     use rand::{thread_rng, Rng};
-    use solana_runtime::genesis_utils::{
-        self, create_genesis_config_with_vote_accounts, ValidatorVoteKeypairs,
+    use solana_runtime::{
+        block_cost::MAX_BLOCK_UNITS,
+        genesis_utils::{self, create_genesis_config_with_vote_accounts, ValidatorVoteKeypairs},
     };
     use solana_sdk::{
         account::Account,
+        clock::MAX_PROCESSING_AGE,
         epoch_schedule::EpochSchedule,
         hash::Hash,
+        instruction::Instruction,
+        message::Message,
+        process_instruction::BpfComputeBudget,
         pubkey::Pubkey,
         signature::{Keypair, Signer},
         system_instruction::SystemError,
@@ -1353,6 +1392,90 @@ pub mod tests {
         assert_eq!(frozen_bank_slots(&bank_forks), vec![0]);
     }
 
+    #[test]
+    fn test_confirm_slot_max_block_units() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            mint_keypair,
+            genesis_config,
+            ..
+        } = create_genesis_config(10_000);
+        let ticks_per_slot = genesis_config.ticks_per_slot;
+        let (ledger_path, blockhash) = create_new_tmp_ledger!(&genesis_config);
+        let blockstore = Blockstore::open(&ledger_path).unwrap();
+
+        // Instructions of BPF programs are charged the whole compute budget, whether or not the
+        // program exists
+        let tx_entry = {
+            let instruction = Instruction::new(Pubkey::new_unique(), &(), vec![]);
+            let message = Message::new(&[instruction], Some(&mint_keypair.pubkey()));
+            let tx = Transaction::new(&[&mint_keypair], message, blockhash);
+            next_entry(&blockhash, 1, vec![tx])
+        };
+        let mut entries = vec![tx_entry.clone()];
+        entries.extend(create_ticks(ticks_per_slot, 0, tx_entry.hash));
+        blockstore
+            .write_entries(
+                1,
+                0,
+                0,
+                ticks_per_slot,
+                Some(0),
+                true,
+                &Arc::new(Keypair::new()),
+                entries,
+                0,
+            )
+            .unwrap();
+
+        let confirm_slot_with_bpf_units = |bpf_units| {
+            let mut bank0 = Bank::new(&genesis_config);
+            bank0.set_bpf_compute_budget(Some(BpfComputeBudget {
+                max_units: bpf_units,
+                ..BpfComputeBudget::default()
+            }));
+            let bank1 = Arc::new(Bank::new_from_parent(
+                &Arc::new(bank0),
+                &Pubkey::default(),
+                1,
+            ));
+            let tx_cost = bank1.transaction_cost(&tx_entry.transactions[0]);
+            let result = confirm_slot(
+                &blockstore,
+                &bank1,
+                &mut ConfirmationTiming::default(),
+                &mut ConfirmationProgress::new(blockhash),
+                false,
+                false,
+                None,
+                None,
+                None,
+                &VerifyRecyclers::default(),
+            );
+            (tx_cost, result)
+        };
+        let other_units = confirm_slot_with_bpf_units(0).0;
+
+        // The block is replayed, and fails on the missing program
+        let (tx_cost, result) = confirm_slot_with_bpf_units(MAX_BLOCK_UNITS - other_units);
+        assert_eq!(tx_cost, MAX_BLOCK_UNITS);
+        assert_matches!(
+            result,
+            Err(BlockstoreProcessorError::InvalidTransaction(
+                TransactionError::ProgramAccountNotFound
+            ))
+        );
+        let (tx_cost, result) = confirm_slot_with_bpf_units(MAX_BLOCK_UNITS - other_units + 1);
+        assert_eq!(tx_cost, MAX_BLOCK_UNITS + 1);
+        assert_matches!(
+            result,
+            Err(BlockstoreProcessorError::InvalidBlock(
+                BlockError::MaxBlockUnitsExceeded
+            ))
+        );
+    }
+
     #[test]
     fn test_process_blockstore_with_incomplete_slot() {
         solana_logger::setup();
@@ -2060,6 +2183,41 @@ pub mod tests {
         assert_eq!(*callback_counter.write().unwrap(), 2);
     }
 
+    #[test]
+    fn test_process_ledger_options_slot_callback() {
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(100);
+        let ticks_per_slot = genesis_config.ticks_per_slot;
+        let (ledger_path, blockhash) = create_new_tmp_ledger!(&genesis_config);
+        let blockstore = Blockstore::open(&ledger_path).unwrap();
+
+        let blockhash =
+            fill_blockstore_slot_with_ticks(&blockstore, ticks_per_slot, 1, 0, blockhash);
+        fill_blockstore_slot_with_ticks(&blockstore, ticks_per_slot, 2, 1, blockhash);
+
+        let frozen_slots: Arc<RwLock<Vec<(Slot, Hash)>>> = Arc::default();
+        let slot_callback = {
+            let frozen_slots = frozen_slots.clone();
+            Arc::new(move |bank: &Bank| {
+                assert!(bank.is_frozen());
+                frozen_slots
+                    .write()
+                    .unwrap()
+                    .push((bank.slot(), bank.hash()));
+            })
+        };
+
+        let opts = ProcessOptions {
+            slot_callback: Some(slot_callback),
+            ..ProcessOptions::default()
+        };
+        let (bank_forks, _leader_schedule) =
+            process_blockstore(&genesis_config, &blockstore, Vec::new(), opts).unwrap();
+        assert_eq!(
+            *frozen_slots.read().unwrap(),
+            vec![(1, bank_forks[1].hash()), (2, bank_forks[2].hash())]
+        );
+    }
+
     #[test]
     fn test_process_entries_tick() {
         let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(1000);
@@ -16,25 +16,28 @@ use solana_metrics::*;
 use solana_perf::cuda_runtime::PinnedVec;
 use solana_perf::perf_libs;
 use solana_perf::recycler::Recycler;
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_sdk::hash::Hash;
 use solana_sdk::packet::PACKET_DATA_SIZE;
 use solana_sdk::timing;
 use solana_sdk::transaction::Transaction;
 use std::cell::RefCell;
 use std::ffi::OsStr;
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Once;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::Instant;
 use std::{cmp, thread};
 
-thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
-                    .num_threads(get_thread_count())
-                    .thread_name(|ix| format!("entry_{}", ix))
-                    .build()
-                    .unwrap()));
+thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(
+    thread_manager::build_thread_pool("solana-entry", get_thread_count())));
+
+lazy_static! {
+    // Verifies PoH in the background, see `EntrySlice::start_verify_in_background`
+    static ref POH_VERIFY_THREAD_POOL: ThreadPool =
+        thread_manager::build_thread_pool("solana-poh-verify", get_thread_count());
+}
 
 pub type EntrySender = Sender<Vec<Entry>>;
 pub type EntryReceiver = Receiver<Vec<Entry>>;
@@ -104,7 +107,8 @@ pub struct Api<'a> {
 /// a Verifiable Delay Function (VDF) and a Proof of Work (not to be confused with Proof of
 /// Work consensus!)
 
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+#[frozen_abi(digest = "FwFd26hj71SmP4hfQqUWoH9WYpCaAeNXfzqcxbkxpoNn")]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone, AbiExample)]
 pub struct Entry {
     /// The number of hashes since the previous Entry ID.
     pub num_hashes: u64,
@@ -194,16 +198,95 @@ pub fn hash_transactions(transactions: &[Transaction]) -> Hash {
 /// the signature.  If num_hashes is zero and there's no transaction data,
 ///  start_hash is returned.
 pub fn next_hash(start_hash: &Hash, num_hashes: u64, transactions: &[Transaction]) -> Hash {
-    if num_hashes == 0 && transactions.is_empty() {
+    let mixin = if transactions.is_empty() {
+        None
+    } else {
+        Some(hash_transactions(transactions))
+    };
+    next_hash_with_mixin(start_hash, num_hashes, mixin)
+}
+
+/// Like `next_hash`, with the hash of the transactions, if any, already computed
+fn next_hash_with_mixin(start_hash: &Hash, num_hashes: u64, mixin: Option<Hash>) -> Hash {
+    if num_hashes == 0 && mixin.is_none() {
         return *start_hash;
     }
 
     let mut poh = Poh::new(*start_hash, None);
     poh.hash(num_hashes.saturating_sub(1));
-    if transactions.is_empty() {
-        poh.tick().unwrap().hash
-    } else {
-        poh.record(hash_transactions(transactions)).unwrap().hash
+    match mixin {
+        None => poh.tick().unwrap().hash,
+        Some(mixin) => poh.record(mixin).unwrap().hash,
+    }
+}
+
+/// What verifying the PoH of an entry takes, without its transactions
+struct PohEntry {
+    num_hashes: u64,
+    mixin: Option<Hash>,
+    hash: Hash,
+}
+
+impl From<&Entry> for PohEntry {
+    fn from(entry: &Entry) -> Self {
+        Self {
+            num_hashes: entry.num_hashes,
+            mixin: if entry.transactions.is_empty() {
+                None
+            } else {
+                Some(hash_transactions(&entry.transactions))
+            },
+            hash: entry.hash,
+        }
+    }
+}
+
+/// Verifies the PoH hash chain of `entries` following `start_hash`, split into at most
+/// `num_batches` batches hashed in parallel.  Each batch hashes on from the hash its first entry
+/// claims to follow and checks each of its entries but the last, then a final stitching check
+/// compares the hash each batch ended on with the one the next batch started from, the last
+/// batch's with the hash of the last entry
+fn verify_poh_batches(start_hash: &Hash, entries: &[PohEntry], num_batches: usize) -> bool {
+    if entries.is_empty() {
+        return true;
+    }
+    let batch_len = (entries.len() + num_batches - 1) / cmp::max(num_batches, 1);
+    let batch_end_hashes: Option<Vec<Hash>> = entries
+        .par_chunks(batch_len)
+        .enumerate()
+        .map(|(i, batch)| {
+            let mut hash = if i == 0 {
+                *start_hash
+            } else {
+                entries[i * batch_len - 1].hash
+            };
+            for (j, entry) in batch.iter().enumerate() {
+                hash = next_hash_with_mixin(&hash, entry.num_hashes, entry.mixin);
+                if j + 1 < batch.len() && hash != entry.hash {
+                    warn!(
+                        "entry invalid!: expected: {:?}, actual: {:?}",
+                        hash, entry.hash
+                    );
+                    return None;
+                }
+            }
+            Some(hash)
+        })
+        .collect();
+
+    match batch_end_hashes {
+        Some(batch_end_hashes) => batch_end_hashes.iter().enumerate().all(|(i, hash)| {
+            let batch_last_entry = cmp::min((i + 1) * batch_len, entries.len()) - 1;
+            let stitched = *hash == entries[batch_last_entry].hash;
+            if !stitched {
+                warn!(
+                    "entry invalid!: expected: {:?}, actual: {:?}",
+                    hash, entries[batch_last_entry].hash
+                );
+            }
+            stitched
+        }),
+        None => false,
     }
 }
 
@@ -216,6 +299,9 @@ pub struct GpuVerificationData {
 pub enum DeviceVerificationData {
     CPU(),
     GPU(GpuVerificationData),
+    /// Verification running on POH_VERIFY_THREAD_POOL, which sends whether it passed and how
+    /// long it took
+    CPUBackground(Receiver<(bool, u64)>),
 }
 
 pub struct EntryVerificationState {
@@ -300,6 +386,16 @@ impl EntryVerificationState {
             DeviceVerificationData::CPU() => {
                 self.verification_status == EntryVerificationStatus::Success
             }
+            DeviceVerificationData::CPUBackground(result_receiver) => {
+                let (res, poh_duration_us) = result_receiver.recv().unwrap_or((false, 0));
+                self.poh_duration_us += poh_duration_us;
+                self.verification_status = if res {
+                    EntryVerificationStatus::Success
+                } else {
+                    EntryVerificationStatus::Failure
+                };
+                res
+            }
         }
     }
 }
@@ -330,6 +426,16 @@ pub trait EntrySlice {
         recyclers: VerifyRecyclers,
         secp256k1_program_enabled: bool,
     ) -> EntryVerificationState;
+    /// Like `start_verify`, but leaves the PoH to verify in the background, on the GPU if
+    /// `use_gpu` is set and the perf libs are loaded, and otherwise on the CPU in batches verified
+    /// in parallel, so that the caller can replay the entries meanwhile
+    fn start_verify_in_background(
+        &self,
+        start_hash: &Hash,
+        recyclers: VerifyRecyclers,
+        secp256k1_program_enabled: bool,
+        use_gpu: bool,
+    ) -> EntryVerificationState;
     fn verify(&self, start_hash: &Hash) -> bool;
     /// Checks that each entry tick has the correct number of hashes. Entry slices do not
     /// necessarily end in a tick, so `tick_hash_count` is used to carry over the hash count
@@ -531,92 +637,64 @@ impl EntrySlice for [Entry] {
             };
         }
 
-        let start = Instant::now();
         let api = perf_libs::api();
         if api.is_none() {
             let mut res: EntryVerificationState = self.verify_cpu(start_hash);
             res.set_transaction_duration_us(transaction_duration_us);
             return res;
         }
-        let api = api.unwrap();
-        inc_new_counter_info!("entry_verify-num_entries", self.len() as usize);
-
-        let genesis = [Entry {
-            num_hashes: 0,
-            hash: *start_hash,
-            transactions: vec![],
-        }];
-
-        let hashes: Vec<Hash> = genesis
-            .iter()
-            .chain(self)
-            .map(|entry| entry.hash)
-            .take(self.len())
-            .collect();
-
-        let mut hashes_pinned = recyclers.hash_recycler.allocate("poh_verify_hash");
-        hashes_pinned.set_pinnable();
-        hashes_pinned.resize(hashes.len(), Hash::default());
-        hashes_pinned.copy_from_slice(&hashes);
+        let mut res = start_verify_gpu(self, start_hash, recyclers, api.unwrap());
+        res.set_transaction_duration_us(transaction_duration_us);
+        res
+    }
 
-        let mut num_hashes_vec = recyclers
-            .tick_count_recycler
-            .allocate("poh_verify_num_hashes");
-        num_hashes_vec.reserve_and_pin(cmp::max(1, self.len()));
-        for entry in self {
-            num_hashes_vec.push(entry.num_hashes.saturating_sub(1));
+    fn start_verify_in_background(
+        &self,
+        start_hash: &Hash,
+        recyclers: VerifyRecyclers,
+        secp256k1_program_enabled: bool,
+        use_gpu: bool,
+    ) -> EntryVerificationState {
+        let start = Instant::now();
+        let res = self.verify_transaction_signatures(secp256k1_program_enabled);
+        let transaction_duration_us = timing::duration_as_us(&start.elapsed());
+        if !res {
+            return EntryVerificationState {
+                verification_status: EntryVerificationStatus::Failure,
+                transaction_duration_us,
+                poh_duration_us: 0,
+                device_verification_data: DeviceVerificationData::CPU(),
+            };
         }
 
-        let length = self.len();
-        let hashes = Arc::new(Mutex::new(hashes_pinned));
-        let hashes_clone = hashes.clone();
-
-        let gpu_verify_thread = thread::spawn(move || {
-            let mut hashes = hashes_clone.lock().unwrap();
-            let gpu_wait = Instant::now();
-            let res;
-            unsafe {
-                res = (api.poh_verify_many)(
-                    hashes.as_mut_ptr() as *mut u8,
-                    num_hashes_vec.as_ptr(),
-                    length,
-                    1,
-                );
-            }
-            if res != 0 {
-                panic!("GPU PoH verify many failed");
-            }
-            inc_new_counter_info!(
-                "entry_verify-gpu_thread",
-                timing::duration_as_us(&gpu_wait.elapsed()) as usize
-            );
-            timing::duration_as_us(&gpu_wait.elapsed())
-        });
+        if let Some(api) = perf_libs::api().filter(|_| use_gpu) {
+            let mut res = start_verify_gpu(self, start_hash, recyclers, api);
+            res.set_transaction_duration_us(transaction_duration_us);
+            return res;
+        }
 
-        let tx_hashes = PAR_THREAD_POOL.with(|thread_pool| {
-            thread_pool.borrow().install(|| {
-                self.into_par_iter()
-                    .map(|entry| {
-                        if entry.transactions.is_empty() {
-                            None
-                        } else {
-                            Some(hash_transactions(&entry.transactions))
-                        }
-                    })
-                    .collect()
-            })
+        let start = Instant::now();
+        let poh_entries: Vec<PohEntry> = PAR_THREAD_POOL.with(|thread_pool| {
+            thread_pool
+                .borrow()
+                .install(|| self.par_iter().map(PohEntry::from).collect())
         });
-
-        let device_verification_data = DeviceVerificationData::GPU(GpuVerificationData {
-            thread_h: Some(gpu_verify_thread),
-            tx_hashes,
-            hashes: Some(hashes),
+        let start_hash = *start_hash;
+        let (result_sender, result_receiver) = channel();
+        POH_VERIFY_THREAD_POOL.spawn(move || {
+            let start = Instant::now();
+            let res = verify_poh_batches(
+                &start_hash,
+                &poh_entries,
+                POH_VERIFY_THREAD_POOL.current_num_threads(),
+            );
+            let _ = result_sender.send((res, timing::duration_as_us(&start.elapsed())));
         });
         EntryVerificationState {
             verification_status: EntryVerificationStatus::Pending,
             poh_duration_us: timing::duration_as_us(&start.elapsed()),
             transaction_duration_us,
-            device_verification_data,
+            device_verification_data: DeviceVerificationData::CPUBackground(result_receiver),
         }
     }
 
@@ -649,6 +727,95 @@ impl EntrySlice for [Entry] {
     }
 }
 
+/// Starts hashing the PoH of `entries` on the GPU, `finish_verify` checks the hashes it computes
+fn start_verify_gpu(
+    entries: &[Entry],
+    start_hash: &Hash,
+    recyclers: VerifyRecyclers,
+    api: &'static Container<perf_libs::Api<'static>>,
+) -> EntryVerificationState {
+    let start = Instant::now();
+    inc_new_counter_info!("entry_verify-num_entries", entries.len() as usize);
+    let genesis = [Entry {
+        num_hashes: 0,
+        hash: *start_hash,
+        transactions: vec![],
+    }];
+
+    let hashes: Vec<Hash> = genesis
+        .iter()
+        .chain(entries)
+        .map(|entry| entry.hash)
+        .take(entries.len())
+        .collect();
+
+    let mut hashes_pinned = recyclers.hash_recycler.allocate("poh_verify_hash");
+    hashes_pinned.set_pinnable();
+    hashes_pinned.resize(hashes.len(), Hash::default());
+    hashes_pinned.copy_from_slice(&hashes);
+
+    let mut num_hashes_vec = recyclers
+        .tick_count_recycler
+        .allocate("poh_verify_num_hashes");
+    num_hashes_vec.reserve_and_pin(cmp::max(1, entries.len()));
+    for entry in entries {
+        num_hashes_vec.push(entry.num_hashes.saturating_sub(1));
+    }
+
+    let length = entries.len();
+    let hashes = Arc::new(Mutex::new(hashes_pinned));
+    let hashes_clone = hashes.clone();
+
+    let gpu_verify_thread = thread::spawn(move || {
+        let mut hashes = hashes_clone.lock().unwrap();
+        let gpu_wait = Instant::now();
+        let res;
+        unsafe {
+            res = (api.poh_verify_many)(
+                hashes.as_mut_ptr() as *mut u8,
+                num_hashes_vec.as_ptr(),
+                length,
+                1,
+            );
+        }
+        if res != 0 {
+            panic!("GPU PoH verify many failed");
+        }
+        inc_new_counter_info!(
+            "entry_verify-gpu_thread",
+            timing::duration_as_us(&gpu_wait.elapsed()) as usize
+        );
+        timing::duration_as_us(&gpu_wait.elapsed())
+    });
+
+    let tx_hashes = PAR_THREAD_POOL.with(|thread_pool| {
+        thread_pool.borrow().install(|| {
+            entries
+                .into_par_iter()
+                .map(|entry| {
+                    if entry.transactions.is_empty() {
+                        None
+                    } else {
+                        Some(hash_transactions(&entry.transactions))
+                    }
+                })
+                .collect()
+        })
+    });
+
+    let device_verification_data = DeviceVerificationData::GPU(GpuVerificationData {
+        thread_h: Some(gpu_verify_thread),
+        tx_hashes,
+        hashes: Some(hashes),
+    });
+    EntryVerificationState {
+        verification_status: EntryVerificationStatus::Pending,
+        poh_duration_us: timing::duration_as_us(&start.elapsed()),
+        transaction_duration_us: 0,
+        device_verification_data,
+    }
+}
+
 pub fn next_entry_mut(start: &mut Hash, num_hashes: u64, transactions: Vec<Transaction>) -> Entry {
     let entry = Entry::new(&start, num_hashes, transactions);
     *start = entry.hash;
@@ -901,6 +1068,66 @@ mod tests {
         assert_eq!(bad_ticks.verify(&one), false); // inductive step, bad
     }
 
+    #[test]
+    fn test_verify_poh_batches() {
+        solana_logger::setup();
+        let zero = Hash::default();
+        let keypair = Keypair::new();
+        let tx = create_sample_payment(&keypair, zero);
+        let mut last_hash = zero;
+        let entries: Vec<_> = (0..10)
+            .map(|i| {
+                let transactions = if i % 3 == 0 { vec![tx.clone()] } else { vec![] };
+                next_entry_mut(&mut last_hash, i % 4, transactions)
+            })
+            .collect();
+        let poh_entries =
+            |entries: &[Entry]| -> Vec<PohEntry> { entries.iter().map(PohEntry::from).collect() };
+
+        assert!(verify_poh_batches(&zero, &[], 4));
+        for num_batches in 1..=12 {
+            assert!(verify_poh_batches(
+                &zero,
+                &poh_entries(&entries),
+                num_batches
+            ));
+            assert!(!verify_poh_batches(
+                &hash(zero.as_ref()),
+                &poh_entries(&entries),
+                num_batches
+            ));
+            // A bad hash is caught within a batch and at the end of one alike
+            for i in 0..entries.len() {
+                let mut bad_entries = entries.clone();
+                bad_entries[i].hash = hash(&[i as u8]);
+                assert!(!verify_poh_batches(
+                    &zero,
+                    &poh_entries(&bad_entries),
+                    num_batches
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_start_verify_in_background() {
+        solana_logger::setup();
+        let zero = Hash::default();
+        let entries = create_ticks(64, 3, zero);
+        let mut state =
+            entries.start_verify_in_background(&zero, VerifyRecyclers::default(), true, false);
+        assert_eq!(state.status(), EntryVerificationStatus::Pending);
+        assert!(state.finish_verify(&entries));
+        assert_eq!(state.status(), EntryVerificationStatus::Success);
+
+        let mut bad_entries = entries;
+        bad_entries[40].hash = zero;
+        let mut state =
+            bad_entries.start_verify_in_background(&zero, VerifyRecyclers::default(), true, false);
+        assert!(!state.finish_verify(&bad_entries));
+        assert_eq!(state.status(), EntryVerificationStatus::Failure);
+    }
+
     #[test]
     fn test_verify_transaction_signatures_packet_data_size() {
         let mut rng = rand::thread_rng();
@@ -51,7 +51,8 @@ pub const NUM_DATA: usize = 8;
 /// Number of coding shreds; also the maximum number that can go missing.
 pub const NUM_CODING: usize = 8;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[frozen_abi(digest = "J51p5QyLtkkEqdchBW3RgRCEvqnZuxfn6VTEHzUEA9Wu")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, AbiExample)]
 pub struct ErasureConfig {
     num_data: usize,
     num_coding: usize,
@@ -11,6 +11,22 @@ pub struct FixedSchedule {
     pub start_epoch: u64,
 }
 
+impl FixedSchedule {
+    /// `leader` is the leader of every slot from `start_epoch` on
+    pub fn single_leader(leader: Pubkey, start_epoch: u64) -> Self {
+        Self::round_robin(&[leader], 1, start_epoch)
+    }
+
+    /// `leaders` take turns being leader for `slots_per_leader` consecutive slots from
+    /// `start_epoch` on, each epoch starting over with the first of them
+    pub fn round_robin(leaders: &[Pubkey], slots_per_leader: u64, start_epoch: u64) -> Self {
+        Self {
+            leader_schedule: Arc::new(LeaderSchedule::new_round_robin(leaders, slots_per_leader)),
+            start_epoch,
+        }
+    }
+}
+
 /// Stake-weighted leader schedule for one epoch.
 #[derive(Debug, Default, PartialEq)]
 pub struct LeaderSchedule {
@@ -41,6 +57,16 @@ impl LeaderSchedule {
         Self { slot_leaders }
     }
 
+    // Note: passing in no leaders or zero `slots_per_leader` will cause a panic.
+    pub fn new_round_robin(leaders: &[Pubkey], slots_per_leader: u64) -> Self {
+        assert!(!leaders.is_empty() && slots_per_leader > 0);
+        let slot_leaders = leaders
+            .iter()
+            .flat_map(|leader| std::iter::repeat(*leader).take(slots_per_leader as usize))
+            .collect();
+        Self { slot_leaders }
+    }
+
     pub fn get_slot_leaders(&self) -> &[Pubkey] {
         &self.slot_leaders
     }
@@ -157,4 +183,25 @@ mod tests {
         assert_eq!(leaders1, leaders1_expected);
         assert_eq!(leaders2, leaders2_expected);
     }
+
+    #[test]
+    fn test_fixed_schedule() {
+        let alice_pubkey = solana_sdk::pubkey::new_rand();
+        let bob_pubkey = solana_sdk::pubkey::new_rand();
+
+        let fixed_schedule = FixedSchedule::single_leader(alice_pubkey, 3);
+        assert_eq!(fixed_schedule.start_epoch, 3);
+        assert!(
+            (0..100).all(|slot_index| fixed_schedule.leader_schedule[slot_index] == alice_pubkey)
+        );
+
+        let fixed_schedule = FixedSchedule::round_robin(&[alice_pubkey, bob_pubkey], 2, 0);
+        assert_eq!(fixed_schedule.start_epoch, 0);
+        assert_eq!(
+            fixed_schedule.leader_schedule.get_slot_leaders(),
+            &[alice_pubkey, alice_pubkey, bob_pubkey, bob_pubkey]
+        );
+        assert_eq!(fixed_schedule.leader_schedule[4], alice_pubkey);
+        assert_eq!(fixed_schedule.leader_schedule[7], bob_pubkey);
+    }
 }
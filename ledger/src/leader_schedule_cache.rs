@@ -273,7 +273,7 @@ mod tests {
         MINIMUM_SLOTS_PER_EPOCH,
     };
     use solana_sdk::signature::{Keypair, Signer};
-    use std::{sync::mpsc::channel, sync::Arc, thread::Builder};
+    use std::{iter, sync::mpsc::channel, sync::Arc, thread::Builder};
 
     #[test]
     fn test_new_cache() {
@@ -426,6 +426,54 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_fixed_leader_schedule() {
+        let pubkey = solana_sdk::pubkey::new_rand();
+        let other_pubkey = solana_sdk::pubkey::new_rand();
+        let mut genesis_config =
+            create_genesis_config_with_leader(42, &pubkey, bootstrap_validator_stake_lamports())
+                .genesis_config;
+        genesis_config.epoch_schedule = EpochSchedule::custom(
+            DEFAULT_SLOTS_PER_EPOCH,
+            DEFAULT_LEADER_SCHEDULE_SLOT_OFFSET,
+            false,
+        );
+
+        let bank = Bank::new(&genesis_config);
+        let mut cache = LeaderScheduleCache::new_from_bank(&bank);
+        cache.set_fixed_leader_schedule(Some(FixedSchedule::round_robin(
+            &[other_pubkey, pubkey],
+            4,
+            1,
+        )));
+
+        // The stake-weighted schedule still applies before the start epoch
+        let first_slot = genesis_config.epoch_schedule.get_first_slot_in_epoch(1);
+        assert_eq!(
+            cache.slot_leader_at(first_slot - 1, Some(&bank)),
+            Some(pubkey)
+        );
+        for (slot, leader) in (first_slot..first_slot + 16).zip(
+            [other_pubkey, pubkey]
+                .iter()
+                .flat_map(|leader| iter::repeat(leader).take(4))
+                .cycle(),
+        ) {
+            assert_eq!(cache.slot_leader_at(slot, Some(&bank)), Some(*leader));
+        }
+        assert_eq!(
+            cache.next_leader_slot(&other_pubkey, 0, &bank, None, std::u64::MAX),
+            Some((first_slot, first_slot + 3))
+        );
+        assert_eq!(
+            cache.next_leader_slot(&pubkey, first_slot, &bank, None, std::u64::MAX),
+            Some((first_slot + 4, first_slot + 7))
+        );
+
+        cache.set_fixed_leader_schedule(None);
+        assert_eq!(cache.slot_leader_at(first_slot, Some(&bank)), Some(pubkey));
+    }
+
     #[test]
     fn test_next_leader_slot_blockstore() {
         let pubkey = solana_sdk::pubkey::new_rand();
@@ -22,6 +22,8 @@ pub mod next_slots_iterator;
 pub mod poh;
 pub mod rooted_slot_iterator;
 pub mod shred;
+pub mod shred_encryption;
+pub mod signing_journal;
 pub mod sigverify_shreds;
 pub mod staking_utils;
 
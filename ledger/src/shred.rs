@@ -14,7 +14,7 @@ use rayon::{
 use serde::{Deserialize, Serialize};
 use solana_measure::measure::Measure;
 use solana_perf::packet::{limited_deserialize, Packet};
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_sdk::{
     clock::Slot,
     hash::Hash,
@@ -80,11 +80,8 @@ pub const OFFSET_OF_SHRED_SLOT: usize = SIZE_OF_SIGNATURE + SIZE_OF_SHRED_TYPE;
 pub const OFFSET_OF_SHRED_INDEX: usize = OFFSET_OF_SHRED_SLOT + SIZE_OF_SHRED_SLOT;
 pub const SHRED_PAYLOAD_SIZE: usize = PACKET_DATA_SIZE - SIZE_OF_NONCE;
 
-thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(rayon::ThreadPoolBuilder::new()
-                    .num_threads(get_thread_count())
-                    .thread_name(|ix| format!("shredder_{}", ix))
-                    .build()
-                    .unwrap()));
+thread_local!(static PAR_THREAD_POOL: RefCell<ThreadPool> = RefCell::new(
+    thread_manager::build_thread_pool("solana-shredder", get_thread_count())));
 
 /// The constants that define if a shred is data or coding
 pub const DATA_SHRED: u8 = 0b1010_0101;
@@ -119,6 +116,7 @@ pub enum ShredError {
 
 pub type Result<T> = std::result::Result<T, ShredError>;
 
+#[frozen_abi(digest = "DMgAH8XuCbyj8iajbog6fTGB5VsVHQR8EWMYBq5b2kRX")]
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, AbiExample, Deserialize, Serialize)]
 pub struct ShredType(pub u8);
 impl Default for ShredType {
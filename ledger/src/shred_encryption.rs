@@ -0,0 +1,192 @@
+//! Optional encryption of the shred payloads exchanged over turbine and repair, for permissioned
+//! clusters sharing their network with untrusted parties.
+//!
+//! Everything but the signature and the common header of a shred is XORed with a ChaCha20
+//! keystream derived from the cluster key and the shred signature, so shreds keep their size and
+//! can still be routed, deduplicated and verified once decrypted.  The cluster key itself is
+//! distributed out of band; genesis only records its fingerprint, in the account at
+//! `fingerprint_account::id()`, which also makes the shred version of encrypted clusters
+//! distinct from any other cluster's.
+
+use crate::shred::{SHRED_PAYLOAD_SIZE, SIZE_OF_COMMON_SHRED_HEADER, SIZE_OF_SIGNATURE};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use sha2::{Digest, Sha256};
+use solana_sdk::{genesis_config::GenesisConfig, hash::Hash};
+use std::{fmt, fs, io, path::Path};
+
+pub mod fingerprint_account {
+    solana_sdk::declare_id!("ShredEncryption1111111111111111111111111111");
+}
+
+pub const SHRED_ENCRYPTION_KEY_SIZE: usize = 32;
+
+#[derive(Clone, PartialEq)]
+pub struct ShredEncryptionKey([u8; SHRED_ENCRYPTION_KEY_SIZE]);
+
+impl fmt::Debug for ShredEncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never log the key itself
+        write!(f, "ShredEncryptionKey({})", self.fingerprint())
+    }
+}
+
+impl ShredEncryptionKey {
+    pub fn new_rand() -> Self {
+        let mut key = [0u8; SHRED_ENCRYPTION_KEY_SIZE];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self(key)
+    }
+
+    /// Hash identifying the key, safe to publish
+    pub fn fingerprint(&self) -> Hash {
+        let mut hasher = Sha256::new();
+        hasher.update(b"solana-shred-encryption-fingerprint");
+        hasher.update(&self.0);
+        Hash::new(&hasher.finalize())
+    }
+
+    /// Reads a key file holding the key as 64 hexadecimal characters
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let contents = contents.trim();
+        let invalid_key = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "expected {} hexadecimal characters",
+                    SHRED_ENCRYPTION_KEY_SIZE * 2
+                ),
+            )
+        };
+        if contents.len() != SHRED_ENCRYPTION_KEY_SIZE * 2 || !contents.is_ascii() {
+            return Err(invalid_key());
+        }
+        let mut key = [0u8; SHRED_ENCRYPTION_KEY_SIZE];
+        for (byte, hex) in key.iter_mut().zip(contents.as_bytes().chunks(2)) {
+            let hex = std::str::from_utf8(hex).map_err(|_| invalid_key())?;
+            *byte = u8::from_str_radix(hex, 16).map_err(|_| invalid_key())?;
+        }
+        Ok(Self(key))
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let hex: String = self.0.iter().map(|byte| format!("{:02x}", byte)).collect();
+        fs::write(path, hex + "\n")
+    }
+}
+
+/// Returns the fingerprint of the shred encryption key of the cluster, if its shreds are
+/// encrypted
+pub fn genesis_key_fingerprint(genesis_config: &GenesisConfig) -> Option<Hash> {
+    genesis_config
+        .accounts
+        .get(&fingerprint_account::id())
+        .filter(|account| account.data.len() == std::mem::size_of::<Hash>())
+        .map(|account| Hash::new(&account.data))
+}
+
+pub struct ShredCipher {
+    key: ShredEncryptionKey,
+}
+
+impl ShredCipher {
+    pub fn new(key: ShredEncryptionKey) -> Self {
+        Self { key }
+    }
+
+    /// Encrypts or decrypts, the operation being its own inverse, the shred at the start of
+    /// `buf`.  Anything past the shred payload, such as a repair nonce, is left untouched
+    pub fn apply(&self, buf: &mut [u8]) {
+        let end = buf.len().min(SHRED_PAYLOAD_SIZE);
+        if end <= SIZE_OF_COMMON_SHRED_HEADER {
+            return;
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(&self.key.0);
+        hasher.update(&buf[..SIZE_OF_SIGNATURE]);
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(&hasher.finalize());
+        let mut keystream = vec![0u8; end - SIZE_OF_COMMON_SHRED_HEADER];
+        ChaChaRng::from_seed(seed).fill_bytes(&mut keystream);
+        buf[SIZE_OF_COMMON_SHRED_HEADER..end]
+            .iter_mut()
+            .zip(keystream)
+            .for_each(|(byte, key)| *byte ^= key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shred::{Shred, Shredder};
+    use solana_sdk::{
+        account::Account,
+        signature::{Keypair, Signer},
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn test_shred_cipher() {
+        let keypair = Arc::new(Keypair::new());
+        let mut shred = Shred::new_from_data(5, 3, 1, Some(&[1, 2, 3, 4]), true, true, 0, 7, 3);
+        Shredder::sign_shred(&keypair, &mut shred);
+        let plaintext = shred.payload.clone();
+
+        let cipher = ShredCipher::new(ShredEncryptionKey::new_rand());
+        let mut buf = plaintext.clone();
+        // A repair nonce past the shred payload
+        buf.extend_from_slice(&[9, 9, 9, 9]);
+        cipher.apply(&mut buf);
+        assert_eq!(
+            buf[..SIZE_OF_COMMON_SHRED_HEADER],
+            plaintext[..SIZE_OF_COMMON_SHRED_HEADER]
+        );
+        assert_ne!(buf[..plaintext.len()], plaintext[..]);
+        assert_eq!(buf[plaintext.len()..], [9, 9, 9, 9]);
+
+        // Another key does not decrypt the shred
+        let mut other = buf.clone();
+        ShredCipher::new(ShredEncryptionKey::new_rand()).apply(&mut other);
+        assert!(
+            Shred::new_from_serialized_shred(other[..plaintext.len()].to_vec())
+                .map(|other| !other.verify(&keypair.pubkey()))
+                .unwrap_or(true)
+        );
+
+        cipher.apply(&mut buf);
+        assert_eq!(buf[..plaintext.len()], plaintext[..]);
+        let decrypted = Shred::new_from_serialized_shred(buf[..plaintext.len()].to_vec()).unwrap();
+        assert!(decrypted.verify(&keypair.pubkey()));
+        assert_eq!(decrypted, shred);
+    }
+
+    #[test]
+    fn test_key_file_and_fingerprint() {
+        let key = ShredEncryptionKey::new_rand();
+        let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+        key.write(&path).unwrap();
+        assert_eq!(ShredEncryptionKey::read(&path).unwrap(), key);
+        fs::write(&path, "not a key").unwrap();
+        assert!(ShredEncryptionKey::read(&path).is_err());
+
+        let mut genesis_config = GenesisConfig::default();
+        assert_eq!(genesis_key_fingerprint(&genesis_config), None);
+        genesis_config.add_account(
+            fingerprint_account::id(),
+            Account {
+                lamports: 1,
+                data: key.fingerprint().as_ref().to_vec(),
+                ..Account::default()
+            },
+        );
+        assert_eq!(
+            genesis_key_fingerprint(&genesis_config),
+            Some(key.fingerprint())
+        );
+        assert_ne!(
+            ShredEncryptionKey::new_rand().fingerprint(),
+            key.fingerprint()
+        );
+    }
+}
@@ -0,0 +1,409 @@
+//! An append-only journal of what the validator signed with its identity and vote keys, so that
+//! operators can audit what a possibly compromised key signed.  Each event is a line of tab
+//! separated columns, readable by `solana-ledger-tool signing-journal` as well as grep, and a
+//! crash mid-write only tears the last line
+use solana_sdk::{
+    clock::Slot,
+    hash::{hash, Hash},
+    pubkey::Pubkey,
+    signature::Signature,
+    timing::timestamp,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SigningEventKind {
+    /// The validator started, with its identity and authorized voters as the signers
+    Startup,
+    /// A vote transaction, signed by the identity and the authorized voter
+    Vote,
+    /// Votes are signed by another authorized voter than the previous vote was
+    AuthorizedVoterRotation,
+    /// The shreds of a block produced by the validator, recorded once its last shred is signed
+    Shreds,
+    /// The tower saved after voting, signed by the identity
+    Tower,
+    /// A gossip value or prune message, signed by the identity.  Pings and pongs only sign random
+    /// tokens and aren't recorded
+    Gossip,
+}
+
+impl fmt::Display for SigningEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            Self::Startup => "startup",
+            Self::Vote => "vote",
+            Self::AuthorizedVoterRotation => "authorized-voter-rotation",
+            Self::Shreds => "shreds",
+            Self::Tower => "tower",
+            Self::Gossip => "gossip",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+impl FromStr for SigningEventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "startup" => Ok(Self::Startup),
+            "vote" => Ok(Self::Vote),
+            "authorized-voter-rotation" => Ok(Self::AuthorizedVoterRotation),
+            "shreds" => Ok(Self::Shreds),
+            "tower" => Ok(Self::Tower),
+            "gossip" => Ok(Self::Gossip),
+            _ => Err(format!("Unknown signing event kind: {}", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SigningEvent {
+    /// Milliseconds since the UNIX epoch
+    pub timestamp: u64,
+    pub kind: SigningEventKind,
+    /// The slot voted on or produced, the root at startup, or 0 for gossip
+    pub slot: Slot,
+    /// The bank hash voted on, the hash of the last entry of a block, the root's bank hash at
+    /// startup, or the hash of the signed gossip message.  Default when unknown, like for blocks
+    /// interrupted before their last entry
+    pub hash: Hash,
+    /// The signature of the vote transaction, the last shred, the saved tower or the gossip
+    /// message, default otherwise
+    pub signature: Signature,
+    pub signers: Vec<Pubkey>,
+}
+
+impl fmt::Display for SigningEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let signers: Vec<_> = self.signers.iter().map(|s| s.to_string()).collect();
+        write!(
+            f,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.timestamp,
+            self.kind,
+            self.slot,
+            self.hash,
+            self.signature,
+            signers.join(",")
+        )
+    }
+}
+
+impl FromStr for SigningEvent {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let columns: Vec<_> = line.split('\t').collect();
+        if columns.len() != 6 {
+            return Err(format!("Expected 6 columns, found {}", columns.len()));
+        }
+        let parse_error = |name: &str, err: &dyn fmt::Debug| format!("Invalid {}: {:?}", name, err);
+        let signers = if columns[5].is_empty() {
+            vec![]
+        } else {
+            columns[5]
+                .split(',')
+                .map(|signer| signer.parse().map_err(|err| parse_error("signer", &err)))
+                .collect::<Result<_, _>>()?
+        };
+        Ok(Self {
+            timestamp: columns[0]
+                .parse()
+                .map_err(|err| parse_error("timestamp", &err))?,
+            kind: columns[1].parse()?,
+            slot: columns[2]
+                .parse()
+                .map_err(|err| parse_error("slot", &err))?,
+            hash: columns[3]
+                .parse()
+                .map_err(|err| parse_error("hash", &err))?,
+            signature: columns[4]
+                .parse()
+                .map_err(|err| parse_error("signature", &err))?,
+            signers,
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SigningJournalError {
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Line {line} of the signing journal is invalid: {reason}")]
+    InvalidLine { line: usize, reason: String },
+}
+
+struct SigningJournalInner {
+    file: File,
+    last_authorized_voter: Option<Pubkey>,
+}
+
+pub struct SigningJournal {
+    path: PathBuf,
+    inner: Mutex<SigningJournalInner>,
+}
+
+impl SigningJournal {
+    /// Opens the journal at `path` for appending, creating it if missing
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            inner: Mutex::new(SigningJournalInner {
+                file,
+                last_authorized_voter: None,
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `event` with a single write.  Failures are logged rather than returned, the
+    /// journal must not keep the validator from signing
+    pub fn record(&self, event: &SigningEvent) {
+        let mut inner = self.inner.lock().unwrap();
+        Self::append(&mut inner.file, event, &self.path);
+    }
+
+    fn append(file: &mut File, event: &SigningEvent, path: &Path) {
+        if let Err(err) = file.write_all(format!("{}\n", event).as_bytes()) {
+            error!("Unable to write to signing journal {:?}: {}", path, err);
+            inc_new_counter_error!("signing_journal-write_error", 1);
+        }
+    }
+
+    pub fn record_startup(&self, root: Slot, root_hash: Hash, signers: Vec<Pubkey>) {
+        self.record(&SigningEvent {
+            timestamp: timestamp(),
+            kind: SigningEventKind::Startup,
+            slot: root,
+            hash: root_hash,
+            signature: Signature::default(),
+            signers,
+        });
+    }
+
+    /// Records a vote on `bank_hash` of `slot`, preceded by a rotation event when its authorized
+    /// voter isn't the one of the previous vote since startup
+    pub fn record_vote(
+        &self,
+        slot: Slot,
+        bank_hash: Hash,
+        signature: Signature,
+        identity: Pubkey,
+        authorized_voter: Pubkey,
+    ) {
+        let now = timestamp();
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(last_authorized_voter) = inner.last_authorized_voter {
+            if last_authorized_voter != authorized_voter {
+                let rotation = SigningEvent {
+                    timestamp: now,
+                    kind: SigningEventKind::AuthorizedVoterRotation,
+                    slot,
+                    hash: Hash::default(),
+                    signature: Signature::default(),
+                    signers: vec![last_authorized_voter, authorized_voter],
+                };
+                Self::append(&mut inner.file, &rotation, &self.path);
+            }
+        }
+        inner.last_authorized_voter = Some(authorized_voter);
+        let vote = SigningEvent {
+            timestamp: now,
+            kind: SigningEventKind::Vote,
+            slot,
+            hash: bank_hash,
+            signature,
+            signers: vec![identity, authorized_voter],
+        };
+        Self::append(&mut inner.file, &vote, &self.path);
+    }
+
+    pub fn record_shreds(
+        &self,
+        slot: Slot,
+        last_entry_hash: Hash,
+        signature: Signature,
+        identity: Pubkey,
+    ) {
+        self.record(&SigningEvent {
+            timestamp: timestamp(),
+            kind: SigningEventKind::Shreds,
+            slot,
+            hash: last_entry_hash,
+            signature,
+            signers: vec![identity],
+        });
+    }
+
+    /// Records the tower saved after voting on `bank_hash` of `slot`
+    pub fn record_tower(
+        &self,
+        slot: Slot,
+        bank_hash: Hash,
+        signature: Signature,
+        identity: Pubkey,
+    ) {
+        self.record(&SigningEvent {
+            timestamp: timestamp(),
+            kind: SigningEventKind::Tower,
+            slot,
+            hash: bank_hash,
+            signature,
+            signers: vec![identity],
+        });
+    }
+
+    /// Records the gossip message whose signed bytes are `signed_data`
+    pub fn record_gossip(&self, signed_data: &[u8], signature: Signature, identity: Pubkey) {
+        self.record(&SigningEvent {
+            timestamp: timestamp(),
+            kind: SigningEventKind::Gossip,
+            slot: 0,
+            hash: hash(signed_data),
+            signature,
+            signers: vec![identity],
+        });
+    }
+}
+
+/// Reads the events of the journal at `path`.  A last line without its newline was torn by a
+/// crash and is skipped, any other invalid line is an error
+pub fn read_signing_journal(path: &Path) -> Result<Vec<SigningEvent>, SigningJournalError> {
+    let contents = std::fs::read_to_string(path)?;
+    let complete = contents.ends_with('\n');
+    let lines: Vec<_> = contents.lines().collect();
+    let mut events = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        match line.parse() {
+            Ok(event) => events.push(event),
+            Err(_) if !complete && i + 1 == lines.len() => {
+                warn!("Skipping the torn last line of signing journal {:?}", path);
+            }
+            Err(reason) => {
+                return Err(SigningJournalError::InvalidLine {
+                    line: i + 1,
+                    reason,
+                })
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// Slots for which events of the same kind signed different hashes, by kind and slot.  Blocks
+/// produced twice, or votes on two versions of a block, are what a key used by two validators at
+/// once signs
+pub fn find_conflicting_hashes(
+    events: &[SigningEvent],
+) -> BTreeMap<(SigningEventKind, Slot), BTreeSet<Hash>> {
+    let mut hashes: BTreeMap<_, BTreeSet<_>> = BTreeMap::new();
+    for event in events {
+        let signs_hash = matches!(
+            event.kind,
+            SigningEventKind::Vote | SigningEventKind::Shreds
+        );
+        if signs_hash && event.hash != Hash::default() {
+            hashes
+                .entry((event.kind, event.slot))
+                .or_default()
+                .insert(event.hash);
+        }
+    }
+    hashes
+        .into_iter()
+        .filter(|(_, hashes)| hashes.len() > 1)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_journal() {
+        let ledger_path = get_tmp_ledger_path!();
+        std::fs::create_dir_all(&ledger_path).unwrap();
+        let path = ledger_path.join("signing-journal");
+        let identity = Pubkey::new_unique();
+        let voter = Pubkey::new_unique();
+        let new_voter = Pubkey::new_unique();
+        let signature = Signature::new(&[7u8; 64]);
+
+        let journal = SigningJournal::open(&path).unwrap();
+        journal.record_startup(10, hash(&[10]), vec![identity, voter, new_voter]);
+        journal.record_vote(11, hash(&[11]), signature, identity, voter);
+        journal.record_shreds(12, hash(&[12]), signature, identity);
+        journal.record_vote(12, hash(&[12]), signature, identity, new_voter);
+        journal.record_tower(12, hash(&[12]), signature, identity);
+        journal.record_gossip(b"contact info", signature, identity);
+        drop(journal);
+
+        // Reopening appends
+        let journal = SigningJournal::open(&path).unwrap();
+        journal.record_shreds(12, hash(&[13]), signature, identity);
+        drop(journal);
+
+        let events = read_signing_journal(&path).unwrap();
+        let kinds: Vec<_> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SigningEventKind::Startup,
+                SigningEventKind::Vote,
+                SigningEventKind::Shreds,
+                SigningEventKind::AuthorizedVoterRotation,
+                SigningEventKind::Vote,
+                SigningEventKind::Tower,
+                SigningEventKind::Gossip,
+                SigningEventKind::Shreds,
+            ]
+        );
+        assert_eq!(events[1].slot, 11);
+        assert_eq!(events[1].hash, hash(&[11]));
+        assert_eq!(events[1].signature, signature);
+        assert_eq!(events[1].signers, vec![identity, voter]);
+        assert_eq!(events[3].signers, vec![voter, new_voter]);
+        assert_eq!(events[5].slot, 12);
+        assert_eq!(events[5].hash, hash(&[12]));
+        assert_eq!(events[5].signers, vec![identity]);
+        assert_eq!(events[6].slot, 0);
+        assert_eq!(events[6].hash, hash(b"contact info"));
+        assert_eq!(events[6].signature, signature);
+
+        // Two different blocks were produced for slot 12
+        let conflicts = find_conflicting_hashes(&events);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[&(SigningEventKind::Shreds, 12)],
+            vec![hash(&[12]), hash(&[13])].into_iter().collect()
+        );
+
+        // A torn last line is skipped, an invalid line before it is not
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"1614556800000\tvote\t13").unwrap();
+        assert_eq!(read_signing_journal(&path).unwrap(), events);
+        file.write_all(b"\n").unwrap();
+        assert!(matches!(
+            read_signing_journal(&path),
+            Err(SigningJournalError::InvalidLine { line: 9, .. })
+        ));
+
+        std::fs::remove_dir_all(&ledger_path).unwrap();
+    }
+}
@@ -15,7 +15,7 @@ use solana_perf::{
     recycler_cache::RecyclerCache,
     sigverify::{self, batch_size, TxOffset},
 };
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_sdk::{
     clock::Slot,
     pubkey::Pubkey,
@@ -28,11 +28,8 @@ use std::{collections::HashMap, mem::size_of};
 pub const SIGN_SHRED_GPU_MIN: usize = 256;
 
 lazy_static! {
-    pub static ref SIGVERIFY_THREAD_POOL: ThreadPool = rayon::ThreadPoolBuilder::new()
-        .num_threads(get_thread_count())
-        .thread_name(|ix| format!("sigverify_shreds_{}", ix))
-        .build()
-        .unwrap();
+    pub static ref SIGVERIFY_THREAD_POOL: ThreadPool =
+        thread_manager::build_thread_pool("solana-sigverify-shreds", get_thread_count());
 }
 
 /// Assuming layout is
@@ -11,7 +11,7 @@ use solana_core::{
     gossip_service::discover_cluster,
     validator::{Validator, ValidatorConfig},
 };
-use solana_ledger::create_new_tmp_ledger;
+use solana_ledger::{create_new_tmp_ledger, leader_schedule::FixedSchedule};
 use solana_runtime::genesis_utils::{
     create_genesis_config_with_vote_accounts_and_cluster_type, GenesisConfigInfo,
     ValidatorVoteKeypairs,
@@ -69,6 +69,9 @@ pub struct ClusterConfig {
     pub cluster_type: ClusterType,
     pub poh_config: PohConfig,
     pub additional_accounts: Vec<(Pubkey, Account)>,
+    /// Leader schedule every node in the cluster follows instead of the stake-weighted one,
+    /// naming nodes by the keys in `validator_keys`
+    pub fixed_leader_schedule: Option<FixedSchedule>,
 }
 
 impl Default for ClusterConfig {
@@ -87,6 +90,7 @@ impl Default for ClusterConfig {
             poh_config: PohConfig::default(),
             skip_warmup_slots: false,
             additional_accounts: vec![],
+            fixed_leader_schedule: None,
         }
     }
 }
@@ -118,6 +122,11 @@ impl LocalCluster {
 
     pub fn new(config: &mut ClusterConfig) -> Self {
         assert_eq!(config.validator_configs.len(), config.node_stakes.len());
+        if let Some(ref fixed_leader_schedule) = config.fixed_leader_schedule {
+            for validator_config in config.validator_configs.iter_mut() {
+                validator_config.fixed_leader_schedule = Some(fixed_leader_schedule.clone());
+            }
+        }
         let mut validator_keys = {
             if let Some(ref keys) = config.validator_keys {
                 assert_eq!(config.validator_configs.len(), keys.len());
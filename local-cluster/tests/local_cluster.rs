@@ -324,16 +324,17 @@ fn run_cluster_partition<E, F>(
     assert_eq!(node_stakes.len(), num_nodes);
     let cluster_lamports = node_stakes.iter().sum::<u64>() * 2;
     let enable_partition = Arc::new(AtomicBool::new(true));
-    let mut validator_config = ValidatorConfig {
+    let validator_config = ValidatorConfig {
         enable_partition: Some(enable_partition.clone()),
         ..ValidatorConfig::default()
     };
 
     // Returns:
     // 1) The keys for the validators
-    // 2) The amount of time it would take to iterate through one full iteration of the given
+    // 2) The fixed leader schedule of the cluster, if any
+    // 3) The amount of time it would take to iterate through one full iteration of the given
     // leader schedule
-    let (validator_keys, leader_schedule_time): (Vec<_>, u64) = {
+    let (validator_keys, fixed_leader_schedule, leader_schedule_time): (Vec<_>, _, u64) = {
         if let Some((leader_schedule, validator_keys)) = leader_schedule {
             assert_eq!(validator_keys.len(), num_nodes);
             let num_slots_per_rotation = leader_schedule.num_slots() as u64;
@@ -341,9 +342,9 @@ fn run_cluster_partition<E, F>(
                 start_epoch: 0,
                 leader_schedule: Arc::new(leader_schedule),
             };
-            validator_config.fixed_leader_schedule = Some(fixed_schedule);
             (
                 validator_keys,
+                Some(fixed_schedule),
                 num_slots_per_rotation * clock::DEFAULT_MS_PER_SLOT,
             )
         } else {
@@ -351,6 +352,7 @@ fn run_cluster_partition<E, F>(
                 iter::repeat_with(|| Arc::new(Keypair::new()))
                     .take(partitions.len())
                     .collect(),
+                None,
                 10_000,
             )
         }
@@ -371,6 +373,7 @@ fn run_cluster_partition<E, F>(
         stakers_slot_offset: slots_per_epoch,
         skip_warmup_slots: true,
         additional_accounts,
+        fixed_leader_schedule,
         ..ClusterConfig::default()
     };
 
@@ -1422,6 +1425,39 @@ fn test_no_voting() {
     }
 }
 
+#[test]
+fn test_rpc_only_listener() {
+    solana_logger::setup();
+    let mut config = ClusterConfig {
+        cluster_lamports: 10_000,
+        node_stakes: vec![100],
+        validator_configs: vec![ValidatorConfig::default()],
+        ..ClusterConfig::default()
+    };
+    let mut cluster = LocalCluster::new(&mut config);
+    let rpc_only_config = ValidatorConfig {
+        voting_disabled: true,
+        rpc_only: true,
+        ..ValidatorConfig::default()
+    };
+    let rpc_only_pubkey =
+        cluster.add_validator(&rpc_only_config, 0, Arc::new(Keypair::new()), None);
+    let client = cluster.get_validator_client(&rpc_only_pubkey).unwrap();
+
+    // The rpc-only node should keep replaying the leader's blocks
+    let mut last_slot = 0;
+    for _ in 0..30 {
+        last_slot = client
+            .get_slot_with_commitment(CommitmentConfig::processed())
+            .expect("Couldn't get slot");
+        if last_slot > 2 * VOTE_THRESHOLD_DEPTH as u64 {
+            break;
+        }
+        sleep(Duration::from_secs(1));
+    }
+    assert!(last_slot > 2 * VOTE_THRESHOLD_DEPTH as u64);
+}
+
 #[test]
 #[serial]
 fn test_optimistic_confirmation_violation_detection() {
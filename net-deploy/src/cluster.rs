@@ -0,0 +1,693 @@
+//! The steps bringing up a cluster: provision the instances, install the software, create the
+//! genesis with every validator staked, start the validators and run bench-tps from the clients
+use {
+    crate::{
+        command::{shell_join, shell_quote},
+        config::{BenchTpsScenario, ClusterConfig, NodeConfig, Software},
+        error::{DeployError, Result},
+        provider::{new_provider, Instance, Provider},
+        template,
+    },
+    log::*,
+    std::{
+        collections::HashMap,
+        fmt, fs,
+        thread::sleep,
+        time::{Duration, Instant},
+    },
+};
+
+/// Directory of the keypairs, ledger and logs, under the home directory of the nodes
+const DEPLOY_DIR: &str = "solana-net-deploy";
+const GOSSIP_PORT: u16 = 8001;
+const RPC_PORT: u16 = 8899;
+const FAUCET_PORT: u16 = 9900;
+
+const READY_TIMEOUT: Duration = Duration::from_secs(600);
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Role {
+    Bootstrap,
+    Validator(usize),
+    Client(usize),
+}
+
+impl Role {
+    pub fn instance_name(&self, cluster: &str) -> String {
+        format!("{}-{}", cluster, self)
+    }
+
+    pub fn from_instance_name(cluster: &str, name: &str) -> Option<Self> {
+        let suffix = name.strip_prefix(cluster)?.strip_prefix('-')?;
+        if suffix == "bootstrap" {
+            Some(Role::Bootstrap)
+        } else if let Some(index) = suffix.strip_prefix("validator-") {
+            index.parse().ok().map(Role::Validator)
+        } else if let Some(index) = suffix.strip_prefix("client-") {
+            index.parse().ok().map(Role::Client)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Role::Bootstrap => write!(f, "bootstrap"),
+            Role::Validator(index) => write!(f, "validator-{}", index),
+            Role::Client(index) => write!(f, "client-{}", index),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub role: Role,
+    pub instance: Instance,
+}
+
+/// Keypairs and paths of a validator, absolute as processes are started from another directory
+#[derive(Debug)]
+struct ValidatorFiles {
+    dir: String,
+    identity: String,
+    vote_account: String,
+    stake_account: String,
+    ledger: String,
+    log: String,
+}
+
+impl ValidatorFiles {
+    fn new(home: &str) -> Self {
+        let dir = format!("{}/{}", home.trim_end_matches('/'), DEPLOY_DIR);
+        Self {
+            identity: format!("{}/identity.json", dir),
+            vote_account: format!("{}/vote-account.json", dir),
+            stake_account: format!("{}/stake-account.json", dir),
+            ledger: format!("{}/ledger", dir),
+            log: format!("{}/validator.log", dir),
+            dir,
+        }
+    }
+}
+
+pub fn default_bootstrap_validator_args() -> Vec<String> {
+    let mut args = common_validator_args();
+    args.extend(
+        vec![
+            "--gossip-host",
+            "{{private_ip}}",
+            "--rpc-faucet-address",
+            "{{faucet}}",
+        ]
+        .into_iter()
+        .map(String::from),
+    );
+    args
+}
+
+pub fn default_validator_args() -> Vec<String> {
+    let mut args = common_validator_args();
+    args.extend(
+        vec!["--entrypoint", "{{entrypoint}}", "--no-poh-speed-test"]
+            .into_iter()
+            .map(String::from),
+    );
+    args
+}
+
+fn common_validator_args() -> Vec<String> {
+    vec![
+        "--identity",
+        "{{identity}}",
+        "--vote-account",
+        "{{vote_account}}",
+        "--ledger",
+        "{{ledger}}",
+        "--log",
+        "{{log}}",
+        "--gossip-port",
+        "8001",
+        "--rpc-port",
+        "8899",
+        "--dynamic-port-range",
+        "8002-8020",
+        "--expected-genesis-hash",
+        "{{genesis_hash}}",
+        "--expected-shred-version",
+        "{{shred_version}}",
+        "--enable-rpc-transaction-history",
+        "--limit-ledger-size",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+pub struct Cluster {
+    config: ClusterConfig,
+    provider: Box<dyn Provider>,
+}
+
+impl Cluster {
+    pub fn new(config: ClusterConfig) -> Self {
+        let provider = new_provider(&config.provider, &config.ssh, &config.name);
+        Self { config, provider }
+    }
+
+    fn roles(&self) -> Vec<Role> {
+        let mut roles = vec![Role::Bootstrap];
+        roles.extend((0..self.config.validators.count).map(Role::Validator));
+        roles.extend((0..self.config.clients.count).map(Role::Client));
+        roles
+    }
+
+    /// The running instances of the cluster, whether or not its config still lists them
+    pub fn nodes(&self) -> Result<Vec<Node>> {
+        let prefix = format!("{}-", self.config.name);
+        let mut nodes = vec![];
+        for instance in self.provider.list_instances(&prefix)? {
+            match Role::from_instance_name(&self.config.name, &instance.name) {
+                Some(role) => nodes.push(Node { role, instance }),
+                None => warn!("Ignoring instance {}", instance.name),
+            }
+        }
+        nodes.sort_by_key(|node| node.role);
+        Ok(nodes)
+    }
+
+    /// The nodes of every role of the config, failing if any of them is missing
+    fn expected_nodes(&self) -> Result<Vec<Node>> {
+        let nodes = self.nodes()?;
+        let missing: Vec<_> = self
+            .roles()
+            .into_iter()
+            .filter(|role| !nodes.iter().any(|node| node.role == *role))
+            .map(|role| role.instance_name(&self.config.name))
+            .collect();
+        if !missing.is_empty() {
+            return Err(DeployError::Cluster(format!(
+                "Instances {} are missing, run `create` first",
+                missing.join(", ")
+            )));
+        }
+        let roles = self.roles();
+        Ok(nodes
+            .into_iter()
+            .filter(|node| roles.contains(&node.role))
+            .collect())
+    }
+
+    /// Runs `command` on `node` with the Solana binaries on the PATH
+    fn run(&self, node: &Node, command: &str) -> Result<String> {
+        debug!("{}: {}", node.role, command);
+        let path = "export PATH=\"$HOME/solana-release/bin:\
+                    $HOME/.local/share/solana/install/active_release/bin:$PATH\"";
+        self.provider
+            .exec(&node.instance, &format!("{}; {}", path, command))
+    }
+
+    /// Provisions the instances missing from the cluster and installs the software on them
+    pub fn create(&self) -> Result<()> {
+        let existing = self.nodes()?;
+        let missing: Vec<_> = self
+            .roles()
+            .into_iter()
+            .filter(|role| !existing.iter().any(|node| node.role == *role))
+            .map(|role| role.instance_name(&self.config.name))
+            .collect();
+        if missing.is_empty() {
+            info!("All {} instances already exist", existing.len());
+        } else {
+            info!("Creating {}", missing.join(", "));
+            self.provider.create_instances(&missing)?;
+        }
+
+        let mut nodes = vec![];
+        wait_for("the instances to boot", || match self.expected_nodes() {
+            Ok(expected) => {
+                nodes = expected;
+                true
+            }
+            Err(_) => false,
+        })?;
+        for node in &nodes {
+            wait_for(&format!("{} to accept commands", node.role), || {
+                self.provider.exec(&node.instance, "true").is_ok()
+            })?;
+            self.install_software(node)?;
+        }
+        info!("Cluster {} is ready to start", self.config.name);
+        Ok(())
+    }
+
+    fn install_software(&self, node: &Node) -> Result<()> {
+        match &self.config.software {
+            Software::Preinstalled => {}
+            Software::Release(release) => {
+                info!("Installing {} on {}", release, node.role);
+                self.run(
+                    node,
+                    &format!(
+                        "curl -sSfL https://release.solana.com/{}/install | sh -s - {}",
+                        release,
+                        shell_quote(release)
+                    ),
+                )?;
+            }
+            Software::Tarball(tarball) => {
+                info!("Copying {:?} to {}", tarball, node.role);
+                self.provider
+                    .copy_to(&node.instance, tarball, "solana-release.tar.bz2")?;
+                self.run(
+                    node,
+                    "rm -rf solana-release && tar -xjf solana-release.tar.bz2 \
+                     && rm solana-release.tar.bz2",
+                )?;
+            }
+        }
+        self.run(node, "solana --version").map(|version| {
+            info!("{}: {}", node.role, version.trim());
+        })
+    }
+
+    /// Creates a new genesis and (re)starts the validators and the faucet from it
+    pub fn start(&self) -> Result<()> {
+        let nodes = self.expected_nodes()?;
+        self.stop_nodes(&nodes)?;
+        let validators: Vec<_> = nodes
+            .iter()
+            .filter(|node| !matches!(node.role, Role::Client(_)))
+            .collect();
+        let bootstrap = validators[0];
+
+        let mut files = HashMap::new();
+        let mut genesis_args = vec![];
+        for node in &validators {
+            let home = self.run(node, "echo $HOME")?.trim().to_string();
+            let node_files = ValidatorFiles::new(&home);
+            let mut keygen = vec![format!("mkdir -p {}", shell_quote(&node_files.dir))];
+            for keypair in &[
+                &node_files.identity,
+                &node_files.vote_account,
+                &node_files.stake_account,
+            ] {
+                keygen.push(shell_join(&[
+                    "solana-keygen",
+                    "new",
+                    "--no-passphrase",
+                    "--silent",
+                    "--force",
+                    "-o",
+                    keypair.as_str(),
+                ]));
+            }
+            self.run(node, &keygen.join(" && "))?;
+            genesis_args.push("--bootstrap-validator".to_string());
+            for keypair in &[
+                &node_files.identity,
+                &node_files.vote_account,
+                &node_files.stake_account,
+            ] {
+                let pubkey = self.run(
+                    node,
+                    &shell_join(&["solana-keygen", "pubkey", keypair.as_str()]),
+                )?;
+                genesis_args.push(pubkey.trim().to_string());
+            }
+            files.insert(node.role, node_files);
+        }
+
+        let bootstrap_files = &files[&Role::Bootstrap];
+        let faucet_keypair = format!("{}/faucet.json", bootstrap_files.dir);
+        let genesis = &self.config.genesis;
+        let mut genesis_command = vec![
+            "solana-genesis".to_string(),
+            "--ledger".to_string(),
+            bootstrap_files.ledger.clone(),
+            "--cluster-type".to_string(),
+            genesis.cluster_type.clone(),
+            "--hashes-per-tick".to_string(),
+            genesis.hashes_per_tick.clone(),
+            "--faucet-pubkey".to_string(),
+            faucet_keypair.clone(),
+            "--faucet-lamports".to_string(),
+            genesis.faucet_lamports.to_string(),
+            "--bootstrap-validator-lamports".to_string(),
+            genesis.validator_lamports.to_string(),
+            "--bootstrap-validator-stake-lamports".to_string(),
+            genesis.validator_stake_lamports.to_string(),
+        ];
+        genesis_command.extend(genesis_args);
+        genesis_command.extend(genesis.extra_args.iter().cloned());
+        info!("Creating the genesis of {} validators", validators.len());
+        self.run(
+            bootstrap,
+            &format!(
+                "rm -rf {ledger} && {keygen} && {genesis}",
+                ledger = shell_quote(&bootstrap_files.ledger),
+                keygen = shell_join(&[
+                    "solana-keygen",
+                    "new",
+                    "--no-passphrase",
+                    "--silent",
+                    "--force",
+                    "-o",
+                    faucet_keypair.as_str(),
+                ]),
+                genesis = shell_join(&genesis_command),
+            ),
+        )?;
+        let ledger_tool = |subcommand: &str| {
+            self.run(
+                bootstrap,
+                &shell_join(&[
+                    "solana-ledger-tool",
+                    "--ledger",
+                    bootstrap_files.ledger.as_str(),
+                    subcommand,
+                ]),
+            )
+            .map(|output| output.trim().to_string())
+        };
+        let genesis_hash = ledger_tool("genesis-hash")?;
+        let shred_version = ledger_tool("shred-version")?;
+        info!(
+            "Genesis hash {}, shred version {}",
+            genesis_hash, shred_version
+        );
+
+        self.distribute_genesis(&validators, &files)?;
+
+        let entrypoint = format!("{}:{}", bootstrap.instance.private_ip, GOSSIP_PORT);
+        let faucet = format!("{}:{}", bootstrap.instance.private_ip, FAUCET_PORT);
+        self.run(
+            bootstrap,
+            &format!(
+                "nohup {} > {}/faucet.log 2>&1 < /dev/null &",
+                shell_join(&["solana-faucet", "--keypair", faucet_keypair.as_str()]),
+                shell_quote(&bootstrap_files.dir)
+            ),
+        )?;
+        for node in &validators {
+            let node_files = &files[&node.role];
+            let mut vars: HashMap<&str, String> = HashMap::new();
+            vars.insert("name", node.instance.name.clone());
+            vars.insert("public_ip", node.instance.public_ip.clone());
+            vars.insert("private_ip", node.instance.private_ip.clone());
+            vars.insert("identity", node_files.identity.clone());
+            vars.insert("vote_account", node_files.vote_account.clone());
+            vars.insert("ledger", node_files.ledger.clone());
+            vars.insert("log", node_files.log.clone());
+            vars.insert("entrypoint", entrypoint.clone());
+            vars.insert("faucet", faucet.clone());
+            vars.insert("genesis_hash", genesis_hash.clone());
+            vars.insert("shred_version", shred_version.clone());
+            let (node_config, default_args) = match node.role {
+                Role::Bootstrap => (
+                    self.config.bootstrap_validator.clone(),
+                    default_bootstrap_validator_args(),
+                ),
+                _ => (self.config.validators.node(), default_validator_args()),
+            };
+            let args = validator_args(&node_config, default_args, &vars)?;
+            info!("Starting {}", node.role);
+            self.run(
+                node,
+                &format!(
+                    "cd {} && nohup solana-validator {} > validator.out 2>&1 < /dev/null &",
+                    shell_quote(&node_files.dir),
+                    shell_join(&args)
+                ),
+            )?;
+            if node.role == Role::Bootstrap {
+                wait_for("the RPC of the bootstrap validator", || {
+                    self.run(
+                        bootstrap,
+                        &format!("solana --url http://127.0.0.1:{} cluster-version", RPC_PORT),
+                    )
+                    .is_ok()
+                })?;
+            }
+        }
+
+        info!("Waiting for {} validators in gossip", validators.len());
+        self.run(
+            bootstrap,
+            &shell_join(&[
+                "solana-gossip",
+                "spy",
+                "--entrypoint",
+                format!("127.0.0.1:{}", GOSSIP_PORT).as_str(),
+                "--num-nodes",
+                validators.len().to_string().as_str(),
+                "--timeout",
+                READY_TIMEOUT.as_secs().to_string().as_str(),
+            ]),
+        )?;
+        info!(
+            "Cluster {} is up, its entrypoint is {}, its RPC http://{}:{}",
+            self.config.name, entrypoint, bootstrap.instance.public_ip, RPC_PORT
+        );
+        Ok(())
+    }
+
+    /// Copies the genesis archive of the bootstrap validator to the ledgers of the others
+    fn distribute_genesis(
+        &self,
+        validators: &[&Node],
+        files: &HashMap<Role, ValidatorFiles>,
+    ) -> Result<()> {
+        let local_dir = std::env::temp_dir().join(format!("{}-{}", DEPLOY_DIR, self.config.name));
+        fs::create_dir_all(&local_dir)?;
+        let local_archive = local_dir.join("genesis.tar.bz2");
+        let bootstrap_ledger = &files[&Role::Bootstrap].ledger;
+        self.provider.copy_from(
+            &validators[0].instance,
+            &format!("{}/genesis.tar.bz2", bootstrap_ledger),
+            &local_archive,
+        )?;
+        for node in &validators[1..] {
+            let node_files = &files[&node.role];
+            let remote_archive = format!("{}/genesis.tar.bz2", node_files.dir);
+            info!("Copying the genesis to {}", node.role);
+            self.provider
+                .copy_to(&node.instance, &local_archive, &remote_archive)?;
+            self.run(
+                node,
+                &format!(
+                    "rm -rf {ledger} && mkdir -p {ledger} && tar -C {ledger} -xjf {archive}",
+                    ledger = shell_quote(&node_files.ledger),
+                    archive = shell_quote(&remote_archive)
+                ),
+            )?;
+        }
+        fs::remove_dir_all(&local_dir)?;
+        Ok(())
+    }
+
+    /// Runs the bench-tps scenarios, or just `scenario`, printing what each client reports
+    pub fn bench(&self, scenario: Option<&str>) -> Result<()> {
+        let nodes = self.expected_nodes()?;
+        let bootstrap = &nodes[0];
+        let clients: Vec<_> = nodes
+            .iter()
+            .filter(|node| matches!(node.role, Role::Client(_)))
+            .collect();
+        if clients.is_empty() {
+            return Err(DeployError::Config(
+                "no clients to run bench-tps".to_string(),
+            ));
+        }
+        let scenarios: Vec<_> = self
+            .config
+            .clients
+            .bench_tps
+            .iter()
+            .filter(|s| scenario.map_or(true, |name| s.name == name))
+            .collect();
+        if scenarios.is_empty() {
+            return Err(DeployError::Config(format!(
+                "no bench_tps scenario {}",
+                scenario.unwrap_or_default()
+            )));
+        }
+
+        let entrypoint = format!("{}:{}", bootstrap.instance.private_ip, GOSSIP_PORT);
+        let faucet = format!("{}:{}", bootstrap.instance.private_ip, FAUCET_PORT);
+        for scenario in scenarios {
+            info!("Running bench-tps scenario {}", scenario.name);
+            let log = format!("{}/bench-tps-{}.log", DEPLOY_DIR, scenario.name);
+            let command = bench_tps_command(scenario, &entrypoint, &faucet);
+            for client in &clients {
+                self.run(
+                    client,
+                    &format!(
+                        "mkdir -p {dir} && nohup {command} > {log} 2>&1 < /dev/null &",
+                        dir = DEPLOY_DIR,
+                        command = shell_join(&command),
+                        log = shell_quote(&log)
+                    ),
+                )?;
+            }
+            for client in &clients {
+                wait_for(&format!("bench-tps on {}", client.role), || {
+                    // The brackets keep the pattern from matching the shell running pgrep
+                    self.run(client, "pgrep -f '[s]olana-bench-tps'").is_err()
+                })?;
+                let output = self.run(client, &format!("cat {}", shell_quote(&log)))?;
+                // The summary is the last lines bench-tps logs
+                let summary: Vec<_> = output
+                    .lines()
+                    .filter(|line| line.contains("TPS") || line.contains("Average"))
+                    .collect();
+                println!("{} {}:", scenario.name, client.role);
+                for line in summary {
+                    println!("  {}", line);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Stops the validators, the faucet and bench-tps, leaving the instances up
+    pub fn stop(&self) -> Result<()> {
+        self.stop_nodes(&self.nodes()?)
+    }
+
+    fn stop_nodes(&self, nodes: &[Node]) -> Result<()> {
+        for node in nodes {
+            info!("Stopping {}", node.role);
+            self.run(
+                node,
+                "pkill -f '[s]olana-(validator|faucet|bench-tps)' ; \
+                 while pgrep -f '[s]olana-(validator|faucet|bench-tps)' > /dev/null; do sleep 1; done",
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Deletes every instance of the cluster
+    pub fn delete(&self) -> Result<()> {
+        let nodes = self.nodes()?;
+        if nodes.is_empty() {
+            info!("Cluster {} has no instances", self.config.name);
+            return Ok(());
+        }
+        let instances: Vec<_> = nodes.into_iter().map(|node| node.instance).collect();
+        info!("Deleting {} instances", instances.len());
+        self.provider.delete_instances(&instances)
+    }
+}
+
+fn wait_for<F>(what: &str, mut is_ready: F) -> Result<()>
+where
+    F: FnMut() -> bool,
+{
+    let start = Instant::now();
+    while !is_ready() {
+        if start.elapsed() > READY_TIMEOUT {
+            return Err(DeployError::Timeout(what.to_string()));
+        }
+        sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+fn validator_args(
+    node_config: &NodeConfig,
+    default_args: Vec<String>,
+    vars: &HashMap<&str, String>,
+) -> Result<Vec<String>> {
+    let mut args = template::render_all(node_config.args.as_ref().unwrap_or(&default_args), vars)?;
+    args.extend(template::render_all(&node_config.extra_args, vars)?);
+    Ok(args)
+}
+
+fn bench_tps_command(scenario: &BenchTpsScenario, entrypoint: &str, faucet: &str) -> Vec<String> {
+    let mut command: Vec<String> = vec![
+        "solana-bench-tps",
+        "--entrypoint",
+        entrypoint,
+        "--faucet",
+        faucet,
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+    command.extend(vec![
+        "--duration".to_string(),
+        scenario.duration_secs.to_string(),
+        "--tx_count".to_string(),
+        scenario.tx_count.to_string(),
+        "--threads".to_string(),
+        scenario.threads.to_string(),
+        "--thread-batch-sleep-ms".to_string(),
+        scenario.thread_batch_sleep_ms.to_string(),
+    ]);
+    command.extend(scenario.extra_args.iter().cloned());
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_instance_name() {
+        for role in &[Role::Bootstrap, Role::Validator(3), Role::Client(0)] {
+            let name = role.instance_name("testnet");
+            assert_eq!(Role::from_instance_name("testnet", &name), Some(*role));
+        }
+        assert_eq!(Role::Validator(3).instance_name("net"), "net-validator-3");
+        assert_eq!(Role::from_instance_name("net", "net2-bootstrap"), None);
+        assert_eq!(Role::from_instance_name("net", "net-validator-x"), None);
+    }
+
+    #[test]
+    fn test_validator_args() {
+        let files = ValidatorFiles::new("/home/solana/");
+        let mut vars: HashMap<&str, String> = HashMap::new();
+        vars.insert("identity", files.identity.clone());
+        vars.insert("vote_account", files.vote_account.clone());
+        vars.insert("ledger", files.ledger.clone());
+        vars.insert("log", files.log.clone());
+        vars.insert("entrypoint", "10.0.0.2:8001".to_string());
+        vars.insert(
+            "genesis_hash",
+            "4uhcVJyU9pJkvQyS88uRDiswHXSCkY3zQawwpjk2NsNY".to_string(),
+        );
+        vars.insert("shred_version", "1234".to_string());
+        vars.insert("private_ip", "10.0.0.3".to_string());
+
+        let node_config = NodeConfig {
+            args: None,
+            extra_args: vec!["--gossip-host={{private_ip}}".to_string()],
+        };
+        let args = validator_args(&node_config, default_validator_args(), &vars).unwrap();
+        assert_eq!(args[1], "/home/solana/solana-net-deploy/identity.json");
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["--entrypoint", "10.0.0.2:8001"]));
+        assert_eq!(args.last().unwrap(), "--gossip-host=10.0.0.3");
+
+        // The bootstrap validator's defaults use the faucet, which validators aren't given
+        assert!(validator_args(
+            &NodeConfig::default(),
+            default_bootstrap_validator_args(),
+            &vars
+        )
+        .is_err());
+
+        let node_config = NodeConfig {
+            args: Some(vec!["--ledger".to_string(), "{{ledger}}".to_string()]),
+            extra_args: vec![],
+        };
+        assert_eq!(
+            validator_args(&node_config, default_validator_args(), &vars).unwrap(),
+            vec!["--ledger", "/home/solana/solana-net-deploy/ledger"]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use {
+    crate::error::{DeployError, Result},
+    log::*,
+    std::{fmt, process::Command},
+};
+
+/// A command run on this machine, kept as plain strings so that what the providers run can be
+/// checked without running it
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandLine {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl CommandLine {
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            args: vec![],
+        }
+    }
+
+    pub fn arg<S: AsRef<str>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_string());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_string()));
+        self
+    }
+
+    /// Runs the command, returning its stdout, or an error with its stderr if it fails
+    pub fn output(&self) -> Result<String> {
+        debug!("Running {}", self);
+        let output = Command::new(&self.program).args(&self.args).output()?;
+        if !output.status.success() {
+            return Err(DeployError::Command {
+                command: self.to_string(),
+                status: output.status.to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    pub fn run(&self) -> Result<()> {
+        self.output().map(|_| ())
+    }
+}
+
+impl fmt::Display for CommandLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", shell_quote(arg))?;
+        }
+        Ok(())
+    }
+}
+
+/// Quotes `arg` for a POSIX shell, leaving it as is when it doesn't need quoting
+pub fn shell_quote(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-+=./:,@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r#"'\''"#))
+    }
+}
+
+/// Joins `args` into a command line for a remote shell
+pub fn shell_join<I, S>(args: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    args.into_iter()
+        .map(|arg| shell_quote(arg.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("--rpc-port"), "--rpc-port");
+        assert_eq!(shell_quote("/home/solana/ledger"), "/home/solana/ledger");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("a b"), "'a b'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+        assert_eq!(shell_quote("it's"), r#"'it'\''s'"#);
+        assert_eq!(
+            shell_join(&["solana-validator", "--log", "-", "--limit-ledger-size"]),
+            "solana-validator --log - --limit-ledger-size"
+        );
+    }
+}
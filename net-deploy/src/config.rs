@@ -0,0 +1,356 @@
+use {
+    crate::error::{DeployError, Result},
+    serde_derive::{Deserialize, Serialize},
+    std::{fs::File, path::Path, path::PathBuf},
+};
+
+/// Everything needed to bring up a cluster, read from a YAML file
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClusterConfig {
+    /// Prefix of the names of the instances of the cluster, which is how they are found again
+    pub name: String,
+    pub provider: ProviderConfig,
+    #[serde(default)]
+    pub software: Software,
+    #[serde(default)]
+    pub ssh: SshConfig,
+    #[serde(default)]
+    pub genesis: GenesisConfig,
+    #[serde(default)]
+    pub bootstrap_validator: NodeConfig,
+    #[serde(default)]
+    pub validators: ValidatorsConfig,
+    #[serde(default)]
+    pub clients: ClientsConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderConfig {
+    Gce(GceConfig),
+    Ec2(Ec2Config),
+    Docker(DockerConfig),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GceConfig {
+    pub project: Option<String>,
+    pub zone: String,
+    #[serde(default = "GceConfig::default_machine_type")]
+    pub machine_type: String,
+    #[serde(default = "GceConfig::default_image")]
+    pub image: String,
+    #[serde(default = "GceConfig::default_image_project")]
+    pub image_project: String,
+    #[serde(default = "default_disk_size_gb")]
+    pub disk_size_gb: u64,
+}
+
+impl GceConfig {
+    fn default_machine_type() -> String {
+        "n1-standard-16".to_string()
+    }
+
+    fn default_image() -> String {
+        "ubuntu-2004-focal-v20201201".to_string()
+    }
+
+    fn default_image_project() -> String {
+        "ubuntu-os-cloud".to_string()
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Ec2Config {
+    pub region: String,
+    #[serde(default = "Ec2Config::default_instance_type")]
+    pub instance_type: String,
+    pub ami: String,
+    /// Name of the EC2 key pair whose private key is `ssh.private_key`
+    pub key_name: String,
+    pub security_group: Option<String>,
+    #[serde(default = "default_disk_size_gb")]
+    pub disk_size_gb: u64,
+}
+
+impl Ec2Config {
+    fn default_instance_type() -> String {
+        "m5.4xlarge".to_string()
+    }
+}
+
+fn default_disk_size_gb() -> u64 {
+    500
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct DockerConfig {
+    /// Image with the Solana binaries on its PATH
+    #[serde(default = "DockerConfig::default_image")]
+    pub image: String,
+    /// Network joining the containers, created if missing and named after the cluster by default
+    pub network: Option<String>,
+}
+
+impl DockerConfig {
+    fn default_image() -> String {
+        "solanalabs/solana:stable".to_string()
+    }
+}
+
+/// Where the nodes get the Solana binaries from
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Software {
+    /// The binaries are already on the PATH of the nodes, like in the Docker images
+    Preinstalled,
+    /// Installed with solana-install from a release channel or tag, such as `stable` or `v1.6.0`
+    Release(String),
+    /// A solana-release tarball of this machine, copied to and unpacked on every node
+    Tarball(PathBuf),
+}
+
+impl Default for Software {
+    fn default() -> Self {
+        Self::Preinstalled
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SshConfig {
+    /// Login of the nodes, ssh's default when unset
+    pub user: Option<String>,
+    /// Key to log in with, ssh's default identities when unset
+    pub private_key: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisConfig {
+    #[serde(default = "GenesisConfig::default_cluster_type")]
+    pub cluster_type: String,
+    /// `auto`, `sleep` or a number of hashes
+    #[serde(default = "GenesisConfig::default_hashes_per_tick")]
+    pub hashes_per_tick: String,
+    /// Lamports of the identity of each validator, which all start out staked
+    #[serde(default = "GenesisConfig::default_validator_lamports")]
+    pub validator_lamports: u64,
+    #[serde(default = "GenesisConfig::default_validator_stake_lamports")]
+    pub validator_stake_lamports: u64,
+    #[serde(default = "GenesisConfig::default_faucet_lamports")]
+    pub faucet_lamports: u64,
+    /// Passed to solana-genesis as is
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+impl GenesisConfig {
+    fn default_cluster_type() -> String {
+        "development".to_string()
+    }
+
+    fn default_hashes_per_tick() -> String {
+        "auto".to_string()
+    }
+
+    fn default_validator_lamports() -> u64 {
+        500_000_000_000
+    }
+
+    fn default_validator_stake_lamports() -> u64 {
+        500_000_000_000
+    }
+
+    fn default_faucet_lamports() -> u64 {
+        500_000_000_000_000_000
+    }
+}
+
+impl Default for GenesisConfig {
+    fn default() -> Self {
+        Self {
+            cluster_type: Self::default_cluster_type(),
+            hashes_per_tick: Self::default_hashes_per_tick(),
+            validator_lamports: Self::default_validator_lamports(),
+            validator_stake_lamports: Self::default_validator_stake_lamports(),
+            faucet_lamports: Self::default_faucet_lamports(),
+            extra_args: vec![],
+        }
+    }
+}
+
+/// Arguments of solana-validator, each a template of the variables listed in the README
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NodeConfig {
+    /// Replaces the default arguments when set
+    pub args: Option<Vec<String>>,
+    /// Appended to the arguments
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ValidatorsConfig {
+    /// Number of validators besides the bootstrap validator
+    #[serde(default)]
+    pub count: usize,
+    pub args: Option<Vec<String>>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+impl ValidatorsConfig {
+    pub fn node(&self) -> NodeConfig {
+        NodeConfig {
+            args: self.args.clone(),
+            extra_args: self.extra_args.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ClientsConfig {
+    #[serde(default)]
+    pub count: usize,
+    /// Scenarios run one after the other by `bench`, every client running each of them at once
+    #[serde(default)]
+    pub bench_tps: Vec<BenchTpsScenario>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct BenchTpsScenario {
+    pub name: String,
+    #[serde(default = "BenchTpsScenario::default_duration_secs")]
+    pub duration_secs: u64,
+    #[serde(default = "BenchTpsScenario::default_tx_count")]
+    pub tx_count: u64,
+    #[serde(default = "BenchTpsScenario::default_threads")]
+    pub threads: usize,
+    #[serde(default)]
+    pub thread_batch_sleep_ms: u64,
+    /// Passed to solana-bench-tps as is
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+impl BenchTpsScenario {
+    fn default_duration_secs() -> u64 {
+        60
+    }
+
+    fn default_tx_count() -> u64 {
+        50_000
+    }
+
+    fn default_threads() -> usize {
+        4
+    }
+}
+
+impl ClusterConfig {
+    pub fn load(config_file: &Path) -> Result<Self> {
+        let file = File::open(config_file).map_err(|err| {
+            DeployError::Config(format!("Unable to open {:?}: {}", config_file, err))
+        })?;
+        let config: Self = serde_yaml::from_reader(file)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        // Instance names of every provider allow lowercase letters, digits and dashes
+        let valid_name = !self.name.is_empty()
+            && self.name.starts_with(|c: char| c.is_ascii_lowercase())
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !valid_name {
+            return Err(DeployError::Config(format!(
+                "name '{}' must start with a lowercase letter and only contain lowercase \
+                 letters, digits and dashes",
+                self.name
+            )));
+        }
+        if let Software::Tarball(tarball) = &self.software {
+            if !tarball.is_file() {
+                return Err(DeployError::Config(format!(
+                    "release tarball {:?} does not exist",
+                    tarball
+                )));
+            }
+        }
+        let mut scenario_names: Vec<_> = self.clients.bench_tps.iter().map(|s| &s.name).collect();
+        scenario_names.sort();
+        scenario_names.dedup();
+        if scenario_names.len() != self.clients.bench_tps.len() {
+            return Err(DeployError::Config(
+                "bench_tps scenario names must be unique".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cluster_config() {
+        let config: ClusterConfig = serde_yaml::from_str(
+            r#"
+name: testnet-dev
+provider:
+  gce:
+    zone: us-west1-b
+validators:
+  count: 2
+  extra_args: ["--no-voting"]
+clients:
+  count: 1
+  bench_tps:
+    - name: transfers
+      tx_count: 10000
+"#,
+        )
+        .unwrap();
+        config.validate().unwrap();
+        assert_eq!(
+            config.provider,
+            ProviderConfig::Gce(GceConfig {
+                project: None,
+                zone: "us-west1-b".to_string(),
+                machine_type: "n1-standard-16".to_string(),
+                image: "ubuntu-2004-focal-v20201201".to_string(),
+                image_project: "ubuntu-os-cloud".to_string(),
+                disk_size_gb: 500,
+            })
+        );
+        assert_eq!(config.software, Software::Preinstalled);
+        assert_eq!(config.validators.count, 2);
+        assert_eq!(config.validators.args, None);
+        assert_eq!(config.validators.extra_args, vec!["--no-voting"]);
+        assert_eq!(config.clients.bench_tps[0].tx_count, 10_000);
+        assert_eq!(config.clients.bench_tps[0].duration_secs, 60);
+
+        // Typos are caught rather than silently ignored
+        assert!(serde_yaml::from_str::<ClusterConfig>(
+            "name: x\nprovider:\n  docker: {}\nvalidator:\n  count: 2\n"
+        )
+        .is_err());
+
+        let mut config = config;
+        config.name = "Testnet_dev".to_string();
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,36 @@
+use {std::io, thiserror::Error};
+
+#[derive(Debug, Error)]
+pub enum DeployError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid config: {0}")]
+    Config(String),
+
+    #[error("Failed to parse config: {0}")]
+    ConfigParse(#[from] serde_yaml::Error),
+
+    /// A local command, or a command run on a node, exited with a failure
+    #[error("`{command}` failed with {status}: {stderr}")]
+    Command {
+        command: String,
+        status: String,
+        stderr: String,
+    },
+
+    #[error("Invalid template '{template}': {reason}")]
+    Template { template: String, reason: String },
+
+    #[error("Unexpected output of `{command}`: {output}")]
+    UnexpectedOutput { command: String, output: String },
+
+    #[error("Timed out waiting for {0}")]
+    Timeout(String),
+
+    /// The instances of the cluster don't match its config, it likely needs to be created first
+    #[error("{0}")]
+    Cluster(String),
+}
+
+pub type Result<T> = std::result::Result<T, DeployError>;
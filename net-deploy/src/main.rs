@@ -0,0 +1,105 @@
+//! Brings up test clusters on GCE, EC2 or local docker containers from a YAML config, in place
+//! of the net/ scripts
+mod cluster;
+mod command;
+mod config;
+mod error;
+mod provider;
+mod template;
+
+use {
+    clap::{crate_description, crate_name, App, AppSettings, Arg, ArgMatches, SubCommand},
+    cluster::Cluster,
+    config::ClusterConfig,
+    log::*,
+    std::{path::Path, process::exit},
+};
+
+fn main() {
+    solana_logger::setup_with_default("solana=info,solana_net_deploy=info");
+
+    let config_arg = Arg::with_name("config_file")
+        .short("c")
+        .long("config")
+        .value_name("FILE")
+        .takes_value(true)
+        .required(true)
+        .help("Cluster config, see the README for its format");
+    let matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(solana_version::version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("create")
+                .about("Provision the missing instances of the cluster and install the software")
+                .arg(&config_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("start")
+                .about("Create a new genesis and (re)start every validator and the faucet")
+                .arg(&config_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Run the bench-tps scenarios from the clients")
+                .arg(&config_arg)
+                .arg(
+                    Arg::with_name("scenario")
+                        .long("scenario")
+                        .value_name("NAME")
+                        .takes_value(true)
+                        .help("Only run this scenario [default: all of them, in order]"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stop")
+                .about("Stop the validators, the faucet and the clients, keeping the instances")
+                .arg(&config_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("delete")
+                .about("Delete every instance of the cluster")
+                .arg(&config_arg),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("List the instances of the cluster")
+                .arg(&config_arg),
+        )
+        .get_matches();
+
+    let (subcommand, subcommand_matches) = matches.subcommand();
+    let subcommand_matches = subcommand_matches.unwrap();
+    let cluster = Cluster::new(load_config(subcommand_matches));
+    let result = match subcommand {
+        "create" => cluster.create(),
+        "start" => cluster.start(),
+        "bench" => cluster.bench(subcommand_matches.value_of("scenario")),
+        "stop" => cluster.stop(),
+        "delete" => cluster.delete(),
+        "info" => cluster.nodes().map(|nodes| {
+            for node in nodes {
+                println!(
+                    "{:<16} {:<32} {:<16} {}",
+                    node.role.to_string(),
+                    node.instance.name,
+                    node.instance.public_ip,
+                    node.instance.private_ip
+                );
+            }
+        }),
+        _ => unreachable!(),
+    };
+    if let Err(err) = result {
+        error!("{} failed: {}", subcommand, err);
+        exit(1);
+    }
+}
+
+fn load_config(matches: &ArgMatches) -> ClusterConfig {
+    let config_file = Path::new(matches.value_of("config_file").unwrap());
+    ClusterConfig::load(config_file).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    })
+}
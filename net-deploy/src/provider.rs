@@ -0,0 +1,483 @@
+//! Provisioning of instances and running commands on them, through the gcloud, aws and docker
+//! command line tools, which must be installed and logged in
+use {
+    crate::{
+        command::CommandLine,
+        config::{DockerConfig, Ec2Config, GceConfig, ProviderConfig, SshConfig},
+        error::{DeployError, Result},
+    },
+    std::path::Path,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    /// What the provider's tool takes to delete the instance, which is the name unless on EC2
+    pub id: String,
+    pub public_ip: String,
+    pub private_ip: String,
+}
+
+pub trait Provider {
+    fn create_instances(&self, names: &[String]) -> Result<()>;
+    /// Instances whose name starts with `prefix`, including those still booting
+    fn list_instances(&self, prefix: &str) -> Result<Vec<Instance>>;
+    fn delete_instances(&self, instances: &[Instance]) -> Result<()>;
+    /// Runs `command` with the shell of `instance`, returning its stdout
+    fn exec(&self, instance: &Instance, command: &str) -> Result<String>;
+    fn copy_to(&self, instance: &Instance, local: &Path, remote: &str) -> Result<()>;
+    fn copy_from(&self, instance: &Instance, remote: &str, local: &Path) -> Result<()>;
+}
+
+pub fn new_provider(config: &ProviderConfig, ssh: &SshConfig, cluster: &str) -> Box<dyn Provider> {
+    match config {
+        ProviderConfig::Gce(config) => Box::new(Gce {
+            config: config.clone(),
+            ssh: Ssh(ssh.clone()),
+            cluster: cluster.to_string(),
+        }),
+        ProviderConfig::Ec2(config) => Box::new(Ec2 {
+            config: config.clone(),
+            ssh: Ssh(ssh.clone()),
+        }),
+        ProviderConfig::Docker(config) => Box::new(Docker {
+            config: config.clone(),
+            cluster: cluster.to_string(),
+        }),
+    }
+}
+
+/// Parses lines of whitespace separated `name id public_ip private_ip` columns
+fn parse_instances(command: &CommandLine, output: &str) -> Result<Vec<Instance>> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let columns: Vec<_> = line.split_whitespace().collect();
+            match columns.as_slice() {
+                [name, id, public_ip, private_ip] => Ok(Instance {
+                    name: name.to_string(),
+                    id: id.to_string(),
+                    public_ip: public_ip.to_string(),
+                    private_ip: private_ip.to_string(),
+                }),
+                _ => Err(DeployError::UnexpectedOutput {
+                    command: command.to_string(),
+                    output: line.to_string(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Runs commands on the instances of the cloud providers over ssh
+struct Ssh(SshConfig);
+
+impl Ssh {
+    fn options(&self) -> Vec<String> {
+        let mut options: Vec<String> = [
+            "-o",
+            "StrictHostKeyChecking=no",
+            "-o",
+            "UserKnownHostsFile=/dev/null",
+            "-o",
+            "LogLevel=ERROR",
+            "-o",
+            "ConnectTimeout=10",
+        ]
+        .iter()
+        .map(|option| option.to_string())
+        .collect();
+        if let Some(private_key) = &self.0.private_key {
+            options.push("-i".to_string());
+            options.push(private_key.display().to_string());
+        }
+        options
+    }
+
+    fn host(&self, instance: &Instance) -> String {
+        match &self.0.user {
+            Some(user) => format!("{}@{}", user, instance.public_ip),
+            None => instance.public_ip.clone(),
+        }
+    }
+
+    fn exec_command(&self, instance: &Instance, command: &str) -> CommandLine {
+        CommandLine::new("ssh")
+            .args(self.options())
+            .arg(self.host(instance))
+            .arg(command)
+    }
+
+    fn exec(&self, instance: &Instance, command: &str) -> Result<String> {
+        self.exec_command(instance, command).output()
+    }
+
+    fn copy_to(&self, instance: &Instance, local: &Path, remote: &str) -> Result<()> {
+        CommandLine::new("scp")
+            .args(self.options())
+            .arg(local.display().to_string())
+            .arg(format!("{}:{}", self.host(instance), remote))
+            .run()
+    }
+
+    fn copy_from(&self, instance: &Instance, remote: &str, local: &Path) -> Result<()> {
+        CommandLine::new("scp")
+            .args(self.options())
+            .arg(format!("{}:{}", self.host(instance), remote))
+            .arg(local.display().to_string())
+            .run()
+    }
+}
+
+struct Gce {
+    config: GceConfig,
+    ssh: Ssh,
+    cluster: String,
+}
+
+impl Gce {
+    fn gcloud(&self) -> CommandLine {
+        let gcloud = CommandLine::new("gcloud");
+        match &self.config.project {
+            Some(project) => gcloud.arg(format!("--project={}", project)),
+            None => gcloud,
+        }
+    }
+
+    fn create_command(&self, names: &[String]) -> CommandLine {
+        self.gcloud()
+            .args(&["compute", "instances", "create"])
+            .args(names)
+            .arg(format!("--zone={}", self.config.zone))
+            .arg(format!("--machine-type={}", self.config.machine_type))
+            .arg(format!("--image={}", self.config.image))
+            .arg(format!("--image-project={}", self.config.image_project))
+            .arg(format!("--boot-disk-size={}GB", self.config.disk_size_gb))
+            .arg("--boot-disk-type=pd-ssd")
+            .arg("--tags=testnet")
+            .arg(format!("--metadata=testnet={}", self.cluster))
+    }
+
+    fn list_command(&self, prefix: &str) -> CommandLine {
+        self.gcloud()
+            .args(&["compute", "instances", "list"])
+            .arg(format!(
+                "--filter=name~^{} AND zone:{} AND status=RUNNING",
+                prefix, self.config.zone
+            ))
+            .arg(
+                "--format=value(name,name,networkInterfaces[0].accessConfigs[0].natIP,\
+                 networkInterfaces[0].networkIP)",
+            )
+    }
+}
+
+impl Provider for Gce {
+    fn create_instances(&self, names: &[String]) -> Result<()> {
+        self.create_command(names).run()
+    }
+
+    fn list_instances(&self, prefix: &str) -> Result<Vec<Instance>> {
+        let command = self.list_command(prefix);
+        parse_instances(&command, &command.output()?)
+    }
+
+    fn delete_instances(&self, instances: &[Instance]) -> Result<()> {
+        self.gcloud()
+            .args(&["compute", "instances", "delete", "--quiet"])
+            .arg(format!("--zone={}", self.config.zone))
+            .args(instances.iter().map(|instance| &instance.id))
+            .run()
+    }
+
+    fn exec(&self, instance: &Instance, command: &str) -> Result<String> {
+        self.ssh.exec(instance, command)
+    }
+
+    fn copy_to(&self, instance: &Instance, local: &Path, remote: &str) -> Result<()> {
+        self.ssh.copy_to(instance, local, remote)
+    }
+
+    fn copy_from(&self, instance: &Instance, remote: &str, local: &Path) -> Result<()> {
+        self.ssh.copy_from(instance, remote, local)
+    }
+}
+
+struct Ec2 {
+    config: Ec2Config,
+    ssh: Ssh,
+}
+
+impl Ec2 {
+    fn aws(&self) -> CommandLine {
+        CommandLine::new("aws")
+            .arg("ec2")
+            .arg(format!("--region={}", self.config.region))
+    }
+
+    fn create_command(&self, name: &str) -> CommandLine {
+        let command = self
+            .aws()
+            .arg("run-instances")
+            .arg(format!("--image-id={}", self.config.ami))
+            .arg(format!("--instance-type={}", self.config.instance_type))
+            .arg(format!("--key-name={}", self.config.key_name))
+            .arg("--count=1")
+            .arg(format!(
+                "--block-device-mappings=DeviceName=/dev/sda1,Ebs={{VolumeSize={},VolumeType=gp2}}",
+                self.config.disk_size_gb
+            ))
+            .arg(format!(
+                "--tag-specifications=ResourceType=instance,Tags=[{{Key=Name,Value={}}}]",
+                name
+            ));
+        match &self.config.security_group {
+            Some(security_group) => command.arg(format!("--security-groups={}", security_group)),
+            None => command,
+        }
+    }
+
+    fn list_command(&self, prefix: &str) -> CommandLine {
+        self.aws()
+            .arg("describe-instances")
+            .arg("--filters")
+            .arg(format!("Name=tag:Name,Values={}*", prefix))
+            .arg("Name=instance-state-name,Values=pending,running")
+            .arg(
+                "--query=Reservations[].Instances[].[Tags[?Key=='Name']|[0].Value,InstanceId,\
+                 PublicIpAddress,PrivateIpAddress]",
+            )
+            .arg("--output=text")
+    }
+}
+
+impl Provider for Ec2 {
+    fn create_instances(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            self.create_command(name).run()?;
+        }
+        Ok(())
+    }
+
+    fn list_instances(&self, prefix: &str) -> Result<Vec<Instance>> {
+        let command = self.list_command(prefix);
+        // Instances without an address yet list it as "None"
+        let instances = parse_instances(&command, &command.output()?)?;
+        Ok(instances
+            .into_iter()
+            .filter(|instance| instance.public_ip != "None")
+            .collect())
+    }
+
+    fn delete_instances(&self, instances: &[Instance]) -> Result<()> {
+        self.aws()
+            .arg("terminate-instances")
+            .arg("--instance-ids")
+            .args(instances.iter().map(|instance| &instance.id))
+            .run()
+    }
+
+    fn exec(&self, instance: &Instance, command: &str) -> Result<String> {
+        self.ssh.exec(instance, command)
+    }
+
+    fn copy_to(&self, instance: &Instance, local: &Path, remote: &str) -> Result<()> {
+        self.ssh.copy_to(instance, local, remote)
+    }
+
+    fn copy_from(&self, instance: &Instance, remote: &str, local: &Path) -> Result<()> {
+        self.ssh.copy_from(instance, remote, local)
+    }
+}
+
+/// Containers on this machine sharing a network, for clusters small enough to run locally
+struct Docker {
+    config: DockerConfig,
+    cluster: String,
+}
+
+impl Docker {
+    fn network(&self) -> String {
+        self.config
+            .network
+            .clone()
+            .unwrap_or_else(|| self.cluster.clone())
+    }
+
+    fn create_command(&self, name: &str) -> CommandLine {
+        // The containers idle until commands are run in them
+        CommandLine::new("docker")
+            .args(&["run", "--detach", "--init"])
+            .arg(format!("--name={}", name))
+            .arg(format!("--hostname={}", name))
+            .arg(format!("--network={}", self.network()))
+            .arg(format!("--label=testnet={}", self.cluster))
+            .arg("--entrypoint=sleep")
+            .arg(&self.config.image)
+            .arg("infinity")
+    }
+}
+
+impl Provider for Docker {
+    fn create_instances(&self, names: &[String]) -> Result<()> {
+        let network = self.network();
+        let networks = CommandLine::new("docker")
+            .args(&["network", "ls", "--format={{.Name}}"])
+            .output()?;
+        if !networks.lines().any(|line| line == network) {
+            CommandLine::new("docker")
+                .args(&["network", "create", &network])
+                .run()?;
+        }
+        for name in names {
+            self.create_command(name).run()?;
+        }
+        Ok(())
+    }
+
+    fn list_instances(&self, prefix: &str) -> Result<Vec<Instance>> {
+        let names = CommandLine::new("docker")
+            .args(&["ps", "--format={{.Names}}"])
+            // Container names are listed with a leading slash by older versions of docker
+            .arg(format!("--filter=name=^/?{}", prefix))
+            .output()?;
+        let names: Vec<_> = names.lines().filter(|name| !name.is_empty()).collect();
+        if names.is_empty() {
+            return Ok(vec![]);
+        }
+        let command = CommandLine::new("docker")
+            .arg("inspect")
+            .arg(
+                "--format={{.Name}} {{.Id}} \
+                 {{range .NetworkSettings.Networks}}{{.IPAddress}} {{.IPAddress}}{{end}}",
+            )
+            .args(names);
+        let mut instances = parse_instances(&command, &command.output()?)?;
+        for instance in &mut instances {
+            instance.name = instance.name.trim_start_matches('/').to_string();
+        }
+        Ok(instances)
+    }
+
+    fn delete_instances(&self, instances: &[Instance]) -> Result<()> {
+        CommandLine::new("docker")
+            .args(&["rm", "--force"])
+            .args(instances.iter().map(|instance| &instance.id))
+            .run()?;
+        if self.config.network.is_none() {
+            CommandLine::new("docker")
+                .args(&["network", "rm", &self.network()])
+                .run()?;
+        }
+        Ok(())
+    }
+
+    fn exec(&self, instance: &Instance, command: &str) -> Result<String> {
+        CommandLine::new("docker")
+            .arg("exec")
+            .arg(&instance.id)
+            .args(&["sh", "-c", command])
+            .output()
+    }
+
+    fn copy_to(&self, instance: &Instance, local: &Path, remote: &str) -> Result<()> {
+        CommandLine::new("docker")
+            .arg("cp")
+            .arg(local.display().to_string())
+            .arg(format!("{}:{}", instance.id, remote))
+            .run()
+    }
+
+    fn copy_from(&self, instance: &Instance, remote: &str, local: &Path) -> Result<()> {
+        CommandLine::new("docker")
+            .arg("cp")
+            .arg(format!("{}:{}", instance.id, remote))
+            .arg(local.display().to_string())
+            .run()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_instances() {
+        let command = CommandLine::new("gcloud");
+        let output = "net-bootstrap net-bootstrap 34.83.1.2 10.138.0.2\n\
+                      net-validator-0  net-validator-0\t34.83.1.3  10.138.0.3\n\n";
+        assert_eq!(
+            parse_instances(&command, output).unwrap(),
+            vec![
+                Instance {
+                    name: "net-bootstrap".to_string(),
+                    id: "net-bootstrap".to_string(),
+                    public_ip: "34.83.1.2".to_string(),
+                    private_ip: "10.138.0.2".to_string(),
+                },
+                Instance {
+                    name: "net-validator-0".to_string(),
+                    id: "net-validator-0".to_string(),
+                    public_ip: "34.83.1.3".to_string(),
+                    private_ip: "10.138.0.3".to_string(),
+                },
+            ]
+        );
+        assert!(parse_instances(&command, "net-bootstrap 34.83.1.2").is_err());
+    }
+
+    #[test]
+    fn test_gce_commands() {
+        let gce = Gce {
+            config: GceConfig {
+                project: Some("my-project".to_string()),
+                zone: "us-west1-b".to_string(),
+                machine_type: "n1-standard-16".to_string(),
+                image: "ubuntu-2004-focal-v20201201".to_string(),
+                image_project: "ubuntu-os-cloud".to_string(),
+                disk_size_gb: 500,
+            },
+            ssh: Ssh(SshConfig::default()),
+            cluster: "net".to_string(),
+        };
+        assert_eq!(
+            gce.create_command(&["net-bootstrap".to_string()])
+                .to_string(),
+            "gcloud --project=my-project compute instances create net-bootstrap \
+             --zone=us-west1-b --machine-type=n1-standard-16 \
+             --image=ubuntu-2004-focal-v20201201 --image-project=ubuntu-os-cloud \
+             --boot-disk-size=500GB --boot-disk-type=pd-ssd --tags=testnet \
+             --metadata=testnet=net"
+        );
+        assert_eq!(
+            gce.list_command("net-").args[4],
+            "--filter=name~^net- AND zone:us-west1-b AND status=RUNNING"
+        );
+    }
+
+    #[test]
+    fn test_ssh_command() {
+        let ssh = Ssh(SshConfig {
+            user: Some("solana".to_string()),
+            private_key: Some("/home/me/.ssh/id_testnet".into()),
+        });
+        let instance = Instance {
+            name: "net-bootstrap".to_string(),
+            id: "net-bootstrap".to_string(),
+            public_ip: "34.83.1.2".to_string(),
+            private_ip: "10.138.0.2".to_string(),
+        };
+        let command = ssh.exec_command(&instance, "echo $HOME");
+        assert_eq!(command.program, "ssh");
+        assert_eq!(
+            &command.args[command.args.len() - 4..],
+            &[
+                "-i",
+                "/home/me/.ssh/id_testnet",
+                "solana@34.83.1.2",
+                "echo $HOME"
+            ]
+        );
+    }
+}
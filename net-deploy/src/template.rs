@@ -0,0 +1,67 @@
+use {
+    crate::error::{DeployError, Result},
+    std::collections::HashMap,
+};
+
+/// Replaces every `{{name}}` of `template` by the value of the variable `name`.  Unknown
+/// variables are an error rather than left in place, so that typos in a config are caught before
+/// any node is started with them
+pub fn render(template: &str, vars: &HashMap<&str, String>) -> Result<String> {
+    let error = |reason: String| DeployError::Template {
+        template: template.to_string(),
+        reason,
+    };
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        let end = after_open
+            .find("}}")
+            .ok_or_else(|| error("unterminated {{".to_string()))?;
+        let name = after_open[..end].trim();
+        let value = vars
+            .get(name)
+            .ok_or_else(|| error(format!("unknown variable '{}'", name)))?;
+        rendered.push_str(value);
+        rest = &after_open[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+pub fn render_all(templates: &[String], vars: &HashMap<&str, String>) -> Result<Vec<String>> {
+    templates
+        .iter()
+        .map(|template| render(template, vars))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let vars: HashMap<_, _> = vec![
+            ("entrypoint", "10.0.0.1:8001".to_string()),
+            ("index", "2".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(render("--no-voting", &vars).unwrap(), "--no-voting");
+        assert_eq!(render("{{entrypoint}}", &vars).unwrap(), "10.0.0.1:8001");
+        assert_eq!(
+            render("validator-{{ index }}.log", &vars).unwrap(),
+            "validator-2.log"
+        );
+        assert_eq!(render("{{index}}{{index}}", &vars).unwrap(), "22");
+
+        assert!(matches!(
+            render("{{identiy}}", &vars),
+            Err(DeployError::Template { reason, .. }) if reason == "unknown variable 'identiy'"
+        ));
+        assert!(render("{{index", &vars).is_err());
+    }
+}
@@ -1,5 +1,9 @@
-use clap::{App, Arg};
-use std::net::{SocketAddr, TcpListener};
+use clap::{values_t, App, Arg};
+use solana_net_utils::{IpCidr, IpEchoServerConfig, IpFilter};
+use std::{
+    net::{SocketAddr, TcpListener},
+    sync::Arc,
+};
 
 fn main() {
     solana_logger::setup();
@@ -11,6 +15,39 @@ fn main() {
                 .required(true)
                 .help("TCP port to bind to"),
         )
+        .arg(
+            Arg::with_name("allow")
+                .long("allow")
+                .value_name("CIDR")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|value| value.parse::<IpCidr>().map(|_| ()))
+                .help("Only serve peers within this network, may be specified multiple times"),
+        )
+        .arg(
+            Arg::with_name("deny")
+                .long("deny")
+                .value_name("CIDR")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(|value| value.parse::<IpCidr>().map(|_| ()))
+                .help("Never serve peers within this network, may be specified multiple times"),
+        )
+        .arg(
+            Arg::with_name("ban_after_bad_headers")
+                .long("ban-after-bad-headers")
+                .value_name("COUNT")
+                .takes_value(true)
+                .validator(|value| {
+                    value
+                        .parse::<usize>()
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                })
+                .help("Ban peers sending this many requests with bad headers for a while"),
+        )
         .get_matches();
 
     let port = matches.value_of("port").unwrap();
@@ -19,7 +56,23 @@ fn main() {
         .unwrap_or_else(|_| panic!("Unable to parse {}", port));
     let bind_addr = SocketAddr::from(([0, 0, 0, 0], port));
     let tcp_listener = TcpListener::bind(bind_addr).expect("unable to start tcp listener");
-    let _runtime = solana_net_utils::ip_echo_server(tcp_listener);
+    let config = IpEchoServerConfig {
+        ip_filter: Arc::new(IpFilter::new(
+            values_t!(matches, "allow", IpCidr).unwrap_or_default(),
+            values_t!(matches, "deny", IpCidr).unwrap_or_default(),
+        )),
+        max_bad_headers_per_ip: matches
+            .value_of("ban_after_bad_headers")
+            .map(|count| count.parse().unwrap()),
+        ..IpEchoServerConfig::default()
+    };
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to create Runtime");
+    let _server = solana_net_utils::ip_echo_server_with_config(
+        runtime.handle(),
+        tcp_listener,
+        config,
+        Arc::default(),
+    );
     loop {
         std::thread::park();
     }
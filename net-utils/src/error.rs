@@ -0,0 +1,245 @@
+use {
+    crate::ip_echo_server::IpEchoServerRejection,
+    solana_sdk::pubkey::Pubkey,
+    std::{io, net::SocketAddr, time::Duration},
+    thiserror::Error,
+};
+
+/// Why a request to an ip_echo_server or STUN server, or a check of the reachability of local
+/// ports, failed
+#[derive(Debug, Error)]
+pub enum NetUtilsError {
+    /// Connecting to, sending to or receiving from a server failed
+    #[error("{0}")]
+    Io(#[source] io::Error),
+
+    /// A server took too long to accept the connection or to respond
+    #[error("Timed out: {0}")]
+    Timeout(#[source] io::Error),
+
+    /// A local socket could not be bound
+    #[error("Failed to bind {addr}: {source}")]
+    Bind { addr: SocketAddr, source: io::Error },
+
+    /// The server answered with an HTTP response, `addr` is likely a JSON RPC port
+    #[error("Invalid gossip entrypoint. {addr} looks to be an HTTP port: {response}")]
+    HttpPort { addr: SocketAddr, response: String },
+
+    /// The response doesn't start with the header of a supported protocol version
+    #[error("Invalid gossip entrypoint. {addr} provided an invalid response header: '{header}'")]
+    InvalidHeader { addr: SocketAddr, header: String },
+
+    /// The server turned the connection away
+    #[error("{addr} turned the connection away: {rejection}")]
+    Rejected {
+        addr: SocketAddr,
+        rejection: IpEchoServerRejection,
+    },
+
+    /// The response ended before its header, after 0 bytes if the server closed the connection
+    /// without responding
+    #[error("Response too short, received {0} bytes")]
+    ResponseTooShort(usize),
+
+    #[error("Failed to deserialize: {0}")]
+    Deserialize(#[from] bincode::Error),
+
+    /// The server's protocol version predates what was asked of it
+    #[error("{addr} is too old to {capability}, version {version}")]
+    UnsupportedVersion {
+        addr: SocketAddr,
+        version: u8,
+        capability: &'static str,
+    },
+
+    /// The server's protocol version is recent enough, but it isn't set up for what was asked
+    /// of it
+    #[error("{addr} does not {capability}")]
+    Unsupported {
+        addr: SocketAddr,
+        capability: &'static str,
+    },
+
+    /// The response is not signed by any of the expected identities
+    #[error(
+        "The response of {addr} is not validly signed by any of {expected:?}, but claims to be \
+         from {claimed}"
+    )]
+    InvalidSignature {
+        addr: SocketAddr,
+        expected: Vec<Pubkey>,
+        claimed: Pubkey,
+    },
+
+    /// The server's probes of these local ports didn't get through
+    #[error("{protocol} ports {ports:?} are not reachable from {addr}")]
+    Unreachable {
+        addr: SocketAddr,
+        protocol: &'static str,
+        ports: Vec<u16>,
+    },
+
+    /// Every attempt of a request failed, each failure listed with the time since the first
+    /// attempt
+    #[error(
+        "{} attempts to reach {addr} failed: {}",
+        .failures.len(),
+        format_failures(.failures)
+    )]
+    RetriesExhausted {
+        addr: SocketAddr,
+        failures: Vec<(Duration, NetUtilsError)>,
+    },
+
+    /// None of the servers asked answered, or too few of them agreed on the answer
+    #[error("{0}")]
+    NoConsensus(String),
+
+    #[error("{0}")]
+    InvalidArgument(String),
+}
+
+fn format_failures(failures: &[(Duration, NetUtilsError)]) -> String {
+    let history: Vec<_> = failures
+        .iter()
+        .enumerate()
+        .map(|(i, (elapsed, err))| format!("#{} after {}ms: {}", i + 1, elapsed.as_millis(), err))
+        .collect();
+    history.join("; ")
+}
+
+impl NetUtilsError {
+    /// The closest `io::ErrorKind`, which the conversion to `io::Error` keeps
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            Self::Io(err) | Self::Timeout(err) | Self::Bind { source: err, .. } => err.kind(),
+            Self::ResponseTooShort(0) => io::ErrorKind::UnexpectedEof,
+            Self::HttpPort { .. }
+            | Self::InvalidHeader { .. }
+            | Self::ResponseTooShort(_)
+            | Self::Deserialize(_)
+            | Self::InvalidSignature { .. } => io::ErrorKind::InvalidData,
+            Self::RetriesExhausted { failures, .. } => failures
+                .last()
+                .map_or(io::ErrorKind::Other, |(_, err)| err.kind()),
+            Self::InvalidArgument(_) => io::ErrorKind::InvalidInput,
+            Self::Rejected { rejection, .. } => match rejection {
+                IpEchoServerRejection::Denied | IpEchoServerRejection::Banned => {
+                    io::ErrorKind::PermissionDenied
+                }
+                IpEchoServerRejection::RateLimited | IpEchoServerRejection::Busy => {
+                    io::ErrorKind::Other
+                }
+            },
+            Self::UnsupportedVersion { .. }
+            | Self::Unsupported { .. }
+            | Self::Unreachable { .. }
+            | Self::NoConsensus(_) => io::ErrorKind::Other,
+        }
+    }
+
+    /// Whether another attempt of the request may well succeed, such as after a refused
+    /// connection or a timeout.  Errors from the server itself are not transient, but for it
+    /// being busy
+    pub fn is_transient(&self) -> bool {
+        if let Self::Rejected { rejection, .. } = self {
+            // A rate limit wouldn't lift by the next attempt, retrying only counts against it
+            return *rejection == IpEchoServerRejection::Busy;
+        }
+        if let Self::RetriesExhausted { failures, .. } = self {
+            return failures.last().map_or(false, |(_, err)| err.is_transient());
+        }
+        matches!(
+            self.kind(),
+            io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::NotConnected
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::TimedOut
+                | io::ErrorKind::WouldBlock
+                | io::ErrorKind::Interrupted
+                | io::ErrorKind::UnexpectedEof
+        )
+    }
+}
+
+impl From<io::Error> for NetUtilsError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            // Reads past their timeout fail with WouldBlock on unix
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Self::Timeout(err),
+            _ => Self::Io(err),
+        }
+    }
+}
+
+impl From<tokio::time::error::Elapsed> for NetUtilsError {
+    fn from(err: tokio::time::error::Elapsed) -> Self {
+        Self::Timeout(err.into())
+    }
+}
+
+impl From<NetUtilsError> for io::Error {
+    fn from(err: NetUtilsError) -> Self {
+        match err {
+            NetUtilsError::Io(err) | NetUtilsError::Timeout(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_net_utils_error_kind() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 8001));
+        let timeout = NetUtilsError::from(io::Error::from(io::ErrorKind::WouldBlock));
+        assert!(matches!(timeout, NetUtilsError::Timeout(_)));
+        assert!(timeout.is_transient());
+        let closed = NetUtilsError::ResponseTooShort(0);
+        assert_eq!(closed.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(closed.is_transient());
+        let bad_header = NetUtilsError::InvalidHeader {
+            addr,
+            header: "\u{1}\u{2}\u{3}\u{4}".to_string(),
+        };
+        assert!(!bad_header.is_transient());
+        let busy = NetUtilsError::Rejected {
+            addr,
+            rejection: IpEchoServerRejection::Busy,
+        };
+        assert!(busy.is_transient());
+        let rate_limited = NetUtilsError::Rejected {
+            addr,
+            rejection: IpEchoServerRejection::RateLimited,
+        };
+        assert!(!rate_limited.is_transient());
+        assert_eq!(
+            rate_limited.to_string(),
+            "127.0.0.1:8001 turned the connection away: too many connections from this address"
+        );
+
+        // Exhausted retries are as transient as the last failure
+        let err = NetUtilsError::RetriesExhausted {
+            addr,
+            failures: vec![
+                (Duration::from_millis(0), closed),
+                (Duration::from_millis(600), timeout),
+            ],
+        };
+        assert_eq!(
+            err.to_string(),
+            "2 attempts to reach 127.0.0.1:8001 failed: #1 after 0ms: Response too short, \
+             received 0 bytes; #2 after 600ms: Timed out: operation would block"
+        );
+        assert!(err.is_transient());
+
+        // Converting to io::Error keeps the kind and the message
+        let io_err = io::Error::from(bad_header);
+        assert_eq!(io_err.kind(), io::ErrorKind::InvalidData);
+        assert!(io_err.to_string().contains("invalid response header"));
+    }
+}
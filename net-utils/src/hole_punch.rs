@@ -0,0 +1,181 @@
+//! UDP hole punching, so that validators behind cone NATs receive turbine and repair traffic
+//! without forwarding ports by hand.  A NAT only admits datagrams to a local socket once the
+//! socket sent something out, and a cone NAT keeps the public address of a socket the same
+//! whoever it sends to.  `punch_udp_holes` has an ip echo server send through the holes the
+//! sockets punch, learning the public address each socket is reached at, which is the address
+//! to advertise in gossip.  Restricted cone NATs further only admit the peers a socket sent to,
+//! `open_udp_holes` sends to each of them, repeated every UDP_HOLE_KEEPALIVE_INTERVAL to keep
+//! the holes from expiring.  Symmetric NATs give each destination another public address, which
+//! no hole punching gets around, see `detect_nat_type`
+use {
+    crate::{
+        ip_echo_server::{IpEchoServerMessage, MAX_PORT_COUNT_PER_MESSAGE, UDP_TOKEN_LENGTH},
+        ip_echo_server_request_opening_nat, NetUtilsError,
+    },
+    log::*,
+    std::{
+        net::{SocketAddr, UdpSocket},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+/// How often `open_udp_holes` should be repeated for its holes to stay open, well within the
+/// time NATs forget idle UDP mappings after
+pub const UDP_HOLE_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// How long the sockets echo tokens, well past the time servers wait for them
+const PUNCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Punch a hole through the NAT for each of `udp_sockets` by sending to the ip echo server at
+/// `ip_echo_server_addr`, and have the server send back through it.  Returns the public address
+/// of each socket, in order.  Servers older than version 7 send to the local port of a socket
+/// rather than through its hole, which only gets through NATs keeping the port of the socket
+pub fn punch_udp_holes(
+    ip_echo_server_addr: &SocketAddr,
+    udp_sockets: &[&UdpSocket],
+) -> Result<Vec<SocketAddr>, NetUtilsError> {
+    if udp_sockets.len() > MAX_PORT_COUNT_PER_MESSAGE {
+        return Err(NetUtilsError::InvalidArgument(format!(
+            "At most {} udp sockets can punch holes at once",
+            MAX_PORT_COUNT_PER_MESSAGE
+        )));
+    }
+    let udp_ports: Vec<_> = udp_sockets
+        .iter()
+        .map(|udp_socket| udp_socket.local_addr().unwrap().port())
+        .collect();
+
+    let request_done = Arc::new(AtomicBool::new(false));
+    let echo_threads: Vec<_> = udp_sockets
+        .iter()
+        .zip(&udp_ports)
+        .map(|(udp_socket, port)| {
+            let port = *port;
+            let udp_socket = udp_socket.try_clone().expect("Unable to clone udp socket");
+            let request_done = request_done.clone();
+            std::thread::spawn(move || {
+                let start = Instant::now();
+                let original_read_timeout = udp_socket.read_timeout().unwrap();
+                udp_socket
+                    .set_read_timeout(Some(Duration::from_millis(250)))
+                    .unwrap();
+                let mut buf = [0; UDP_TOKEN_LENGTH];
+                // Keep echoing until the server has replied, it resends tokens whose echo was lost
+                while !request_done.load(Ordering::Relaxed) && start.elapsed() < PUNCH_TIMEOUT {
+                    if let Ok((len, from)) = udp_socket.recv_from(&mut buf) {
+                        if len == UDP_TOKEN_LENGTH {
+                            let _ = udp_socket
+                                .send_to(&buf, from)
+                                .map_err(|err| warn!("udp/{} echo failed: {}", port, err));
+                        }
+                    }
+                }
+                udp_socket.set_read_timeout(original_read_timeout).unwrap();
+            })
+        })
+        .collect();
+
+    let response = ip_echo_server_request_opening_nat(
+        ip_echo_server_addr,
+        &IpEchoServerMessage::new(&[], &udp_ports),
+        udp_sockets,
+    );
+    request_done.store(true, Ordering::Relaxed);
+    for echo_thread in echo_threads {
+        echo_thread.join().unwrap();
+    }
+
+    let response = response?;
+    let udp_ports_mapped = response
+        .udp_ports_mapped
+        .ok_or(NetUtilsError::UnsupportedVersion {
+            addr: *ip_echo_server_addr,
+            version: response.version,
+            capability: "report the address of udp ports",
+        })?;
+    let unpunched_ports: Vec<_> = udp_ports
+        .iter()
+        .zip(&udp_ports_mapped)
+        .filter(|(_, mapped_addr)| mapped_addr.is_none())
+        .map(|(port, _)| *port)
+        .collect();
+    if !unpunched_ports.is_empty() {
+        return Err(NetUtilsError::Unreachable {
+            addr: *ip_echo_server_addr,
+            protocol: "udp",
+            ports: unpunched_ports,
+        });
+    }
+    for (port, mapped_addr) in udp_ports.iter().zip(&udp_ports_mapped) {
+        info!("udp/{} punched a hole at {:?}", port, mapped_addr);
+    }
+    Ok(udp_ports_mapped.into_iter().flatten().collect())
+}
+
+/// Send a datagram from `udp_socket` to each of `peer_addrs`, so that restricted cone NATs admit
+/// what these peers send to the socket.  Returns how many datagrams were sent
+pub fn open_udp_holes(udp_socket: &UdpSocket, peer_addrs: &[SocketAddr]) -> usize {
+    peer_addrs
+        .iter()
+        .filter(|peer_addr| {
+            udp_socket
+                .send_to(&[0], peer_addr)
+                .map_err(|err| debug!("Failed to open a udp hole to {}: {}", peer_addr, err))
+                .is_ok()
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::ip_echo_server,
+        std::net::{IpAddr, Ipv4Addr},
+    };
+
+    #[test]
+    fn test_punch_udp_holes() {
+        solana_logger::setup();
+        let tcp_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let ip_echo_server_addr = tcp_listener.local_addr().unwrap();
+        let _runtime = ip_echo_server(tcp_listener);
+
+        let udp_sockets: Vec<_> = (0..2)
+            .map(|_| UdpSocket::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let udp_socket_refs: Vec<_> = udp_sockets.iter().collect();
+        // Nothing stands between loopback sockets, their public address is their own
+        assert_eq!(
+            punch_udp_holes(&ip_echo_server_addr, &udp_socket_refs).unwrap(),
+            udp_sockets
+                .iter()
+                .map(|udp_socket| udp_socket.local_addr().unwrap())
+                .collect::<Vec<_>>()
+        );
+        // The sockets can be read from as before
+        assert_eq!(udp_sockets[0].read_timeout().unwrap(), None);
+    }
+
+    #[test]
+    fn test_open_udp_holes() {
+        let udp_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let peer_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let unspecified_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+        assert_eq!(
+            open_udp_holes(
+                &udp_socket,
+                &[peer_socket.local_addr().unwrap(), unspecified_addr]
+            ),
+            1
+        );
+        let mut buf = [0; 8];
+        let (len, from) = peer_socket.recv_from(&mut buf).unwrap();
+        assert_eq!(len, 1);
+        assert_eq!(from, udp_socket.local_addr().unwrap());
+    }
+}
@@ -1,128 +1,1241 @@
 use {
-    crate::{ip_echo_server_reply_length, HEADER_LENGTH},
+    crate::{
+        ip_echo_server_reply_length,
+        ip_filter::{IpFilter, IpFilterRejection},
+        quic, HEADER_LENGTH,
+    },
+    futures::future::join_all,
     log::*,
     serde_derive::{Deserialize, Serialize},
-    std::{io, net::SocketAddr, time::Duration},
+    solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signature, Signer},
+    },
+    std::{
+        collections::HashMap,
+        fmt, io,
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+        sync::{
+            atomic::{AtomicU64, AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::{Duration, Instant},
+    },
     tokio::{
-        net::{TcpListener, TcpStream},
+        net::{TcpListener, TcpStream, UdpSocket},
         prelude::*,
         runtime::{self, Runtime},
-        time::timeout,
+        task::JoinHandle,
+        time::{self, timeout},
     },
 };
 
 pub type IpEchoServer = Runtime;
 
-pub const MAX_PORT_COUNT_PER_MESSAGE: usize = 4;
+pub const MAX_PORT_COUNT_PER_MESSAGE: usize = 16;
+/// Number of ports of each kind in the fixed size port lists of version 0-2 requests
+pub(crate) const LEGACY_PORT_COUNT_PER_MESSAGE: usize = 4;
+
+// The server resends the UDP tokens that were not echoed back and the QUIC probes that were not
+// answered every UDP_TOKEN_RESEND_INTERVAL, to ride out packet loss
+const UDP_TOKEN_RESEND_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Size of the random token the server sends to each UDP port for the client to echo back
+pub(crate) const UDP_TOKEN_LENGTH: usize = 8;
+
+/// Size of the datagram version 7 clients punch a hole through their NAT with, the local port it
+/// was sent from as a bincode u16
+pub(crate) const UDP_PUNCH_LENGTH: usize = 2;
+
+/// Size of the random challenge version 9 clients end their requests with
+pub(crate) const CHALLENGE_LENGTH: usize = 32;
+
+/// What servers send instead of a response header to the connections they turn away, followed
+/// by the `IpEchoServerRejection`.  Older clients take it for an invalid header, rather than for a
+/// legacy server to retry with an older version
+pub(crate) const REJECTION_HEADER: [u8; HEADER_LENGTH] = *b"RJCT";
 
-const IO_TIMEOUT: Duration = Duration::from_secs(5);
+/// Size of a serialized `IpEchoServerRejection`
+pub(crate) const REJECTION_LENGTH: usize = 4;
 
+/// What the messages servers sign start with.  The challenge is the client's choice, and the
+/// keypair is the node identity, the tag keeps the signed messages from ever being a transaction,
+/// a gossip value or anything else the identity signs
+const SIGNED_RESPONSE_DOMAIN: &[u8] = b"solana-ip-echo-response-v1\0";
+
+/// The request prefix every version sends, holding the first LEGACY_PORT_COUNT_PER_MESSAGE ports
+/// of each kind
 #[derive(Serialize, Deserialize, Default, Debug)]
+pub(crate) struct LegacyIpEchoServerMessage {
+    tcp_ports: [u16; LEGACY_PORT_COUNT_PER_MESSAGE], // Fixed size list of ports to avoid vec serde
+    udp_ports: [u16; LEGACY_PORT_COUNT_PER_MESSAGE], // Fixed size list of ports to avoid vec serde
+}
+
+/// The ports to check.  Version 3 servers acknowledge the request prefix with their header,
+/// after which the client sends this message, with up to MAX_PORT_COUNT_PER_MESSAGE ports of
+/// each kind, in full
+#[derive(Default, Debug, PartialEq)]
 pub(crate) struct IpEchoServerMessage {
-    tcp_ports: [u16; MAX_PORT_COUNT_PER_MESSAGE], // Fixed size list of ports to avoid vec serde
-    udp_ports: [u16; MAX_PORT_COUNT_PER_MESSAGE], // Fixed size list of ports to avoid vec serde
+    tcp_ports: Vec<u16>,
+    udp_ports: Vec<u16>,
+    /// Sent since version 4
+    quic_ports: Vec<u16>,
+    /// Sent since version 9, signed by the server along with its response
+    challenge: [u8; CHALLENGE_LENGTH],
 }
 
 impl IpEchoServerMessage {
     pub fn new(tcp_ports: &[u16], udp_ports: &[u16]) -> Self {
-        let mut msg = Self::default();
-        assert!(tcp_ports.len() <= msg.tcp_ports.len());
-        assert!(udp_ports.len() <= msg.udp_ports.len());
+        assert!(tcp_ports.len() <= MAX_PORT_COUNT_PER_MESSAGE);
+        assert!(udp_ports.len() <= MAX_PORT_COUNT_PER_MESSAGE);
+        Self {
+            tcp_ports: tcp_ports.to_vec(),
+            udp_ports: udp_ports.to_vec(),
+            quic_ports: vec![],
+            challenge: [0; CHALLENGE_LENGTH],
+        }
+    }
+
+    /// With a fresh random challenge, for the server to sign its response to
+    pub fn with_random_challenge(mut self) -> Self {
+        self.challenge = rand::random();
+        self
+    }
+
+    pub(crate) fn challenge(&self) -> &[u8] {
+        &self.challenge
+    }
+
+    pub fn with_quic_ports(mut self, quic_ports: &[u16]) -> Self {
+        assert!(quic_ports.len() <= MAX_PORT_COUNT_PER_MESSAGE);
+        self.quic_ports = quic_ports.to_vec();
+        self
+    }
+
+    /// The largest message, its serialized length bounds what servers read
+    fn largest() -> Self {
+        let ports = [0; MAX_PORT_COUNT_PER_MESSAGE];
+        Self::new(&ports, &ports).with_quic_ports(&ports)
+    }
+
+    /// Whether servers older than version 3 can check all of the ports
+    pub(crate) fn fits_legacy(&self) -> bool {
+        self.tcp_ports.len() <= LEGACY_PORT_COUNT_PER_MESSAGE
+            && self.udp_ports.len() <= LEGACY_PORT_COUNT_PER_MESSAGE
+    }
+
+    pub(crate) fn has_quic_ports(&self) -> bool {
+        !self.quic_ports.is_empty()
+    }
+
+    /// Serializes the port lists of the given version, 3 or later
+    pub(crate) fn serialize(&self, version: u8) -> Vec<u8> {
+        let mut bytes = bincode::serialize(&(&self.tcp_ports, &self.udp_ports)).unwrap();
+        if version >= 4 {
+            bincode::serialize_into(&mut bytes, &self.quic_ports).unwrap();
+        }
+        if version >= 9 {
+            bytes.extend_from_slice(&self.challenge);
+        }
+        bytes
+    }
+
+    pub(crate) fn deserialize(version: u8, mut bytes: &[u8]) -> bincode::Result<Self> {
+        let (tcp_ports, udp_ports) = bincode::deserialize_from(&mut bytes)?;
+        let quic_ports = if version >= 4 {
+            bincode::deserialize_from(&mut bytes)?
+        } else {
+            vec![]
+        };
+        let challenge = if version >= 9 {
+            bincode::deserialize_from(&mut bytes)?
+        } else {
+            [0; CHALLENGE_LENGTH]
+        };
+        Ok(Self {
+            tcp_ports,
+            udp_ports,
+            quic_ports,
+            challenge,
+        })
+    }
 
-        msg.tcp_ports[..tcp_ports.len()].copy_from_slice(tcp_ports);
-        msg.udp_ports[..udp_ports.len()].copy_from_slice(udp_ports);
+    pub(crate) fn legacy(&self) -> LegacyIpEchoServerMessage {
+        let mut msg = LegacyIpEchoServerMessage::default();
+        for (port, legacy_port) in self.tcp_ports.iter().zip(msg.tcp_ports.iter_mut()) {
+            *legacy_port = *port;
+        }
+        for (port, legacy_port) in self.udp_ports.iter().zip(msg.udp_ports.iter_mut()) {
+            *legacy_port = *port;
+        }
         msg
     }
 }
 
+impl From<LegacyIpEchoServerMessage> for IpEchoServerMessage {
+    fn from(msg: LegacyIpEchoServerMessage) -> Self {
+        Self {
+            tcp_ports: msg.tcp_ports.to_vec(),
+            udp_ports: msg.udp_ports.to_vec(),
+            quic_ports: vec![],
+            challenge: [0; CHALLENGE_LENGTH],
+        }
+    }
+}
+
+/// The last byte of the request and response headers.  The server replies with the lower of
+/// the client's version and its own.  Version 0 responses hold only the IP address of the peer,
+/// version 1 responses also report which ports the server could reach, and version 2 responses
+/// report which UDP ports echoed back the token sent to them.  Each version appends its fields
+/// to the previous version's, so that new fields can be added without breaking older peers.
+/// Version 3 lifts the limit of LEGACY_PORT_COUNT_PER_MESSAGE ports of each kind per request,
+/// its port lists are length prefixed.  Version 4 requests also list QUIC ports, which the
+/// server probes for a Version Negotiation packet, and its responses report which answered.
+/// Version 5 servers follow their acknowledgement with the port of the UDP socket they send the
+/// tokens from, so that clients behind NATs dropping unsolicited datagrams can send to it first,
+/// and its responses report the address each UDP port echoed its token back from.  Version 6
+/// responses start with the source port of the request next to the address, making it an
+/// `(IpAddr, u16)` pair, so that clients can tell how their NAT rewrites ports.  Version 7
+/// clients open their NAT with a UDP_PUNCH_LENGTH datagram naming the local port it was sent
+/// from, and the server sends that port's token to wherever the datagram came from rather than
+/// to the port itself, reaching sockets whose NAT maps them to another public port.  Version 8
+/// responses end with the `ClusterIdentity` of the server, if it was given one.  Version 9
+/// requests end with a random challenge, and servers given a keypair end their responses with
+/// its pubkey and its signature of SIGNED_RESPONSE_DOMAIN, the challenge and the rest of the
+/// response, so that clients can tell the response of the entrypoint they meant from one forged
+/// on the way
+pub(crate) const IP_ECHO_SERVER_PROTOCOL_VERSION: u8 = 9;
+
+/// The number of ports of each kind a request of the given version can hold
+pub(crate) fn max_port_count_per_message(version: u8) -> usize {
+    if version >= 3 {
+        MAX_PORT_COUNT_PER_MESSAGE
+    } else {
+        LEGACY_PORT_COUNT_PER_MESSAGE
+    }
+}
+
+/// The number of QUIC ports a request of the given version can hold, none before version 4
+pub(crate) fn max_quic_port_count_per_message(version: u8) -> usize {
+    if version >= 4 {
+        MAX_PORT_COUNT_PER_MESSAGE
+    } else {
+        0
+    }
+}
+
+/// The first LEGACY_PORT_COUNT_PER_MESSAGE entries of a port list, for version 1 and 2 responses
+fn legacy_port_list(ports: &[bool]) -> [bool; LEGACY_PORT_COUNT_PER_MESSAGE] {
+    let mut legacy_ports = [false; LEGACY_PORT_COUNT_PER_MESSAGE];
+    for (port, legacy_port) in ports.iter().zip(legacy_ports.iter_mut()) {
+        *legacy_port = *port;
+    }
+    legacy_ports
+}
+
+/// Why a server turned a connection away
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpEchoServerRejection {
+    /// The peer's IP address opened more connections than the server's rate limit allows
+    RateLimited,
+    /// The server is serving as many connections as it can
+    Busy,
+    /// The peer's IP address is outside the networks the server admits connections from
+    Denied,
+    /// The peer's IP address is banned
+    Banned,
+}
+
+impl fmt::Display for IpEchoServerRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Self::RateLimited => "too many connections from this address",
+            Self::Busy => "the server is busy",
+            Self::Denied => "connections from this address are not allowed",
+            Self::Banned => "this address is banned",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// The cluster an ip echo server serves, for joining nodes to check they are about to join the
+/// cluster they meant to
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct ClusterIdentity {
+    pub shred_version: u16,
+    pub genesis_hash: [u8; 32],
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct IpEchoServerResponse {
+    /// Protocol version negotiated with the server
+    pub version: u8,
+    /// Public IP address of the request's sender
+    pub address: IpAddr,
+    /// Source port of the request's TCP connection as the server saw it, as mapped by any NAT in
+    /// between, since version 6
+    pub source_port: Option<u16>,
+    /// Whether the server could connect to each of the requested TCP ports, since version 1.
+    /// Responses older than version 3 cover the first LEGACY_PORT_COUNT_PER_MESSAGE ports only
+    pub tcp_ports_reachable: Option<Vec<bool>>,
+    /// Whether the server could send a datagram to each of the requested UDP ports, since
+    /// version 1
+    pub udp_ports_sent: Option<Vec<bool>>,
+    /// Whether each of the requested UDP ports echoed back the server's token, since version 2
+    pub udp_ports_confirmed: Option<Vec<bool>>,
+    /// Whether each of the requested QUIC ports answered the server's probe, since version 4
+    pub quic_ports_reachable: Option<Vec<bool>>,
+    /// The address each of the requested UDP ports echoed back the server's token from, as
+    /// mapped by any NAT in between, since version 5
+    pub udp_ports_mapped: Option<Vec<Option<SocketAddr>>>,
+    /// The cluster the server serves, since version 8 and for servers given one only
+    pub cluster_identity: Option<ClusterIdentity>,
+    /// The server's pubkey and its signature of the request's challenge and the response, since
+    /// version 9 and for servers given a keypair only
+    pub signature: Option<(Pubkey, Signature)>,
+}
+
+impl IpEchoServerResponse {
+    /// The largest response of the given version, replies are padded to its length
+    pub(crate) fn largest(version: u8) -> Self {
+        let ports = Some(vec![false; max_port_count_per_message(version)]);
+        Self {
+            version,
+            address: IpAddr::from([0u16; 8]), // IPv6 variant
+            source_port: Some(0),
+            tcp_ports_reachable: ports.clone(),
+            udp_ports_sent: ports.clone(),
+            udp_ports_confirmed: ports.clone(),
+            quic_ports_reachable: ports,
+            udp_ports_mapped: Some(vec![
+                Some(SocketAddr::from(([0u16; 8], 0)));
+                max_port_count_per_message(version)
+            ]),
+            cluster_identity: Some(ClusterIdentity::default()),
+            signature: Some((Pubkey::default(), Signature::default())),
+        }
+    }
+
+    /// Serializes the fields of the response's version
+    pub(crate) fn serialize(&self) -> Vec<u8> {
+        let mut bytes = self.serialize_unsigned();
+        if self.version >= 9 {
+            bincode::serialize_into(&mut bytes, &self.signature).unwrap();
+        }
+        bytes
+    }
+
+    /// What the server signs, the domain tag and the challenge of the request followed by the
+    /// response
+    fn signed_message(&self, challenge: &[u8]) -> Vec<u8> {
+        assert_eq!(challenge.len(), CHALLENGE_LENGTH);
+        let mut message = SIGNED_RESPONSE_DOMAIN.to_vec();
+        message.extend_from_slice(challenge);
+        message.extend(self.serialize_unsigned());
+        message
+    }
+
+    /// Signs the response to a request with the given challenge
+    fn sign(&mut self, keypair: &Keypair, challenge: &[u8]) {
+        let signature = keypair.sign_message(&self.signed_message(challenge));
+        self.signature = Some((keypair.pubkey(), signature));
+    }
+
+    /// Whether the response is signed by one of `pubkeys` for the request with the given
+    /// challenge
+    pub(crate) fn is_signed_by(&self, pubkeys: &[Pubkey], challenge: &[u8]) -> bool {
+        match &self.signature {
+            Some((pubkey, signature)) => {
+                pubkeys.contains(pubkey)
+                    && signature.verify(pubkey.as_ref(), &self.signed_message(challenge))
+            }
+            None => false,
+        }
+    }
+
+    /// Serializes the fields of the response's version but the signature
+    fn serialize_unsigned(&self) -> Vec<u8> {
+        let mut bytes = if self.version >= 6 {
+            bincode::serialize(&(self.address, self.source_port.unwrap_or_default())).unwrap()
+        } else {
+            bincode::serialize(&self.address).unwrap()
+        };
+        let serialize_ports = |bytes: &mut Vec<u8>, ports: &Option<Vec<bool>>| {
+            if self.version >= 3 {
+                bincode::serialize_into(bytes, ports).unwrap();
+            } else {
+                let ports = ports.as_deref().map(legacy_port_list);
+                bincode::serialize_into(bytes, &ports).unwrap();
+            }
+        };
+        if self.version >= 1 {
+            serialize_ports(&mut bytes, &self.tcp_ports_reachable);
+            serialize_ports(&mut bytes, &self.udp_ports_sent);
+        }
+        if self.version >= 2 {
+            serialize_ports(&mut bytes, &self.udp_ports_confirmed);
+        }
+        if self.version >= 4 {
+            serialize_ports(&mut bytes, &self.quic_ports_reachable);
+        }
+        if self.version >= 5 {
+            bincode::serialize_into(&mut bytes, &self.udp_ports_mapped).unwrap();
+        }
+        if self.version >= 8 {
+            bincode::serialize_into(&mut bytes, &self.cluster_identity).unwrap();
+        }
+        bytes
+    }
+
+    /// Deserializes the fields of a response of the given version, ignoring any padding
+    pub(crate) fn deserialize(version: u8, mut bytes: &[u8]) -> bincode::Result<Self> {
+        let (address, source_port) = if version >= 6 {
+            let (address, source_port) = bincode::deserialize_from(&mut bytes)?;
+            (address, Some(source_port))
+        } else {
+            (bincode::deserialize_from(&mut bytes)?, None)
+        };
+        let mut response = Self {
+            version,
+            address,
+            source_port,
+            tcp_ports_reachable: None,
+            udp_ports_sent: None,
+            udp_ports_confirmed: None,
+            quic_ports_reachable: None,
+            udp_ports_mapped: None,
+            cluster_identity: None,
+            signature: None,
+        };
+        let mut deserialize_ports = || -> bincode::Result<Option<Vec<bool>>> {
+            if version >= 3 {
+                bincode::deserialize_from(&mut bytes)
+            } else {
+                let ports: Option<[bool; LEGACY_PORT_COUNT_PER_MESSAGE]> =
+                    bincode::deserialize_from(&mut bytes)?;
+                Ok(ports.map(|ports| ports.to_vec()))
+            }
+        };
+        if version >= 1 {
+            response.tcp_ports_reachable = deserialize_ports()?;
+            response.udp_ports_sent = deserialize_ports()?;
+        }
+        if version >= 2 {
+            response.udp_ports_confirmed = deserialize_ports()?;
+        }
+        if version >= 4 {
+            response.quic_ports_reachable = deserialize_ports()?;
+        }
+        if version >= 5 {
+            response.udp_ports_mapped = bincode::deserialize_from(&mut bytes)?;
+        }
+        if version >= 8 {
+            response.cluster_identity = bincode::deserialize_from(&mut bytes)?;
+        }
+        if version >= 9 {
+            response.signature = bincode::deserialize_from(&mut bytes)?;
+        }
+        Ok(response)
+    }
+}
+
 pub(crate) fn ip_echo_server_request_length() -> usize {
     const REQUEST_TERMINUS_LENGTH: usize = 1;
     HEADER_LENGTH
-        + bincode::serialized_size(&IpEchoServerMessage::default()).unwrap() as usize
+        + bincode::serialized_size(&LegacyIpEchoServerMessage::default()).unwrap() as usize
         + REQUEST_TERMINUS_LENGTH
 }
 
-async fn process_connection(mut socket: TcpStream, peer_addr: SocketAddr) -> io::Result<()> {
+/// IPv4 peers of a dual-stack listener show up as IPv4-mapped IPv6 addresses, reply with the
+/// plain IPv4 address they would get from an IPv4 listener
+fn canonical_ip_addr(ip_addr: IpAddr) -> IpAddr {
+    match ip_addr {
+        IpAddr::V6(ipv6_addr) => match ipv6_addr.segments() {
+            [0, 0, 0, 0, 0, 0xffff, _, _] => IpAddr::V4(ipv6_addr.to_ipv4().unwrap()),
+            _ => ip_addr,
+        },
+        IpAddr::V4(_) => ip_addr,
+    }
+}
+
+// solana_sdk::rpc_port::DEFAULT_RPC_PORT, where the JSON RPC service of most nodes listens
+const DEFAULT_RPC_PORT: u16 = 8899;
+
+/// The reply to an HTTP request, most likely meant for the node's JSON RPC service at `rpc_url`.
+/// POST requests are taken for JSON RPC calls and get a JSON RPC error, others a short HTML page
+fn http_response(request_header: &str, rpc_url: &str, rpc_url_known: bool) -> String {
+    let message = format!(
+        "This is the gossip entrypoint of a Solana node, not a JSON RPC endpoint. {} {}",
+        if rpc_url_known {
+            "Its JSON RPC URL is"
+        } else {
+            "The JSON RPC URL is probably"
+        },
+        rpc_url
+    );
+    let (content_type, body) = if request_header == "POST" {
+        (
+            "application/json",
+            format!(
+                r#"{{"jsonrpc":"2.0","error":{{"code":-32600,"message":"{}"}},"id":null}}"#,
+                message
+            ),
+        )
+    } else {
+        (
+            "text/html",
+            format!(
+                "<!DOCTYPE html>\n<html><head><title>Solana gossip entrypoint</title></head>\n\
+                 <body><p>{}</p></body></html>\n",
+                message.replace(rpc_url, &format!(r#"<a href="{0}">{0}</a>"#, rpc_url))
+            ),
+        )
+    };
+    format!(
+        "HTTP/1.1 400 Bad Request\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+async fn process_connection(
+    mut socket: TcpStream,
+    peer_addr: SocketAddr,
+    config: &IpEchoServerConfig,
+    stats: &IpEchoServerStats,
+) -> io::Result<()> {
     info!("connection from {:?}", peer_addr);
 
     let mut data = vec![0u8; ip_echo_server_request_length()];
+    let local_addr = socket.local_addr()?;
     let (mut reader, mut writer) = socket.split();
 
-    let _ = timeout(IO_TIMEOUT, reader.read_exact(&mut data)).await??;
-    drop(reader);
+    let _ = timeout(config.io_timeout, reader.read_exact(&mut data)).await??;
 
     let request_header: String = data[0..HEADER_LENGTH].iter().map(|b| *b as char).collect();
-    if request_header != "\0\0\0\0" {
+    if data[..HEADER_LENGTH - 1] != [0; HEADER_LENGTH - 1] {
         // Explicitly check for HTTP GET/POST requests to more gracefully handle
         // the case where a user accidentally tried to use a gossip entrypoint in
         // place of a JSON RPC URL:
         if request_header == "GET " || request_header == "POST" {
-            // Send HTTP error response
-            timeout(
-                IO_TIMEOUT,
-                writer.write_all(b"HTTP/1.1 400 Bad Request\nContent-length: 0\n\n"),
-            )
-            .await??;
+            stats.requests_http.fetch_add(1, Ordering::Relaxed);
+            // Point the user to the RPC service, by default at the address they reached us at
+            let rpc_addr = config.rpc_addr.unwrap_or_else(|| {
+                SocketAddr::new(canonical_ip_addr(local_addr.ip()), DEFAULT_RPC_PORT)
+            });
+            let response = http_response(
+                &request_header,
+                &format!("http://{}", rpc_addr),
+                config.rpc_addr.is_some(),
+            );
+            timeout(config.io_timeout, writer.write_all(response.as_bytes())).await??;
             return Ok(());
         }
+        stats.requests_bad_header.fetch_add(1, Ordering::Relaxed);
+        if let Some(max_bad_headers) = config.max_bad_headers_per_ip {
+            let ip_addr = canonical_ip_addr(peer_addr.ip());
+            if config.ip_filter.record_bad_header(
+                ip_addr,
+                Instant::now(),
+                max_bad_headers,
+                config.ban_duration,
+            ) {
+                info!(
+                    "banned {} for {:?} after {} bad request headers",
+                    ip_addr, config.ban_duration, max_bad_headers
+                );
+                stats.ips_banned.fetch_add(1, Ordering::Relaxed);
+            }
+        }
         return Err(io::Error::new(
             io::ErrorKind::Other,
             format!("Bad request header: {}", request_header),
         ));
     }
 
-    let msg =
-        bincode::deserialize::<IpEchoServerMessage>(&data[HEADER_LENGTH..]).map_err(|err| {
+    let msg = bincode::deserialize::<LegacyIpEchoServerMessage>(&data[HEADER_LENGTH..]).map_err(
+        |err| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("Failed to deserialize LegacyIpEchoServerMessage: {:?}", err),
+            )
+        },
+    )?;
+
+    // Datagrams go out from a socket of the peer's address family
+    let bind_addr = match peer_addr {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let udp_socket = UdpSocket::bind(bind_addr)
+        .await
+        .map_err(|err| warn!("Failed to bind local udp socket: {}", err))
+        .ok();
+
+    // Negotiate down to the client's version, newer clients fall back to this server's
+    let version = data[HEADER_LENGTH - 1].min(IP_ECHO_SERVER_PROTOCOL_VERSION);
+    let msg = if version >= 3 {
+        // Acknowledge with the negotiated header, then read the full port lists
+        let mut header = vec![0u8; HEADER_LENGTH];
+        header[HEADER_LENGTH - 1] = version;
+        if version >= 5 {
+            // Zero if there is no socket to send tokens from
+            let udp_port = udp_socket
+                .as_ref()
+                .and_then(|udp_socket| udp_socket.local_addr().ok())
+                .map_or(0, |addr| addr.port());
+            bincode::serialize_into(&mut header, &udp_port).unwrap();
+        }
+        timeout(config.io_timeout, writer.write_all(&header)).await??;
+
+        let max_length = IpEchoServerMessage::largest().serialize(version).len() as u64;
+        let mut data = vec![];
+        let _ = timeout(
+            config.io_timeout,
+            (&mut reader).take(max_length).read_to_end(&mut data),
+        )
+        .await??;
+        let msg = IpEchoServerMessage::deserialize(version, &data).map_err(|err| {
             io::Error::new(
                 io::ErrorKind::Other,
                 format!("Failed to deserialize IpEchoServerMessage: {:?}", err),
             )
         })?;
+        if msg.tcp_ports.len() > MAX_PORT_COUNT_PER_MESSAGE
+            || msg.udp_ports.len() > MAX_PORT_COUNT_PER_MESSAGE
+            || msg.quic_ports.len() > MAX_PORT_COUNT_PER_MESSAGE
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("Too many ports requested: {:?}", msg),
+            ));
+        }
+        msg
+    } else {
+        IpEchoServerMessage::from(msg)
+    };
+    let probed_ports = |ports: &[u16]| ports.iter().filter(|port| **port != 0).count();
+    if [&msg.tcp_ports, &msg.udp_ports, &msg.quic_ports]
+        .iter()
+        .any(|ports| probed_ports(ports) > config.max_probed_ports)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "Too many ports to probe, at most {} of each kind: {:?}",
+                config.max_probed_ports, msg
+            ),
+        ));
+    }
+    drop(reader);
+    trace!("request: {:?}, version {}", msg, version);
+
+    // Fire a datagram at each non-zero UDP port.  Version 2 clients echo back the random token it
+    // carries, older clients only need one byte
+    let mut udp_ports_sent = vec![false; msg.udp_ports.len()];
+    let udp_tokens: Vec<[u8; UDP_TOKEN_LENGTH]> =
+        msg.udp_ports.iter().map(|_| rand::random()).collect();
+    if let Some(udp_socket) = &udp_socket {
+        for ((udp_port, token), sent) in msg
+            .udp_ports
+            .iter()
+            .zip(&udp_tokens)
+            .zip(udp_ports_sent.iter_mut())
+        {
+            if *udp_port != 0 {
+                let datagram: &[u8] = if version >= 2 { token } else { &[0] };
+                match udp_socket
+                    .send_to(datagram, SocketAddr::new(peer_addr.ip(), *udp_port))
+                    .await
+                {
+                    Ok(_) => {
+                        debug!("Successful send_to udp/{}", udp_port);
+                        *sent = true;
+                    }
+                    Err(err) => info!("Failed to send_to udp/{}: {}", udp_port, err),
+                }
+            }
+        }
+    }
+
+    // Probe the TCP ports while waiting on the UDP tokens and QUIC probes.  The UDP and QUIC
+    // probes share the UDP socket, so they take turns
+    let udp_probes = async {
+        let udp_ports_mapped = match &udp_socket {
+            Some(udp_socket) if version >= 2 => Some(
+                confirm_udp_tokens(
+                    udp_socket,
+                    peer_addr.ip(),
+                    &msg.udp_ports,
+                    &udp_tokens,
+                    version >= 7,
+                    config.probe_timeout,
+                )
+                .await,
+            ),
+            _ => None,
+        };
+        let quic_ports_reachable = match &udp_socket {
+            Some(udp_socket) if version >= 4 => Some(
+                probe_quic_ports(
+                    udp_socket,
+                    peer_addr.ip(),
+                    &msg.quic_ports,
+                    config.probe_timeout,
+                )
+                .await,
+            ),
+            _ => None,
+        };
+        (udp_ports_mapped, quic_ports_reachable)
+    };
+    let (tcp_ports_reachable, (udp_ports_mapped, quic_ports_reachable)) = futures::join!(
+        probe_tcp_ports(peer_addr.ip(), &msg.tcp_ports, config.io_timeout, stats),
+        udp_probes
+    );
+    if version == 0
+        && tcp_ports_reachable
+            .iter()
+            .zip(&msg.tcp_ports)
+            .any(|(reachable, port)| *port != 0 && !reachable)
+    {
+        // Version 0 clients learn of unreachable TCP ports by the lack of a response
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("Unable to connect to tcp ports {:?}", msg.tcp_ports),
+        ));
+    }
+    if let Some(quic_ports_reachable) = &quic_ports_reachable {
+        let quic_probes_failed = quic_ports_reachable
+            .iter()
+            .zip(&msg.quic_ports)
+            .filter(|(reachable, port)| **port != 0 && !**reachable)
+            .count();
+        stats
+            .quic_probes_failed
+            .fetch_add(quic_probes_failed as u64, Ordering::Relaxed);
+    }
 
-    trace!("request: {:?}", msg);
+    let udp_ports_confirmed = udp_ports_mapped
+        .as_ref()
+        .map(|mapped| mapped.iter().map(Option::is_some).collect());
 
-    // Fire a datagram at each non-zero UDP port
-    match std::net::UdpSocket::bind("0.0.0.0:0") {
-        Ok(udp_socket) => {
-            for udp_port in &msg.udp_ports {
-                if *udp_port != 0 {
-                    match udp_socket.send_to(&[0], SocketAddr::from((peer_addr.ip(), *udp_port))) {
-                        Ok(_) => debug!("Successful send_to udp/{}", udp_port),
-                        Err(err) => info!("Failed to send_to udp/{}: {}", udp_port, err),
+    // A header of three null bytes and the protocol version is added to ensure a valid response
+    // will never conflict with the first four bytes of a valid HTTP response.  Version 3 clients
+    // already received it
+    let mut bytes = vec![0u8; ip_echo_server_reply_length(version)];
+    bytes[HEADER_LENGTH - 1] = version;
+    let mut response = IpEchoServerResponse {
+        version,
+        address: canonical_ip_addr(peer_addr.ip()),
+        source_port: Some(peer_addr.port()).filter(|_| version >= 6),
+        tcp_ports_reachable: Some(tcp_ports_reachable),
+        udp_ports_sent: Some(udp_ports_sent),
+        udp_ports_confirmed,
+        quic_ports_reachable,
+        udp_ports_mapped: udp_ports_mapped.filter(|_| version >= 5),
+        cluster_identity: config.cluster_identity.filter(|_| version >= 8),
+        signature: None,
+    };
+    if let Some(keypair) = config.keypair.as_ref().filter(|_| version >= 9) {
+        response.sign(keypair, msg.challenge());
+    }
+    let response = response.serialize();
+    bytes[HEADER_LENGTH..HEADER_LENGTH + response.len()].copy_from_slice(&response);
+    trace!("response: {:?}", bytes);
+    let header_length = if version >= 3 { HEADER_LENGTH } else { 0 };
+    timeout(config.io_timeout, writer.write_all(&bytes[header_length..])).await?
+}
+
+/// Connects to all non-zero TCP ports at once, giving up on the ones not connected within
+/// `probe_timeout`, and returns which of them were reachable
+async fn probe_tcp_ports(
+    peer_ip: IpAddr,
+    tcp_ports: &[u16],
+    probe_timeout: Duration,
+    stats: &IpEchoServerStats,
+) -> Vec<bool> {
+    let deadline = time::Instant::now() + probe_timeout;
+    join_all(tcp_ports.iter().map(|tcp_port| async move {
+        if *tcp_port == 0 {
+            return false;
+        }
+        debug!("Connecting to tcp/{}", tcp_port);
+        let result = time::timeout_at(
+            deadline,
+            TcpStream::connect(&SocketAddr::new(peer_ip, *tcp_port)),
+        )
+        .await;
+        match result {
+            Ok(Ok(tcp_stream)) => {
+                debug!("Connection established to tcp/{}", tcp_port);
+                let _ = tcp_stream.shutdown(std::net::Shutdown::Both);
+                true
+            }
+            Ok(Err(err)) => {
+                info!("Failed to connect to tcp/{}: {}", tcp_port, err);
+                stats.tcp_probes_failed.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+            Err(_) => {
+                info!("Timed out connecting to tcp/{}", tcp_port);
+                stats.tcp_probes_failed.fetch_add(1, Ordering::Relaxed);
+                false
+            }
+        }
+    }))
+    .await
+}
+
+/// Waits up to `probe_timeout` for the client to echo back the token sent to each non-zero UDP
+/// port, resending the tokens that have not been echoed yet in case either datagram was lost.
+/// With `follow_punches`, a port's token goes wherever the peer punched a hole for that port
+/// from.  Returns the address each token was echoed back from
+async fn confirm_udp_tokens(
+    udp_socket: &UdpSocket,
+    peer_ip: IpAddr,
+    udp_ports: &[u16],
+    udp_tokens: &[[u8; UDP_TOKEN_LENGTH]],
+    follow_punches: bool,
+    probe_timeout: Duration,
+) -> Vec<Option<SocketAddr>> {
+    let mut mapped = vec![None; udp_ports.len()];
+    let mut token_addrs: Vec<_> = udp_ports
+        .iter()
+        .map(|udp_port| SocketAddr::new(peer_ip, *udp_port))
+        .collect();
+    let unconfirmed = |mapped: &[Option<SocketAddr>]| {
+        udp_ports
+            .iter()
+            .zip(mapped.iter())
+            .enumerate()
+            .filter(|(_, (udp_port, mapped))| **udp_port != 0 && mapped.is_none())
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>()
+    };
+
+    let deadline = Instant::now() + probe_timeout;
+    let mut buf = [0u8; UDP_TOKEN_LENGTH + 1];
+    while !unconfirmed(&mapped).is_empty() && Instant::now() < deadline {
+        match timeout(UDP_TOKEN_RESEND_INTERVAL, udp_socket.recv_from(&mut buf)).await {
+            Ok(Ok((len, from))) => {
+                // Tokens are matched regardless of the source port, NATs may rewrite it.  Other
+                // datagrams, such as those older clients open a path through their NAT with, are
+                // ignored
+                if len == UDP_TOKEN_LENGTH {
+                    if let Some(i) = udp_tokens.iter().position(|token| token[..] == buf[..len]) {
+                        debug!("udp/{} echoed its token from {}", udp_ports[i], from);
+                        mapped[i] =
+                            Some(SocketAddr::new(canonical_ip_addr(from.ip()), from.port()));
+                    }
+                } else if len == UDP_PUNCH_LENGTH
+                    && follow_punches
+                    // Only ever send to the peer, whatever the datagram claims
+                    && canonical_ip_addr(from.ip()) == canonical_ip_addr(peer_ip)
+                {
+                    let udp_port: u16 = bincode::deserialize(&buf[..len]).unwrap();
+                    for i in unconfirmed(&mapped) {
+                        if udp_ports[i] == udp_port {
+                            debug!("udp/{} punched a hole at {}", udp_port, from);
+                            token_addrs[i] = from;
+                            let _ = udp_socket.send_to(&udp_tokens[i], from).await;
+                        }
                     }
                 }
             }
+            Ok(Err(err)) => {
+                info!("Failed to receive udp token echo: {}", err);
+                break;
+            }
+            Err(_) => {
+                for i in unconfirmed(&mapped) {
+                    let _ = udp_socket.send_to(&udp_tokens[i], token_addrs[i]).await;
+                }
+            }
+        }
+    }
+    for i in unconfirmed(&mapped) {
+        info!("udp/{} did not echo its token", udp_ports[i]);
+    }
+    mapped
+}
+
+/// Sends a QUIC probe to each non-zero port and waits up to `probe_timeout` for the Version
+/// Negotiation packets answering them, resending the unanswered probes in case either packet was
+/// lost
+async fn probe_quic_ports(
+    udp_socket: &UdpSocket,
+    peer_ip: IpAddr,
+    quic_ports: &[u16],
+    probe_timeout: Duration,
+) -> Vec<bool> {
+    let connection_ids: Vec<(quic::ConnectionId, quic::ConnectionId)> = quic_ports
+        .iter()
+        .map(|_| (rand::random(), rand::random()))
+        .collect();
+    let mut answered = vec![false; quic_ports.len()];
+    let unanswered = |answered: &[bool]| {
+        quic_ports
+            .iter()
+            .zip(&connection_ids)
+            .zip(answered.iter())
+            .filter(|((quic_port, _), answered)| **quic_port != 0 && !**answered)
+            .map(|((quic_port, connection_ids), _)| (*quic_port, *connection_ids))
+            .collect::<Vec<_>>()
+    };
+    let send_probes = |probes: Vec<(u16, (quic::ConnectionId, quic::ConnectionId))>| async move {
+        for (quic_port, (destination_connection_id, source_connection_id)) in probes {
+            let probe = quic::probe_packet(&destination_connection_id, &source_connection_id);
+            if let Err(err) = udp_socket
+                .send_to(&probe, SocketAddr::new(peer_ip, quic_port))
+                .await
+            {
+                info!("Failed to send a probe to quic/{}: {}", quic_port, err);
+            }
+        }
+    };
+
+    send_probes(unanswered(&answered)).await;
+    let deadline = Instant::now() + probe_timeout;
+    let mut buf = [0u8; quic::PROBE_DATAGRAM_SIZE];
+    while !unanswered(&answered).is_empty() && Instant::now() < deadline {
+        match timeout(UDP_TOKEN_RESEND_INTERVAL, udp_socket.recv_from(&mut buf)).await {
+            Ok(Ok((len, from))) => {
+                // Answers are matched by their connection IDs, NATs may rewrite the source port
+                if let Some(i) = connection_ids.iter().position(
+                    |(destination_connection_id, source_connection_id)| {
+                        quic::is_probe_answer(
+                            &buf[..len],
+                            destination_connection_id,
+                            source_connection_id,
+                        )
+                    },
+                ) {
+                    debug!("quic/{} answered its probe from {}", quic_ports[i], from);
+                    answered[i] = true;
+                }
+            }
+            Ok(Err(err)) => {
+                info!("Failed to receive quic probe answer: {}", err);
+                break;
+            }
+            Err(_) => send_probes(unanswered(&answered)).await,
         }
-        Err(err) => {
-            warn!("Failed to bind local udp socket: {}", err);
+    }
+    for (quic_port, _) in unanswered(&answered) {
+        info!("quic/{} did not answer its probe", quic_port);
+    }
+    answered
+}
+
+/// Timeouts of the server, limits protecting it from peers opening connections or asking for
+/// probes faster than it can serve them, the peers it serves at all, and the cluster it reports
+/// to serve
+#[derive(Clone, Debug)]
+pub struct IpEchoServerConfig {
+    /// How long each read and write of a session may take, and how long the TCP ports of a
+    /// request are given to accept the connections probing them, all at once
+    pub io_timeout: Duration,
+    /// How long UDP ports are given to echo back their tokens and QUIC ports to answer their
+    /// probes
+    pub probe_timeout: Duration,
+    /// Ports of each kind a single request may ask to probe, requests asking for more fail.  At
+    /// most MAX_PORT_COUNT_PER_MESSAGE take effect
+    pub max_probed_ports: usize,
+    /// Connections served at once, further connections are turned away as `Busy`
+    pub max_concurrent_connections: usize,
+    /// Connections accepted from a single IP address per `rate_limit_interval`, further
+    /// connections are turned away as `RateLimited`.  Not limited when `None`, as many nodes can
+    /// share the address of a NAT
+    pub max_requests_per_ip: Option<usize>,
+    pub rate_limit_interval: Duration,
+    /// Reported to version 8 clients, nodes that don't know the cluster they serve leave it out
+    pub cluster_identity: Option<ClusterIdentity>,
+    /// The node's advertised JSON RPC address, which HTTP requests sent to the server by mistake
+    /// are pointed to.  Without it they are pointed to the default RPC port of the address they
+    /// connected to
+    pub rpc_addr: Option<SocketAddr>,
+    /// Signs the responses to version 9 clients, which can then check they come from this node
+    pub keypair: Option<Arc<Keypair>>,
+    /// The networks connections are admitted from and the addresses banned.  Clones of the
+    /// config share it, so updates to it apply to the running server
+    pub ip_filter: Arc<IpFilter>,
+    /// Requests with bad headers a single IP address may send within `ban_duration` before it
+    /// is banned for `ban_duration`, never banned when `None`
+    pub max_bad_headers_per_ip: Option<usize>,
+    pub ban_duration: Duration,
+}
+
+impl Default for IpEchoServerConfig {
+    fn default() -> Self {
+        Self {
+            io_timeout: Duration::from_secs(5),
+            probe_timeout: Duration::from_secs(2),
+            max_probed_ports: MAX_PORT_COUNT_PER_MESSAGE,
+            // Far more than a cluster restarting at once opens, only bounding floods
+            max_concurrent_connections: 4096,
+            max_requests_per_ip: None,
+            rate_limit_interval: Duration::from_secs(10),
+            cluster_identity: None,
+            rpc_addr: None,
+            keypair: None,
+            ip_filter: Arc::default(),
+            max_bad_headers_per_ip: None,
+            ban_duration: Duration::from_secs(10 * 60),
         }
     }
+}
 
-    // Try to connect to each non-zero TCP port
-    for tcp_port in &msg.tcp_ports {
-        if *tcp_port != 0 {
-            debug!("Connecting to tcp/{}", tcp_port);
+/// Upper bounds of the buckets sessions are counted in by processing time, the last bucket
+/// holds the sessions that took longer
+pub const PROCESSING_TIME_BUCKETS_MS: [u64; 4] = [10, 100, 1_000, 5_000];
 
-            let tcp_stream = timeout(
-                IO_TIMEOUT,
-                TcpStream::connect(&SocketAddr::new(peer_addr.ip(), *tcp_port)),
-            )
-            .await??;
+/// Counters of the server's activity since it started
+#[derive(Debug, Default)]
+pub struct IpEchoServerStats {
+    pub connections_accepted: AtomicU64,
+    pub connections_rejected_rate_limit: AtomicU64,
+    pub connections_rejected_capacity: AtomicU64,
+    /// Connections from denied networks, or from outside the allowed ones
+    pub connections_rejected_ip_filter: AtomicU64,
+    pub connections_rejected_banned: AtomicU64,
+    /// IP addresses banned for sending too many requests with bad headers
+    pub ips_banned: AtomicU64,
+    /// Requests not starting with the null bytes of the header
+    pub requests_bad_header: AtomicU64,
+    /// HTTP requests, most likely sent to the gossip port instead of the RPC port
+    pub requests_http: AtomicU64,
+    pub sessions_failed: AtomicU64,
+    pub tcp_probes_failed: AtomicU64,
+    pub quic_probes_failed: AtomicU64,
+    /// Sessions by processing time, bucketed by PROCESSING_TIME_BUCKETS_MS
+    pub processing_time_ms: [AtomicU64; PROCESSING_TIME_BUCKETS_MS.len() + 1],
+}
+
+impl IpEchoServerStats {
+    fn record_processing_time(&self, processing_time: Duration) {
+        let processing_time_ms = processing_time.as_millis() as u64;
+        let bucket = PROCESSING_TIME_BUCKETS_MS
+            .iter()
+            .position(|bound| processing_time_ms < *bound)
+            .unwrap_or(PROCESSING_TIME_BUCKETS_MS.len());
+        self.processing_time_ms[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The counters by metrics field name
+    pub fn counters(&self) -> Vec<(&'static str, u64)> {
+        const PROCESSING_TIME_FIELDS: [&str; PROCESSING_TIME_BUCKETS_MS.len() + 1] = [
+            "processing_time_lt_10ms",
+            "processing_time_lt_100ms",
+            "processing_time_lt_1000ms",
+            "processing_time_lt_5000ms",
+            "processing_time_ge_5000ms",
+        ];
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        let mut counters = vec![
+            ("connections_accepted", load(&self.connections_accepted)),
+            (
+                "connections_rejected_rate_limit",
+                load(&self.connections_rejected_rate_limit),
+            ),
+            (
+                "connections_rejected_capacity",
+                load(&self.connections_rejected_capacity),
+            ),
+            (
+                "connections_rejected_ip_filter",
+                load(&self.connections_rejected_ip_filter),
+            ),
+            (
+                "connections_rejected_banned",
+                load(&self.connections_rejected_banned),
+            ),
+            ("ips_banned", load(&self.ips_banned)),
+            ("requests_bad_header", load(&self.requests_bad_header)),
+            ("requests_http", load(&self.requests_http)),
+            ("sessions_failed", load(&self.sessions_failed)),
+            ("tcp_probes_failed", load(&self.tcp_probes_failed)),
+            ("quic_probes_failed", load(&self.quic_probes_failed)),
+        ];
+        counters.extend(
+            PROCESSING_TIME_FIELDS
+                .iter()
+                .zip(self.processing_time_ms.iter())
+                .map(|(name, counter)| (*name, load(counter))),
+        );
+        counters
+    }
+}
+
+/// Submits the counters' increments over each METRICS_REPORT_INTERVAL
+#[cfg(feature = "solana-metrics")]
+async fn report_stats(stats: Arc<IpEchoServerStats>) {
+    const METRICS_REPORT_INTERVAL: Duration = Duration::from_secs(10);
+
+    let mut last_counters = stats.counters();
+    loop {
+        tokio::time::sleep(METRICS_REPORT_INTERVAL).await;
+        let counters = stats.counters();
+        let mut point = solana_metrics::datapoint::DataPoint::new("ip-echo-server");
+        for ((name, value), (_, last_value)) in counters.iter().zip(&last_counters) {
+            point.add_field_i64(*name, value.saturating_sub(*last_value) as i64);
+        }
+        solana_metrics::submit(point, log::Level::Info);
+        last_counters = counters;
+    }
+}
 
-            debug!("Connection established to tcp/{}", *tcp_port);
-            let _ = tcp_stream.shutdown(std::net::Shutdown::Both);
+/// Counts the connections from each IP address in fixed windows of `rate_limit_interval`
+struct RateLimiter {
+    max_requests_per_ip: Option<usize>,
+    interval: Duration,
+    windows: HashMap<IpAddr, (/*start:*/ Instant, /*count:*/ usize)>,
+}
+
+impl RateLimiter {
+    // Stale windows are only purged once this many IP addresses are tracked
+    const PURGE_THRESHOLD: usize = 1024;
+
+    fn new(config: &IpEchoServerConfig) -> Self {
+        Self {
+            max_requests_per_ip: config.max_requests_per_ip,
+            interval: config.rate_limit_interval,
+            windows: HashMap::new(),
         }
     }
 
-    // "\0\0\0\0" header is added to ensure a valid response will never
-    // conflict with the first four bytes of a valid HTTP response.
-    let mut bytes = vec![0u8; ip_echo_server_reply_length()];
-    bincode::serialize_into(&mut bytes[HEADER_LENGTH..], &peer_addr.ip()).unwrap();
-    trace!("response: {:?}", bytes);
-    writer.write_all(&bytes).await
+    /// Counts a connection from `ip_addr`, returning false if it exceeds the limit
+    fn check(&mut self, ip_addr: IpAddr, now: Instant) -> bool {
+        let max_requests_per_ip = match self.max_requests_per_ip {
+            Some(max_requests_per_ip) => max_requests_per_ip,
+            None => return true,
+        };
+        let interval = self.interval;
+        if self.windows.len() >= Self::PURGE_THRESHOLD {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < interval);
+        }
+        let (start, count) = self.windows.entry(ip_addr).or_insert((now, 0));
+        if now.duration_since(*start) >= interval {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        *count <= max_requests_per_ip
+    }
+}
+
+/// A slot of the concurrent connection budget, released on drop
+struct ConnectionPermit(Arc<AtomicUsize>);
+
+impl ConnectionPermit {
+    fn try_acquire(connections: &Arc<AtomicUsize>, max_connections: usize) -> Option<Self> {
+        let mut current = connections.load(Ordering::Relaxed);
+        loop {
+            if current >= max_connections {
+                return None;
+            }
+            match connections.compare_exchange_weak(
+                current,
+                current + 1,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return Some(Self(connections.clone())),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+// Rejections sent at once, connections turned away past it are closed without one
+const MAX_CONCURRENT_REJECTIONS: usize = 64;
+// How long a peer turned away is given to read its rejection
+const REJECTION_TIMEOUT: Duration = Duration::from_secs(1);
+
+async fn send_rejection(mut socket: TcpStream, rejection: IpEchoServerRejection) -> io::Result<()> {
+    let mut bytes = REJECTION_HEADER.to_vec();
+    bincode::serialize_into(&mut bytes, &rejection).unwrap();
+    timeout(REJECTION_TIMEOUT, async {
+        socket.write_all(&bytes).await?;
+        socket.shutdown(std::net::Shutdown::Write)?;
+        // Closing with some of the request unread would reset the connection, possibly before
+        // the peer read the rejection
+        let max_length = ip_echo_server_request_length()
+            + IpEchoServerMessage::largest()
+                .serialize(IP_ECHO_SERVER_PROTOCOL_VERSION)
+                .len();
+        let mut request = vec![];
+        (&mut socket)
+            .take(max_length as u64)
+            .read_to_end(&mut request)
+            .await?;
+        io::Result::Ok(())
+    })
+    .await?
 }
 
-async fn run_echo_server(tcp_listener: std::net::TcpListener) {
+/// Tells the peer of `socket` why it is turned away, unless MAX_CONCURRENT_REJECTIONS are being
+/// sent already
+fn reject_connection(
+    socket: TcpStream,
+    peer_addr: SocketAddr,
+    rejection: IpEchoServerRejection,
+    rejections: &Arc<AtomicUsize>,
+) {
+    debug!("rejected connection from {:?}: {}", peer_addr, rejection);
+    if let Some(permit) = ConnectionPermit::try_acquire(rejections, MAX_CONCURRENT_REJECTIONS) {
+        runtime::Handle::current().spawn(async move {
+            if let Err(err) = send_rejection(socket, rejection).await {
+                debug!("failed to send rejection to {:?}: {:?}", peer_addr, err);
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn run_echo_server(
+    tcp_listeners: Vec<std::net::TcpListener>,
+    config: Arc<IpEchoServerConfig>,
+    stats: Arc<IpEchoServerStats>,
+) {
+    // The listeners share the rate limits and the connection budget, a peer reaching the server
+    // over both IPv4 and IPv6 or several interfaces is still one peer
+    let rate_limiter = Arc::new(Mutex::new(RateLimiter::new(&config)));
+    let connections = Arc::new(AtomicUsize::default());
+    let rejections = Arc::new(AtomicUsize::default());
+    #[cfg(feature = "solana-metrics")]
+    runtime::Handle::current().spawn(report_stats(stats.clone()));
+
+    join_all(tcp_listeners.into_iter().map(|tcp_listener| {
+        accept_connections(
+            tcp_listener,
+            config.clone(),
+            stats.clone(),
+            rate_limiter.clone(),
+            connections.clone(),
+            rejections.clone(),
+        )
+    }))
+    .await;
+}
+
+async fn accept_connections(
+    tcp_listener: std::net::TcpListener,
+    config: Arc<IpEchoServerConfig>,
+    stats: Arc<IpEchoServerStats>,
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    connections: Arc<AtomicUsize>,
+    rejections: Arc<AtomicUsize>,
+) {
     info!("bound to {:?}", tcp_listener.local_addr().unwrap());
     let tcp_listener =
         TcpListener::from_std(tcp_listener).expect("Failed to convert std::TcpListener");
@@ -130,10 +1243,76 @@ async fn run_echo_server(tcp_listener: std::net::TcpListener) {
     loop {
         match tcp_listener.accept().await {
             Ok((socket, peer_addr)) => {
+                let ip_addr = canonical_ip_addr(peer_addr.ip());
+                let now = Instant::now();
+                match config.ip_filter.check(&ip_addr, now) {
+                    Ok(()) => (),
+                    Err(IpFilterRejection::Denied) => {
+                        stats
+                            .connections_rejected_ip_filter
+                            .fetch_add(1, Ordering::Relaxed);
+                        reject_connection(
+                            socket,
+                            peer_addr,
+                            IpEchoServerRejection::Denied,
+                            &rejections,
+                        );
+                        continue;
+                    }
+                    Err(IpFilterRejection::Banned) => {
+                        stats
+                            .connections_rejected_banned
+                            .fetch_add(1, Ordering::Relaxed);
+                        reject_connection(
+                            socket,
+                            peer_addr,
+                            IpEchoServerRejection::Banned,
+                            &rejections,
+                        );
+                        continue;
+                    }
+                }
+                if !rate_limiter.lock().unwrap().check(ip_addr, now) {
+                    stats
+                        .connections_rejected_rate_limit
+                        .fetch_add(1, Ordering::Relaxed);
+                    reject_connection(
+                        socket,
+                        peer_addr,
+                        IpEchoServerRejection::RateLimited,
+                        &rejections,
+                    );
+                    continue;
+                }
+                let permit = match ConnectionPermit::try_acquire(
+                    &connections,
+                    config.max_concurrent_connections,
+                ) {
+                    Some(permit) => permit,
+                    None => {
+                        stats
+                            .connections_rejected_capacity
+                            .fetch_add(1, Ordering::Relaxed);
+                        reject_connection(
+                            socket,
+                            peer_addr,
+                            IpEchoServerRejection::Busy,
+                            &rejections,
+                        );
+                        continue;
+                    }
+                };
+                stats.connections_accepted.fetch_add(1, Ordering::Relaxed);
+                let config = config.clone();
+                let stats = stats.clone();
                 runtime::Handle::current().spawn(async move {
-                    if let Err(err) = process_connection(socket, peer_addr).await {
+                    let start = Instant::now();
+                    if let Err(err) = process_connection(socket, peer_addr, &config, &stats).await {
                         info!("session failed: {:?}", err);
+                        stats.sessions_failed.fetch_add(1, Ordering::Relaxed);
                     }
+                    stats.record_processing_time(start.elapsed());
+                    drop(permit);
                 });
             }
             Err(err) => warn!("listener accept failed: {:?}", err),
@@ -144,9 +1323,401 @@ async fn run_echo_server(tcp_listener: std::net::TcpListener) {
 /// Starts a simple TCP server on the given port that echos the IP address of any peer that
 /// connects.  Used by |get_public_ip_addr|
 pub fn ip_echo_server(tcp_listener: std::net::TcpListener) -> IpEchoServer {
-    tcp_listener.set_nonblocking(true).unwrap();
+    let runtime = Runtime::new().expect("Failed to create Runtime");
+    ip_echo_server_on(runtime.handle(), tcp_listener);
+    runtime
+}
+
+/// Like |ip_echo_server|, but spawns the server onto an existing runtime instead of a dedicated
+/// one.  The server runs for as long as the runtime does
+pub fn ip_echo_server_on(
+    handle: &runtime::Handle,
+    tcp_listener: std::net::TcpListener,
+) -> JoinHandle<()> {
+    ip_echo_server_with_config(
+        handle,
+        tcp_listener,
+        IpEchoServerConfig::default(),
+        Arc::default(),
+    )
+}
+
+/// Like |ip_echo_server|, reporting `cluster_identity` as the cluster it serves, signing its
+/// responses with the node's `identity_keypair` and pointing HTTP requests to the JSON RPC
+/// service at `rpc_addr`
+pub fn ip_echo_server_for_cluster(
+    tcp_listener: std::net::TcpListener,
+    cluster_identity: ClusterIdentity,
+    identity_keypair: Arc<Keypair>,
+    rpc_addr: Option<SocketAddr>,
+) -> IpEchoServer {
+    let runtime = Runtime::new().expect("Failed to create Runtime");
+    ip_echo_server_with_config(
+        runtime.handle(),
+        tcp_listener,
+        IpEchoServerConfig {
+            cluster_identity: Some(cluster_identity),
+            keypair: Some(identity_keypair),
+            rpc_addr,
+            ..IpEchoServerConfig::default()
+        },
+        Arc::default(),
+    );
+    runtime
+}
+
+/// Like |ip_echo_server_on|, with the timeouts and limits of `config`, counting the server's
+/// activity in `stats`, which builds with the `solana-metrics` feature also submit as metrics
+pub fn ip_echo_server_with_config(
+    handle: &runtime::Handle,
+    tcp_listener: std::net::TcpListener,
+    config: IpEchoServerConfig,
+    stats: Arc<IpEchoServerStats>,
+) -> JoinHandle<()> {
+    ip_echo_server_multi_with_config(handle, vec![tcp_listener], config, stats)
+}
 
+/// Like |ip_echo_server|, serving the peers connecting to any of `tcp_listeners` from a single
+/// runtime, e.g. one bound to 0.0.0.0 and one bound to [::] with IPV6_V6ONLY set so that both can
+/// share the port.  Dropping the runtime shuts them all down
+pub fn ip_echo_server_multi(tcp_listeners: Vec<std::net::TcpListener>) -> IpEchoServer {
     let runtime = Runtime::new().expect("Failed to create Runtime");
-    runtime.spawn(run_echo_server(tcp_listener));
+    ip_echo_server_multi_with_config(
+        runtime.handle(),
+        tcp_listeners,
+        IpEchoServerConfig::default(),
+        Arc::default(),
+    );
     runtime
 }
+
+/// Like |ip_echo_server_with_config|, serving the peers connecting to any of `tcp_listeners` from
+/// a single task.  The listeners share the rate limits and the connection budget of `config`
+pub fn ip_echo_server_multi_with_config(
+    handle: &runtime::Handle,
+    tcp_listeners: Vec<std::net::TcpListener>,
+    config: IpEchoServerConfig,
+    stats: Arc<IpEchoServerStats>,
+) -> JoinHandle<()> {
+    for tcp_listener in &tcp_listeners {
+        tcp_listener.set_nonblocking(true).unwrap();
+    }
+
+    handle.spawn(run_echo_server(tcp_listeners, Arc::new(config), stats))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_echo_server_response_serialization() {
+        let address = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let ports = Some(vec![true, false, true, false, false, true]);
+        for version in 0..=IP_ECHO_SERVER_PROTOCOL_VERSION {
+            let response = IpEchoServerResponse {
+                version,
+                address,
+                source_port: Some(8000),
+                tcp_ports_reachable: ports.clone(),
+                udp_ports_sent: ports.clone(),
+                udp_ports_confirmed: ports.clone(),
+                quic_ports_reachable: ports.clone(),
+                udp_ports_mapped: Some(vec![
+                    Some(SocketAddr::new(address, 8001)),
+                    None,
+                    Some(SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 7], 8002))),
+                ]),
+                cluster_identity: Some(ClusterIdentity {
+                    shred_version: 0x1b2c,
+                    genesis_hash: [7; 32],
+                }),
+                signature: Some((Pubkey::new_unique(), Signature::new(&[3; 64]))),
+            };
+            let mut bytes = response.serialize();
+            assert!(bytes.len() <= IpEchoServerResponse::largest(version).serialize().len());
+            // Padding is ignored
+            bytes.resize(bytes.len() + 8, 0);
+            let deserialized = IpEchoServerResponse::deserialize(version, &bytes).unwrap();
+            assert_eq!(deserialized.address, address);
+            assert_eq!(
+                deserialized.source_port,
+                response.source_port.filter(|_| version >= 6)
+            );
+            assert_eq!(deserialized.tcp_ports_reachable.is_some(), version >= 1);
+            assert_eq!(deserialized.udp_ports_confirmed.is_some(), version >= 2);
+            assert_eq!(deserialized.quic_ports_reachable.is_some(), version >= 4);
+            assert_eq!(
+                deserialized.udp_ports_mapped,
+                response.udp_ports_mapped.filter(|_| version >= 5)
+            );
+            assert_eq!(
+                deserialized.cluster_identity,
+                response.cluster_identity.filter(|_| version >= 8)
+            );
+            assert_eq!(
+                deserialized.signature,
+                response.signature.filter(|_| version >= 9)
+            );
+            // Older versions only hold the legacy fixed size port lists
+            let expected_ports = if version >= 3 {
+                ports.clone()
+            } else {
+                Some(vec![true, false, true, false])
+            };
+            if version >= 1 {
+                assert_eq!(deserialized.udp_ports_sent, expected_ports);
+            }
+        }
+
+        // Version 0 responses are a bare address
+        assert_eq!(
+            IpEchoServerResponse::deserialize(0, &bincode::serialize(&address).unwrap()).unwrap(),
+            IpEchoServerResponse {
+                version: 0,
+                address,
+                source_port: None,
+                tcp_ports_reachable: None,
+                udp_ports_sent: None,
+                udp_ports_confirmed: None,
+                quic_ports_reachable: None,
+                udp_ports_mapped: None,
+                cluster_identity: None,
+                signature: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_ip_echo_server_response_signature() {
+        let keypair = Keypair::new();
+        let challenge = [5; CHALLENGE_LENGTH];
+        let mut response = IpEchoServerResponse {
+            source_port: Some(8000),
+            tcp_ports_reachable: Some(vec![true]),
+            signature: None,
+            ..IpEchoServerResponse::largest(IP_ECHO_SERVER_PROTOCOL_VERSION)
+        };
+        assert!(!response.is_signed_by(&[keypair.pubkey()], &challenge));
+        response.sign(&keypair, &challenge);
+        assert!(response.is_signed_by(&[Pubkey::new_unique(), keypair.pubkey()], &challenge));
+        // Not by anyone else, for no other request
+        assert!(!response.is_signed_by(&[Pubkey::new_unique()], &challenge));
+        assert!(!response.is_signed_by(&[keypair.pubkey()], &[6; CHALLENGE_LENGTH]));
+
+        // What is signed is tagged, the challenge and response alone are not
+        let (_, signature) = response.signature.unwrap();
+        let mut untagged = challenge.to_vec();
+        untagged.extend(response.serialize_unsigned());
+        assert!(!signature.verify(keypair.pubkey().as_ref(), &untagged));
+        assert!(response
+            .signed_message(&challenge)
+            .starts_with(SIGNED_RESPONSE_DOMAIN));
+
+        // Nor once the address was tampered with
+        let mut bytes = response.serialize();
+        // The first byte of the address, past the u32 of its variant
+        bytes[4] ^= 1;
+        let tampered =
+            IpEchoServerResponse::deserialize(IP_ECHO_SERVER_PROTOCOL_VERSION, &bytes).unwrap();
+        assert_ne!(tampered.address, response.address);
+        assert!(!tampered.is_signed_by(&[keypair.pubkey()], &challenge));
+    }
+
+    #[test]
+    fn test_ip_echo_server_message_legacy() {
+        let msg = IpEchoServerMessage::new(&[1, 2], &[3, 4, 5, 6, 7]);
+        assert!(!msg.fits_legacy());
+        let legacy_msg = msg.legacy();
+        assert_eq!(legacy_msg.tcp_ports, [1, 2, 0, 0]);
+        assert_eq!(legacy_msg.udp_ports, [3, 4, 5, 6]);
+        // The request prefix keeps the length older servers read
+        assert_eq!(
+            bincode::serialized_size(&legacy_msg).unwrap() as usize,
+            2 * LEGACY_PORT_COUNT_PER_MESSAGE * std::mem::size_of::<u16>()
+        );
+        assert!(IpEchoServerMessage::from(legacy_msg).fits_legacy());
+    }
+
+    #[test]
+    fn test_ip_echo_server_message_serialization() {
+        let msg = IpEchoServerMessage::new(&[1, 2], &[3]).with_quic_ports(&[4, 5]);
+        assert_eq!(
+            IpEchoServerMessage::deserialize(4, &msg.serialize(4)).unwrap(),
+            msg
+        );
+        // Version 3 messages hold no QUIC ports and keep their layout
+        let bytes = msg.serialize(3);
+        assert_eq!(
+            bytes,
+            bincode::serialize(&(vec![1u16, 2], vec![3u16])).unwrap()
+        );
+        assert_eq!(
+            IpEchoServerMessage::deserialize(3, &bytes).unwrap(),
+            IpEchoServerMessage::new(&[1, 2], &[3])
+        );
+        assert!(
+            IpEchoServerMessage::largest().serialize(4).len()
+                > IpEchoServerMessage::largest().serialize(3).len()
+        );
+        // Version 9 messages end with the challenge
+        let msg = msg.with_random_challenge();
+        let bytes = msg.serialize(9);
+        assert_eq!(&bytes[bytes.len() - CHALLENGE_LENGTH..], msg.challenge());
+        assert_eq!(IpEchoServerMessage::deserialize(9, &bytes).unwrap(), msg);
+    }
+
+    #[test]
+    fn test_rate_limiter() {
+        let config = IpEchoServerConfig {
+            max_requests_per_ip: Some(2),
+            rate_limit_interval: Duration::from_secs(10),
+            ..IpEchoServerConfig::default()
+        };
+        let mut rate_limiter = RateLimiter::new(&config);
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let other_ip_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        let now = Instant::now();
+        assert!(rate_limiter.check(ip_addr, now));
+        assert!(rate_limiter.check(ip_addr, now));
+        assert!(!rate_limiter.check(ip_addr, now));
+        assert!(rate_limiter.check(other_ip_addr, now));
+        // The limit resets with the next window
+        assert!(rate_limiter.check(ip_addr, now + Duration::from_secs(10)));
+
+        // Nothing is limited by default
+        let mut rate_limiter = RateLimiter::new(&IpEchoServerConfig::default());
+        assert!((0..1_000).all(|_| rate_limiter.check(ip_addr, now)));
+    }
+
+    #[test]
+    fn test_ip_echo_server_rejection_length() {
+        for rejection in &[
+            IpEchoServerRejection::RateLimited,
+            IpEchoServerRejection::Busy,
+            IpEchoServerRejection::Denied,
+            IpEchoServerRejection::Banned,
+        ] {
+            let bytes = bincode::serialize(rejection).unwrap();
+            assert_eq!(bytes.len(), REJECTION_LENGTH);
+            assert_eq!(
+                bincode::deserialize::<IpEchoServerRejection>(&bytes).unwrap(),
+                *rejection
+            );
+        }
+    }
+
+    #[test]
+    fn test_connection_permit() {
+        let connections = Arc::new(AtomicUsize::default());
+        let permit = ConnectionPermit::try_acquire(&connections, 2).unwrap();
+        let _permit = ConnectionPermit::try_acquire(&connections, 2).unwrap();
+        assert!(ConnectionPermit::try_acquire(&connections, 2).is_none());
+        drop(permit);
+        assert_eq!(connections.load(Ordering::Relaxed), 1);
+        assert!(ConnectionPermit::try_acquire(&connections, 2).is_some());
+    }
+
+    #[test]
+    fn test_ip_echo_server_stats() {
+        let stats = IpEchoServerStats::default();
+        stats.record_processing_time(Duration::from_millis(5));
+        stats.record_processing_time(Duration::from_millis(100));
+        stats.record_processing_time(Duration::from_secs(60));
+        stats.requests_http.fetch_add(1, Ordering::Relaxed);
+        let counters: HashMap<_, _> = stats.counters().into_iter().collect();
+        assert_eq!(counters["requests_http"], 1);
+        assert_eq!(counters["processing_time_lt_10ms"], 1);
+        assert_eq!(counters["processing_time_lt_100ms"], 0);
+        assert_eq!(counters["processing_time_lt_1000ms"], 1);
+        assert_eq!(counters["processing_time_ge_5000ms"], 1);
+    }
+
+    #[test]
+    fn test_http_response() {
+        let response = http_response("POST", "http://10.0.0.1:8899", true);
+        let (head, body) = {
+            let mut parts = response.splitn(2, "\r\n\r\n");
+            (parts.next().unwrap(), parts.next().unwrap())
+        };
+        assert!(head.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(head.contains("Content-Type: application/json"));
+        assert!(head.contains(&format!("Content-Length: {}", body.len())));
+        assert_eq!(
+            body,
+            r#"{"jsonrpc":"2.0","error":{"code":-32600,"message":"This is the gossip entrypoint of a Solana node, not a JSON RPC endpoint. Its JSON RPC URL is http://10.0.0.1:8899"},"id":null}"#
+        );
+
+        let response = http_response("GET ", "http://10.0.0.1:8899", false);
+        assert!(response.contains("Content-Type: text/html"));
+        assert!(response.contains(
+            r#"The JSON RPC URL is probably <a href="http://10.0.0.1:8899">http://10.0.0.1:8899</a>"#
+        ));
+    }
+
+    #[test]
+    fn test_canonical_ip_addr() {
+        let ipv4_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(canonical_ip_addr(ipv4_addr), ipv4_addr);
+        let mapped_addr = IpAddr::V6(Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped());
+        assert_eq!(canonical_ip_addr(mapped_addr), ipv4_addr);
+        let ipv6_addr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+        assert_eq!(canonical_ip_addr(ipv6_addr), ipv6_addr);
+        // IPv4-compatible addresses are plain IPv6 addresses
+        let compatible_addr = IpAddr::V6(Ipv4Addr::new(10, 0, 0, 1).to_ipv6_compatible());
+        assert_eq!(canonical_ip_addr(compatible_addr), compatible_addr);
+    }
+
+    #[test]
+    fn test_confirm_udp_tokens_follows_punches() {
+        let runtime = runtime::Runtime::new().unwrap();
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        // The punching socket stands in for the public address a NAT maps `nat_port` to,
+        // nothing listens at that port itself
+        let punching_socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let nat_port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let echo_thread = {
+            let punching_socket = punching_socket.try_clone().unwrap();
+            punching_socket
+                .set_read_timeout(Some(Duration::from_secs(1)))
+                .unwrap();
+            std::thread::spawn(move || {
+                let mut buf = [0; UDP_TOKEN_LENGTH];
+                while let Ok((len, from)) = punching_socket.recv_from(&mut buf) {
+                    punching_socket.send_to(&buf[..len], from).unwrap();
+                }
+            })
+        };
+
+        let confirm = |follow_punches| {
+            runtime.block_on(async {
+                let udp_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+                let punch = bincode::serialize(&nat_port).unwrap();
+                assert_eq!(punch.len(), UDP_PUNCH_LENGTH);
+                punching_socket
+                    .send_to(&punch, udp_socket.local_addr().unwrap())
+                    .unwrap();
+                confirm_udp_tokens(
+                    &udp_socket,
+                    localhost,
+                    &[nat_port],
+                    &[rand::random()],
+                    follow_punches,
+                    Duration::from_millis(500),
+                )
+                .await
+            })
+        };
+        assert_eq!(
+            confirm(true),
+            vec![Some(punching_socket.local_addr().unwrap())]
+        );
+        // Older versions send to the port itself
+        assert_eq!(confirm(false), vec![None]);
+        echo_thread.join().unwrap();
+    }
+}
@@ -0,0 +1,328 @@
+//! Which peers may connect to the ip echo server.  Public entrypoints get scanned all day long,
+//! an `IpFilter` turns away connections from denied networks, or from outside the allowed ones,
+//! before any session is spawned for them, and bans the addresses that keep sending requests
+//! with bad headers for a while.  The lists can be replaced while the server runs
+use {
+    log::*,
+    std::{
+        collections::HashMap,
+        fmt,
+        net::IpAddr,
+        str::FromStr,
+        sync::{Mutex, RwLock},
+        time::{Duration, Instant},
+    },
+};
+
+/// A network in CIDR notation, like `10.0.0.0/8` or `2001:db8::/32`.  A bare IP address is the
+/// network of that address alone
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpCidr {
+    /// Fails if `prefix_len` is longer than the addresses of the family of `addr`
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, String> {
+        let max_prefix_len = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_prefix_len {
+            return Err(format!(
+                "Prefix length {} is longer than the {} bits of {}",
+                prefix_len, max_prefix_len, addr
+            ));
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    /// Whether `ip_addr` is within the network.  Addresses of the other family never are, IPv4
+    /// addresses mapped to IPv6 should be converted back beforehand
+    pub fn contains(&self, ip_addr: &IpAddr) -> bool {
+        match (self.addr, ip_addr) {
+            (IpAddr::V4(network), IpAddr::V4(ip_addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip_addr) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip_addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip_addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpCidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '/');
+        let addr: IpAddr = parts
+            .next()
+            .unwrap()
+            .parse()
+            .map_err(|err| format!("Invalid IP address in {}: {}", s, err))?;
+        match parts.next() {
+            Some(prefix_len) => {
+                let prefix_len = prefix_len
+                    .parse()
+                    .map_err(|err| format!("Invalid prefix length in {}: {}", s, err))?;
+                Self::new(addr, prefix_len)
+            }
+            None => Self::new(addr, if addr.is_ipv4() { 32 } else { 128 }),
+        }
+    }
+}
+
+impl fmt::Display for IpCidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Why an `IpFilter` turned a peer away
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpFilterRejection {
+    /// The address is in the denylist, or there is an allowlist and the address is not in it
+    Denied,
+    /// The address was banned for sending too many requests with bad headers
+    Banned,
+}
+
+/// Allowed and denied networks, and the addresses banned until some time
+#[derive(Debug, Default)]
+pub struct IpFilter {
+    /// Admitted networks, all of them when empty
+    allowlist: RwLock<Vec<IpCidr>>,
+    /// Networks turned away even when in the allowlist
+    denylist: RwLock<Vec<IpCidr>>,
+    bans: Mutex<Bans>,
+}
+
+#[derive(Debug, Default)]
+struct Bans {
+    /// When each banned address is admitted again
+    banned_until: HashMap<IpAddr, Instant>,
+    /// Bad headers of each address counted in windows of the ban duration
+    bad_headers: HashMap<IpAddr, (/*start:*/ Instant, /*count:*/ usize)>,
+}
+
+impl IpFilter {
+    // Expired bans and stale counts are only purged once this many IP addresses are tracked
+    const PURGE_THRESHOLD: usize = 1024;
+
+    pub fn new(allowlist: Vec<IpCidr>, denylist: Vec<IpCidr>) -> Self {
+        Self {
+            allowlist: RwLock::new(allowlist),
+            denylist: RwLock::new(denylist),
+            bans: Mutex::default(),
+        }
+    }
+
+    pub fn allowlist(&self) -> Vec<IpCidr> {
+        self.allowlist.read().unwrap().clone()
+    }
+
+    pub fn denylist(&self) -> Vec<IpCidr> {
+        self.denylist.read().unwrap().clone()
+    }
+
+    /// Replaces the allowed networks, an empty list admits everyone not denied.  Connections
+    /// already being served are left alone
+    pub fn set_allowlist(&self, allowlist: Vec<IpCidr>) {
+        info!("ip echo server allowlist: {:?}", allowlist);
+        *self.allowlist.write().unwrap() = allowlist;
+    }
+
+    /// Replaces the denied networks.  Connections already being served are left alone
+    pub fn set_denylist(&self, denylist: Vec<IpCidr>) {
+        info!("ip echo server denylist: {:?}", denylist);
+        *self.denylist.write().unwrap() = denylist;
+    }
+
+    /// Turns `ip_addr` away until `ban_duration` from now
+    pub fn ban(&self, ip_addr: IpAddr, ban_duration: Duration) {
+        let mut bans = self.bans.lock().unwrap();
+        bans.banned_until
+            .insert(ip_addr, Instant::now() + ban_duration);
+    }
+
+    /// Lifts the ban of `ip_addr`, returning whether it was banned
+    pub fn unban(&self, ip_addr: &IpAddr) -> bool {
+        let mut bans = self.bans.lock().unwrap();
+        bans.bad_headers.remove(ip_addr);
+        bans.banned_until
+            .remove(ip_addr)
+            .map_or(false, |banned_until| Instant::now() < banned_until)
+    }
+
+    /// The addresses banned as of now, with when their bans expire
+    pub fn banned(&self) -> Vec<(IpAddr, Instant)> {
+        let now = Instant::now();
+        let bans = self.bans.lock().unwrap();
+        bans.banned_until
+            .iter()
+            .filter(|(_, banned_until)| now < **banned_until)
+            .map(|(ip_addr, banned_until)| (*ip_addr, *banned_until))
+            .collect()
+    }
+
+    /// Whether a connection from `ip_addr` is admitted at `now`
+    pub fn check(&self, ip_addr: &IpAddr, now: Instant) -> Result<(), IpFilterRejection> {
+        if self
+            .denylist
+            .read()
+            .unwrap()
+            .iter()
+            .any(|cidr| cidr.contains(ip_addr))
+        {
+            return Err(IpFilterRejection::Denied);
+        }
+        {
+            let allowlist = self.allowlist.read().unwrap();
+            if !allowlist.is_empty() && !allowlist.iter().any(|cidr| cidr.contains(ip_addr)) {
+                return Err(IpFilterRejection::Denied);
+            }
+        }
+        match self.bans.lock().unwrap().banned_until.get(ip_addr) {
+            Some(banned_until) if now < *banned_until => Err(IpFilterRejection::Banned),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts a request with a bad header from `ip_addr`, banning the address for
+    /// `ban_duration` once it sent `max_bad_headers` of them within that duration.  Returns
+    /// whether the address got banned
+    pub(crate) fn record_bad_header(
+        &self,
+        ip_addr: IpAddr,
+        now: Instant,
+        max_bad_headers: usize,
+        ban_duration: Duration,
+    ) -> bool {
+        let mut bans = self.bans.lock().unwrap();
+        if bans.bad_headers.len() >= Self::PURGE_THRESHOLD {
+            bans.bad_headers
+                .retain(|_, (start, _)| now.saturating_duration_since(*start) < ban_duration);
+        }
+        if bans.banned_until.len() >= Self::PURGE_THRESHOLD {
+            bans.banned_until
+                .retain(|_, banned_until| now < *banned_until);
+        }
+        let (start, count) = bans.bad_headers.entry(ip_addr).or_insert((now, 0));
+        if now.saturating_duration_since(*start) >= ban_duration {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+        if *count < max_bad_headers {
+            return false;
+        }
+        bans.bad_headers.remove(&ip_addr);
+        bans.banned_until.insert(ip_addr, now + ban_duration);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::net::{Ipv4Addr, Ipv6Addr},
+    };
+
+    #[test]
+    fn test_ip_cidr() {
+        let cidr: IpCidr = "10.1.0.0/16".parse().unwrap();
+        assert!(cidr.contains(&IpAddr::V4(Ipv4Addr::new(10, 1, 200, 3))));
+        assert!(!cidr.contains(&IpAddr::V4(Ipv4Addr::new(10, 2, 0, 1))));
+        assert_eq!(cidr.to_string(), "10.1.0.0/16");
+        // Host bits of the network address are ignored
+        assert!("10.1.2.3/16"
+            .parse::<IpCidr>()
+            .unwrap()
+            .contains(&IpAddr::V4(Ipv4Addr::new(10, 1, 0, 1))));
+
+        let any: IpCidr = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains(&IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7))));
+        // Addresses of the other family are not within the network
+        assert!(!any.contains(&IpAddr::V6(Ipv6Addr::LOCALHOST)));
+
+        let host: IpCidr = "2001:db8::7".parse().unwrap();
+        assert_eq!(host.to_string(), "2001:db8::7/128");
+        assert!(host.contains(&"2001:db8::7".parse().unwrap()));
+        assert!(!host.contains(&"2001:db8::8".parse().unwrap()));
+        assert!("2001:db8::/32"
+            .parse::<IpCidr>()
+            .unwrap()
+            .contains(&"2001:db8:ffff::1".parse().unwrap()));
+
+        assert!("10.0.0.0/33".parse::<IpCidr>().is_err());
+        assert!("10.0.0/8".parse::<IpCidr>().is_err());
+        assert!("10.0.0.0/x".parse::<IpCidr>().is_err());
+    }
+
+    #[test]
+    fn test_ip_filter() {
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let other_ip_addr = IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1));
+        let now = Instant::now();
+        let ip_filter = IpFilter::default();
+        assert_eq!(ip_filter.check(&ip_addr, now), Ok(()));
+
+        ip_filter.set_allowlist(vec!["10.0.0.0/8".parse().unwrap()]);
+        assert_eq!(ip_filter.check(&ip_addr, now), Ok(()));
+        assert_eq!(
+            ip_filter.check(&other_ip_addr, now),
+            Err(IpFilterRejection::Denied)
+        );
+        // The denylist wins over the allowlist
+        ip_filter.set_denylist(vec![ip_addr.to_string().parse().unwrap()]);
+        assert_eq!(
+            ip_filter.check(&ip_addr, now),
+            Err(IpFilterRejection::Denied)
+        );
+        ip_filter.set_allowlist(vec![]);
+        ip_filter.set_denylist(vec![]);
+        assert_eq!(ip_filter.check(&other_ip_addr, now), Ok(()));
+
+        ip_filter.ban(other_ip_addr, Duration::from_secs(60));
+        assert_eq!(
+            ip_filter.check(&other_ip_addr, Instant::now()),
+            Err(IpFilterRejection::Banned)
+        );
+        assert_eq!(ip_filter.banned().len(), 1);
+        assert!(ip_filter.unban(&other_ip_addr));
+        assert!(!ip_filter.unban(&other_ip_addr));
+        assert_eq!(ip_filter.check(&other_ip_addr, Instant::now()), Ok(()));
+    }
+
+    #[test]
+    fn test_ip_filter_record_bad_header() {
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let ban_duration = Duration::from_secs(60);
+        let now = Instant::now();
+        let ip_filter = IpFilter::default();
+        assert!(!ip_filter.record_bad_header(ip_addr, now, 3, ban_duration));
+        assert!(!ip_filter.record_bad_header(ip_addr, now, 3, ban_duration));
+        // Bad headers only add up within the ban duration
+        let later = now + ban_duration;
+        assert!(!ip_filter.record_bad_header(ip_addr, later, 3, ban_duration));
+        assert!(!ip_filter.record_bad_header(ip_addr, later, 3, ban_duration));
+        assert_eq!(ip_filter.check(&ip_addr, later), Ok(()));
+        assert!(ip_filter.record_bad_header(ip_addr, later, 3, ban_duration));
+        assert_eq!(
+            ip_filter.check(&ip_addr, later),
+            Err(IpFilterRejection::Banned)
+        );
+        // The ban expires
+        assert_eq!(ip_filter.check(&ip_addr, later + ban_duration), Ok(()));
+    }
+}
@@ -1,21 +1,51 @@
 //! The `net_utils` module assists with networking
 use {
+    futures::future::join_all,
     log::*,
     rand::{thread_rng, Rng},
     socket2::{Domain, SockAddr, Socket, Type},
+    solana_sdk::pubkey::Pubkey,
     std::{
         collections::{BTreeMap, HashSet},
         io::{self, Read, Write},
-        net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
-        sync::{mpsc::channel, Arc, RwLock},
+        net::{
+            IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs,
+            UdpSocket,
+        },
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::channel,
+            Arc, Mutex, RwLock,
+        },
         time::{Duration, Instant},
     },
     url::Url,
 };
 
+mod error;
+mod hole_punch;
 mod ip_echo_server;
-use ip_echo_server::IpEchoServerMessage;
-pub use ip_echo_server::{ip_echo_server, IpEchoServer, MAX_PORT_COUNT_PER_MESSAGE};
+mod ip_filter;
+mod nat_type;
+mod port_mapping;
+mod quic;
+mod stun;
+pub use error::NetUtilsError;
+pub use hole_punch::{open_udp_holes, punch_udp_holes, UDP_HOLE_KEEPALIVE_INTERVAL};
+pub use ip_echo_server::{
+    ip_echo_server, ip_echo_server_for_cluster, ip_echo_server_multi,
+    ip_echo_server_multi_with_config, ip_echo_server_on, ip_echo_server_with_config,
+    ClusterIdentity, IpEchoServer, IpEchoServerConfig, IpEchoServerRejection, IpEchoServerStats,
+    MAX_PORT_COUNT_PER_MESSAGE, PROCESSING_TIME_BUCKETS_MS,
+};
+use ip_echo_server::{
+    max_port_count_per_message, max_quic_port_count_per_message, IpEchoServerMessage,
+    IpEchoServerResponse, IP_ECHO_SERVER_PROTOCOL_VERSION, LEGACY_PORT_COUNT_PER_MESSAGE,
+    REJECTION_HEADER, REJECTION_LENGTH, UDP_TOKEN_LENGTH,
+};
+pub use ip_filter::{IpCidr, IpFilter, IpFilterRejection};
+pub use nat_type::{detect_nat_type, NatType};
+pub use port_mapping::{PortMapper, PortMappingConfig, PortMappingProtocol};
 
 /// A data type representing a public Udp socket
 pub struct UdpSocketPair {
@@ -27,81 +57,823 @@ pub struct UdpSocketPair {
 pub type PortRange = (u16, u16);
 
 pub(crate) const HEADER_LENGTH: usize = 4;
-pub(crate) fn ip_echo_server_reply_length() -> usize {
-    let largest_ip_addr = IpAddr::from([0u16; 8]); // IPv6 variant
-    HEADER_LENGTH + bincode::serialized_size(&largest_ip_addr).unwrap() as usize
+pub(crate) fn ip_echo_server_reply_length(version: u8) -> usize {
+    HEADER_LENGTH + IpEchoServerResponse::largest(version).serialize().len()
+}
+
+/// How the ip echo client retries a request that failed on a transient error, such as a refused
+/// connection or a timeout.  Errors from the server itself are never retried
+#[derive(Clone, Debug, PartialEq)]
+pub struct IpEchoClientRetryConfig {
+    /// Attempts made before giving up, the first one included
+    pub attempts: usize,
+    /// Delay before the first retry, doubled before every further one
+    pub base_delay: Duration,
+    /// Upper bound of the random delay added to every retry, so that nodes restarted together
+    /// don't retry in lockstep
+    pub max_jitter: Duration,
+}
+
+impl Default for IpEchoClientRetryConfig {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_jitter: Duration::from_millis(250),
+        }
+    }
+}
+
+impl IpEchoClientRetryConfig {
+    /// A single attempt, failing on the first error
+    pub fn no_retry() -> Self {
+        Self {
+            attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the given retry, counting from 1
+    fn delay(&self, retry: usize) -> Duration {
+        let backoff = self
+            .base_delay
+            .checked_mul(1 << retry.saturating_sub(1).min(16))
+            .unwrap_or(self.base_delay);
+        let max_jitter_ms = self.max_jitter.as_millis() as u64;
+        let jitter_ms = if max_jitter_ms > 0 {
+            thread_rng().gen_range(0, max_jitter_ms + 1)
+        } else {
+            0
+        };
+        backoff + Duration::from_millis(jitter_ms)
+    }
+}
+
+/// The failed attempts of a request, reported together when the last one fails so that a
+/// firewall refusing every attempt can be told apart from a flaky entrypoint
+struct IpEchoRequestAttempts {
+    start: Instant,
+    failures: Vec<(Duration, NetUtilsError)>,
+}
+
+impl IpEchoRequestAttempts {
+    fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            failures: vec![],
+        }
+    }
+
+    /// Records a failed attempt, returning the delay before the next one or `None` if the
+    /// request should give up
+    fn record_failure(
+        &mut self,
+        ip_echo_server_addr: &SocketAddr,
+        err: NetUtilsError,
+        retry_config: &IpEchoClientRetryConfig,
+    ) -> Option<Duration> {
+        let retry = err.is_transient() && self.failures.len() + 1 < retry_config.attempts;
+        self.failures.push((self.start.elapsed(), err));
+        if !retry {
+            return None;
+        }
+        let delay = retry_config.delay(self.failures.len());
+        debug!(
+            "ip_echo_server request to {} failed: {}, retrying in {:?}",
+            ip_echo_server_addr,
+            self.failures.last().unwrap().1,
+            delay
+        );
+        Some(delay)
+    }
+
+    fn into_error(mut self, ip_echo_server_addr: &SocketAddr) -> NetUtilsError {
+        if self.failures.len() == 1 {
+            return self.failures.pop().unwrap().1;
+        }
+        NetUtilsError::RetriesExhausted {
+            addr: *ip_echo_server_addr,
+            failures: self.failures,
+        }
+    }
 }
 
 fn ip_echo_server_request(
     ip_echo_server_addr: &SocketAddr,
     msg: IpEchoServerMessage,
-) -> Result<IpAddr, String> {
-    let mut data = vec![0u8; ip_echo_server_reply_length()];
+    retry_config: &IpEchoClientRetryConfig,
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    let mut attempts = IpEchoRequestAttempts::new();
+    loop {
+        match ip_echo_server_request_negotiating_version(ip_echo_server_addr, &msg) {
+            Ok(response) => return Ok(response),
+            Err(err) => match attempts.record_failure(ip_echo_server_addr, err, retry_config) {
+                Some(delay) => std::thread::sleep(delay),
+                None => return Err(attempts.into_error(ip_echo_server_addr)),
+            },
+        }
+    }
+}
 
-    let timeout = Duration::new(5, 0);
-    TcpStream::connect_timeout(ip_echo_server_addr, timeout)
-        .and_then(|mut stream| {
-            // Start with HEADER_LENGTH null bytes to avoid looking like an HTTP GET/POST request
-            let mut bytes = vec![0; HEADER_LENGTH];
+fn ip_echo_server_request_negotiating_version(
+    ip_echo_server_addr: &SocketAddr,
+    msg: &IpEchoServerMessage,
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    ip_echo_server_request_opening_nat(ip_echo_server_addr, msg, &[])
+}
 
-            bytes.append(&mut bincode::serialize(&msg).expect("serialize IpEchoServerMessage"));
+/// Like `ip_echo_server_request_negotiating_version`, first sending a datagram from each of
+/// `udp_sockets` to the socket version 5 servers send their tokens from, so that NATs dropping
+/// datagrams from addresses these sockets never sent to let the tokens through.  Version 7
+/// servers send the tokens to the public addresses these datagrams came from
+fn ip_echo_server_request_opening_nat(
+    ip_echo_server_addr: &SocketAddr,
+    msg: &IpEchoServerMessage,
+    udp_sockets: &[&UdpSocket],
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    let mut version = IP_ECHO_SERVER_PROTOCOL_VERSION;
+    loop {
+        match ip_echo_server_request_with_version(ip_echo_server_addr, msg, version, udp_sockets) {
+            // Servers that predate version negotiation close the connection without a response
+            // to any version but 0, every later server negotiates or says why it turned the
+            // connection away.  So a bare EOF is taken for a legacy server once, rather than
+            // stepping down one version per connection
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && version > 0 => {
+                debug!(
+                    "{} did not respond to a version {} request, retrying with version 0",
+                    ip_echo_server_addr, version
+                );
+                version = 0;
+            }
+            result => return result,
+        }
+    }
+}
 
-            // End with '\n' to make this request look HTTP-ish and tickle an error response back
-            // from an HTTP server
-            bytes.push(b'\n');
+/// Like `ip_echo_server_request`, on the caller's runtime
+async fn ip_echo_server_request_async(
+    ip_echo_server_addr: &SocketAddr,
+    msg: IpEchoServerMessage,
+    retry_config: &IpEchoClientRetryConfig,
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    let mut attempts = IpEchoRequestAttempts::new();
+    loop {
+        match ip_echo_server_request_negotiating_version_async(ip_echo_server_addr, &msg).await {
+            Ok(response) => return Ok(response),
+            Err(err) => match attempts.record_failure(ip_echo_server_addr, err, retry_config) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(attempts.into_error(ip_echo_server_addr)),
+            },
+        }
+    }
+}
 
-            stream.set_read_timeout(Some(Duration::new(10, 0)))?;
-            stream.write_all(&bytes)?;
-            stream.shutdown(std::net::Shutdown::Write)?;
-            stream.read(data.as_mut_slice())
-        })
-        .and_then(|_| {
-            // It's common for users to accidentally confuse the validator's gossip port and JSON
-            // RPC port.  Attempt to detect when this occurs by looking for the standard HTTP
-            // response header and provide the user with a helpful error message
-            if data.len() < HEADER_LENGTH {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Response too short, received {} bytes", data.len()),
-                ));
+async fn ip_echo_server_request_negotiating_version_async(
+    ip_echo_server_addr: &SocketAddr,
+    msg: &IpEchoServerMessage,
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    let mut version = IP_ECHO_SERVER_PROTOCOL_VERSION;
+    loop {
+        match ip_echo_server_request_with_version_async(ip_echo_server_addr, msg, version).await {
+            // Like in `ip_echo_server_request_opening_nat`, a bare EOF means a legacy server
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && version > 0 => {
+                debug!(
+                    "{} did not respond to a version {} request, retrying with version 0",
+                    ip_echo_server_addr, version
+                );
+                version = 0;
             }
+            result => return result,
+        }
+    }
+}
 
-            let response_header: String =
-                data[0..HEADER_LENGTH].iter().map(|b| *b as char).collect();
-            if response_header != "\0\0\0\0" {
-                if response_header == "HTTP" {
-                    let http_response = data.iter().map(|b| *b as char).collect::<String>();
-                    return Err(io::Error::new(
-                        io::ErrorKind::Other,
-                        format!(
-                            "Invalid gossip entrypoint. {} looks to be an HTTP port: {}",
-                            ip_echo_server_addr, http_response
-                        ),
-                    ));
+/// The request prefix every version sends, the legacy fixed size port lists included
+fn ip_echo_server_request_prefix(msg: &IpEchoServerMessage, version: u8) -> Vec<u8> {
+    // Start with HEADER_LENGTH - 1 null bytes and the protocol version to avoid looking like an
+    // HTTP GET/POST request
+    let mut bytes = vec![0; HEADER_LENGTH];
+    bytes[HEADER_LENGTH - 1] = version;
+
+    bytes.append(&mut bincode::serialize(&msg.legacy()).expect("serialize IpEchoServerMessage"));
+
+    // End with '\n' to make this request look HTTP-ish and tickle an error response back from an
+    // HTTP server
+    bytes.push(b'\n');
+    bytes
+}
+
+/// The error for a server that sent REJECTION_HEADER followed by `rejection`
+fn rejected(ip_echo_server_addr: &SocketAddr, rejection: &[u8]) -> NetUtilsError {
+    match bincode::deserialize(rejection) {
+        Ok(rejection) => NetUtilsError::Rejected {
+            addr: *ip_echo_server_addr,
+            rejection,
+        },
+        Err(err) => err.into(),
+    }
+}
+
+/// Version 3 servers acknowledge the request prefix with their header and then wait for the full
+/// port lists, older servers reply right away
+fn negotiated_version(
+    ip_echo_server_addr: &SocketAddr,
+    msg: &IpEchoServerMessage,
+    data: &[u8],
+    version: u8,
+) -> u8 {
+    let negotiated_version =
+        if data.len() == HEADER_LENGTH && data[..HEADER_LENGTH - 1] == [0; HEADER_LENGTH - 1] {
+            data[HEADER_LENGTH - 1].min(version)
+        } else {
+            0
+        };
+    if negotiated_version < 3 && !msg.fits_legacy() {
+        warn!(
+            "{} only checks the first {} ports of each kind",
+            ip_echo_server_addr, LEGACY_PORT_COUNT_PER_MESSAGE
+        );
+    }
+    if negotiated_version < 4 && msg.has_quic_ports() {
+        warn!("{} does not check quic ports", ip_echo_server_addr);
+    }
+    negotiated_version
+}
+
+fn ip_echo_server_request_with_version(
+    ip_echo_server_addr: &SocketAddr,
+    msg: &IpEchoServerMessage,
+    version: u8,
+    udp_sockets: &[&UdpSocket],
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    let mut data = Vec::with_capacity(ip_echo_server_reply_length(version));
+
+    let timeout = Duration::new(5, 0);
+    let stream = TcpStream::connect_timeout(ip_echo_server_addr, timeout);
+    stream.map_err(NetUtilsError::from).and_then(|mut stream| {
+        stream.set_read_timeout(Some(Duration::new(10, 0)))?;
+        stream.write_all(&ip_echo_server_request_prefix(msg, version))?;
+
+        Read::by_ref(&mut stream)
+            .take(HEADER_LENGTH as u64)
+            .read_to_end(&mut data)?;
+        if data == REJECTION_HEADER {
+            let mut rejection = [0u8; REJECTION_LENGTH];
+            stream.read_exact(&mut rejection)?;
+            return Err(rejected(ip_echo_server_addr, &rejection));
+        }
+        if !data.is_empty() {
+            let negotiated_version = negotiated_version(ip_echo_server_addr, msg, &data, version);
+            if negotiated_version >= 5 {
+                let mut udp_port = [0u8; 2];
+                stream.read_exact(&mut udp_port)?;
+                let udp_port: u16 = bincode::deserialize(&udp_port).unwrap();
+                if udp_port != 0 {
+                    let server_udp_addr = SocketAddr::new(ip_echo_server_addr.ip(), udp_port);
+                    for udp_socket in udp_sockets {
+                        // Version 7 servers send the token of the socket's port back through
+                        // the hole this punches
+                        let punch = if negotiated_version >= 7 {
+                            bincode::serialize(&udp_socket.local_addr()?.port()).unwrap()
+                        } else {
+                            vec![0]
+                        };
+                        udp_socket.send_to(&punch, server_udp_addr)?;
+                    }
                 }
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    format!(
-                        "Invalid gossip entrypoint. {} provided an invalid response header: '{}'",
-                        ip_echo_server_addr, response_header
-                    ),
-                ));
             }
+            if negotiated_version >= 3 {
+                stream.write_all(&msg.serialize(negotiated_version))?;
+            }
+            stream.shutdown(std::net::Shutdown::Write)?;
+            // Replies are padded to the length of an IPv6 reply, read all of it rather than
+            // whatever the first segment carried
+            stream
+                .take((ip_echo_server_reply_length(negotiated_version) - data.len()) as u64)
+                .read_to_end(&mut data)?;
+        }
+        parse_ip_echo_server_response(ip_echo_server_addr, &data, version)
+    })
+}
 
-            bincode::deserialize(&data[HEADER_LENGTH..]).map_err(|err| {
-                io::Error::new(
-                    io::ErrorKind::Other,
-                    format!("Failed to deserialize: {:?}", err),
-                )
-            })
-        })
-        .map_err(|err| err.to_string())
+/// Like `ip_echo_server_request_with_version`, on the caller's runtime
+async fn ip_echo_server_request_with_version_async(
+    ip_echo_server_addr: &SocketAddr,
+    msg: &IpEchoServerMessage,
+    version: u8,
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        time::timeout,
+    };
+
+    let mut data = Vec::with_capacity(ip_echo_server_reply_length(version));
+
+    let mut stream = timeout(
+        Duration::new(5, 0),
+        tokio::net::TcpStream::connect(ip_echo_server_addr),
+    )
+    .await??;
+    timeout(Duration::new(10, 0), async {
+        stream
+            .write_all(&ip_echo_server_request_prefix(msg, version))
+            .await?;
+
+        (&mut stream)
+            .take(HEADER_LENGTH as u64)
+            .read_to_end(&mut data)
+            .await?;
+        if data == REJECTION_HEADER {
+            let mut rejection = [0u8; REJECTION_LENGTH];
+            stream.read_exact(&mut rejection).await?;
+            return Err(rejected(ip_echo_server_addr, &rejection));
+        }
+        if !data.is_empty() {
+            let negotiated_version = negotiated_version(ip_echo_server_addr, msg, &data, version);
+            if negotiated_version >= 5 {
+                // Only needed to open NATs for the tokens, which this client doesn't ask for
+                let mut udp_port = [0u8; 2];
+                stream.read_exact(&mut udp_port).await?;
+            }
+            if negotiated_version >= 3 {
+                stream.write_all(&msg.serialize(negotiated_version)).await?;
+            }
+            stream.shutdown(std::net::Shutdown::Write)?;
+            stream
+                .take((ip_echo_server_reply_length(negotiated_version) - data.len()) as u64)
+                .read_to_end(&mut data)
+                .await?;
+        }
+        Result::<(), NetUtilsError>::Ok(())
+    })
+    .await??;
+    parse_ip_echo_server_response(ip_echo_server_addr, &data, version)
+}
+
+fn parse_ip_echo_server_response(
+    ip_echo_server_addr: &SocketAddr,
+    data: &[u8],
+    version: u8,
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    // It's common for users to accidentally confuse the validator's gossip port and JSON RPC
+    // port.  Attempt to detect when this occurs by looking for the standard HTTP response header
+    // and provide the user with a helpful error message
+    if data.len() < HEADER_LENGTH {
+        return Err(NetUtilsError::ResponseTooShort(data.len()));
+    }
+
+    let response_header: String = data[0..HEADER_LENGTH].iter().map(|b| *b as char).collect();
+    if data[..HEADER_LENGTH - 1] != [0; HEADER_LENGTH - 1] || data[HEADER_LENGTH - 1] > version {
+        if response_header == "HTTP" {
+            let http_response = data.iter().map(|b| *b as char).collect::<String>();
+            return Err(NetUtilsError::HttpPort {
+                addr: *ip_echo_server_addr,
+                response: http_response,
+            });
+        }
+        return Err(NetUtilsError::InvalidHeader {
+            addr: *ip_echo_server_addr,
+            header: response_header,
+        });
+    }
+
+    // The server may have negotiated down to an older version
+    Ok(IpEchoServerResponse::deserialize(
+        data[HEADER_LENGTH - 1],
+        &data[HEADER_LENGTH..],
+    )?)
 }
 
 /// Determine the public IP address of this machine by asking an ip_echo_server at the given
 /// address
-pub fn get_public_ip_addr(ip_echo_server_addr: &SocketAddr) -> Result<IpAddr, String> {
-    ip_echo_server_request(ip_echo_server_addr, IpEchoServerMessage::default())
+pub fn get_public_ip_addr(ip_echo_server_addr: &SocketAddr) -> Result<IpAddr, NetUtilsError> {
+    get_public_ip_addr_and_protocol_version(ip_echo_server_addr).map(|(address, _)| address)
+}
+
+/// Like `get_public_ip_addr`, also returning the protocol version negotiated with the
+/// ip_echo_server, which tells which reachability checks it supports
+pub fn get_public_ip_addr_and_protocol_version(
+    ip_echo_server_addr: &SocketAddr,
+) -> Result<(IpAddr, u8), NetUtilsError> {
+    ip_echo_server_request(
+        ip_echo_server_addr,
+        IpEchoServerMessage::default(),
+        &IpEchoClientRetryConfig::default(),
+    )
+    .map(|response| (response.address, response.version))
+}
+
+/// Determine the public IP address of this machine and the port its connections to the
+/// ip_echo_server at the given address come from, as rewritten by any NAT in between.  The
+/// server must be at least version 6
+pub fn get_public_socket_addr(
+    ip_echo_server_addr: &SocketAddr,
+) -> Result<SocketAddr, NetUtilsError> {
+    let response = ip_echo_server_request(
+        ip_echo_server_addr,
+        IpEchoServerMessage::default(),
+        &IpEchoClientRetryConfig::default(),
+    )?;
+    let source_port = response
+        .source_port
+        .ok_or(NetUtilsError::UnsupportedVersion {
+            addr: *ip_echo_server_addr,
+            version: response.version,
+            capability: "report the source port",
+        })?;
+    Ok(SocketAddr::new(response.address, source_port))
+}
+
+/// Like `get_public_ip_addr`, trusting the answer only if the ip_echo_server signed it with one of
+/// `expected_pubkeys`, so that a man in the middle can't have this machine advertise an address
+/// of his choosing.  The server must be at least version 9 and given a keypair
+pub fn get_public_ip_addr_signed_by(
+    ip_echo_server_addr: &SocketAddr,
+    expected_pubkeys: &[Pubkey],
+) -> Result<IpAddr, NetUtilsError> {
+    signed_ip_echo_server_request(
+        ip_echo_server_addr,
+        expected_pubkeys,
+        &IpEchoClientRetryConfig::default(),
+    )
+    .map(|response| response.address)
+}
+
+/// Make a request with a random challenge to the ip_echo_server at the given address, accepting
+/// only a response signed by one of `expected_pubkeys`
+fn signed_ip_echo_server_request(
+    ip_echo_server_addr: &SocketAddr,
+    expected_pubkeys: &[Pubkey],
+    retry_config: &IpEchoClientRetryConfig,
+) -> Result<IpEchoServerResponse, NetUtilsError> {
+    let msg = IpEchoServerMessage::default().with_random_challenge();
+    let challenge = msg.challenge().to_vec();
+    let response = ip_echo_server_request(ip_echo_server_addr, msg, retry_config)?;
+    if response.version < 9 {
+        return Err(NetUtilsError::UnsupportedVersion {
+            addr: *ip_echo_server_addr,
+            version: response.version,
+            capability: "sign its responses",
+        });
+    }
+    match &response.signature {
+        None => Err(NetUtilsError::Unsupported {
+            addr: *ip_echo_server_addr,
+            capability: "sign its responses",
+        }),
+        Some((pubkey, _)) if !response.is_signed_by(expected_pubkeys, &challenge) => {
+            Err(NetUtilsError::InvalidSignature {
+                addr: *ip_echo_server_addr,
+                expected: expected_pubkeys.to_vec(),
+                claimed: *pubkey,
+            })
+        }
+        Some(_) => Ok(response),
+    }
+}
+
+/// Ask the ip_echo_server at the given address which cluster it serves.  The server must be at
+/// least version 8 and know its cluster
+pub fn get_cluster_identity(
+    ip_echo_server_addr: &SocketAddr,
+) -> Result<ClusterIdentity, NetUtilsError> {
+    let response = ip_echo_server_request(
+        ip_echo_server_addr,
+        IpEchoServerMessage::default(),
+        &IpEchoClientRetryConfig::default(),
+    )?;
+    if response.version < 8 {
+        return Err(NetUtilsError::UnsupportedVersion {
+            addr: *ip_echo_server_addr,
+            version: response.version,
+            capability: "report the cluster it serves",
+        });
+    }
+    response.cluster_identity.ok_or(NetUtilsError::Unsupported {
+        addr: *ip_echo_server_addr,
+        capability: "report the cluster it serves",
+    })
+}
+
+/// Like `get_cluster_identity`, only accepting an answer signed by one of `expected_pubkeys`, so
+/// that whoever sits between this node and the server can't hand out another cluster's identity
+pub fn get_cluster_identity_signed_by(
+    ip_echo_server_addr: &SocketAddr,
+    expected_pubkeys: &[Pubkey],
+    retry_config: &IpEchoClientRetryConfig,
+) -> Result<ClusterIdentity, NetUtilsError> {
+    let response =
+        signed_ip_echo_server_request(ip_echo_server_addr, expected_pubkeys, retry_config)?;
+    response.cluster_identity.ok_or(NetUtilsError::Unsupported {
+        addr: *ip_echo_server_addr,
+        capability: "report the cluster it serves",
+    })
+}
+
+/// Ask all the ip_echo_servers at the given addresses concurrently which cluster they serve, see
+/// `get_cluster_identity_signed_by`.  Every server gets a single attempt, and those that haven't
+/// answered by `timeout` fail with `NetUtilsError::Timeout`.  The answers are in the order of
+/// `ip_echo_server_addrs`
+pub fn get_cluster_identities_signed_by(
+    ip_echo_server_addrs: &[SocketAddr],
+    expected_pubkeys: &[Pubkey],
+    timeout: Duration,
+) -> Vec<(SocketAddr, Result<ClusterIdentity, NetUtilsError>)> {
+    let deadline = Instant::now() + timeout;
+    let (sender, receiver) = channel();
+    let mut answers: Vec<_> = ip_echo_server_addrs
+        .iter()
+        .map(|ip_echo_server_addr| (*ip_echo_server_addr, None))
+        .collect();
+    for (i, (ip_echo_server_addr, answer)) in answers.iter_mut().enumerate() {
+        let ip_echo_server_addr = *ip_echo_server_addr;
+        let expected_pubkeys = expected_pubkeys.to_vec();
+        let sender = sender.clone();
+        // A server that doesn't answer in time is left to its thread, which ends on the
+        // request's own timeouts
+        let spawned = std::thread::Builder::new()
+            .name("solClusterIdent".to_string())
+            .spawn(move || {
+                let _ = sender.send((
+                    i,
+                    get_cluster_identity_signed_by(
+                        &ip_echo_server_addr,
+                        &expected_pubkeys,
+                        &IpEchoClientRetryConfig::no_retry(),
+                    ),
+                ));
+            });
+        if let Err(err) = spawned {
+            *answer = Some(Err(NetUtilsError::Io(err)));
+        }
+    }
+    drop(sender);
+
+    while answers.iter().any(|(_, answer)| answer.is_none()) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(remaining) {
+            Ok((i, answer)) => answers[i].1 = Some(answer),
+            Err(_) => break,
+        }
+    }
+    answers
+        .into_iter()
+        .map(|(ip_echo_server_addr, answer)| {
+            let answer = answer.unwrap_or_else(|| {
+                Err(NetUtilsError::Timeout(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{} did not report its cluster in time", ip_echo_server_addr),
+                )))
+            });
+            (ip_echo_server_addr, answer)
+        })
+        .collect()
+}
+
+/// Determine the shred version of the cluster the entrypoint at the given address serves, see
+/// `get_cluster_identity`
+pub fn get_cluster_shred_version(entrypoint_addr: &SocketAddr) -> Result<u16, NetUtilsError> {
+    get_cluster_identity(entrypoint_addr).map(|cluster_identity| cluster_identity.shred_version)
+}
+
+/// Like `get_public_ip_addr`, retrying transient failures as `retry_config` says.  If every
+/// attempt fails the error lists each of them
+pub fn get_public_ip_addr_with_retry_config(
+    ip_echo_server_addr: &SocketAddr,
+    retry_config: &IpEchoClientRetryConfig,
+) -> Result<IpAddr, NetUtilsError> {
+    ip_echo_server_request(
+        ip_echo_server_addr,
+        IpEchoServerMessage::default(),
+        retry_config,
+    )
+    .map(|response| response.address)
+}
+
+/// Like `get_public_ip_addr`, on the caller's runtime.  Dropping the returned future cancels the
+/// request
+pub async fn get_public_ip_addr_async(
+    ip_echo_server_addr: &SocketAddr,
+) -> Result<IpAddr, NetUtilsError> {
+    ip_echo_server_request_async(
+        ip_echo_server_addr,
+        IpEchoServerMessage::default(),
+        &IpEchoClientRetryConfig::default(),
+    )
+    .await
+    .map(|response| response.address)
+}
+
+/// The public IP address most of the ip_echo_servers asked by `get_public_ip_addr_with_quorum`
+/// agreed on, with the answers of the others
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicIpAddrConsensus {
+    pub address: IpAddr,
+    /// Servers that agreed on `address`
+    pub agreeing: Vec<SocketAddr>,
+    /// Servers that answered another address, as happens behind NAT hairpinning or when a
+    /// server is misconfigured or lying
+    pub dissenting: Vec<(SocketAddr, IpAddr)>,
+    /// Servers that failed to answer, with their errors
+    pub failed: Vec<(SocketAddr, String)>,
+}
+
+/// Determine the public IP address of this machine by asking all the ip_echo_servers at the
+/// given addresses concurrently, requiring at least `quorum` of them and a strict majority of the
+/// ones that answered to agree on it, so that a single misconfigured or malicious server can't
+/// hand out a wrong address
+pub fn get_public_ip_addr_with_quorum(
+    ip_echo_server_addrs: &[SocketAddr],
+    quorum: usize,
+) -> Result<PublicIpAddrConsensus, NetUtilsError> {
+    if quorum == 0 || quorum > ip_echo_server_addrs.len() {
+        return Err(NetUtilsError::InvalidArgument(format!(
+            "Invalid quorum {} of {} ip echo servers",
+            quorum,
+            ip_echo_server_addrs.len()
+        )));
+    }
+
+    let (sender, receiver) = channel();
+    for ip_echo_server_addr in ip_echo_server_addrs {
+        let ip_echo_server_addr = *ip_echo_server_addr;
+        let sender = sender.clone();
+        std::thread::Builder::new()
+            .name("solIpEchoQuorum".to_string())
+            .spawn(move || {
+                let _ = sender.send((
+                    ip_echo_server_addr,
+                    get_public_ip_addr(&ip_echo_server_addr),
+                ));
+            })?;
+    }
+    drop(sender);
+
+    // Preserve the order of `ip_echo_server_addrs` in the answers, whichever answered first
+    let mut answers: Vec<_> = receiver.iter().collect();
+    answers.sort_by_key(|(ip_echo_server_addr, _)| {
+        ip_echo_server_addrs
+            .iter()
+            .position(|addr| addr == ip_echo_server_addr)
+    });
+
+    let mut votes = BTreeMap::<IpAddr, Vec<SocketAddr>>::new();
+    let mut failed = vec![];
+    for (ip_echo_server_addr, answer) in answers {
+        match answer {
+            Ok(address) => votes.entry(address).or_default().push(ip_echo_server_addr),
+            Err(err) => failed.push((ip_echo_server_addr, err.to_string())),
+        }
+    }
+    let (address, agreeing) = votes
+        .iter()
+        .max_by_key(|(_, agreeing)| agreeing.len())
+        .map(|(address, agreeing)| (*address, agreeing.clone()))
+        .ok_or_else(|| {
+            NetUtilsError::NoConsensus(format!("No ip echo server answered: {:?}", failed))
+        })?;
+    let dissenting: Vec<_> = votes
+        .into_iter()
+        .filter(|(other_address, _)| *other_address != address)
+        .flat_map(|(other_address, servers)| {
+            servers
+                .into_iter()
+                .map(move |ip_echo_server_addr| (ip_echo_server_addr, other_address))
+        })
+        .collect();
+
+    if agreeing.len() < quorum {
+        return Err(NetUtilsError::NoConsensus(format!(
+            "Only {} of the required {} ip echo servers agree on public address {}, \
+             dissenting: {:?}, failed: {:?}",
+            agreeing.len(),
+            quorum,
+            address,
+            dissenting,
+            failed
+        )));
+    }
+    // Without a strict majority, the address with as many votes could be the right one just as
+    // well
+    if agreeing.len() * 2 <= agreeing.len() + dissenting.len() {
+        return Err(NetUtilsError::NoConsensus(format!(
+            "Only {} of the {} ip echo servers that answered agree on public address {}, \
+             dissenting: {:?}",
+            agreeing.len(),
+            agreeing.len() + dissenting.len(),
+            address,
+            dissenting
+        )));
+    }
+    for (ip_echo_server_addr, other_address) in &dissenting {
+        warn!(
+            "ip echo server {} answered public address {}, while {} others answered {}",
+            ip_echo_server_addr,
+            other_address,
+            agreeing.len(),
+            address
+        );
+    }
+    Ok(PublicIpAddrConsensus {
+        address,
+        agreeing,
+        dissenting,
+        failed,
+    })
+}
+
+// STUN clients retransmit unanswered requests with doubling timeouts, starting at 500ms
+const STUN_REQUEST_TIMEOUTS_MS: [u64; 3] = [500, 1_000, 2_000];
+
+/// Sends a STUN Binding request to `stun_server_addr`, returning the address the server saw it
+/// come from
+fn stun_binding_request(stun_server_addr: &SocketAddr) -> Result<SocketAddr, NetUtilsError> {
+    let bind_addr = match stun_server_addr {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let udp_socket = UdpSocket::bind(bind_addr).map_err(|source| NetUtilsError::Bind {
+        addr: bind_addr,
+        source,
+    })?;
+    let transaction_id = stun::new_transaction_id();
+    let request = stun::binding_request(&transaction_id);
+    let mut buf = [0u8; 512];
+    for timeout_ms in STUN_REQUEST_TIMEOUTS_MS.iter() {
+        udp_socket.send_to(&request, stun_server_addr)?;
+        let deadline = Instant::now() + Duration::from_millis(*timeout_ms);
+        // Skip datagrams that don't answer this request until the timeout
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            if timeout == Duration::default() {
+                break;
+            }
+            udp_socket.set_read_timeout(Some(timeout))?;
+            match udp_socket.recv_from(&mut buf) {
+                Ok((len, _from)) => {
+                    if let Some(mapped_addr) =
+                        stun::parse_binding_response(&buf[..len], &transaction_id)
+                    {
+                        return Ok(mapped_addr);
+                    }
+                }
+                Err(err)
+                    if err.kind() == io::ErrorKind::WouldBlock
+                        || err.kind() == io::ErrorKind::TimedOut =>
+                {
+                    break
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+    Err(NetUtilsError::Timeout(io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("No STUN response from {}", stun_server_addr),
+    )))
+}
+
+/// Determine the public IP address of this machine by asking the STUN servers at the given
+/// addresses in turn, for when no ip_echo_server is reachable, such as while bootstrapping a new
+/// cluster.  The first server to answer decides the address
+pub fn get_public_ip_addr_with_stun(
+    stun_server_addrs: &[SocketAddr],
+) -> Result<IpAddr, NetUtilsError> {
+    if stun_server_addrs.is_empty() {
+        return Err(NetUtilsError::InvalidArgument(
+            "No STUN servers given".to_string(),
+        ));
+    }
+    let mut errors = vec![];
+    for stun_server_addr in stun_server_addrs {
+        match stun_binding_request(stun_server_addr) {
+            Ok(mapped_addr) => return Ok(mapped_addr.ip()),
+            Err(err) => {
+                warn!("STUN request to {} failed: {}", stun_server_addr, err);
+                errors.push(format!("{}: {}", stun_server_addr, err));
+            }
+        }
+    }
+    Err(NetUtilsError::NoConsensus(format!(
+        "No STUN server answered: {}",
+        errors.join("; ")
+    )))
+}
+
+/// Like `get_public_ip_addr`, falling back to `get_public_ip_addr_with_stun` if the
+/// ip_echo_server can't be reached
+pub fn get_public_ip_addr_with_stun_fallback(
+    ip_echo_server_addr: &SocketAddr,
+    stun_server_addrs: &[SocketAddr],
+) -> Result<IpAddr, NetUtilsError> {
+    get_public_ip_addr(ip_echo_server_addr).or_else(|ip_echo_err| {
+        warn!(
+            "Unable to get the public IP address from {}: {}, asking STUN servers instead",
+            ip_echo_server_addr, ip_echo_err
+        );
+        get_public_ip_addr_with_stun(stun_server_addrs).map_err(|stun_err| {
+            NetUtilsError::NoConsensus(format!("{}. {}", ip_echo_err, stun_err))
+        })
+    })
 }
 
 // Checks if any of the provided TCP/UDP ports are not reachable by the machine at
@@ -113,26 +885,55 @@ fn do_verify_reachable_ports(
     ip_echo_server_addr: &SocketAddr,
     tcp_listeners: Vec<(u16, TcpListener)>,
     udp_sockets: &[&UdpSocket],
+    quic_sockets: &[&UdpSocket],
     timeout: u64,
     udp_retry_count: usize,
-) -> bool {
+    retry_config: &IpEchoClientRetryConfig,
+) -> Result<(), NetUtilsError> {
     info!(
         "Checking that tcp ports {:?} are reachable from {:?}",
         tcp_listeners, ip_echo_server_addr
     );
+    for local_addr in tcp_listeners
+        .iter()
+        .filter_map(|(_, tcp_listener)| tcp_listener.local_addr().ok())
+        .chain(
+            udp_sockets
+                .iter()
+                .chain(quic_sockets)
+                .filter_map(|udp_socket| udp_socket.local_addr().ok()),
+        )
+    {
+        if local_addr.is_ipv4() && ip_echo_server_addr.is_ipv6() {
+            warn!(
+                "{} is bound to IPv4 but {} will probe the IPv6 address of this node, bind to an \
+                 IPv6 (dual-stack) address instead",
+                local_addr, ip_echo_server_addr
+            );
+        }
+    }
 
     let tcp_ports: Vec<_> = tcp_listeners.iter().map(|(port, _)| *port).collect();
-    let _ = ip_echo_server_request(
+    let tcp_response = ip_echo_server_request(
         ip_echo_server_addr,
         IpEchoServerMessage::new(&tcp_ports, &[]),
+        retry_config,
     )
-    .map_err(|err| warn!("ip_echo_server request failed: {}", err));
-
-    let mut ok = true;
+    .map_err(|err| warn!("ip_echo_server request failed: {}", err))
+    .ok();
+    // Check as many UDP and QUIC ports per request as the server's version allows.  If it
+    // failed to answer, the QUIC requests negotiate their version again
+    let server_version = tcp_response.as_ref().map(|response| response.version);
+    let max_udp_ports_per_request = max_port_count_per_message(server_version.unwrap_or_default());
+    let max_quic_ports_per_request =
+        max_quic_port_count_per_message(server_version.unwrap_or(IP_ECHO_SERVER_PROTOCOL_VERSION));
+    let tcp_ports_reachable = tcp_response.and_then(|response| response.tcp_ports_reachable);
+
+    let mut blocked_tcp_ports = vec![];
     let timeout = Duration::from_secs(timeout);
 
     // Wait for a connection to open on each TCP port
-    for (port, tcp_listener) in tcp_listeners {
+    for (i, (port, tcp_listener)) in tcp_listeners.into_iter().enumerate() {
         let (sender, receiver) = channel();
         let listening_addr = tcp_listener.local_addr().unwrap();
         let thread_handle = std::thread::spawn(move || {
@@ -144,7 +945,18 @@ fn do_verify_reachable_ports(
                 None => warn!("tcp incoming failed"),
             }
         });
-        match receiver.recv_timeout(timeout) {
+        // Don't wait out the timeout on ports the server already failed to connect to
+        let reported_unreachable = tcp_ports_reachable
+            .as_ref()
+            .map_or(false, |reachable| reachable.get(i) == Some(&false));
+        let result = if reported_unreachable {
+            Err(format!("{} could not connect to it", ip_echo_server_addr))
+        } else {
+            receiver
+                .recv_timeout(timeout)
+                .map_err(|err| err.to_string())
+        };
+        match result {
             Ok(_) => {
                 info!("tcp/{} is reachable", port);
             }
@@ -157,17 +969,25 @@ fn do_verify_reachable_ports(
                 // nonblocking-status of sockets because of lack of getter, only the setter...
                 // So, to close the thread cleanly, just connect from here.
                 // ref: https://github.com/rust-lang/rust/issues/31615
-                TcpStream::connect_timeout(&listening_addr, timeout).unwrap();
-                ok = false;
+                TcpStream::connect_timeout(&connectable_addr(listening_addr), timeout).unwrap();
+                blocked_tcp_ports.push(port);
             }
         }
         // ensure to reap the thread
         thread_handle.join().unwrap();
     }
 
-    if !ok {
+    if !blocked_tcp_ports.is_empty() {
         // No retries for TCP, abort on the first failure
-        return ok;
+        error!(
+            "tcp ports {:?} are not reachable from {:?}",
+            blocked_tcp_ports, ip_echo_server_addr
+        );
+        return Err(NetUtilsError::Unreachable {
+            addr: *ip_echo_server_addr,
+            protocol: "tcp",
+            ports: blocked_tcp_ports,
+        });
     }
 
     let mut udp_ports: BTreeMap<_, _> = BTreeMap::new();
@@ -186,8 +1006,11 @@ fn do_verify_reachable_ports(
         ip_echo_server_addr
     );
 
-    'outer: for checked_ports_and_sockets in udp_ports.chunks(MAX_PORT_COUNT_PER_MESSAGE) {
-        ok = false;
+    for checked_ports_and_sockets in udp_ports.chunks(max_udp_ports_per_request) {
+        let mut unreachable_ports: Vec<_> = checked_ports_and_sockets
+            .iter()
+            .map(|(port, _)| *port)
+            .collect();
 
         for udp_remaining_retry in (0_usize..udp_retry_count).rev() {
             let (checked_ports, checked_socket_iter) = (
@@ -201,19 +1024,16 @@ fn do_verify_reachable_ports(
                     .flatten(),
             );
 
-            let _ = ip_echo_server_request(
-                ip_echo_server_addr,
-                IpEchoServerMessage::new(&[], &checked_ports),
-            )
-            .map_err(|err| warn!("ip_echo_server request failed: {}", err));
-
-            // Spawn threads at once!
+            // Spawn threads at once!  They listen before the request is made since version 2
+            // servers wait for their tokens to be echoed back before replying
             let reachable_ports = Arc::new(RwLock::new(HashSet::new()));
+            let request_done = Arc::new(AtomicBool::new(false));
             let thread_handles: Vec<_> = checked_socket_iter
                 .map(|udp_socket| {
                     let port = udp_socket.local_addr().unwrap().port();
                     let udp_socket = udp_socket.try_clone().expect("Unable to clone udp socket");
                     let reachable_ports = reachable_ports.clone();
+                    let request_done = request_done.clone();
                     std::thread::spawn(move || {
                         let start = Instant::now();
 
@@ -221,22 +1041,30 @@ fn do_verify_reachable_ports(
                         udp_socket
                             .set_read_timeout(Some(Duration::from_millis(250)))
                             .unwrap();
+                        let mut buf = [0; UDP_TOKEN_LENGTH];
                         loop {
-                            if reachable_ports.read().unwrap().contains(&port)
+                            // Keep echoing until the server has replied, it resends tokens whose
+                            // echo was lost
+                            if (request_done.load(Ordering::Relaxed)
+                                && reachable_ports.read().unwrap().contains(&port))
                                 || Instant::now().duration_since(start) >= timeout
                             {
                                 break;
                             }
 
-                            let recv_result = udp_socket.recv(&mut [0; 1]);
+                            let recv_result = udp_socket.recv_from(&mut buf);
                             debug!(
                                 "Waited for incoming datagram on udp/{}: {:?}",
                                 port, recv_result
                             );
 
-                            if recv_result.is_ok() {
+                            if let Ok((len, from)) = recv_result {
+                                if len == UDP_TOKEN_LENGTH {
+                                    let _ = udp_socket
+                                        .send_to(&buf, from)
+                                        .map_err(|err| warn!("udp/{} echo failed: {}", port, err));
+                                }
                                 reachable_ports.write().unwrap().insert(port);
-                                break;
                             }
                         }
                         udp_socket.set_read_timeout(original_read_timeout).unwrap();
@@ -244,6 +1072,18 @@ fn do_verify_reachable_ports(
                 })
                 .collect();
 
+            let udp_ports_confirmed = ip_echo_server_request(
+                ip_echo_server_addr,
+                IpEchoServerMessage::new(&[], &checked_ports),
+                retry_config,
+            )
+            .map_err(|err| warn!("ip_echo_server request failed: {}", err))
+            .ok()
+            .and_then(|response| {
+                udp_ports_confirmed(ip_echo_server_addr, &checked_ports, response)
+            });
+            request_done.store(true, Ordering::Relaxed);
+
             // Now join threads!
             // Separate from the above by collect()-ing as an intermediately step to make the iterator
             // eager not lazy so that joining happens here at once after creating bunch of threads
@@ -252,13 +1092,17 @@ fn do_verify_reachable_ports(
                 thread.join().unwrap();
             }
 
-            let reachable_ports = reachable_ports.read().unwrap().clone();
+            let reachable_ports = reachable_udp_ports(
+                &checked_ports,
+                udp_ports_confirmed,
+                reachable_ports.read().unwrap().clone(),
+            );
             if reachable_ports.len() == checked_ports.len() {
                 info!(
                     "checked udp ports: {:?}, reachable udp ports: {:?}",
                     checked_ports, reachable_ports
                 );
-                ok = true;
+                unreachable_ports.clear();
                 break;
             } else if udp_remaining_retry > 0 {
                 // Might have lost a UDP packet, retry a couple times
@@ -268,49 +1112,557 @@ fn do_verify_reachable_ports(
                 );
                 error!("There are some udp ports with no response!! Retrying...");
             } else {
-                error!("Maximum retry count is reached....");
-                break 'outer;
+                unreachable_ports.retain(|port| !reachable_ports.contains(port));
+                error!(
+                    "Maximum retry count is reached, udp ports {:?} are not reachable from {:?}",
+                    unreachable_ports, ip_echo_server_addr
+                );
             }
         }
+        if !unreachable_ports.is_empty() {
+            return Err(NetUtilsError::Unreachable {
+                addr: *ip_echo_server_addr,
+                protocol: "udp",
+                ports: unreachable_ports,
+            });
+        }
     }
 
-    ok
+    if !quic_sockets.is_empty() {
+        verify_reachable_quic_ports(
+            ip_echo_server_addr,
+            quic_sockets,
+            max_quic_ports_per_request,
+            timeout,
+            retry_config,
+        )?;
+    }
+    Ok(())
 }
 
-pub fn verify_reachable_ports(
+/// The UDP ports of a request that echoed back the server's token, since version 2, warning
+/// about the ports the server failed to send its token to
+fn udp_ports_confirmed(
     ip_echo_server_addr: &SocketAddr,
-    tcp_listeners: Vec<(u16, TcpListener)>,
-    udp_sockets: &[&UdpSocket],
-) -> bool {
-    do_verify_reachable_ports(
-        ip_echo_server_addr,
-        tcp_listeners,
-        udp_sockets,
-        DEFAULT_TIMEOUT_SECS,
-        DEFAULT_RETRY_COUNT,
-    )
-}
-
-pub fn parse_port_or_addr(optstr: Option<&str>, default_addr: SocketAddr) -> SocketAddr {
-    if let Some(addrstr) = optstr {
-        if let Ok(port) = addrstr.parse() {
-            let mut addr = default_addr;
-            addr.set_port(port);
-            addr
-        } else if let Ok(addr) = addrstr.parse() {
-            addr
-        } else {
-            default_addr
+    checked_ports: &[u16],
+    response: IpEchoServerResponse,
+) -> Option<Vec<bool>> {
+    if let Some(udp_ports_sent) = response.udp_ports_sent {
+        for (port, _) in checked_ports
+            .iter()
+            .zip(udp_ports_sent.iter())
+            .filter(|(_, sent)| !**sent)
+        {
+            warn!(
+                "{} failed to send a datagram to udp/{}",
+                ip_echo_server_addr, port
+            );
         }
-    } else {
-        default_addr
     }
+    response.udp_ports_confirmed
 }
 
-pub fn parse_port_range(port_range: &str) -> Option<PortRange> {
-    let ports: Vec<&str> = port_range.split('-').collect();
-    if ports.len() != 2 {
-        return None;
+/// Trusts the server's confirmation of the round trip over a datagram seen locally, which older
+/// servers send without knowing whether it arrived
+fn reachable_udp_ports(
+    checked_ports: &[u16],
+    udp_ports_confirmed: Option<Vec<bool>>,
+    ports_received: HashSet<u16>,
+) -> HashSet<u16> {
+    match udp_ports_confirmed {
+        Some(udp_ports_confirmed) => checked_ports
+            .iter()
+            .zip(udp_ports_confirmed.iter())
+            .filter(|(_, confirmed)| **confirmed)
+            .map(|(port, _)| *port)
+            .collect(),
+        None => ports_received,
+    }
+}
+
+/// Checks that the QUIC ports of `quic_sockets` are reachable from `ip_echo_server_addr`,
+/// answering the server's probes the way the QUIC endpoints yet to be started on them would
+fn verify_reachable_quic_ports(
+    ip_echo_server_addr: &SocketAddr,
+    quic_sockets: &[&UdpSocket],
+    max_quic_ports_per_request: usize,
+    timeout: Duration,
+    retry_config: &IpEchoClientRetryConfig,
+) -> Result<(), NetUtilsError> {
+    let mut quic_ports: BTreeMap<_, _> = BTreeMap::new();
+    quic_sockets.iter().for_each(|quic_socket| {
+        let port = quic_socket.local_addr().unwrap().port();
+        quic_ports
+            .entry(port)
+            .or_insert_with(Vec::new)
+            .push(quic_socket);
+    });
+    let quic_ports: Vec<_> = quic_ports.into_iter().collect();
+
+    info!(
+        "Checking that quic ports {:?} are reachable from {:?}",
+        quic_ports.iter().map(|(port, _)| port).collect::<Vec<_>>(),
+        ip_echo_server_addr
+    );
+
+    if max_quic_ports_per_request == 0 {
+        warn!(
+            "{} is too old to probe quic ports, skipping the check of quic ports {:?}",
+            ip_echo_server_addr,
+            quic_ports.iter().map(|(port, _)| port).collect::<Vec<_>>()
+        );
+        return Ok(());
+    }
+    for checked_ports_and_sockets in quic_ports.chunks(max_quic_ports_per_request) {
+        let checked_ports: Vec<_> = checked_ports_and_sockets
+            .iter()
+            .map(|(port, _)| *port)
+            .collect();
+
+        let request_done = Arc::new(AtomicBool::new(false));
+        let thread_handles: Vec<_> = checked_ports_and_sockets
+            .iter()
+            .flat_map(|(_, sockets)| sockets)
+            .map(|quic_socket| {
+                let port = quic_socket.local_addr().unwrap().port();
+                let quic_socket = quic_socket.try_clone().expect("Unable to clone udp socket");
+                let request_done = request_done.clone();
+                std::thread::spawn(move || {
+                    let start = Instant::now();
+                    let original_read_timeout = quic_socket.read_timeout().unwrap();
+                    quic_socket
+                        .set_read_timeout(Some(Duration::from_millis(250)))
+                        .unwrap();
+                    let mut buf = [0; quic::PROBE_DATAGRAM_SIZE];
+                    // Keep answering until the server has replied, it resends unanswered probes
+                    while !request_done.load(Ordering::Relaxed)
+                        && Instant::now().duration_since(start) < timeout
+                    {
+                        if let Ok((len, from)) = quic_socket.recv_from(&mut buf) {
+                            if let Some(answer) = quic::answer_probe(&buf[..len]) {
+                                debug!("Answering a probe from {} on quic/{}", from, port);
+                                let _ = quic_socket.send_to(&answer, from).map_err(|err| {
+                                    warn!("quic/{} probe answer failed: {}", port, err)
+                                });
+                            }
+                        }
+                    }
+                    quic_socket.set_read_timeout(original_read_timeout).unwrap();
+                })
+            })
+            .collect();
+
+        let response = ip_echo_server_request(
+            ip_echo_server_addr,
+            IpEchoServerMessage::new(&[], &[]).with_quic_ports(&checked_ports),
+            retry_config,
+        );
+        request_done.store(true, Ordering::Relaxed);
+        for thread in thread_handles {
+            thread.join().unwrap();
+        }
+
+        let quic_ports_reachable = match response {
+            Ok(response) => match response.quic_ports_reachable {
+                Some(quic_ports_reachable) => quic_ports_reachable,
+                None => {
+                    warn!(
+                        "{} is too old to probe quic ports, skipping the check of quic ports {:?}",
+                        ip_echo_server_addr, checked_ports
+                    );
+                    continue;
+                }
+            },
+            Err(err) => {
+                error!("ip_echo_server request failed: {}", err);
+                return Err(err);
+            }
+        };
+        let unreachable_ports: Vec<_> = checked_ports
+            .iter()
+            .zip(quic_ports_reachable.iter().chain(std::iter::repeat(&false)))
+            .filter(|(_, reachable)| !**reachable)
+            .map(|(port, _)| *port)
+            .collect();
+        if !unreachable_ports.is_empty() {
+            error!(
+                "quic ports {:?} are not reachable from {:?}",
+                unreachable_ports, ip_echo_server_addr
+            );
+            return Err(NetUtilsError::Unreachable {
+                addr: *ip_echo_server_addr,
+                protocol: "quic",
+                ports: unreachable_ports,
+            });
+        }
+        info!("checked quic ports: {:?}, all reachable", checked_ports);
+    }
+    Ok(())
+}
+
+// Unspecified addresses are not connectable everywhere, connect to the loopback address of the
+// same family instead
+fn connectable_addr(addr: SocketAddr) -> SocketAddr {
+    match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), addr.port())
+        }
+        IpAddr::V6(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), addr.port())
+        }
+        _ => addr,
+    }
+}
+
+pub fn verify_reachable_ports(
+    ip_echo_server_addr: &SocketAddr,
+    tcp_listeners: Vec<(u16, TcpListener)>,
+    udp_sockets: &[&UdpSocket],
+) -> bool {
+    verify_reachable_ports_with_retry_config(
+        ip_echo_server_addr,
+        tcp_listeners,
+        udp_sockets,
+        &IpEchoClientRetryConfig::default(),
+    )
+}
+
+/// Like `verify_reachable_ports`, retrying the requests to the ip_echo_server that fail
+/// transiently as `retry_config` says
+pub fn verify_reachable_ports_with_retry_config(
+    ip_echo_server_addr: &SocketAddr,
+    tcp_listeners: Vec<(u16, TcpListener)>,
+    udp_sockets: &[&UdpSocket],
+    retry_config: &IpEchoClientRetryConfig,
+) -> bool {
+    do_verify_reachable_ports(
+        ip_echo_server_addr,
+        tcp_listeners,
+        udp_sockets,
+        &[],
+        DEFAULT_TIMEOUT_SECS,
+        DEFAULT_RETRY_COUNT,
+        retry_config,
+    )
+    .is_ok()
+}
+
+/// Like `verify_reachable_ports`, also checking that the QUIC endpoints to be served on
+/// `quic_sockets` are reachable.  The ip_echo_server probes each QUIC port for the Version
+/// Negotiation packet any QUIC endpoint answers a packet of an unknown version with, which this
+/// node answers until its endpoints start.  Servers older than the QUIC probes only get the
+/// other ports checked
+pub fn verify_reachable_ports_with_quic(
+    ip_echo_server_addr: &SocketAddr,
+    tcp_listeners: Vec<(u16, TcpListener)>,
+    udp_sockets: &[&UdpSocket],
+    quic_sockets: &[&UdpSocket],
+) -> bool {
+    check_reachable_ports(
+        ip_echo_server_addr,
+        tcp_listeners,
+        udp_sockets,
+        quic_sockets,
+    )
+    .is_ok()
+}
+
+/// Like `verify_reachable_ports_with_quic`, returning why the check failed, such as the ports
+/// the ip_echo_server's probes did not get through to
+pub fn check_reachable_ports(
+    ip_echo_server_addr: &SocketAddr,
+    tcp_listeners: Vec<(u16, TcpListener)>,
+    udp_sockets: &[&UdpSocket],
+    quic_sockets: &[&UdpSocket],
+) -> Result<(), NetUtilsError> {
+    do_verify_reachable_ports(
+        ip_echo_server_addr,
+        tcp_listeners,
+        udp_sockets,
+        quic_sockets,
+        DEFAULT_TIMEOUT_SECS,
+        DEFAULT_RETRY_COUNT,
+        &IpEchoClientRetryConfig::default(),
+    )
+}
+
+/// Like `verify_reachable_ports`, on the caller's runtime.  The sockets are waited on as tokio
+/// sockets, each wait within a timeout, and dropping the returned future stops the checks
+pub async fn verify_reachable_ports_async(
+    ip_echo_server_addr: SocketAddr,
+    tcp_listeners: Vec<(u16, TcpListener)>,
+    udp_sockets: Vec<UdpSocket>,
+) -> bool {
+    do_verify_reachable_ports_async(
+        &ip_echo_server_addr,
+        tcp_listeners,
+        udp_sockets,
+        Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        DEFAULT_RETRY_COUNT,
+        &IpEchoClientRetryConfig::default(),
+    )
+    .await
+    .is_ok()
+}
+
+/// Switches the sockets of an async check back to blocking mode once the check is done or
+/// dropped, as the clones of them the caller may hold share their mode
+struct RestoreBlockingMode {
+    tcp_listeners: Vec<TcpListener>,
+    udp_sockets: Vec<UdpSocket>,
+}
+
+impl Drop for RestoreBlockingMode {
+    fn drop(&mut self) {
+        for tcp_listener in &self.tcp_listeners {
+            let _ = tcp_listener.set_nonblocking(false);
+        }
+        for udp_socket in &self.udp_sockets {
+            let _ = udp_socket.set_nonblocking(false);
+        }
+    }
+}
+
+/// Echoes the tokens the ip_echo_server sends to `udp_socket` back to it, until the request is
+/// done and a token arrived or `timeout` expires
+async fn echo_udp_tokens(
+    port: u16,
+    udp_socket: &tokio::net::UdpSocket,
+    timeout: Duration,
+    request_done: &AtomicBool,
+    ports_received: &Mutex<HashSet<u16>>,
+) {
+    let start = Instant::now();
+    let mut buf = [0; UDP_TOKEN_LENGTH];
+    // Keep echoing until the server has replied, it resends tokens whose echo was lost
+    while !(request_done.load(Ordering::Relaxed) && ports_received.lock().unwrap().contains(&port))
+        && start.elapsed() < timeout
+    {
+        let recv_result =
+            tokio::time::timeout(Duration::from_millis(250), udp_socket.recv_from(&mut buf)).await;
+        if let Ok(Ok((len, from))) = recv_result {
+            if len == UDP_TOKEN_LENGTH {
+                let _ = udp_socket
+                    .send_to(&buf, from)
+                    .await
+                    .map_err(|err| warn!("udp/{} echo failed: {}", port, err));
+            }
+            ports_received.lock().unwrap().insert(port);
+        }
+    }
+}
+
+async fn do_verify_reachable_ports_async(
+    ip_echo_server_addr: &SocketAddr,
+    tcp_listeners: Vec<(u16, TcpListener)>,
+    udp_sockets: Vec<UdpSocket>,
+    timeout: Duration,
+    udp_retry_count: usize,
+    retry_config: &IpEchoClientRetryConfig,
+) -> Result<(), NetUtilsError> {
+    // Dropped after the tokio sockets, declared before them
+    let _restore_blocking_mode = RestoreBlockingMode {
+        tcp_listeners: tcp_listeners
+            .iter()
+            .map(|(_, tcp_listener)| tcp_listener.try_clone())
+            .collect::<io::Result<_>>()?,
+        udp_sockets: udp_sockets
+            .iter()
+            .map(UdpSocket::try_clone)
+            .collect::<io::Result<_>>()?,
+    };
+    let tcp_listeners = tcp_listeners
+        .into_iter()
+        .map(|(port, tcp_listener)| {
+            tcp_listener.set_nonblocking(true)?;
+            Ok((port, tokio::net::TcpListener::from_std(tcp_listener)?))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let udp_sockets = udp_sockets
+        .into_iter()
+        .map(|udp_socket| {
+            udp_socket.set_nonblocking(true)?;
+            tokio::net::UdpSocket::from_std(udp_socket)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    info!(
+        "Checking that tcp ports {:?} are reachable from {:?}",
+        tcp_listeners, ip_echo_server_addr
+    );
+    let tcp_ports: Vec<_> = tcp_listeners.iter().map(|(port, _)| *port).collect();
+    let tcp_response = ip_echo_server_request_async(
+        ip_echo_server_addr,
+        IpEchoServerMessage::new(&tcp_ports, &[]),
+        retry_config,
+    )
+    .await
+    .map_err(|err| warn!("ip_echo_server request failed: {}", err))
+    .ok();
+    let max_udp_ports_per_request =
+        max_port_count_per_message(tcp_response.as_ref().map_or(0, |response| response.version));
+    let tcp_ports_reachable = tcp_response.and_then(|response| response.tcp_ports_reachable);
+
+    // The server's connections wait in the listeners' backlogs until accepted
+    let accepted = join_all(
+        tcp_listeners
+            .iter()
+            .enumerate()
+            .map(|(i, (port, tcp_listener))| {
+                let reported_unreachable = tcp_ports_reachable
+                    .as_ref()
+                    .map_or(false, |reachable| reachable.get(i) == Some(&false));
+                async move {
+                    let result = if reported_unreachable {
+                        Err(format!("{} could not connect to it", ip_echo_server_addr))
+                    } else {
+                        match tokio::time::timeout(timeout, tcp_listener.accept()).await {
+                            Ok(Ok(_)) => Ok(()),
+                            Ok(Err(err)) => Err(err.to_string()),
+                            Err(err) => Err(err.to_string()),
+                        }
+                    };
+                    match result {
+                        Ok(()) => info!("tcp/{} is reachable", port),
+                        Err(ref err) => error!(
+                            "Received no response at tcp/{}, check your port configuration: {}",
+                            port, err
+                        ),
+                    }
+                    result.is_ok()
+                }
+            }),
+    )
+    .await;
+    let blocked_tcp_ports: Vec<_> = tcp_ports
+        .iter()
+        .zip(accepted)
+        .filter(|(_, accepted)| !accepted)
+        .map(|(port, _)| *port)
+        .collect();
+    if !blocked_tcp_ports.is_empty() {
+        error!(
+            "tcp ports {:?} are not reachable from {:?}",
+            blocked_tcp_ports, ip_echo_server_addr
+        );
+        return Err(NetUtilsError::Unreachable {
+            addr: *ip_echo_server_addr,
+            protocol: "tcp",
+            ports: blocked_tcp_ports,
+        });
+    }
+
+    let mut udp_ports: BTreeMap<_, Vec<_>> = BTreeMap::new();
+    for udp_socket in &udp_sockets {
+        udp_ports
+            .entry(udp_socket.local_addr()?.port())
+            .or_default()
+            .push(udp_socket);
+    }
+    let udp_ports: Vec<_> = udp_ports.into_iter().collect();
+    info!(
+        "Checking that udp ports {:?} are reachable from {:?}",
+        udp_ports.iter().map(|(port, _)| port).collect::<Vec<_>>(),
+        ip_echo_server_addr
+    );
+
+    for checked_ports_and_sockets in udp_ports.chunks(max_udp_ports_per_request.max(1)) {
+        let checked_ports: Vec<_> = checked_ports_and_sockets
+            .iter()
+            .map(|(port, _)| *port)
+            .collect();
+        let mut reachable_ports = HashSet::new();
+        for udp_remaining_retry in (0..udp_retry_count).rev() {
+            // Listen while the request is made, version 2 servers wait for their tokens to be
+            // echoed back before replying
+            let request_done = AtomicBool::new(false);
+            let ports_received = Mutex::new(HashSet::new());
+            let request = async {
+                let response = ip_echo_server_request_async(
+                    ip_echo_server_addr,
+                    IpEchoServerMessage::new(&[], &checked_ports),
+                    retry_config,
+                )
+                .await;
+                request_done.store(true, Ordering::Relaxed);
+                response
+            };
+            let (request_done, ports_received) = (&request_done, &ports_received);
+            let echoes = join_all(
+                checked_ports_and_sockets
+                    .iter()
+                    .flat_map(|(port, sockets)| {
+                        let port = *port;
+                        sockets.iter().map(move |udp_socket| {
+                            echo_udp_tokens(port, udp_socket, timeout, request_done, ports_received)
+                        })
+                    }),
+            );
+            let (response, _) = tokio::join!(request, echoes);
+
+            let udp_ports_confirmed = response
+                .map_err(|err| warn!("ip_echo_server request failed: {}", err))
+                .ok()
+                .and_then(|response| {
+                    udp_ports_confirmed(ip_echo_server_addr, &checked_ports, response)
+                });
+            reachable_ports = reachable_udp_ports(
+                &checked_ports,
+                udp_ports_confirmed,
+                ports_received.lock().unwrap().clone(),
+            );
+            if reachable_ports.len() == checked_ports.len() {
+                info!(
+                    "checked udp ports: {:?}, reachable udp ports: {:?}",
+                    checked_ports, reachable_ports
+                );
+                break;
+            } else if udp_remaining_retry > 0 {
+                error!(
+                    "checked udp ports: {:?}, reachable udp ports: {:?}, retrying",
+                    checked_ports, reachable_ports
+                );
+            }
+        }
+        let unreachable_ports: Vec<_> = checked_ports
+            .into_iter()
+            .filter(|port| !reachable_ports.contains(port))
+            .collect();
+        if !unreachable_ports.is_empty() {
+            error!(
+                "udp ports {:?} are not reachable from {:?}",
+                unreachable_ports, ip_echo_server_addr
+            );
+            return Err(NetUtilsError::Unreachable {
+                addr: *ip_echo_server_addr,
+                protocol: "udp",
+                ports: unreachable_ports,
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_port_or_addr(optstr: Option<&str>, default_addr: SocketAddr) -> SocketAddr {
+    if let Some(addrstr) = optstr {
+        if let Ok(port) = addrstr.parse() {
+            let mut addr = default_addr;
+            addr.set_port(port);
+            addr
+        } else if let Ok(addr) = addrstr.parse() {
+            addr
+        } else {
+            default_addr
+        }
+    } else {
+        default_addr
+    }
+}
+
+pub fn parse_port_range(port_range: &str) -> Option<PortRange> {
+    let ports: Vec<&str> = port_range.split('-').collect();
+    if ports.len() != 2 {
+        return None;
     }
 
     let start_port = ports[0].parse();
@@ -328,6 +1680,11 @@ pub fn parse_port_range(port_range: &str) -> Option<PortRange> {
 }
 
 pub fn parse_host(host: &str) -> Result<IpAddr, String> {
+    // IPv6 literals, bare or bracketed, do not need resolving
+    if let Ok(ip_addr) = host.trim_start_matches('[').trim_end_matches(']').parse() {
+        return Ok(ip_addr);
+    }
+
     // First, check if the host syntax is valid. This check is needed because addresses
     // such as `("localhost:1234", 0)` will resolve to IPs on some networks.
     let parsed_url = Url::parse(&format!("http://{}", host)).map_err(|e| e.to_string())?;
@@ -368,19 +1725,26 @@ pub fn is_host_port(string: String) -> Result<(), String> {
     parse_host_port(&string).map(|_| ())
 }
 
+fn socket_domain(ip_addr: IpAddr) -> Domain {
+    match ip_addr {
+        IpAddr::V4(_) => Domain::ipv4(),
+        IpAddr::V6(_) => Domain::ipv6(),
+    }
+}
+
 #[cfg(windows)]
-fn udp_socket(_reuseaddr: bool) -> io::Result<Socket> {
-    let sock = Socket::new(Domain::ipv4(), Type::dgram(), None)?;
+fn udp_socket(ip_addr: IpAddr, _reuseaddr: bool) -> io::Result<Socket> {
+    let sock = Socket::new(socket_domain(ip_addr), Type::dgram(), None)?;
     Ok(sock)
 }
 
 #[cfg(not(windows))]
-fn udp_socket(reuseaddr: bool) -> io::Result<Socket> {
+fn udp_socket(ip_addr: IpAddr, reuseaddr: bool) -> io::Result<Socket> {
     use nix::sys::socket::setsockopt;
     use nix::sys::socket::sockopt::{ReuseAddr, ReusePort};
     use std::os::unix::io::AsRawFd;
 
-    let sock = Socket::new(Domain::ipv4(), Type::dgram(), None)?;
+    let sock = Socket::new(socket_domain(ip_addr), Type::dgram(), None)?;
     let sock_fd = sock.as_raw_fd();
 
     if reuseaddr {
@@ -410,7 +1774,7 @@ pub fn bind_common_in_range(
 }
 
 pub fn bind_in_range(ip_addr: IpAddr, range: PortRange) -> io::Result<(u16, UdpSocket)> {
-    let sock = udp_socket(false)?;
+    let sock = udp_socket(ip_addr, false)?;
 
     for port in range.0..range.1 {
         let addr = SocketAddr::new(ip_addr, port);
@@ -446,9 +1810,10 @@ pub fn multi_bind_in_range(
     const NUM_TRIES: usize = 100;
     let mut port = 0;
     let mut error = None;
+    let mut start = range.0;
     for _ in 0..NUM_TRIES {
         port = {
-            let (port, _) = bind_in_range(ip_addr, range)?;
+            let (port, _) = bind_in_range(ip_addr, (start, range.1))?;
             port
         }; // drop the probe, port should be available... briefly.
 
@@ -464,7 +1829,11 @@ pub fn multi_bind_in_range(
         if sockets.len() == num {
             break;
         } else {
+            // Something else grabbed the port, retry with the remainder of the range rather than
+            // probing the same port again
+            debug!("multi_bind_in_range() lost port {}, retrying", port);
             sockets.clear();
+            start = port.saturating_add(1);
         }
     }
     if sockets.len() != num {
@@ -474,7 +1843,7 @@ pub fn multi_bind_in_range(
 }
 
 pub fn bind_to(ip_addr: IpAddr, port: u16, reuseaddr: bool) -> io::Result<UdpSocket> {
-    let sock = udp_socket(reuseaddr)?;
+    let sock = udp_socket(ip_addr, reuseaddr)?;
 
     let addr = SocketAddr::new(ip_addr, port);
 
@@ -488,7 +1857,7 @@ pub fn bind_common(
     port: u16,
     reuseaddr: bool,
 ) -> io::Result<(UdpSocket, TcpListener)> {
-    let sock = udp_socket(reuseaddr)?;
+    let sock = udp_socket(ip_addr, reuseaddr)?;
 
     let addr = SocketAddr::new(ip_addr, port);
     let sock_addr = SockAddr::from(addr);
@@ -496,6 +1865,42 @@ pub fn bind_common(
         .and_then(|_| TcpListener::bind(&addr).map(|listener| (sock.into_udp_socket(), listener)))
 }
 
+/// Binds both UDP and TCP on every port of `ports` that falls within `range`, so that the
+/// `*_in_range()` binders skip over them until the returned sockets are dropped.  Fails with the
+/// offending port if any of them is already in use.
+pub fn reserve_ports_in_range(
+    ip_addr: IpAddr,
+    range: PortRange,
+    ports: &[u16],
+) -> io::Result<Vec<(UdpSocket, TcpListener)>> {
+    ports
+        .iter()
+        .filter(|port| (range.0..range.1).contains(*port))
+        .map(|port| {
+            bind_common(ip_addr, *port, false).map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("Unable to reserve port {}: {}", port, err),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Returns the first port claimed by more than one of the named services
+pub fn find_port_conflict<'a>(ports: &[(&'a str, u16)]) -> Option<(&'a str, &'a str, u16)> {
+    let mut claimed = BTreeMap::new();
+    for (name, port) in ports {
+        if *port == 0 {
+            continue;
+        }
+        if let Some(other) = claimed.insert(*port, *name) {
+            return Some((other, *name, *port));
+        }
+    }
+    None
+}
+
 pub fn find_available_port_in_range(ip_addr: IpAddr, range: PortRange) -> io::Result<u16> {
     let (start, end) = range;
     let mut tries_left = end - start;
@@ -521,8 +1926,11 @@ pub fn find_available_port_in_range(ip_addr: IpAddr, range: PortRange) -> io::Re
 
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use std::net::Ipv4Addr;
+    use {
+        super::*,
+        solana_sdk::signature::{Keypair, Signer},
+        std::sync::atomic::AtomicU64,
+    };
 
     #[test]
     fn test_parse_port_or_addr() {
@@ -551,6 +1959,9 @@ mod tests {
         parse_host("localhost").unwrap();
         parse_host("127.0.0.0:1234").unwrap_err();
         parse_host("127.0.0.0").unwrap();
+        assert_eq!(parse_host("::1"), Ok(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert_eq!(parse_host("[::1]"), Ok(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        parse_host("[::1]:1234").unwrap_err();
     }
 
     #[test]
@@ -608,6 +2019,36 @@ mod tests {
         find_available_port_in_range(ip_addr, (port, port + 1)).unwrap_err();
     }
 
+    #[test]
+    fn test_reserve_ports_in_range() {
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let reserved = reserve_ports_in_range(ip_addr, (3150, 3160), &[3150, 3170]).unwrap();
+        assert_eq!(reserved.len(), 1);
+        assert_eq!(reserved[0].0.local_addr().unwrap().port(), 3150);
+        assert_eq!(reserved[0].1.local_addr().unwrap().port(), 3150);
+
+        assert_ne!(bind_in_range(ip_addr, (3150, 3160)).unwrap().0, 3150);
+        let (port, _sockets) = bind_common_in_range(ip_addr, (3150, 3160)).unwrap();
+        assert_ne!(port, 3150);
+        reserve_ports_in_range(ip_addr, (3150, 3160), &[3150]).unwrap_err();
+
+        drop(reserved);
+        reserve_ports_in_range(ip_addr, (3150, 3160), &[3150]).unwrap();
+    }
+
+    #[test]
+    fn test_find_port_conflict() {
+        assert_eq!(find_port_conflict(&[]), None);
+        assert_eq!(
+            find_port_conflict(&[("gossip", 8001), ("rpc", 8899), ("unused", 0), ("tpu", 0)]),
+            None
+        );
+        assert_eq!(
+            find_port_conflict(&[("gossip", 8001), ("rpc", 8899), ("rpc_pubsub", 8001)]),
+            Some(("gossip", "rpc_pubsub", 8001))
+        );
+    }
+
     #[test]
     fn test_bind_common_in_range() {
         let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
@@ -628,8 +2069,8 @@ mod tests {
 
         let server_ip_echo_addr = server_udp_socket.local_addr().unwrap();
         assert_eq!(
-            get_public_ip_addr(&server_ip_echo_addr),
-            parse_host("127.0.0.1"),
+            get_public_ip_addr(&server_ip_echo_addr).unwrap(),
+            parse_host("127.0.0.1").unwrap()
         );
 
         assert!(verify_reachable_ports(&server_ip_echo_addr, vec![], &[],));
@@ -648,8 +2089,8 @@ mod tests {
 
         let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
         assert_eq!(
-            get_public_ip_addr(&ip_echo_server_addr),
-            parse_host("127.0.0.1"),
+            get_public_ip_addr(&ip_echo_server_addr).unwrap(),
+            parse_host("127.0.0.1").unwrap()
         );
 
         assert!(verify_reachable_ports(
@@ -660,48 +2101,1038 @@ mod tests {
     }
 
     #[test]
-    fn test_get_public_ip_addr_tcp_unreachable() {
+    fn test_get_public_socket_addr() {
         solana_logger::setup();
-        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
-        let (_server_port, (server_udp_socket, _server_tcp_listener)) =
-            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
-
-        // make the socket unreachable by not running the ip echo server!
+        let tcp_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let ip_echo_server_addr = tcp_listener.local_addr().unwrap();
+        let _runtime = ip_echo_server(tcp_listener);
 
-        let server_ip_echo_addr = server_udp_socket.local_addr().unwrap();
+        let public_socket_addr = get_public_socket_addr(&ip_echo_server_addr).unwrap();
+        assert_eq!(public_socket_addr.ip(), parse_host("127.0.0.1").unwrap());
+        assert_ne!(public_socket_addr.port(), 0);
 
-        let (correct_client_port, (_client_udp_socket, client_tcp_listener)) =
-            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        // Older versions don't report the source port
+        let response = ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::default(),
+            5,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(response.version, 5);
+        assert_eq!(response.source_port, None);
+    }
 
-        assert!(!do_verify_reachable_ports(
-            &server_ip_echo_addr,
-            vec![(correct_client_port, client_tcp_listener)],
+    #[test]
+    fn test_get_cluster_shred_version() {
+        solana_logger::setup();
+        let cluster_identity = ClusterIdentity {
+            shred_version: 4242,
+            genesis_hash: [3; 32],
+        };
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let ip_echo_server_addr = tcp_listener.local_addr().unwrap();
+        let _runtime = ip_echo_server_for_cluster(
+            tcp_listener,
+            cluster_identity,
+            Arc::new(Keypair::new()),
+            None,
+        );
+        assert_eq!(
+            get_cluster_identity(&ip_echo_server_addr).unwrap(),
+            cluster_identity
+        );
+        assert_eq!(
+            get_cluster_shred_version(&ip_echo_server_addr).unwrap(),
+            4242
+        );
+        // Older versions don't report it
+        let response = ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::default(),
+            7,
             &[],
-            2,
-            3,
+        )
+        .unwrap();
+        assert_eq!(response.cluster_identity, None);
+
+        // Servers not given a cluster leave it out
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let ip_echo_server_addr = tcp_listener.local_addr().unwrap();
+        let _runtime = ip_echo_server(tcp_listener);
+        assert!(get_cluster_shred_version(&ip_echo_server_addr).is_err());
+        let response = ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::default(),
+            IP_ECHO_SERVER_PROTOCOL_VERSION,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(response.cluster_identity, None);
+    }
+
+    #[test]
+    fn test_get_cluster_identities_signed_by() {
+        solana_logger::setup();
+        let cluster_identity = ClusterIdentity {
+            shred_version: 4242,
+            genesis_hash: [3; 32],
+        };
+        let keypair = Arc::new(Keypair::new());
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let signed_addr = tcp_listener.local_addr().unwrap();
+        let _signed_runtime =
+            ip_echo_server_for_cluster(tcp_listener, cluster_identity, keypair.clone(), None);
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let impostor_addr = tcp_listener.local_addr().unwrap();
+        let _impostor_runtime = ip_echo_server_for_cluster(
+            tcp_listener,
+            cluster_identity,
+            Arc::new(Keypair::new()),
+            None,
+        );
+        // Accepts the connection, but never answers
+        let silent_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let silent_addr = silent_listener.local_addr().unwrap();
+
+        let start = Instant::now();
+        let answers = get_cluster_identities_signed_by(
+            &[silent_addr, impostor_addr, signed_addr],
+            &[keypair.pubkey()],
+            Duration::from_secs(2),
+        );
+        assert!(start.elapsed() < Duration::from_secs(4));
+        assert_eq!(
+            answers.iter().map(|(addr, _)| *addr).collect::<Vec<_>>(),
+            vec![silent_addr, impostor_addr, signed_addr]
+        );
+        assert!(matches!(answers[0].1, Err(NetUtilsError::Timeout(_))));
+        assert!(matches!(
+            answers[1].1,
+            Err(NetUtilsError::InvalidSignature { .. })
         ));
+        assert_eq!(answers[2].1.as_ref().unwrap(), &cluster_identity);
     }
 
     #[test]
-    fn test_get_public_ip_addr_udp_unreachable() {
+    fn test_get_public_ip_addr_signed_by() {
+        solana_logger::setup();
+        let keypair = Arc::new(Keypair::new());
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let ip_echo_server_addr = tcp_listener.local_addr().unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        ip_echo_server_with_config(
+            runtime.handle(),
+            tcp_listener,
+            IpEchoServerConfig {
+                keypair: Some(keypair.clone()),
+                ..IpEchoServerConfig::default()
+            },
+            Arc::default(),
+        );
+        assert_eq!(
+            get_public_ip_addr_signed_by(
+                &ip_echo_server_addr,
+                &[Pubkey::new_unique(), keypair.pubkey()]
+            )
+            .unwrap(),
+            parse_host("127.0.0.1").unwrap()
+        );
+        assert!(matches!(
+            get_public_ip_addr_signed_by(&ip_echo_server_addr, &[Pubkey::new_unique()]),
+            Err(NetUtilsError::InvalidSignature { claimed, .. }) if claimed == keypair.pubkey()
+        ));
+        // Older versions are never signed
+        let response = ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::default(),
+            8,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(response.signature, None);
+
+        // Servers not given a keypair don't sign
+        let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let ip_echo_server_addr = tcp_listener.local_addr().unwrap();
+        let _runtime = ip_echo_server(tcp_listener);
+        assert!(get_public_ip_addr_signed_by(&ip_echo_server_addr, &[keypair.pubkey()]).is_err());
+    }
+
+    #[test]
+    fn test_ip_echo_server_on_shared_runtime() {
         solana_logger::setup();
         let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
-        let (_server_port, (server_udp_socket, _server_tcp_listener)) =
-            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let ip_echo_server_addrs: Vec<_> = (0..2)
+            .map(|_| {
+                let (_server_port, (server_udp_socket, server_tcp_listener)) =
+                    bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+                ip_echo_server_on(runtime.handle(), server_tcp_listener);
+                server_udp_socket.local_addr().unwrap()
+            })
+            .collect();
 
-        // make the socket unreachable by not running the ip echo server!
+        for ip_echo_server_addr in &ip_echo_server_addrs {
+            assert_eq!(
+                get_public_ip_addr(ip_echo_server_addr).unwrap(),
+                parse_host("127.0.0.1").unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_ip_echo_server_stats_http_request() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let stats = Arc::new(IpEchoServerStats::default());
+        ip_echo_server_with_config(
+            runtime.handle(),
+            server_tcp_listener,
+            IpEchoServerConfig::default(),
+            stats.clone(),
+        );
+
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+        let mut stream = TcpStream::connect(&ip_echo_server_addr).unwrap();
+        let mut request = b"GET / HTTP/1.1\n".to_vec();
+        request.resize(ip_echo_server::ip_echo_server_request_length(), b'\n');
+        stream.write_all(&request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"));
+        // Pointing to the RPC port of the address connected to
+        assert!(response.contains("The JSON RPC URL is probably"));
+        assert!(response.contains(":8899"));
+
+        // The session is counted once it ended
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let sessions = || -> u64 {
+            stats
+                .processing_time_ms
+                .iter()
+                .map(|counter| counter.load(Ordering::Relaxed))
+                .sum()
+        };
+        while sessions() == 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(stats.connections_accepted.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.requests_http.load(Ordering::Relaxed), 1);
+        assert_eq!(stats.sessions_failed.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_ip_echo_server_with_config() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (client_port, (_client_udp_socket, client_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (other_client_port, (_, other_client_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let stats = Arc::new(IpEchoServerStats::default());
+        ip_echo_server_with_config(
+            runtime.handle(),
+            server_tcp_listener,
+            IpEchoServerConfig {
+                io_timeout: Duration::from_millis(500),
+                max_probed_ports: 1,
+                ..IpEchoServerConfig::default()
+            },
+            stats.clone(),
+        );
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+
+        assert!(verify_reachable_ports(
+            &ip_echo_server_addr,
+            vec![(client_port, client_tcp_listener)],
+            &[],
+        ));
+        // Asking for more ports than the server probes fails
+        assert!(ip_echo_server_request(
+            &ip_echo_server_addr,
+            IpEchoServerMessage::new(&[client_port, other_client_port], &[]),
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .is_err());
+        drop(other_client_tcp_listener);
+
+        // Stalled sessions are closed once the io timeout expires
+        let start = Instant::now();
+        let mut stream = TcpStream::connect(&ip_echo_server_addr).unwrap();
+        let mut response = vec![];
+        stream.read_to_end(&mut response).unwrap();
+        assert!(response.is_empty());
+        assert!(start.elapsed() < IpEchoServerConfig::default().io_timeout);
+        // The session is counted once it ended
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while stats.sessions_failed.load(Ordering::Relaxed) < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(stats.sessions_failed.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_ip_echo_server_ip_filter() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let stats = Arc::new(IpEchoServerStats::default());
+        let ip_filter = Arc::new(IpFilter::default());
+        ip_echo_server_with_config(
+            runtime.handle(),
+            server_tcp_listener,
+            IpEchoServerConfig {
+                ip_filter: ip_filter.clone(),
+                max_bad_headers_per_ip: Some(2),
+                ..IpEchoServerConfig::default()
+            },
+            stats.clone(),
+        );
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let wait_for = |counter: &AtomicU64, value: u64| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while counter.load(Ordering::Relaxed) < value && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(counter.load(Ordering::Relaxed), value);
+        };
+        let echo = || {
+            ip_echo_server_request(
+                &ip_echo_server_addr,
+                IpEchoServerMessage::default(),
+                &IpEchoClientRetryConfig::no_retry(),
+            )
+            .map(|response| response.address)
+        };
+
+        let rejected = |rejection| {
+            matches!(
+                echo(),
+                Err(NetUtilsError::Rejected { rejection: r, .. }) if r == rejection
+            )
+        };
+
+        // Updating the lists applies to the running server
+        ip_filter.set_denylist(vec!["127.0.0.0/8".parse().unwrap()]);
+        assert!(rejected(IpEchoServerRejection::Denied));
+        ip_filter.set_denylist(vec![]);
+        ip_filter.set_allowlist(vec!["10.0.0.0/8".parse().unwrap()]);
+        assert!(rejected(IpEchoServerRejection::Denied));
+        wait_for(&stats.connections_rejected_ip_filter, 2);
+        ip_filter.set_allowlist(vec!["127.0.0.1".parse().unwrap()]);
+        assert_eq!(echo().unwrap(), localhost);
+
+        // Sending bad headers gets the address banned
+        for _ in 0..2 {
+            let mut stream = TcpStream::connect(&ip_echo_server_addr).unwrap();
+            stream
+                .write_all(&vec![0xff; ip_echo_server::ip_echo_server_request_length()])
+                .unwrap();
+            let mut response = vec![];
+            let _ = stream.read_to_end(&mut response);
+        }
+        wait_for(&stats.ips_banned, 1);
+        assert!(rejected(IpEchoServerRejection::Banned));
+        wait_for(&stats.connections_rejected_banned, 1);
+        assert!(ip_filter.unban(&localhost));
+        assert_eq!(echo().unwrap(), localhost);
+    }
+
+    #[test]
+    fn test_ip_echo_server_multi() {
+        solana_logger::setup();
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let server_tcp_listeners: Vec<_> = (0..2)
+            .map(|_| std::net::TcpListener::bind((localhost, 0)).unwrap())
+            .collect();
+        let server_addrs: Vec<_> = server_tcp_listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap())
+            .collect();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let stats = Arc::new(IpEchoServerStats::default());
+        ip_echo_server_multi_with_config(
+            runtime.handle(),
+            server_tcp_listeners,
+            IpEchoServerConfig {
+                max_requests_per_ip: Some(2),
+                ..IpEchoServerConfig::default()
+            },
+            stats.clone(),
+        );
+        let echo = |ip_echo_server_addr| {
+            ip_echo_server_request(
+                ip_echo_server_addr,
+                IpEchoServerMessage::default(),
+                &IpEchoClientRetryConfig::no_retry(),
+            )
+            .map(|response| response.address)
+        };
+
+        for server_addr in &server_addrs {
+            assert_eq!(echo(server_addr).unwrap(), localhost);
+        }
+        // The listeners share the rate limits, and say so to the peers they turn away
+        for server_addr in &server_addrs {
+            assert!(matches!(
+                echo(server_addr),
+                Err(NetUtilsError::Rejected {
+                    rejection: IpEchoServerRejection::RateLimited,
+                    ..
+                })
+            ));
+        }
+        assert_eq!(stats.connections_accepted.load(Ordering::Relaxed), 2);
+        assert_eq!(
+            stats
+                .connections_rejected_rate_limit
+                .load(Ordering::Relaxed),
+            2
+        );
+
+        // Dropping the runtime shuts down every listener
+        let server_tcp_listeners: Vec<_> = (0..2)
+            .map(|_| std::net::TcpListener::bind((localhost, 0)).unwrap())
+            .collect();
+        let server_addrs: Vec<_> = server_tcp_listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap())
+            .collect();
+        let server = ip_echo_server_multi(server_tcp_listeners);
+        for server_addr in &server_addrs {
+            assert_eq!(echo(server_addr).unwrap(), localhost);
+        }
+        drop(server);
+        for server_addr in &server_addrs {
+            assert!(TcpStream::connect(server_addr).is_err());
+        }
+    }
+
+    #[test]
+    fn test_ip_echo_server_probes_tcp_ports_concurrently() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let io_timeout = Duration::from_secs(1);
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        ip_echo_server_with_config(
+            runtime.handle(),
+            server_tcp_listener,
+            IpEchoServerConfig {
+                io_timeout,
+                ..IpEchoServerConfig::default()
+            },
+            Arc::new(IpEchoServerStats::default()),
+        );
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+
+        // Connections to a listener with a full accept queue stall until they time out
+        let stalled_listeners: Vec<_> = (0..4)
+            .map(|_| {
+                let sock = Socket::new(Domain::ipv4(), Type::stream(), None).unwrap();
+                sock.bind(&SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).into())
+                    .unwrap();
+                sock.listen(0).unwrap();
+                let listener = sock.into_tcp_listener();
+                let listener_addr = listener.local_addr().unwrap();
+                let queued: Vec<_> = (0..2)
+                    .filter_map(|_| {
+                        TcpStream::connect_timeout(&listener_addr, Duration::from_millis(100)).ok()
+                    })
+                    .collect();
+                (listener, queued)
+            })
+            .collect();
+        let stalled_ports: Vec<_> = stalled_listeners
+            .iter()
+            .map(|(listener, _)| listener.local_addr().unwrap().port())
+            .collect();
+
+        let start = Instant::now();
+        let response = ip_echo_server_request(
+            &ip_echo_server_addr,
+            IpEchoServerMessage::new(&stalled_ports, &[]),
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .unwrap();
+        assert_eq!(response.tcp_ports_reachable, Some(vec![false; 4]));
+        // All ports share a single timeout rather than taking one each
+        assert!(start.elapsed() < 2 * io_timeout);
+    }
+
+    #[test]
+    fn test_get_public_ip_addr_reachable_async() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (client_port, (client_udp_socket, client_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        ip_echo_server_on(runtime.handle(), server_tcp_listener);
+
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+        assert_eq!(
+            runtime
+                .block_on(get_public_ip_addr_async(&ip_echo_server_addr))
+                .unwrap(),
+            parse_host("127.0.0.1").unwrap()
+        );
+        assert!(runtime.block_on(verify_reachable_ports_async(
+            ip_echo_server_addr,
+            vec![(client_port, client_tcp_listener)],
+            vec![client_udp_socket],
+        )));
+
+        // A server that never answers holds the checks up until the future is dropped
+        let silent_server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (_udp_port, udp_socket) = bind_in_range(ip_addr, (3200, 3250)).unwrap();
+        let udp_socket_clone = udp_socket.try_clone().unwrap();
+        let start = Instant::now();
+        assert!(runtime
+            .block_on(async {
+                tokio::time::timeout(
+                    Duration::from_millis(200),
+                    verify_reachable_ports_async(
+                        silent_server.local_addr().unwrap(),
+                        vec![],
+                        vec![udp_socket],
+                    ),
+                )
+                .await
+            })
+            .is_err());
+        assert!(start.elapsed() < Duration::from_secs(2));
+        // The clone is back to blocking reads
+        udp_socket_clone
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let start = Instant::now();
+        assert!(udp_socket_clone.recv_from(&mut [0; 8]).is_err());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_get_public_ip_addr_reachable_ipv6() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+        let (server_port, (_server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (client_port, (client_udp_socket, client_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        let _runtime = ip_echo_server(server_tcp_listener);
+
+        let ip_echo_server_addr = SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), server_port);
+        assert_eq!(
+            get_public_ip_addr(&ip_echo_server_addr).unwrap(),
+            parse_host("::1").unwrap()
+        );
+
+        assert!(verify_reachable_ports(
+            &ip_echo_server_addr,
+            vec![(client_port, client_tcp_listener)],
+            &[&client_udp_socket],
+        ));
+
+        // IPv4 peers of the dual-stack server get their IPv4 address back
+        let ip_echo_server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), server_port);
+        assert_eq!(
+            get_public_ip_addr(&ip_echo_server_addr).unwrap(),
+            parse_host("127.0.0.1").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_get_public_ip_addr_with_quorum() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V6(Ipv6Addr::UNSPECIFIED);
+        let (first_port, (_first_udp_socket, first_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (second_port, (_second_udp_socket, second_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (closed_port, _) = bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        let _first_runtime = ip_echo_server(first_tcp_listener);
+        let _second_runtime = ip_echo_server(second_tcp_listener);
+
+        // The dual-stack servers answer IPv6 peers with their IPv6 address
+        let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ip_echo_server_addrs = [
+            SocketAddr::new(localhost, first_port),
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), first_port),
+            SocketAddr::new(localhost, second_port),
+            SocketAddr::new(localhost, closed_port),
+        ];
+
+        let consensus = get_public_ip_addr_with_quorum(&ip_echo_server_addrs, 2).unwrap();
+        assert_eq!(consensus.address, localhost);
+        assert_eq!(
+            consensus.agreeing,
+            vec![ip_echo_server_addrs[0], ip_echo_server_addrs[2]]
+        );
+        assert_eq!(
+            consensus.dissenting,
+            vec![(ip_echo_server_addrs[1], parse_host("::1").unwrap())]
+        );
+        assert_eq!(consensus.failed.len(), 1);
+        assert_eq!(consensus.failed[0].0, ip_echo_server_addrs[3]);
+
+        assert!(get_public_ip_addr_with_quorum(&ip_echo_server_addrs, 3).is_err());
+        // Meeting the quorum isn't enough when as many servers answered another address
+        assert!(matches!(
+            get_public_ip_addr_with_quorum(&ip_echo_server_addrs[..2], 1),
+            Err(NetUtilsError::NoConsensus(_))
+        ));
+        assert!(get_public_ip_addr_with_quorum(&ip_echo_server_addrs, 0).is_err());
+        assert!(get_public_ip_addr_with_quorum(&ip_echo_server_addrs, 5).is_err());
+        assert!(get_public_ip_addr_with_quorum(&ip_echo_server_addrs[3..], 1).is_err());
+    }
+
+    #[test]
+    fn test_ip_echo_server_reports_blocked_ports() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (client_port, (_client_udp_socket, _client_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        // Nothing listens on a port once its listener is dropped
+        let (blocked_port, _) = bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        let _runtime = ip_echo_server(server_tcp_listener);
+
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+        let response = ip_echo_server_request(
+            &ip_echo_server_addr,
+            IpEchoServerMessage::new(&[client_port, blocked_port], &[client_port]),
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .unwrap();
+        assert_eq!(response.address, parse_host("127.0.0.1").unwrap());
+        assert_eq!(response.tcp_ports_reachable, Some(vec![true, false]));
+        assert_eq!(response.udp_ports_sent, Some(vec![true]));
+        // Nothing echoed the token back
+        assert_eq!(response.udp_ports_confirmed, Some(vec![false]));
+
+        // Version 2 responses report on the legacy fixed size port lists
+        let response = ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::new(&[client_port, blocked_port], &[]),
+            2,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(response.version, 2);
+        assert_eq!(
+            response.tcp_ports_reachable,
+            Some(vec![true, false, false, false])
+        );
+
+        // Version 0 requests still get the bare address, and no response at all when a TCP
+        // port is unreachable
+        let response = ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::new(&[client_port], &[]),
+            0,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            response,
+            IpEchoServerResponse {
+                version: 0,
+                address: parse_host("127.0.0.1").unwrap(),
+                source_port: None,
+                tcp_ports_reachable: None,
+                udp_ports_sent: None,
+                udp_ports_confirmed: None,
+                quic_ports_reachable: None,
+                udp_ports_mapped: None,
+                cluster_identity: None,
+                signature: None,
+            }
+        );
+        assert!(ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::new(&[blocked_port], &[]),
+            0,
+            &[],
+        )
+        .is_err());
+
+        // Clients newer than the server are negotiated down to the server's version
+        let response = ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::default(),
+            IP_ECHO_SERVER_PROTOCOL_VERSION + 1,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(response.version, IP_ECHO_SERVER_PROTOCOL_VERSION);
+        assert_eq!(
+            get_public_ip_addr_and_protocol_version(&ip_echo_server_addr).unwrap(),
+            (
+                parse_host("127.0.0.1").unwrap(),
+                IP_ECHO_SERVER_PROTOCOL_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn test_ip_echo_server_udp_token_echo() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (client_port, (client_udp_socket, _client_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        let _runtime = ip_echo_server(server_tcp_listener);
+
+        // Drop the first token to exercise the server resending it
+        let echo_thread = std::thread::spawn(move || {
+            let mut buf = [0; UDP_TOKEN_LENGTH];
+            let (len, _) = client_udp_socket.recv_from(&mut buf).unwrap();
+            assert_eq!(len, UDP_TOKEN_LENGTH);
+            let (len, from) = client_udp_socket.recv_from(&mut buf).unwrap();
+            client_udp_socket.send_to(&buf[..len], from).unwrap();
+        });
+
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+        let response = ip_echo_server_request(
+            &ip_echo_server_addr,
+            IpEchoServerMessage::new(&[], &[client_port]),
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .unwrap();
+        echo_thread.join().unwrap();
+        assert_eq!(response.udp_ports_sent, Some(vec![true]));
+        assert_eq!(response.udp_ports_confirmed, Some(vec![true]));
+        assert_eq!(
+            response.udp_ports_mapped,
+            Some(vec![Some(SocketAddr::from(([127, 0, 0, 1], client_port)))])
+        );
+    }
+
+    #[test]
+    fn test_verify_reachable_ports_single_request() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (client_port, (client_udp_socket, client_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let client_udp_sockets: Vec<_> = (1..MAX_PORT_COUNT_PER_MESSAGE)
+            .map(|_| bind_in_range(ip_addr, (3250, 3300)).unwrap().1)
+            .collect();
+
+        let _runtime = ip_echo_server(server_tcp_listener);
+
+        // More ports than the legacy fixed size port lists hold
+        let udp_sockets: Vec<_> = std::iter::once(&client_udp_socket)
+            .chain(client_udp_sockets.iter())
+            .collect();
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+        let response = ip_echo_server_request(
+            &ip_echo_server_addr,
+            IpEchoServerMessage::new(
+                &[],
+                &udp_sockets
+                    .iter()
+                    .map(|udp_socket| udp_socket.local_addr().unwrap().port())
+                    .collect::<Vec<_>>(),
+            ),
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .unwrap();
+        assert_eq!(response.version, IP_ECHO_SERVER_PROTOCOL_VERSION);
+        assert_eq!(
+            response.udp_ports_sent,
+            Some(vec![true; MAX_PORT_COUNT_PER_MESSAGE])
+        );
+
+        assert!(do_verify_reachable_ports(
+            &ip_echo_server_addr,
+            vec![(client_port, client_tcp_listener)],
+            &udp_sockets,
+            &[],
+            2,
+            1,
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_verify_reachable_quic_ports() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let (_quic_port, quic_socket) = bind_in_range(ip_addr, (3200, 3250)).unwrap();
+        // Nothing answers probes on a socket that isn't checked
+        let (silent_port, silent_socket) = bind_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        let _runtime = ip_echo_server(server_tcp_listener);
+        let ip_echo_server_addr = server_udp_socket.local_addr().unwrap();
+
+        let response = ip_echo_server_request(
+            &ip_echo_server_addr,
+            IpEchoServerMessage::new(&[], &[]).with_quic_ports(&[silent_port]),
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .unwrap();
+        assert_eq!(response.quic_ports_reachable, Some(vec![false]));
+
+        // Version 3 responses don't report on QUIC ports
+        let response = ip_echo_server_request_with_version(
+            &ip_echo_server_addr,
+            &IpEchoServerMessage::new(&[], &[]).with_quic_ports(&[silent_port]),
+            3,
+            &[],
+        )
+        .unwrap();
+        assert_eq!(response.quic_ports_reachable, None);
+
+        assert!(verify_reachable_ports_with_quic(
+            &ip_echo_server_addr,
+            vec![],
+            &[],
+            &[&quic_socket],
+        ));
+
+        // Version 3 servers aren't asked to probe QUIC ports at all
+        assert!(verify_reachable_quic_ports(
+            &ip_echo_server_addr,
+            &[&silent_socket],
+            max_quic_port_count_per_message(3),
+            Duration::from_secs(1),
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_get_public_ip_addr_with_stun() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_, stun_server_socket) = bind_in_range(ip_addr, (3200, 3250)).unwrap();
+        let stun_server_addr = SocketAddr::new(
+            parse_host("127.0.0.1").unwrap(),
+            stun_server_socket.local_addr().unwrap().port(),
+        );
+        let stun_server_thread = std::thread::spawn(move || {
+            // Ignore the first request so that the client retransmits it
+            let mut buf = [0u8; 512];
+            stun_server_socket.recv_from(&mut buf).unwrap();
+            for _ in 0..2 {
+                let (len, from) = stun_server_socket.recv_from(&mut buf).unwrap();
+                let response = stun::tests::binding_response(&buf[..len], from, true);
+                stun_server_socket.send_to(&response, from).unwrap();
+            }
+        });
+        // Nothing listens on a port once its socket is dropped
+        let (silent_port, _) = bind_in_range(ip_addr, (3200, 3250)).unwrap();
+        let silent_addr = SocketAddr::new(parse_host("127.0.0.1").unwrap(), silent_port);
+
+        assert_eq!(
+            get_public_ip_addr_with_stun(&[stun_server_addr]).unwrap(),
+            parse_host("127.0.0.1").unwrap()
+        );
+        assert!(get_public_ip_addr_with_stun(&[]).is_err());
+
+        // Unreachable ip_echo_servers fall back to STUN
+        assert_eq!(
+            get_public_ip_addr_with_stun_fallback(&silent_addr, &[stun_server_addr]).unwrap(),
+            parse_host("127.0.0.1").unwrap()
+        );
+        stun_server_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_get_public_ip_addr_tcp_unreachable() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, _server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        // make the socket unreachable by not running the ip echo server!
+
+        let server_ip_echo_addr = server_udp_socket.local_addr().unwrap();
+
+        let (correct_client_port, (_client_udp_socket, client_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        let err = do_verify_reachable_ports(
+            &server_ip_echo_addr,
+            vec![(correct_client_port, client_tcp_listener)],
+            &[],
+            &[],
+            2,
+            3,
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .unwrap_err();
+        match err {
+            NetUtilsError::Unreachable {
+                protocol, ports, ..
+            } => {
+                assert_eq!(protocol, "tcp");
+                assert_eq!(ports, vec![correct_client_port]);
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_get_public_ip_addr_udp_unreachable() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        let (_server_port, (server_udp_socket, _server_tcp_listener)) =
+            bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+
+        // make the socket unreachable by not running the ip echo server!
 
         let server_ip_echo_addr = server_udp_socket.local_addr().unwrap();
 
         let (_correct_client_port, (client_udp_socket, _client_tcp_listener)) =
             bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
 
-        assert!(!do_verify_reachable_ports(
+        let err = do_verify_reachable_ports(
             &server_ip_echo_addr,
             vec![],
             &[&client_udp_socket],
+            &[],
             2,
             3,
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            NetUtilsError::Unreachable {
+                protocol: "udp",
+                ..
+            }
         ));
     }
+
+    #[test]
+    fn test_ip_echo_server_request_retry() {
+        solana_logger::setup();
+        let ip_addr = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
+        // Nothing listens on a port once its listener is dropped
+        let (server_port, _) = bind_common_in_range(ip_addr, (3200, 3250)).unwrap();
+        let ip_echo_server_addr = SocketAddr::new(parse_host("127.0.0.1").unwrap(), server_port);
+        let retry_config = IpEchoClientRetryConfig {
+            attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_jitter: Duration::from_millis(10),
+        };
+
+        // Every attempt is listed once they all fail
+        let err =
+            get_public_ip_addr_with_retry_config(&ip_echo_server_addr, &retry_config).unwrap_err();
+        assert!(matches!(
+            &err,
+            NetUtilsError::RetriesExhausted { failures, .. } if failures.len() == 3
+        ));
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused);
+        let err = err.to_string();
+        assert!(err.starts_with(&format!(
+            "3 attempts to reach {} failed: #1 after",
+            ip_echo_server_addr
+        )));
+        assert!(err.contains("; #2 after"));
+        assert!(err.contains("; #3 after"));
+
+        // A single failed attempt is reported as is
+        let err = get_public_ip_addr_with_retry_config(
+            &ip_echo_server_addr,
+            &IpEchoClientRetryConfig::no_retry(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, NetUtilsError::Io(_)));
+
+        // A server that comes up while the client backs off is reached by a retry
+        let server_thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            let (_, server_tcp_listener) = bind_common(ip_addr, server_port, false).unwrap();
+            let runtime = ip_echo_server(server_tcp_listener);
+            std::thread::sleep(Duration::from_secs(2));
+            drop(runtime);
+        });
+        let retry_config = IpEchoClientRetryConfig {
+            attempts: 10,
+            base_delay: Duration::from_millis(50),
+            max_jitter: Duration::default(),
+        };
+        assert_eq!(
+            get_public_ip_addr_with_retry_config(&ip_echo_server_addr, &retry_config).unwrap(),
+            parse_host("127.0.0.1").unwrap()
+        );
+        server_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_ip_echo_server_request_legacy_server() {
+        solana_logger::setup();
+        // A server predating version negotiation, which closes the connection on any request
+        // but a version 0 one
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let ip_echo_server_addr = listener.local_addr().unwrap();
+        let server_thread = std::thread::spawn(move || {
+            let mut versions = vec![];
+            for stream in listener.incoming().take(2) {
+                let mut stream = stream.unwrap();
+                let mut request = vec![0; ip_echo_server::ip_echo_server_request_length()];
+                stream.read_exact(&mut request).unwrap();
+                versions.push(request[HEADER_LENGTH - 1]);
+                if request[HEADER_LENGTH - 1] == 0 {
+                    let mut response = vec![0; HEADER_LENGTH];
+                    bincode::serialize_into(&mut response, &stream.peer_addr().unwrap().ip())
+                        .unwrap();
+                    stream.write_all(&response).unwrap();
+                }
+            }
+            versions
+        });
+
+        // The client goes straight to version 0, in two connections
+        assert_eq!(
+            get_public_ip_addr_and_protocol_version(&ip_echo_server_addr).unwrap(),
+            (parse_host("127.0.0.1").unwrap(), 0)
+        );
+        assert_eq!(
+            server_thread.join().unwrap(),
+            vec![IP_ECHO_SERVER_PROTOCOL_VERSION, 0]
+        );
+    }
+
+    #[test]
+    fn test_ip_echo_client_retry_config_delay() {
+        let retry_config = IpEchoClientRetryConfig {
+            attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_jitter: Duration::from_millis(10),
+        };
+        for (retry, backoff_ms) in [(1, 100), (2, 200), (3, 400)].iter() {
+            let delay = retry_config.delay(*retry);
+            assert!(delay >= Duration::from_millis(*backoff_ms));
+            assert!(delay <= Duration::from_millis(*backoff_ms + 10));
+        }
+    }
 }
@@ -0,0 +1,296 @@
+//! Classifies the NAT between this node and the ip echo servers by the addresses version 5 servers
+//! report the UDP token echoes of local sockets to come from, and by which tokens get through
+use {
+    crate::{
+        ip_echo_server::{IpEchoServerMessage, UDP_TOKEN_LENGTH},
+        ip_echo_server_request_opening_nat, NetUtilsError,
+    },
+    log::*,
+    std::{
+        net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    },
+};
+
+// How long a probe's socket echoes tokens, well past the time servers wait for them
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How datagrams reach this node, in the classic terms of RFC 3489
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NatType {
+    /// The ip echo servers saw the local address itself, there is no NAT in between
+    Open,
+    /// A local socket keeps the same public address whoever it sends to, and anyone can send to
+    /// that address once it exists
+    FullCone,
+    /// A local socket keeps the same public address whoever it sends to, but only the IP
+    /// addresses it sent to can send back
+    RestrictedCone,
+    /// Like `RestrictedCone`, only the exact addresses and ports it sent to can send back
+    PortRestrictedCone,
+    /// A local socket gets another public address for each destination, so peers can't learn
+    /// the address to reach it at from anyone else
+    Symmetric,
+}
+
+impl NatType {
+    /// Whether peers learning this node's public address from a third party can reach it, as
+    /// long as both sides send to each other first
+    pub fn allows_hole_punching(&self) -> bool {
+        *self != NatType::Symmetric
+    }
+}
+
+/// What the probes of `detect_nat_type` observed
+#[derive(Debug)]
+struct NatProbes {
+    /// The address this node sends to the servers from
+    local_addr: SocketAddr,
+    /// The addresses the servers saw a single local socket echo their tokens from
+    mapped_addrs: Vec<SocketAddr>,
+    /// Whether the tokens of the first server reached a socket that only sent to another port of
+    /// that server
+    delivered_from_other_port: bool,
+    /// Whether the tokens of a server at another IP address reached that socket too, `None`
+    /// without such a server
+    delivered_from_other_ip: Option<bool>,
+}
+
+impl NatProbes {
+    fn nat_type(&self) -> NatType {
+        if self
+            .mapped_addrs
+            .iter()
+            .any(|mapped_addr| *mapped_addr != self.mapped_addrs[0])
+        {
+            NatType::Symmetric
+        } else if self.mapped_addrs[0] == self.local_addr {
+            NatType::Open
+        } else if self.delivered_from_other_ip == Some(true) {
+            NatType::FullCone
+        } else if self.delivered_from_other_port {
+            // Without a second server a full cone NAT can't be told apart
+            NatType::RestrictedCone
+        } else {
+            NatType::PortRestrictedCone
+        }
+    }
+}
+
+fn bind_probe_socket(ip_echo_server_addr: &SocketAddr) -> Result<UdpSocket, NetUtilsError> {
+    let bind_addr = match ip_echo_server_addr {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    UdpSocket::bind(bind_addr).map_err(|source| NetUtilsError::Bind {
+        addr: bind_addr,
+        source,
+    })
+}
+
+/// The local IP address datagrams to `ip_echo_server_addr` are sent from
+fn local_ip_addr_towards(ip_echo_server_addr: &SocketAddr) -> Result<IpAddr, NetUtilsError> {
+    let udp_socket = bind_probe_socket(ip_echo_server_addr)?;
+    udp_socket.connect(ip_echo_server_addr)?;
+    Ok(udp_socket.local_addr()?.ip())
+}
+
+/// Has the server at `ip_echo_server_addr` send a token to `udp_socket`, which echoes it back,
+/// returning the address the server saw the echo come from, or `None` if the token never got
+/// through.  With `open_nat` the socket sends to the server's token socket first
+fn probe_udp_mapping(
+    ip_echo_server_addr: &SocketAddr,
+    udp_socket: &UdpSocket,
+    open_nat: bool,
+) -> Result<Option<SocketAddr>, NetUtilsError> {
+    let port = udp_socket.local_addr().unwrap().port();
+    let request_done = Arc::new(AtomicBool::new(false));
+    let echo_thread = {
+        let udp_socket = udp_socket.try_clone().expect("Unable to clone udp socket");
+        let request_done = request_done.clone();
+        std::thread::spawn(move || {
+            let start = Instant::now();
+            udp_socket
+                .set_read_timeout(Some(Duration::from_millis(250)))
+                .unwrap();
+            let mut buf = [0; UDP_TOKEN_LENGTH];
+            // Keep echoing until the server has replied, it resends tokens whose echo was lost
+            while !request_done.load(Ordering::Relaxed) && start.elapsed() < PROBE_TIMEOUT {
+                if let Ok((len, from)) = udp_socket.recv_from(&mut buf) {
+                    if len == UDP_TOKEN_LENGTH {
+                        let _ = udp_socket
+                            .send_to(&buf, from)
+                            .map_err(|err| warn!("udp/{} echo failed: {}", port, err));
+                    }
+                }
+            }
+        })
+    };
+
+    let udp_sockets = if open_nat { vec![udp_socket] } else { vec![] };
+    let response = ip_echo_server_request_opening_nat(
+        ip_echo_server_addr,
+        &IpEchoServerMessage::new(&[], &[port]),
+        &udp_sockets,
+    );
+    request_done.store(true, Ordering::Relaxed);
+    echo_thread.join().unwrap();
+
+    let response = response?;
+    let udp_ports_mapped = response
+        .udp_ports_mapped
+        .ok_or(NetUtilsError::UnsupportedVersion {
+            addr: *ip_echo_server_addr,
+            version: response.version,
+            capability: "report the address of udp ports",
+        })?;
+    let mapped_addr = udp_ports_mapped.into_iter().next().flatten();
+    debug!(
+        "{} saw udp/{} at {:?}, open_nat: {}",
+        ip_echo_server_addr, port, mapped_addr, open_nat
+    );
+    Ok(mapped_addr)
+}
+
+/// Determine the kind of NAT this machine is behind by having the ip_echo_servers at the given
+/// addresses send UDP tokens to local sockets.  The public address the servers see a socket
+/// echo them from tells how the NAT maps the socket, and whether tokens reach a socket that
+/// never sent to the server tells how it filters.  Telling a full cone NAT from a restricted one
+/// takes servers at two IP addresses, with a single one the result is `RestrictedCone`.  Servers
+/// must be at least version 5
+pub fn detect_nat_type(ip_echo_server_addrs: &[SocketAddr]) -> Result<NatType, NetUtilsError> {
+    let ip_echo_server_addr = ip_echo_server_addrs
+        .first()
+        .ok_or_else(|| NetUtilsError::InvalidArgument("No ip echo servers given".to_string()))?;
+    let local_ip_addr = local_ip_addr_towards(ip_echo_server_addr)?;
+
+    // Compare the public address of a single socket across at least two sessions.  Each session
+    // sends from another server socket, so even a single server exposes per destination mappings
+    let udp_socket = bind_probe_socket(ip_echo_server_addr)?;
+    let mut mapped_addrs = vec![];
+    for ip_echo_server_addr in ip_echo_server_addrs
+        .iter()
+        .chain(ip_echo_server_addrs.iter())
+        .take(ip_echo_server_addrs.len().max(2))
+    {
+        // No token getting through means udp is likely blocked
+        let mapped_addr =
+            probe_udp_mapping(ip_echo_server_addr, &udp_socket, true)?.ok_or_else(|| {
+                NetUtilsError::Unreachable {
+                    addr: *ip_echo_server_addr,
+                    protocol: "udp",
+                    ports: vec![udp_socket.local_addr().unwrap().port()],
+                }
+            })?;
+        mapped_addrs.push(mapped_addr);
+    }
+    let local_addr = SocketAddr::new(local_ip_addr, udp_socket.local_addr().unwrap().port());
+
+    // Then have the servers send to a fresh socket that only sent to the first server's ip echo
+    // port, never to the sockets the tokens come from
+    let udp_socket = bind_probe_socket(ip_echo_server_addr)?;
+    udp_socket.send_to(&[0], ip_echo_server_addr)?;
+    let delivered_from_other_port =
+        probe_udp_mapping(ip_echo_server_addr, &udp_socket, false)?.is_some();
+    let delivered_from_other_ip = match ip_echo_server_addrs
+        .iter()
+        .find(|addr| addr.ip() != ip_echo_server_addr.ip())
+    {
+        Some(other_ip_echo_server_addr) => {
+            Some(probe_udp_mapping(other_ip_echo_server_addr, &udp_socket, false)?.is_some())
+        }
+        None => None,
+    };
+
+    let probes = NatProbes {
+        local_addr,
+        mapped_addrs,
+        delivered_from_other_port,
+        delivered_from_other_ip,
+    };
+    let nat_type = probes.nat_type();
+    info!("Detected {:?} NAT from {:?}", nat_type, probes);
+    Ok(nat_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::ip_echo_server};
+
+    #[test]
+    fn test_nat_type() {
+        let local_addr = SocketAddr::from(([192, 168, 1, 7], 8001));
+        let mapped_addr = SocketAddr::from(([203, 0, 113, 7], 41_000));
+        let probes = |mapped_addrs: Vec<SocketAddr>,
+                      delivered_from_other_port: bool,
+                      delivered_from_other_ip: Option<bool>| {
+            NatProbes {
+                local_addr,
+                mapped_addrs,
+                delivered_from_other_port,
+                delivered_from_other_ip,
+            }
+            .nat_type()
+        };
+
+        assert_eq!(
+            probes(vec![local_addr, local_addr], true, None),
+            NatType::Open
+        );
+        assert_eq!(
+            probes(vec![mapped_addr, mapped_addr], true, Some(true)),
+            NatType::FullCone
+        );
+        assert_eq!(
+            probes(vec![mapped_addr, mapped_addr], true, Some(false)),
+            NatType::RestrictedCone
+        );
+        // A single server can't rule out a restricted cone
+        assert_eq!(
+            probes(vec![mapped_addr, mapped_addr], true, None),
+            NatType::RestrictedCone
+        );
+        assert_eq!(
+            probes(vec![mapped_addr, mapped_addr], false, Some(false)),
+            NatType::PortRestrictedCone
+        );
+        let other_mapped_addr = SocketAddr::new(mapped_addr.ip(), 41_001);
+        assert_eq!(
+            probes(vec![mapped_addr, other_mapped_addr], false, Some(false)),
+            NatType::Symmetric
+        );
+        assert!(!NatType::Symmetric.allows_hole_punching());
+        assert!(NatType::PortRestrictedCone.allows_hole_punching());
+    }
+
+    #[test]
+    fn test_detect_nat_type() {
+        solana_logger::setup();
+        let ip_echo_servers: Vec<_> = (0..2)
+            .map(|_| {
+                let tcp_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+                let ip_echo_server_addr = tcp_listener.local_addr().unwrap();
+                (ip_echo_server_addr, ip_echo_server(tcp_listener))
+            })
+            .collect();
+        let ip_echo_server_addrs: Vec<_> = ip_echo_servers.iter().map(|(addr, _)| *addr).collect();
+
+        // Nothing stands between loopback sockets
+        assert_eq!(
+            detect_nat_type(&ip_echo_server_addrs).unwrap(),
+            NatType::Open
+        );
+        assert_eq!(
+            detect_nat_type(&ip_echo_server_addrs[..1]).unwrap(),
+            NatType::Open
+        );
+        assert!(matches!(
+            detect_nat_type(&[]),
+            Err(NetUtilsError::InvalidArgument(_))
+        ));
+    }
+}
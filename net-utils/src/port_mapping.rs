@@ -0,0 +1,955 @@
+//! Port mappings requested from the NAT gateway in front of this machine through NAT-PMP
+//! (RFC 6886) or UPnP IGD, so that nodes behind consumer routers are reachable without
+//! forwarding their ports by hand.  Mappings are leased, `PortMapper` renews them in the
+//! background and removes them when dropped
+use {
+    log::*,
+    std::{
+        fmt,
+        io::{self, Read, Write},
+        iter,
+        net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+        sync::mpsc::{channel, RecvTimeoutError, Sender},
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    },
+    url::Url,
+};
+
+const NATPMP_PORT: u16 = 5351;
+const NATPMP_VERSION: u8 = 0;
+const NATPMP_OPCODE_EXTERNAL_ADDRESS: u8 = 0;
+const NATPMP_RESPONSE_BIT: u8 = 128;
+// NAT-PMP clients retransmit unanswered requests with doubling timeouts, starting at 250ms
+const NATPMP_INITIAL_TIMEOUT: Duration = Duration::from_millis(250);
+
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+const UPNP_GATEWAY_DEVICE_TYPE: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const UPNP_WAN_SERVICE_TYPES: [&str; 2] = ["WANIPConnection", "WANPPPConnection"];
+// Some gateways only accept mappings without expiration
+const UPNP_ONLY_PERMANENT_LEASES_SUPPORTED: &str = "725";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortMappingProtocol {
+    Tcp,
+    Udp,
+}
+
+impl PortMappingProtocol {
+    fn natpmp_opcode(self) -> u8 {
+        match self {
+            Self::Udp => 1,
+            Self::Tcp => 2,
+        }
+    }
+
+    fn upnp_name(self) -> &'static str {
+        match self {
+            Self::Tcp => "TCP",
+            Self::Udp => "UDP",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PortMappingConfig {
+    /// Lifetime requested for the mappings, they are renewed halfway through it
+    pub lease_duration: Duration,
+    /// How long to wait for the gateway to answer a request
+    pub timeout: Duration,
+    /// Description of the mappings in the gateway's UPnP port mapping table
+    pub description: String,
+}
+
+impl Default for PortMappingConfig {
+    fn default() -> Self {
+        Self {
+            lease_duration: Duration::from_secs(60 * 60),
+            timeout: Duration::from_secs(2),
+            description: "solana".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Gateway {
+    NatPmp(SocketAddr),
+    Upnp {
+        control_url: Url,
+        service_type: String,
+        // Address of this machine on the gateway's network, the target of the mappings
+        internal_ip: IpAddr,
+    },
+}
+
+impl fmt::Display for Gateway {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NatPmp(addr) => write!(f, "NAT-PMP gateway {}", addr),
+            Self::Upnp { control_url, .. } => write!(f, "UPnP gateway {}", control_url),
+        }
+    }
+}
+
+/// Port mappings kept alive on the NAT gateway until the `PortMapper` is shut down or dropped
+pub struct PortMapper {
+    gateway: Gateway,
+    mappings: Vec<(PortMappingProtocol, u16)>,
+    exit_sender: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PortMapper {
+    /// Discovers the NAT gateway and maps each of `ports` for TCP and UDP to the same external
+    /// port.  Fails if no gateway is found or none of the ports could be mapped
+    pub fn start<I: IntoIterator<Item = u16>>(
+        ports: I,
+        config: PortMappingConfig,
+    ) -> io::Result<Self> {
+        let gateway = discover_gateway(config.timeout)?;
+        Self::start_with_gateway(gateway, ports, config)
+    }
+
+    fn start_with_gateway<I: IntoIterator<Item = u16>>(
+        gateway: Gateway,
+        ports: I,
+        config: PortMappingConfig,
+    ) -> io::Result<Self> {
+        let requested: Vec<_> = ports
+            .into_iter()
+            .flat_map(|port| {
+                iter::once((PortMappingProtocol::Tcp, port))
+                    .chain(iter::once((PortMappingProtocol::Udp, port)))
+            })
+            .collect();
+        info!("Requesting port mappings from {}", gateway);
+
+        let mut mappings = vec![];
+        let mut lifetimes = vec![];
+        let mut errors = vec![];
+        for (protocol, port) in requested {
+            match add_mapping(&gateway, protocol, port, &config) {
+                Ok(lifetime) => {
+                    mappings.push((protocol, port));
+                    lifetimes.push(lifetime);
+                }
+                Err(err) => {
+                    warn!(
+                        "Unable to map {} port {}: {}",
+                        protocol.upnp_name(),
+                        port,
+                        err
+                    );
+                    errors.push(format!("{} port {}: {}", protocol.upnp_name(), port, err));
+                }
+            }
+        }
+        if mappings.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!("{} mapped no port: {}", gateway, errors.join("; ")),
+            ));
+        }
+        info!("Mapped {:?} through {}", mappings, gateway);
+
+        let (exit_sender, exit_receiver) = channel();
+        let thread = {
+            let gateway = gateway.clone();
+            let mappings = mappings.clone();
+            thread::Builder::new()
+                .name("solPortMapper".to_string())
+                .spawn(move || {
+                    let mut interval = renew_interval(&lifetimes, &config);
+                    while let Err(RecvTimeoutError::Timeout) = exit_receiver.recv_timeout(interval)
+                    {
+                        lifetimes.clear();
+                        for (protocol, port) in &mappings {
+                            match add_mapping(&gateway, *protocol, *port, &config) {
+                                Ok(lifetime) => lifetimes.push(lifetime),
+                                Err(err) => warn!(
+                                    "Unable to renew the mapping of {} port {}: {}",
+                                    protocol.upnp_name(),
+                                    port,
+                                    err
+                                ),
+                            }
+                        }
+                        interval = renew_interval(&lifetimes, &config);
+                    }
+                    for (protocol, port) in &mappings {
+                        if let Err(err) = delete_mapping(&gateway, *protocol, *port, &config) {
+                            warn!(
+                                "Unable to remove the mapping of {} port {}: {}",
+                                protocol.upnp_name(),
+                                port,
+                                err
+                            );
+                        }
+                    }
+                    info!("Removed the port mappings from {}", gateway);
+                })?
+        };
+
+        Ok(Self {
+            gateway,
+            mappings,
+            exit_sender: Some(exit_sender),
+            thread: Some(thread),
+        })
+    }
+
+    /// The mapped ports
+    pub fn mappings(&self) -> &[(PortMappingProtocol, u16)] {
+        &self.mappings
+    }
+
+    /// Removes the mappings from the gateway
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        // Dropping the sender wakes the renewal thread up to remove the mappings
+        self.exit_sender.take();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                warn!("Port mapping thread for {} panicked", self.gateway);
+            }
+        }
+    }
+}
+
+impl Drop for PortMapper {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Mappings are renewed halfway through the shortest lifetime the gateway granted, permanent
+/// mappings (zero lifetime) are renewed in case the gateway restarted
+fn renew_interval(lifetimes: &[Duration], config: &PortMappingConfig) -> Duration {
+    let lifetime = lifetimes
+        .iter()
+        .filter(|lifetime| **lifetime > Duration::default())
+        .min()
+        .unwrap_or(&config.lease_duration);
+    (*lifetime.min(&config.lease_duration) / 2).max(Duration::from_millis(10))
+}
+
+/// Requests a mapping of the same external `port` to `port`, returning the lifetime the gateway
+/// granted
+fn add_mapping(
+    gateway: &Gateway,
+    protocol: PortMappingProtocol,
+    port: u16,
+    config: &PortMappingConfig,
+) -> io::Result<Duration> {
+    match gateway {
+        Gateway::NatPmp(gateway_addr) => {
+            let (external_port, lifetime) = natpmp_map(
+                gateway_addr,
+                protocol,
+                port,
+                config.lease_duration,
+                config.timeout,
+            )?;
+            if external_port != port {
+                warn!(
+                    "{} mapped {} port {} to external port {}, peers will not reach it",
+                    gateway,
+                    protocol.upnp_name(),
+                    port,
+                    external_port
+                );
+            }
+            Ok(lifetime)
+        }
+        Gateway::Upnp {
+            control_url,
+            service_type,
+            internal_ip,
+        } => {
+            let add_port_mapping = |lease_duration: Duration| {
+                upnp_action(
+                    control_url,
+                    service_type,
+                    "AddPortMapping",
+                    &[
+                        ("NewRemoteHost", String::new()),
+                        ("NewExternalPort", port.to_string()),
+                        ("NewProtocol", protocol.upnp_name().to_string()),
+                        ("NewInternalPort", port.to_string()),
+                        ("NewInternalClient", internal_ip.to_string()),
+                        ("NewEnabled", "1".to_string()),
+                        ("NewPortMappingDescription", config.description.clone()),
+                        ("NewLeaseDuration", lease_duration.as_secs().to_string()),
+                    ],
+                    config.timeout,
+                )
+            };
+            match add_port_mapping(config.lease_duration) {
+                Ok(_) => Ok(config.lease_duration),
+                Err(err)
+                    if err
+                        .to_string()
+                        .contains(&format!("error {}:", UPNP_ONLY_PERMANENT_LEASES_SUPPORTED)) =>
+                {
+                    add_port_mapping(Duration::default()).map(|_| Duration::default())
+                }
+                Err(err) => Err(err),
+            }
+        }
+    }
+}
+
+fn delete_mapping(
+    gateway: &Gateway,
+    protocol: PortMappingProtocol,
+    port: u16,
+    config: &PortMappingConfig,
+) -> io::Result<()> {
+    match gateway {
+        // A zero lifetime deletes the mapping
+        Gateway::NatPmp(gateway_addr) => natpmp_map(
+            gateway_addr,
+            protocol,
+            port,
+            Duration::default(),
+            config.timeout,
+        )
+        .map(|_| ()),
+        Gateway::Upnp {
+            control_url,
+            service_type,
+            ..
+        } => upnp_action(
+            control_url,
+            service_type,
+            "DeletePortMapping",
+            &[
+                ("NewRemoteHost", String::new()),
+                ("NewExternalPort", port.to_string()),
+                ("NewProtocol", protocol.upnp_name().to_string()),
+            ],
+            config.timeout,
+        )
+        .map(|_| ()),
+    }
+}
+
+/// Finds a NAT-PMP gateway at the default route, or else a UPnP internet gateway device on the
+/// local network
+fn discover_gateway(timeout: Duration) -> io::Result<Gateway> {
+    let mut errors = vec![];
+    match default_gateway() {
+        Some(gateway_ip) => {
+            let gateway_addr = SocketAddr::new(IpAddr::V4(gateway_ip), NATPMP_PORT);
+            let request = [NATPMP_VERSION, NATPMP_OPCODE_EXTERNAL_ADDRESS];
+            match natpmp_request(&gateway_addr, &request, timeout) {
+                Ok(_) => return Ok(Gateway::NatPmp(gateway_addr)),
+                Err(err) => errors.push(format!("NAT-PMP gateway {}: {}", gateway_addr, err)),
+            }
+        }
+        None => errors.push("no default IPv4 gateway for NAT-PMP".to_string()),
+    }
+    match ssdp_search(timeout) {
+        Ok(locations) => {
+            for location in locations {
+                match upnp_gateway(&location, timeout) {
+                    Ok(gateway) => return Ok(gateway),
+                    Err(err) => errors.push(format!("UPnP device {}: {}", location, err)),
+                }
+            }
+        }
+        Err(err) => errors.push(format!("UPnP discovery: {}", err)),
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("No port mapping gateway found: {}", errors.join("; ")),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn default_gateway() -> Option<Ipv4Addr> {
+    parse_default_gateway(&std::fs::read_to_string("/proc/net/route").ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn default_gateway() -> Option<Ipv4Addr> {
+    None
+}
+
+/// The gateway of the default route in a /proc/net/route table
+fn parse_default_gateway(route_table: &str) -> Option<Ipv4Addr> {
+    route_table.lines().skip(1).find_map(|line| {
+        let fields: Vec<_> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            [_iface, "00000000", gateway, ..] => {
+                // Addresses are in network byte order, printed as a native endian integer
+                let gateway = u32::from_str_radix(gateway, 16).ok()?;
+                Some(Ipv4Addr::from(gateway.to_ne_bytes())).filter(|ip| !ip.is_unspecified())
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Sends a NAT-PMP request, retransmitting it with doubling timeouts until the gateway answers
+/// or `timeout` expires.  Returns the successful response
+fn natpmp_request(
+    gateway_addr: &SocketAddr,
+    request: &[u8],
+    timeout: Duration,
+) -> io::Result<Vec<u8>> {
+    let udp_socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))?;
+    let deadline = Instant::now() + timeout;
+    let mut retransmit_timeout = NATPMP_INITIAL_TIMEOUT;
+    let mut buf = [0u8; 16];
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        udp_socket.send_to(request, gateway_addr)?;
+        let retransmit_deadline = Instant::now() + retransmit_timeout.min(remaining);
+        retransmit_timeout *= 2;
+        while let Some(read_timeout) = retransmit_deadline.checked_duration_since(Instant::now()) {
+            if read_timeout == Duration::default() {
+                break;
+            }
+            udp_socket.set_read_timeout(Some(read_timeout))?;
+            let (len, from) = match udp_socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(err)
+                    if err.kind() == io::ErrorKind::WouldBlock
+                        || err.kind() == io::ErrorKind::TimedOut =>
+                {
+                    break
+                }
+                Err(err) => return Err(err),
+            };
+            let response = &buf[..len];
+            if from.ip() != gateway_addr.ip()
+                || len < 8
+                || response[0] != NATPMP_VERSION
+                || response[1] != request[1] | NATPMP_RESPONSE_BIT
+            {
+                continue;
+            }
+            return match u16::from_be_bytes([response[2], response[3]]) {
+                0 => Ok(response.to_vec()),
+                1 => Err(natpmp_error("unsupported version")),
+                2 => Err(natpmp_error("not authorized")),
+                3 => Err(natpmp_error("network failure")),
+                4 => Err(natpmp_error("out of resources")),
+                5 => Err(natpmp_error("unsupported opcode")),
+                result => Err(natpmp_error(&format!("result code {}", result))),
+            };
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!("No NAT-PMP response from {}", gateway_addr),
+    ))
+}
+
+fn natpmp_error(error: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("NAT-PMP {}", error))
+}
+
+/// Maps `port` for `lifetime`, deleting the mapping for a zero lifetime.  Returns the external
+/// port and the lifetime the gateway granted
+fn natpmp_map(
+    gateway_addr: &SocketAddr,
+    protocol: PortMappingProtocol,
+    port: u16,
+    lifetime: Duration,
+    timeout: Duration,
+) -> io::Result<(u16, Duration)> {
+    let suggested_external_port = if lifetime == Duration::default() {
+        0
+    } else {
+        port
+    };
+    let mut request = vec![NATPMP_VERSION, protocol.natpmp_opcode(), 0, 0];
+    request.extend_from_slice(&port.to_be_bytes());
+    request.extend_from_slice(&suggested_external_port.to_be_bytes());
+    request.extend_from_slice(&(lifetime.as_secs() as u32).to_be_bytes());
+    let response = natpmp_request(gateway_addr, &request, timeout)?;
+    if response.len() < 16 || response[8..10] != port.to_be_bytes() {
+        return Err(natpmp_error("malformed mapping response"));
+    }
+    let external_port = u16::from_be_bytes([response[10], response[11]]);
+    let lifetime = u32::from_be_bytes([response[12], response[13], response[14], response[15]]);
+    Ok((external_port, Duration::from_secs(lifetime as u64)))
+}
+
+/// Locations of the descriptions of the UPnP internet gateway devices answering an SSDP search
+fn ssdp_search(timeout: Duration) -> io::Result<Vec<Url>> {
+    let udp_socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))?;
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\n\r\n",
+        SSDP_ADDR,
+        UPNP_GATEWAY_DEVICE_TYPE,
+        timeout.as_secs().max(1),
+    );
+    udp_socket.send_to(request.as_bytes(), SSDP_ADDR)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut locations = vec![];
+    let mut buf = [0u8; 2048];
+    while let Some(read_timeout) = deadline.checked_duration_since(Instant::now()) {
+        if read_timeout == Duration::default() {
+            break;
+        }
+        udp_socket.set_read_timeout(Some(read_timeout))?;
+        let len = match udp_socket.recv_from(&mut buf) {
+            Ok((len, _from)) => len,
+            Err(err)
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::TimedOut =>
+            {
+                break
+            }
+            Err(err) => return Err(err),
+        };
+        let response = String::from_utf8_lossy(&buf[..len]);
+        let location = response
+            .lines()
+            .filter_map(|line| split_once(line, ":"))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("location"))
+            .and_then(|(_, value)| Url::parse(value.trim()).ok());
+        if let Some(location) = location {
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+    }
+    Ok(locations)
+}
+
+/// The WAN connection service of the UPnP internet gateway device described at `location`
+fn upnp_gateway(location: &Url, timeout: Duration) -> io::Result<Gateway> {
+    let (status, description, local_addr) = http_request(location, "GET", &[], "", timeout)?;
+    if status != 200 {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("HTTP status {} fetching {}", status, location),
+        ));
+    }
+    description
+        .split("<service>")
+        .skip(1)
+        .find_map(|service| {
+            let service_type = xml_element(service, "serviceType")?;
+            if !UPNP_WAN_SERVICE_TYPES
+                .iter()
+                .any(|wan_service_type| service_type.contains(wan_service_type))
+            {
+                return None;
+            }
+            let control_url = location.join(xml_element(service, "controlURL")?).ok()?;
+            Some(Gateway::Upnp {
+                control_url,
+                service_type: service_type.to_string(),
+                internal_ip: local_addr.ip(),
+            })
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "No WAN connection service in the device description",
+            )
+        })
+}
+
+/// Invokes a SOAP action of a UPnP service, returning the response
+fn upnp_action(
+    control_url: &Url,
+    service_type: &str,
+    action: &str,
+    arguments: &[(&str, String)],
+    timeout: Duration,
+) -> io::Result<String> {
+    let arguments: String = arguments
+        .iter()
+        .map(|(name, value)| format!("<{0}>{1}</{0}>", name, value))
+        .collect();
+    let body = format!(
+        "<?xml version=\"1.0\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{0} xmlns:u=\"{1}\">{2}</u:{0}></s:Body></s:Envelope>",
+        action, service_type, arguments
+    );
+    let (status, response, _) = http_request(
+        control_url,
+        "POST",
+        &[
+            ("Content-Type", "text/xml; charset=\"utf-8\"".to_string()),
+            ("SOAPAction", format!("\"{}#{}\"", service_type, action)),
+        ],
+        &body,
+        timeout,
+    )?;
+    if status != 200 {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "UPnP {} failed with HTTP status {}, error {}: {}",
+                action,
+                status,
+                xml_element(&response, "errorCode").unwrap_or("unknown"),
+                xml_element(&response, "errorDescription").unwrap_or_default()
+            ),
+        ));
+    }
+    Ok(response)
+}
+
+/// The text of the first `name` element in `xml`
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", name))? + name.len() + 2;
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    Some(xml[start..end].trim())
+}
+
+/// A minimal HTTP/1.1 client for the gateway's UPnP endpoints.  Returns the response status and
+/// body, and the local address of the connection
+fn http_request(
+    url: &Url,
+    method: &str,
+    headers: &[(&str, String)],
+    body: &str,
+    timeout: Duration,
+) -> io::Result<(u16, String, SocketAddr)> {
+    let invalid_url = || io::Error::new(io::ErrorKind::InvalidInput, format!("Bad URL {}", url));
+    let host = url.host_str().ok_or_else(invalid_url)?;
+    let port = url.port_or_known_default().ok_or_else(invalid_url)?;
+    let addr = (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(invalid_url)?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let path = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}:{}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        host,
+        port,
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = vec![];
+    stream.read_to_end(&mut response)?;
+    let (status, body) = parse_http_response(&response).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Malformed HTTP response from {}", url),
+        )
+    })?;
+    Ok((status, body, stream.local_addr()?))
+}
+
+fn split_once<'a>(text: &'a str, delimiter: &str) -> Option<(&'a str, &'a str)> {
+    let index = text.find(delimiter)?;
+    Some((&text[..index], &text[index + delimiter.len()..]))
+}
+
+fn parse_http_response(response: &[u8]) -> Option<(u16, String)> {
+    let response = String::from_utf8_lossy(response);
+    let (head, body) = split_once(&response, "\r\n\r\n")?;
+    let mut lines = head.lines();
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let chunked = lines.any(|line| {
+        split_once(line, ":").map_or(false, |(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+    if !chunked {
+        return Some((status, body.to_string()));
+    }
+    let mut rest = body;
+    let mut body = String::new();
+    loop {
+        let (size, tail) = split_once(rest, "\r\n")?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        if size == 0 {
+            return Some((status, body));
+        }
+        body.push_str(tail.get(..size)?);
+        rest = tail.get(size..)?.strip_prefix("\r\n")?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::{
+            net::TcpListener,
+            sync::{Arc, Mutex},
+        },
+    };
+
+    fn test_config(lease_duration: Duration) -> PortMappingConfig {
+        PortMappingConfig {
+            lease_duration,
+            timeout: Duration::from_secs(1),
+            ..PortMappingConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_default_gateway() {
+        let route_table = "\
+            Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+            eth0\t0000A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+            eth0\t00000000\t0101A8C0\t0003\t0\t0\t0\t00000000\n";
+        let gateway = u32::from_str_radix("0101A8C0", 16).unwrap().to_ne_bytes();
+        assert_eq!(
+            parse_default_gateway(route_table),
+            Some(Ipv4Addr::from(gateway))
+        );
+        let route_table = "\
+            Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+            eth0\t0000A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n";
+        assert_eq!(parse_default_gateway(route_table), None);
+    }
+
+    #[test]
+    fn test_parse_http_response() {
+        assert_eq!(
+            parse_http_response(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\nbody"),
+            Some((200, "body".to_string()))
+        );
+        assert_eq!(
+            parse_http_response(
+                b"HTTP/1.1 500 Internal Server Error\r\nTransfer-Encoding: chunked\r\n\r\n\
+                  3\r\nbod\r\n1;ext\r\ny\r\n0\r\n\r\n"
+            ),
+            Some((500, "body".to_string()))
+        );
+        assert_eq!(parse_http_response(b"HTTP/1.1 200 OK\r\n"), None);
+    }
+
+    #[test]
+    fn test_natpmp_port_mapper() {
+        let gateway_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let gateway_addr = gateway_socket.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(vec![]));
+        {
+            let requests = requests.clone();
+            thread::spawn(move || {
+                let mut buf = [0u8; 64];
+                while let Ok((len, from)) = gateway_socket.recv_from(&mut buf) {
+                    let request = buf[..len].to_vec();
+                    // Grants half of the requested lifetime
+                    let lifetime =
+                        u32::from_be_bytes([request[8], request[9], request[10], request[11]]) / 2;
+                    let mut response = vec![0, request[1] | NATPMP_RESPONSE_BIT, 0, 0];
+                    response.extend_from_slice(&[0; 4]);
+                    response.extend_from_slice(&request[4..6]);
+                    response.extend_from_slice(&request[6..8]);
+                    response.extend_from_slice(&lifetime.to_be_bytes());
+                    gateway_socket.send_to(&response, from).unwrap();
+                    requests.lock().unwrap().push(request);
+                }
+            });
+        }
+        let opcode_port_lifetime = |request: &Vec<u8>| {
+            (
+                request[1],
+                u16::from_be_bytes([request[4], request[5]]),
+                u32::from_be_bytes([request[8], request[9], request[10], request[11]]),
+            )
+        };
+
+        let port_mapper = PortMapper::start_with_gateway(
+            Gateway::NatPmp(gateway_addr),
+            8001..8003,
+            test_config(Duration::from_secs(4)),
+        )
+        .unwrap();
+        assert_eq!(port_mapper.mappings().len(), 4);
+        assert_eq!(
+            requests
+                .lock()
+                .unwrap()
+                .iter()
+                .map(opcode_port_lifetime)
+                .collect::<Vec<_>>(),
+            vec![(2, 8001, 4), (1, 8001, 4), (2, 8002, 4), (1, 8002, 4)]
+        );
+
+        // Mappings are renewed halfway through the granted lifetime
+        thread::sleep(Duration::from_millis(1_500));
+        assert_eq!(requests.lock().unwrap().len(), 8);
+
+        port_mapper.shutdown();
+        let requests = requests.lock().unwrap();
+        assert_eq!(
+            requests[8..]
+                .iter()
+                .map(opcode_port_lifetime)
+                .collect::<Vec<_>>(),
+            vec![(2, 8001, 0), (1, 8001, 0), (2, 8002, 0), (1, 8002, 0)]
+        );
+    }
+
+    #[test]
+    fn test_natpmp_unanswered() {
+        let gateway_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let gateway_addr = gateway_socket.local_addr().unwrap();
+        assert!(PortMapper::start_with_gateway(
+            Gateway::NatPmp(gateway_addr),
+            iter::once(8001),
+            PortMappingConfig {
+                timeout: Duration::from_millis(300),
+                ..PortMappingConfig::default()
+            },
+        )
+        .is_err());
+    }
+
+    /// A UPnP internet gateway device serving its description and the SOAP actions of its
+    /// WANIPConnection service, recording the actions.  It only supports permanent mappings
+    fn upnp_gateway_device() -> (Url, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let location = Url::parse(&format!(
+            "http://{}/rootDesc.xml",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let actions = Arc::new(Mutex::new(vec![]));
+        {
+            let actions = actions.clone();
+            thread::spawn(move || {
+                for mut stream in listener.incoming().flatten() {
+                    let mut request = vec![0u8; 4096];
+                    let mut len = 0;
+                    // Read up to the end of the body, Content-Length being the last header
+                    loop {
+                        len += stream.read(&mut request[len..]).unwrap();
+                        let text = String::from_utf8_lossy(&request[..len]);
+                        if let Some((head, body)) = split_once(&text, "\r\n\r\n") {
+                            let content_length = head
+                                .lines()
+                                .find_map(|line| line.strip_prefix("Content-Length: "))
+                                .map_or(0, |length| length.parse().unwrap());
+                            if body.len() >= content_length {
+                                break;
+                            }
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request[..len]).to_string();
+                    let (status, body) = if request.starts_with("GET /rootDesc.xml ") {
+                        (
+                            "200 OK",
+                            "<root><device><serviceList>\
+                             <service><serviceType>urn:schemas-upnp-org:service:Layer3Forwarding:1</serviceType>\
+                             <controlURL>/l3f</controlURL></service>\
+                             <service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType>\
+                             <controlURL>/ctl/IPConn</controlURL></service>\
+                             </serviceList></device></root>"
+                                .to_string(),
+                        )
+                    } else if request.starts_with("POST /ctl/IPConn ") {
+                        let action = xml_element(&request, "NewProtocol")
+                            .map(|protocol| {
+                                format!(
+                                    "{} {} {} {}",
+                                    if request.contains("u:AddPortMapping") {
+                                        "add"
+                                    } else {
+                                        "delete"
+                                    },
+                                    protocol,
+                                    xml_element(&request, "NewExternalPort").unwrap(),
+                                    xml_element(&request, "NewLeaseDuration").unwrap_or("-"),
+                                )
+                            })
+                            .unwrap();
+                        actions.lock().unwrap().push(action);
+                        match xml_element(&request, "NewLeaseDuration") {
+                            Some(lease_duration) if lease_duration != "0" => (
+                                "500 Internal Server Error",
+                                "<s:Envelope><s:Body><s:Fault><detail><UPnPError>\
+                                 <errorCode>725</errorCode>\
+                                 <errorDescription>OnlyPermanentLeasesSupported</errorDescription>\
+                                 </UPnPError></detail></s:Fault></s:Body></s:Envelope>"
+                                    .to_string(),
+                            ),
+                            _ => ("200 OK", "<s:Envelope></s:Envelope>".to_string()),
+                        }
+                    } else {
+                        ("404 Not Found", String::new())
+                    };
+                    let _ = stream.write_all(
+                        format!(
+                            "HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n{}",
+                            status,
+                            body.len(),
+                            body
+                        )
+                        .as_bytes(),
+                    );
+                }
+            });
+        }
+        (location, actions)
+    }
+
+    #[test]
+    fn test_upnp_port_mapper() {
+        let (location, actions) = upnp_gateway_device();
+        let timeout = Duration::from_secs(1);
+        let gateway = upnp_gateway(&location, timeout).unwrap();
+        assert_eq!(
+            gateway,
+            Gateway::Upnp {
+                control_url: location.join("/ctl/IPConn").unwrap(),
+                service_type: "urn:schemas-upnp-org:service:WANIPConnection:1".to_string(),
+                internal_ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            }
+        );
+        assert!(upnp_gateway(&location.join("/missing.xml").unwrap(), timeout).is_err());
+
+        let port_mapper = PortMapper::start_with_gateway(
+            gateway,
+            iter::once(8001),
+            test_config(Duration::from_secs(3_600)),
+        )
+        .unwrap();
+        assert_eq!(
+            port_mapper.mappings(),
+            &[
+                (PortMappingProtocol::Tcp, 8001),
+                (PortMappingProtocol::Udp, 8001)
+            ]
+        );
+        port_mapper.shutdown();
+        // Leases are retried as permanent mappings when the gateway only supports those
+        assert_eq!(
+            *actions.lock().unwrap(),
+            vec![
+                "add TCP 8001 3600",
+                "add TCP 8001 0",
+                "add UDP 8001 3600",
+                "add UDP 8001 0",
+                "delete TCP 8001 -",
+                "delete UDP 8001 -",
+            ]
+        );
+    }
+}
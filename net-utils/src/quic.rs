@@ -0,0 +1,163 @@
+//! Just enough of the version independent properties of QUIC (RFC 8999) to tell whether a QUIC
+//! endpoint is reachable.  Endpoints answer a long header packet of a version they don't support
+//! with a Version Negotiation packet, before setting up any connection state, so a packet of a
+//! version reserved to exercise version negotiation probes any QUIC endpoint without a handshake
+
+/// Versions of the form 0x?a?a?a?a are reserved for exercising version negotiation, no endpoint
+/// supports them
+pub(crate) const PROBE_VERSION: u32 = 0x1a2a_3a4a;
+const RESERVED_VERSION_MASK: u32 = 0x0f0f_0f0f;
+const RESERVED_VERSION_PATTERN: u32 = 0x0a0a_0a0a;
+
+/// Endpoints ignore initial packets in datagrams smaller than this, probes are padded to it
+pub(crate) const PROBE_DATAGRAM_SIZE: usize = 1200;
+
+/// Version advertised by the Version Negotiation packets answering probes, QUIC version 1
+const SUPPORTED_VERSION: u32 = 0x0000_0001;
+
+pub(crate) const CONNECTION_ID_LENGTH: usize = 8;
+pub(crate) type ConnectionId = [u8; CONNECTION_ID_LENGTH];
+
+const LONG_HEADER_FORM: u8 = 0x80;
+// The fixed bit RFC 9000 sets in the long header of every version 1 packet
+const FIXED_BIT: u8 = 0x40;
+const MAX_CONNECTION_ID_LENGTH: usize = 255;
+
+struct LongHeader<'a> {
+    version: u32,
+    destination_connection_id: &'a [u8],
+    source_connection_id: &'a [u8],
+}
+
+fn parse_long_header(packet: &[u8]) -> Option<LongHeader<'_>> {
+    if packet.len() < 7 || packet[0] & LONG_HEADER_FORM == 0 {
+        return None;
+    }
+    let version = u32::from_be_bytes([packet[1], packet[2], packet[3], packet[4]]);
+    let mut rest = &packet[5..];
+    let mut connection_id = || {
+        let (length, tail) = rest.split_first()?;
+        let length = *length as usize;
+        if length > MAX_CONNECTION_ID_LENGTH || tail.len() < length {
+            return None;
+        }
+        let (connection_id, tail) = tail.split_at(length);
+        rest = tail;
+        Some(connection_id)
+    };
+    let destination_connection_id = connection_id()?;
+    let source_connection_id = connection_id()?;
+    Some(LongHeader {
+        version,
+        destination_connection_id,
+        source_connection_id,
+    })
+}
+
+fn long_header(
+    first_byte: u8,
+    version: u32,
+    destination_connection_id: &[u8],
+    source_connection_id: &[u8],
+) -> Vec<u8> {
+    let mut packet = vec![first_byte];
+    packet.extend_from_slice(&version.to_be_bytes());
+    packet.push(destination_connection_id.len() as u8);
+    packet.extend_from_slice(destination_connection_id);
+    packet.push(source_connection_id.len() as u8);
+    packet.extend_from_slice(source_connection_id);
+    packet
+}
+
+/// A long header packet of `PROBE_VERSION`, padded to `PROBE_DATAGRAM_SIZE`
+pub(crate) fn probe_packet(
+    destination_connection_id: &ConnectionId,
+    source_connection_id: &ConnectionId,
+) -> Vec<u8> {
+    let mut packet = long_header(
+        LONG_HEADER_FORM | FIXED_BIT,
+        PROBE_VERSION,
+        destination_connection_id,
+        source_connection_id,
+    );
+    packet.resize(PROBE_DATAGRAM_SIZE, 0);
+    packet
+}
+
+/// Whether `packet` is the Version Negotiation packet answering the probe sent with the given
+/// connection IDs, which the answer swaps
+pub(crate) fn is_probe_answer(
+    packet: &[u8],
+    destination_connection_id: &ConnectionId,
+    source_connection_id: &ConnectionId,
+) -> bool {
+    parse_long_header(packet).map_or(false, |header| {
+        header.version == 0
+            && header.destination_connection_id == source_connection_id
+            && header.source_connection_id == destination_connection_id
+    })
+}
+
+/// The Version Negotiation packet a QUIC endpoint answers `packet` with if it is a probe, for
+/// sockets whose QUIC endpoint has not started yet
+pub(crate) fn answer_probe(packet: &[u8]) -> Option<Vec<u8>> {
+    if packet.len() < PROBE_DATAGRAM_SIZE {
+        return None;
+    }
+    let header = parse_long_header(packet)?;
+    if header.version & RESERVED_VERSION_MASK != RESERVED_VERSION_PATTERN {
+        return None;
+    }
+    let mut answer = long_header(
+        LONG_HEADER_FORM | rand::random::<u8>(),
+        0,
+        header.source_connection_id,
+        header.destination_connection_id,
+    );
+    answer.extend_from_slice(&SUPPORTED_VERSION.to_be_bytes());
+    Some(answer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_answer() {
+        let destination_connection_id = [1; CONNECTION_ID_LENGTH];
+        let source_connection_id = [2; CONNECTION_ID_LENGTH];
+        let probe = probe_packet(&destination_connection_id, &source_connection_id);
+        assert_eq!(probe.len(), PROBE_DATAGRAM_SIZE);
+
+        let answer = answer_probe(&probe).unwrap();
+        assert!(is_probe_answer(
+            &answer,
+            &destination_connection_id,
+            &source_connection_id
+        ));
+        // Answers to other probes don't count
+        assert!(!is_probe_answer(
+            &answer,
+            &source_connection_id,
+            &destination_connection_id
+        ));
+
+        // Endpoints ignore probes in short datagrams
+        assert_eq!(answer_probe(&probe[..PROBE_DATAGRAM_SIZE - 1]), None);
+        // and packets of versions they may support
+        let mut packet = probe.clone();
+        packet[1..5].copy_from_slice(&SUPPORTED_VERSION.to_be_bytes());
+        assert_eq!(answer_probe(&packet), None);
+        // and short header packets
+        let mut packet = probe;
+        packet[0] &= !LONG_HEADER_FORM;
+        assert_eq!(answer_probe(&packet), None);
+
+        // Truncated packets are no answer
+        assert!(!is_probe_answer(
+            &answer[..answer.len() - 4 - CONNECTION_ID_LENGTH],
+            &destination_connection_id,
+            &source_connection_id
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+//! A minimal STUN client (RFC 5389), sending Binding requests to learn the address a STUN server
+//! sees them come from
+use {
+    rand::{thread_rng, Rng},
+    std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+
+const HEADER_LENGTH: usize = 20;
+const MAGIC_COOKIE: u32 = 0x2112_a442;
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS_RESPONSE: u16 = 0x0101;
+const ATTRIBUTE_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTRIBUTE_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+const FAMILY_IPV4: u8 = 0x01;
+const FAMILY_IPV6: u8 = 0x02;
+
+pub(crate) type TransactionId = [u8; 12];
+
+pub(crate) fn new_transaction_id() -> TransactionId {
+    thread_rng().gen()
+}
+
+/// A Binding request without attributes
+pub(crate) fn binding_request(transaction_id: &TransactionId) -> Vec<u8> {
+    let mut request = Vec::with_capacity(HEADER_LENGTH);
+    request.extend_from_slice(&BINDING_REQUEST.to_be_bytes());
+    request.extend_from_slice(&0u16.to_be_bytes());
+    request.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    request.extend_from_slice(transaction_id);
+    request
+}
+
+/// The address a Binding success response to the request of `transaction_id` maps the request
+/// to, preferring the XOR-MAPPED-ADDRESS of current servers to the MAPPED-ADDRESS of RFC 3489
+/// ones.  Anything else is `None`
+pub(crate) fn parse_binding_response(
+    response: &[u8],
+    transaction_id: &TransactionId,
+) -> Option<SocketAddr> {
+    if response.len() < HEADER_LENGTH
+        || response[0..2] != BINDING_SUCCESS_RESPONSE.to_be_bytes()
+        || response[4..8] != MAGIC_COOKIE.to_be_bytes()
+        || response[8..HEADER_LENGTH] != transaction_id[..]
+    {
+        return None;
+    }
+    let length = u16::from_be_bytes([response[2], response[3]]) as usize;
+    let mut attributes = response.get(HEADER_LENGTH..HEADER_LENGTH + length)?;
+
+    let mut mapped_address = None;
+    while attributes.len() >= 4 {
+        let attribute_type = u16::from_be_bytes([attributes[0], attributes[1]]);
+        let attribute_length = u16::from_be_bytes([attributes[2], attributes[3]]) as usize;
+        let value = attributes.get(4..4 + attribute_length)?;
+        match attribute_type {
+            ATTRIBUTE_XOR_MAPPED_ADDRESS => {
+                return parse_address(value, Some(transaction_id));
+            }
+            ATTRIBUTE_MAPPED_ADDRESS => mapped_address = parse_address(value, None),
+            _ => {}
+        }
+        // Attributes are padded to a multiple of four bytes
+        let padded_length = (4 + attribute_length + 3) & !3;
+        attributes = attributes.get(padded_length..).unwrap_or(&[]);
+    }
+    mapped_address
+}
+
+/// Parses a (XOR-)MAPPED-ADDRESS value, XOR-ed with the magic cookie and transaction ID if
+/// `xor_transaction_id` is given
+fn parse_address(value: &[u8], xor_transaction_id: Option<&TransactionId>) -> Option<SocketAddr> {
+    if value.len() < 4 {
+        return None;
+    }
+    let mut mask = [0u8; 16];
+    if let Some(transaction_id) = xor_transaction_id {
+        mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        mask[4..].copy_from_slice(transaction_id);
+    }
+    let port = u16::from_be_bytes([value[2] ^ mask[0], value[3] ^ mask[1]]);
+    let address = &value[4..];
+    let ip_addr = match value[1] {
+        FAMILY_IPV4 if address.len() == 4 => {
+            let mut octets = [0u8; 4];
+            for (i, octet) in octets.iter_mut().enumerate() {
+                *octet = address[i] ^ mask[i];
+            }
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        FAMILY_IPV6 if address.len() == 16 => {
+            let mut octets = [0u8; 16];
+            for (i, octet) in octets.iter_mut().enumerate() {
+                *octet = address[i] ^ mask[i];
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+    Some(SocketAddr::new(ip_addr, port))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// The Binding success response a STUN server sends to `request` from `from`, with the
+    /// mapped address in a XOR-MAPPED-ADDRESS attribute or, for `xor` false, a MAPPED-ADDRESS
+    /// one
+    pub(crate) fn binding_response(request: &[u8], from: SocketAddr, xor: bool) -> Vec<u8> {
+        let mut transaction_id = TransactionId::default();
+        transaction_id.copy_from_slice(&request[8..HEADER_LENGTH]);
+        let mut mask = [0u8; 16];
+        if xor {
+            mask[..4].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+            mask[4..].copy_from_slice(&transaction_id);
+        }
+        let (family, octets) = match from.ip() {
+            IpAddr::V4(ip) => (FAMILY_IPV4, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (FAMILY_IPV6, ip.octets().to_vec()),
+        };
+        let mut value = vec![0, family];
+        let port = from.port().to_be_bytes();
+        value.extend_from_slice(&[port[0] ^ mask[0], port[1] ^ mask[1]]);
+        value.extend(
+            octets
+                .iter()
+                .zip(mask.iter())
+                .map(|(octet, mask)| octet ^ mask),
+        );
+
+        let attribute_type = if xor {
+            ATTRIBUTE_XOR_MAPPED_ADDRESS
+        } else {
+            ATTRIBUTE_MAPPED_ADDRESS
+        };
+        // An unknown attribute to skip first
+        let mut attributes = vec![0x80, 0x22, 0, 3, b'f', b'o', b'o', 0];
+        attributes.extend_from_slice(&attribute_type.to_be_bytes());
+        attributes.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        attributes.extend_from_slice(&value);
+
+        let mut response = BINDING_SUCCESS_RESPONSE.to_be_bytes().to_vec();
+        response.extend_from_slice(&(attributes.len() as u16).to_be_bytes());
+        response.extend_from_slice(&MAGIC_COOKIE.to_be_bytes());
+        response.extend_from_slice(&transaction_id);
+        response.extend_from_slice(&attributes);
+        response
+    }
+
+    #[test]
+    fn test_binding_response() {
+        let transaction_id = new_transaction_id();
+        let request = binding_request(&transaction_id);
+        assert_eq!(request.len(), HEADER_LENGTH);
+
+        for from in &[
+            SocketAddr::from(([203, 0, 113, 7], 8001)),
+            SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 7], 8001)),
+        ] {
+            for xor in &[true, false] {
+                let response = binding_response(&request, *from, *xor);
+                assert_eq!(
+                    parse_binding_response(&response, &transaction_id),
+                    Some(*from)
+                );
+                // Responses to other requests are ignored
+                assert_eq!(
+                    parse_binding_response(&response, &new_transaction_id()),
+                    None
+                );
+                // as are truncated ones
+                assert_eq!(
+                    parse_binding_response(&response[..response.len() - 1], &transaction_id),
+                    None
+                );
+            }
+        }
+        assert_eq!(parse_binding_response(&request, &transaction_id), None);
+    }
+}
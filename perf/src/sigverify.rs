@@ -11,7 +11,7 @@ use crate::recycler::Recycler;
 use bincode::serialized_size;
 use rayon::ThreadPool;
 use solana_metrics::inc_new_counter_debug;
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_sdk::message::MessageHeader;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::short_vec::decode_len;
@@ -21,11 +21,8 @@ use solana_sdk::transaction::Transaction;
 use std::mem::size_of;
 
 lazy_static! {
-    static ref PAR_THREAD_POOL: ThreadPool = rayon::ThreadPoolBuilder::new()
-        .num_threads(get_thread_count())
-        .thread_name(|ix| format!("sigverify_{}", ix))
-        .build()
-        .unwrap();
+    static ref PAR_THREAD_POOL: ThreadPool =
+        thread_manager::build_thread_pool("solana-sigverify", get_thread_count());
 }
 
 pub type TxOffset = PinnedVec<u32>;
@@ -7,10 +7,21 @@ use {
     solana_banks_client::start_client,
     solana_banks_server::banks_server::start_local_server,
     solana_program::{
-        account_info::AccountInfo, entrypoint::ProgramResult, fee_calculator::FeeCalculator,
-        hash::Hash, instruction::Instruction, instruction::InstructionError, message::Message,
-        native_token::sol_to_lamports, program_error::ProgramError, program_stubs, pubkey::Pubkey,
+        account_info::AccountInfo,
+        clock::Clock,
+        entrypoint::{ProgramResult, SUCCESS},
+        epoch_schedule::EpochSchedule,
+        fee_calculator::FeeCalculator,
+        hash::Hash,
+        instruction::Instruction,
+        instruction::InstructionError,
+        message::Message,
+        native_token::sol_to_lamports,
+        program_error::{ProgramError, UNSUPPORTED_SYSVAR},
+        program_stubs,
+        pubkey::Pubkey,
         rent::Rent,
+        sysvar::{self, Sysvar},
     },
     solana_runtime::{
         bank::{Bank, Builtin, ExecuteTimings},
@@ -22,7 +33,7 @@ use {
         genesis_config::GenesisConfig,
         keyed_account::KeyedAccount,
         process_instruction::{
-            stable_log, BpfComputeBudget, InvokeContext, ProcessInstructionWithContext,
+            self, stable_log, BpfComputeBudget, InvokeContext, ProcessInstructionWithContext,
         },
         signature::{Keypair, Signer},
     },
@@ -67,6 +78,7 @@ pub fn to_instruction_error(error: ProgramError) -> InstructionError {
         ProgramError::AccountBorrowFailed => InstructionError::AccountBorrowFailed,
         ProgramError::MaxSeedLengthExceeded => InstructionError::MaxSeedLengthExceeded,
         ProgramError::InvalidSeeds => InstructionError::InvalidSeeds,
+        ProgramError::UnsupportedSysvar => InstructionError::UnsupportedSysvar,
     }
 }
 
@@ -323,6 +335,30 @@ impl program_stubs::SyscallStubs for SyscallStubs {
         stable_log::program_success(&logger, &program_id);
         Ok(())
     }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar::<Clock>(&sysvar::clock::id(), var_addr)
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar::<Rent>(&sysvar::rent::id(), var_addr)
+    }
+
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        get_sysvar::<EpochSchedule>(&sysvar::epoch_schedule::id(), var_addr)
+    }
+}
+
+fn get_sysvar<T: Sysvar>(id: &Pubkey, var_addr: *mut u8) -> u64 {
+    match process_instruction::get_sysvar::<T>(get_invoke_context(), id) {
+        Ok(var) => {
+            unsafe {
+                *(var_addr as *mut T) = var;
+            }
+            SUCCESS
+        }
+        Err(_) => UNSUPPORTED_SYSVAR,
+    }
 }
 
 pub fn find_file(filename: &str) -> Option<PathBuf> {
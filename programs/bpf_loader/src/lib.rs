@@ -8,11 +8,17 @@ pub mod upgradeable;
 pub mod upgradeable_with_jit;
 pub mod with_jit;
 
+#[macro_use]
+extern crate solana_metrics;
+
 use crate::{
     bpf_verifier::VerifierError,
     serialization::{deserialize_parameters, serialize_parameters},
     syscalls::SyscallError,
 };
+use log::*;
+use solana_measure::measure::Measure;
+use solana_metrics::counter::Counter;
 use solana_rbpf::{
     ebpf::MM_HEAP_START,
     error::{EbpfError, UserDefinedError},
@@ -38,7 +44,12 @@ use solana_sdk::{
     rent::Rent,
     system_instruction,
 };
-use std::{cell::RefCell, fmt::Debug, rc::Rc, sync::Arc};
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    rc::Rc,
+    sync::{Arc, Once},
+};
 use thiserror::Error;
 
 solana_sdk::declare_builtin!(
@@ -71,6 +82,57 @@ pub fn create_and_cache_executor(
     invoke_context: &mut dyn InvokeContext,
     use_jit: bool,
 ) -> Result<Arc<BPFExecutor>, InstructionError> {
+    let executor = create_executor(data, invoke_context, use_jit)?;
+    invoke_context.add_executor(key, executor.clone());
+    Ok(executor)
+}
+
+/// Verifies and compiles the program, replacing the cached executor of a program that is being
+/// deployed or upgraded
+fn create_and_update_executor(
+    key: &Pubkey,
+    data: &[u8],
+    invoke_context: &mut dyn InvokeContext,
+    use_jit: bool,
+) -> Result<(), InstructionError> {
+    let executor = create_executor(data, invoke_context, use_jit)?;
+    invoke_context.update_executor(key, executor);
+    Ok(())
+}
+
+/// Programs which failed to JIT compile and are run by the interpreter instead
+static mut JIT_COMPILE_FAILURES: Counter = create_counter!("bpf-jit-compile-failure", 0, 0);
+static JIT_COMPILE_FAILURES_INIT: Once = Once::new();
+
+fn inc_jit_compile_failures() {
+    unsafe {
+        JIT_COMPILE_FAILURES_INIT.call_once(|| JIT_COMPILE_FAILURES.init());
+        JIT_COMPILE_FAILURES.inc(Level::Info, 1);
+    }
+}
+
+fn create_executor(
+    data: &[u8],
+    invoke_context: &mut dyn InvokeContext,
+    use_jit: bool,
+) -> Result<Arc<BPFExecutor>, InstructionError> {
+    create_executor_with_jit_compiler(data, invoke_context, use_jit, |program| {
+        program.jit_compile()
+    })
+}
+
+/// Creates the executor of the program in `data`, compiling it with `jit_compile` if `use_jit`
+fn create_executor_with_jit_compiler<F>(
+    data: &[u8],
+    invoke_context: &mut dyn InvokeContext,
+    use_jit: bool,
+    jit_compile: F,
+) -> Result<Arc<BPFExecutor>, InstructionError>
+where
+    F: FnOnce(
+        &mut dyn Executable<BPFError, ThisInstructionMeter>,
+    ) -> Result<(), EbpfError<BPFError>>,
+{
     let bpf_compute_budget = invoke_context.get_bpf_compute_budget();
     let mut program = Executable::<BPFError, ThisInstructionMeter>::from_elf(
         data,
@@ -96,15 +158,30 @@ pub fn create_and_cache_executor(
         InstructionError::ProgramEnvironmentSetupFailure
     })?;
     program.set_syscall_registry(syscall_registry);
+    let mut is_jit_compiled = false;
     if use_jit {
-        if let Err(err) = program.jit_compile() {
-            ic_msg!(invoke_context, "Failed to compile program {:?}", err);
-            return Err(InstructionError::ProgramFailedToCompile);
+        let mut jit_compile_time = Measure::start("jit_compile");
+        match jit_compile(program.as_mut()) {
+            Ok(()) => {
+                jit_compile_time.stop();
+                inc_new_counter_info!("bpf-jit-compile-us", jit_compile_time.as_us() as usize);
+                is_jit_compiled = true;
+            }
+            Err(err) => {
+                // The interpreter runs the very same program, only slower
+                ic_msg!(
+                    invoke_context,
+                    "Failed to compile program, falling back to the interpreter {:?}",
+                    err
+                );
+                inc_jit_compile_failures();
+            }
         }
     }
-    let executor = Arc::new(BPFExecutor { program });
-    invoke_context.add_executor(key, executor.clone());
-    Ok(executor)
+    Ok(Arc::new(BPFExecutor {
+        program,
+        is_jit_compiled,
+    }))
 }
 
 fn write_program_data(
@@ -416,8 +493,8 @@ fn process_loader_upgradeable_instruction(
             )?;
 
             // Load and verify the program bits
-            let _ = create_and_cache_executor(
-                program_id,
+            create_and_update_executor(
+                program.unsigned_key(),
                 &buffer.try_account_ref()?.data[buffer_data_offset..],
                 invoke_context,
                 use_jit,
@@ -542,7 +619,7 @@ fn process_loader_upgradeable_instruction(
 
             // Load and verify the program bits
 
-            let _ = create_and_cache_executor(
+            create_and_update_executor(
                 program.unsigned_key(),
                 &buffer.try_account_ref()?.data[buffer_data_offset..],
                 invoke_context,
@@ -670,7 +747,7 @@ fn process_loader_instruction(
                 return Err(InstructionError::MissingRequiredSignature);
             }
 
-            let _ = create_and_cache_executor(
+            create_and_update_executor(
                 program.unsigned_key(),
                 &program.try_account_ref()?.data,
                 invoke_context,
@@ -711,6 +788,8 @@ impl InstructionMeter for ThisInstructionMeter {
 /// BPF Loader's Executor implementation
 pub struct BPFExecutor {
     program: Box<dyn Executable<BPFError, ThisInstructionMeter>>,
+    /// Whether the program was JIT compiled, it is interpreted otherwise
+    is_jit_compiled: bool,
 }
 
 // Well, implement Debug for solana_rbpf::vm::Executable in solana-rbpf...
@@ -757,10 +836,18 @@ impl Executor for BPFExecutor {
             stable_log::program_invoke(&logger, program_id, invoke_depth);
             let mut instruction_meter = ThisInstructionMeter::new(compute_meter.clone());
             let before = compute_meter.borrow().get_remaining();
-            let result = if use_jit {
-                vm.execute_program_jit(&mut instruction_meter)
+            // Includes the time spent in syscalls and cross-program invocations
+            let mut execute_time = Measure::start("execute");
+            let result = if use_jit && self.is_jit_compiled {
+                let result = vm.execute_program_jit(&mut instruction_meter);
+                execute_time.stop();
+                inc_new_counter_info!("bpf-execute-jit-us", execute_time.as_us() as usize);
+                result
             } else {
-                vm.execute_program_interpreted(&mut instruction_meter)
+                let result = vm.execute_program_interpreted(&mut instruction_meter);
+                execute_time.stop();
+                inc_new_counter_info!("bpf-execute-interpreted-us", execute_time.as_us() as usize);
+                result
             };
             let after = compute_meter.borrow().get_remaining();
             ic_logger_msg!(
@@ -825,7 +912,14 @@ mod tests {
         system_program, sysvar,
         transaction::TransactionError,
     };
-    use std::{cell::RefCell, fs::File, io::Read, ops::Range, rc::Rc, sync::Arc};
+    use std::{
+        cell::RefCell,
+        fs::File,
+        io::Read,
+        ops::Range,
+        rc::Rc,
+        sync::{atomic::Ordering, Arc},
+    };
 
     struct TestInstructionMeter {
         remaining: u64,
@@ -1050,6 +1144,7 @@ mod tests {
 
         // Case: limited budget
         let program_id = Pubkey::default();
+        let message = Message::default();
         let mut invoke_context = ThisInvokeContext::new(
             &program_id,
             Rent::default(),
@@ -1070,10 +1165,17 @@ mod tests {
                 stack_frame_size: 4096,
                 log_pubkey_units: 100,
                 max_cpi_instruction_size: usize::MAX,
+                introspection_units: 100,
+                slot_hash_units: 100,
+                sysvar_base_cost: 100,
             },
             Rc::new(RefCell::new(Executors::default())),
             None,
             Arc::new(FeatureSet::default()),
+            &message,
+            0,
+            &[],
+            &[],
         );
         assert_eq!(
             Err(InstructionError::ProgramFailedToComplete),
@@ -1097,6 +1199,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_bpf_loader_jit_compile_failure_falls_back_to_interpreter() {
+        let program_id = bpf_loader::id();
+        let program_key = solana_sdk::pubkey::new_rand();
+        let mut file = File::open("test_elfs/noop_aligned.so").expect("file open failed");
+        let mut elf = Vec::new();
+        file.read_to_end(&mut elf).unwrap();
+        let program_account = Account::new_ref(1, 0, &program_id);
+        program_account.borrow_mut().data = elf.clone();
+        program_account.borrow_mut().executable = true;
+        let keyed_accounts = vec![KeyedAccount::new(&program_key, false, &program_account)];
+
+        let mut invoke_context = MockInvokeContext::default();
+        let jit_compile_failures =
+            || unsafe { JIT_COMPILE_FAILURES.counts.load(Ordering::Relaxed) };
+        let failures = jit_compile_failures();
+        let executor =
+            create_executor_with_jit_compiler(&elf, &mut invoke_context, true, |_program| {
+                Err(EbpfError::UserError(BPFError::VerifierError(
+                    VerifierError::LDDWCannotBeLast,
+                )))
+            })
+            .unwrap();
+        assert!(!executor.is_jit_compiled);
+        assert_eq!(jit_compile_failures(), failures + 1);
+        assert!(invoke_context
+            .logger
+            .log
+            .borrow()
+            .iter()
+            .any(|message| message
+                .contains("Failed to compile program, falling back to the interpreter")));
+
+        // The program is run by the interpreter even though the JIT was asked for
+        assert_eq!(
+            Ok(()),
+            executor.execute(
+                &program_id,
+                &program_key,
+                &keyed_accounts,
+                &[],
+                &mut invoke_context,
+                true
+            )
+        );
+
+        // Without the JIT the compiler isn't invoked at all
+        let executor =
+            create_executor_with_jit_compiler(&elf, &mut invoke_context, false, |_program| {
+                panic!("compiled without the JIT")
+            })
+            .unwrap();
+        assert!(!executor.is_jit_compiled);
+        assert_eq!(jit_compile_failures(), failures + 1);
+    }
+
     #[test]
     fn test_bpf_loader_serialize_unaligned() {
         let program_id = bpf_loader_deprecated::id();
@@ -17,24 +17,30 @@ use solana_sdk::{
     bpf_loader_upgradeable::{self, UpgradeableLoaderState},
     entrypoint::{MAX_PERMITTED_DATA_INCREASE, SUCCESS},
     feature_set::{
-        abort_on_all_cpi_failures, limit_cpi_loader_invoke, pubkey_log_syscall_enabled,
-        ristretto_mul_syscall_enabled, sha256_syscall_enabled, sol_log_compute_units_syscall,
-        try_find_program_address_syscall_enabled, use_loaded_executables,
+        abort_on_all_cpi_failures, instruction_introspection_syscalls_enabled,
+        limit_cpi_loader_invoke, pubkey_log_syscall_enabled, ristretto_mul_syscall_enabled,
+        sha256_syscall_enabled, slot_hashes_syscall_enabled, sol_log_compute_units_syscall,
+        sysvar_via_syscall, try_find_program_address_syscall_enabled, use_loaded_executables,
         use_loaded_program_accounts,
     },
-    hash::{Hasher, HASH_BYTES},
+    hash::{Hash, Hasher, HASH_BYTES},
     ic_msg,
-    instruction::{AccountMeta, Instruction, InstructionError},
+    instruction::{
+        AccountMeta, Instruction, InstructionError, TransactionInstructionAccountMeta,
+        TransactionInstructionMeta,
+    },
     keyed_account::KeyedAccount,
     native_loader,
-    process_instruction::{stable_log, ComputeMeter, InvokeContext, Logger},
+    process_instruction::{get_sysvar, stable_log, ComputeMeter, InvokeContext, Logger},
     program_error::ProgramError,
     pubkey::{Pubkey, PubkeyError, MAX_SEEDS},
+    sysvar::{self, clock::Clock, epoch_schedule::EpochSchedule, rent::Rent, Sysvar},
 };
 use std::{
     alloc::Layout,
     cell::{Ref, RefCell, RefMut},
     convert::TryFrom,
+    marker::PhantomData,
     mem::{align_of, size_of},
     rc::Rc,
     slice::from_raw_parts_mut,
@@ -144,6 +150,37 @@ pub fn register_syscalls(
         .register_syscall_by_name(b"sol_invoke_signed_rust", SyscallInvokeSignedRust::call)?;
     syscall_registry.register_syscall_by_name(b"sol_alloc_free_", SyscallAllocFree::call)?;
 
+    if invoke_context.is_feature_active(&instruction_introspection_syscalls_enabled::id()) {
+        syscall_registry
+            .register_syscall_by_name(b"sol_get_stack_height", SyscallGetStackHeight::call)?;
+        syscall_registry.register_syscall_by_name(
+            b"sol_get_current_instruction_index",
+            SyscallGetCurrentInstructionIndex::call,
+        )?;
+        syscall_registry.register_syscall_by_name(
+            b"sol_get_transaction_instruction",
+            SyscallGetTransactionInstruction::call,
+        )?;
+    }
+
+    if invoke_context.is_feature_active(&slot_hashes_syscall_enabled::id()) {
+        syscall_registry.register_syscall_by_name(
+            b"sol_get_recent_slot_hash",
+            SyscallGetRecentSlotHash::call,
+        )?;
+    }
+
+    if invoke_context.is_feature_active(&sysvar_via_syscall::id()) {
+        syscall_registry
+            .register_syscall_by_name(b"sol_get_clock_sysvar", SyscallGetSysvar::<Clock>::call)?;
+        syscall_registry
+            .register_syscall_by_name(b"sol_get_rent_sysvar", SyscallGetSysvar::<Rent>::call)?;
+        syscall_registry.register_syscall_by_name(
+            b"sol_get_epoch_schedule_sysvar",
+            SyscallGetSysvar::<EpochSchedule>::call,
+        )?;
+    }
+
     Ok(syscall_registry)
 }
 
@@ -257,6 +294,10 @@ pub fn bind_syscall_context_objects<'a>(
         }),
     );
 
+    let introspection_units = bpf_compute_budget.introspection_units;
+    let slot_hash_units = bpf_compute_budget.slot_hash_units;
+    let sysvar_base_cost = bpf_compute_budget.sysvar_base_cost;
+
     // Cross-program invocation syscalls
 
     let invoke_context = Rc::new(RefCell::new(invoke_context));
@@ -277,6 +318,91 @@ pub fn bind_syscall_context_objects<'a>(
         None,
     )?;
 
+    // Instruction introspection syscalls
+
+    let compute_meter = invoke_context.borrow().get_compute_meter();
+    bind_feature_gated_syscall_context_object!(
+        vm,
+        invoke_context.borrow(),
+        &instruction_introspection_syscalls_enabled::id(),
+        Box::new(SyscallGetStackHeight {
+            cost: introspection_units,
+            compute_meter: compute_meter.clone(),
+            invoke_context: invoke_context.clone(),
+        }),
+    );
+    bind_feature_gated_syscall_context_object!(
+        vm,
+        invoke_context.borrow(),
+        &instruction_introspection_syscalls_enabled::id(),
+        Box::new(SyscallGetCurrentInstructionIndex {
+            cost: introspection_units,
+            compute_meter: compute_meter.clone(),
+            invoke_context: invoke_context.clone(),
+        }),
+    );
+    bind_feature_gated_syscall_context_object!(
+        vm,
+        invoke_context.borrow(),
+        &instruction_introspection_syscalls_enabled::id(),
+        Box::new(SyscallGetTransactionInstruction {
+            cost: introspection_units,
+            compute_meter,
+            invoke_context: invoke_context.clone(),
+            loader_id,
+        }),
+    );
+
+    // Recent slot hashes syscall
+
+    bind_feature_gated_syscall_context_object!(
+        vm,
+        invoke_context.borrow(),
+        &slot_hashes_syscall_enabled::id(),
+        Box::new(SyscallGetRecentSlotHash {
+            cost: slot_hash_units,
+            compute_meter: invoke_context.borrow().get_compute_meter(),
+            invoke_context: invoke_context.clone(),
+            loader_id,
+        }),
+    );
+
+    // Sysvar syscalls
+
+    bind_feature_gated_syscall_context_object!(
+        vm,
+        invoke_context.borrow(),
+        &sysvar_via_syscall::id(),
+        Box::new(SyscallGetSysvar::<Clock>::new(
+            sysvar::clock::id(),
+            sysvar_base_cost,
+            invoke_context.clone(),
+            loader_id,
+        )),
+    );
+    bind_feature_gated_syscall_context_object!(
+        vm,
+        invoke_context.borrow(),
+        &sysvar_via_syscall::id(),
+        Box::new(SyscallGetSysvar::<Rent>::new(
+            sysvar::rent::id(),
+            sysvar_base_cost,
+            invoke_context.clone(),
+            loader_id,
+        )),
+    );
+    bind_feature_gated_syscall_context_object!(
+        vm,
+        invoke_context.borrow(),
+        &sysvar_via_syscall::id(),
+        Box::new(SyscallGetSysvar::<EpochSchedule>::new(
+            sysvar::epoch_schedule::id(),
+            sysvar_base_cost,
+            invoke_context.clone(),
+            loader_id,
+        )),
+    );
+
     // Memory allocator
 
     vm.bind_syscall_context_object(
@@ -844,6 +970,249 @@ impl<'a> SyscallObject<BPFError> for SyscallRistrettoMul<'a> {
     }
 }
 
+/// Get the height of the invocation stack, 1 for a transaction instruction and incremented
+/// by each cross-program invocation
+pub struct SyscallGetStackHeight<'a> {
+    cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    invoke_context: Rc<RefCell<&'a mut dyn InvokeContext>>,
+}
+impl<'a> SyscallObject<BPFError> for SyscallGetStackHeight<'a> {
+    fn call(
+        &mut self,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        _memory_mapping: &MemoryMapping,
+        result: &mut Result<u64, EbpfError<BPFError>>,
+    ) {
+        question_mark!(self.compute_meter.consume(self.cost), result);
+        let invoke_context = question_mark!(
+            self.invoke_context
+                .try_borrow()
+                .map_err(|_| SyscallError::InvokeContextBorrowFailed),
+            result
+        );
+        *result = Ok(invoke_context.invoke_depth() as u64);
+    }
+}
+
+/// Get the index of the transaction instruction being processed
+pub struct SyscallGetCurrentInstructionIndex<'a> {
+    cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    invoke_context: Rc<RefCell<&'a mut dyn InvokeContext>>,
+}
+impl<'a> SyscallObject<BPFError> for SyscallGetCurrentInstructionIndex<'a> {
+    fn call(
+        &mut self,
+        _arg1: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        _memory_mapping: &MemoryMapping,
+        result: &mut Result<u64, EbpfError<BPFError>>,
+    ) {
+        question_mark!(self.compute_meter.consume(self.cost), result);
+        let invoke_context = question_mark!(
+            self.invoke_context
+                .try_borrow()
+                .map_err(|_| SyscallError::InvokeContextBorrowFailed),
+            result
+        );
+        *result = Ok(invoke_context.get_instruction_index() as u64);
+    }
+}
+
+/// Get an instruction of the transaction being processed.
+///
+/// The lengths of the instruction's data and accounts are always written to `meta`, the
+/// program id, data and accounts are only written if the lengths in `meta` already match.
+/// Returns 1 if the transaction has no instruction at `index`
+pub struct SyscallGetTransactionInstruction<'a> {
+    cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    invoke_context: Rc<RefCell<&'a mut dyn InvokeContext>>,
+    loader_id: &'a Pubkey,
+}
+impl<'a> SyscallObject<BPFError> for SyscallGetTransactionInstruction<'a> {
+    fn call(
+        &mut self,
+        index: u64,
+        meta_addr: u64,
+        program_id_addr: u64,
+        data_addr: u64,
+        accounts_addr: u64,
+        memory_mapping: &MemoryMapping,
+        result: &mut Result<u64, EbpfError<BPFError>>,
+    ) {
+        question_mark!(self.compute_meter.consume(self.cost), result);
+        let invoke_context = question_mark!(
+            self.invoke_context
+                .try_borrow()
+                .map_err(|_| SyscallError::InvokeContextBorrowFailed),
+            result
+        );
+        let instruction = match invoke_context.get_transaction_instruction(index as usize) {
+            Some(instruction) => instruction,
+            None => {
+                *result = Ok(1);
+                return;
+            }
+        };
+
+        let meta = question_mark!(
+            translate_type_mut::<TransactionInstructionMeta>(
+                memory_mapping,
+                meta_addr,
+                self.loader_id
+            ),
+            result
+        );
+        if meta.data_len == instruction.data.len() as u64
+            && meta.accounts_len == instruction.accounts.len() as u64
+        {
+            let program_id = question_mark!(
+                translate_type_mut::<Pubkey>(memory_mapping, program_id_addr, self.loader_id),
+                result
+            );
+            let data = question_mark!(
+                translate_slice_mut::<u8>(memory_mapping, data_addr, meta.data_len, self.loader_id),
+                result
+            );
+            let accounts = question_mark!(
+                translate_slice_mut::<TransactionInstructionAccountMeta>(
+                    memory_mapping,
+                    accounts_addr,
+                    meta.accounts_len,
+                    self.loader_id
+                ),
+                result
+            );
+            *program_id = instruction.program_id;
+            data.copy_from_slice(&instruction.data);
+            for (account, account_meta) in accounts.iter_mut().zip(instruction.accounts.iter()) {
+                *account = TransactionInstructionAccountMeta::from(account_meta);
+            }
+        }
+        meta.data_len = instruction.data.len() as u64;
+        meta.accounts_len = instruction.accounts.len() as u64;
+        *result = Ok(0);
+    }
+}
+
+/// Get an entry of the SlotHashes sysvar, `index` 0 being the most recent one.
+///
+/// Writes the entry's slot and bank hash, returns 1 if the sysvar has no entry at `index`
+pub struct SyscallGetRecentSlotHash<'a> {
+    cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    invoke_context: Rc<RefCell<&'a mut dyn InvokeContext>>,
+    loader_id: &'a Pubkey,
+}
+impl<'a> SyscallObject<BPFError> for SyscallGetRecentSlotHash<'a> {
+    fn call(
+        &mut self,
+        index: u64,
+        slot_addr: u64,
+        hash_addr: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &MemoryMapping,
+        result: &mut Result<u64, EbpfError<BPFError>>,
+    ) {
+        question_mark!(self.compute_meter.consume(self.cost), result);
+        let invoke_context = question_mark!(
+            self.invoke_context
+                .try_borrow()
+                .map_err(|_| SyscallError::InvokeContextBorrowFailed),
+            result
+        );
+        let (slot, hash) = match invoke_context.get_slot_hashes().get(index as usize) {
+            Some(slot_hash) => slot_hash,
+            None => {
+                *result = Ok(1);
+                return;
+            }
+        };
+        *question_mark!(
+            translate_type_mut::<u64>(memory_mapping, slot_addr, self.loader_id),
+            result
+        ) = *slot;
+        *question_mark!(
+            translate_type_mut::<Hash>(memory_mapping, hash_addr, self.loader_id),
+            result
+        ) = *hash;
+        *result = Ok(0);
+    }
+}
+
+/// Copy a sysvar into program memory, sparing programs from being passed the sysvar account.
+///
+/// Costs the base cost plus the size of the sysvar
+pub struct SyscallGetSysvar<'a, T: Sysvar> {
+    id: Pubkey,
+    base_cost: u64,
+    compute_meter: Rc<RefCell<dyn ComputeMeter>>,
+    invoke_context: Rc<RefCell<&'a mut dyn InvokeContext>>,
+    loader_id: &'a Pubkey,
+    sysvar: PhantomData<T>,
+}
+impl<'a, T: Sysvar> SyscallGetSysvar<'a, T> {
+    fn new(
+        id: Pubkey,
+        base_cost: u64,
+        invoke_context: Rc<RefCell<&'a mut dyn InvokeContext>>,
+        loader_id: &'a Pubkey,
+    ) -> Self {
+        let compute_meter = invoke_context.borrow().get_compute_meter();
+        Self {
+            id,
+            base_cost,
+            compute_meter,
+            invoke_context,
+            loader_id,
+            sysvar: PhantomData,
+        }
+    }
+}
+impl<'a, T: Sysvar> SyscallObject<BPFError> for SyscallGetSysvar<'a, T> {
+    fn call(
+        &mut self,
+        var_addr: u64,
+        _arg2: u64,
+        _arg3: u64,
+        _arg4: u64,
+        _arg5: u64,
+        memory_mapping: &MemoryMapping,
+        result: &mut Result<u64, EbpfError<BPFError>>,
+    ) {
+        question_mark!(
+            self.compute_meter
+                .consume(self.base_cost + size_of::<T>() as u64),
+            result
+        );
+        let invoke_context = question_mark!(
+            self.invoke_context
+                .try_borrow()
+                .map_err(|_| SyscallError::InvokeContextBorrowFailed),
+            result
+        );
+        let var = question_mark!(
+            get_sysvar::<T>(&**invoke_context, &self.id).map_err(SyscallError::InstructionError),
+            result
+        );
+        *question_mark!(
+            translate_type_mut::<T>(memory_mapping, var_addr, self.loader_id),
+            result
+        ) = var;
+        *result = Ok(SUCCESS);
+    }
+}
+
 // Cross-program invocation syscalls
 
 struct AccountReferences<'a> {
@@ -1734,7 +2103,7 @@ mod tests {
     use solana_sdk::{
         bpf_loader,
         hash::hashv,
-        process_instruction::{MockComputeMeter, MockLogger},
+        process_instruction::{MockComputeMeter, MockInvokeContext, MockLogger},
     };
     use std::str::FromStr;
 
@@ -2386,4 +2755,357 @@ mod tests {
             result
         );
     }
+
+    #[test]
+    fn test_syscall_get_transaction_instruction() {
+        let program_id = Pubkey::new_unique();
+        let instruction = Instruction::new(
+            program_id,
+            &[1u8, 2, 3],
+            vec![
+                AccountMeta::new(Pubkey::new_unique(), true),
+                AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            ],
+        );
+        let mut invoke_context = MockInvokeContext {
+            instructions: vec![instruction.clone()],
+            instruction_index: 0,
+            invoke_depth: 1,
+            ..MockInvokeContext::default()
+        };
+        let invoke_context: &mut dyn InvokeContext = &mut invoke_context;
+        let invoke_context = Rc::new(RefCell::new(invoke_context));
+
+        let mut meta = TransactionInstructionMeta::default();
+        let mut program_id_result = Pubkey::default();
+        let mut data = vec![0u8; instruction.data.len()];
+        let mut accounts =
+            vec![TransactionInstructionAccountMeta::default(); instruction.accounts.len()];
+        let meta_va = 4096;
+        let program_id_va = 8192;
+        let data_va = 12288;
+        let accounts_va = 16384;
+        let memory_mapping = MemoryMapping::new(
+            vec![
+                MemoryRegion {
+                    host_addr: &mut meta as *mut _ as u64,
+                    vm_addr: meta_va,
+                    len: size_of::<TransactionInstructionMeta>() as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+                MemoryRegion {
+                    host_addr: &mut program_id_result as *mut _ as u64,
+                    vm_addr: program_id_va,
+                    len: size_of::<Pubkey>() as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+                MemoryRegion {
+                    host_addr: data.as_mut_ptr() as u64,
+                    vm_addr: data_va,
+                    len: data.len() as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+                MemoryRegion {
+                    host_addr: accounts.as_mut_ptr() as u64,
+                    vm_addr: accounts_va,
+                    len: (accounts.len() * size_of::<TransactionInstructionAccountMeta>()) as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+            ],
+            &DEFAULT_CONFIG,
+        );
+        let compute_meter: Rc<RefCell<dyn ComputeMeter>> =
+            Rc::new(RefCell::new(MockComputeMeter { remaining: 3 }));
+
+        let mut syscall_stack_height = SyscallGetStackHeight {
+            cost: 0,
+            compute_meter: compute_meter.clone(),
+            invoke_context: invoke_context.clone(),
+        };
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall_stack_height.call(0, 0, 0, 0, 0, &memory_mapping, &mut result);
+        assert_eq!(result.unwrap(), 1);
+
+        let mut syscall_instruction_index = SyscallGetCurrentInstructionIndex {
+            cost: 0,
+            compute_meter: compute_meter.clone(),
+            invoke_context: invoke_context.clone(),
+        };
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall_instruction_index.call(0, 0, 0, 0, 0, &memory_mapping, &mut result);
+        assert_eq!(result.unwrap(), 0);
+
+        let mut syscall = SyscallGetTransactionInstruction {
+            cost: 1,
+            compute_meter,
+            invoke_context,
+            loader_id: &bpf_loader::id(),
+        };
+
+        // Lengths don't match, only the meta is written
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(
+            0,
+            meta_va,
+            program_id_va,
+            data_va,
+            accounts_va,
+            &memory_mapping,
+            &mut result,
+        );
+        assert_eq!(result.unwrap(), 0);
+        assert_eq!(
+            meta,
+            TransactionInstructionMeta {
+                data_len: instruction.data.len() as u64,
+                accounts_len: instruction.accounts.len() as u64,
+            }
+        );
+        assert_eq!(program_id_result, Pubkey::default());
+
+        // Lengths match, the instruction is written
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(
+            0,
+            meta_va,
+            program_id_va,
+            data_va,
+            accounts_va,
+            &memory_mapping,
+            &mut result,
+        );
+        assert_eq!(result.unwrap(), 0);
+        assert_eq!(
+            Instruction {
+                program_id: program_id_result,
+                accounts: accounts.iter().copied().map(AccountMeta::from).collect(),
+                data: data.clone(),
+            },
+            instruction
+        );
+
+        // No instruction at the index
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(
+            1,
+            meta_va,
+            program_id_va,
+            data_va,
+            accounts_va,
+            &memory_mapping,
+            &mut result,
+        );
+        assert_eq!(result.unwrap(), 1);
+
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(
+            0,
+            meta_va,
+            program_id_va,
+            data_va,
+            accounts_va,
+            &memory_mapping,
+            &mut result,
+        );
+        assert_eq!(
+            Err(EbpfError::UserError(BPFError::SyscallError(
+                SyscallError::InstructionError(InstructionError::ComputationalBudgetExceeded)
+            ))),
+            result
+        );
+    }
+
+    #[test]
+    fn test_syscall_get_recent_slot_hash() {
+        let slot_hashes = vec![(2, hashv(&[b"2"])), (1, hashv(&[b"1"]))];
+        let mut invoke_context = MockInvokeContext {
+            slot_hashes: slot_hashes.clone(),
+            ..MockInvokeContext::default()
+        };
+        let invoke_context: &mut dyn InvokeContext = &mut invoke_context;
+        let invoke_context = Rc::new(RefCell::new(invoke_context));
+
+        let mut slot = 0u64;
+        let mut hash = Hash::default();
+        let slot_va = 4096;
+        let hash_va = 8192;
+        let memory_mapping = MemoryMapping::new(
+            vec![
+                MemoryRegion {
+                    host_addr: &mut slot as *mut _ as u64,
+                    vm_addr: slot_va,
+                    len: size_of::<u64>() as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+                MemoryRegion {
+                    host_addr: &mut hash as *mut _ as u64,
+                    vm_addr: hash_va,
+                    len: size_of::<Hash>() as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+            ],
+            &DEFAULT_CONFIG,
+        );
+        let compute_meter: Rc<RefCell<dyn ComputeMeter>> =
+            Rc::new(RefCell::new(MockComputeMeter { remaining: 3 }));
+        let mut syscall = SyscallGetRecentSlotHash {
+            cost: 1,
+            compute_meter,
+            invoke_context,
+            loader_id: &bpf_loader::id(),
+        };
+
+        for (index, slot_hash) in slot_hashes.iter().enumerate() {
+            let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+            syscall.call(
+                index as u64,
+                slot_va,
+                hash_va,
+                0,
+                0,
+                &memory_mapping,
+                &mut result,
+            );
+            assert_eq!(result.unwrap(), 0);
+            assert_eq!((slot, hash), *slot_hash);
+        }
+
+        // No entry at the index
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(2, slot_va, hash_va, 0, 0, &memory_mapping, &mut result);
+        assert_eq!(result.unwrap(), 1);
+
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(0, slot_va, hash_va, 0, 0, &memory_mapping, &mut result);
+        assert_eq!(
+            Err(EbpfError::UserError(BPFError::SyscallError(
+                SyscallError::InstructionError(InstructionError::ComputationalBudgetExceeded)
+            ))),
+            result
+        );
+    }
+
+    #[test]
+    fn test_syscall_get_sysvar() {
+        let src_clock = Clock {
+            slot: 1,
+            epoch_start_timestamp: 2,
+            epoch: 3,
+            leader_schedule_epoch: 4,
+            unix_timestamp: 5,
+        };
+        let src_rent = Rent {
+            lamports_per_byte_year: 1,
+            exemption_threshold: 2.0,
+            burn_percent: 3,
+        };
+        let mut invoke_context = MockInvokeContext {
+            sysvars: vec![
+                (sysvar::clock::id(), bincode::serialize(&src_clock).unwrap()),
+                (sysvar::rent::id(), bincode::serialize(&src_rent).unwrap()),
+            ],
+            ..MockInvokeContext::default()
+        };
+        let invoke_context: &mut dyn InvokeContext = &mut invoke_context;
+        let invoke_context = Rc::new(RefCell::new(invoke_context));
+
+        let mut clock = Clock::default();
+        let mut rent = Rent::default();
+        let mut epoch_schedule = EpochSchedule::default();
+        let clock_va = 4096;
+        let rent_va = 8192;
+        let epoch_schedule_va = 12288;
+        let memory_mapping = MemoryMapping::new(
+            vec![
+                MemoryRegion {
+                    host_addr: &mut clock as *mut _ as u64,
+                    vm_addr: clock_va,
+                    len: size_of::<Clock>() as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+                MemoryRegion {
+                    host_addr: &mut rent as *mut _ as u64,
+                    vm_addr: rent_va,
+                    len: size_of::<Rent>() as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+                MemoryRegion {
+                    host_addr: &mut epoch_schedule as *mut _ as u64,
+                    vm_addr: epoch_schedule_va,
+                    len: size_of::<EpochSchedule>() as u64,
+                    vm_gap_shift: 63,
+                    is_writable: true,
+                },
+            ],
+            &DEFAULT_CONFIG,
+        );
+
+        let clock_cost = 1 + size_of::<Clock>() as u64;
+        let compute_meter: Rc<RefCell<dyn ComputeMeter>> =
+            Rc::new(RefCell::new(MockComputeMeter {
+                remaining: clock_cost * 2 - 1,
+            }));
+        let mut syscall = SyscallGetSysvar::<Clock> {
+            id: sysvar::clock::id(),
+            base_cost: 1,
+            compute_meter: compute_meter.clone(),
+            invoke_context: invoke_context.clone(),
+            loader_id: &bpf_loader::id(),
+            sysvar: PhantomData,
+        };
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(clock_va, 0, 0, 0, 0, &memory_mapping, &mut result);
+        assert_eq!(result.unwrap(), SUCCESS);
+        assert_eq!(clock, src_clock);
+        assert_eq!(compute_meter.borrow().get_remaining(), clock_cost - 1);
+
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(clock_va, 0, 0, 0, 0, &memory_mapping, &mut result);
+        assert_eq!(
+            Err(EbpfError::UserError(BPFError::SyscallError(
+                SyscallError::InstructionError(InstructionError::ComputationalBudgetExceeded)
+            ))),
+            result
+        );
+
+        let mut syscall = SyscallGetSysvar::<Rent> {
+            id: sysvar::rent::id(),
+            base_cost: 0,
+            compute_meter: Rc::new(RefCell::new(MockComputeMeter { remaining: 100 })),
+            invoke_context: invoke_context.clone(),
+            loader_id: &bpf_loader::id(),
+            sysvar: PhantomData,
+        };
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(rent_va, 0, 0, 0, 0, &memory_mapping, &mut result);
+        assert_eq!(result.unwrap(), SUCCESS);
+        assert_eq!(rent, src_rent);
+
+        // The invoke context has no epoch schedule
+        let mut syscall = SyscallGetSysvar::<EpochSchedule> {
+            id: sysvar::epoch_schedule::id(),
+            base_cost: 0,
+            compute_meter: Rc::new(RefCell::new(MockComputeMeter { remaining: 100 })),
+            invoke_context,
+            loader_id: &bpf_loader::id(),
+            sysvar: PhantomData,
+        };
+        let mut result: Result<u64, EbpfError<BPFError>> = Ok(0);
+        syscall.call(epoch_schedule_va, 0, 0, 0, 0, &memory_mapping, &mut result);
+        assert_eq!(
+            Err(EbpfError::UserError(BPFError::SyscallError(
+                SyscallError::InstructionError(InstructionError::UnsupportedSysvar)
+            ))),
+            result
+        );
+        assert_eq!(epoch_schedule, EpochSchedule::default());
+    }
 }
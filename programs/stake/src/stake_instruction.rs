@@ -161,7 +161,9 @@ pub struct AuthorizeWithSeedArgs {
     pub authority_owner: Pubkey,
 }
 
-fn initialize(stake_pubkey: &Pubkey, authorized: &Authorized, lockup: &Lockup) -> Instruction {
+/// Initializes a system account already sized for `StakeState` and assigned to the stake
+/// program, it must be created in the same transaction or another party may initialize it first
+pub fn initialize(stake_pubkey: &Pubkey, authorized: &Authorized, lockup: &Lockup) -> Instruction {
     Instruction::new(
         id(),
         &StakeInstruction::Initialize(*authorized, *lockup),
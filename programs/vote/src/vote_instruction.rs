@@ -11,6 +11,7 @@ use serde_derive::{Deserialize, Serialize};
 use solana_metrics::inc_new_counter_info;
 use solana_sdk::{
     decode_error::DecodeError,
+    feature_set,
     hash::Hash,
     instruction::{AccountMeta, Instruction, InstructionError},
     keyed_account::{from_keyed_account, get_signers, next_keyed_account, KeyedAccount},
@@ -18,7 +19,7 @@ use solana_sdk::{
     program_utils::limited_deserialize,
     pubkey::Pubkey,
     system_instruction,
-    sysvar::{self, clock::Clock, slot_hashes::SlotHashes},
+    sysvar::{self, clock::Clock, rent::Rent, slot_hashes::SlotHashes},
 };
 use std::collections::HashSet;
 use thiserror::Error;
@@ -43,6 +44,9 @@ pub enum VoteError {
 
     #[error("authorized voter has already been changed this epoch")]
     TooSoonToReauthorize,
+
+    #[error("cannot close a vote account that is still voting in the current epoch")]
+    ActiveVoteAccountClose,
 }
 
 impl<E> DecodeError<E> for VoteError {
@@ -85,6 +89,8 @@ pub enum VoteInstruction {
     ///   0. [WRITE] Vote account to withdraw from
     ///   1. [WRITE] Recipient account
     ///   2. [SIGNER] Withdraw authority
+    ///   3. [] Rent sysvar, required once the vote withdraw safety checks are active
+    ///   4. [] Clock sysvar, required once the vote withdraw safety checks are active
     Withdraw(u64),
 
     /// Update the vote account's validator identity (node_pubkey)
@@ -112,7 +118,9 @@ pub enum VoteInstruction {
     VoteSwitch(Vote, Hash),
 }
 
-fn initialize_account(vote_pubkey: &Pubkey, vote_init: &VoteInit) -> Instruction {
+/// Initializes a system account already sized for `VoteState` and assigned to the vote program,
+/// signed by the node identity
+pub fn initialize_account(vote_pubkey: &Pubkey, vote_init: &VoteInit) -> Instruction {
     let account_metas = vec![
         AccountMeta::new(*vote_pubkey, false),
         AccountMeta::new_readonly(sysvar::rent::id(), false),
@@ -257,6 +265,8 @@ pub fn withdraw(
         AccountMeta::new(*vote_pubkey, false),
         AccountMeta::new(*to_pubkey, false),
         AccountMeta::new_readonly(*authorized_withdrawer_pubkey, true),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
     ];
 
     Instruction::new(id(), &VoteInstruction::Withdraw(lamports), account_metas)
@@ -278,7 +288,7 @@ pub fn process_instruction(
     _program_id: &Pubkey,
     keyed_accounts: &[KeyedAccount],
     data: &[u8],
-    _invoke_context: &mut dyn InvokeContext,
+    invoke_context: &mut dyn InvokeContext,
 ) -> Result<(), InstructionError> {
     trace!("process_instruction: {:?}", data);
     trace!("keyed_accounts: {:?}", keyed_accounts);
@@ -325,7 +335,14 @@ pub fn process_instruction(
         }
         VoteInstruction::Withdraw(lamports) => {
             let to = next_keyed_account(keyed_accounts)?;
-            vote_state::withdraw(me, lamports, to, &signers)
+            if invoke_context.is_feature_active(&feature_set::vote_withdraw_safety_checks::id()) {
+                let _withdraw_authority = next_keyed_account(keyed_accounts)?;
+                let rent_sysvar: Rent = from_keyed_account(next_keyed_account(keyed_accounts)?)?;
+                let clock: Clock = from_keyed_account(next_keyed_account(keyed_accounts)?)?;
+                vote_state::withdraw(me, lamports, to, &signers, Some(&rent_sysvar), Some(&clock))
+            } else {
+                vote_state::withdraw(me, lamports, to, &signers, None, None)
+            }
         }
     }
 }
@@ -651,24 +651,46 @@ fn verify_authorized_signer<S: std::hash::BuildHasher>(
 }
 
 /// Withdraw funds from the vote account
+///
+/// When `rent_sysvar` and `clock` are provided, a partial withdrawal must leave the account rent
+/// exempt, and the account can't be closed while it is still earning credits in the current epoch
 pub fn withdraw<S: std::hash::BuildHasher>(
     vote_account: &KeyedAccount,
     lamports: u64,
     to_account: &KeyedAccount,
     signers: &HashSet<Pubkey, S>,
+    rent_sysvar: Option<&Rent>,
+    clock: Option<&Clock>,
 ) -> Result<(), InstructionError> {
     let vote_state: VoteState =
         State::<VoteStateVersions>::state(vote_account)?.convert_to_current();
 
     verify_authorized_signer(&vote_state.authorized_withdrawer, signers)?;
 
-    match vote_account.lamports()?.cmp(&lamports) {
+    let balance = vote_account.lamports()?;
+    match balance.cmp(&lamports) {
         Ordering::Less => return Err(InstructionError::InsufficientFunds),
         Ordering::Equal => {
+            if let Some(clock) = clock {
+                if vote_state
+                    .epoch_credits
+                    .last()
+                    .map(|(epoch, _, _)| *epoch == clock.epoch)
+                    .unwrap_or(false)
+                {
+                    return Err(VoteError::ActiveVoteAccountClose.into());
+                }
+            }
             // Deinitialize upon zero-balance
             vote_account.set_state(&VoteStateVersions::new_current(VoteState::default()))?;
         }
-        _ => (),
+        Ordering::Greater => {
+            if let Some(rent_sysvar) = rent_sysvar {
+                if !rent_sysvar.is_exempt(balance - lamports, vote_account.data_len()?) {
+                    return Err(InstructionError::InsufficientFunds);
+                }
+            }
+        }
     }
     vote_account.try_account_ref_mut()?.lamports -= lamports;
     to_account.try_account_ref_mut()?.lamports += lamports;
@@ -1613,6 +1635,8 @@ mod tests {
                 &RefCell::new(Account::default()),
             ),
             &signers,
+            None,
+            None,
         );
         assert_eq!(res, Err(InstructionError::MissingRequiredSignature));
 
@@ -1628,6 +1652,8 @@ mod tests {
                 &RefCell::new(Account::default()),
             ),
             &signers,
+            None,
+            None,
         );
         assert_eq!(res, Err(InstructionError::InsufficientFunds));
 
@@ -1642,6 +1668,8 @@ mod tests {
             lamports,
             &KeyedAccount::new(&solana_sdk::pubkey::new_rand(), false, &to_account),
             &signers,
+            None,
+            None,
         );
         assert_eq!(res, Ok(()));
         assert_eq!(vote_account.borrow().lamports, 0);
@@ -1682,6 +1710,8 @@ mod tests {
             lamports,
             withdrawer_keyed_account,
             &signers,
+            None,
+            None,
         );
         assert_eq!(res, Ok(()));
         assert_eq!(vote_account.borrow().lamports, 0);
@@ -1691,6 +1721,84 @@ mod tests {
         assert!(post_state.is_uninitialized());
     }
 
+    #[test]
+    fn test_vote_state_withdraw_safety_checks() {
+        let (vote_pubkey, vote_account) = create_test_account();
+        let rent = Rent::default();
+        let minimum_balance = rent.minimum_balance(VoteState::size_of());
+        vote_account.borrow_mut().lamports = minimum_balance + 100;
+        let lamports = vote_account.borrow().lamports;
+        let keyed_accounts = &[KeyedAccount::new(&vote_pubkey, true, &vote_account)];
+        let signers: HashSet<Pubkey> = get_signers(keyed_accounts);
+        let to_pubkey = solana_sdk::pubkey::new_rand();
+        let to_account = RefCell::new(Account::default());
+        let to_keyed_account = KeyedAccount::new(&to_pubkey, false, &to_account);
+        let clock = Clock {
+            epoch: 1,
+            ..Clock::default()
+        };
+
+        // leaving the account below rent exemption fails
+        let res = withdraw(
+            &keyed_accounts[0],
+            101,
+            &to_keyed_account,
+            &signers,
+            Some(&rent),
+            Some(&clock),
+        );
+        assert_eq!(res, Err(InstructionError::InsufficientFunds));
+
+        // leaving the account rent exempt succeeds
+        let res = withdraw(
+            &keyed_accounts[0],
+            100,
+            &to_keyed_account,
+            &signers,
+            Some(&rent),
+            Some(&clock),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(vote_account.borrow().lamports, minimum_balance);
+
+        // closing an account that earned credits this epoch fails
+        let mut vote_state = VoteState::from(&vote_account.borrow()).unwrap();
+        vote_state.increment_credits(clock.epoch);
+        VoteState::to(
+            &VoteStateVersions::new_current(vote_state),
+            &mut vote_account.borrow_mut(),
+        )
+        .unwrap();
+        let res = withdraw(
+            &keyed_accounts[0],
+            minimum_balance,
+            &to_keyed_account,
+            &signers,
+            Some(&rent),
+            Some(&clock),
+        );
+        assert_eq!(res, Err(VoteError::ActiveVoteAccountClose.into()));
+
+        // once the epoch is over, the account can be closed
+        let clock = Clock {
+            epoch: 2,
+            ..Clock::default()
+        };
+        let res = withdraw(
+            &keyed_accounts[0],
+            minimum_balance,
+            &to_keyed_account,
+            &signers,
+            Some(&rent),
+            Some(&clock),
+        );
+        assert_eq!(res, Ok(()));
+        assert_eq!(vote_account.borrow().lamports, 0);
+        assert_eq!(to_account.borrow().lamports, lamports);
+        let post_state: VoteStateVersions = vote_account.borrow().state().unwrap();
+        assert!(post_state.is_uninitialized());
+    }
+
     #[test]
     fn test_vote_state_epoch_credits() {
         let mut vote_state = VoteState::default();
@@ -0,0 +1,87 @@
+//! Detection of the NUMA layout of the machine's CPUs, for placing hot threads.
+
+/// Parses a list of CPUs in the kernel's format, e.g. "0-3,8,10-11".
+pub fn parse_cpu_list(cpu_list: &str) -> Result<Vec<usize>, String> {
+    let mut cpus = vec![];
+    for range in cpu_list.trim().split(',').filter(|range| !range.is_empty()) {
+        let parse = |cpu: &str| {
+            cpu.trim()
+                .parse::<usize>()
+                .map_err(|err| format!("invalid cpu {:?}: {}", cpu, err))
+        };
+        match range.find('-') {
+            Some(dash) => {
+                let (first, last) = (parse(&range[..dash])?, parse(&range[dash + 1..])?);
+                if first > last {
+                    return Err(format!("invalid cpu range {:?}", range));
+                }
+                cpus.extend(first..=last);
+            }
+            None => cpus.push(parse(range)?),
+        }
+    }
+    cpus.sort_unstable();
+    cpus.dedup();
+    Ok(cpus)
+}
+
+/// Returns the CPUs of each NUMA node, indexed by node. Machines without NUMA information
+/// are reported as a single node holding all CPUs.
+pub fn numa_nodes() -> Vec<Vec<usize>> {
+    let nodes = read_numa_nodes().unwrap_or_default();
+    if nodes.iter().any(|cpus| !cpus.is_empty()) {
+        nodes
+    } else {
+        vec![(0..num_cpus::get()).collect()]
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn read_numa_nodes() -> Option<Vec<Vec<usize>>> {
+    use std::fs;
+
+    let mut nodes = vec![];
+    for entry in fs::read_dir("/sys/devices/system/node").ok()? {
+        let entry = entry.ok()?;
+        let file_name = entry.file_name();
+        let node = match file_name.to_str()?.strip_prefix("node") {
+            Some(node) => node.parse::<usize>().ok()?,
+            None => continue,
+        };
+        let cpu_list = fs::read_to_string(entry.path().join("cpulist")).ok()?;
+        if nodes.len() <= node {
+            nodes.resize(node + 1, vec![]);
+        }
+        nodes[node] = parse_cpu_list(&cpu_list).ok()?;
+    }
+    Some(nodes)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_numa_nodes() -> Option<Vec<Vec<usize>>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(
+            parse_cpu_list("0-3,8,10-11\n"),
+            Ok(vec![0, 1, 2, 3, 8, 10, 11])
+        );
+        assert_eq!(parse_cpu_list("5,1,5"), Ok(vec![1, 5]));
+        assert_eq!(parse_cpu_list(""), Ok(vec![]));
+        assert!(parse_cpu_list("3-1").is_err());
+        assert!(parse_cpu_list("a").is_err());
+    }
+
+    #[test]
+    fn test_numa_nodes() {
+        let nodes = numa_nodes();
+        assert!(!nodes.is_empty());
+        assert!(nodes.iter().flatten().count() > 0);
+    }
+}
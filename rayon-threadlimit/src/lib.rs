@@ -1,7 +1,13 @@
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate log;
 
 use std::env;
+
+pub mod cpu_topology;
+pub mod thread_manager;
+
 //TODO remove this hack when rayon fixes itself
 
 lazy_static! {
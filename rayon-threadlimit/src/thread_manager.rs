@@ -0,0 +1,180 @@
+//! Registry of the named thread pools of the validator.
+//!
+//! Subsystems ask for their pools by name along with a default size, and an operator can
+//! override the size of any named pool with `configure` before the pools are built. The
+//! registry also tracks how many pools and threads exist under each name for metrics.
+//!
+//! Threads can also be pinned to a set of CPUs by name with `configure_affinity`. Pools
+//! built by `build_thread_pool` pin their threads on start; other hot threads call
+//! `pin_current_thread` with the name of their group.
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+#[derive(Default)]
+struct PoolEntry {
+    num_threads: usize,
+    num_pools: AtomicUsize,
+    num_live_threads: Arc<AtomicUsize>,
+}
+
+#[derive(Default)]
+struct ThreadManager {
+    overrides: HashMap<String, usize>,
+    affinity: HashMap<String, Vec<usize>>,
+    pools: HashMap<String, PoolEntry>,
+}
+
+lazy_static! {
+    static ref THREAD_MANAGER: RwLock<ThreadManager> = RwLock::default();
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ThreadPoolStats {
+    pub name: String,
+    // Threads per pool
+    pub num_threads: usize,
+    // Number of pools built under this name, e.g. one per thread for thread-local pools
+    pub num_pools: usize,
+    // Number of rayon worker threads currently running; zero for non-rayon runtimes
+    pub num_live_threads: usize,
+}
+
+/// Overrides the number of threads of the named pools. Only pools built afterwards are
+/// affected, so this should be called on startup before any subsystem is created.
+pub fn configure(overrides: HashMap<String, usize>) {
+    let mut thread_manager = THREAD_MANAGER.write().unwrap();
+    for (name, num_threads) in &overrides {
+        if let Some(entry) = thread_manager.pools.get(name) {
+            if entry.num_threads != *num_threads {
+                warn!(
+                    "thread pool {} was already built with {} threads",
+                    name, entry.num_threads
+                );
+            }
+        }
+    }
+    thread_manager.overrides = overrides;
+}
+
+/// Pins the threads of each named group to its set of CPUs. Threads already running are not
+/// moved, so this should be called on startup before any subsystem is created.
+pub fn configure_affinity(affinity: HashMap<String, Vec<usize>>) {
+    THREAD_MANAGER.write().unwrap().affinity = affinity;
+}
+
+/// Pins the calling thread, the `index`-th thread of the group `name`, to one of the CPUs
+/// configured for the group; the threads of a group are spread over its CPUs in turn.
+/// Returns false if no CPUs are configured for the group.
+pub fn pin_current_thread(name: &str, index: usize) -> bool {
+    let cpu = match THREAD_MANAGER.read().unwrap().affinity.get(name) {
+        Some(cpus) if !cpus.is_empty() => cpus[index % cpus.len()],
+        _ => return false,
+    };
+    core_affinity::set_for_current(core_affinity::CoreId { id: cpu });
+    true
+}
+
+/// Returns the number of threads for the named pool and registers the pool, for runtimes
+/// which are not built by `build_thread_pool`.
+pub fn register_pool(name: &str, default_num_threads: usize) -> usize {
+    register(name, default_num_threads).0
+}
+
+fn register(name: &str, default_num_threads: usize) -> (usize, Arc<AtomicUsize>) {
+    let mut thread_manager = THREAD_MANAGER.write().unwrap();
+    let num_threads = thread_manager
+        .overrides
+        .get(name)
+        .copied()
+        .unwrap_or(default_num_threads)
+        .max(1);
+    let entry = thread_manager
+        .pools
+        .entry(name.to_string())
+        .or_insert_with(|| PoolEntry {
+            num_threads,
+            ..PoolEntry::default()
+        });
+    entry.num_threads = num_threads;
+    entry.num_pools.fetch_add(1, Ordering::Relaxed);
+    (num_threads, entry.num_live_threads.clone())
+}
+
+/// Builds a rayon pool registered under `name`; its threads are named `name-<index>`.
+pub fn build_thread_pool(name: &'static str, default_num_threads: usize) -> ThreadPool {
+    let (num_threads, num_live_threads) = register(name, default_num_threads);
+    let num_exited_threads = num_live_threads.clone();
+    ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .thread_name(move |ix| format!("{}-{}", name, ix))
+        .start_handler(move |ix| {
+            pin_current_thread(name, ix);
+            num_live_threads.fetch_add(1, Ordering::Relaxed);
+        })
+        .exit_handler(move |_| {
+            num_exited_threads.fetch_sub(1, Ordering::Relaxed);
+        })
+        .build()
+        .unwrap()
+}
+
+pub fn thread_pool_stats() -> Vec<ThreadPoolStats> {
+    let thread_manager = THREAD_MANAGER.read().unwrap();
+    let mut stats: Vec<_> = thread_manager
+        .pools
+        .iter()
+        .map(|(name, entry)| ThreadPoolStats {
+            name: name.clone(),
+            num_threads: entry.num_threads,
+            num_pools: entry.num_pools.load(Ordering::Relaxed),
+            num_live_threads: entry.num_live_threads.load(Ordering::Relaxed),
+        })
+        .collect();
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_thread_pool() {
+        let mut overrides = HashMap::new();
+        overrides.insert("test-configured".to_string(), 3);
+        configure(overrides);
+
+        let pool = build_thread_pool("test-configured", 1);
+        assert_eq!(pool.current_num_threads(), 3);
+        let thread_name = pool.install(|| std::thread::current().name().unwrap().to_string());
+        assert!(thread_name.starts_with("test-configured-"));
+        let pool = build_thread_pool("test-default", 2);
+        assert_eq!(pool.current_num_threads(), 2);
+        assert_eq!(register_pool("test-default", 2), 2);
+
+        let stats = thread_pool_stats();
+        let stats: HashMap<_, _> = stats.iter().map(|s| (s.name.as_str(), s)).collect();
+        assert_eq!(stats["test-configured"].num_threads, 3);
+        assert_eq!(stats["test-configured"].num_pools, 1);
+        assert_eq!(stats["test-default"].num_threads, 2);
+        assert_eq!(stats["test-default"].num_pools, 2);
+    }
+
+    #[test]
+    fn test_pin_current_thread() {
+        assert!(!pin_current_thread("test-unpinned", 0));
+        let mut affinity = HashMap::new();
+        affinity.insert("test-pinned".to_string(), vec![0]);
+        configure_affinity(affinity);
+        assert!(std::thread::spawn(|| pin_current_thread("test-pinned", 3))
+            .join()
+            .unwrap());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::{
     accounts_db::{AccountsDB, BankHashInfo, ErrorCounters, LoadedAccount, ScanStorageResult},
     accounts_index::{AccountIndex, Ancestors, IndexKey},
+    accounts_load_cache::AccountsLoadCache,
     bank::{
         NonceRollbackFull, NonceRollbackInfo, TransactionCheckResult, TransactionExecutionResult,
     },
@@ -59,6 +60,9 @@ pub struct Accounts {
 
     /// Set of read-only accounts which are currently in the pipeline, caching number of locks.
     pub(crate) readonly_locks: Arc<RwLock<Option<HashMap<Pubkey, ReadonlyLock>>>>,
+
+    /// Accounts loaded for the transactions of my slot
+    pub(crate) load_cache: AccountsLoadCache,
 }
 
 // for the load instructions
@@ -114,6 +118,7 @@ impl Accounts {
             accounts_db,
             account_locks: Mutex::new(HashSet::new()),
             readonly_locks: Arc::new(RwLock::new(Some(HashMap::new()))),
+            load_cache: AccountsLoadCache::default(),
         }
     }
 
@@ -150,6 +155,13 @@ impl Accounts {
         account
     }
 
+    /// Loads for transactions go through the load cache of the slot, `ancestors` must be the
+    /// ancestors of my slot
+    fn load_cached(&self, ancestors: &Ancestors, pubkey: &Pubkey) -> Option<(Account, Slot)> {
+        self.load_cache
+            .load(pubkey, || self.accounts_db.load(ancestors, pubkey))
+    }
+
     fn load_transaction(
         &self,
         ancestors: &Ancestors,
@@ -187,8 +199,7 @@ impl Accounts {
                         Self::construct_instructions_account(message)
                     } else {
                         let (account, rent) = self
-                            .accounts_db
-                            .load(ancestors, key)
+                            .load_cached(ancestors, key)
                             .map(|(mut account, _)| {
                                 if message.is_writable(i) {
                                     let rent_due = rent_collector.collect_from_existing_account(
@@ -210,8 +221,7 @@ impl Accounts {
                             }) = account.state()
                             {
                                 if let Some(account) = self
-                                    .accounts_db
-                                    .load(ancestors, &programdata_address)
+                                    .load_cached(ancestors, &programdata_address)
                                     .map(|(account, _)| account)
                                 {
                                     account_deps.push((programdata_address, account));
@@ -317,8 +327,7 @@ impl Accounts {
             depth += 1;
 
             let program = match self
-                .accounts_db
-                .load(ancestors, &program_id)
+                .load_cached(ancestors, &program_id)
                 .map(|(account, _)| account)
             {
                 Some(program) => program,
@@ -342,8 +351,7 @@ impl Accounts {
                 }) = program.state()
                 {
                     if let Some(program) = self
-                        .accounts_db
-                        .load(ancestors, &programdata_address)
+                        .load_cached(ancestors, &programdata_address)
                         .map(|(account, _)| account)
                     {
                         accounts.insert(0, (programdata_address, program));
@@ -363,6 +371,8 @@ impl Accounts {
         Ok(accounts)
     }
 
+    /// Loads the accounts of `txs` as of `ancestors`, which must be the ancestors of my slot as
+    /// the accounts loaded are cached for the slot
     pub fn load_accounts(
         &self,
         ancestors: &Ancestors,
@@ -693,10 +703,12 @@ impl Accounts {
     /// as bypassing the cache in general is not supported
     pub fn store_slow_uncached(&self, slot: Slot, pubkey: &Pubkey, account: &Account) {
         self.accounts_db.store_uncached(slot, &[(pubkey, account)]);
+        self.load_cache.invalidate(&[*pubkey]);
     }
 
     pub fn store_slow_cached(&self, slot: Slot, pubkey: &Pubkey, account: &Account) {
         self.accounts_db.store_cached(slot, &[(pubkey, account)]);
+        self.load_cache.invalidate(&[*pubkey]);
     }
 
     fn is_locked_readonly(&self, key: &Pubkey) -> bool {
@@ -888,12 +900,15 @@ impl Accounts {
             rent_fix_enabled,
         );
         self.accounts_db.store_cached(slot, &accounts_to_store);
+        self.load_cache
+            .invalidate(accounts_to_store.iter().map(|(pubkey, _)| *pubkey));
     }
 
     /// Purge a slot if it is not a root
     /// Root slots cannot be purged
     pub fn purge_slot(&self, slot: Slot) {
         self.accounts_db.purge_slot(slot);
+        self.load_cache.clear();
     }
 
     /// Add a slot to root.  Root slots cannot be purged
@@ -2044,6 +2059,39 @@ mod tests {
         assert!(loaded_accounts[0].0.is_err());
     }
 
+    #[test]
+    fn test_load_accounts_after_store() {
+        let accounts =
+            Accounts::new_with_config(Vec::new(), &ClusterType::Development, HashSet::new(), false);
+        let keypair = Keypair::new();
+        let instructions = vec![CompiledInstruction::new(1, &(), vec![0])];
+        let tx = Transaction::new_with_compiled_instructions(
+            &[&keypair],
+            &[],
+            Hash::default(),
+            vec![native_loader::id()],
+            instructions,
+        );
+        let payer_lamports = |accounts: &Accounts| {
+            load_accounts_no_store(accounts, tx.clone())[0]
+                .0
+                .as_ref()
+                .unwrap()
+                .accounts[0]
+                .lamports
+        };
+
+        let account = Account::new(100, 0, &Pubkey::default());
+        accounts.store_slow_uncached(0, &keypair.pubkey(), &account);
+        assert_eq!(payer_lamports(&accounts), 90);
+        // Loaded from the cache the second time around
+        assert_eq!(payer_lamports(&accounts), 90);
+        // Until the account is stored again
+        let account = Account::new(50, 0, &Pubkey::default());
+        accounts.store_slow_uncached(0, &keypair.pubkey(), &account);
+        assert_eq!(payer_lamports(&accounts), 40);
+    }
+
     fn create_accounts_prepare_if_nonce_account() -> (
         Pubkey,
         Account,
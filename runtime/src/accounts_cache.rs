@@ -42,7 +42,7 @@ impl SlotCacheInner {
         );
     }
 
-    pub fn insert(&self, pubkey: &Pubkey, account: Account, hash: Hash) {
+    pub fn insert(&self, pubkey: &Pubkey, account: Account, hash: Hash, write_version: u64) {
         if self.cache.contains_key(pubkey) {
             self.same_account_writes.fetch_add(1, Ordering::Relaxed);
             self.same_account_writes_size
@@ -51,7 +51,14 @@ impl SlotCacheInner {
             self.unique_account_writes_size
                 .fetch_add(account.data.len() as u64, Ordering::Relaxed);
         }
-        self.cache.insert(*pubkey, CachedAccount { account, hash });
+        self.cache.insert(
+            *pubkey,
+            CachedAccount {
+                account,
+                hash,
+                write_version,
+            },
+        );
     }
 
     pub fn get_cloned(&self, pubkey: &Pubkey) -> Option<CachedAccount> {
@@ -88,6 +95,8 @@ impl Deref for SlotCacheInner {
 pub struct CachedAccount {
     pub account: Account,
     pub hash: Hash,
+    /// Write version the account was stored with, kept when the cache is flushed
+    pub write_version: u64,
 }
 
 #[derive(Debug, Default)]
@@ -123,7 +132,14 @@ impl AccountsCache {
         );
     }
 
-    pub fn store(&self, slot: Slot, pubkey: &Pubkey, account: Account, hash: Hash) {
+    pub fn store(
+        &self,
+        slot: Slot,
+        pubkey: &Pubkey,
+        account: Account,
+        hash: Hash,
+        write_version: u64,
+    ) {
         let slot_cache = self.slot_cache(slot).unwrap_or_else(||
             // DashMap entry.or_insert() returns a RefMut, essentially a write lock,
             // which is dropped after this block ends, minimizing time held by the lock.
@@ -135,7 +151,7 @@ impl AccountsCache {
                 .or_insert(Arc::new(SlotCacheInner::default()))
                 .clone());
 
-        slot_cache.insert(pubkey, account, hash);
+        slot_cache.insert(pubkey, account, hash, write_version);
     }
 
     pub fn load(&self, slot: Slot, pubkey: &Pubkey) -> Option<CachedAccount> {
@@ -237,6 +253,7 @@ pub mod tests {
             &Pubkey::new_unique(),
             Account::new(1, 0, &Pubkey::default()),
             Hash::default(),
+            0,
         );
         // If the cache is told the size limit is 0, it should return the one slot
         let removed = cache.remove_slots_le(0);
@@ -255,6 +272,7 @@ pub mod tests {
             &Pubkey::new_unique(),
             Account::new(1, 0, &Pubkey::default()),
             Hash::default(),
+            0,
         );
 
         // If the cache is told the size limit is 0, it should return nothing because there's only
@@ -17,6 +17,8 @@
 //! a "write_version".  A single global atomic `AccountsDB::write_version`
 //! tracks the number of commits to the entire data store. So the latest
 //! commit for each slot entry would be indexed.
+//! The write versions also order the updates given to an `AccountsUpdateNotifier`,
+//! see `accounts_update_notifier` for the guarantees they come with.
 
 use crate::{
     accounts_cache::{AccountsCache, CachedAccount, SlotCache},
@@ -24,6 +26,7 @@ use crate::{
         AccountIndex, AccountsIndex, AccountsIndexRootsStats, Ancestors, IndexKey, IsCached,
         SlotList, SlotSlice, ZeroLamport,
     },
+    accounts_update_notifier::{AccountUpdate, AccountsUpdateNotifier},
     append_vec::{AppendVec, StoredAccountMeta, StoredMeta},
     contains::Contains,
 };
@@ -38,12 +41,14 @@ use rand::{prelude::SliceRandom, thread_rng, Rng};
 use rayon::{prelude::*, ThreadPool};
 use serde::{Deserialize, Serialize};
 use solana_measure::measure::Measure;
-use solana_rayon_threadlimit::get_thread_count;
+use solana_rayon_threadlimit::{get_thread_count, thread_manager};
 use solana_sdk::{
     account::Account,
+    bpf_loader, bpf_loader_deprecated, bpf_loader_upgradeable,
     clock::{Epoch, Slot},
     genesis_config::ClusterType,
     hash::{Hash, Hasher},
+    native_loader,
     pubkey::Pubkey,
 };
 use solana_vote_program::vote_state::MAX_LOCKOUT_HISTORY;
@@ -52,8 +57,9 @@ use std::{
     boxed::Box,
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     convert::{TryFrom, TryInto},
+    fmt,
     io::{Error as IOError, Result as IOResult},
-    ops::RangeBounds,
+    ops::{Range, RangeBounds},
     path::{Path, PathBuf},
     sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     sync::{Arc, Mutex, MutexGuard, RwLock},
@@ -246,7 +252,7 @@ impl<'a> LoadedAccount<'a> {
     pub fn write_version(&self) -> u64 {
         match self {
             LoadedAccount::Stored(stored_account_meta) => stored_account_meta.meta.write_version,
-            LoadedAccount::Cached(_) => CACHE_VIRTUAL_WRITE_VERSION,
+            LoadedAccount::Cached((_, cached_account)) => cached_account.write_version,
         }
     }
 
@@ -337,6 +343,74 @@ struct CleanKeyTimings {
     zero_lamport_count: u64,
 }
 
+/// A problem found in an append vec by `AccountsDB::verify_storages()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StorageCorruption {
+    /// The stored accounts do not fill the append vec up to its length, or one of them is
+    /// malformed
+    InvalidLayout { readable_accounts: usize },
+    /// The stored hash does not match the hash of the stored account
+    HashMismatch { pubkey: Pubkey, offset: usize },
+    /// An executable account is not owned by a loader
+    InvalidOwner {
+        pubkey: Pubkey,
+        offset: usize,
+        owner: Pubkey,
+    },
+    /// The accounts index refers to an offset that does not hold the indexed account
+    IndexMismatch { pubkey: Pubkey, offset: usize },
+}
+
+impl fmt::Display for StorageCorruption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageCorruption::InvalidLayout { readable_accounts } => write!(
+                f,
+                "invalid layout after {} readable accounts",
+                readable_accounts
+            ),
+            StorageCorruption::HashMismatch { pubkey, offset } => {
+                write!(f, "hash mismatch for {} at offset {}", pubkey, offset)
+            }
+            StorageCorruption::InvalidOwner {
+                pubkey,
+                offset,
+                owner,
+            } => write!(
+                f,
+                "executable account {} at offset {} is owned by {}, not a loader",
+                pubkey, offset, owner
+            ),
+            StorageCorruption::IndexMismatch { pubkey, offset } => write!(
+                f,
+                "index entry for {} points at offset {} which does not hold it",
+                pubkey, offset
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CorruptStorage {
+    pub slot: Slot,
+    pub id: AppendVecId,
+    pub path: PathBuf,
+    pub corruptions: Vec<StorageCorruption>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StorageVerificationReport {
+    pub storages_checked: usize,
+    pub accounts_checked: usize,
+    pub corrupt_storages: Vec<CorruptStorage>,
+}
+
+impl StorageVerificationReport {
+    pub fn is_ok(&self) -> bool {
+        self.corrupt_storages.is_empty()
+    }
+}
+
 /// Persistent storage structure holding the accounts
 #[derive(Debug)]
 pub struct AccountStorageEntry {
@@ -654,6 +728,9 @@ pub struct AccountsDB {
 
     pub caching_enabled: bool,
 
+    /// Told of every account stored, see `accounts_update_notifier`
+    accounts_update_notifier: RwLock<Option<Arc<dyn AccountsUpdateNotifier>>>,
+
     /// Set of unique keys per slot which is used
     /// to drive clean_accounts
     /// Generated by get_accounts_delta_hash
@@ -937,11 +1014,7 @@ impl ShrinkStats {
 fn make_min_priority_thread_pool() -> ThreadPool {
     // Use lower thread count to reduce priority.
     let num_threads = std::cmp::max(2, num_cpus::get() / 4);
-    rayon::ThreadPoolBuilder::new()
-        .thread_name(|i| format!("solana-accounts-cleanup-{}", i))
-        .num_threads(num_threads)
-        .build()
-        .unwrap()
+    thread_manager::build_thread_pool("solana-accounts-cleanup", num_threads)
 }
 
 #[cfg(all(test, RUSTC_WITH_SPECIALIZATION))]
@@ -961,7 +1034,9 @@ impl solana_frozen_abi::abi_example::AbiExample for AccountsDB {
 
 impl Default for AccountsDB {
     fn default() -> Self {
-        let num_threads = get_thread_count();
+        let thread_pool =
+            thread_manager::build_thread_pool("solana-accounts-db", get_thread_count());
+        let num_threads = thread_pool.current_num_threads();
 
         let mut bank_hashes = HashMap::new();
         bank_hashes.insert(0, BankHashInfo::default());
@@ -979,11 +1054,7 @@ impl Default for AccountsDB {
             shrink_paths: RwLock::new(None),
             temp_paths: None,
             file_size: DEFAULT_FILE_SIZE,
-            thread_pool: rayon::ThreadPoolBuilder::new()
-                .num_threads(num_threads)
-                .thread_name(|i| format!("solana-accounts-db-{}", i))
-                .build()
-                .unwrap(),
+            thread_pool,
             thread_pool_clean: make_min_priority_thread_pool(),
             min_num_stores: num_threads,
             bank_hashes: RwLock::new(bank_hashes),
@@ -995,6 +1066,7 @@ impl Default for AccountsDB {
             cluster_type: None,
             account_indexes: HashSet::new(),
             caching_enabled: false,
+            accounts_update_notifier: RwLock::default(),
         }
     }
 }
@@ -1053,6 +1125,11 @@ impl AccountsDB {
         self.file_size
     }
 
+    /// Tells `notifier` of every account stored from now on, replacing any previous notifier
+    pub fn set_accounts_update_notifier(&self, notifier: Option<Arc<dyn AccountsUpdateNotifier>>) {
+        *self.accounts_update_notifier.write().unwrap() = notifier;
+    }
+
     pub fn new_single() -> Self {
         AccountsDB {
             min_num_stores: 0,
@@ -3426,6 +3503,7 @@ impl AccountsDB {
             let mut total_size = 0;
             let mut purged_slot_pubkeys: HashSet<(Slot, Pubkey)> = HashSet::new();
             let mut pubkey_to_slot_set: Vec<(Pubkey, Slot)> = vec![];
+            let mut write_versions = vec![];
             let (accounts, hashes): (Vec<(&Pubkey, &Account)>, Vec<Hash>) = iter_items
                 .iter()
                 .filter_map(|iter_item| {
@@ -3437,6 +3515,7 @@ impl AccountsDB {
                         .unwrap_or(true);
                     if should_flush {
                         let hash = iter_item.value().hash;
+                        write_versions.push(iter_item.value().write_version);
                         total_size += (account.data.len() + STORE_META_OVERHEAD) as u64;
                         Some(((key, account), hash))
                     } else {
@@ -3471,7 +3550,8 @@ impl AccountsDB {
                     &accounts,
                     &hashes,
                     Some(Box::new(move |_, _| flushed_store.clone())),
-                    None,
+                    // Keep the write versions the accounts were stored in the cache with
+                    Some(Box::new(write_versions.into_iter())),
                     false,
                 );
                 // If the above sizing function is correct, just one AppendVec is enough to hold
@@ -3507,8 +3587,13 @@ impl AccountsDB {
             .iter()
             .zip(hashes)
             .map(|((meta, account), hash)| {
-                self.accounts_cache
-                    .store(slot, &meta.pubkey, (**account).clone(), *hash);
+                self.accounts_cache.store(
+                    slot,
+                    &meta.pubkey,
+                    (**account).clone(),
+                    *hash,
+                    meta.write_version,
+                );
                 AccountInfo {
                     store_id: CACHE_VIRTUAL_STORAGE_ID,
                     offset: CACHE_VIRTUAL_OFFSET,
@@ -4247,16 +4332,31 @@ impl AccountsDB {
         self.store(slot, accounts, self.caching_enabled);
     }
 
+    /// Like `store_cached`, returning the write versions the accounts were stored with, in order
+    pub fn store_cached_with_write_versions(
+        &self,
+        slot: Slot,
+        accounts: &[(&Pubkey, &Account)],
+    ) -> Range<u64> {
+        self.store(slot, accounts, self.caching_enabled)
+    }
+
     /// Store the account update.
     pub fn store_uncached(&self, slot: Slot, accounts: &[(&Pubkey, &Account)]) {
         self.store(slot, accounts, false);
     }
 
-    fn store(&self, slot: Slot, accounts: &[(&Pubkey, &Account)], is_cached_store: bool) {
+    fn store(
+        &self,
+        slot: Slot,
+        accounts: &[(&Pubkey, &Account)],
+        is_cached_store: bool,
+    ) -> Range<u64> {
         // If all transactions in a batch are errored,
         // it's possible to get a store with no accounts.
         if accounts.is_empty() {
-            return;
+            let write_version = self.write_version.load(Ordering::Relaxed);
+            return write_version..write_version;
         }
         self.assert_frozen_accounts(accounts);
         let mut hash_time = Measure::start("hash_accounts");
@@ -4271,8 +4371,30 @@ impl AccountsDB {
         self.stats
             .store_hash_accounts
             .fetch_add(hash_time.as_us(), Ordering::Relaxed);
-        self.store_accounts_default(slot, accounts, &hashes, is_cached_store);
+        let write_versions = self.store_accounts_default(slot, accounts, &hashes, is_cached_store);
+        self.notify_accounts_update(slot, accounts, &write_versions);
         self.report_store_timings();
+        write_versions
+    }
+
+    fn notify_accounts_update(
+        &self,
+        slot: Slot,
+        accounts: &[(&Pubkey, &Account)],
+        write_versions: &Range<u64>,
+    ) {
+        if let Some(notifier) = &*self.accounts_update_notifier.read().unwrap() {
+            let updates: Vec<_> = accounts
+                .iter()
+                .zip(write_versions.clone())
+                .map(|((pubkey, account), write_version)| AccountUpdate {
+                    pubkey,
+                    account,
+                    write_version,
+                })
+                .collect();
+            notifier.notify_accounts_update(slot, &updates);
+        }
     }
 
     fn report_store_timings(&self) {
@@ -4368,15 +4490,18 @@ impl AccountsDB {
         accounts: &[(&Pubkey, &Account)],
         hashes: &[Hash],
         is_cached_store: bool,
-    ) {
+    ) -> Range<u64> {
+        let write_version = self.bulk_assign_write_version(accounts.len());
+        let write_versions = write_version..write_version + accounts.len() as u64;
         self.store_accounts_custom(
             slot,
             accounts,
             hashes,
             None::<StorageFinder>,
-            None::<Box<dyn Iterator<Item = u64>>>,
+            Some(Box::new(write_versions.clone())),
             is_cached_store,
         );
+        write_versions
     }
 
     fn store_accounts_custom<'a>(
@@ -4658,6 +4783,94 @@ impl AccountsDB {
         }
     }
 
+    /// Checks that every append vec is well formed, that the stored hashes match the stored
+    /// accounts, that executable accounts are owned by a loader, and that the accounts index
+    /// points at the accounts it indexes
+    pub fn verify_storages(&self) -> StorageVerificationReport {
+        let cluster_type = self
+            .cluster_type
+            .expect("Cluster type must be set at initialization");
+
+        let mut indexed_offsets: HashMap<AppendVecId, Vec<(Pubkey, usize)>> = HashMap::new();
+        for (pubkey, account_entry) in self.accounts_index.account_maps.read().unwrap().iter() {
+            for (_slot, account_info) in account_entry.slot_list.read().unwrap().iter() {
+                indexed_offsets
+                    .entry(account_info.store_id)
+                    .or_default()
+                    .push((*pubkey, account_info.offset));
+            }
+        }
+
+        let mut report = StorageVerificationReport::default();
+        let mut slots = self.storage.all_slots();
+        slots.sort_unstable();
+        for slot in slots {
+            let stores: Vec<_> = match self.storage.get_slot_stores(slot) {
+                Some(slot_stores) => slot_stores.read().unwrap().values().cloned().collect(),
+                None => continue,
+            };
+            for store in stores {
+                report.storages_checked += 1;
+                let mut corruptions = vec![];
+
+                let (layout_ok, readable_accounts) = store.accounts.sanitize_layout_and_length();
+                if !layout_ok {
+                    corruptions.push(StorageCorruption::InvalidLayout { readable_accounts });
+                }
+                for account in store.accounts.accounts(0) {
+                    report.accounts_checked += 1;
+                    if *account.hash != Self::hash_stored_account(slot, &account, &cluster_type) {
+                        corruptions.push(StorageCorruption::HashMismatch {
+                            pubkey: account.meta.pubkey,
+                            offset: account.offset,
+                        });
+                    }
+                    let owner = account.account_meta.owner;
+                    if account.account_meta.executable && !Self::is_loader(&owner) {
+                        corruptions.push(StorageCorruption::InvalidOwner {
+                            pubkey: account.meta.pubkey,
+                            offset: account.offset,
+                            owner,
+                        });
+                    }
+                }
+                for (pubkey, offset) in indexed_offsets
+                    .get(&store.append_vec_id())
+                    .map(|offsets| offsets.as_slice())
+                    .unwrap_or_default()
+                {
+                    let holds_account = store
+                        .get_stored_account_meta(*offset)
+                        .map(|account| account.meta.pubkey == *pubkey)
+                        .unwrap_or(false);
+                    if !holds_account {
+                        corruptions.push(StorageCorruption::IndexMismatch {
+                            pubkey: *pubkey,
+                            offset: *offset,
+                        });
+                    }
+                }
+
+                if !corruptions.is_empty() {
+                    report.corrupt_storages.push(CorruptStorage {
+                        slot,
+                        id: store.append_vec_id(),
+                        path: store.get_path(),
+                        corruptions,
+                    });
+                }
+            }
+        }
+        report
+    }
+
+    fn is_loader(owner: &Pubkey) -> bool {
+        *owner == bpf_loader::id()
+            || *owner == bpf_loader_deprecated::id()
+            || *owner == bpf_loader_upgradeable::id()
+            || *owner == native_loader::id()
+    }
+
     #[cfg(test)]
     pub fn get_append_vec_id(&self, pubkey: &Pubkey, slot: Slot) -> Option<AppendVecId> {
         let ancestors = vec![(slot, 1)].into_iter().collect();
@@ -7602,6 +7815,99 @@ pub mod tests {
         );
     }
 
+    #[derive(Debug, Default)]
+    struct RecordingAccountsUpdateNotifier {
+        updates: Mutex<Vec<(Slot, Pubkey, u64)>>,
+    }
+
+    impl AccountsUpdateNotifier for RecordingAccountsUpdateNotifier {
+        fn notify_accounts_update(&self, slot: Slot, updates: &[AccountUpdate]) {
+            self.updates.lock().unwrap().extend(
+                updates
+                    .iter()
+                    .map(|update| (slot, *update.pubkey, update.write_version)),
+            );
+        }
+    }
+
+    #[test]
+    fn test_accounts_update_write_versions() {
+        let mut db = AccountsDB::new(Vec::new(), &ClusterType::Development);
+        db.caching_enabled = true;
+        let notifier = Arc::new(RecordingAccountsUpdateNotifier::default());
+        db.set_accounts_update_notifier(Some(notifier.clone()));
+        let account0 = Account::new(1, 0, &Pubkey::default());
+        let account1 = Account::new(2, 0, &Pubkey::default());
+        let key0 = solana_sdk::pubkey::new_rand();
+        let key1 = solana_sdk::pubkey::new_rand();
+        let key2 = solana_sdk::pubkey::new_rand();
+
+        let write_versions =
+            db.store_cached_with_write_versions(1, &[(&key0, &account0), (&key1, &account0)]);
+        assert_eq!(write_versions.end - write_versions.start, 2);
+        db.store_cached(1, &[(&key0, &account1)]);
+        db.store_cached(2, &[(&key2, &account1)]);
+        // Empty stores assign nothing and aren't notified
+        let empty_write_versions = db.store_cached_with_write_versions(2, &[]);
+        assert!(empty_write_versions.start >= write_versions.end);
+        assert!(empty_write_versions.is_empty());
+
+        let updates = notifier.updates.lock().unwrap().clone();
+        assert_eq!(
+            updates
+                .iter()
+                .map(|(slot, pubkey, _)| (*slot, *pubkey))
+                .collect::<Vec<_>>(),
+            vec![(1, key0), (1, key1), (1, key0), (2, key2)]
+        );
+        assert_eq!(updates[0].2, write_versions.start);
+        assert_eq!(updates[1].2, write_versions.start + 1);
+        // Write versions strictly increase from one store to the next
+        assert!(updates
+            .windows(2)
+            .all(|updates| updates[0].2 < updates[1].2));
+
+        // Cached accounts keep their write versions through the flush to storage
+        let cached_write_version = |slot, pubkey| {
+            db.accounts_cache
+                .load(slot, pubkey)
+                .map(|cached_account| cached_account.write_version)
+        };
+        assert_eq!(cached_write_version(1, &key0), Some(updates[2].2));
+        for slot in &[1, 2] {
+            db.mark_slot_frozen(*slot);
+            db.add_root(*slot);
+        }
+        db.flush_accounts_cache(true, None);
+        assert_eq!(db.accounts_cache.num_slots(), 0);
+        let stored_write_versions = |slot| -> HashMap<Pubkey, u64> {
+            db.storage
+                .get_slot_stores(slot)
+                .unwrap()
+                .read()
+                .unwrap()
+                .values()
+                .flat_map(|store| store.accounts.accounts(0))
+                .map(|stored_account| {
+                    (
+                        stored_account.meta.pubkey,
+                        stored_account.meta.write_version,
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(
+            stored_write_versions(1),
+            vec![(key0, updates[2].2), (key1, updates[1].2)]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            stored_write_versions(2),
+            vec![(key2, updates[3].2)].into_iter().collect()
+        );
+    }
+
     #[test]
     fn test_flush_accounts_cache_if_needed() {
         run_test_flush_accounts_cache_if_needed(0, 2 * MAX_CACHE_SLOTS);
@@ -8343,4 +8649,56 @@ pub mod tests {
         assert!(slot_stores(&db, 0).is_empty());
         assert!(!slot_stores(&db, 1).is_empty());
     }
+
+    #[test]
+    fn test_verify_storages() {
+        let db = AccountsDB::new_single();
+        let key1 = Pubkey::new_unique();
+        let key2 = Pubkey::new_unique();
+        db.store_uncached(0, &[(&key1, &Account::new(1, 0, &Pubkey::default()))]);
+        let mut program = Account::new(1, 0, &bpf_loader::id());
+        program.executable = true;
+        db.store_uncached(0, &[(&key2, &program)]);
+        let report = db.verify_storages();
+        assert!(report.is_ok());
+        assert_eq!(report.accounts_checked, 2);
+
+        // An executable account owned by a non-loader
+        let key3 = Pubkey::new_unique();
+        program.owner = Pubkey::new_unique();
+        db.store_uncached(1, &[(&key3, &program)]);
+        // An index entry pointing into an emptied append vec
+        let key4 = Pubkey::new_unique();
+        db.store_uncached(2, &[(&key4, &Account::new(1, 0, &Pubkey::default()))]);
+        slot_stores(&db, 2)[0].accounts.reset();
+
+        let report = db.verify_storages();
+        assert_eq!(report.storages_checked, 3);
+        assert_eq!(report.accounts_checked, 3);
+        let corruptions: Vec<_> = report
+            .corrupt_storages
+            .iter()
+            .map(|storage| (storage.slot, storage.corruptions.clone()))
+            .collect();
+        assert_eq!(
+            corruptions,
+            vec![
+                (
+                    1,
+                    vec![StorageCorruption::InvalidOwner {
+                        pubkey: key3,
+                        offset: 0,
+                        owner: program.owner,
+                    }]
+                ),
+                (
+                    2,
+                    vec![StorageCorruption::IndexMismatch {
+                        pubkey: key4,
+                        offset: 0,
+                    }]
+                ),
+            ]
+        );
+    }
 }
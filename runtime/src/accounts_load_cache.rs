@@ -0,0 +1,185 @@
+use solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        RwLock,
+    },
+};
+
+// Bounds on what a slot keeps cached, loads past them go to AccountsDB every time
+const MAX_CACHED_ACCOUNTS: usize = 16 * 1024;
+const MAX_CACHED_DATA_BYTES: usize = 256 * 1024 * 1024;
+
+/// The accounts loaded for the transactions of a single slot, so that the ones loaded over and
+/// over, like programs, sysvars and popular markets, are only looked up in AccountsDB once per
+/// slot.  Entries hold the account as stored, before any rent is collected from the copy handed
+/// to a transaction, and are dropped when the account is stored again.  Once the slot is frozen
+/// the cache is closed, and later loads, like those of simulated transactions, skip it
+#[derive(Debug, Default)]
+pub struct AccountsLoadCache {
+    inner: RwLock<AccountsLoadCacheInner>,
+    /// Set once the slot loads no more accounts for the transactions of its block
+    closed: AtomicBool,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct AccountsLoadCacheInner {
+    accounts: HashMap<Pubkey, Option<(Account, Slot)>>,
+    data_bytes: usize,
+    /// Counts the stores, a load that raced with one must not be cached
+    store_count: u64,
+}
+
+impl AccountsLoadCache {
+    /// Returns the cached load of `pubkey`, or calls `load` and caches what it returns.  A load
+    /// running while `pubkey` is stored may have seen the old account, so it is only cached when
+    /// nothing was stored in the meantime
+    pub fn load<F>(&self, pubkey: &Pubkey, load: F) -> Option<(Account, Slot)>
+    where
+        F: FnOnce() -> Option<(Account, Slot)>,
+    {
+        if self.closed.load(Ordering::Relaxed) {
+            return load();
+        }
+        let store_count = {
+            let inner = self.inner.read().unwrap();
+            if let Some(loaded) = inner.accounts.get(pubkey) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return loaded.clone();
+            }
+            inner.store_count
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let loaded = load();
+
+        let mut inner = self.inner.write().unwrap();
+        let data_len = loaded.as_ref().map_or(0, |(account, _)| account.data.len());
+        if inner.store_count == store_count
+            && !self.closed.load(Ordering::Relaxed)
+            && inner.accounts.len() < MAX_CACHED_ACCOUNTS
+            && inner.data_bytes + data_len <= MAX_CACHED_DATA_BYTES
+            && !inner.accounts.contains_key(pubkey)
+        {
+            inner.data_bytes += data_len;
+            inner.accounts.insert(*pubkey, loaded.clone());
+        }
+        loaded
+    }
+
+    /// Drops the entries of the accounts just stored.  Called once the store is done, so that
+    /// loads that started before it can tell they raced with it
+    pub fn invalidate<'a>(&self, pubkeys: impl IntoIterator<Item = &'a Pubkey>) {
+        let mut inner = self.inner.write().unwrap();
+        inner.store_count += 1;
+        for pubkey in pubkeys {
+            if let Some(loaded) = inner.accounts.remove(pubkey) {
+                inner.data_bytes -= loaded.map_or(0, |(account, _)| account.data.len());
+                self.invalidations.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Drops every entry, once the slot is done loading accounts or its accounts are gone
+    pub fn clear(&self) {
+        let mut inner = self.inner.write().unwrap();
+        inner.store_count += 1;
+        inner.accounts = HashMap::new();
+        inner.data_bytes = 0;
+    }
+
+    /// Drops every entry and stops caching, once the slot loads no more accounts for the
+    /// transactions of its block
+    pub fn close(&self) {
+        let mut inner = self.inner.write().unwrap();
+        self.closed.store(true, Ordering::Relaxed);
+        inner.store_count += 1;
+        inner.accounts = HashMap::new();
+        inner.data_bytes = 0;
+    }
+
+    pub fn report_metrics(&self, slot: Slot) {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let hit_rate_percent = if hits + misses == 0 {
+            0
+        } else {
+            hits * 100 / (hits + misses)
+        };
+        datapoint_info!(
+            "accounts_load_cache",
+            ("slot", slot as i64, i64),
+            ("hits", hits as i64, i64),
+            ("misses", misses as i64, i64),
+            ("hit_rate_percent", hit_rate_percent as i64, i64),
+            (
+                "invalidations",
+                self.invalidations.load(Ordering::Relaxed) as i64,
+                i64
+            ),
+            (
+                "cached_accounts",
+                self.inner.read().unwrap().accounts.len() as i64,
+                i64
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounts_load_cache() {
+        let cache = AccountsLoadCache::default();
+        let pubkey = Pubkey::new_unique();
+        let account = Account::new(1, 3, &Pubkey::default());
+        let loaded = Some((account.clone(), 0));
+
+        assert_eq!(cache.load(&pubkey, || loaded.clone()), loaded);
+        assert_eq!(cache.load(&pubkey, || panic!("cached")), loaded);
+        // Missing accounts are cached as well
+        let other_pubkey = Pubkey::new_unique();
+        assert_eq!(cache.load(&other_pubkey, || None), None);
+        assert_eq!(cache.load(&other_pubkey, || panic!("cached")), None);
+        assert_eq!(cache.hits.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 2);
+
+        // Stores invalidate the accounts stored alone
+        cache.invalidate(&[pubkey]);
+        let stored = Some((Account::new(2, 0, &Pubkey::default()), 1));
+        assert_eq!(cache.load(&pubkey, || stored.clone()), stored);
+        assert_eq!(cache.load(&other_pubkey, || panic!("cached")), None);
+        assert_eq!(cache.invalidations.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.inner.read().unwrap().data_bytes, 0);
+
+        cache.clear();
+        assert_eq!(cache.load(&pubkey, || loaded.clone()), loaded);
+
+        // Closed caches load every time, and keep nothing
+        cache.close();
+        assert_eq!(cache.inner.read().unwrap().accounts.len(), 0);
+        assert_eq!(cache.load(&pubkey, || loaded.clone()), loaded);
+        assert_eq!(cache.load(&pubkey, || stored.clone()), stored);
+        assert_eq!(cache.inner.read().unwrap().accounts.len(), 0);
+    }
+
+    #[test]
+    fn test_accounts_load_cache_racing_store() {
+        let cache = AccountsLoadCache::default();
+        let pubkey = Pubkey::new_unique();
+        let stale = Some((Account::new(1, 0, &Pubkey::default()), 0));
+        // The account is stored while it is being loaded, what the load saw is not cached
+        let loaded = cache.load(&pubkey, || {
+            cache.invalidate(&[pubkey]);
+            stale.clone()
+        });
+        assert_eq!(loaded, stale);
+        let stored = Some((Account::new(2, 0, &Pubkey::default()), 1));
+        assert_eq!(cache.load(&pubkey, || stored.clone()), stored);
+    }
+}
@@ -0,0 +1,31 @@
+//! Notifications of the accounts stored by `AccountsDB`, for plugins and replication services
+//! following account updates as they happen.
+//!
+//! Every account stored is given a write version, which orders the updates:
+//!  - Write versions are unique within an `AccountsDB` and only ever increase, a store call
+//!    gives its accounts consecutive write versions in the order they were passed in.  A store
+//!    that returned before another one started has the lower write versions.
+//!  - Within a slot, the latest update of an account is the one with the highest write version.
+//!  - Across slots, the state of an account as of a slot is its update with the highest write
+//!    version among that slot and its ancestors.  Forks are replayed in any order, so a later
+//!    slot may well have lower write versions than an earlier one on another fork.
+//!  - An account keeps its write version when the accounts cache is flushed to storage and
+//!    across snapshots.
+//!
+//! Stores running concurrently on different threads may be notified out of write version order,
+//! consumers must order updates by `(slot, write_version)` rather than by arrival.
+use solana_sdk::{account::Account, clock::Slot, pubkey::Pubkey};
+use std::fmt::Debug;
+
+pub trait AccountsUpdateNotifier: Debug + Send + Sync {
+    /// Called once per store, with the accounts in the order of their write versions.  It runs
+    /// on the storing thread, implementations should queue the updates rather than process them
+    fn notify_accounts_update(&self, slot: Slot, updates: &[AccountUpdate]);
+}
+
+#[derive(Debug, PartialEq)]
+pub struct AccountUpdate<'a> {
+    pub pubkey: &'a Pubkey,
+    pub account: &'a Account,
+    pub write_version: u64,
+}
@@ -303,7 +303,7 @@ impl AppendVec {
         Ok((new, num_accounts))
     }
 
-    fn sanitize_layout_and_length(&self) -> (bool, usize) {
+    pub(crate) fn sanitize_layout_and_length(&self) -> (bool, usize) {
         let mut offset = 0;
 
         // This discards allocated accounts immediately after check at each loop iteration.
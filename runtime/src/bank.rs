@@ -7,15 +7,16 @@ use crate::{
         AccountAddressFilter, Accounts, TransactionAccountDeps, TransactionAccounts,
         TransactionLoadResult, TransactionLoaders,
     },
-    accounts_db::{ErrorCounters, SnapshotStorages},
+    accounts_db::{ErrorCounters, SnapshotStorages, StorageVerificationReport},
     accounts_index::{AccountIndex, Ancestors, IndexKey},
+    block_cost,
     blockhash_queue::BlockhashQueue,
     builtins::{self, ActivationType},
     epoch_stakes::{EpochStakes, NodeVoteAccounts},
     inline_spl_token_v2_0,
     instruction_recorder::InstructionRecorder,
     log_collector::LogCollector,
-    message_processor::{Executors, MessageProcessor},
+    message_processor::{ExecuteDetailsTimings, Executors, MessageProcessor},
     rent_collector::RentCollector,
     stakes::Stakes,
     status_cache::{SlotDelta, StatusCache},
@@ -33,8 +34,8 @@ use solana_sdk::{
     account::{create_account, from_account, Account},
     clock::{
         Epoch, Slot, SlotCount, SlotIndex, UnixTimestamp, DEFAULT_TICKS_PER_SECOND,
-        MAX_PROCESSING_AGE, MAX_RECENT_BLOCKHASHES, MAX_TRANSACTION_FORWARDING_DELAY,
-        SECONDS_PER_DAY,
+        EXTENDED_MAX_PROCESSING_AGE, MAX_PROCESSING_AGE, MAX_RECENT_BLOCKHASHES,
+        MAX_TRANSACTION_FORWARDING_DELAY, SECONDS_PER_DAY,
     },
     epoch_info::EpochInfo,
     epoch_schedule::EpochSchedule,
@@ -83,7 +84,10 @@ use std::{
     ptr,
     rc::Rc,
     sync::{
-        atomic::{AtomicBool, AtomicU64, Ordering::Relaxed},
+        atomic::{
+            AtomicBool, AtomicU64,
+            Ordering::{AcqRel, Acquire, Relaxed, Release},
+        },
         LockResult, RwLockWriteGuard, {Arc, RwLock, RwLockReadGuard},
     },
     time::Duration,
@@ -93,18 +97,22 @@ pub const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
 
 pub const MAX_LEADER_SCHEDULE_STAKES: Epoch = 5;
 
-#[derive(Default)]
+#[derive(AbiExample, Clone, Debug, Default)]
 pub struct ExecuteTimings {
+    pub lock_us: u64,
     pub load_us: u64,
     pub execute_us: u64,
     pub store_us: u64,
+    pub details: ExecuteDetailsTimings,
 }
 
 impl ExecuteTimings {
     pub fn accumulate(&mut self, other: &ExecuteTimings) {
+        self.lock_us += other.lock_us;
         self.load_us += other.load_us;
         self.execute_us += other.execute_us;
         self.store_us += other.store_us;
+        self.details.accumulate(&other.details);
     }
 }
 
@@ -270,23 +278,32 @@ impl CachedExecutors {
     }
     fn put(&mut self, pubkey: &Pubkey, executor: Arc<dyn Executor>) {
         if !self.executors.contains_key(pubkey) && self.executors.len() >= self.max {
-            let mut least = u64::MAX;
-            let default_key = Pubkey::default();
-            let mut least_key = &default_key;
-            for (key, (count, _)) in self.executors.iter() {
-                let count = count.load(Relaxed);
-                if count < least {
-                    least = count;
-                    least_key = key;
-                }
-            }
-            let least_key = *least_key;
-            let _ = self.executors.remove(&least_key);
+            self.remove_least_used();
         }
         let _ = self
             .executors
             .insert(*pubkey, (AtomicU64::new(0), executor));
     }
+    fn remove_least_used(&mut self) {
+        let mut least = u64::MAX;
+        let default_key = Pubkey::default();
+        let mut least_key = &default_key;
+        for (key, (count, _)) in self.executors.iter() {
+            let count = count.load(Relaxed);
+            if count < least {
+                least = count;
+                least_key = key;
+            }
+        }
+        let least_key = *least_key;
+        let _ = self.executors.remove(&least_key);
+    }
+    fn set_max(&mut self, max: usize) {
+        self.max = max;
+        while self.executors.len() > max {
+            self.remove_least_used();
+        }
+    }
     fn remove(&mut self, pubkey: &Pubkey) {
         let _ = self.executors.remove(pubkey);
     }
@@ -852,6 +869,9 @@ pub struct Bank {
     /// Cached executors
     cached_executors: RwLock<CowCachedExecutors>,
 
+    /// SlotHashes sysvar exposed to programs, deserialized by the first batch that needs it
+    slot_hashes_for_programs: RwLock<Option<Arc<SlotHashes>>>,
+
     transaction_debug_keys: Option<Arc<HashSet<Pubkey>>>,
 
     // Global configuration for how transaction logs should be collected across all banks
@@ -866,6 +886,15 @@ pub struct Bank {
     pub drop_callback: RwLock<OptionalDropCallback>,
 
     pub freeze_started: AtomicBool,
+
+    // Transaction execution timings accumulated over all the batches processed in this Bank
+    execute_timings: RwLock<ExecuteTimings>,
+
+    // Cost of the transactions committed to this Bank, in compute units
+    block_units: AtomicU64,
+
+    // Cost of the transactions a leader is processing into this Bank, but hasn't committed yet
+    pending_block_units: AtomicU64,
 }
 
 impl Default for BlockhashQueue {
@@ -1040,6 +1069,7 @@ impl Bank {
             no_stake_rewrite: AtomicBool::new(parent.no_stake_rewrite.load(Relaxed)),
             rewards_pool_pubkeys: parent.rewards_pool_pubkeys.clone(),
             cached_executors: RwLock::new((*parent.cached_executors.read().unwrap()).clone()),
+            slot_hashes_for_programs: RwLock::new(None),
             transaction_debug_keys: parent.transaction_debug_keys.clone(),
             transaction_log_collector_config: parent.transaction_log_collector_config.clone(),
             transaction_log_collector: Arc::new(RwLock::new(TransactionLogCollector::default())),
@@ -1054,6 +1084,9 @@ impl Bank {
                     .map(|drop_callback| drop_callback.clone_box()),
             )),
             freeze_started: AtomicBool::new(false),
+            execute_timings: RwLock::new(ExecuteTimings::default()),
+            block_units: AtomicU64::new(0),
+            pending_block_units: AtomicU64::new(0),
         };
 
         datapoint_info!(
@@ -1184,14 +1217,21 @@ impl Bank {
             cached_executors: RwLock::new(CowCachedExecutors::new(Arc::new(RwLock::new(
                 CachedExecutors::new(MAX_CACHED_EXECUTORS),
             )))),
+            slot_hashes_for_programs: new(),
             transaction_debug_keys: debug_keys,
             transaction_log_collector_config: new(),
             transaction_log_collector: new(),
             feature_set: new(),
             drop_callback: RwLock::new(OptionalDropCallback(None)),
             freeze_started: AtomicBool::new(fields.hash != Hash::default()),
+            execute_timings: new(),
+            block_units: new(),
+            pending_block_units: new(),
         };
         bank.finish_init(genesis_config, additional_builtins);
+        if bank.freeze_started() {
+            bank.rc.accounts.load_cache.close();
+        }
 
         // Sanity assertions between bank snapshot and genesis config
         // Consider removing from serializable bank state
@@ -1297,6 +1337,62 @@ impl Bank {
         self.freeze_started.load(Relaxed)
     }
 
+    /// Adds the timings of a batch of transactions processed in this Bank to its totals
+    pub fn accumulate_execute_timings(&self, timings: &ExecuteTimings) {
+        self.execute_timings.write().unwrap().accumulate(timings);
+    }
+
+    /// Transaction execution timings accumulated over all the batches processed so far
+    pub fn execute_timings(&self) -> ExecuteTimings {
+        self.execute_timings.read().unwrap().clone()
+    }
+
+    /// Cost of `tx` in compute units, see `block_cost`
+    pub fn transaction_cost(&self, tx: &Transaction) -> u64 {
+        let bpf_compute_budget = self
+            .bpf_compute_budget
+            .unwrap_or_else(|| BpfComputeBudget::new(&self.feature_set));
+        block_cost::transaction_cost(
+            tx.message(),
+            |program_id| self.message_processor.is_builtin(program_id),
+            bpf_compute_budget.max_units,
+        )
+    }
+
+    /// Cost of the transactions committed so far, in compute units
+    pub fn block_units(&self) -> u64 {
+        self.block_units.load(Relaxed)
+    }
+
+    /// The most the transactions of this Bank may cost once block cost limits are enabled
+    pub fn max_block_units(&self) -> u64 {
+        block_cost::MAX_BLOCK_UNITS
+    }
+
+    /// Reserves `units` for a transaction a leader is about to process, if they fit in the max
+    /// block units along with the transactions committed and those still being processed
+    pub fn try_reserve_block_units(&self, units: u64) -> bool {
+        self.pending_block_units
+            .fetch_update(AcqRel, Acquire, |pending_block_units| {
+                let block_units = self
+                    .block_units()
+                    .saturating_add(pending_block_units)
+                    .saturating_add(units);
+                if block_units <= self.max_block_units() {
+                    Some(pending_block_units + units)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+
+    /// Gives back units reserved by `try_reserve_block_units`, once the transactions that were
+    /// committed count in `block_units`
+    pub fn release_block_units(&self, units: u64) {
+        self.pending_block_units.fetch_sub(units, Release);
+    }
+
     pub fn status_cache_ancestors(&self) -> Vec<u64> {
         let mut roots = self.src.status_cache.read().unwrap().roots().clone();
         let min = roots.iter().min().cloned().unwrap_or(0);
@@ -1474,6 +1570,7 @@ impl Bank {
                 self.inherit_specially_retained_account_balance(account),
             )
         });
+        *self.slot_hashes_for_programs.write().unwrap() = None;
     }
 
     pub fn get_slot_history(&self) -> SlotHistory {
@@ -2042,6 +2139,11 @@ impl Bank {
             self.freeze_started.store(true, Relaxed);
             *hash = self.hash_internal_state();
             self.rc.accounts.accounts_db.mark_slot_frozen(self.slot());
+
+            // A frozen bank loads no more accounts for the transactions of its block, those of
+            // simulations alone are not worth caching for as long as the bank lives
+            self.rc.accounts.load_cache.report_metrics(self.slot());
+            self.rc.accounts.load_cache.close();
         }
     }
 
@@ -2296,17 +2398,40 @@ impl Bank {
         blockhash_queue.get_fee_calculator(hash).cloned()
     }
 
+    /// The fee this bank charges for `message`, None if its recent blockhash isn't in the
+    /// blockhash queue
+    pub fn get_fee_for_message(&self, message: &Message) -> Option<u64> {
+        let fee_config = FeeConfig {
+            secp256k1_program_enabled: self.secp256k1_program_enabled(),
+        };
+        self.get_fee_calculator(&message.recent_blockhash)
+            .map(|fee_calculator| fee_calculator.calculate_fee_with_config(message, &fee_config))
+    }
+
     pub fn get_fee_rate_governor(&self) -> &FeeRateGovernor {
         &self.fee_rate_governor
     }
 
+    /// The maximum age, in slots, of a blockhash that transactions processed by this bank may
+    /// reference
+    pub fn max_processing_age(&self) -> usize {
+        if self
+            .feature_set
+            .is_active(&feature_set::extended_max_processing_age::id())
+        {
+            EXTENDED_MAX_PROCESSING_AGE
+        } else {
+            MAX_PROCESSING_AGE
+        }
+    }
+
     pub fn get_blockhash_last_valid_slot(&self, blockhash: &Hash) -> Option<Slot> {
         let blockhash_queue = self.blockhash_queue.read().unwrap();
-        // This calculation will need to be updated to consider epoch boundaries if BlockhashQueue
-        // length is made variable by epoch
+        // This calculation will need to be updated to consider epoch boundaries if the max
+        // processing age changes mid-epoch
         blockhash_queue
             .get_hash_age(blockhash)
-            .map(|age| self.slot + blockhash_queue.len() as u64 - age)
+            .map(|age| (self.slot + self.max_processing_age() as u64).saturating_sub(age))
     }
 
     pub fn confirmed_last_blockhash(&self) -> (Hash, FeeCalculator) {
@@ -2431,11 +2556,16 @@ impl Bank {
         batch
     }
 
-    /// Run transactions against a frozen bank without committing the results
+    /// Run a transaction against a frozen bank without committing the results, returning the
+    /// outcome along with the log messages and inner instructions it recorded
     pub fn simulate_transaction(
         &self,
         transaction: Transaction,
-    ) -> (Result<()>, TransactionLogMessages) {
+    ) -> (
+        Result<()>,
+        TransactionLogMessages,
+        Option<InnerInstructionsList>,
+    ) {
         assert!(self.is_frozen(), "simulation bank must be frozen");
 
         let txs = &[transaction];
@@ -2444,7 +2574,7 @@ impl Bank {
         let (
             _loaded_accounts,
             executed,
-            _inner_instructions,
+            inner_instructions,
             log_messages,
             _retryable_transactions,
             _transaction_count,
@@ -2454,8 +2584,8 @@ impl Bank {
             // After simulation, transactions will need to be forwarded to the leader
             // for processing. During forwarding, the transaction could expire if the
             // delay is not accounted for.
-            MAX_PROCESSING_AGE - MAX_TRANSACTION_FORWARDING_DELAY,
-            false,
+            self.max_processing_age() - MAX_TRANSACTION_FORWARDING_DELAY,
+            true,
             true,
             &mut ExecuteTimings::default(),
         );
@@ -2464,8 +2594,9 @@ impl Bank {
         let log_messages = log_messages
             .get(0)
             .map_or(vec![], |messages| messages.to_vec());
+        let inner_instructions = inner_instructions.into_iter().next().flatten();
 
-        (transaction_result, log_messages)
+        (transaction_result, log_messages, inner_instructions)
     }
 
     pub fn unlock_accounts(&self, batch: &mut TransactionBatch) {
@@ -2819,22 +2950,87 @@ impl Bank {
 
         Rc::new(RefCell::new(Executors {
             executors,
+            updated: HashSet::new(),
             is_dirty: false,
         }))
     }
 
-    /// Add executors back to the bank's cache if modified
-    fn update_executors(&self, executors: Rc<RefCell<Executors>>) {
+    /// Add executors back to the bank's cache if modified.  Executors of programs deployed or
+    /// upgraded by the transaction are only added when `allow_updates` is set, so that the
+    /// verified executors of failed transactions can still be reused
+    fn update_executors(&self, allow_updates: bool, executors: Rc<RefCell<Executors>>) {
         let executors = executors.borrow();
         if executors.is_dirty {
             let mut cow_cache = self.cached_executors.write().unwrap();
             let mut cache = cow_cache.write().unwrap();
             for (key, executor) in executors.executors.iter() {
-                cache.put(key, (*executor).clone());
+                if allow_updates || !executors.updated.contains(key) {
+                    cache.put(key, (*executor).clone());
+                }
             }
         }
     }
 
+    /// Drop all the executors of the bank's cache, they were verified and compiled against
+    /// the previous feature set
+    fn clear_executors(&self) {
+        let mut cow_cache = self.cached_executors.write().unwrap();
+        let max = cow_cache.read().unwrap().max;
+        *cow_cache = CowCachedExecutors {
+            shared: false,
+            executors: Arc::new(RwLock::new(CachedExecutors::new(max))),
+        };
+    }
+
+    /// Entries of the SlotHashes sysvar exposed to programs through the recent slot hash
+    /// syscall. The sysvar is only updated when the bank is created, so every transaction of the
+    /// bank sees the same entries and they are only deserialized once
+    fn slot_hashes_for_programs(&self) -> Arc<SlotHashes> {
+        if !self
+            .feature_set
+            .is_active(&feature_set::slot_hashes_syscall_enabled::id())
+        {
+            return Arc::default();
+        }
+        if let Some(slot_hashes) = self.slot_hashes_for_programs.read().unwrap().as_ref() {
+            return slot_hashes.clone();
+        }
+        let slot_hashes = Arc::new(
+            self.get_account(&sysvar::slot_hashes::id())
+                .and_then(|account| from_account::<SlotHashes>(&account))
+                .unwrap_or_default(),
+        );
+        *self.slot_hashes_for_programs.write().unwrap() = Some(slot_hashes.clone());
+        slot_hashes
+    }
+
+    /// Account data of the sysvars programs read through the sysvar syscalls rather than being
+    /// passed the sysvar accounts
+    fn sysvars_for_programs(&self) -> Vec<(Pubkey, Vec<u8>)> {
+        if !self
+            .feature_set
+            .is_active(&feature_set::sysvar_via_syscall::id())
+        {
+            return vec![];
+        }
+        [
+            sysvar::clock::id(),
+            sysvar::epoch_schedule::id(),
+            sysvar::rent::id(),
+        ]
+        .iter()
+        .filter_map(|id| Some((*id, self.get_account(id)?.data)))
+        .collect()
+    }
+
+    /// Cap the executors cached by the bank and the banks created from it, dropping the least
+    /// used ones past `max`
+    pub fn set_max_cached_executors(&self, max: usize) {
+        let mut cow_cache = self.cached_executors.write().unwrap();
+        let mut cache = cow_cache.write().unwrap();
+        cache.set_max(max);
+    }
+
     /// Remove an executor from the bank's cache
     pub fn remove_executor(&self, pubkey: &Pubkey) {
         let mut cow_cache = self.cached_executors.write().unwrap();
@@ -2897,6 +3093,9 @@ impl Bank {
         );
         load_time.stop();
 
+        let slot_hashes = self.slot_hashes_for_programs();
+        let sysvars = self.sysvars_for_programs();
+
         let mut execution_time = Measure::start("execution_time");
         let mut signature_count: u64 = 0;
         let mut inner_instructions: Vec<Option<InnerInstructionsList>> =
@@ -2949,6 +3148,9 @@ impl Bank {
                         instruction_recorders.as_deref(),
                         self.feature_set.clone(),
                         bpf_compute_budget,
+                        &mut timings.details,
+                        &slot_hashes,
+                        &sysvars,
                     );
 
                     if enable_log_recording {
@@ -2973,9 +3175,7 @@ impl Bank {
                         loader_refcells,
                     );
 
-                    if process_result.is_ok() {
-                        self.update_executors(executors);
-                    }
+                    self.update_executors(process_result.is_ok(), executors);
 
                     let nonce_rollback =
                         if let Err(TransactionError::InstructionError(_, _)) = &process_result {
@@ -3165,6 +3365,12 @@ impl Bank {
 
         self.increment_transaction_count(tx_count);
         self.increment_signature_count(signature_count);
+        let block_units: u64 = OrderedIterator::new(txs, iteration_order)
+            .zip(executed)
+            .filter(|(_, (result, _nonce_rollback))| Self::can_commit(result))
+            .map(|((_, tx), _)| self.transaction_cost(tx))
+            .sum();
+        self.block_units.fetch_add(block_units, Relaxed);
 
         inc_new_counter_info!("bank-process_transactions-txs", tx_count as usize);
         inc_new_counter_info!("bank-process_transactions-sigs", signature_count as usize);
@@ -3835,7 +4041,7 @@ impl Bank {
         let batch = self.prepare_batch(txs, None);
         self.load_execute_and_commit_transactions(
             &batch,
-            MAX_PROCESSING_AGE,
+            self.max_processing_age(),
             false,
             false,
             false,
@@ -4592,6 +4798,10 @@ impl Bank {
         self.rc.accounts.accounts_db.print_accounts_stats("");
     }
 
+    pub fn verify_accounts_storages(&self) -> StorageVerificationReport {
+        self.rc.accounts.accounts_db.verify_storages()
+    }
+
     pub fn process_stale_slot_with_budget(
         &self,
         mut consumed_budget: usize,
@@ -4619,6 +4829,11 @@ impl Bank {
             .is_active(&feature_set::secp256k1_program_enabled::id())
     }
 
+    pub fn block_cost_limits_enabled(&self) -> bool {
+        self.feature_set
+            .is_active(&feature_set::block_cost_limits::id())
+    }
+
     pub fn no_overflow_rent_distribution_enabled(&self) -> bool {
         self.feature_set
             .is_active(&feature_set::no_overflow_rent_distribution::id())
@@ -4705,6 +4920,10 @@ impl Bank {
             self.adjust_capitalization_for_existing_specially_retained_accounts();
         }
 
+        if !new_feature_activations.is_empty() {
+            self.clear_executors();
+        }
+
         self.ensure_feature_builtins(init_finish_or_warp, &new_feature_activations);
         self.reconfigure_token2_native_mint();
         self.ensure_no_storage_rewards_pool();
@@ -4975,7 +5194,7 @@ pub(crate) mod tests {
         message::{Message, MessageHeader},
         nonce,
         poh_config::PohConfig,
-        process_instruction::InvokeContext,
+        process_instruction::{get_sysvar, InvokeContext},
         rent::Rent,
         signature::{keypair_from_seed, Keypair, Signer},
         system_instruction::{self, SystemError},
@@ -7288,6 +7507,48 @@ pub(crate) mod tests {
         assert_eq!(bank.get_balance(&pubkey), 500);
     }
 
+    #[test]
+    fn test_block_units() {
+        let (genesis_config, mint_keypair) = create_genesis_config(10_000);
+        let bank = Arc::new(Bank::new(&genesis_config));
+        assert_eq!(bank.max_block_units(), block_cost::MAX_BLOCK_UNITS);
+
+        let pubkey = solana_sdk::pubkey::new_rand();
+        let tx = system_transaction::transfer(&mint_keypair, &pubkey, 500, bank.last_blockhash());
+        let cost = bank.transaction_cost(&tx);
+        assert_eq!(
+            cost,
+            block_cost::SIGNATURE_UNITS
+                + 2 * block_cost::WRITE_LOCK_UNITS
+                + 1
+                + block_cost::BUILTIN_INSTRUCTION_UNITS
+        );
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+        // Transactions that are not committed cost nothing
+        assert!(bank.process_transaction(&tx).is_err());
+        assert_eq!(bank.block_units(), cost);
+
+        // Children start from nothing
+        let child = Bank::new_from_parent(&bank, &Pubkey::default(), 1);
+        assert_eq!(child.block_units(), 0);
+    }
+
+    #[test]
+    fn test_try_reserve_block_units() {
+        let (genesis_config, _mint_keypair) = create_genesis_config(10_000);
+        let bank = Bank::new(&genesis_config);
+        let max_block_units = bank.max_block_units();
+        assert!(bank.try_reserve_block_units(max_block_units - 1));
+        assert!(!bank.try_reserve_block_units(2));
+        assert!(bank.try_reserve_block_units(1));
+        assert!(!bank.try_reserve_block_units(1));
+
+        // Reservations don't count once given back
+        bank.release_block_units(max_block_units);
+        assert!(bank.try_reserve_block_units(max_block_units));
+        assert!(!bank.try_reserve_block_units(u64::MAX));
+    }
+
     #[test]
     fn test_transfer_to_sysvar() {
         solana_logger::setup();
@@ -7389,6 +7650,36 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_bank_get_fee_for_message() {
+        let (mut genesis_config, mint_keypair) = create_genesis_config(10_000);
+        genesis_config.fee_rate_governor = FeeRateGovernor::new(5, 0);
+        let bank = Bank::new(&genesis_config);
+        let lamports_per_signature = genesis_config
+            .fee_rate_governor
+            .create_fee_calculator()
+            .lamports_per_signature;
+
+        let tx = system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            1,
+            bank.last_blockhash(),
+        );
+        assert_eq!(
+            bank.get_fee_for_message(&tx.message),
+            Some(lamports_per_signature)
+        );
+
+        let tx = system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            1,
+            Hash::default(),
+        );
+        assert_eq!(bank.get_fee_for_message(&tx.message), None);
+    }
+
     #[test]
     fn test_bank_tx_fee() {
         solana_logger::setup();
@@ -9077,6 +9368,35 @@ pub(crate) mod tests {
         assert_eq!(sysvar_recent_blockhash, bank_last_blockhash);
     }
 
+    #[test]
+    fn test_extended_max_processing_age() {
+        let (genesis_config, mint_keypair) = create_genesis_config(100_000);
+        let mut bank = Arc::new(Bank::new(&genesis_config));
+        let blockhash = bank.last_blockhash();
+        for _ in 0..MAX_PROCESSING_AGE + 1 {
+            goto_end_of_slot(Arc::get_mut(&mut bank).unwrap());
+            bank = Arc::new(new_from_parent(&bank));
+        }
+        let mut bank = new_from_parent(&bank);
+        let tx = system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            1,
+            blockhash,
+        );
+
+        bank.deactivate_feature(&feature_set::extended_max_processing_age::id());
+        assert_eq!(bank.max_processing_age(), MAX_PROCESSING_AGE);
+        assert_eq!(
+            bank.process_transaction(&tx),
+            Err(TransactionError::BlockhashNotFound)
+        );
+
+        bank.activate_feature(&feature_set::extended_max_processing_age::id());
+        assert_eq!(bank.max_processing_age(), EXTENDED_MAX_PROCESSING_AGE);
+        assert_eq!(bank.process_transaction(&tx), Ok(()));
+    }
+
     #[test]
     fn test_bank_inherit_last_vote_sync() {
         let (genesis_config, _) = create_genesis_config(500);
@@ -9767,6 +10087,63 @@ pub(crate) mod tests {
         assert_eq!(bank.get_balance(&to_pubkey), 20);
     }
 
+    #[test]
+    fn test_simulate_transaction_inner_instructions() {
+        let (genesis_config, mint_keypair) = create_genesis_config(500);
+        let mut bank = Bank::new(&genesis_config);
+
+        fn mock_process_instruction(
+            _program_id: &Pubkey,
+            keyed_accounts: &[KeyedAccount],
+            data: &[u8],
+            invoke_context: &mut dyn InvokeContext,
+        ) -> result::Result<(), InstructionError> {
+            // Record the instruction as if the program had invoked it
+            invoke_context.record_instruction(&system_instruction::transfer(
+                keyed_accounts[0].unsigned_key(),
+                keyed_accounts[1].unsigned_key(),
+                data[0] as u64,
+            ));
+            Ok(())
+        }
+
+        let mock_program_id = Pubkey::new(&[2u8; 32]);
+        bank.add_builtin("mock_program", mock_program_id, mock_process_instruction);
+
+        let mint_pubkey = mint_keypair.pubkey();
+        let to_pubkey = solana_sdk::pubkey::new_rand();
+        let instructions = vec![
+            system_instruction::transfer(&mint_pubkey, &to_pubkey, 1),
+            Instruction::new(
+                mock_program_id,
+                &10u8,
+                vec![
+                    AccountMeta::new(mint_pubkey, true),
+                    AccountMeta::new(to_pubkey, false),
+                ],
+            ),
+        ];
+        let tx = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&mint_pubkey),
+            &[&mint_keypair],
+            bank.last_blockhash(),
+        );
+        bank.freeze();
+
+        let (result, _logs, inner_instructions) = bank.simulate_transaction(tx.clone());
+        assert_eq!(result, Ok(()));
+        let invoked_instruction = system_instruction::transfer(&mint_pubkey, &to_pubkey, 10);
+        assert_eq!(
+            inner_instructions,
+            Some(vec![
+                vec![],
+                vec![tx.message.compile_instruction(&invoked_instruction)]
+            ])
+        );
+        assert_eq!(bank.get_balance(&to_pubkey), 0);
+    }
+
     #[test]
     fn test_transaction_with_program_ids_passed_to_programs() {
         let (genesis_config, mint_keypair) = create_genesis_config(500);
@@ -10888,6 +11265,36 @@ pub(crate) mod tests {
         assert!(cache.get(&key4).is_some());
     }
 
+    #[test]
+    fn test_bank_set_max_cached_executors() {
+        let (genesis_config, _) = create_genesis_config(1);
+        let bank = Arc::new(Bank::new(&genesis_config));
+        let executor: Arc<dyn Executor> = Arc::new(TestExecutor {});
+        let keys: Vec<_> = (0..4).map(|_| solana_sdk::pubkey::new_rand()).collect();
+        {
+            let mut cow_cache = bank.cached_executors.write().unwrap();
+            let mut cache = cow_cache.write().unwrap();
+            for key in &keys {
+                cache.put(key, executor.clone());
+            }
+            // Used executors are kept over unused ones
+            cache.get(&keys[3]);
+        }
+        assert_eq!(Arc::strong_count(&executor), 5);
+
+        bank.set_max_cached_executors(1);
+        // The trimmed executors are released
+        assert_eq!(Arc::strong_count(&executor), 2);
+        let cow_cache = bank.cached_executors.read().unwrap();
+        assert!(cow_cache.read().unwrap().get(&keys[3]).is_some());
+        drop(cow_cache);
+
+        // Banks created from the bank keep the cap
+        let child = Bank::new_from_parent(&bank, &Pubkey::default(), 1);
+        let cow_cache = child.cached_executors.read().unwrap();
+        assert_eq!(cow_cache.read().unwrap().max, 1);
+    }
+
     #[test]
     fn test_bank_executor_cache() {
         solana_logger::setup();
@@ -10925,7 +11332,7 @@ pub(crate) mod tests {
         executors.insert(key4, executor.clone());
         let executors = Rc::new(RefCell::new(executors));
         executors.borrow_mut().is_dirty = false;
-        bank.update_executors(executors);
+        bank.update_executors(true, executors);
         let executors = bank.get_executors(&message, loaders);
         assert_eq!(executors.borrow().executors.len(), 0);
 
@@ -10936,7 +11343,7 @@ pub(crate) mod tests {
         executors.insert(key3, executor.clone());
         executors.insert(key4, executor.clone());
         let executors = Rc::new(RefCell::new(executors));
-        bank.update_executors(executors);
+        bank.update_executors(true, executors);
         let executors = bank.get_executors(&message, loaders);
         assert_eq!(executors.borrow().executors.len(), 4);
         assert!(executors.borrow().executors.contains_key(&key1));
@@ -10982,7 +11389,7 @@ pub(crate) mod tests {
         let mut executors = Executors::default();
         executors.insert(key1, executor.clone());
         let executors = Rc::new(RefCell::new(executors));
-        root.update_executors(executors);
+        root.update_executors(true, executors);
         let executors = root.get_executors(&Message::default(), loaders);
         assert_eq!(executors.borrow().executors.len(), 1);
 
@@ -10997,7 +11404,7 @@ pub(crate) mod tests {
         let mut executors = Executors::default();
         executors.insert(key2, executor.clone());
         let executors = Rc::new(RefCell::new(executors));
-        fork1.update_executors(executors);
+        fork1.update_executors(true, executors);
 
         let executors = fork1.get_executors(&Message::default(), loaders);
         assert_eq!(executors.borrow().executors.len(), 2);
@@ -11012,6 +11419,178 @@ pub(crate) mod tests {
         assert_eq!(executors.borrow().executors.len(), 1);
     }
 
+    #[test]
+    fn test_bank_executor_updates() {
+        solana_logger::setup();
+
+        let (genesis_config, _) = create_genesis_config(1);
+        let mut bank = Bank::new(&genesis_config);
+
+        let key1 = solana_sdk::pubkey::new_rand();
+        let key2 = solana_sdk::pubkey::new_rand();
+        let executor: Arc<dyn Executor> = Arc::new(TestExecutor {});
+
+        let loaders = &[vec![(key1, Account::default()), (key2, Account::default())]];
+
+        // a failed transaction only caches the executors of unmodified programs
+        let mut executors = Executors::default();
+        executors.insert(key1, executor.clone());
+        executors.update(key2, executor.clone());
+        let executors = Rc::new(RefCell::new(executors));
+        bank.update_executors(false, executors);
+        let executors = bank.get_executors(&Message::default(), loaders);
+        assert_eq!(executors.borrow().executors.len(), 1);
+        assert!(executors.borrow().executors.contains_key(&key1));
+
+        // a successful one caches upgrades too
+        let mut executors = Executors::default();
+        executors.update(key2, executor.clone());
+        let executors = Rc::new(RefCell::new(executors));
+        bank.update_executors(true, executors);
+        let executors = bank.get_executors(&Message::default(), loaders);
+        assert_eq!(executors.borrow().executors.len(), 2);
+
+        // activating a feature drops the cached executors
+        let test_feature = "TestFeature11111111111111111111111111111111"
+            .parse::<Pubkey>()
+            .unwrap();
+        let mut feature_set = FeatureSet::default();
+        feature_set.inactive.insert(test_feature);
+        bank.feature_set = Arc::new(feature_set);
+        bank.store_account(
+            &test_feature,
+            &feature::create_account(&Feature::default(), 42),
+        );
+        bank.apply_feature_activations(false);
+        assert!(bank.feature_set.is_active(&test_feature));
+        let executors = bank.get_executors(&Message::default(), loaders);
+        assert_eq!(executors.borrow().executors.len(), 0);
+    }
+
+    #[test]
+    fn test_bank_slot_hashes_for_programs() {
+        let (genesis_config, mint_keypair) = create_genesis_config(500);
+        let mut bank0 = Bank::new(&genesis_config);
+
+        fn mock_process_instruction(
+            _program_id: &Pubkey,
+            keyed_accounts: &[KeyedAccount],
+            _data: &[u8],
+            invoke_context: &mut dyn InvokeContext,
+        ) -> result::Result<(), InstructionError> {
+            let (slot, hash) = invoke_context
+                .get_slot_hashes()
+                .first()
+                .cloned()
+                .unwrap_or_default();
+            let mut account = keyed_accounts[0].try_account_ref_mut()?;
+            account.data[..8].copy_from_slice(&slot.to_le_bytes());
+            account.data[8..].copy_from_slice(hash.as_ref());
+            Ok(())
+        }
+
+        let mock_program_id = Pubkey::new(&[2u8; 32]);
+        bank0.add_builtin("mock_program", mock_program_id, mock_process_instruction);
+        let pubkey = solana_sdk::pubkey::new_rand();
+        bank0.store_account(&pubkey, &Account::new(1, 40, &mock_program_id));
+        let run = |bank: &Bank| {
+            let instruction =
+                Instruction::new(mock_program_id, &(), vec![AccountMeta::new(pubkey, false)]);
+            let tx = Transaction::new_signed_with_payer(
+                &[instruction],
+                Some(&mint_keypair.pubkey()),
+                &[&mint_keypair],
+                bank.last_blockhash(),
+            );
+            assert_eq!(bank.process_transaction(&tx), Ok(()));
+            bank.get_account(&pubkey).unwrap().data
+        };
+
+        // without the feature programs see no slot hashes
+        let bank0 = Arc::new(bank0);
+        let bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        assert_eq!(run(&bank1), vec![0; 40]);
+
+        // with it, every bank descending from the same parent sees the same most recent entry
+        let mut bank2 = Bank::new_from_parent(&bank0, &Pubkey::default(), 2);
+        bank2.activate_feature(&feature_set::slot_hashes_syscall_enabled::id());
+        let mut bank3 = Bank::new_from_parent(&bank0, &solana_sdk::pubkey::new_rand(), 3);
+        bank3.activate_feature(&feature_set::slot_hashes_syscall_enabled::id());
+        let mut expected = 0u64.to_le_bytes().to_vec();
+        expected.extend_from_slice(bank0.hash().as_ref());
+        assert_eq!(run(&bank2), expected);
+        assert_eq!(run(&bank3), expected);
+
+        // the entries are deserialized once per bank and shared by its batches
+        assert!(Arc::ptr_eq(
+            &bank2.slot_hashes_for_programs(),
+            &bank2.slot_hashes_for_programs()
+        ));
+        assert_eq!(
+            bank2.slot_hashes_for_programs().first(),
+            Some(&(0, bank0.hash()))
+        );
+    }
+
+    #[test]
+    fn test_bank_sysvars_for_programs() {
+        let (genesis_config, mint_keypair) = create_genesis_config(500);
+        let mut bank0 = Bank::new(&genesis_config);
+
+        fn mock_process_instruction(
+            _program_id: &Pubkey,
+            keyed_accounts: &[KeyedAccount],
+            _data: &[u8],
+            invoke_context: &mut dyn InvokeContext,
+        ) -> result::Result<(), InstructionError> {
+            let clock: sysvar::clock::Clock = get_sysvar(invoke_context, &sysvar::clock::id())?;
+            let rent: Rent = get_sysvar(invoke_context, &sysvar::rent::id())?;
+            let epoch_schedule: EpochSchedule =
+                get_sysvar(invoke_context, &sysvar::epoch_schedule::id())?;
+            let mut account = keyed_accounts[0].try_account_ref_mut()?;
+            account.data[..8].copy_from_slice(&clock.slot.to_le_bytes());
+            account.data[8..16].copy_from_slice(&rent.lamports_per_byte_year.to_le_bytes());
+            account.data[16..].copy_from_slice(&epoch_schedule.slots_per_epoch.to_le_bytes());
+            Ok(())
+        }
+
+        let mock_program_id = Pubkey::new(&[2u8; 32]);
+        bank0.add_builtin("mock_program", mock_program_id, mock_process_instruction);
+        let pubkey = solana_sdk::pubkey::new_rand();
+        bank0.store_account(&pubkey, &Account::new(1, 24, &mock_program_id));
+        let run = |bank: &Bank| {
+            let instruction =
+                Instruction::new(mock_program_id, &(), vec![AccountMeta::new(pubkey, false)]);
+            let tx = Transaction::new_signed_with_payer(
+                &[instruction],
+                Some(&mint_keypair.pubkey()),
+                &[&mint_keypair],
+                bank.last_blockhash(),
+            );
+            bank.process_transaction(&tx)
+                .map(|()| bank.get_account(&pubkey).unwrap().data)
+        };
+
+        // without the feature programs can't read sysvars they are not passed
+        let bank0 = Arc::new(bank0);
+        let bank1 = Bank::new_from_parent(&bank0, &Pubkey::default(), 1);
+        assert_eq!(
+            run(&bank1),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::UnsupportedSysvar
+            ))
+        );
+
+        let mut bank2 = Bank::new_from_parent(&bank0, &Pubkey::default(), 2);
+        bank2.activate_feature(&feature_set::sysvar_via_syscall::id());
+        let rent = &bank2.rent_collector.rent;
+        let mut expected = 2u64.to_le_bytes().to_vec();
+        expected.extend_from_slice(&rent.lamports_per_byte_year.to_le_bytes());
+        expected.extend_from_slice(&bank2.epoch_schedule.slots_per_epoch.to_le_bytes());
+        assert_eq!(run(&bank2), Ok(expected));
+    }
+
     #[test]
     fn test_compute_active_feature_set() {
         let (genesis_config, _mint_keypair) = create_genesis_config(100_000);
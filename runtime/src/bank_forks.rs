@@ -42,6 +42,23 @@ pub struct SnapshotConfig {
     pub snapshot_version: SnapshotVersion,
 }
 
+/// Limits on the banks BankForks retains ahead of the root, protecting a node from running out
+/// of memory replaying forks that will never be rooted, as during a long partition
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BankForksPruningConfig {
+    // Prune the forks whose newest bank is more than this many slots behind the best bank
+    pub max_fork_depth: Option<Slot>,
+
+    // Prune the forks whose newest banks are the oldest while more banks than this are retained
+    pub max_bank_count: Option<usize>,
+}
+
+impl BankForksPruningConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.max_fork_depth.is_some() || self.max_bank_count.is_some()
+    }
+}
+
 pub struct BankForks {
     pub banks: HashMap<Slot, Arc<Bank>>,
     root: Slot,
@@ -49,6 +66,11 @@ pub struct BankForks {
 
     pub accounts_hash_interval_slots: Slot,
     last_accounts_hash_slot: Slot,
+
+    pruning_config: BankForksPruningConfig,
+    // Slots pruned by `prune_dead_forks`, until their banks are created again or the root
+    // passes them
+    pruned_slots: HashSet<Slot>,
 }
 
 impl Index<u64> for BankForks {
@@ -145,6 +167,8 @@ impl BankForks {
             snapshot_config: None,
             accounts_hash_interval_slots: std::u64::MAX,
             last_accounts_hash_slot: root,
+            pruning_config: BankForksPruningConfig::default(),
+            pruned_slots: HashSet::new(),
         }
     }
 
@@ -152,6 +176,8 @@ impl BankForks {
         let bank = Arc::new(bank);
         let prev = self.banks.insert(bank.slot(), bank.clone());
         assert!(prev.is_none());
+        // A pruned slot is created again once the cluster turns out to build on its fork
+        self.pruned_slots.remove(&bank.slot());
         bank
     }
 
@@ -268,12 +294,105 @@ impl BankForks {
                     && *slot >= highest_confirmed_root.unwrap_or(root)
                     && descendants[slot].contains(&root))
         });
+        self.pruned_slots.retain(|slot| *slot > root);
         datapoint_debug!(
             "bank_forks_purge_non_root",
             ("num_banks_retained", self.banks.len(), i64),
         );
     }
 
+    /// Prunes the forks off the fork of `best_slot`, the slot fork choice would build on, that
+    /// exceed the limits of the pruning config, returning the pruned slots.  A fork is kept if
+    /// `keep` is true for its slots, as for forks voted on
+    pub fn prune_dead_forks<F: Fn(&[Slot]) -> bool>(
+        &mut self,
+        best_slot: Slot,
+        keep: F,
+    ) -> Vec<Slot> {
+        if !self.pruning_config.is_enabled() {
+            return vec![];
+        }
+        let root = self.root;
+        let best_fork = match self.banks.get(&best_slot) {
+            Some(best_bank) => &best_bank.ancestors,
+            None => return vec![],
+        };
+        let descendants = self.descendants();
+
+        // The forks branching off the fork of the best bank, by their newest slot.  The banks
+        // built on the best bank are still being replayed and never pruned
+        let mut forks: Vec<(Slot, Vec<Slot>)> = self
+            .banks
+            .values()
+            .filter(|bank| {
+                bank.slot() > root
+                    && !best_fork.contains_key(&bank.slot())
+                    && best_fork.contains_key(&bank.parent_slot())
+                    && bank.parent_slot() != best_slot
+            })
+            .map(|bank| {
+                let mut fork: Vec<Slot> = descendants[&bank.slot()].iter().copied().collect();
+                fork.push(bank.slot());
+                (*fork.iter().max().unwrap(), fork)
+            })
+            .filter(|(_, fork)| !keep(fork))
+            .collect();
+        forks.sort_unstable_by_key(|(newest_slot, _)| *newest_slot);
+
+        let mut num_banks = self.banks.len();
+        let mut pruned_slots = vec![];
+        for (newest_slot, fork) in forks {
+            let is_dead = self
+                .pruning_config
+                .max_fork_depth
+                .map_or(false, |max_fork_depth| {
+                    newest_slot + max_fork_depth < best_slot
+                });
+            let is_over_count = self
+                .pruning_config
+                .max_bank_count
+                .map_or(false, |max_bank_count| num_banks > max_bank_count);
+            if !is_dead && !is_over_count {
+                // The remaining forks are newer
+                break;
+            }
+            num_banks -= fork.len();
+            pruned_slots.extend(fork);
+        }
+        for slot in &pruned_slots {
+            self.banks.remove(slot);
+            self.pruned_slots.insert(*slot);
+        }
+
+        if let Some(max_bank_count) = self.pruning_config.max_bank_count {
+            if self.banks.len() > max_bank_count {
+                warn!(
+                    "{} banks retained, over the limit of {}",
+                    self.banks.len(),
+                    max_bank_count
+                );
+            }
+        }
+        datapoint_info!(
+            "bank_forks_prune_dead_forks",
+            ("num_banks", self.banks.len(), i64),
+            ("num_frozen_banks", self.frozen_banks().len(), i64),
+            ("num_pruned_banks", pruned_slots.len(), i64),
+            ("fork_depth", best_slot - root, i64),
+        );
+        pruned_slots
+    }
+
+    /// Whether `slot` was pruned by `prune_dead_forks`, and should not be replayed again unless
+    /// the cluster builds on its fork
+    pub fn is_pruned(&self, slot: Slot) -> bool {
+        self.pruned_slots.contains(&slot)
+    }
+
+    pub fn set_pruning_config(&mut self, pruning_config: BankForksPruningConfig) {
+        self.pruning_config = pruning_config;
+    }
+
     pub fn set_snapshot_config(&mut self, snapshot_config: Option<SnapshotConfig>) {
         self.snapshot_config = snapshot_config;
     }
@@ -389,6 +508,101 @@ mod tests {
         assert_eq!(bank_forks.active_banks(), vec![1]);
     }
 
+    #[test]
+    fn test_bank_forks_prune_dead_forks() {
+        let new_bank_forks = || {
+            let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+            let mut bank_forks = BankForks::new(Bank::new(&genesis_config));
+            // 0 - 1 - 3 - 5 - 10
+            //  \   \   \
+            //   2   6   8
+            //   |
+            //   4
+            for (parent, slot) in &[
+                (0, 1),
+                (1, 3),
+                (3, 5),
+                (5, 10),
+                (0, 2),
+                (2, 4),
+                (1, 6),
+                (3, 8),
+            ] {
+                let bank = Bank::new_from_parent(&bank_forks[*parent], &Pubkey::default(), *slot);
+                bank_forks.insert(bank);
+            }
+            bank_forks
+        };
+        let sorted = |mut slots: Vec<Slot>| {
+            slots.sort_unstable();
+            slots
+        };
+
+        let mut bank_forks = new_bank_forks();
+        assert!(bank_forks.prune_dead_forks(10, |_| false).is_empty());
+
+        // The fork of 4 is more than 5 slots behind 10
+        bank_forks.set_pruning_config(BankForksPruningConfig {
+            max_fork_depth: Some(5),
+            max_bank_count: None,
+        });
+        assert_eq!(
+            sorted(bank_forks.prune_dead_forks(10, |_| false)),
+            vec![2, 4]
+        );
+        assert!(bank_forks.is_pruned(2) && bank_forks.is_pruned(4));
+        assert!(bank_forks.get(4).is_none());
+        assert_eq!(bank_forks.banks.len(), 7);
+
+        // Then the oldest forks go while over the bank count
+        bank_forks.set_pruning_config(BankForksPruningConfig {
+            max_fork_depth: Some(5),
+            max_bank_count: Some(6),
+        });
+        assert_eq!(bank_forks.prune_dead_forks(10, |_| false), vec![6]);
+        assert_eq!(bank_forks.banks.len(), 6);
+
+        // Pruned slots are created again once the cluster builds on them
+        let bank = Bank::new_from_parent(&bank_forks[1], &Pubkey::default(), 6);
+        bank_forks.insert(bank);
+        assert!(!bank_forks.is_pruned(6));
+
+        // Unless kept, the forks off the best fork go, even that of the highest bank
+        let mut bank_forks = new_bank_forks();
+        bank_forks.set_pruning_config(BankForksPruningConfig {
+            max_fork_depth: None,
+            max_bank_count: Some(1),
+        });
+        assert_eq!(
+            sorted(bank_forks.prune_dead_forks(8, |fork| fork.contains(&6))),
+            vec![2, 4, 5, 10]
+        );
+        assert_eq!(
+            sorted(bank_forks.banks.keys().copied().collect()),
+            vec![0, 1, 3, 6, 8]
+        );
+
+        // The banks built on the best bank are still being replayed
+        let mut bank_forks = new_bank_forks();
+        bank_forks.set_pruning_config(BankForksPruningConfig {
+            max_fork_depth: None,
+            max_bank_count: Some(1),
+        });
+        assert_eq!(
+            sorted(bank_forks.prune_dead_forks(5, |_| false)),
+            vec![2, 4, 6, 8]
+        );
+        assert_eq!(
+            sorted(bank_forks.banks.keys().copied().collect()),
+            vec![0, 1, 3, 5, 10]
+        );
+
+        // Pruned slots below the root are forgotten
+        bank_forks.set_root(5, &ABSRequestSender::default(), None);
+        assert!(!bank_forks.is_pruned(2));
+        assert!(!bank_forks.is_pruned(4));
+    }
+
     #[test]
     fn test_bank_forks_different_set_root() {
         solana_logger::setup();
@@ -0,0 +1,88 @@
+//! The cost of transactions in compute units, estimated from their messages alone, and the limit
+//! on the cost of a block.  A transaction costs at most what replaying it takes: instructions of
+//! BPF programs are charged the whole compute budget they may use.  Blocks within the limit can
+//! then be replayed by every validator in time, whatever their transactions do
+use solana_sdk::{message::Message, pubkey::Pubkey};
+
+/// Units charged per signature to verify
+pub const SIGNATURE_UNITS: u64 = 720;
+/// Units charged per account locked for writing
+pub const WRITE_LOCK_UNITS: u64 = 300;
+/// Instruction data bytes charged a unit, rounded up
+pub const INSTRUCTION_DATA_BYTES_PER_UNIT: u64 = 64;
+/// Units charged per instruction of a builtin program
+pub const BUILTIN_INSTRUCTION_UNITS: u64 = 2_000;
+/// Limit on the cost of a block, the same for every validator of the cluster
+pub const MAX_BLOCK_UNITS: u64 = 2_000_000_000;
+
+/// Cost of the transaction of `message`, with `is_builtin` telling the builtin programs from the
+/// BPF programs, whose instructions cost `bpf_instruction_units`
+pub fn transaction_cost<F>(message: &Message, is_builtin: F, bpf_instruction_units: u64) -> u64
+where
+    F: Fn(&Pubkey) -> bool,
+{
+    let signature_units = u64::from(message.header.num_required_signatures) * SIGNATURE_UNITS;
+    let write_lock_units = (0..message.account_keys.len())
+        .filter(|i| message.is_writable(*i))
+        .count() as u64
+        * WRITE_LOCK_UNITS;
+    let instruction_data_bytes: u64 = message
+        .instructions
+        .iter()
+        .map(|instruction| instruction.data.len() as u64)
+        .sum();
+    let instruction_data_units = (instruction_data_bytes + INSTRUCTION_DATA_BYTES_PER_UNIT - 1)
+        / INSTRUCTION_DATA_BYTES_PER_UNIT;
+    let instruction_units: u64 = message
+        .instructions
+        .iter()
+        .map(|instruction| {
+            // Instructions of unknown programs fail to load, charge them all the same
+            match message
+                .account_keys
+                .get(instruction.program_id_index as usize)
+            {
+                Some(program_id) if is_builtin(program_id) => BUILTIN_INSTRUCTION_UNITS,
+                _ => bpf_instruction_units,
+            }
+        })
+        .sum();
+    signature_units + write_lock_units + instruction_data_units + instruction_units
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::{
+        instruction::{AccountMeta, Instruction},
+        system_instruction, system_program,
+    };
+
+    #[test]
+    fn test_transaction_cost() {
+        let from = Pubkey::new_unique();
+        let to = Pubkey::new_unique();
+        let is_system = |program_id: &Pubkey| system_program::check_id(program_id);
+
+        // One signature, two writable accounts and a 12 byte instruction of a builtin
+        let message = Message::new(&[system_instruction::transfer(&from, &to, 1)], Some(&from));
+        assert_eq!(
+            transaction_cost(&message, is_system, 200_000),
+            SIGNATURE_UNITS + 2 * WRITE_LOCK_UNITS + 1 + BUILTIN_INSTRUCTION_UNITS
+        );
+
+        // Instructions of BPF programs are charged their whole compute budget
+        let program_id = Pubkey::new_unique();
+        let message = Message::new(
+            &[
+                Instruction::new(program_id, &[0u8; 100], vec![AccountMeta::new(to, false)]),
+                Instruction::new(program_id, &(), vec![AccountMeta::new_readonly(to, false)]),
+            ],
+            Some(&from),
+        );
+        assert_eq!(
+            transaction_cost(&message, is_system, 200_000),
+            SIGNATURE_UNITS + 2 * WRITE_LOCK_UNITS + 2 + 2 * 200_000
+        );
+    }
+}
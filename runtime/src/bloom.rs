@@ -118,6 +118,18 @@ impl<T: BloomHashIndex> Bloom<T> {
         }
         true
     }
+    pub fn num_bits_set(&self) -> u64 {
+        self.num_bits_set
+    }
+    /// Probability that `contains` returns true for a key which was never added, given the
+    /// current fill of the filter
+    pub fn false_positive_rate(&self) -> f64 {
+        if self.bits.is_empty() {
+            return 1.0;
+        }
+        let fill = self.num_bits_set as f64 / self.bits.len() as f64;
+        fill.powi(self.keys.len() as i32)
+    }
 }
 
 fn slice_hash(slice: &[u8], hash_index: u64) -> u64 {
@@ -12,6 +12,7 @@ pub struct NodeVoteAccounts {
     pub total_stake: u64,
 }
 
+#[frozen_abi(digest = "75bMnDQJZrYicefQ4JwNG1b5LviZKTt7WwFWfXALdfaq")]
 #[derive(Clone, Debug, Serialize, Deserialize, AbiExample, PartialEq)]
 pub struct EpochStakes {
     stakes: Arc<Stakes>,
@@ -4,11 +4,14 @@ pub mod accounts_background_service;
 pub mod accounts_cache;
 pub mod accounts_db;
 pub mod accounts_index;
+pub mod accounts_load_cache;
+pub mod accounts_update_notifier;
 pub mod append_vec;
 pub mod bank;
 pub mod bank_client;
 pub mod bank_forks;
 pub mod bank_utils;
+pub mod block_cost;
 mod blockhash_queue;
 pub mod bloom;
 pub mod builtins;
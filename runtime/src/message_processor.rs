@@ -4,6 +4,7 @@ use crate::{
 };
 use log::*;
 use serde::{Deserialize, Serialize};
+use solana_measure::measure::Measure;
 use solana_sdk::{
     account::Account,
     account_utils::StateMut,
@@ -20,33 +21,71 @@ use solana_sdk::{
     },
     pubkey::Pubkey,
     rent::Rent,
+    slot_hashes::SlotHash,
     system_program,
     transaction::TransactionError,
 };
 use std::{
     cell::{Ref, RefCell},
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     rc::Rc,
     sync::Arc,
 };
 
+#[derive(AbiExample, Clone, Debug, Default, PartialEq)]
+pub struct ProgramTiming {
+    pub accumulated_us: u64,
+    pub count: u32,
+}
+
+/// Execution time of the instructions of a message, broken down by program id
+#[derive(AbiExample, Clone, Debug, Default, PartialEq)]
+pub struct ExecuteDetailsTimings {
+    pub per_program_timings: HashMap<Pubkey, ProgramTiming>,
+}
+
+impl ExecuteDetailsTimings {
+    pub fn accumulate(&mut self, other: &ExecuteDetailsTimings) {
+        for (program_id, other) in &other.per_program_timings {
+            let program_timing = self.per_program_timings.entry(*program_id).or_default();
+            program_timing.accumulated_us += other.accumulated_us;
+            program_timing.count += other.count;
+        }
+    }
+
+    pub fn accumulate_program(&mut self, program_id: &Pubkey, us: u64) {
+        let program_timing = self.per_program_timings.entry(*program_id).or_default();
+        program_timing.accumulated_us += us;
+        program_timing.count += 1;
+    }
+}
+
 pub struct Executors {
     pub executors: HashMap<Pubkey, Arc<dyn Executor>>,
+    /// Programs deployed or upgraded by the transaction
+    pub updated: HashSet<Pubkey>,
     pub is_dirty: bool,
 }
 impl Default for Executors {
     fn default() -> Self {
         Self {
             executors: HashMap::default(),
+            updated: HashSet::default(),
             is_dirty: false,
         }
     }
 }
 impl Executors {
+    /// Add the executor of a program loaded from its current account data
     pub fn insert(&mut self, key: Pubkey, executor: Arc<dyn Executor>) {
         let _ = self.executors.insert(key, executor);
         self.is_dirty = true;
     }
+    /// Replace the executor of a program deployed or upgraded by the transaction
+    pub fn update(&mut self, key: Pubkey, executor: Arc<dyn Executor>) {
+        self.insert(key, executor);
+        self.updated.insert(key);
+    }
     pub fn get(&self, key: &Pubkey) -> Option<Arc<dyn Executor>> {
         self.executors.get(key).cloned()
     }
@@ -222,6 +261,10 @@ pub struct ThisInvokeContext<'a> {
     executors: Rc<RefCell<Executors>>,
     instruction_recorder: Option<InstructionRecorder>,
     feature_set: Arc<FeatureSet>,
+    message: &'a Message,
+    instruction_index: usize,
+    slot_hashes: &'a [SlotHash],
+    sysvars: &'a [(Pubkey, Vec<u8>)],
 }
 impl<'a> ThisInvokeContext<'a> {
     #[allow(clippy::too_many_arguments)]
@@ -236,6 +279,10 @@ impl<'a> ThisInvokeContext<'a> {
         executors: Rc<RefCell<Executors>>,
         instruction_recorder: Option<InstructionRecorder>,
         feature_set: Arc<FeatureSet>,
+        message: &'a Message,
+        instruction_index: usize,
+        slot_hashes: &'a [SlotHash],
+        sysvars: &'a [(Pubkey, Vec<u8>)],
     ) -> Self {
         let mut program_ids = Vec::with_capacity(bpf_compute_budget.max_invoke_depth);
         program_ids.push(*program_id);
@@ -253,6 +300,10 @@ impl<'a> ThisInvokeContext<'a> {
             executors,
             instruction_recorder,
             feature_set,
+            message,
+            instruction_index,
+            slot_hashes,
+            sysvars,
         }
     }
 }
@@ -274,6 +325,21 @@ impl<'a> InvokeContext for ThisInvokeContext<'a> {
     fn invoke_depth(&self) -> usize {
         self.program_ids.len()
     }
+    fn get_instruction_index(&self) -> usize {
+        self.instruction_index
+    }
+    fn get_transaction_instruction(&self, index: usize) -> Option<Instruction> {
+        self.message.decompile_instruction(index)
+    }
+    fn get_slot_hashes(&self) -> &[SlotHash] {
+        self.slot_hashes
+    }
+    fn get_sysvar_data(&self, id: &Pubkey) -> Option<&[u8]> {
+        self.sysvars
+            .iter()
+            .find(|(key, _)| key == id)
+            .map(|(_, data)| data.as_slice())
+    }
     fn verify_and_update(
         &mut self,
         message: &Message,
@@ -317,6 +383,9 @@ impl<'a> InvokeContext for ThisInvokeContext<'a> {
     fn add_executor(&self, pubkey: &Pubkey, executor: Arc<dyn Executor>) {
         self.executors.borrow_mut().insert(*pubkey, executor);
     }
+    fn update_executor(&self, pubkey: &Pubkey, executor: Arc<dyn Executor>) {
+        self.executors.borrow_mut().update(*pubkey, executor);
+    }
     fn get_executor(&self, pubkey: &Pubkey) -> Option<Arc<dyn Executor>> {
         self.executors.borrow().get(&pubkey)
     }
@@ -444,6 +513,11 @@ impl MessageProcessor {
         }
     }
 
+    /// Whether `program_id` is one of the programs built into the runtime
+    pub fn is_builtin(&self, program_id: &Pubkey) -> bool {
+        self.programs.iter().any(|(key, _)| key == program_id)
+    }
+
     pub fn add_loader(
         &mut self,
         program_id: Pubkey,
@@ -934,6 +1008,8 @@ impl MessageProcessor {
         instruction_index: usize,
         feature_set: Arc<FeatureSet>,
         bpf_compute_budget: BpfComputeBudget,
+        slot_hashes: &[SlotHash],
+        sysvars: &[(Pubkey, Vec<u8>)],
     ) -> Result<(), InstructionError> {
         // Fixup the special instructions key if present
         // before the account pre-values are taken care of
@@ -963,6 +1039,10 @@ impl MessageProcessor {
             executors,
             instruction_recorder,
             feature_set,
+            message,
+            instruction_index,
+            slot_hashes,
+            sysvars,
         );
         let keyed_accounts =
             Self::create_keyed_accounts(message, instruction, executable_accounts, accounts);
@@ -999,12 +1079,16 @@ impl MessageProcessor {
         instruction_recorders: Option<&[InstructionRecorder]>,
         feature_set: Arc<FeatureSet>,
         bpf_compute_budget: BpfComputeBudget,
+        timings: &mut ExecuteDetailsTimings,
+        slot_hashes: &[SlotHash],
+        sysvars: &[(Pubkey, Vec<u8>)],
     ) -> Result<(), TransactionError> {
         for (instruction_index, instruction) in message.instructions.iter().enumerate() {
+            let mut time = Measure::start("execute_instruction");
             let instruction_recorder = instruction_recorders
                 .as_ref()
                 .map(|recorders| recorders[instruction_index].clone());
-            self.execute_instruction(
+            let result = self.execute_instruction(
                 message,
                 instruction,
                 &loaders[instruction_index],
@@ -1017,8 +1101,13 @@ impl MessageProcessor {
                 instruction_index,
                 feature_set.clone(),
                 bpf_compute_budget,
-            )
-            .map_err(|err| TransactionError::InstructionError(instruction_index as u8, err))?;
+                slot_hashes,
+                sysvars,
+            );
+            time.stop();
+            timings.accumulate_program(instruction.program_id(&message.account_keys), time.as_us());
+            result
+                .map_err(|err| TransactionError::InstructionError(instruction_index as u8, err))?;
         }
         Ok(())
     }
@@ -1055,6 +1144,15 @@ mod tests {
             pre_accounts.push(PreAccount::new(program_id, &account.clone(), false));
         }
 
+        let instructions = vec![
+            Instruction::new(program_ids[0], &0, vec![AccountMeta::new(keys[0], true)]),
+            Instruction::new(
+                program_ids[1],
+                &1,
+                vec![AccountMeta::new_readonly(keys[1], false)],
+            ),
+        ];
+        let message = Message::new(&instructions, Some(&keys[0]));
         let mut invoke_context = ThisInvokeContext::new(
             &program_ids[0],
             Rent::default(),
@@ -1066,6 +1164,23 @@ mod tests {
             Rc::new(RefCell::new(Executors::default())),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            &message,
+            1,
+            &[],
+            &[],
+        );
+
+        // Check the transaction instructions are visible to the program
+        assert_eq!(invoke_context.get_instruction_index(), 1);
+        for (index, instruction) in instructions.iter().enumerate() {
+            assert_eq!(
+                invoke_context.get_transaction_instruction(index).as_ref(),
+                Some(instruction)
+            );
+        }
+        assert_eq!(
+            invoke_context.get_transaction_instruction(instructions.len()),
+            None
         );
 
         // Check call depth increases and has a limit
@@ -1559,6 +1674,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_execute_details_timings_accumulate() {
+        let program_id = Pubkey::new_unique();
+        let mut timings = ExecuteDetailsTimings::default();
+        timings.accumulate_program(&program_id, 10);
+        timings.accumulate_program(&program_id, 20);
+
+        let mut other = ExecuteDetailsTimings::default();
+        other.accumulate_program(&program_id, 5);
+        other.accumulate_program(&Pubkey::new_unique(), 7);
+        timings.accumulate(&other);
+
+        assert_eq!(timings.per_program_timings.len(), 2);
+        assert_eq!(
+            timings.per_program_timings[&program_id],
+            ProgramTiming {
+                accumulated_us: 35,
+                count: 3,
+            }
+        );
+    }
+
     #[test]
     fn test_process_message_readonly_handling() {
         #[derive(Serialize, Deserialize)]
@@ -1625,6 +1762,7 @@ mod tests {
             Some(&from_pubkey),
         );
 
+        let mut timings = ExecuteDetailsTimings::default();
         let result = message_processor.process_message(
             &message,
             &loaders,
@@ -1636,10 +1774,18 @@ mod tests {
             None,
             Arc::new(FeatureSet::all_enabled()),
             BpfComputeBudget::new(&FeatureSet::all_enabled()),
+            &mut timings,
+            &[],
+            &[],
         );
         assert_eq!(result, Ok(()));
         assert_eq!(accounts[0].borrow().lamports, 100);
         assert_eq!(accounts[1].borrow().lamports, 0);
+        assert_eq!(timings.per_program_timings.len(), 1);
+        assert_eq!(
+            timings.per_program_timings[&mock_system_program_id].count,
+            1
+        );
 
         let message = Message::new(
             &[Instruction::new(
@@ -1661,6 +1807,9 @@ mod tests {
             None,
             Arc::new(FeatureSet::all_enabled()),
             BpfComputeBudget::new(&FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
+            &[],
+            &[],
         );
         assert_eq!(
             result,
@@ -1690,6 +1839,9 @@ mod tests {
             None,
             Arc::new(FeatureSet::all_enabled()),
             BpfComputeBudget::new(&FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
+            &[],
+            &[],
         );
         assert_eq!(
             result,
@@ -1803,6 +1955,9 @@ mod tests {
             None,
             Arc::new(FeatureSet::all_enabled()),
             BpfComputeBudget::new(&FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
+            &[],
+            &[],
         );
         assert_eq!(
             result,
@@ -1832,6 +1987,9 @@ mod tests {
             None,
             Arc::new(FeatureSet::all_enabled()),
             BpfComputeBudget::new(&FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
+            &[],
+            &[],
         );
         assert_eq!(result, Ok(()));
 
@@ -1858,6 +2016,9 @@ mod tests {
             None,
             Arc::new(FeatureSet::all_enabled()),
             BpfComputeBudget::new(&FeatureSet::all_enabled()),
+            &mut ExecuteDetailsTimings::default(),
+            &[],
+            &[],
         );
         assert_eq!(result, Ok(()));
         assert_eq!(accounts[0].borrow().lamports, 80);
@@ -1928,6 +2089,7 @@ mod tests {
         ];
         let programs: Vec<(_, ProcessInstructionWithContext)> =
             vec![(callee_program_id, mock_process_instruction)];
+        let message = Message::default();
         let mut invoke_context = ThisInvokeContext::new(
             &caller_program_id,
             Rent::default(),
@@ -1943,6 +2105,10 @@ mod tests {
             Rc::new(RefCell::new(Executors::default())),
             None,
             Arc::new(FeatureSet::all_enabled()),
+            &message,
+            0,
+            &[],
+            &[],
         );
         let metas = vec![
             AccountMeta::new(owned_key, false),
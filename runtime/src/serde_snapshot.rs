@@ -43,10 +43,12 @@ use {
 use solana_frozen_abi::abi_example::IgnoreAsHelper;
 
 mod common;
+mod extra_fields;
 mod future;
 mod tests;
 mod utils;
 
+use extra_fields::ExtraFields;
 use future::Context as TypeContextFuture;
 #[allow(unused_imports)]
 use utils::{serialize_iter_as_map, serialize_iter_as_seq, serialize_iter_as_tuple};
@@ -57,6 +59,9 @@ pub(crate) use self::tests::reconstruct_accounts_db_via_serialization;
 
 pub(crate) use crate::accounts_db::{SnapshotStorage, SnapshotStorages};
 
+/// The schema the bank and accounts db fields are serialized with, each implemented by a module
+/// of its own: NEWER by `future`.  Fields added within a schema go into the `ExtraFields`
+/// appended to it
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub(crate) enum SerdeStyle {
     NEWER,
@@ -138,6 +143,8 @@ where
     macro_rules! INTO {
         ($x:ident) => {{
             let (bank_fields, accounts_db_fields) = $x::deserialize_bank_fields(stream)?;
+            // No extra field is known of yet, the ones of newer releases are skipped
+            let _extra_fields = ExtraFields::deserialize_from(stream)?;
 
             let bank = reconstruct_bank_from_fields(
                 bank_fields,
@@ -175,13 +182,14 @@ where
     macro_rules! INTO {
         ($x:ident) => {
             bincode::serialize_into(
-                stream,
+                &mut *stream,
                 &SerializableBankAndStorage::<$x> {
                     bank,
                     snapshot_storages,
                     phantom: std::marker::PhantomData::default(),
                 },
             )
+            .and_then(|_| ExtraFields::default().serialize_into(stream))
         };
     }
     match serde_style {
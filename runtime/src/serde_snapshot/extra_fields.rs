@@ -0,0 +1,103 @@
+use {
+    super::*,
+    log::debug,
+    std::{collections::BTreeMap, io::BufRead},
+};
+
+// Tags of the extra fields this release knows of.  Tags are never reused, a field that is no
+// longer written keeps its tag reserved so that older snapshots don't get misread
+const KNOWN_EXTRA_FIELD_TAGS: &[u32] = &[];
+
+/// Fields appended to the bank snapshot after its format was frozen.  Each field is tagged and
+/// length prefixed, so that readers skip the fields introduced by newer releases and snapshots
+/// written by older releases load with the fields' defaults.  Nothing is appended while no field
+/// is set, keeping the snapshot loadable by releases predating the extra fields
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub(crate) struct ExtraFields(BTreeMap<u32, Vec<u8>>);
+
+impl ExtraFields {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn insert<T: Serialize>(&mut self, tag: u32, value: &T) -> Result<(), Error> {
+        self.0.insert(tag, bincode::serialize(value)?);
+        Ok(())
+    }
+
+    /// The field of the given tag, None if the snapshot predates it
+    #[allow(dead_code)]
+    pub(crate) fn get<T: DeserializeOwned>(&self, tag: u32) -> Result<Option<T>, Error> {
+        self.0
+            .get(&tag)
+            .map(|bytes| deserialize_from(&bytes[..]))
+            .transpose()
+    }
+
+    /// Tags of the fields written by a newer release, which this release ignores
+    pub(crate) fn unknown_tags(&self) -> impl Iterator<Item = u32> + '_ {
+        self.0
+            .keys()
+            .copied()
+            .filter(|tag| !KNOWN_EXTRA_FIELD_TAGS.contains(tag))
+    }
+
+    pub(crate) fn serialize_into<W: Write>(&self, stream: &mut W) -> Result<(), Error> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        bincode::serialize_into(stream, self)
+    }
+
+    /// Reads the fields following the accounts db fields, if any.  Snapshots written before
+    /// the extra fields were introduced, or without any field set, end right there
+    pub(crate) fn deserialize_from<R: BufRead>(stream: &mut R) -> Result<Self, Error> {
+        if stream.fill_buf()?.is_empty() {
+            return Ok(Self::default());
+        }
+        let extra_fields: Self = deserialize_from(stream)?;
+        for tag in extra_fields.unknown_tags() {
+            debug!("ignoring unknown snapshot extra field {}", tag);
+        }
+        Ok(extra_fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extra_fields_round_trip() {
+        let mut extra_fields = ExtraFields::default();
+        extra_fields.insert(1, &42u64).unwrap();
+        extra_fields.insert(7, &"future".to_string()).unwrap();
+
+        let mut buf = vec![];
+        extra_fields.serialize_into(&mut buf).unwrap();
+        let deserialized = ExtraFields::deserialize_from(&mut &buf[..]).unwrap();
+        assert_eq!(deserialized, extra_fields);
+        assert_eq!(deserialized.get::<u64>(1).unwrap(), Some(42));
+        assert_eq!(deserialized.get::<u64>(2).unwrap(), None);
+        assert_eq!(deserialized.unknown_tags().collect::<Vec<_>>(), vec![1, 7]);
+    }
+
+    #[test]
+    fn test_extra_fields_missing() {
+        // Nothing is written without fields, and nothing to read means no fields
+        let mut buf = vec![];
+        ExtraFields::default().serialize_into(&mut buf).unwrap();
+        assert!(buf.is_empty());
+        assert!(ExtraFields::deserialize_from(&mut &buf[..])
+            .unwrap()
+            .is_empty());
+
+        // A truncated trailer is still an error
+        let mut extra_fields = ExtraFields::default();
+        extra_fields.insert(1, &42u64).unwrap();
+        extra_fields.serialize_into(&mut buf).unwrap();
+        let truncated = &buf[..buf.len() - 1];
+        assert!(ExtraFields::deserialize_from(&mut &truncated[..]).is_err());
+    }
+}
@@ -15,7 +15,7 @@ use {
         pubkey::Pubkey,
         signature::{Keypair, Signer},
     },
-    std::io::{BufReader, Cursor},
+    std::io::{BufRead, BufReader, Cursor},
     tempfile::TempDir,
 };
 
@@ -162,7 +162,7 @@ fn test_accounts_serialize_style(serde_style: SerdeStyle) {
 }
 
 #[cfg(test)]
-fn test_bank_serialize_style(serde_style: SerdeStyle) {
+fn test_bank_serialize_style(serde_style: SerdeStyle, extra_fields: &ExtraFields) {
     solana_logger::setup();
     let (genesis_config, _) = create_genesis_config(500);
     let bank0 = Arc::new(Bank::new(&genesis_config));
@@ -197,6 +197,8 @@ fn test_bank_serialize_style(serde_style: SerdeStyle) {
         &snapshot_storages,
     )
     .unwrap();
+    // Simulate the extra fields appended by a newer release
+    extra_fields.serialize_into(&mut buf).unwrap();
 
     let rdr = Cursor::new(&buf[..]);
     let mut reader = std::io::BufReader::new(&buf[rdr.position() as usize..]);
@@ -221,6 +223,8 @@ fn test_bank_serialize_style(serde_style: SerdeStyle) {
         false,
     )
     .unwrap();
+    // The whole stream is consumed, extra fields included
+    assert!(reader.fill_buf().unwrap().is_empty());
     dbank.src = ref_sc;
     assert_eq!(dbank.get_balance(&key1.pubkey()), 0);
     assert_eq!(dbank.get_balance(&key2.pubkey()), 10);
@@ -259,7 +263,14 @@ fn test_accounts_serialize_newer() {
 
 #[test]
 fn test_bank_serialize_newer() {
-    test_bank_serialize_style(SerdeStyle::NEWER)
+    test_bank_serialize_style(SerdeStyle::NEWER, &ExtraFields::default())
+}
+
+#[test]
+fn test_bank_serialize_newer_with_unknown_extra_fields() {
+    let mut extra_fields = ExtraFields::default();
+    extra_fields.insert(u32::MAX, &[1u8; 32]).unwrap();
+    test_bank_serialize_style(SerdeStyle::NEWER, &extra_fields)
 }
 
 #[cfg(all(test, RUSTC_WITH_SPECIALIZATION))]
@@ -711,6 +711,18 @@ pub fn get_highest_snapshot_archive_path<P: AsRef<Path>>(
     archives.into_iter().next()
 }
 
+/// Returns the highest snapshot archive whose slot is not greater than `max_slot`, which allows
+/// the bank state of a past slot to be rebuilt from a retained archive
+pub fn get_highest_snapshot_archive_path_at_or_below<P: AsRef<Path>>(
+    snapshot_output_dir: P,
+    max_slot: Slot,
+) -> Option<(PathBuf, (Slot, Hash, ArchiveFormat))> {
+    let archives = get_snapshot_archives(snapshot_output_dir);
+    archives
+        .into_iter()
+        .find(|(_, (slot, _, _))| *slot <= max_slot)
+}
+
 pub fn purge_old_snapshot_archives<P: AsRef<Path>>(snapshot_output_dir: P) {
     let mut archives = get_snapshot_archives(snapshot_output_dir);
     // Keep the oldest snapshot so we can always play the ledger from it.
@@ -1081,4 +1093,26 @@ mod tests {
 
         assert!(snapshot_hash_of("invalid").is_none());
     }
+
+    #[test]
+    fn test_get_highest_snapshot_archive_path_at_or_below() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        for slot in &[100, 200, 300] {
+            File::create(get_snapshot_archive_path(
+                temp_dir.path(),
+                &(*slot, Hash::default()),
+                ArchiveFormat::TarBzip2,
+            ))
+            .unwrap();
+        }
+
+        let archive_slot = |max_slot| {
+            get_highest_snapshot_archive_path_at_or_below(temp_dir.path(), max_slot)
+                .map(|(_, (slot, _, _))| slot)
+        };
+        assert_eq!(archive_slot(99), None);
+        assert_eq!(archive_slot(100), Some(100));
+        assert_eq!(archive_slot(299), Some(200));
+        assert_eq!(archive_slot(Slot::MAX), Some(300));
+    }
 }
@@ -7,6 +7,7 @@ use solana_sdk::{
 use solana_stake_program::stake_state::{new_stake_history_entry, Delegation, StakeState};
 use std::{borrow::Borrow, collections::HashMap};
 
+#[frozen_abi(digest = "AQoDxRSevQk6E2AshnkHSNPu3mHop3a6rR9vpThY6e8q")]
 #[derive(Default, Clone, PartialEq, Debug, Deserialize, Serialize, AbiExample)]
 pub struct Stakes {
     /// vote accounts
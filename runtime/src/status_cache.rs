@@ -285,7 +285,7 @@ impl<T: Serialize + Clone> StatusCache<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use solana_sdk::hash::hash;
+    use solana_sdk::{clock::EXTENDED_MAX_PROCESSING_AGE, hash::hash};
 
     type BankStatusCache = StatusCache<()>;
 
@@ -459,6 +459,7 @@ mod tests {
     #[allow(clippy::assertions_on_constants)]
     fn test_age_sanity() {
         assert!(MAX_CACHE_ENTRIES <= MAX_RECENT_BLOCKHASHES);
+        assert!(EXTENDED_MAX_PROCESSING_AGE <= MAX_CACHE_ENTRIES);
     }
 
     #[test]
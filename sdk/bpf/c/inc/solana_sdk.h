@@ -113,6 +113,8 @@ static_assert(sizeof(uint64_t) == 8);
 #define MAX_SEED_LENGTH_EXCEEDED TO_BUILTIN(13)
 /** Provided seeds do not result in a valid address */
 #define INVALID_SEEDS TO_BUILTIN(14)
+/** Unsupported sysvar */
+#define UNSUPPORTED_SYSVAR TO_BUILTIN(15)
 
 /**
  * Boolean type
@@ -139,6 +141,17 @@ void sol_log_64_(uint64_t, uint64_t, uint64_t, uint64_t, uint64_t);
 void sol_log_compute_units_();
 #define sol_log_compute_units() sol_log_compute_units_()
 
+/**
+ * Height of the invocation stack, 1 while processing a transaction instruction
+ * and incremented by each cross-program invocation
+ */
+uint64_t sol_get_stack_height();
+
+/**
+ * Index of the transaction instruction being processed
+ */
+uint64_t sol_get_current_instruction_index();
+
 /**
  * Size of Public key in bytes
  */
@@ -437,6 +450,64 @@ static uint64_t sol_sha256(
     const uint8_t *result
 );
 
+/**
+ * Entry `index` of the SlotHashes sysvar, most recent first
+ *
+ * Slot hashes are deterministic but predictable by the transaction sender and
+ * influenced by slot leaders, they must not be used directly as randomness
+ *
+ * @param index Entry index
+ * @param slot Slot of the entry
+ * @param hash 32 byte array to hold the hash of the entry
+ * @return 0 on success, 1 if the sysvar has fewer entries
+ */
+uint64_t sol_get_recent_slot_hash(
+    uint64_t index,
+    uint64_t *slot,
+    uint8_t *hash
+);
+
+/**
+ * Clock sysvar
+ */
+typedef struct {
+  uint64_t slot; /** The current slot */
+  int64_t epoch_start_timestamp; /** Timestamp of the first slot of the epoch */
+  uint64_t epoch; /** The current epoch */
+  uint64_t leader_schedule_epoch; /** The future epoch of the most recent leader schedule */
+  int64_t unix_timestamp; /** Estimated Unix timestamp of the current slot */
+} SolClock;
+
+/**
+ * Rent sysvar
+ */
+typedef struct {
+  uint64_t lamports_per_byte_year; /** Rental rate */
+  double exemption_threshold; /** Years of rent an account must hold to be rent exempt */
+  uint8_t burn_percent; /** Percentage of collected rent that is burned */
+} SolRent;
+
+/**
+ * EpochSchedule sysvar
+ */
+typedef struct {
+  uint64_t slots_per_epoch; /** Maximum number of slots in an epoch */
+  uint64_t leader_schedule_slot_offset; /** Slots before an epoch its leader schedule is computed */
+  bool warmup; /** Whether epochs start short and grow */
+  uint64_t first_normal_epoch; /** First epoch of slots_per_epoch slots */
+  uint64_t first_normal_slot; /** First slot of first_normal_epoch */
+} SolEpochSchedule;
+
+/**
+ * Sysvars read without passing their accounts to the instruction
+ *
+ * @param var Sysvar to fill
+ * @return SUCCESS, or UNSUPPORTED_SYSVAR
+ */
+uint64_t sol_get_clock_sysvar(SolClock *var);
+uint64_t sol_get_rent_sysvar(SolRent *var);
+uint64_t sol_get_epoch_schedule_sysvar(SolEpochSchedule *var);
+
 /**
  * Account Meta
  */
@@ -457,6 +528,45 @@ typedef struct {
   uint64_t data_len; /** Length of the data in bytes */
 } SolInstruction;
 
+/**
+ * Lengths of a transaction instruction's data and accounts
+ */
+typedef struct {
+  uint64_t data_len; /** Length of the data in bytes */
+  uint64_t accounts_len; /** Number of accounts */
+} SolTransactionInstructionMeta;
+
+/**
+ * Account of a transaction instruction, written by sol_get_transaction_instruction
+ */
+typedef struct {
+  SolPubkey pubkey; /** An account's public key */
+  bool is_signer; /** True if the transaction is signed by `pubkey` */
+  bool is_writable; /** True if `pubkey` is loaded as a read-write account */
+} SolTransactionInstructionAccountMeta;
+
+/**
+ * Get an instruction of the transaction being processed
+ *
+ * The lengths of the instruction's data and accounts are always written to
+ * `meta`, the program id, data and accounts are only written if the lengths in
+ * `meta` already match.
+ *
+ * @param index Index of the instruction in the transaction
+ * @param meta Lengths of the data and accounts buffers, filled on return
+ * @param program_id Program id of the instruction, filled on return
+ * @param data Buffer of `meta->data_len` bytes for the instruction data
+ * @param accounts Buffer of `meta->accounts_len` accounts
+ * @return 0 on success, 1 if the transaction has no instruction at `index`
+ */
+uint64_t sol_get_transaction_instruction(
+    uint64_t index,
+    SolTransactionInstructionMeta *meta,
+    SolPubkey *program_id,
+    uint8_t *data,
+    SolTransactionInstructionAccountMeta *accounts
+);
+
 /**
  * Seed used to create a program address or passed to sol_invoke_signed
  */
@@ -656,6 +766,33 @@ void sol_log_pubkey(const SolPubkey *pubkey) {
 void sol_log_compute_units_() {
   printf("Program consumption: __ units remaining\n");
 }
+uint64_t sol_get_stack_height() {
+  return 0;
+}
+uint64_t sol_get_current_instruction_index() {
+  return 0;
+}
+uint64_t sol_get_transaction_instruction(
+    uint64_t index,
+    SolTransactionInstructionMeta *meta,
+    SolPubkey *program_id,
+    uint8_t *data,
+    SolTransactionInstructionAccountMeta *accounts
+) {
+  return 1;
+}
+uint64_t sol_get_recent_slot_hash(uint64_t index, uint64_t *slot, uint8_t *hash) {
+  return 1;
+}
+uint64_t sol_get_clock_sysvar(SolClock *var) {
+  return UNSUPPORTED_SYSVAR;
+}
+uint64_t sol_get_rent_sysvar(SolRent *var) {
+  return UNSUPPORTED_SYSVAR;
+}
+uint64_t sol_get_epoch_schedule_sysvar(SolEpochSchedule *var) {
+  return UNSUPPORTED_SYSVAR;
+}
 void sol_panic_(const char *file, uint64_t len, uint64_t line, uint64_t column) {
   printf("Panic in %s at %d:%d\n", file, line, column);
   abort();
@@ -83,6 +83,11 @@ impl UpgradeableLoaderState {
     }
 }
 
+/// Returns the address of the ProgramData account of a program.
+pub fn get_program_data_address(program_address: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_address.as_ref()], &id()).0
+}
+
 /// Returns the instructions required to initialize a Buffer account.
 pub fn create_buffer(
     payer_address: &Pubkey,
@@ -91,10 +96,6 @@ pub fn create_buffer(
     lamports: u64,
     program_len: usize,
 ) -> Result<Vec<Instruction>, InstructionError> {
-    let mut metas = vec![AccountMeta::new(*buffer_address, false)];
-    if let Some(authority_address) = authority_address {
-        metas.push(AccountMeta::new(*authority_address, false));
-    }
     Ok(vec![
         system_instruction::create_account(
             payer_address,
@@ -103,10 +104,23 @@ pub fn create_buffer(
             UpgradeableLoaderState::buffer_len(program_len)? as u64,
             &id(),
         ),
-        Instruction::new(id(), &UpgradeableLoaderInstruction::InitializeBuffer, metas),
+        initialize_buffer(buffer_address, authority_address),
     ])
 }
 
+/// Returns the instruction to initialize a Buffer account created by the same
+/// transaction.
+pub fn initialize_buffer(
+    buffer_address: &Pubkey,
+    authority_address: Option<&Pubkey>,
+) -> Instruction {
+    let mut metas = vec![AccountMeta::new(*buffer_address, false)];
+    if let Some(authority_address) = authority_address {
+        metas.push(AccountMeta::new_readonly(*authority_address, false));
+    }
+    Instruction::new(id(), &UpgradeableLoaderInstruction::InitializeBuffer, metas)
+}
+
 /// Returns the instructions required to write a chunk of program data to a
 /// buffer account.
 pub fn write(
@@ -140,7 +154,7 @@ pub fn deploy_with_max_program_len(
     program_lamports: u64,
     max_data_len: usize,
 ) -> Result<Vec<Instruction>, InstructionError> {
-    let (programdata_address, _) = Pubkey::find_program_address(&[program_address.as_ref()], &id());
+    let programdata_address = get_program_data_address(program_address);
     let mut metas = vec![
         AccountMeta::new(*payer_address, true),
         AccountMeta::new(programdata_address, false),
@@ -176,7 +190,7 @@ pub fn upgrade(
     authority_address: &Pubkey,
     spill_address: &Pubkey,
 ) -> Instruction {
-    let (programdata_address, _) = Pubkey::find_program_address(&[program_address.as_ref()], &id());
+    let programdata_address = get_program_data_address(program_address);
     Instruction::new(
         id(),
         &UpgradeableLoaderInstruction::Upgrade,
@@ -218,7 +232,7 @@ pub fn set_upgrade_authority(
     current_authority_address: &Pubkey,
     new_authority_address: Option<&Pubkey>,
 ) -> Instruction {
-    let (programdata_address, _) = Pubkey::find_program_address(&[program_address.as_ref()], &id());
+    let programdata_address = get_program_data_address(program_address);
 
     let mut metas = vec![
         AccountMeta::new(programdata_address, false),
@@ -251,6 +265,50 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_instruction_accounts() {
+        let payer_address = Pubkey::new_unique();
+        let buffer_address = Pubkey::new_unique();
+        let authority_address = Pubkey::new_unique();
+        let instructions = create_buffer(
+            &payer_address,
+            &buffer_address,
+            Some(&authority_address),
+            1,
+            42,
+        )
+        .unwrap();
+        assert_eq!(
+            instructions[1].accounts,
+            vec![
+                AccountMeta::new(buffer_address, false),
+                AccountMeta::new_readonly(authority_address, false),
+            ]
+        );
+        assert_eq!(
+            initialize_buffer(&buffer_address, None).accounts,
+            vec![AccountMeta::new(buffer_address, false)]
+        );
+
+        let program_address = Pubkey::new_unique();
+        let programdata_address = get_program_data_address(&program_address);
+        assert_eq!(
+            programdata_address,
+            Pubkey::find_program_address(&[program_address.as_ref()], &id()).0
+        );
+        let instruction = upgrade(
+            &program_address,
+            &buffer_address,
+            &authority_address,
+            &payer_address,
+        );
+        assert_eq!(instruction.accounts[0].pubkey, programdata_address);
+        assert_eq!(
+            set_upgrade_authority(&program_address, &authority_address, None).accounts[0].pubkey,
+            programdata_address
+        );
+    }
+
     #[test]
     fn test_is_upgrade_instruction() {
         assert_eq!(
@@ -45,6 +45,11 @@ pub const MAX_RECENT_BLOCKHASHES: usize =
 // The maximum age of a blockhash that will be accepted by the leader
 pub const MAX_PROCESSING_AGE: usize = MAX_RECENT_BLOCKHASHES / 2;
 
+// The maximum age of a blockhash that will be accepted by the leader once the
+// `extended_max_processing_age` feature is active. Must not exceed MAX_RECENT_BLOCKHASHES,
+// otherwise the status cache could forget signatures of still-valid transactions
+pub const EXTENDED_MAX_PROCESSING_AGE: usize = MAX_RECENT_BLOCKHASHES * 3 / 4;
+
 /// This is maximum time consumed in forwarding a transaction from one node to next, before
 /// it can be processed in the target node
 pub const MAX_TRANSACTION_FORWARDING_DELAY_GPU: usize = 2;
@@ -186,6 +186,9 @@ pub enum InstructionError {
 
     #[error("Incorrect authority provided")]
     IncorrectAuthority,
+
+    #[error("Unsupported sysvar")]
+    UnsupportedSysvar,
 }
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
@@ -289,6 +292,129 @@ impl CompiledInstruction {
     }
 }
 
+/// Lengths of a transaction instruction's data and accounts, used to size the buffers passed to
+/// the `sol_get_transaction_instruction` syscall
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct TransactionInstructionMeta {
+    pub data_len: u64,
+    pub accounts_len: u64,
+}
+
+/// An account of a transaction instruction as the `sol_get_transaction_instruction` syscall
+/// writes it, C's `SolTransactionInstructionAccountMeta`
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct TransactionInstructionAccountMeta {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl From<&AccountMeta> for TransactionInstructionAccountMeta {
+    fn from(account_meta: &AccountMeta) -> Self {
+        Self {
+            pubkey: account_meta.pubkey,
+            is_signer: account_meta.is_signer,
+            is_writable: account_meta.is_writable,
+        }
+    }
+}
+
+impl From<TransactionInstructionAccountMeta> for AccountMeta {
+    fn from(account_meta: TransactionInstructionAccountMeta) -> Self {
+        Self {
+            pubkey: account_meta.pubkey,
+            is_signer: account_meta.is_signer,
+            is_writable: account_meta.is_writable,
+        }
+    }
+}
+
+/// Height of the invocation stack, 1 while processing a transaction instruction and incremented
+/// by each cross-program invocation
+pub fn get_stack_height() -> usize {
+    #[cfg(target_arch = "bpf")]
+    unsafe {
+        sol_get_stack_height() as usize
+    }
+
+    #[cfg(not(target_arch = "bpf"))]
+    {
+        crate::program_stubs::sol_get_stack_height() as usize
+    }
+}
+
+/// Index of the transaction instruction being processed
+pub fn get_current_instruction_index() -> usize {
+    #[cfg(target_arch = "bpf")]
+    unsafe {
+        sol_get_current_instruction_index() as usize
+    }
+
+    #[cfg(not(target_arch = "bpf"))]
+    {
+        crate::program_stubs::sol_get_current_instruction_index() as usize
+    }
+}
+
+/// Instruction at `index` of the transaction being processed, or None if the transaction has
+/// fewer instructions
+pub fn get_transaction_instruction(index: usize) -> Option<Instruction> {
+    #[cfg(target_arch = "bpf")]
+    {
+        let mut meta = TransactionInstructionMeta::default();
+        let mut program_id = Pubkey::default();
+        if 0 != unsafe {
+            sol_get_transaction_instruction(
+                index as u64,
+                &mut meta,
+                &mut program_id,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        } {
+            return None;
+        }
+
+        let mut data = vec![0; meta.data_len as usize];
+        let mut accounts =
+            vec![TransactionInstructionAccountMeta::default(); meta.accounts_len as usize];
+        if !data.is_empty() || !accounts.is_empty() {
+            unsafe {
+                sol_get_transaction_instruction(
+                    index as u64,
+                    &mut meta,
+                    &mut program_id,
+                    data.as_mut_ptr(),
+                    accounts.as_mut_ptr(),
+                );
+            }
+        }
+        Some(Instruction {
+            program_id,
+            accounts: accounts.into_iter().map(AccountMeta::from).collect(),
+            data,
+        })
+    }
+
+    #[cfg(not(target_arch = "bpf"))]
+    crate::program_stubs::sol_get_transaction_instruction(index)
+}
+
+#[cfg(target_arch = "bpf")]
+extern "C" {
+    fn sol_get_stack_height() -> u64;
+    fn sol_get_current_instruction_index() -> u64;
+    fn sol_get_transaction_instruction(
+        index: u64,
+        meta: *mut TransactionInstructionMeta,
+        program_id: *mut Pubkey,
+        data: *mut u8,
+        accounts: *mut TransactionInstructionAccountMeta,
+    ) -> u64;
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -12,6 +12,21 @@ use crate::{
 };
 use itertools::Itertools;
 use std::convert::TryFrom;
+use thiserror::Error;
+
+/// Maximum number of account keys in a message, instructions reference accounts by a u8 index
+pub const MAX_ACCOUNT_KEYS: usize = 256;
+
+/// Reasons a message can't be compiled from a list of instructions
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum MessageError {
+    #[error("Message references {0} accounts, the limit is {}", MAX_ACCOUNT_KEYS)]
+    TooManyAccountKeys(usize),
+    #[error("Message requires {0} signatures, the limit is {}", u8::MAX)]
+    TooManySignatures(usize),
+    #[error("Message references {0} read-only accounts, the limit is {}", u8::MAX)]
+    TooManyReadonlyAccounts(usize),
+}
 
 fn position(keys: &[Pubkey], key: &Pubkey) -> u8 {
     keys.iter().position(|k| k == key).unwrap() as u8
@@ -40,16 +55,16 @@ fn compile_instructions(ixs: &[Instruction], keys: &[Pubkey]) -> Vec<CompiledIns
 struct InstructionKeys {
     pub signed_keys: Vec<Pubkey>,
     pub unsigned_keys: Vec<Pubkey>,
-    pub num_readonly_signed_accounts: u8,
-    pub num_readonly_unsigned_accounts: u8,
+    pub num_readonly_signed_accounts: usize,
+    pub num_readonly_unsigned_accounts: usize,
 }
 
 impl InstructionKeys {
     fn new(
         signed_keys: Vec<Pubkey>,
         unsigned_keys: Vec<Pubkey>,
-        num_readonly_signed_accounts: u8,
-        num_readonly_unsigned_accounts: u8,
+        num_readonly_signed_accounts: usize,
+        num_readonly_unsigned_accounts: usize,
     ) -> Self {
         Self {
             signed_keys,
@@ -239,19 +254,45 @@ impl Message {
     }
 
     pub fn new(instructions: &[Instruction], payer: Option<&Pubkey>) -> Self {
+        Self::new_with_keys(instructions, get_keys(instructions, payer))
+    }
+
+    /// Like `new`, but fails instead of compiling a message whose account indexes or header
+    /// counts overflow
+    pub fn try_new(
+        instructions: &[Instruction],
+        payer: Option<&Pubkey>,
+    ) -> Result<Self, MessageError> {
+        let keys = get_keys(instructions, payer);
+        if keys.signed_keys.len() > u8::MAX as usize {
+            return Err(MessageError::TooManySignatures(keys.signed_keys.len()));
+        }
+        let num_account_keys = keys.signed_keys.len() + keys.unsigned_keys.len();
+        if num_account_keys > MAX_ACCOUNT_KEYS {
+            return Err(MessageError::TooManyAccountKeys(num_account_keys));
+        }
+        if keys.num_readonly_unsigned_accounts > u8::MAX as usize {
+            return Err(MessageError::TooManyReadonlyAccounts(
+                keys.num_readonly_unsigned_accounts,
+            ));
+        }
+        Ok(Self::new_with_keys(instructions, keys))
+    }
+
+    fn new_with_keys(instructions: &[Instruction], keys: InstructionKeys) -> Self {
         let InstructionKeys {
             mut signed_keys,
             unsigned_keys,
             num_readonly_signed_accounts,
             num_readonly_unsigned_accounts,
-        } = get_keys(instructions, payer);
+        } = keys;
         let num_required_signatures = signed_keys.len() as u8;
         signed_keys.extend(&unsigned_keys);
         let instructions = compile_instructions(instructions, &signed_keys);
         Self::new_with_compiled_instructions(
             num_required_signatures,
-            num_readonly_signed_accounts,
-            num_readonly_unsigned_accounts,
+            num_readonly_signed_accounts as u8,
+            num_readonly_unsigned_accounts as u8,
             signed_keys,
             Hash::default(),
             instructions,
@@ -286,6 +327,31 @@ impl Message {
         )
     }
 
+    /// Rebuilds the instruction at `instruction_index` from the message's account keys and
+    /// their signer and writable privileges
+    pub fn decompile_instruction(&self, instruction_index: usize) -> Option<Instruction> {
+        let instruction = self.instructions.get(instruction_index)?;
+        let accounts = instruction
+            .accounts
+            .iter()
+            .map(|account_index| {
+                let account_index = *account_index as usize;
+                Some(AccountMeta {
+                    pubkey: *self.account_keys.get(account_index)?,
+                    is_signer: self.is_signer(account_index),
+                    is_writable: self.is_writable(account_index),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(Instruction {
+            program_id: *self
+                .account_keys
+                .get(instruction.program_id_index as usize)?,
+            accounts,
+            data: instruction.data.clone(),
+        })
+    }
+
     pub fn program_index(&self, instruction_index: usize) -> Option<usize> {
         Some(self.instructions.get(instruction_index)?.program_id_index as usize)
     }
@@ -806,7 +872,9 @@ mod tests {
                 Message::deserialize_instruction(i, &serialized).unwrap(),
                 *instruction
             );
+            assert_eq!(message.decompile_instruction(i).unwrap(), *instruction);
         }
+        assert_eq!(message.decompile_instruction(instructions.len()), None);
     }
 
     #[test]
@@ -865,4 +933,45 @@ mod tests {
         assert!(message.is_non_loader_key(&key1, 1));
         assert!(!message.is_non_loader_key(&loader2, 2));
     }
+
+    #[test]
+    fn test_message_try_new() {
+        let program_id = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let accounts = |num_accounts: usize, is_signer: bool| -> Vec<_> {
+            (0..num_accounts)
+                .map(|_| AccountMeta::new(Pubkey::new_unique(), is_signer))
+                .collect()
+        };
+
+        // Payer and program id take two of the keys
+        let ix = Instruction::new(program_id, &0, accounts(MAX_ACCOUNT_KEYS - 2, false));
+        let message = Message::try_new(&[ix.clone()], Some(&payer)).unwrap();
+        assert_eq!(message, Message::new(&[ix], Some(&payer)));
+        assert_eq!(message.account_keys.len(), MAX_ACCOUNT_KEYS);
+
+        let ix = Instruction::new(program_id, &0, accounts(MAX_ACCOUNT_KEYS - 1, false));
+        assert_eq!(
+            Message::try_new(&[ix], Some(&payer)),
+            Err(MessageError::TooManyAccountKeys(MAX_ACCOUNT_KEYS + 1))
+        );
+
+        let ix = Instruction::new(program_id, &0, accounts(255, true));
+        assert_eq!(
+            Message::try_new(&[ix], Some(&payer)),
+            Err(MessageError::TooManySignatures(256))
+        );
+
+        let ix = Instruction::new(
+            program_id,
+            &0,
+            (0..255)
+                .map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false))
+                .collect(),
+        );
+        assert_eq!(
+            Message::try_new(&[ix], None),
+            Err(MessageError::TooManyReadonlyAccounts(256))
+        );
+    }
 }
@@ -2,7 +2,10 @@
 
 #![cfg(not(target_arch = "bpf"))]
 
-use crate::{account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction};
+use crate::{
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction,
+    program_error::UNSUPPORTED_SYSVAR, slot_hashes::SlotHash,
+};
 use std::sync::{Arc, RwLock};
 
 lazy_static::lazy_static! {
@@ -31,6 +34,31 @@ pub trait SyscallStubs: Sync + Send {
         sol_log("SyscallStubs: sol_invoke_signed() not available");
         Ok(())
     }
+    fn sol_get_stack_height(&self) -> u64 {
+        sol_log("SyscallStubs: sol_get_stack_height() not available");
+        0
+    }
+    fn sol_get_current_instruction_index(&self) -> u64 {
+        sol_log("SyscallStubs: sol_get_current_instruction_index() not available");
+        0
+    }
+    fn sol_get_transaction_instruction(&self, _index: usize) -> Option<Instruction> {
+        sol_log("SyscallStubs: sol_get_transaction_instruction() not available");
+        None
+    }
+    fn sol_get_recent_slot_hash(&self, _index: usize) -> Option<SlotHash> {
+        sol_log("SyscallStubs: sol_get_recent_slot_hash() not available");
+        None
+    }
+    fn sol_get_clock_sysvar(&self, _var_addr: *mut u8) -> u64 {
+        UNSUPPORTED_SYSVAR
+    }
+    fn sol_get_rent_sysvar(&self, _var_addr: *mut u8) -> u64 {
+        UNSUPPORTED_SYSVAR
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, _var_addr: *mut u8) -> u64 {
+        UNSUPPORTED_SYSVAR
+    }
 }
 
 struct DefaultSyscallStubs {}
@@ -61,3 +89,43 @@ pub(crate) fn sol_invoke_signed(
         .unwrap()
         .sol_invoke_signed(instruction, account_infos, signers_seeds)
 }
+
+pub(crate) fn sol_get_stack_height() -> u64 {
+    SYSCALL_STUBS.read().unwrap().sol_get_stack_height()
+}
+
+pub(crate) fn sol_get_current_instruction_index() -> u64 {
+    SYSCALL_STUBS
+        .read()
+        .unwrap()
+        .sol_get_current_instruction_index()
+}
+
+pub(crate) fn sol_get_transaction_instruction(index: usize) -> Option<Instruction> {
+    SYSCALL_STUBS
+        .read()
+        .unwrap()
+        .sol_get_transaction_instruction(index)
+}
+
+pub(crate) fn sol_get_recent_slot_hash(index: usize) -> Option<SlotHash> {
+    SYSCALL_STUBS
+        .read()
+        .unwrap()
+        .sol_get_recent_slot_hash(index)
+}
+
+pub(crate) fn sol_get_clock_sysvar(var_addr: *mut u8) -> u64 {
+    SYSCALL_STUBS.read().unwrap().sol_get_clock_sysvar(var_addr)
+}
+
+pub(crate) fn sol_get_rent_sysvar(var_addr: *mut u8) -> u64 {
+    SYSCALL_STUBS.read().unwrap().sol_get_rent_sysvar(var_addr)
+}
+
+pub(crate) fn sol_get_epoch_schedule_sysvar(var_addr: *mut u8) -> u64 {
+    SYSCALL_STUBS
+        .read()
+        .unwrap()
+        .sol_get_epoch_schedule_sysvar(var_addr)
+}
@@ -2,7 +2,22 @@
 //!
 //! this account carries the Bank's most recent bank hashes for some N parents
 //!
-use crate::hash::Hash;
+//! ## Deriving values from slot hashes
+//!
+//! Programs can read recent slot hashes without being passed the sysvar account through
+//! `get_recent_slot_hash()`.  Every validator replaying a transaction sees the same slot hashes,
+//! so values derived from them are deterministic, but they are NOT secure randomness:
+//!
+//! * A slot hash is public as soon as its slot is frozen, so a transaction sender can compute
+//!   any value derived from the hashes visible to its transaction before submitting it, and only
+//!   submit when the outcome suits them.
+//! * The leader of a slot influences the slot hash through the transactions it includes.
+//!
+//! Values that decide an outcome should use commit-reveal: record the slot of a commitment in
+//! one transaction, then in a later transaction derive the value with `derive_value()` from
+//! `get_slot_hash_after(commit_slot)`, a hash that did not exist when the commitment was made.
+//!
+use crate::hash::{hashv, Hash};
 use std::{iter::FromIterator, ops::Deref};
 
 pub const MAX_ENTRIES: usize = 512; // about 2.5 minutes to get your vote in
@@ -49,10 +64,68 @@ impl Deref for SlotHashes {
     }
 }
 
+/// Entry `index` of the SlotHashes sysvar, most recent first, or None if the sysvar has fewer
+/// entries
+pub fn get_recent_slot_hash(index: usize) -> Option<SlotHash> {
+    #[cfg(target_arch = "bpf")]
+    {
+        let mut slot = 0;
+        let mut hash = Hash::default();
+        if 0 != unsafe { sol_get_recent_slot_hash(index as u64, &mut slot, &mut hash) } {
+            return None;
+        }
+        Some((slot, hash))
+    }
+
+    #[cfg(not(target_arch = "bpf"))]
+    crate::program_stubs::sol_get_recent_slot_hash(index)
+}
+
+/// Oldest SlotHashes entry of a slot after `slot`, or None if no later slot has been recorded
+/// yet or if the entries right after `slot` may already have been evicted
+pub fn get_slot_hash_after(slot: Slot) -> Option<SlotHash> {
+    if get_recent_slot_hash(0)?.0 <= slot {
+        return None;
+    }
+    // Entries are sorted by descending slot, entry `after` is known to be after `slot` and
+    // entries from `not_after` on are not after `slot` or don't exist
+    let (mut after, mut not_after) = (0, MAX_ENTRIES);
+    while not_after - after > 1 {
+        let middle = after + (not_after - after) / 2;
+        match get_recent_slot_hash(middle) {
+            Some((middle_slot, _)) if middle_slot > slot => after = middle,
+            _ => not_after = middle,
+        }
+    }
+    if after == MAX_ENTRIES - 1 {
+        // The sysvar is full, the slot following `slot` may be gone and the oldest remaining
+        // entry would change over time
+        return None;
+    }
+    get_recent_slot_hash(after)
+}
+
+/// Derives a value from a slot hash and program chosen seeds, e.g. the drawing account and a
+/// counter, so that several values derived from the same slot hash are independent
+pub fn derive_value(slot_hash: &Hash, seeds: &[&[u8]]) -> Hash {
+    let mut vals = Vec::with_capacity(seeds.len() + 1);
+    vals.push(slot_hash.as_ref());
+    vals.extend_from_slice(seeds);
+    hashv(&vals)
+}
+
+#[cfg(target_arch = "bpf")]
+extern "C" {
+    fn sol_get_recent_slot_hash(index: u64, slot: *mut Slot, hash: *mut Hash) -> u64;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hash::hash;
+    use crate::{
+        hash::hash,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+    };
 
     #[test]
     fn test() {
@@ -80,4 +153,53 @@ mod tests {
 
         assert_eq!(slot_hashes.len(), MAX_ENTRIES);
     }
+
+    #[test]
+    fn test_derive_value() {
+        let slot_hash = hash(&[1]);
+        assert_eq!(
+            derive_value(&slot_hash, &[b"a", b"b"]),
+            derive_value(&slot_hash, &[b"a", b"b"])
+        );
+        assert_ne!(
+            derive_value(&slot_hash, &[b"a"]),
+            derive_value(&slot_hash, &[b"b"])
+        );
+        assert_ne!(
+            derive_value(&slot_hash, &[b"a"]),
+            derive_value(&hash(&[2]), &[b"a"])
+        );
+    }
+
+    #[test]
+    fn test_get_slot_hash_after() {
+        struct SlotHashesStubs(Vec<SlotHash>);
+        impl SyscallStubs for SlotHashesStubs {
+            fn sol_get_recent_slot_hash(&self, index: usize) -> Option<SlotHash> {
+                self.0.get(index).cloned()
+            }
+        }
+
+        // Every other slot was skipped
+        let slot_hashes: Vec<_> = (0..100u64)
+            .rev()
+            .map(|slot| (slot * 2, hash(&slot.to_le_bytes())))
+            .collect();
+        let stubs = set_syscall_stubs(Box::new(SlotHashesStubs(slot_hashes.clone())));
+        assert_eq!(get_recent_slot_hash(0), Some(slot_hashes[0]));
+        assert_eq!(get_recent_slot_hash(100), None);
+        assert_eq!(get_slot_hash_after(0), Some(slot_hashes[98]));
+        assert_eq!(get_slot_hash_after(41), Some(slot_hashes[78]));
+        assert_eq!(get_slot_hash_after(42), Some(slot_hashes[77]));
+        assert_eq!(get_slot_hash_after(198), None);
+
+        let full: Vec<_> = (0..MAX_ENTRIES as u64)
+            .rev()
+            .map(|slot| (slot + 10, Hash::default()))
+            .collect();
+        set_syscall_stubs(Box::new(SlotHashesStubs(full)));
+        assert_eq!(get_slot_hash_after(5), None);
+        assert_eq!(get_slot_hash_after(10), Some((11, Hash::default())));
+        set_syscall_stubs(stubs);
+    }
 }
@@ -377,6 +377,21 @@ pub fn transfer_many(from_pubkey: &Pubkey, to_lamports: &[(Pubkey, u64)]) -> Vec
         .collect()
 }
 
+/// Initializes a system account already sized for `nonce::State`, it must be created in the
+/// same transaction or another party may initialize it first
+pub fn initialize_nonce_account(nonce_pubkey: &Pubkey, authority: &Pubkey) -> Instruction {
+    let account_metas = vec![
+        AccountMeta::new(*nonce_pubkey, false),
+        AccountMeta::new_readonly(recent_blockhashes::id(), false),
+        AccountMeta::new_readonly(rent::id(), false),
+    ];
+    Instruction::new(
+        system_program::id(),
+        &SystemInstruction::InitializeNonceAccount(*authority),
+        account_metas,
+    )
+}
+
 pub fn create_nonce_account_with_seed(
     from_pubkey: &Pubkey,
     nonce_pubkey: &Pubkey,
@@ -395,15 +410,7 @@ pub fn create_nonce_account_with_seed(
             nonce::State::size() as u64,
             &system_program::id(),
         ),
-        Instruction::new(
-            system_program::id(),
-            &SystemInstruction::InitializeNonceAccount(*authority),
-            vec![
-                AccountMeta::new(*nonce_pubkey, false),
-                AccountMeta::new_readonly(recent_blockhashes::id(), false),
-                AccountMeta::new_readonly(rent::id(), false),
-            ],
-        ),
+        initialize_nonce_account(nonce_pubkey, authority),
     ]
 }
 
@@ -421,15 +428,7 @@ pub fn create_nonce_account(
             nonce::State::size() as u64,
             &system_program::id(),
         ),
-        Instruction::new(
-            system_program::id(),
-            &SystemInstruction::InitializeNonceAccount(*authority),
-            vec![
-                AccountMeta::new(*nonce_pubkey, false),
-                AccountMeta::new_readonly(recent_blockhashes::id(), false),
-                AccountMeta::new_readonly(rent::id(), false),
-            ],
-        ),
+        initialize_nonce_account(nonce_pubkey, authority),
     ]
 }
 
@@ -516,6 +515,11 @@ mod tests {
         let pubkeys: Vec<_> = ix.accounts.iter().map(|am| am.pubkey).collect();
         assert!(pubkeys.contains(&from_pubkey));
         assert!(pubkeys.contains(&nonce_pubkey));
+        assert_eq!(ixs[1], initialize_nonce_account(&nonce_pubkey, &authorized));
+        assert_eq!(
+            get_keys(&ixs[1]),
+            vec![nonce_pubkey, recent_blockhashes::id(), rent::id()]
+        );
     }
 
     #[test]
@@ -6,4 +6,6 @@ use crate::sysvar::Sysvar;
 
 crate::declare_sysvar_id!("SysvarC1ock11111111111111111111111111111111", Clock);
 
-impl Sysvar for Clock {}
+impl Sysvar for Clock {
+    impl_sysvar_get!(sol_get_clock_sysvar);
+}
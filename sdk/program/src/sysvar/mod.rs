@@ -2,6 +2,31 @@
 //!
 use crate::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
 
+/// Implements `Sysvar::get()` with the syscall copying the sysvar into program memory
+macro_rules! impl_sysvar_get {
+    ($syscall_name:ident) => {
+        fn get() -> Result<Self, $crate::program_error::ProgramError> {
+            let mut var = Self::default();
+            let var_addr = &mut var as *mut _ as *mut u8;
+
+            #[cfg(target_arch = "bpf")]
+            let result = {
+                extern "C" {
+                    fn $syscall_name(var_addr: *mut u8) -> u64;
+                }
+                unsafe { $syscall_name(var_addr) }
+            };
+            #[cfg(not(target_arch = "bpf"))]
+            let result = $crate::program_stubs::$syscall_name(var_addr);
+
+            match result {
+                $crate::entrypoint::SUCCESS => Ok(var),
+                error => Err(error.into()),
+            }
+        }
+    };
+}
+
 pub mod clock;
 pub mod epoch_schedule;
 pub mod fees;
@@ -70,6 +95,11 @@ pub trait Sysvar:
     fn to_account_info(&self, account_info: &mut AccountInfo) -> Option<()> {
         bincode::serialize_into(&mut account_info.data.borrow_mut()[..], self).ok()
     }
+    /// Read the sysvar without the sysvar account being passed to the instruction, only some
+    /// sysvars support it
+    fn get() -> Result<Self, ProgramError> {
+        Err(ProgramError::UnsupportedSysvar)
+    }
 }
 
 #[cfg(test)]
@@ -124,4 +154,9 @@ mod tests {
         account_info.data = Rc::new(RefCell::new(&mut small_data));
         assert_eq!(test_sysvar.to_account_info(&mut account_info), None);
     }
+
+    #[test]
+    fn test_sysvar_get_unsupported() {
+        assert_eq!(TestSysvar::get(), Err(ProgramError::UnsupportedSysvar));
+    }
 }
@@ -6,4 +6,6 @@ use crate::sysvar::Sysvar;
 
 crate::declare_sysvar_id!("SysvarRent111111111111111111111111111111111", Rent);
 
-impl Sysvar for Rent {}
+impl Sysvar for Rent {
+    impl_sysvar_get!(sol_get_rent_sysvar);
+}
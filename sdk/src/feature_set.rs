@@ -174,6 +174,30 @@ pub mod spl_token_v2_self_transfer_fix {
     solana_sdk::declare_id!("BL99GYhdjjcv6ys22C9wPgn2aTVERDbPHHo4NbS3hgp7");
 }
 
+pub mod instruction_introspection_syscalls_enabled {
+    solana_sdk::declare_id!("5BE93JVDNihRSLJwB4gexAKVCJQGbQoW1jkAJVV8VRZK");
+}
+
+pub mod vote_withdraw_safety_checks {
+    solana_sdk::declare_id!("87AnuoK8wmsp5eWkUaHnRqi9RJNWbzHwMz61UCoTfosN");
+}
+
+pub mod slot_hashes_syscall_enabled {
+    solana_sdk::declare_id!("2zV8zgHTtZWqKrTPyZepUAdgSXFGzjPEsRcsheq168Jy");
+}
+
+pub mod extended_max_processing_age {
+    solana_sdk::declare_id!("6eXn3pPgQkxyYUNUmd6vB6DLF3QWkKxnq5RhwZdRmLQ3");
+}
+
+pub mod sysvar_via_syscall {
+    solana_sdk::declare_id!("79NAtciWHrPuutcGpHU9AotDRUxr77v7YTVPkPu9MNfZ");
+}
+
+pub mod block_cost_limits {
+    solana_sdk::declare_id!("4RCiUbtgiUtLhFAKHHxD1LcL4LnENraQMnnEBXtzJmtE");
+}
+
 lazy_static! {
     /// Map of feature identifiers to user-visible description
     pub static ref FEATURE_NAMES: HashMap<Pubkey, &'static str> = [
@@ -216,6 +240,12 @@ lazy_static! {
         (full_inflation::candidate_example::enable::id(), "full inflation enabled by candidate_example"),
         (track_writable_deescalation::id(), "track account writable deescalation"),
         (spl_token_v2_self_transfer_fix::id(), "spl-token self-transfer fix"),
+        (instruction_introspection_syscalls_enabled::id(), "stack height and instruction introspection syscalls"),
+        (vote_withdraw_safety_checks::id(), "vote withdraw rent-exemption and active account close checks"),
+        (slot_hashes_syscall_enabled::id(), "recent slot hashes syscall"),
+        (extended_max_processing_age::id(), "extend blockhash max processing age to 3/4 of the blockhash queue"),
+        (sysvar_via_syscall::id(), "clock, rent and epoch schedule sysvars via syscalls"),
+        (block_cost_limits::id(), "reject blocks costing more than the max block units"),
         /*************** ADD NEW FEATURES HERE ***************/
     ]
     .iter()
@@ -1,13 +1,16 @@
 use solana_sdk::{
     account::Account,
     feature_set::{
-        bpf_compute_budget_balancing, max_cpi_instruction_size_ipv6_mtu, max_invoke_depth_4,
-        max_program_call_depth_64, pubkey_log_syscall_enabled, FeatureSet,
+        bpf_compute_budget_balancing, instruction_introspection_syscalls_enabled,
+        max_cpi_instruction_size_ipv6_mtu, max_invoke_depth_4, max_program_call_depth_64,
+        pubkey_log_syscall_enabled, slot_hashes_syscall_enabled, sysvar_via_syscall, FeatureSet,
     },
     instruction::{CompiledInstruction, Instruction, InstructionError},
     keyed_account::KeyedAccount,
     message::Message,
     pubkey::Pubkey,
+    slot_hashes::SlotHash,
+    sysvar::Sysvar,
 };
 use std::{cell::RefCell, fmt::Debug, rc::Rc, sync::Arc};
 
@@ -35,6 +38,14 @@ pub trait InvokeContext {
     fn pop(&mut self);
     /// Current depth of the invocation stake
     fn invoke_depth(&self) -> usize;
+    /// Index of the transaction instruction being processed
+    fn get_instruction_index(&self) -> usize;
+    /// Get an instruction of the transaction being processed
+    fn get_transaction_instruction(&self, index: usize) -> Option<Instruction>;
+    /// Get the entries of the SlotHashes sysvar, most recent first
+    fn get_slot_hashes(&self) -> &[SlotHash];
+    /// Get the account data of a sysvar programs can read without being passed its account
+    fn get_sysvar_data(&self, id: &Pubkey) -> Option<&[u8]>;
     /// Verify and update PreAccount state based on program execution
     fn verify_and_update(
         &mut self,
@@ -56,6 +67,9 @@ pub trait InvokeContext {
     /// Loaders may need to do work in order to execute a program.  Cache
     /// the work that can be re-used across executions
     fn add_executor(&self, pubkey: &Pubkey, executor: Arc<dyn Executor>);
+    /// Replace the cached work of a program that was deployed or upgraded.  Unlike
+    /// `add_executor`, the replacement is dropped if the transaction fails
+    fn update_executor(&self, pubkey: &Pubkey, executor: Arc<dyn Executor>);
     /// Get the completed loader work that can be re-used across executions
     fn get_executor(&self, pubkey: &Pubkey) -> Option<Arc<dyn Executor>>;
     /// Record invoked instruction
@@ -66,6 +80,17 @@ pub trait InvokeContext {
     fn get_account(&self, pubkey: &Pubkey) -> Option<RefCell<Account>>;
 }
 
+/// Get a sysvar programs can read without being passed its account
+pub fn get_sysvar<T: Sysvar>(
+    invoke_context: &dyn InvokeContext,
+    id: &Pubkey,
+) -> Result<T, InstructionError> {
+    let data = invoke_context
+        .get_sysvar_data(id)
+        .ok_or(InstructionError::UnsupportedSysvar)?;
+    bincode::deserialize(data).map_err(|_| InstructionError::UnsupportedSysvar)
+}
+
 /// Convenience macro to log a message with an `Rc<RefCell<dyn Logger>>`
 #[macro_export]
 macro_rules! ic_logger_msg {
@@ -124,6 +149,14 @@ pub struct BpfComputeBudget {
     pub log_pubkey_units: u64,
     /// Maximum cross-program invocation instruction size
     pub max_cpi_instruction_size: usize,
+    /// Number of compute units consumed by the stack height and instruction introspection
+    /// syscalls
+    pub introspection_units: u64,
+    /// Number of compute units consumed by the recent slot hash syscall
+    pub slot_hash_units: u64,
+    /// Base number of compute units consumed by the sysvar syscalls, which also charge the size
+    /// of the sysvar
+    pub sysvar_base_cost: u64,
 }
 impl Default for BpfComputeBudget {
     fn default() -> Self {
@@ -147,6 +180,9 @@ impl BpfComputeBudget {
             stack_frame_size: 4_096,
             log_pubkey_units: 0,
             max_cpi_instruction_size: std::usize::MAX,
+            introspection_units: 0,
+            slot_hash_units: 0,
+            sysvar_base_cost: 0,
         };
 
         if feature_set.is_active(&bpf_compute_budget_balancing::id()) {
@@ -184,6 +220,24 @@ impl BpfComputeBudget {
                 ..bpf_compute_budget
             };
         }
+        if feature_set.is_active(&instruction_introspection_syscalls_enabled::id()) {
+            bpf_compute_budget = BpfComputeBudget {
+                introspection_units: 100,
+                ..bpf_compute_budget
+            };
+        }
+        if feature_set.is_active(&slot_hashes_syscall_enabled::id()) {
+            bpf_compute_budget = BpfComputeBudget {
+                slot_hash_units: 100,
+                ..bpf_compute_budget
+            };
+        }
+        if feature_set.is_active(&sysvar_via_syscall::id()) {
+            bpf_compute_budget = BpfComputeBudget {
+                sysvar_base_cost: 100,
+                ..bpf_compute_budget
+            };
+        }
         bpf_compute_budget
     }
 }
@@ -310,6 +364,10 @@ pub struct MockInvokeContext {
     pub compute_meter: MockComputeMeter,
     pub programs: Vec<(Pubkey, ProcessInstructionWithContext)>,
     pub invoke_depth: usize,
+    pub instructions: Vec<Instruction>,
+    pub instruction_index: usize,
+    pub slot_hashes: Vec<SlotHash>,
+    pub sysvars: Vec<(Pubkey, Vec<u8>)>,
 }
 impl Default for MockInvokeContext {
     fn default() -> Self {
@@ -322,6 +380,10 @@ impl Default for MockInvokeContext {
             },
             programs: vec![],
             invoke_depth: 0,
+            instructions: vec![],
+            instruction_index: 0,
+            slot_hashes: vec![],
+            sysvars: vec![],
         }
     }
 }
@@ -336,6 +398,21 @@ impl InvokeContext for MockInvokeContext {
     fn invoke_depth(&self) -> usize {
         self.invoke_depth
     }
+    fn get_instruction_index(&self) -> usize {
+        self.instruction_index
+    }
+    fn get_transaction_instruction(&self, index: usize) -> Option<Instruction> {
+        self.instructions.get(index).cloned()
+    }
+    fn get_slot_hashes(&self) -> &[SlotHash] {
+        &self.slot_hashes
+    }
+    fn get_sysvar_data(&self, id: &Pubkey) -> Option<&[u8]> {
+        self.sysvars
+            .iter()
+            .find(|(key, _)| key == id)
+            .map(|(_, data)| data.as_slice())
+    }
     fn verify_and_update(
         &mut self,
         _message: &Message,
@@ -361,6 +438,7 @@ impl InvokeContext for MockInvokeContext {
         Rc::new(RefCell::new(self.compute_meter.clone()))
     }
     fn add_executor(&self, _pubkey: &Pubkey, _executor: Arc<dyn Executor>) {}
+    fn update_executor(&self, _pubkey: &Pubkey, _executor: Arc<dyn Executor>) {}
     fn get_executor(&self, _pubkey: &Pubkey) -> Option<Arc<dyn Executor>> {
         None
     }
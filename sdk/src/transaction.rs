@@ -7,7 +7,8 @@ use crate::secp256k1_instruction::verify_eth_addresses;
 use crate::{
     hash::Hash,
     instruction::{CompiledInstruction, Instruction, InstructionError},
-    message::Message,
+    message::{Message, MessageError},
+    packet::PACKET_DATA_SIZE,
     program_utils::limited_deserialize,
     pubkey::Pubkey,
     short_vec,
@@ -20,7 +21,9 @@ use std::result;
 use thiserror::Error;
 
 /// Reasons a transaction might be rejected.
-#[derive(Error, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, AbiExample, AbiEnumVisitor)]
+#[derive(
+    Error, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, AbiExample, AbiEnumVisitor,
+)]
 pub enum TransactionError {
     /// An account is already being processed in another transaction in a way
     /// that does not support parallelism
@@ -102,6 +105,23 @@ impl From<SanitizeError> for TransactionError {
     }
 }
 
+/// Reasons a transaction can't be built for submission to the cluster
+#[derive(Error, Debug, PartialEq)]
+pub enum TransactionBuildError {
+    #[error(transparent)]
+    MessageError(#[from] MessageError),
+
+    /// The serialized transaction doesn't fit in a packet
+    #[error(
+        "Transaction is {0} bytes when serialized, the limit is {}",
+        PACKET_DATA_SIZE
+    )]
+    TooLarge(usize),
+
+    #[error(transparent)]
+    SignerError(#[from] SignerError),
+}
+
 /// An atomic transaction
 #[frozen_abi(digest = "EGPL8qfT6of8UDKUTPQfcCADiRrpVxKXmULWtNdfjbQ8")]
 #[derive(Debug, PartialEq, Default, Eq, Clone, Serialize, Deserialize, AbiExample)]
@@ -161,6 +181,46 @@ impl Transaction {
         tx
     }
 
+    /// Like `new_unsigned`, but fails if the signed transaction would not fit in a packet
+    pub fn try_new_unsigned(message: Message) -> result::Result<Self, TransactionBuildError> {
+        let tx = Self::new_unsigned(message);
+        tx.check_size()?;
+        Ok(tx)
+    }
+
+    /// Like `new`, but fails if the transaction would not fit in a packet or can't be signed
+    pub fn try_new<T: Signers>(
+        from_keypairs: &T,
+        message: Message,
+        recent_blockhash: Hash,
+    ) -> result::Result<Self, TransactionBuildError> {
+        let mut tx = Self::try_new_unsigned(message)?;
+        tx.try_sign(from_keypairs, recent_blockhash)?;
+        Ok(tx)
+    }
+
+    /// Like `new_signed_with_payer`, but fails if the instructions can't be compiled into a
+    /// message, if the transaction would not fit in a packet or if it can't be signed
+    pub fn try_new_signed_with_payer<T: Signers>(
+        instructions: &[Instruction],
+        payer: Option<&Pubkey>,
+        signing_keypairs: &T,
+        recent_blockhash: Hash,
+    ) -> result::Result<Self, TransactionBuildError> {
+        let message = Message::try_new(instructions, payer)?;
+        Self::try_new(signing_keypairs, message, recent_blockhash)
+    }
+
+    /// Fails if the serialized transaction is larger than a packet, the cluster drops such
+    /// transactions
+    pub fn check_size(&self) -> result::Result<(), TransactionBuildError> {
+        let size = bincode::serialized_size(self).unwrap() as usize;
+        if size > PACKET_DATA_SIZE {
+            return Err(TransactionBuildError::TooLarge(size));
+        }
+        Ok(())
+    }
+
     /// Create a signed transaction
     /// * `from_keypairs` - The keys used to sign the transaction.
     /// * `keys` - The keys for the transaction.  These are the program state
@@ -431,6 +491,7 @@ mod tests {
     use crate::{
         hash::hash,
         instruction::AccountMeta,
+        message::MAX_ACCOUNT_KEYS,
         signature::{Keypair, Presigner, Signer},
         system_instruction,
     };
@@ -843,6 +904,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_transaction_try_new() {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey();
+        let program_id = Pubkey::new_unique();
+
+        let ix = Instruction::new(program_id, &0, vec![AccountMeta::new(pubkey, true)]);
+        let tx =
+            Transaction::try_new_signed_with_payer(&[ix.clone()], None, &[&keypair], hash(&[1]))
+                .unwrap();
+        assert_eq!(
+            tx,
+            Transaction::new_signed_with_payer(&[ix.clone()], None, &[&keypair], hash(&[1]))
+        );
+        assert_eq!(tx.check_size(), Ok(()));
+        assert_eq!(
+            Transaction::try_new_signed_with_payer(&[ix], None, &[&Keypair::new()], hash(&[1])),
+            Err(TransactionBuildError::SignerError(
+                SignerError::KeypairPubkeyMismatch
+            ))
+        );
+
+        // 40 pubkeys don't fit in a packet
+        let accounts = (0..40)
+            .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+            .collect();
+        let ix = Instruction::new(program_id, &0, accounts);
+        let message = Message::new(&[ix], Some(&pubkey));
+        let size = serialized_size(&Transaction::new_unsigned(message.clone())).unwrap() as usize;
+        assert!(size > PACKET_DATA_SIZE);
+        assert_eq!(
+            Transaction::try_new_unsigned(message.clone()),
+            Err(TransactionBuildError::TooLarge(size))
+        );
+        assert_eq!(
+            Transaction::try_new(&[&keypair], message, hash(&[1])),
+            Err(TransactionBuildError::TooLarge(size))
+        );
+
+        let accounts = (0..MAX_ACCOUNT_KEYS)
+            .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+            .collect();
+        let ix = Instruction::new(program_id, &0, accounts);
+        assert_eq!(
+            Transaction::try_new_signed_with_payer(&[ix], Some(&pubkey), &[&keypair], hash(&[1])),
+            Err(TransactionBuildError::MessageError(
+                MessageError::TooManyAccountKeys(MAX_ACCOUNT_KEYS + 2)
+            ))
+        );
+    }
+
     fn nonced_transfer_tx() -> (Pubkey, Pubkey, Transaction) {
         let from_keypair = Keypair::new();
         let from_pubkey = from_keypair.pubkey();
@@ -4,11 +4,10 @@ use crate::args::{
 };
 use clap::{value_t, value_t_or_exit, App, Arg, ArgMatches, SubCommand};
 use solana_clap_utils::{
-    input_parsers::unix_timestamp_from_rfc3339_datetime,
-    input_validators::{is_amount, is_rfc3339_datetime, is_valid_pubkey, is_valid_signer},
+    input_parsers::{lamports_of_sol, unix_timestamp_from_rfc3339_datetime},
+    input_validators::{is_rfc3339_datetime, is_sol_amount, is_valid_pubkey, is_valid_signer},
 };
 use solana_cli_config::CONFIG_FILE;
-use solana_sdk::native_token::sol_to_lamports;
 use std::ffi::OsString;
 use std::process::exit;
 
@@ -171,7 +170,7 @@ where
                         .index(3)
                         .takes_value(true)
                         .value_name("AMOUNT")
-                        .validator(is_amount)
+                        .validator(is_sol_amount)
                         .help("Amount to move into the new stake accounts, in SOL"),
                 )
                 .arg(
@@ -280,7 +279,7 @@ fn parse_new_args(matches: &ArgMatches<'_>) -> NewArgs<String, String> {
     NewArgs {
         fee_payer: value_t_or_exit!(matches, "fee_payer", String),
         funding_keypair: value_t_or_exit!(matches, "funding_keypair", String),
-        lamports: sol_to_lamports(value_t_or_exit!(matches, "amount", f64)),
+        lamports: lamports_of_sol(matches, "amount").unwrap(),
         base_keypair: value_t_or_exit!(matches, "base_keypair", String),
         stake_authority: value_t_or_exit!(matches, "stake_authority", String),
         withdraw_authority: value_t_or_exit!(matches, "withdraw_authority", String),
@@ -1,8 +1,10 @@
 use clap::{crate_description, crate_name, crate_version, value_t, value_t_or_exit, App, Arg};
 use log::*;
 use solana_clap_utils::{
-    input_parsers::{keypair_of, pubkey_of},
-    input_validators::{is_amount, is_keypair, is_pubkey_or_keypair, is_url, is_valid_percentage},
+    input_parsers::{keypair_of, lamports_of_sol, pubkey_of},
+    input_validators::{
+        is_keypair, is_pubkey_or_keypair, is_sol_amount, is_url, is_valid_percentage,
+    },
 };
 use solana_cli_output::display::format_labeled_address;
 use solana_client::{
@@ -162,7 +164,7 @@ fn get_config() -> Config {
                 .value_name("SOL")
                 .takes_value(true)
                 .default_value("5000")
-                .validator(is_amount)
+                .validator(is_sol_amount)
         )
         .arg(
             Arg::with_name("bonus_stake_amount")
@@ -170,7 +172,7 @@ fn get_config() -> Config {
                 .value_name("SOL")
                 .takes_value(true)
                 .default_value("50000")
-                .validator(is_amount)
+                .validator(is_sol_amount)
         )
         .arg(
             Arg::with_name("max_commission")
@@ -198,9 +200,8 @@ fn get_config() -> Config {
     let max_commission = value_t_or_exit!(matches, "max_commission", u8);
     let max_poor_block_producer_percentage =
         value_t_or_exit!(matches, "max_poor_block_producer_percentage", usize);
-    let baseline_stake_amount =
-        sol_to_lamports(value_t_or_exit!(matches, "baseline_stake_amount", f64));
-    let bonus_stake_amount = sol_to_lamports(value_t_or_exit!(matches, "bonus_stake_amount", f64));
+    let baseline_stake_amount = lamports_of_sol(&matches, "baseline_stake_amount").unwrap();
+    let bonus_stake_amount = lamports_of_sol(&matches, "bonus_stake_amount").unwrap();
 
     let (json_rpc_url, validator_list) = match cluster.as_str() {
         "mainnet-beta" => (
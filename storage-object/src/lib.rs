@@ -0,0 +1,394 @@
+//! Archives confirmed blocks to S3/GCS-compatible object storage, a self-hosted alternative to
+//! the BigTable ledger storage.
+//!
+//! Objects are laid out as:
+//!  * `blocks/<slot>`: a protobuf `ConfirmedBlock`, zstd compressed
+//!  * `manifests/<chunk first slot>`: JSON index of the blocks archived in a chunk of
+//!    `SLOTS_PER_MANIFEST` slots, with the size and SHA-256 of each block object
+//!  * `manifests/state`: JSON range of slots archived so far
+//!
+//! Block objects are always written before the manifest that references them, and the
+//! manifests before the state, so readers never see a slot that is not fully archived.
+use log::*;
+use prost::Message;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::clock::Slot;
+use solana_storage_proto::convert::generated;
+use solana_transaction_status::ConfirmedBlock;
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+mod object_storage;
+mod s3;
+
+pub use object_storage::ObjectStorage;
+
+pub const SLOTS_PER_MANIFEST: Slot = 10_000;
+const STATE_KEY: &str = "manifests/state";
+
+// The manifests read to serve blocks are cached for this long. RPC only reads blocks purged
+// from the blockstore, which were archived long before, so a stale manifest of the chunk
+// still being archived is harmless.
+const MANIFEST_CACHE_TTL: Duration = Duration::from_secs(60);
+const MAX_CACHED_MANIFESTS: usize = 64;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("I/O Error: {0}")]
+    IoError(std::io::Error),
+
+    #[error("S3: {0}")]
+    S3Error(String),
+
+    #[error("Invalid object storage url: {0}")]
+    InvalidUrl(String),
+
+    #[error("Missing credentials: {0} is not set")]
+    MissingCredentials(String),
+
+    #[error("Manifest error: {0}")]
+    ManifestError(serde_json::Error),
+
+    #[error("Failed to decode block {0}: {1}")]
+    BlockDecodeError(Slot, String),
+
+    #[error("Checksum mismatch for block {0}")]
+    ChecksumMismatch(Slot),
+}
+
+impl std::convert::From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(err)
+    }
+}
+
+impl std::convert::From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::ManifestError(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+// Zero padded so that the objects of a bucket list in slot order
+fn block_key(slot: Slot) -> String {
+    format!("blocks/{:020}", slot)
+}
+
+fn manifest_key(chunk_first_slot: Slot) -> String {
+    format!("manifests/{:020}", chunk_first_slot)
+}
+
+fn chunk_first_slot(slot: Slot) -> Slot {
+    slot - slot % SLOTS_PER_MANIFEST
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub key: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub first_slot: Slot,
+    pub blocks: BTreeMap<Slot, ManifestEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveState {
+    pub first_slot: Slot,
+    pub last_slot: Slot,
+}
+
+struct CachedManifest {
+    manifest: Arc<Manifest>,
+    fetched: Instant,
+}
+
+#[derive(Clone)]
+pub struct LedgerArchive {
+    storage: ObjectStorage,
+    manifest_cache: Arc<RwLock<HashMap<Slot, CachedManifest>>>,
+}
+
+impl LedgerArchive {
+    pub fn new(storage: ObjectStorage) -> Self {
+        Self {
+            storage,
+            manifest_cache: Arc::default(),
+        }
+    }
+
+    pub fn from_url(url: &str, timeout: Option<std::time::Duration>) -> Result<Self> {
+        Ok(Self::new(ObjectStorage::from_url(url, timeout)?))
+    }
+
+    /// Returns the range of slots archived so far
+    pub async fn get_state(&self) -> Result<Option<ArchiveState>> {
+        match self.storage.get(STATE_KEY).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn get_first_available_block(&self) -> Result<Option<Slot>> {
+        Ok(self.get_state().await?.map(|state| state.first_slot))
+    }
+
+    pub async fn get_manifest(&self, chunk_first_slot: Slot) -> Result<Option<Manifest>> {
+        match self.storage.get(&manifest_key(chunk_first_slot)).await? {
+            Some(data) => Ok(Some(serde_json::from_slice(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Like `get_manifest`, but served from the cache while it is fresh
+    async fn get_cached_manifest(&self, chunk_first_slot: Slot) -> Result<Option<Arc<Manifest>>> {
+        if let Some(cached) = self.manifest_cache.read().unwrap().get(&chunk_first_slot) {
+            if cached.fetched.elapsed() < MANIFEST_CACHE_TTL {
+                return Ok(Some(cached.manifest.clone()));
+            }
+        }
+        let manifest = match self.get_manifest(chunk_first_slot).await? {
+            Some(manifest) => Arc::new(manifest),
+            None => return Ok(None),
+        };
+        self.cache_manifest(chunk_first_slot, manifest.clone());
+        Ok(Some(manifest))
+    }
+
+    fn cache_manifest(&self, chunk_first_slot: Slot, manifest: Arc<Manifest>) {
+        let mut manifest_cache = self.manifest_cache.write().unwrap();
+        if manifest_cache.len() >= MAX_CACHED_MANIFESTS
+            && !manifest_cache.contains_key(&chunk_first_slot)
+        {
+            let oldest = manifest_cache
+                .iter()
+                .min_by_key(|(_, cached)| cached.fetched)
+                .map(|(chunk_first_slot, _)| *chunk_first_slot);
+            if let Some(oldest) = oldest {
+                manifest_cache.remove(&oldest);
+            }
+        }
+        manifest_cache.insert(
+            chunk_first_slot,
+            CachedManifest {
+                manifest,
+                fetched: Instant::now(),
+            },
+        );
+    }
+
+    /// Archives `blocks`, which must be ordered by slot
+    pub async fn upload_confirmed_blocks(&self, blocks: Vec<(Slot, ConfirmedBlock)>) -> Result<()> {
+        let (first_slot, last_slot) = match (blocks.first(), blocks.last()) {
+            (Some((first_slot, _)), Some((last_slot, _))) => (*first_slot, *last_slot),
+            _ => return Ok(()),
+        };
+
+        let mut manifests: BTreeMap<Slot, Manifest> = BTreeMap::new();
+        for (slot, block) in blocks {
+            let chunk_first_slot = chunk_first_slot(slot);
+            if !manifests.contains_key(&chunk_first_slot) {
+                let manifest = self
+                    .get_manifest(chunk_first_slot)
+                    .await?
+                    .unwrap_or_else(|| Manifest {
+                        first_slot: chunk_first_slot,
+                        ..Manifest::default()
+                    });
+                manifests.insert(chunk_first_slot, manifest);
+            }
+
+            let data = encode_block(block)?;
+            let entry = ManifestEntry {
+                key: block_key(slot),
+                size: data.len() as u64,
+                sha256: hex::encode(Sha256::digest(&data)),
+            };
+            self.storage.put(&entry.key, data).await?;
+            manifests
+                .get_mut(&chunk_first_slot)
+                .unwrap()
+                .blocks
+                .insert(slot, entry);
+        }
+
+        for (chunk_first_slot, manifest) in manifests {
+            self.storage
+                .put(
+                    &manifest_key(chunk_first_slot),
+                    serde_json::to_vec(&manifest)?,
+                )
+                .await?;
+            self.cache_manifest(chunk_first_slot, Arc::new(manifest));
+        }
+
+        let state = match self.get_state().await? {
+            Some(state) => ArchiveState {
+                first_slot: state.first_slot.min(first_slot),
+                last_slot: state.last_slot.max(last_slot),
+            },
+            None => ArchiveState {
+                first_slot,
+                last_slot,
+            },
+        };
+        self.storage
+            .put(STATE_KEY, serde_json::to_vec(&state)?)
+            .await?;
+        debug!("archived slots {} to {}", first_slot, last_slot);
+        Ok(())
+    }
+
+    /// Fetches the block archived for `slot`, verifying it against its manifest entry
+    pub async fn get_confirmed_block(&self, slot: Slot) -> Result<Option<ConfirmedBlock>> {
+        let entry = match self
+            .get_cached_manifest(chunk_first_slot(slot))
+            .await?
+            .and_then(|manifest| manifest.blocks.get(&slot).cloned())
+        {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let data = match self.storage.get(&entry.key).await? {
+            Some(data) => data,
+            None => {
+                warn!("Block {} is listed in its manifest but missing", slot);
+                return Ok(None);
+            }
+        };
+        if data.len() as u64 != entry.size || hex::encode(Sha256::digest(&data)) != entry.sha256 {
+            return Err(Error::ChecksumMismatch(slot));
+        }
+        decode_block(slot, &data).map(Some)
+    }
+
+    /// Returns up to `limit` archived slots, starting at `start_slot`
+    pub async fn get_confirmed_blocks(&self, start_slot: Slot, limit: usize) -> Result<Vec<Slot>> {
+        let last_slot = match self.get_state().await? {
+            Some(state) => state.last_slot,
+            None => return Ok(vec![]),
+        };
+        let mut slots = vec![];
+        let mut chunk_first_slot = chunk_first_slot(start_slot);
+        while slots.len() < limit && chunk_first_slot <= last_slot {
+            if let Some(manifest) = self.get_cached_manifest(chunk_first_slot).await? {
+                slots.extend(
+                    manifest
+                        .blocks
+                        .range(start_slot..)
+                        .map(|(slot, _)| *slot)
+                        .take(limit - slots.len()),
+                );
+            }
+            chunk_first_slot += SLOTS_PER_MANIFEST;
+        }
+        Ok(slots)
+    }
+}
+
+fn encode_block(block: ConfirmedBlock) -> Result<Vec<u8>> {
+    let block: generated::ConfirmedBlock = block.into();
+    let mut buf = Vec::with_capacity(block.encoded_len());
+    block.encode(&mut buf).unwrap();
+    Ok(zstd::stream::encode_all(buf.as_slice(), 0)?)
+}
+
+fn decode_block(slot: Slot, data: &[u8]) -> Result<ConfirmedBlock> {
+    let data = zstd::stream::decode_all(data)?;
+    let block = generated::ConfirmedBlock::decode(data.as_slice())
+        .map_err(|err| Error::BlockDecodeError(slot, err.to_string()))?;
+    ConfirmedBlock::try_from(block).map_err(|err| Error::BlockDecodeError(slot, err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+
+    fn block(slot: Slot) -> ConfirmedBlock {
+        ConfirmedBlock {
+            previous_blockhash: Hash::new_unique().to_string(),
+            blockhash: Hash::new_unique().to_string(),
+            parent_slot: slot.saturating_sub(1),
+            transactions: vec![],
+            rewards: vec![],
+            block_time: Some(slot as i64),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ledger_archive() {
+        let root = tempfile::tempdir().unwrap();
+        let archive = LedgerArchive::new(ObjectStorage::Filesystem(root.path().to_path_buf()));
+        assert_eq!(archive.get_state().await.unwrap(), None);
+        assert!(archive
+            .get_confirmed_blocks(0, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let slots = vec![5, SLOTS_PER_MANIFEST - 1, SLOTS_PER_MANIFEST + 2];
+        let blocks: Vec<_> = slots.iter().map(|slot| (*slot, block(*slot))).collect();
+        archive
+            .upload_confirmed_blocks(blocks.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            archive.get_state().await.unwrap(),
+            Some(ArchiveState {
+                first_slot: 5,
+                last_slot: SLOTS_PER_MANIFEST + 2,
+            })
+        );
+        for (slot, block) in &blocks {
+            assert_eq!(
+                archive.get_confirmed_block(*slot).await.unwrap().as_ref(),
+                Some(block)
+            );
+        }
+        assert_eq!(archive.get_confirmed_block(6).await.unwrap(), None);
+
+        assert_eq!(archive.get_confirmed_blocks(0, 10).await.unwrap(), slots);
+        assert_eq!(
+            archive.get_confirmed_blocks(6, 1).await.unwrap(),
+            vec![SLOTS_PER_MANIFEST - 1]
+        );
+
+        // A later upload extends the existing manifest
+        let block_7 = block(7);
+        archive
+            .upload_confirmed_blocks(vec![(7, block_7.clone())])
+            .await
+            .unwrap();
+        assert_eq!(
+            archive.get_manifest(0).await.unwrap().unwrap().blocks.len(),
+            3
+        );
+        assert_eq!(archive.get_first_available_block().await.unwrap(), Some(5));
+
+        // Reads are served from the cached manifests
+        std::fs::remove_file(root.path().join(manifest_key(0))).unwrap();
+        assert_eq!(archive.get_confirmed_block(7).await.unwrap(), Some(block_7));
+        assert_eq!(archive.get_manifest(0).await.unwrap(), None);
+
+        // Corrupted block objects are detected
+        std::fs::write(root.path().join(block_key(5)), b"corrupt").unwrap();
+        assert!(matches!(
+            archive.get_confirmed_block(5).await,
+            Err(Error::ChecksumMismatch(5))
+        ));
+    }
+}
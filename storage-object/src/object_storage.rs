@@ -0,0 +1,135 @@
+use crate::{
+    s3::{Credentials, S3Client},
+    Error, Result,
+};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// A flat key/value object store
+#[derive(Clone)]
+pub enum ObjectStorage {
+    /// Objects are files below a local directory, useful for testing and for archives kept on a
+    /// network filesystem
+    Filesystem(PathBuf),
+    /// Objects are stored in an S3-compatible bucket, below `prefix`
+    S3 { client: S3Client, prefix: String },
+}
+
+impl ObjectStorage {
+    /// Opens the object storage at `url`:
+    ///  * `file:///path/to/archive`
+    ///  * `s3://bucket[/prefix][?endpoint=https://host[:port]&region=region]`, with the
+    ///    credentials read from `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`. Google Cloud
+    ///    Storage is accessed through its interoperability endpoint,
+    ///    `endpoint=https://storage.googleapis.com` with HMAC keys
+    pub fn from_url(url: &str, timeout: Option<Duration>) -> Result<Self> {
+        let parsed = url::Url::parse(url).map_err(|err| Error::InvalidUrl(err.to_string()))?;
+        match parsed.scheme() {
+            "file" => Ok(Self::Filesystem(PathBuf::from(parsed.path()))),
+            "s3" => {
+                let bucket = parsed
+                    .host_str()
+                    .ok_or_else(|| Error::InvalidUrl(format!("{}: missing bucket", url)))?;
+                let query_param = |name: &str| {
+                    parsed
+                        .query_pairs()
+                        .find(|(key, _)| key == name)
+                        .map(|(_, value)| value.into_owned())
+                };
+                let client = S3Client::new(
+                    query_param("endpoint").as_deref(),
+                    query_param("region").as_deref(),
+                    bucket,
+                    Credentials::from_env()?,
+                    timeout,
+                )?;
+                Ok(Self::S3 {
+                    client,
+                    prefix: parsed.path().trim_matches('/').to_string(),
+                })
+            }
+            scheme => Err(Error::InvalidUrl(format!(
+                "{}: unsupported scheme {}",
+                url, scheme
+            ))),
+        }
+    }
+
+    fn s3_key(prefix: &str, key: &str) -> String {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", prefix, key)
+        }
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        match self {
+            Self::Filesystem(root) => {
+                let path = root.join(key);
+                tokio::task::spawn_blocking(move || write_atomically(&path, &data))
+                    .await
+                    .map_err(|err| Error::IoError(std::io::Error::new(ErrorKind::Other, err)))?
+            }
+            Self::S3 { client, prefix } => client.put(&Self::s3_key(prefix, key), data).await,
+        }
+    }
+
+    /// Returns the object stored at `key`, or `None` if there is none
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::Filesystem(root) => {
+                let path = root.join(key);
+                tokio::task::spawn_blocking(move || match fs::read(path) {
+                    Ok(data) => Ok(Some(data)),
+                    Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+                    Err(err) => Err(err.into()),
+                })
+                .await
+                .map_err(|err| Error::IoError(std::io::Error::new(ErrorKind::Other, err)))?
+            }
+            Self::S3 { client, prefix } => client.get(&Self::s3_key(prefix, key)).await,
+        }
+    }
+}
+
+// Readers never observe a partially written object
+fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, data)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_url() {
+        match ObjectStorage::from_url("file:///tmp/archive", None).unwrap() {
+            ObjectStorage::Filesystem(path) => assert_eq!(path, PathBuf::from("/tmp/archive")),
+            _ => panic!("expected a filesystem storage"),
+        }
+        assert!(ObjectStorage::from_url("ftp://host/archive", None).is_err());
+        assert!(ObjectStorage::from_url("archive", None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_filesystem_put_get() {
+        let root = tempfile::tempdir().unwrap();
+        let storage = ObjectStorage::Filesystem(root.path().to_path_buf());
+        assert_eq!(storage.get("blocks/1").await.unwrap(), None);
+        storage.put("blocks/1", vec![1, 2, 3]).await.unwrap();
+        assert_eq!(storage.get("blocks/1").await.unwrap(), Some(vec![1, 2, 3]));
+        storage.put("blocks/1", vec![4]).await.unwrap();
+        assert_eq!(storage.get("blocks/1").await.unwrap(), Some(vec![4]));
+    }
+}
@@ -0,0 +1,235 @@
+//! Minimal client for S3-compatible object stores (AWS S3, GCS interoperability mode, MinIO,
+//! Ceph...), signing path-style requests with AWS Signature Version 4
+use crate::{Error, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac, NewMac};
+use log::*;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+const SERVICE: &str = "s3";
+const DEFAULT_REGION: &str = "us-east-1";
+const DEFAULT_ENDPOINT: &str = "https://s3.amazonaws.com";
+
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+impl Credentials {
+    /// Loads the credentials from the `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`
+    /// environment variables
+    pub fn from_env() -> Result<Self> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| Error::MissingCredentials(name.to_string()))
+        };
+        Ok(Self {
+            access_key_id: var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: var("AWS_SECRET_ACCESS_KEY")?,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct S3Client {
+    client: reqwest::Client,
+    endpoint: url::Url,
+    region: String,
+    bucket: String,
+    credentials: Credentials,
+}
+
+impl S3Client {
+    pub fn new(
+        endpoint: Option<&str>,
+        region: Option<&str>,
+        bucket: &str,
+        credentials: Credentials,
+        timeout: Option<Duration>,
+    ) -> Result<Self> {
+        let endpoint = url::Url::parse(endpoint.unwrap_or(DEFAULT_ENDPOINT))
+            .map_err(|err| Error::InvalidUrl(err.to_string()))?;
+        if endpoint.host_str().is_none() {
+            return Err(Error::InvalidUrl(format!("{}: missing host", endpoint)));
+        }
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        Ok(Self {
+            client: builder
+                .build()
+                .map_err(|err| Error::S3Error(err.to_string()))?,
+            endpoint,
+            region: region.unwrap_or(DEFAULT_REGION).to_string(),
+            bucket: bucket.to_string(),
+            credentials,
+        })
+    }
+
+    fn url(&self, key: &str) -> url::Url {
+        let mut url = self.endpoint.clone();
+        url.set_path(&format!("/{}/{}", self.bucket, key));
+        url
+    }
+
+    fn sign(
+        &self,
+        request: reqwest::RequestBuilder,
+        method: &str,
+        url: &url::Url,
+        payload_hash: &str,
+        now: DateTime<Utc>,
+    ) -> reqwest::RequestBuilder {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap(), port),
+            None => url.host_str().unwrap().to_string(),
+        };
+
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method,
+            url.path(),
+            host,
+            payload_hash,
+            amz_date,
+            signed_headers,
+            payload_hash
+        );
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, SERVICE);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+        let signing_key = signing_key(
+            &self.credentials.secret_access_key,
+            &date,
+            &self.region,
+            SERVICE,
+        );
+        let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+        request
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                    self.credentials.access_key_id, scope, signed_headers, signature
+                ),
+            )
+    }
+
+    pub async fn put(&self, key: &str, data: Vec<u8>) -> Result<()> {
+        let url = self.url(key);
+        let payload_hash = hex::encode(Sha256::digest(&data));
+        let request = self.client.put(url.clone()).body(data);
+        let response = self
+            .sign(request, "PUT", &url, &payload_hash, Utc::now())
+            .send()
+            .await
+            .map_err(|err| Error::S3Error(err.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Error::S3Error(format!(
+                "PUT {} failed: {}",
+                key,
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let url = self.url(key);
+        let payload_hash = hex::encode(Sha256::digest(&[]));
+        let request = self.client.get(url.clone());
+        let response = self
+            .sign(request, "GET", &url, &payload_hash, Utc::now())
+            .send()
+            .await
+            .map_err(|err| Error::S3Error(err.to_string()))?;
+        match response.status() {
+            reqwest::StatusCode::NOT_FOUND => {
+                trace!("s3: {} not found", key);
+                Ok(None)
+            }
+            status if status.is_success() => Ok(Some(
+                response
+                    .bytes()
+                    .await
+                    .map_err(|err| Error::S3Error(err.to_string()))?
+                    .to_vec(),
+            )),
+            status => Err(Error::S3Error(format!("GET {} failed: {}", key, status))),
+        }
+    }
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("hmac accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let key = hmac_sha256(
+        format!("AWS4{}", secret_access_key).as_bytes(),
+        date.as_bytes(),
+    );
+    let key = hmac_sha256(&key, region.as_bytes());
+    let key = hmac_sha256(&key, service.as_bytes());
+    hmac_sha256(&key, b"aws4_request")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_key() {
+        // Example from the AWS "Examples of how to derive a signing key" documentation
+        assert_eq!(
+            hex::encode(signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn test_url() {
+        let client = S3Client::new(
+            Some("http://127.0.0.1:9000"),
+            None,
+            "ledger",
+            Credentials {
+                access_key_id: "id".to_string(),
+                secret_access_key: "secret".to_string(),
+            },
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            client.url("mainnet/blocks/00000000000000000042").as_str(),
+            "http://127.0.0.1:9000/ledger/mainnet/blocks/00000000000000000042"
+        );
+        assert!(S3Client::new(
+            Some("not a url"),
+            None,
+            "ledger",
+            client.credentials.clone(),
+            None
+        )
+        .is_err());
+    }
+}
@@ -95,6 +95,8 @@ pub struct TokenBalance {
     pub mint: std::string::String,
     #[prost(message, optional, tag = "3")]
     pub ui_token_amount: ::std::option::Option<UiTokenAmount>,
+    #[prost(string, tag = "4")]
+    pub owner: std::string::String,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UiTokenAmount {
@@ -114,4 +114,5 @@ pub enum InstructionErrorType {
     ProgramFailedToCompile = 41,
     Immutable = 42,
     IncorrectAuthority = 43,
+    UnsupportedSysvar = 44,
 }
@@ -387,6 +387,7 @@ impl From<TransactionTokenBalance> for generated::TokenBalance {
                 decimals: value.ui_token_amount.decimals as u32,
                 amount: value.ui_token_amount.amount,
             }),
+            owner: value.owner,
         }
     }
 }
@@ -402,6 +403,7 @@ impl From<generated::TokenBalance> for TransactionTokenBalance {
                 decimals: ui_token_amount.decimals as u8,
                 amount: ui_token_amount.amount,
             },
+            owner: value.owner,
         }
     }
 }
@@ -483,6 +485,7 @@ impl TryFrom<tx_by_addr::TransactionError> for TransactionError {
                     41 => InstructionError::ProgramFailedToCompile,
                     42 => InstructionError::Immutable,
                     43 => InstructionError::IncorrectAuthority,
+                    44 => InstructionError::UnsupportedSysvar,
                     _ => return Err("Invalid InstructionError"),
                 };
 
@@ -700,6 +703,9 @@ impl From<TransactionError> for tx_by_addr::TransactionError {
                             InstructionError::IncorrectAuthority => {
                                 tx_by_addr::InstructionErrorType::IncorrectAuthority
                             }
+                            InstructionError::UnsupportedSysvar => {
+                                tx_by_addr::InstructionErrorType::UnsupportedSysvar
+                            }
                         } as i32,
                         custom: match instruction_error {
                             InstructionError::Custom(custom) => {
@@ -1115,6 +1121,15 @@ mod test {
             tx_by_addr_transaction_error.try_into().unwrap()
         );
 
+        let transaction_error =
+            TransactionError::InstructionError(10, InstructionError::UnsupportedSysvar);
+        let tx_by_addr_transaction_error: tx_by_addr::TransactionError =
+            transaction_error.clone().into();
+        assert_eq!(
+            transaction_error,
+            tx_by_addr_transaction_error.try_into().unwrap()
+        );
+
         let transaction_error =
             TransactionError::InstructionError(10, InstructionError::IncorrectProgramId);
         let tx_by_addr_transaction_error: tx_by_addr::TransactionError =
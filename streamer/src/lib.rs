@@ -1,3 +1,5 @@
+#[cfg(feature = "tokio")]
+pub mod nonblocking;
 pub mod packet;
 pub mod recvmmsg;
 pub mod sendmmsg;
@@ -0,0 +1,196 @@
+//! The `nonblocking` module provides async variants of the `streamer` receiver and responder,
+//! built on tokio UDP sockets, for services that want packet batching without dedicating an OS
+//! thread to each socket.
+//!
+
+use crate::packet::{Packet, Packets, PacketsRecycler, PACKETS_PER_BATCH};
+use solana_sdk::timing::timestamp;
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    net::UdpSocket,
+    sync::mpsc::{UnboundedReceiver, UnboundedSender},
+    time::timeout,
+};
+
+pub type AsyncPacketReceiver = UnboundedReceiver<Packets>;
+pub type AsyncPacketSender = UnboundedSender<Packets>;
+
+/// Wraps a std socket for use with the async receiver and responder, must be called from within
+/// a tokio runtime
+pub fn udp_socket_from_std(socket: std::net::UdpSocket) -> io::Result<UdpSocket> {
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket)
+}
+
+/// Waits for a packet, then keeps filling `obj` with the packets that arrive within `max_wait`
+/// or until the batch is full
+pub async fn recv_from(
+    obj: &mut Packets,
+    socket: &UdpSocket,
+    max_wait: Duration,
+) -> io::Result<usize> {
+    obj.packets.resize(PACKETS_PER_BATCH, Packet::default());
+    let mut i = 0;
+    let (nrecv, from) = socket.recv_from(&mut obj.packets[i].data).await?;
+    obj.packets[i].meta.size = nrecv;
+    obj.packets[i].meta.set_addr(&from);
+    i += 1;
+
+    let start = Instant::now();
+    while i < PACKETS_PER_BATCH {
+        let remaining = match max_wait.checked_sub(start.elapsed()) {
+            Some(remaining) => remaining,
+            None => break,
+        };
+        match timeout(remaining, socket.recv_from(&mut obj.packets[i].data)).await {
+            Ok(Ok((nrecv, from))) => {
+                obj.packets[i].meta.size = nrecv;
+                obj.packets[i].meta.set_addr(&from);
+                i += 1;
+            }
+            Ok(Err(err)) => {
+                trace!("recv_from err {:?}", err);
+                break;
+            }
+            Err(_) => break,
+        }
+    }
+    obj.packets.truncate(i);
+    inc_new_counter_debug!("packets-recv_count", i);
+    Ok(i)
+}
+
+pub async fn send_to(obj: &Packets, socket: &UdpSocket) -> io::Result<()> {
+    for p in &obj.packets {
+        let a = p.meta.addr();
+        socket.send_to(&p.data[..p.meta.size], a).await?;
+    }
+    Ok(())
+}
+
+/// Async counterpart of `streamer::receiver`, returns once `exit` is set or the receiving end
+/// of `packet_sender` is dropped
+pub async fn receiver(
+    socket: Arc<UdpSocket>,
+    exit: Arc<AtomicBool>,
+    packet_sender: AsyncPacketSender,
+    recycler: PacketsRecycler,
+    name: &'static str,
+) {
+    let mut recv_count = 0;
+    let mut call_count = 0;
+    let mut now = Instant::now();
+    while !exit.load(Ordering::Relaxed) {
+        let mut msgs = Packets::new_with_recycler(recycler.clone(), PACKETS_PER_BATCH, name);
+        // Wake up periodically to check for the exit signal on idle sockets
+        match timeout(
+            Duration::from_secs(1),
+            recv_from(&mut msgs, &socket, Duration::from_millis(1)),
+        )
+        .await
+        {
+            Ok(Ok(len)) => {
+                recv_count += len;
+                call_count += 1;
+                if packet_sender.send(msgs).is_err() {
+                    return;
+                }
+            }
+            Ok(Err(err)) => trace!("{} recv error: {:?}", name, err),
+            Err(_) => {}
+        }
+        if recv_count > 1024 {
+            datapoint_debug!(
+                name,
+                ("received", recv_count as i64, i64),
+                ("call_count", i64::from(call_count), i64),
+                ("elapsed", now.elapsed().as_millis() as i64, i64),
+            );
+            recv_count = 0;
+            call_count = 0;
+            now = Instant::now();
+        }
+    }
+}
+
+/// Async counterpart of `streamer::responder`, returns once every sender of `r` is dropped
+pub async fn responder(name: &'static str, socket: Arc<UdpSocket>, mut r: AsyncPacketReceiver) {
+    let mut errors = 0;
+    let mut last_error = None;
+    let mut last_print = 0;
+    while let Some(msgs) = r.recv().await {
+        if let Err(err) = send_to(&msgs, &socket).await {
+            errors += 1;
+            last_error = Some(err);
+        }
+        let now = timestamp();
+        if now - last_print > 1000 && errors != 0 {
+            datapoint_info!(name, ("errors", errors, i64),);
+            info!("{} last-error: {:?} count: {}", name, last_error, errors);
+            last_print = now;
+            errors = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::packet::PACKET_DATA_SIZE;
+    use solana_perf::recycler::Recycler;
+    use tokio::sync::mpsc::unbounded_channel;
+
+    #[tokio::test]
+    async fn streamer_send_test() {
+        let read = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let addr = read.local_addr().unwrap();
+        let send = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let exit = Arc::new(AtomicBool::new(false));
+
+        let (s_reader, mut r_reader) = unbounded_channel();
+        let t_receiver = tokio::spawn(receiver(
+            read,
+            exit.clone(),
+            s_reader,
+            Recycler::default(),
+            "test",
+        ));
+        let (s_responder, r_responder) = unbounded_channel();
+        let t_responder = tokio::spawn(responder("streamer_send_test", send, r_responder));
+
+        let mut msgs = Packets::default();
+        for i in 0..5 {
+            let mut b = Packet::default();
+            b.data[0] = i as u8;
+            b.meta.size = PACKET_DATA_SIZE;
+            b.meta.set_addr(&addr);
+            msgs.packets.push(b);
+        }
+        s_responder.send(msgs).unwrap();
+
+        let mut num = 5;
+        while num > 0 {
+            let msgs = timeout(Duration::from_secs(10), r_reader.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            for p in &msgs.packets {
+                assert_eq!(p.meta.size, PACKET_DATA_SIZE);
+                assert_eq!(p.data[0], 5 - num as u8);
+                num -= 1;
+            }
+        }
+
+        exit.store(true, Ordering::Relaxed);
+        drop(s_responder);
+        t_receiver.await.unwrap();
+        t_responder.await.unwrap();
+    }
+}
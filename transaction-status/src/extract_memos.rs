@@ -0,0 +1,80 @@
+use crate::parse_instruction::MEMO_PROGRAM_ID;
+use solana_sdk::message::Message;
+
+/// Collects the data of every spl-memo instruction in `message` into a single string, with each
+/// memo prefixed by its length, eg. `[5] hello; [5] world`. Returns `None` if the message
+/// contains no memo instructions.
+pub fn extract_and_fmt_memos(message: &Message) -> Option<String> {
+    let memos = extract_memos(message);
+    if memos.is_empty() {
+        None
+    } else {
+        Some(memos.join("; "))
+    }
+}
+
+fn extract_memos(message: &Message) -> Vec<String> {
+    let mut memos = vec![];
+    if message.account_keys.contains(&MEMO_PROGRAM_ID) {
+        for instruction in &message.instructions {
+            let program_id = message.account_keys[instruction.program_id_index as usize];
+            if program_id == *MEMO_PROGRAM_ID {
+                memos.push(fmt_memo_data(&instruction.data));
+            }
+        }
+    }
+    memos
+}
+
+fn fmt_memo_data(data: &[u8]) -> String {
+    let memo_len = data.len();
+    let parsed_memo = String::from_utf8_lossy(data);
+    format!("[{}] {}", memo_len, parsed_memo)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_sdk::{
+        hash::Hash,
+        instruction::{CompiledInstruction, Instruction},
+        message::MessageHeader,
+        pubkey::Pubkey,
+    };
+
+    #[test]
+    fn test_extract_and_fmt_memos() {
+        let memo0 = "Test memo";
+        let memo1 = "🦖";
+        let expected_memos = format!("[{}] {}; [{}] {}", memo0.len(), memo0, memo1.len(), memo1);
+        let memo_instructions = vec![
+            CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![],
+                data: memo0.as_bytes().to_vec(),
+            },
+            CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![],
+                data: memo1.as_bytes().to_vec(),
+            },
+        ];
+        let message = Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys: vec![Pubkey::new_unique(), *MEMO_PROGRAM_ID],
+            recent_blockhash: Hash::default(),
+            instructions: memo_instructions,
+        };
+        assert_eq!(extract_and_fmt_memos(&message), Some(expected_memos));
+
+        let message = Message::new(
+            &[Instruction::new(Pubkey::new_unique(), &0u8, vec![])],
+            None,
+        );
+        assert_eq!(extract_and_fmt_memos(&message), None);
+    }
+}
@@ -3,6 +3,7 @@ extern crate lazy_static;
 #[macro_use]
 extern crate serde_derive;
 
+pub mod extract_memos;
 pub mod parse_accounts;
 pub mod parse_bpf_loader;
 pub mod parse_instruction;
@@ -17,12 +18,13 @@ use crate::{
     parse_instruction::{parse, ParsedInstruction},
 };
 use solana_account_decoder::parse_token::UiTokenAmount;
+use solana_runtime::bank::InnerInstructionsList;
 pub use solana_runtime::bank::RewardType;
 use solana_sdk::{
     clock::{Slot, UnixTimestamp},
     commitment_config::CommitmentConfig,
     deserialize_utils::default_on_eof,
-    instruction::CompiledInstruction,
+    instruction::{CompiledInstruction, InstructionError},
     message::{Message, MessageHeader},
     pubkey::Pubkey,
     sanitize::Sanitize,
@@ -122,6 +124,10 @@ pub struct TransactionTokenBalance {
     pub account_index: u8,
     pub mint: String,
     pub ui_token_amount: UiTokenAmount,
+    /// Owner of the token account, empty for balances recorded before owners were.  Serialized
+    /// apart from the balance, see `StoredTransactionStatusMeta`
+    #[serde(skip)]
+    pub owner: String,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -130,6 +136,8 @@ pub struct UiTransactionTokenBalance {
     pub account_index: u8,
     pub mint: String,
     pub ui_token_amount: UiTokenAmount,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub owner: String,
 }
 
 impl From<TransactionTokenBalance> for UiTransactionTokenBalance {
@@ -138,12 +146,28 @@ impl From<TransactionTokenBalance> for UiTransactionTokenBalance {
             account_index: token_balance.account_index,
             mint: token_balance.mint,
             ui_token_amount: token_balance.ui_token_amount,
+            owner: token_balance.owner,
         }
     }
 }
 
+/// Pairs the inner instructions recorded during execution with the index of the transaction
+/// instruction that invoked them, dropping instructions that made no cross-program invocations
+pub fn map_inner_instructions(
+    inner_instructions: InnerInstructionsList,
+) -> impl Iterator<Item = InnerInstructions> {
+    inner_instructions
+        .into_iter()
+        .enumerate()
+        .map(|(index, instructions)| InnerInstructions {
+            index: index as u8,
+            instructions,
+        })
+        .filter(|i| !i.instructions.is_empty())
+}
+
 impl UiInnerInstructions {
-    fn parse(inner_instructions: InnerInstructions, message: &Message) -> Self {
+    pub fn parse(inner_instructions: InnerInstructions, message: &Message) -> Self {
         Self {
             index: inner_instructions.index,
             instructions: inner_instructions
@@ -169,22 +193,106 @@ impl From<InnerInstructions> for UiInnerInstructions {
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(
+    from = "StoredTransactionStatusMeta",
+    into = "StoredTransactionStatusMeta"
+)]
 pub struct TransactionStatusMeta {
     pub status: Result<()>,
     pub fee: u64,
     pub pre_balances: Vec<u64>,
     pub post_balances: Vec<u64>,
-    #[serde(deserialize_with = "default_on_eof")]
     pub inner_instructions: Option<Vec<InnerInstructions>>,
-    #[serde(deserialize_with = "default_on_eof")]
     pub log_messages: Option<Vec<String>>,
-    #[serde(deserialize_with = "default_on_eof")]
     pub pre_token_balances: Option<Vec<TransactionTokenBalance>>,
-    #[serde(deserialize_with = "default_on_eof")]
     pub post_token_balances: Option<Vec<TransactionTokenBalance>>,
 }
 
+/// Serialized layout of `TransactionStatusMeta`, as persisted in the blockstore. Fields are
+/// only ever appended, so the owners of the token balances trail the meta rather than extend
+/// each balance, which would break the decoding of previously stored balances
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredTransactionStatusMeta {
+    status: Result<()>,
+    fee: u64,
+    pre_balances: Vec<u64>,
+    post_balances: Vec<u64>,
+    #[serde(deserialize_with = "default_on_eof")]
+    inner_instructions: Option<Vec<InnerInstructions>>,
+    #[serde(deserialize_with = "default_on_eof")]
+    log_messages: Option<Vec<String>>,
+    #[serde(deserialize_with = "default_on_eof")]
+    pre_token_balances: Option<Vec<TransactionTokenBalance>>,
+    #[serde(deserialize_with = "default_on_eof")]
+    post_token_balances: Option<Vec<TransactionTokenBalance>>,
+    #[serde(deserialize_with = "default_on_eof")]
+    pre_token_balance_owners: Option<Vec<String>>,
+    #[serde(deserialize_with = "default_on_eof")]
+    post_token_balance_owners: Option<Vec<String>>,
+}
+
+fn token_balance_owners(balances: &Option<Vec<TransactionTokenBalance>>) -> Option<Vec<String>> {
+    balances.as_ref().map(|balances| {
+        balances
+            .iter()
+            .map(|balance| balance.owner.clone())
+            .collect()
+    })
+}
+
+fn with_token_balance_owners(
+    balances: Option<Vec<TransactionTokenBalance>>,
+    owners: Option<Vec<String>>,
+) -> Option<Vec<TransactionTokenBalance>> {
+    balances.map(|mut balances| {
+        if let Some(owners) = owners.filter(|owners| owners.len() == balances.len()) {
+            for (balance, owner) in balances.iter_mut().zip(owners) {
+                balance.owner = owner;
+            }
+        }
+        balances
+    })
+}
+
+impl From<StoredTransactionStatusMeta> for TransactionStatusMeta {
+    fn from(meta: StoredTransactionStatusMeta) -> Self {
+        Self {
+            status: meta.status,
+            fee: meta.fee,
+            pre_balances: meta.pre_balances,
+            post_balances: meta.post_balances,
+            inner_instructions: meta.inner_instructions,
+            log_messages: meta.log_messages,
+            pre_token_balances: with_token_balance_owners(
+                meta.pre_token_balances,
+                meta.pre_token_balance_owners,
+            ),
+            post_token_balances: with_token_balance_owners(
+                meta.post_token_balances,
+                meta.post_token_balance_owners,
+            ),
+        }
+    }
+}
+
+impl From<TransactionStatusMeta> for StoredTransactionStatusMeta {
+    fn from(meta: TransactionStatusMeta) -> Self {
+        Self {
+            pre_token_balance_owners: token_balance_owners(&meta.pre_token_balances),
+            post_token_balance_owners: token_balance_owners(&meta.post_token_balances),
+            status: meta.status,
+            fee: meta.fee,
+            pre_balances: meta.pre_balances,
+            post_balances: meta.post_balances,
+            inner_instructions: meta.inner_instructions,
+            log_messages: meta.log_messages,
+            pre_token_balances: meta.pre_token_balances,
+            post_token_balances: meta.post_token_balances,
+        }
+    }
+}
+
 impl Default for TransactionStatusMeta {
     fn default() -> Self {
         Self {
@@ -213,11 +321,18 @@ pub struct UiTransactionStatusMeta {
     pub log_messages: Option<Vec<String>>,
     pub pre_token_balances: Option<Vec<UiTransactionTokenBalance>>,
     pub post_token_balances: Option<Vec<UiTransactionTokenBalance>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_instruction: Option<UiFailedInstruction>,
 }
 
 impl UiTransactionStatusMeta {
     fn parse(meta: TransactionStatusMeta, message: &Message) -> Self {
         Self {
+            failed_instruction: meta
+                .status
+                .as_ref()
+                .err()
+                .and_then(|err| UiFailedInstruction::new(err, message)),
             err: meta.status.clone().err(),
             status: meta.status,
             fee: meta.fee,
@@ -257,10 +372,56 @@ impl From<TransactionStatusMeta> for UiTransactionStatusMeta {
             post_token_balances: meta
                 .post_token_balances
                 .map(|balance| balance.into_iter().map(|balance| balance.into()).collect()),
+            failed_instruction: None,
         }
     }
 }
 
+/// The instruction a transaction failed in, which `TransactionError` only identifies by index
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UiFailedInstruction {
+    /// Index of the instruction in the transaction
+    pub index: u8,
+    /// Program invoked by the instruction, as base-58 encoded string
+    pub program_id: String,
+    /// Raw error code returned by the program, for custom program errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_code: Option<u32>,
+}
+
+impl UiFailedInstruction {
+    /// Returns the failed instruction of `message` if `err` is an instruction error
+    pub fn new(err: &TransactionError, message: &Message) -> Option<Self> {
+        if let TransactionError::InstructionError(index, instruction_error) = err {
+            let instruction = message.instructions.get(*index as usize)?;
+            let program_id = message
+                .account_keys
+                .get(instruction.program_id_index as usize)?;
+            Some(Self {
+                index: *index,
+                program_id: program_id.to_string(),
+                custom_code: match instruction_error {
+                    InstructionError::Custom(code) => Some(*code),
+                    _ => None,
+                },
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for UiFailedInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "program {}", self.program_id)?;
+        if let Some(custom_code) = self.custom_code {
+            write!(f, ", error code {}", custom_code)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum TransactionConfirmationStatus {
@@ -465,7 +626,17 @@ impl TransactionStatusMeta {
     fn encode(self, encoding: UiTransactionEncoding, message: &Message) -> UiTransactionStatusMeta {
         match encoding {
             UiTransactionEncoding::JsonParsed => UiTransactionStatusMeta::parse(self, message),
-            _ => self.into(),
+            _ => {
+                let failed_instruction = self
+                    .status
+                    .as_ref()
+                    .err()
+                    .and_then(|err| UiFailedInstruction::new(err, message));
+                UiTransactionStatusMeta {
+                    failed_instruction,
+                    ..self.into()
+                }
+            }
         }
     }
 }
@@ -591,6 +762,67 @@ pub struct TransactionByAddrInfo {
 #[cfg(test)]
 mod test {
     use super::*;
+    use solana_sdk::instruction::Instruction;
+
+    fn token_balance(account_index: u8, owner: &str) -> TransactionTokenBalance {
+        TransactionTokenBalance {
+            account_index,
+            mint: "mint".to_string(),
+            ui_token_amount: UiTokenAmount {
+                ui_amount: 1.5,
+                decimals: 2,
+                amount: "150".to_string(),
+            },
+            owner: owner.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_transaction_status_meta_token_balance_owners() {
+        let meta = TransactionStatusMeta {
+            pre_token_balances: Some(vec![token_balance(1, "alice"), token_balance(2, "bob")]),
+            post_token_balances: Some(vec![token_balance(1, "alice"), token_balance(2, "bob")]),
+            ..TransactionStatusMeta::default()
+        };
+        let serialized = bincode::serialize(&meta).unwrap();
+        assert_eq!(
+            bincode::deserialize::<TransactionStatusMeta>(&serialized).unwrap(),
+            meta
+        );
+
+        // Metas stored before token balance owners were recorded still decode, without owners
+        #[derive(Serialize)]
+        struct LegacyTransactionStatusMeta {
+            status: Result<()>,
+            fee: u64,
+            pre_balances: Vec<u64>,
+            post_balances: Vec<u64>,
+            inner_instructions: Option<Vec<InnerInstructions>>,
+            log_messages: Option<Vec<String>>,
+            pre_token_balances: Option<Vec<TransactionTokenBalance>>,
+            post_token_balances: Option<Vec<TransactionTokenBalance>>,
+        }
+        let legacy = LegacyTransactionStatusMeta {
+            status: Ok(()),
+            fee: 0,
+            pre_balances: vec![],
+            post_balances: vec![],
+            inner_instructions: None,
+            log_messages: None,
+            pre_token_balances: meta.pre_token_balances.clone(),
+            post_token_balances: meta.post_token_balances.clone(),
+        };
+        let serialized = bincode::serialize(&legacy).unwrap();
+        let deserialized = bincode::deserialize::<TransactionStatusMeta>(&serialized).unwrap();
+        assert_eq!(
+            deserialized.pre_token_balances,
+            Some(vec![token_balance(1, ""), token_balance(2, "")])
+        );
+        assert_eq!(
+            deserialized.post_token_balances,
+            Some(vec![token_balance(1, ""), token_balance(2, "")])
+        );
+    }
 
     #[test]
     fn test_decode_invalid_transaction() {
@@ -684,4 +916,85 @@ mod test {
         };
         assert!(status.satisfies_commitment(CommitmentConfig::confirmed()));
     }
+
+    #[test]
+    fn test_failed_instruction() {
+        let program_id = Pubkey::new_unique();
+        let message = Message::new(
+            &[
+                Instruction::new(Pubkey::new_unique(), &(), vec![]),
+                Instruction::new(program_id, &(), vec![]),
+            ],
+            Some(&Pubkey::new_unique()),
+        );
+        let meta = TransactionStatusMeta {
+            status: Err(TransactionError::InstructionError(
+                1,
+                InstructionError::Custom(6),
+            )),
+            ..TransactionStatusMeta::default()
+        };
+        let expected = UiFailedInstruction {
+            index: 1,
+            program_id: program_id.to_string(),
+            custom_code: Some(6),
+        };
+        assert_eq!(
+            meta.clone()
+                .encode(UiTransactionEncoding::Base64, &message)
+                .failed_instruction,
+            Some(expected.clone())
+        );
+        assert_eq!(
+            meta.encode(UiTransactionEncoding::JsonParsed, &message)
+                .failed_instruction,
+            Some(expected)
+        );
+
+        assert_eq!(
+            UiFailedInstruction::new(
+                &TransactionError::InstructionError(0, InstructionError::InvalidArgument),
+                &message
+            )
+            .unwrap()
+            .custom_code,
+            None
+        );
+        assert_eq!(
+            UiFailedInstruction::new(
+                &TransactionError::InstructionError(2, InstructionError::InvalidArgument),
+                &message
+            ),
+            None
+        );
+        assert_eq!(
+            UiFailedInstruction::new(&TransactionError::AccountNotFound, &message),
+            None
+        );
+    }
+
+    #[test]
+    fn test_map_inner_instructions() {
+        let instruction = |data: u8| CompiledInstruction::new(1, &data, vec![0]);
+        let inner_instructions = vec![
+            vec![],
+            vec![instruction(1), instruction(2)],
+            vec![],
+            vec![instruction(3)],
+        ];
+        assert_eq!(
+            map_inner_instructions(inner_instructions).collect::<Vec<_>>(),
+            vec![
+                InnerInstructions {
+                    index: 1,
+                    instructions: vec![instruction(1), instruction(2)],
+                },
+                InnerInstructions {
+                    index: 3,
+                    instructions: vec![instruction(3)],
+                },
+            ]
+        );
+        assert_eq!(map_inner_instructions(vec![vec![], vec![]]).count(), 0);
+    }
 }
@@ -73,13 +73,14 @@ pub fn collect_token_balances(
         let mut transaction_balances: Vec<TransactionTokenBalance> = vec![];
         for index in fetch_account_hash.keys() {
             if let Some(account_id) = account_keys.get(*index as usize) {
-                if let Some((mint, ui_token_amount)) =
+                if let Some((mint, ui_token_amount, owner)) =
                     collect_token_balance_from_account(&bank, account_id, &mut mint_decimals)
                 {
                     transaction_balances.push(TransactionTokenBalance {
                         account_index: *index,
                         mint,
                         ui_token_amount,
+                        owner,
                     });
                 }
             }
@@ -93,7 +94,7 @@ pub fn collect_token_balance_from_account(
     bank: &Bank,
     account_id: &Pubkey,
     mint_decimals: &mut HashMap<Pubkey, u8>,
-) -> Option<(String, UiTokenAmount)> {
+) -> Option<(String, UiTokenAmount, String)> {
     let account = bank.get_account(account_id)?;
 
     let token_account = TokenAccount::unpack(&account.data).ok()?;
@@ -109,5 +110,6 @@ pub fn collect_token_balance_from_account(
     Some((
         mint_string.to_string(),
         token_amount_to_ui_amount(token_account.amount, decimals),
+        token_account.owner.to_string(),
     ))
 }
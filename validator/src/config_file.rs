@@ -0,0 +1,311 @@
+//! Declarative validator configuration.
+//!
+//! A config file is a YAML or TOML mapping from the long name of a command-line argument to
+//! its value, for example:
+//!
+//! ```yaml
+//! identity: /home/sol/validator-keypair.json
+//! ledger: /mnt/ledger
+//! rpc-port: 8899
+//! no-voting: true
+//! known-validator:
+//!   - 7Np41oeYqPefeNQEHSv1UDhYrehxin3NStELsSKCT4K2
+//!   - GdnSyH3YtwcxFvQrVVJMm1JhTS4QVX7MFsX56uJLUfiZ
+//! ```
+//!
+//! Flags take `true` or `false`, and arguments which may be repeated take a list. Any
+//! argument can also be given by an environment variable named after it, e.g.
+//! `SOLANA_VALIDATOR_RPC_PORT=8899`. The command line takes precedence over the environment,
+//! which takes precedence over the config file.
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+pub const ENV_PREFIX: &str = "SOLANA_VALIDATOR_";
+
+// Arguments that locate the configuration itself, and are never read from it
+const CONFIG_ARGS: &[&str] = &["config", "dump-config"];
+
+// Short options of the validator and their long names
+const SHORT_ARGS: &[(&str, &str)] = &[
+    ("-i", "identity"),
+    ("-l", "ledger"),
+    ("-n", "entrypoint"),
+    ("-o", "log"),
+];
+
+/// Arguments by long name; a flag has no values.
+pub type ConfigArgs = BTreeMap<String, Vec<String>>;
+
+pub fn load_config_file<P: AsRef<Path>>(path: P) -> Result<ConfigArgs, String> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+    let is_toml = path
+        .extension()
+        .map_or(false, |extension| extension == "toml");
+    let config = if is_toml {
+        let value: toml::Value = toml::from_str(&contents)
+            .map_err(|err| format!("Unable to parse {}: {}", path.display(), err))?;
+        // Round trip through JSON so that both formats share one conversion
+        serde_json::to_value(value).map_err(|err| err.to_string())?
+    } else {
+        serde_yaml::from_str(&contents)
+            .map_err(|err| format!("Unable to parse {}: {}", path.display(), err))?
+    };
+    config_args_from_value(config).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+fn config_args_from_value(config: serde_json::Value) -> Result<ConfigArgs, String> {
+    use serde_json::Value;
+
+    let config = match config {
+        Value::Object(config) => config,
+        Value::Null => return Ok(ConfigArgs::new()),
+        _ => return Err("expected a mapping of argument names to values".to_string()),
+    };
+    let mut args = ConfigArgs::new();
+    for (name, value) in config {
+        let scalar = |value: &Value| match value {
+            Value::String(value) => Ok(value.clone()),
+            Value::Number(value) => Ok(value.to_string()),
+            _ => Err(format!("unsupported value for {}: {}", name, value)),
+        };
+        let values = match &value {
+            Value::Bool(false) => continue,
+            Value::Bool(true) | Value::Null => vec![],
+            Value::Array(values) => values.iter().map(scalar).collect::<Result<_, _>>()?,
+            value => vec![scalar(value)?],
+        };
+        args.insert(name, values);
+    }
+    Ok(args)
+}
+
+/// Collects the arguments given by `SOLANA_VALIDATOR_*` environment variables. A value of
+/// "true" sets a flag and "false" leaves it unset.
+pub fn env_config_args<I: IntoIterator<Item = (String, String)>>(vars: I) -> ConfigArgs {
+    vars.into_iter()
+        .filter_map(|(key, value)| {
+            let name = key
+                .strip_prefix(ENV_PREFIX)?
+                .to_lowercase()
+                .replace('_', "-");
+            match value.as_str() {
+                "false" => None,
+                "true" => Some((name, vec![])),
+                _ => Some((name, vec![value])),
+            }
+        })
+        .collect()
+}
+
+/// Collects the long and short options of a command line, without the program name.
+/// Positional values are attached to the option preceding them.
+pub fn command_line_config_args(args: &[String]) -> ConfigArgs {
+    let mut config = ConfigArgs::new();
+    let mut current = None;
+    for arg in args {
+        let name = if let Some(long) = arg.strip_prefix("--") {
+            Some(long)
+        } else {
+            SHORT_ARGS
+                .iter()
+                .find(|(short, _)| short == arg)
+                .map(|(_, long)| *long)
+        };
+        match name {
+            Some(name) => {
+                let (name, value) = match name.find('=') {
+                    Some(eq) => (&name[..eq], Some(name[eq + 1..].to_string())),
+                    None => (name, None),
+                };
+                let values = config.entry(name.to_string()).or_default();
+                values.extend(value);
+                current = Some(name.to_string());
+            }
+            None => {
+                if let Some(values) = current.as_ref().and_then(|name| config.get_mut(name)) {
+                    values.push(arg.clone());
+                }
+            }
+        }
+    }
+    config
+}
+
+/// Merges the arguments of each source, later sources replacing the values of an argument
+/// given by earlier ones.
+pub fn merge_config_args(sources: Vec<ConfigArgs>) -> ConfigArgs {
+    let mut config = ConfigArgs::new();
+    for source in sources {
+        config.extend(source);
+    }
+    for name in CONFIG_ARGS {
+        config.remove(*name);
+    }
+    config
+}
+
+/// Builds the command line equivalent to `config`, to be parsed along with the program name.
+pub fn config_args_to_command_line(config: &ConfigArgs) -> Vec<String> {
+    let mut args = vec![];
+    for (name, values) in config {
+        if values.is_empty() {
+            args.push(format!("--{}", name));
+        }
+        for value in values {
+            args.push(format!("--{}", name));
+            args.push(value.clone());
+        }
+    }
+    args
+}
+
+/// Renders `config` as a YAML config file.
+pub fn dump_config(config: &ConfigArgs) -> String {
+    use serde_yaml::Value;
+
+    let config: BTreeMap<&String, Value> = config
+        .iter()
+        .map(|(name, values)| {
+            let value = match values.as_slice() {
+                [] => Value::Bool(true),
+                [value] => Value::String(value.clone()),
+                values => Value::Sequence(values.iter().cloned().map(Value::String).collect()),
+            };
+            (name, value)
+        })
+        .collect();
+    serde_yaml::to_string(&config).unwrap()
+}
+
+/// Expands the command line with the arguments from the config file named by `--config`
+/// and from the environment, returning the command line for clap and the effective
+/// configuration.
+pub fn expand_command_line(args: Vec<String>) -> Result<(Vec<String>, ConfigArgs), String> {
+    let (program, args) = match args.split_first() {
+        Some((program, args)) => (program.clone(), args),
+        None => return Ok((args, ConfigArgs::new())),
+    };
+    let command_line = command_line_config_args(args);
+    let config_file = match command_line.get("config").map(Vec::as_slice) {
+        Some([path]) => load_config_file(path)?,
+        Some(_) => return Err("--config takes a single file".to_string()),
+        None => ConfigArgs::new(),
+    };
+    let env = env_config_args(std::env::vars());
+    let mut defaults = merge_config_args(vec![config_file, env]);
+    defaults.retain(|name, _| !command_line.contains_key(name));
+
+    let mut expanded = vec![program];
+    expanded.extend(config_args_to_command_line(&defaults));
+    expanded.extend(args.iter().cloned());
+    let config = merge_config_args(vec![defaults, command_line]);
+    Ok((expanded, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_load_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let yaml_path = dir.path().join("validator.yml");
+        let mut file = fs::File::create(&yaml_path).unwrap();
+        writeln!(
+            file,
+            "ledger: /mnt/ledger\nrpc-port: 8899\nno-voting: true\nno-snapshot-fetch: false\n\
+             known-validator:\n  - A\n  - B"
+        )
+        .unwrap();
+        let toml_path = dir.path().join("validator.toml");
+        let mut file = fs::File::create(&toml_path).unwrap();
+        writeln!(
+            file,
+            "ledger = \"/mnt/ledger\"\nrpc-port = 8899\nno-voting = true\n\
+             no-snapshot-fetch = false\nknown-validator = [\"A\", \"B\"]"
+        )
+        .unwrap();
+
+        let expected: ConfigArgs = vec![
+            ("ledger".to_string(), args(&["/mnt/ledger"])),
+            ("rpc-port".to_string(), args(&["8899"])),
+            ("no-voting".to_string(), vec![]),
+            ("known-validator".to_string(), args(&["A", "B"])),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(load_config_file(&yaml_path), Ok(expected.clone()));
+        assert_eq!(load_config_file(&toml_path), Ok(expected));
+    }
+
+    #[test]
+    fn test_env_config_args() {
+        let vars = vec![
+            ("SOLANA_VALIDATOR_RPC_PORT".to_string(), "8899".to_string()),
+            ("SOLANA_VALIDATOR_NO_VOTING".to_string(), "true".to_string()),
+            (
+                "SOLANA_VALIDATOR_NO_GENESIS_FETCH".to_string(),
+                "false".to_string(),
+            ),
+            ("HOME".to_string(), "/home/sol".to_string()),
+        ];
+        let expected: ConfigArgs = vec![
+            ("rpc-port".to_string(), args(&["8899"])),
+            ("no-voting".to_string(), vec![]),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(env_config_args(vars), expected);
+    }
+
+    #[test]
+    fn test_command_line_round_trip() {
+        let command_line = args(&[
+            "-l",
+            "/mnt/ledger",
+            "--no-voting",
+            "--rpc-port=8899",
+            "--known-validator",
+            "A",
+            "--known-validator",
+            "B",
+        ]);
+        let config = command_line_config_args(&command_line);
+        assert_eq!(config["ledger"], args(&["/mnt/ledger"]));
+        assert_eq!(config["no-voting"], Vec::<String>::new());
+        assert_eq!(config["rpc-port"], args(&["8899"]));
+        assert_eq!(config["known-validator"], args(&["A", "B"]));
+        assert_eq!(
+            command_line_config_args(&config_args_to_command_line(&config)),
+            config
+        );
+    }
+
+    #[test]
+    fn test_merge_config_args() {
+        let file = command_line_config_args(&args(&["--rpc-port", "8000", "--no-voting"]));
+        let env = env_config_args(vec![(
+            "SOLANA_VALIDATOR_RPC_PORT".to_string(),
+            "8001".to_string(),
+        )]);
+        let command_line =
+            command_line_config_args(&args(&["--rpc-port", "8002", "--config", "a.yml"]));
+        let config = merge_config_args(vec![file.clone(), env.clone()]);
+        assert_eq!(config["rpc-port"], args(&["8001"]));
+        let config = merge_config_args(vec![file, env, command_line]);
+        assert_eq!(config["rpc-port"], args(&["8002"]));
+        assert!(config.contains_key("no-voting"));
+        assert!(!config.contains_key("config"));
+        assert_eq!(
+            dump_config(&config),
+            "---\nno-voting: true\nrpc-port: \"8002\""
+        );
+    }
+}
@@ -4,6 +4,9 @@ use {
     std::{env, process::exit, thread::JoinHandle},
 };
 
+pub mod config_file;
+pub mod preflight;
+
 #[cfg(unix)]
 fn redirect_stderr(filename: &str) {
     use std::{fs::OpenOptions, os::unix::io::AsRawFd};
@@ -5,9 +5,10 @@ use clap::{
 use log::*;
 use rand::{seq::SliceRandom, thread_rng, Rng};
 use solana_clap_utils::{
-    input_parsers::{keypair_of, keypairs_of, pubkey_of, value_of},
+    input_parsers::{duration_of, keypair_of, keypairs_of, pubkey_of, value_of},
     input_validators::{
-        is_keypair_or_ask_keyword, is_parsable, is_pubkey, is_pubkey_or_keypair, is_slot,
+        is_duration, is_keypair_or_ask_keyword, is_parsable, is_pubkey, is_pubkey_or_keypair,
+        is_slot,
     },
     keypair::SKIP_SEED_PHRASE_VALIDATION_ARG,
 };
@@ -16,34 +17,45 @@ use solana_core::ledger_cleanup_service::{
     DEFAULT_MAX_LEDGER_SHREDS, DEFAULT_MIN_MAX_LEDGER_SHREDS,
 };
 use solana_core::{
-    cluster_info::{ClusterInfo, Node, MINIMUM_VALIDATOR_PORT_RANGE_WIDTH, VALIDATOR_PORT_RANGE},
+    clock_drift_service::{ClockDriftConfig, DEFAULT_MAX_CLOCK_DRIFT_SECS},
+    cluster_info::{
+        ClusterInfo, GossipConfig, Node, MINIMUM_VALIDATOR_PORT_RANGE_WIDTH, VALIDATOR_PORT_RANGE,
+    },
     contact_info::ContactInfo,
     gossip_service::GossipService,
+    identity_interlock_service::{
+        IdentityInterlockConfig, DEFAULT_IDENTITY_INTERLOCK_HOLD_DOWN_SECS,
+    },
     poh_service,
     rpc::JsonRpcConfig,
+    rpc_auth::RpcAuthConfig,
     rpc_pubsub_service::PubSubConfig,
     validator::{is_snapshot_config_invalid, Validator, ValidatorConfig},
 };
 use solana_download_utils::{download_genesis_if_missing, download_snapshot};
-use solana_ledger::blockstore_db::BlockstoreRecoveryMode;
+use solana_ledger::{
+    blockstore_db::{BlockstoreCompressionType, BlockstoreRecoveryMode, BlockstoreRocksOptions},
+    shred_encryption::ShredEncryptionKey,
+};
 use solana_perf::recycler::enable_recycler_warming;
+use solana_rayon_threadlimit::{cpu_topology, get_thread_count, thread_manager};
 use solana_runtime::{
     accounts_index::AccountIndex,
-    bank_forks::{ArchiveFormat, SnapshotConfig, SnapshotVersion},
+    bank_forks::{ArchiveFormat, BankForksPruningConfig, SnapshotConfig, SnapshotVersion},
     hardened_unpack::{unpack_genesis_archive, MAX_GENESIS_ARCHIVE_UNPACKED_SIZE},
     snapshot_utils::get_highest_snapshot_archive_path,
 };
 use solana_sdk::{
     clock::Slot,
-    commitment_config::CommitmentConfig,
+    commitment_config::{CommitmentConfig, CommitmentLevel},
     genesis_config::GenesisConfig,
     hash::Hash,
     pubkey::Pubkey,
     signature::{Keypair, Signer},
 };
-use solana_validator::redirect_stderr_to_file;
+use solana_validator::{config_file, preflight::run_preflight_checks, redirect_stderr_to_file};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     env,
     fs::{self, File},
     net::{IpAddr, SocketAddr, TcpListener, UdpSocket},
@@ -58,6 +70,9 @@ use std::{
     time::{Duration, Instant},
 };
 
+// How long startup waits for the entrypoints to report the cluster they serve
+const CLUSTER_IDENTITY_TIMEOUT: Duration = Duration::from_secs(5);
+
 fn port_range_validator(port_range: String) -> Result<(), String> {
     if let Some((start, end)) = solana_net_utils::parse_port_range(&port_range) {
         if end - start < MINIMUM_VALIDATOR_PORT_RANGE_WIDTH {
@@ -80,6 +95,36 @@ fn hash_validator(hash: String) -> Result<(), String> {
         .map_err(|e| format!("{:?}", e))
 }
 
+fn channel_capacity_validator(capacity: String) -> Result<(), String> {
+    match capacity.parse::<usize>() {
+        Ok(0) => Err("channel capacity must be at least 1".to_string()),
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("{:?}", e)),
+    }
+}
+
+// Parses "GROUP=CPU_LIST", the value of --thread-affinity
+fn parse_thread_affinity(value: &str) -> Result<(String, Vec<usize>), String> {
+    let mut parts = value.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(group), Some(cpu_list)) if !group.is_empty() => {
+            let cpus = cpu_topology::parse_cpu_list(cpu_list)?;
+            if cpus.is_empty() {
+                return Err(format!("no CPUs given for {}", group));
+            }
+            let num_cpus = num_cpus::get();
+            if let Some(cpu) = cpus.iter().find(|cpu| **cpu >= num_cpus) {
+                return Err(format!(
+                    "CPU {} must be in the range [0, {})",
+                    cpu, num_cpus
+                ));
+            }
+            Ok((group.to_string(), cpus))
+        }
+        _ => Err(format!("expected GROUP=CPU_LIST, got {:?}", value)),
+    }
+}
+
 fn is_trusted_validator(id: &Pubkey, trusted_validators: &Option<HashSet<Pubkey>>) -> bool {
     if let Some(trusted_validators) = trusted_validators {
         trusted_validators.contains(id)
@@ -827,6 +872,19 @@ pub fn main() {
         .send_transaction_leader_forward_count
         .to_string();
     let default_rpc_threads = num_cpus::get().to_string();
+    let default_max_clock_drift = DEFAULT_MAX_CLOCK_DRIFT_SECS.to_string();
+    let default_gossip_config = GossipConfig::default();
+    let default_gossip_interval = format!("{}ms", default_gossip_config.gossip_interval_ms);
+    let default_gossip_pull_request_interval =
+        format!("{}ms", default_gossip_config.pull_request_interval_ms);
+    let default_identity_interlock_hold_down =
+        DEFAULT_IDENTITY_INTERLOCK_HOLD_DOWN_SECS.to_string();
+
+    let (args, config) =
+        config_file::expand_command_line(env::args().collect()).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            exit(1);
+        });
 
     let matches = App::new(crate_name!()).about(crate_description!())
         .version(solana_version::version!())
@@ -895,6 +953,17 @@ pub fn main() {
                 .validator(solana_net_utils::is_host_port)
                 .help("Rendezvous with the cluster at this gossip entrypoint"),
         )
+        .arg(
+            Arg::with_name("entrypoint_identity")
+                .long("entrypoint-identity")
+                .validator(is_pubkey)
+                .value_name("PUBKEY")
+                .multiple(true)
+                .takes_value(true)
+                .requires("entrypoint")
+                .help("Only trust the public IP address and cluster reported by an entrypoint \
+                       that signs them with this identity. May be specified multiple times"),
+        )
         .arg(
             Arg::with_name("no_snapshot_fetch")
                 .long("no-snapshot-fetch")
@@ -933,6 +1002,54 @@ pub fn main() {
                        The --no-voting flag is implicit when this flag is enabled \
                       "),
         )
+        .arg(
+            Arg::with_name("rpc_only")
+                .long("rpc-only")
+                .takes_value(false)
+                .help("Operate as an RPC node only: never vote or produce blocks, \
+                       and do not start the transaction processing pipeline. \
+                       Fewer program executors are cached and the thread pools \
+                       of block production are shrunk. \
+                       \
+                       The --no-voting flag is implicit when this flag is enabled \
+                      "),
+        )
+        .arg(
+            Arg::with_name("max_clock_drift")
+                .long("max-clock-drift")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value(&default_max_clock_drift)
+                .help("Warn when the local clock drifts from the cluster time, as estimated \
+                       from stake-weighted vote timestamps, by more than this many seconds"),
+        )
+        .arg(
+            Arg::with_name("pause_block_production_on_clock_drift")
+                .long("pause-block-production-on-clock-drift")
+                .takes_value(false)
+                .help("Skip leader slots while the local clock drifts from the cluster time \
+                       by more than --max-clock-drift"),
+        )
+        .arg(
+            Arg::with_name("identity_interlock")
+                .long("identity-interlock")
+                .takes_value(false)
+                .help("Halt block production and voting while gossip shows another validator \
+                       running with the same identity, instead of exiting, for primary/backup \
+                       pairs sharing an identity. The validator also starts halted for the \
+                       hold-down period"),
+        )
+        .arg(
+            Arg::with_name("identity_interlock_hold_down")
+                .long("identity-interlock-hold-down")
+                .value_name("SECONDS")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .default_value(&default_identity_interlock_hold_down)
+                .help("Resume block production and voting once the other validator has not \
+                       been seen in gossip for this many seconds"),
+        )
         .arg(
             Arg::with_name("dev_halt_at_slot")
                 .long("dev-halt-at-slot")
@@ -949,6 +1066,30 @@ pub fn main() {
                 .validator(solana_validator::port_validator)
                 .help("Use this port for JSON RPC and the next port for the RPC websocket"),
         )
+        .arg(
+            Arg::with_name("rpc_pubsub_port")
+                .long("rpc-pubsub-port")
+                .value_name("PORT")
+                .takes_value(true)
+                .requires("rpc_port")
+                .validator(solana_validator::port_validator)
+                .help("Use this port for the RPC websocket [default: --rpc-port + 1]"),
+        )
+        .arg(
+            Arg::with_name("no_json_rpc")
+                .long("no-json-rpc")
+                .takes_value(false)
+                .requires("rpc_port")
+                .conflicts_with("no_rpc_pubsub")
+                .help("Only serve the RPC websocket, not JSON RPC over HTTP"),
+        )
+        .arg(
+            Arg::with_name("no_rpc_pubsub")
+                .long("no-rpc-pubsub")
+                .takes_value(false)
+                .requires("rpc_port")
+                .help("Only serve JSON RPC over HTTP, not the RPC websocket"),
+        )
         .arg(
             Arg::with_name("private_rpc")
                 .long("--private-rpc")
@@ -975,6 +1116,27 @@ pub fn main() {
                 .help("Enable the JSON RPC 'setLogFilter' API. \
                        Only enable in a debug environment"),
         )
+        .arg(
+            Arg::with_name("enable_rpc_set_gossip_config")
+                .long("enable-rpc-set-gossip-config")
+                .takes_value(false)
+                .help("Enable the JSON RPC 'setGossipConfig' API, to adjust the gossip \
+                       intervals and bandwidth budget at runtime. \
+                       Requires --rpc-auth-config to restrict 'setGossipConfig' to \
+                       allowed identities. Never enable it on a public RPC node"),
+        )
+        .arg(
+            Arg::with_name("rpc_auth_config")
+                .long("rpc-auth-config")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("JSON file listing the JSON RPC methods restricted to authenticated \
+                       identities, and the restricted methods each identity may call, over both \
+                       JSON RPC and pubsub. Clients authenticate with a single use token \
+                       signed by their identity keypair and bound to the request, but \
+                       requests are not encrypted, so serve restricted methods behind TLS \
+                       unless the network is trusted"),
+        )
         .arg(
             Arg::with_name("enable_rpc_transaction_history")
                 .long("enable-rpc-transaction-history")
@@ -998,6 +1160,25 @@ pub fn main() {
                 .takes_value(false)
                 .help("Upload new confirmed blocks into a BigTable instance"),
         )
+        .arg(
+            Arg::with_name("rpc_object_storage_url")
+                .long("rpc-object-storage-url")
+                .value_name("URL")
+                .requires("enable_rpc_transaction_history")
+                .takes_value(true)
+                .help("Fetch historical transaction info from a ledger archive in object \
+                       storage as a fallback to local ledger data. \
+                       Either file:///PATH or s3://BUCKET[/PREFIX][?endpoint=URL&region=REGION], \
+                       with S3 credentials read from the AWS_ACCESS_KEY_ID and \
+                       AWS_SECRET_ACCESS_KEY environment variables"),
+        )
+        .arg(
+            Arg::with_name("enable_object_archive_upload")
+                .long("enable-object-archive-upload")
+                .requires("rpc_object_storage_url")
+                .takes_value(false)
+                .help("Archive new rooted blocks into the --rpc-object-storage-url ledger archive"),
+        )
         .arg(
             Arg::with_name("rpc_max_multiple_accounts")
                 .long("rpc-max-multiple-accounts")
@@ -1007,6 +1188,16 @@ pub fn main() {
                 .help("Override the default maximum accounts accepted by \
                        the getMultipleAccounts JSON RPC method")
         )
+        .arg(
+            Arg::with_name("rpc_default_preflight_commitment")
+                .long("rpc-default-preflight-commitment")
+                .value_name("COMMITMENT_LEVEL")
+                .takes_value(true)
+                .possible_values(&["processed", "confirmed", "finalized"])
+                .help("Commitment level of the bank sendTransaction preflight checks run \
+                       against when the request does not specify one. \
+                       [default: finalized]"),
+        )
         .arg(
             Arg::with_name("health_check_slot_distance")
                 .long("health-check-slot-distance")
@@ -1071,6 +1262,17 @@ pub fn main() {
                       Useful for validators running behind a load balancer or proxy \
                       [default: use --rpc-bind-address / --rpc-port]"),
         )
+        .arg(
+            Arg::with_name("public_rpc_pubsub_addr")
+                .long("public-rpc-pubsub-address")
+                .value_name("HOST:PORT")
+                .takes_value(true)
+                .conflicts_with("private_rpc")
+                .validator(solana_net_utils::is_host_port)
+                .help("RPC websocket address for the validator to advertise publicly in gossip \
+                      [default: use --public-rpc-address, or \
+                      --rpc-pubsub-bind-address / --rpc-pubsub-port]"),
+        )
         .arg(
             Arg::with_name("dynamic_port_range")
                 .long("dynamic-port-range")
@@ -1080,6 +1282,13 @@ pub fn main() {
                 .validator(port_range_validator)
                 .help("Range to use for dynamically assigned ports"),
         )
+        .arg(
+            Arg::with_name("enable_port_mapping")
+                .long("enable-port-mapping")
+                .takes_value(false)
+                .help("Request mappings of the validator's ports from the NAT gateway in front \
+                       of it through NAT-PMP or UPnP, renewing them while the validator runs"),
+        )
         .arg(
             Arg::with_name("maximum_local_snapshot_age")
                 .long("maximum-local-snapshot-age")
@@ -1102,10 +1311,65 @@ pub fn main() {
         .arg(
             Arg::with_name("contact_debug_interval")
                 .long("contact-debug-interval")
-                .value_name("CONTACT_DEBUG_INTERVAL")
+                .value_name("DURATION")
+                .takes_value(true)
+                .validator(is_duration)
+                .default_value("10s")
+                .help("Interval between printing contact debug from gossip, \
+                       a bare number is in milliseconds"),
+        )
+        .arg(
+            Arg::with_name("gossip_interval")
+                .long("gossip-interval")
+                .value_name("DURATION")
                 .takes_value(true)
-                .default_value("10000")
-                .help("Milliseconds between printing contact debug from gossip."),
+                .validator(is_duration)
+                .default_value(&default_gossip_interval)
+                .help("Interval between gossip rounds, which push new gossip values, \
+                       a bare number is in milliseconds. Raise it to reduce gossip traffic \
+                       on low-bandwidth connections"),
+        )
+        .arg(
+            Arg::with_name("gossip_pull_request_interval")
+                .long("gossip-pull-request-interval")
+                .value_name("DURATION")
+                .takes_value(true)
+                .validator(is_duration)
+                .default_value(&default_gossip_pull_request_interval)
+                .help("Interval between gossip pull requests, rounded to a whole number of \
+                       gossip rounds, a bare number is in milliseconds"),
+        )
+        .arg(
+            Arg::with_name("gossip_max_bytes_per_second")
+                .long("gossip-max-bytes-per-second")
+                .value_name("BYTES")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Limit the bandwidth spent on gossip pull responses, which otherwise \
+                       grows with the number of staked nodes"),
+        )
+        .arg(
+            Arg::with_name("shred_encryption_key")
+                .long("shred-encryption-key")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("File holding the key the shreds of the cluster are encrypted with. \
+                       Required when the genesis config records a shred encryption key \
+                       fingerprint"),
+        )
+        .arg(
+            Arg::with_name("skip_preflight_checks")
+                .long("skip-preflight-checks")
+                .takes_value(false)
+                .conflicts_with("enforce_preflight_checks")
+                .help("Skip the startup checks of system limits, disk, clock and CPU features"),
+        )
+        .arg(
+            Arg::with_name("enforce_preflight_checks")
+                .long("enforce-preflight-checks")
+                .takes_value(false)
+                .help("Refuse to start if a startup check of system limits, disk, clock \
+                       or CPU features fails"),
         )
         .arg(
             Arg::with_name("no_poh_speed_test")
@@ -1120,6 +1384,24 @@ pub fn main() {
                 .default_value("100")
                 .help("Number of slots between generating accounts hash."),
         )
+        .arg(
+            Arg::with_name("max_fork_depth")
+                .long("max-fork-depth")
+                .value_name("SLOTS")
+                .takes_value(true)
+                .validator(is_parsable::<Slot>)
+                .help("Prune the forks nobody voted on whose newest bank is more than \
+                       this many slots behind the heaviest fork [default: never]"),
+        )
+        .arg(
+            Arg::with_name("max_bank_count")
+                .long("max-bank-count")
+                .value_name("COUNT")
+                .takes_value(true)
+                .validator(is_parsable::<usize>)
+                .help("While more banks than this are retained ahead of the root, prune \
+                       the oldest forks nobody voted on [default: unlimited]"),
+        )
         .arg(
             Arg::with_name("snapshot_version")
                 .long("snapshot-version")
@@ -1255,12 +1537,85 @@ pub fn main() {
                       will not pull/pull from from validators outside this set. \
                       [default: all validators]")
         )
+        .arg(
+            Arg::with_name("replica_sync_port")
+                .long("replica-sync-port")
+                .value_name("PORT")
+                .takes_value(true)
+                .validator(solana_validator::port_validator)
+                .help("Stream rooted account updates to RPC replicas on this port"),
+        )
+        .arg(
+            Arg::with_name("replica_validators")
+                .long("replica-validator")
+                .validator(is_pubkey)
+                .value_name("PUBKEY")
+                .multiple(true)
+                .takes_value(true)
+                .requires("replica_sync_port")
+                .help("A list of replicas allowed to receive account updates. If specified, \
+                       replicas outside this set are rejected [default: all replicas]")
+        )
+        .arg(
+            Arg::with_name("tpu_channel_capacity")
+                .long("tpu-channel-capacity")
+                .value_name("BATCHES")
+                .takes_value(true)
+                .validator(channel_capacity_validator)
+                .help("Number of packet batches that may be queued between the TPU stages \
+                       [default: 4096]"),
+        )
+        .arg(
+            Arg::with_name("tvu_channel_capacity")
+                .long("tvu-channel-capacity")
+                .value_name("BATCHES")
+                .takes_value(true)
+                .validator(channel_capacity_validator)
+                .help("Number of packet batches that may be queued between the TVU stages \
+                       [default: 4096]"),
+        )
+        .arg(
+            Arg::with_name("retransmit_egress_budget")
+                .long("retransmit-egress-budget")
+                .value_name("MEGABITS_PER_SECOND")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Retransmit shreds at no more than this rate. Once it is reached, \
+                       shreds of slots near the tip go to the neighbors and then the \
+                       highest staked children first, older shreds are deferred \
+                       [default: unlimited]"),
+        )
+        .arg(
+            Arg::with_name("signing_journal")
+                .long("signing-journal")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("Append every vote, block, tower and gossip message signed with the \
+                       identity and vote keys to FILE, for `solana-ledger-tool signing-journal` \
+                       to audit"),
+        )
+        .arg(
+            Arg::with_name("packet_channel_drop_policy")
+                .long("packet-channel-drop-policy")
+                .value_name("POLICY")
+                .takes_value(true)
+                .possible_values(&["block", "drop-newest", "drop-oldest"])
+                .help("What to do with incoming packets once the queue in front of \
+                       signature verification is full [default: drop-oldest]"),
+        )
         .arg(
             Arg::with_name("no_rocksdb_compaction")
                 .long("no-rocksdb-compaction")
                 .takes_value(false)
                 .help("Disable manual compaction of the ledger database. May increase storage requirements.")
         )
+        .arg(
+            Arg::with_name("blockstore_integrity_repair")
+                .long("blockstore-integrity-repair")
+                .takes_value(false)
+                .help("Drop the unrooted slots that fail the blockstore integrity check at \
+                       startup, so that they are repaired from the cluster")
+        )
         .arg(
             Arg::with_name("bind_address")
                 .long("bind-address")
@@ -1278,6 +1633,15 @@ pub fn main() {
                 .validator(solana_net_utils::is_host)
                 .help("IP address to bind the RPC port [default: use --bind-address]"),
         )
+        .arg(
+            Arg::with_name("rpc_pubsub_bind_address")
+                .long("rpc-pubsub-bind-address")
+                .value_name("HOST")
+                .takes_value(true)
+                .validator(solana_net_utils::is_host)
+                .help("IP address to bind the RPC websocket port \
+                       [default: use --rpc-bind-address]"),
+        )
         .arg(
             Arg::with_name("rpc_threads")
                 .long("rpc-threads")
@@ -1290,11 +1654,12 @@ pub fn main() {
         .arg(
             Arg::with_name("rpc_bigtable_timeout")
                 .long("rpc-bigtable-timeout")
-                .value_name("SECONDS")
-                .validator(is_parsable::<u64>)
+                .value_name("DURATION")
+                .validator(is_duration)
                 .takes_value(true)
-                .default_value("30")
-                .help("Number of seconds before timing out RPC requests backed by BigTable"),
+                .default_value("30s")
+                .help("Duration before timing out RPC requests backed by BigTable, \
+                       a bare number is in seconds"),
         )
         .arg(
             Arg::with_name("rpc_pubsub_enable_vote_subscription")
@@ -1302,6 +1667,14 @@ pub fn main() {
                 .takes_value(false)
                 .help("Enable the unstable RPC PubSub `voteSubscribe` subscription"),
         )
+        .arg(
+            Arg::with_name("rpc_pubsub_enable_subscription_resume")
+                .long("rpc-pubsub-enable-subscription-resume")
+                .takes_value(false)
+                .help("Keep RPC PubSub subscriptions alive for a short while after their \
+                       connection drops, buffering their notifications until a client \
+                       resumes them with `subscriptionResume`"),
+        )
         .arg(
             Arg::with_name("rpc_pubsub_max_connections")
                 .long("rpc-pubsub-max-connections")
@@ -1411,11 +1784,65 @@ pub fn main() {
                     "Mode to recovery the ledger db write ahead log."
                 ),
         )
+        .arg(
+            Arg::with_name("rocksdb_write_buffer_size")
+                .long("rocksdb-write-buffer-size")
+                .value_name("BYTES")
+                .takes_value(true)
+                .validator(is_parsable::<u64>)
+                .help("Size of the ledger db memtable of each column [default: 256MB]"),
+        )
+        .arg(
+            Arg::with_name("rocksdb_max_write_buffer_number")
+                .long("rocksdb-max-write-buffer-number")
+                .value_name("NUMBER")
+                .takes_value(true)
+                .validator(is_parsable::<i32>)
+                .help("Maximum number of ledger db memtables of each column [default: 8]"),
+        )
+        .arg(
+            Arg::with_name("rocksdb_max_background_jobs")
+                .long("rocksdb-max-background-jobs")
+                .value_name("NUMBER")
+                .takes_value(true)
+                .validator(is_parsable::<i32>)
+                .help(
+                    "Maximum number of concurrent ledger db flushes and compactions \
+                     [default: scaled to the number of cores]",
+                ),
+        )
+        .arg(
+            Arg::with_name("rocksdb_compression")
+                .long("rocksdb-compression")
+                .value_name("COLUMN=TYPE")
+                .takes_value(true)
+                .multiple(true)
+                .validator(|value| {
+                    let mut parts = value.splitn(2, '=');
+                    match (parts.next(), parts.next()) {
+                        (Some(column), Some(compression_type)) if !column.is_empty() => {
+                            BlockstoreCompressionType::from_str(compression_type).map(|_| ())
+                        }
+                        _ => Err(format!("Expected COLUMN=TYPE, got: {}", value)),
+                    }
+                })
+                .help(
+                    "Compress the SST files of a ledger db column. \
+                     TYPE is one of none, lz4 or lz4hc. May be specified multiple times",
+                ),
+        )
+        .arg(
+            Arg::with_name("rocksdb_pin_l0_filter_and_index_blocks")
+                .long("rocksdb-pin-l0-filter-and-index-blocks")
+                .takes_value(false)
+                .help("Keep the ledger db level 0 index and filter blocks pinned in memory"),
+        )
         .arg(
             Arg::with_name("bpf_jit")
                 .long("bpf-jit")
                 .takes_value(false)
-                .help("Use the just-in-time compiler instead of the interpreter for BPF."),
+                .help("Use the just-in-time compiler instead of the interpreter for BPF. \
+                       Programs which fail to compile fall back to the interpreter."),
         )
         .arg(
             Arg::with_name("poh_pinned_cpu_core")
@@ -1454,7 +1881,71 @@ pub fn main() {
                 .conflicts_with("no_accounts_db_caching")
                 .hidden(true)
         )
-        .get_matches();
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("YAML or TOML file of argument values by long argument name, e.g. \
+                       `rpc-port: 8899`. Arguments may also be set with SOLANA_VALIDATOR_* \
+                       environment variables, e.g. SOLANA_VALIDATOR_RPC_PORT=8899. The \
+                       command line overrides the environment, which overrides the file"),
+        )
+        .arg(
+            Arg::with_name("dump_config")
+                .long("dump-config")
+                .takes_value(false)
+                .help("Print the effective configuration as a config file and exit"),
+        )
+        .arg(
+            Arg::with_name("thread_pool_config")
+                .long("thread-pool-config")
+                .value_name("FILE")
+                .takes_value(true)
+                .help("JSON file mapping thread pool names to their number of threads, \
+                       e.g. {\"solana-accounts-db\": 8}. Pools which are not listed \
+                       keep their default size"),
+        )
+        .arg(
+            Arg::with_name("thread_affinity")
+                .long("thread-affinity")
+                .value_name("GROUP=CPU_LIST")
+                .takes_value(true)
+                .multiple(true)
+                .validator(|s| parse_thread_affinity(&s).map(|_| ()))
+                .help("Pin a group of threads to a list of CPUs, e.g. solana-banking=8-11,16. \
+                       Hot thread groups are solana-poh, solana-sigverify, solana-banking \
+                       and solana-turbine; any thread pool name may also be given"),
+        )
+        .arg(
+            Arg::with_name("numa_node")
+                .long("numa-node")
+                .value_name("NODE")
+                .takes_value(true)
+                .validator(|s| {
+                    let node = usize::from_str(&s).map_err(|e| e.to_string())?;
+                    let numa_nodes = cpu_topology::numa_nodes();
+                    match numa_nodes.get(node) {
+                        None => Err(format!(
+                            "NUMA node must be in the range [0, {})",
+                            numa_nodes.len()
+                        )),
+                        Some(cpus) if cpus.is_empty() => {
+                            Err(format!("NUMA node {} has no online CPUs", node))
+                        }
+                        Some(_) => Ok(()),
+                    }
+                })
+                .help("Place the hot thread groups on the CPUs of this NUMA node: PoH on \
+                       the first CPU and the other groups on the rest. Groups given by \
+                       --thread-affinity keep their CPUs"),
+        )
+        .get_matches_from(args);
+
+    if matches.is_present("dump_config") {
+        println!("{}", config_file::dump_config(&config));
+        return;
+    }
 
     let identity_keypair = Arc::new(keypair_of(&matches, "identity").unwrap_or_else(Keypair::new));
 
@@ -1483,6 +1974,32 @@ pub fn main() {
     let wal_recovery_mode = matches
         .value_of("wal_recovery_mode")
         .map(BlockstoreRecoveryMode::from);
+    let blockstore_rocks_options = {
+        let default_options = BlockstoreRocksOptions::default();
+        BlockstoreRocksOptions {
+            write_buffer_size: value_t!(matches, "rocksdb_write_buffer_size", u64)
+                .unwrap_or(default_options.write_buffer_size),
+            max_write_buffer_number: value_t!(matches, "rocksdb_max_write_buffer_number", i32)
+                .unwrap_or(default_options.max_write_buffer_number),
+            max_background_jobs: value_t!(matches, "rocksdb_max_background_jobs", i32).ok(),
+            column_compression: matches
+                .values_of("rocksdb_compression")
+                .map(|values| {
+                    values
+                        .map(|value| {
+                            let mut parts = value.splitn(2, '=');
+                            let column = parts.next().unwrap().to_string();
+                            let compression_type =
+                                BlockstoreCompressionType::from_str(parts.next().unwrap()).unwrap();
+                            (column, compression_type)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            pin_l0_filter_and_index_blocks: matches
+                .is_present("rocksdb_pin_l0_filter_and_index_blocks"),
+        }
+    };
 
     // Canonicalize ledger path to avoid issues with symlink creation
     let _ = fs::create_dir_all(&ledger_path);
@@ -1519,6 +2036,12 @@ pub fn main() {
         "gossip_validators",
         "--gossip-validator",
     );
+    let replica_validators = validators_set(
+        &identity_keypair.pubkey(),
+        &matches,
+        "replica_validators",
+        "--replica-validator",
+    );
 
     let bind_address = solana_net_utils::parse_host(matches.value_of("bind_address").unwrap())
         .expect("invalid bind_address");
@@ -1528,8 +2051,42 @@ pub fn main() {
     } else {
         bind_address
     };
+    let rpc_pubsub_bind_address = if matches.is_present("rpc_pubsub_bind_address") {
+        solana_net_utils::parse_host(matches.value_of("rpc_pubsub_bind_address").unwrap())
+            .expect("invalid rpc_pubsub_bind_address")
+    } else {
+        rpc_bind_address
+    };
 
-    let contact_debug_interval = value_t_or_exit!(matches, "contact_debug_interval", u64);
+    let contact_debug_interval =
+        duration_of(&matches, "contact_debug_interval", Duration::from_millis(1))
+            .unwrap()
+            .as_millis() as u64;
+
+    let gossip_config = GossipConfig {
+        gossip_interval_ms: duration_of(&matches, "gossip_interval", Duration::from_millis(1))
+            .unwrap()
+            .as_millis() as u64,
+        pull_request_interval_ms: duration_of(
+            &matches,
+            "gossip_pull_request_interval",
+            Duration::from_millis(1),
+        )
+        .unwrap()
+        .as_millis() as u64,
+        max_bytes_per_second: value_t!(matches, "gossip_max_bytes_per_second", u64).unwrap_or(0),
+    };
+    if let Err(err) = gossip_config.verify() {
+        eprintln!("Invalid gossip configuration: {}", err);
+        exit(1);
+    }
+
+    let shred_encryption_key = matches.value_of("shred_encryption_key").map(|path| {
+        ShredEncryptionKey::read(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Unable to read shred encryption key {}: {}", path, err);
+            exit(1);
+        })
+    });
 
     let account_indexes: HashSet<AccountIndex> = matches
         .values_of("account_indexes")
@@ -1542,7 +2099,25 @@ pub fn main() {
         })
         .collect();
 
+    let rpc_auth = matches.value_of("rpc_auth_config").map(|path| {
+        RpcAuthConfig::load(Path::new(path)).unwrap_or_else(|err| {
+            eprintln!("Invalid --rpc-auth-config: {}", err);
+            exit(1);
+        })
+    });
+    if matches.is_present("enable_rpc_set_gossip_config")
+        && !rpc_auth.as_ref().map_or(false, |rpc_auth| {
+            rpc_auth.restricted_methods.contains("setGossipConfig")
+        })
+    {
+        eprintln!(
+            "--enable-rpc-set-gossip-config requires --rpc-auth-config to restrict setGossipConfig"
+        );
+        exit(1);
+    }
+
     let restricted_repair_only_mode = matches.is_present("restricted_repair_only_mode");
+    let rpc_only = matches.is_present("rpc_only");
     let mut validator_config = ValidatorConfig {
         require_tower: matches.is_present("require_tower"),
         dev_halt_at_slot: value_t!(matches, "dev_halt_at_slot", Slot).ok(),
@@ -1558,10 +2133,13 @@ pub fn main() {
         rpc_config: JsonRpcConfig {
             enable_validator_exit: matches.is_present("enable_rpc_exit"),
             enable_set_log_filter: matches.is_present("enable_rpc_set_log_filter"),
+            enable_set_gossip_config: matches.is_present("enable_rpc_set_gossip_config"),
             enable_rpc_transaction_history: matches.is_present("enable_rpc_transaction_history"),
             enable_bigtable_ledger_storage: matches
                 .is_present("enable_rpc_bigtable_ledger_storage"),
             enable_bigtable_ledger_upload: matches.is_present("enable_bigtable_ledger_upload"),
+            object_storage_url: matches.value_of("rpc_object_storage_url").map(String::from),
+            enable_object_archive_upload: matches.is_present("enable_object_archive_upload"),
             identity_pubkey: identity_keypair.pubkey(),
             faucet_addr: matches.value_of("rpc_faucet_addr").map(|address| {
                 solana_net_utils::parse_host_port(address).expect("failed to parse faucet address")
@@ -1577,22 +2155,38 @@ pub fn main() {
                 u64
             ),
             rpc_threads: value_t_or_exit!(matches, "rpc_threads", usize),
-            rpc_bigtable_timeout: value_t!(matches, "rpc_bigtable_timeout", u64)
-                .ok()
-                .map(Duration::from_secs),
+            rpc_bigtable_timeout: duration_of(
+                &matches,
+                "rpc_bigtable_timeout",
+                Duration::from_secs(1),
+            ),
             account_indexes: account_indexes.clone(),
+            default_preflight_commitment: value_t!(
+                matches,
+                "rpc_default_preflight_commitment",
+                CommitmentLevel
+            )
+            .ok(),
+            rpc_auth: rpc_auth.clone(),
         },
         rpc_addrs: value_t!(matches, "rpc_port", u16).ok().map(|rpc_port| {
             (
                 SocketAddr::new(rpc_bind_address, rpc_port),
-                SocketAddr::new(rpc_bind_address, rpc_port + 1),
+                SocketAddr::new(
+                    rpc_pubsub_bind_address,
+                    value_t!(matches, "rpc_pubsub_port", u16).unwrap_or(rpc_port + 1),
+                ),
                 // If additional ports are added, +2 needs to be skipped to avoid a conflict with
                 // the websocket port (which is +2) in web3.js This odd port shifting is tracked at
                 // https://github.com/solana-labs/solana/issues/12250
             )
         }),
+        json_rpc_disabled: matches.is_present("no_json_rpc"),
+        rpc_pubsub_disabled: matches.is_present("no_rpc_pubsub"),
         pubsub_config: PubSubConfig {
             enable_vote_subscription: matches.is_present("rpc_pubsub_enable_vote_subscription"),
+            enable_subscription_resume: matches.is_present("rpc_pubsub_enable_subscription_resume"),
+            rpc_auth,
             max_connections: value_t_or_exit!(matches, "rpc_pubsub_max_connections", usize),
             max_fragment_size: value_t_or_exit!(matches, "rpc_pubsub_max_fragment_size", usize),
             max_in_buffer_capacity: value_t_or_exit!(
@@ -1606,17 +2200,25 @@ pub fn main() {
                 usize
             ),
         },
-        voting_disabled: matches.is_present("no_voting") || restricted_repair_only_mode,
+        voting_disabled: matches.is_present("no_voting") || restricted_repair_only_mode || rpc_only,
+        rpc_only,
         wait_for_supermajority: value_t!(matches, "wait_for_supermajority", Slot).ok(),
         trusted_validators,
         repair_validators,
         gossip_validators,
+        replica_sync_addr: value_t!(matches, "replica_sync_port", u16)
+            .ok()
+            .map(|port| SocketAddr::new(rpc_bind_address, port)),
+        replica_validators,
         frozen_accounts: values_t!(matches, "frozen_accounts", Pubkey).unwrap_or_default(),
         no_rocksdb_compaction,
+        blockstore_integrity_repair: matches.is_present("blockstore_integrity_repair"),
         wal_recovery_mode,
+        blockstore_rocks_options,
         poh_verify: !matches.is_present("skip_poh_verify"),
         debug_keys,
         contact_debug_interval,
+        gossip_config,
         bpf_jit: matches.is_present("bpf_jit"),
         send_transaction_retry_ms: value_t_or_exit!(matches, "rpc_send_transaction_retry_ms", u64),
         send_transaction_leader_forward_count: value_t_or_exit!(
@@ -1629,9 +2231,39 @@ pub fn main() {
             .unwrap_or(poh_service::DEFAULT_PINNED_CPU_CORE),
         account_indexes,
         accounts_db_caching_enabled: !matches.is_present("no_accounts_db_caching"),
+        clock_drift_config: ClockDriftConfig {
+            max_drift_secs: value_t_or_exit!(matches, "max_clock_drift", u64),
+            pause_block_production: matches.is_present("pause_block_production_on_clock_drift"),
+        },
+        identity_interlock_config: if matches.is_present("identity_interlock") {
+            Some(IdentityInterlockConfig {
+                hold_down_secs: value_t_or_exit!(matches, "identity_interlock_hold_down", u64),
+            })
+        } else {
+            None
+        },
+        shred_encryption_key,
+        retransmit_egress_budget: value_t!(matches, "retransmit_egress_budget", u64)
+            .ok()
+            .map(|megabits_per_second| megabits_per_second * 1_000_000 / 8),
+        signing_journal_path: value_t!(matches, "signing_journal", PathBuf).ok(),
         ..ValidatorConfig::default()
     };
 
+    let packet_channels = &mut validator_config.packet_channels;
+    if let Some(capacity) = value_of(&matches, "tpu_channel_capacity") {
+        packet_channels.tpu_packets.capacity = capacity;
+        packet_channels.tpu_verified.capacity = capacity;
+    }
+    if let Some(capacity) = value_of(&matches, "tvu_channel_capacity") {
+        packet_channels.tvu_packets.capacity = capacity;
+        packet_channels.tvu_verified.capacity = capacity;
+    }
+    if let Some(drop_policy) = value_of(&matches, "packet_channel_drop_policy") {
+        packet_channels.tpu_packets.drop_policy = drop_policy;
+        packet_channels.tvu_packets.drop_policy = drop_policy;
+    }
+
     let vote_account = pubkey_of(&matches, "vote_account").unwrap_or_else(|| {
         if !validator_config.voting_disabled {
             warn!("--vote-account not specified, validator will not vote");
@@ -1756,6 +2388,11 @@ pub fn main() {
         exit(1);
     }
 
+    validator_config.bank_forks_pruning_config = BankForksPruningConfig {
+        max_fork_depth: value_t!(matches, "max_fork_depth", Slot).ok(),
+        max_bank_count: value_t!(matches, "max_bank_count", usize).ok(),
+    };
+
     if matches.is_present("limit_ledger_size") {
         let limit_ledger_size = match matches.value_of("limit_ledger_size") {
             Some(_) => value_t_or_exit!(matches, "limit_ledger_size", u64),
@@ -1788,12 +2425,21 @@ pub fn main() {
         .into_iter()
         .collect::<Vec<_>>();
 
+    let entrypoint_identities =
+        values_t!(matches, "entrypoint_identity", Pubkey).unwrap_or_default();
+
     let public_rpc_addr = matches.value_of("public_rpc_addr").map(|addr| {
         solana_net_utils::parse_host_port(addr).unwrap_or_else(|e| {
             eprintln!("failed to parse public rpc address: {}", e);
             exit(1);
         })
     });
+    let public_rpc_pubsub_addr = matches.value_of("public_rpc_pubsub_addr").map(|addr| {
+        solana_net_utils::parse_host_port(addr).unwrap_or_else(|e| {
+            eprintln!("failed to parse public rpc pubsub address: {}", e);
+            exit(1);
+        })
+    });
 
     let logfile = {
         let logfile = matches
@@ -1814,6 +2460,79 @@ pub fn main() {
     info!("{} {}", crate_name!(), solana_version::version!());
     info!("Starting validator with: {:#?}", std::env::args_os());
 
+    if !matches.is_present("skip_preflight_checks") {
+        let preflight_report = run_preflight_checks(&ledger_path);
+        preflight_report.log();
+        if preflight_report.has_failures() && matches.is_present("enforce_preflight_checks") {
+            eprintln!(
+                "Preflight checks failed, refusing to start:\n{}",
+                preflight_report
+            );
+            exit(1);
+        }
+    }
+
+    let mut num_threads: HashMap<String, usize> = HashMap::new();
+    if let Some(thread_pool_config) = matches.value_of("thread_pool_config") {
+        num_threads = File::open(thread_pool_config)
+            .map_err(|err| err.to_string())
+            .and_then(|file| serde_json::from_reader(file).map_err(|err| err.to_string()))
+            .unwrap_or_else(|err| {
+                eprintln!("Unable to read {}: {}", thread_pool_config, err);
+                exit(1);
+            });
+    }
+    if rpc_only {
+        // Shreds are only made to broadcast blocks, and signatures are only verified for the
+        // votes seen in gossip since the TPU doesn't run
+        num_threads
+            .entry("solana-shredder".to_string())
+            .or_insert(1);
+        num_threads
+            .entry("solana-sigverify".to_string())
+            .or_insert_with(|| (get_thread_count() / 4).max(1));
+    }
+    if !num_threads.is_empty() {
+        info!("Thread pool sizes: {:?}", num_threads);
+        thread_manager::configure(num_threads);
+    }
+
+    let mut thread_affinity: HashMap<String, Vec<usize>> =
+        values_t!(matches, "thread_affinity", String)
+            .unwrap_or_default()
+            .iter()
+            .map(|s| parse_thread_affinity(s).unwrap())
+            .collect();
+    if let Some(node) = value_of::<usize>(&matches, "numa_node") {
+        let numa_nodes = cpu_topology::numa_nodes();
+        match numa_nodes.get(node).and_then(|cpus| cpus.split_first()) {
+            Some((poh_cpu, worker_cpus)) => {
+                let worker_cpus = if worker_cpus.is_empty() {
+                    &numa_nodes[node]
+                } else {
+                    worker_cpus
+                };
+                thread_affinity
+                    .entry("solana-poh".to_string())
+                    .or_insert_with(|| vec![*poh_cpu]);
+                for group in &["solana-sigverify", "solana-banking", "solana-turbine"] {
+                    thread_affinity
+                        .entry(group.to_string())
+                        .or_insert_with(|| worker_cpus.to_vec());
+                }
+            }
+            // The topology changed since the argument was validated
+            None => warn!(
+                "NUMA node {} has no online CPUs, leaving the hot thread groups unpinned",
+                node
+            ),
+        }
+    }
+    if !thread_affinity.is_empty() {
+        info!("Thread affinity: {:?}", thread_affinity);
+        thread_manager::configure_affinity(thread_affinity);
+    }
+
     let gossip_host: IpAddr = matches
         .value_of("gossip_host")
         .map(|gossip_host| {
@@ -1833,7 +2552,15 @@ pub fn main() {
                         "Contacting {} to determine the validator's public IP address",
                         entrypoint_addr
                     );
-                    solana_net_utils::get_public_ip_addr(entrypoint_addr).map_or_else(
+                    if entrypoint_identities.is_empty() {
+                        solana_net_utils::get_public_ip_addr(entrypoint_addr)
+                    } else {
+                        solana_net_utils::get_public_ip_addr_signed_by(
+                            entrypoint_addr,
+                            &entrypoint_identities,
+                        )
+                    }
+                    .map_or_else(
                         |err| {
                             eprintln!(
                                 "Failed to contact cluster entrypoint {}: {}",
@@ -1871,12 +2598,107 @@ pub fn main() {
         .map(ContactInfo::new_gossip_entry_point)
         .collect::<Vec<_>>();
 
+    // Fail fast when an entrypoint serves another cluster than the expected one, and otherwise
+    // adopt its shred version.  Only answers signed by an entrypoint identity or a trusted
+    // validator count, and entrypoints older than ip echo protocol version 9 can't sign them
+    let mut cluster_identity_signers = entrypoint_identities.clone();
+    cluster_identity_signers.extend(validator_config.trusted_validators.iter().flatten());
+    if !entrypoint_addrs.is_empty() && cluster_identity_signers.is_empty() {
+        info!(
+            "Not checking the cluster of the entrypoints without --entrypoint-identity or \
+             --trusted-validator to verify their answers"
+        );
+    }
+    let cluster_identities = if cluster_identity_signers.is_empty() {
+        vec![]
+    } else {
+        solana_net_utils::get_cluster_identities_signed_by(
+            &entrypoint_addrs,
+            &cluster_identity_signers,
+            CLUSTER_IDENTITY_TIMEOUT,
+        )
+    };
+    for (entrypoint_addr, cluster_identity) in cluster_identities {
+        let cluster_identity = match cluster_identity {
+            Ok(cluster_identity) => cluster_identity,
+            Err(err) => {
+                info!(
+                    "Unable to identify the cluster of {}: {}",
+                    entrypoint_addr, err
+                );
+                continue;
+            }
+        };
+        let genesis_hash = Hash::new_from_array(cluster_identity.genesis_hash);
+        if let Some(expected_genesis_hash) = validator_config.expected_genesis_hash {
+            if expected_genesis_hash != genesis_hash {
+                eprintln!(
+                    "Entrypoint {} serves a cluster with genesis hash {}, expected {}",
+                    entrypoint_addr, genesis_hash, expected_genesis_hash
+                );
+                exit(1);
+            }
+        }
+        match validator_config.expected_shred_version {
+            Some(expected_shred_version) => {
+                if expected_shred_version != cluster_identity.shred_version {
+                    eprintln!(
+                        "Entrypoint {} serves shred version {}, expected {}",
+                        entrypoint_addr, cluster_identity.shred_version, expected_shred_version
+                    );
+                    exit(1);
+                }
+            }
+            None => {
+                info!(
+                    "Using shred version {} of entrypoint {}",
+                    cluster_identity.shred_version, entrypoint_addr
+                );
+                validator_config.expected_shred_version = Some(cluster_identity.shred_version);
+            }
+        }
+    }
+
+    let mut requested_ports = vec![("gossip", gossip_addr.port())];
+    if let Some((rpc_addr, rpc_pubsub_addr)) = validator_config.rpc_addrs {
+        if !validator_config.json_rpc_disabled {
+            requested_ports.push(("rpc", rpc_addr.port()));
+        }
+        if !validator_config.rpc_pubsub_disabled {
+            requested_ports.push(("rpc_pubsub", rpc_pubsub_addr.port()));
+        }
+    }
+    if let Some((service, other_service, port)) =
+        solana_net_utils::find_port_conflict(&requested_ports)
+    {
+        eprintln!(
+            "Port {} is requested for both {} and {}",
+            port, service, other_service
+        );
+        exit(1);
+    }
+    let rpc_ports = requested_ports[1..]
+        .iter()
+        .map(|(_, port)| *port)
+        .collect::<Vec<_>>();
+
+    // Keep the RPC ports out of the dynamic port range until the other services are bound
+    let rpc_port_reservations =
+        solana_net_utils::reserve_ports_in_range(bind_address, dynamic_port_range, &rpc_ports)
+            .unwrap_or_else(|err| {
+                eprintln!(
+                    "Unable to reserve RPC ports in --dynamic-port-range: {}",
+                    err
+                );
+                exit(1);
+            });
     let mut node = Node::new_with_external_ip(
         &identity_keypair.pubkey(),
         &gossip_addr,
         dynamic_port_range,
         bind_address,
     );
+    drop(rpc_port_reservations);
 
     if restricted_repair_only_mode {
         let any = SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::new(0, 0, 0, 0)), 0);
@@ -1891,18 +2713,52 @@ pub fn main() {
 
         // A node in this configuration shouldn't be an entrypoint to other nodes
         node.sockets.ip_echo = None;
+    } else if rpc_only {
+        // No TPU pipeline is running, so don't advertise ports that would drop transactions
+        let any = SocketAddr::new(std::net::IpAddr::V4(std::net::Ipv4Addr::new(0, 0, 0, 0)), 0);
+        node.info.tpu = any;
+        node.info.tpu_forwards = any;
     }
 
     if !private_rpc {
-        if let Some(public_rpc_addr) = public_rpc_addr {
+        if let Some((rpc_addr, rpc_pubsub_addr)) = validator_config.rpc_addrs {
+            if !validator_config.json_rpc_disabled {
+                node.info.rpc = public_rpc_addr
+                    .unwrap_or_else(|| SocketAddr::new(node.info.gossip.ip(), rpc_addr.port()));
+            }
+            if !validator_config.rpc_pubsub_disabled {
+                let default_rpc_pubsub_addr =
+                    SocketAddr::new(node.info.gossip.ip(), rpc_pubsub_addr.port());
+                node.info.rpc_pubsub = public_rpc_pubsub_addr
+                    .or(public_rpc_addr)
+                    .unwrap_or(default_rpc_pubsub_addr);
+            }
+        } else if let Some(public_rpc_addr) = public_rpc_addr {
             node.info.rpc = public_rpc_addr;
-            node.info.rpc_pubsub = public_rpc_addr;
-        } else if let Some((rpc_addr, rpc_pubsub_addr)) = validator_config.rpc_addrs {
-            node.info.rpc = SocketAddr::new(node.info.gossip.ip(), rpc_addr.port());
-            node.info.rpc_pubsub = SocketAddr::new(node.info.gossip.ip(), rpc_pubsub_addr.port());
+            node.info.rpc_pubsub = public_rpc_pubsub_addr.unwrap_or(public_rpc_addr);
         }
     }
 
+    for (service, port) in node.port_map() {
+        info!("Port map: {:<13} {}", service, port);
+    }
+
+    let port_mapper = if matches.is_present("enable_port_mapping") {
+        let mut ports: Vec<_> = node
+            .port_map()
+            .into_iter()
+            .map(|(_, port)| port)
+            .filter(|port| *port != 0)
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        solana_net_utils::PortMapper::start(ports, solana_net_utils::PortMappingConfig::default())
+            .map_err(|err| warn!("Unable to map the validator's ports: {}", err))
+            .ok()
+    } else {
+        None
+    };
+
     solana_metrics::set_host_id(identity_keypair.pubkey().to_string());
     solana_metrics::set_panic_hook("validator");
 
@@ -1928,5 +2784,8 @@ pub fn main() {
     }
     info!("Validator initialized");
     validator.join();
+    if let Some(port_mapper) = port_mapper {
+        port_mapper.shutdown();
+    }
     info!("Validator exiting..");
 }
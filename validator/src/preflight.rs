@@ -0,0 +1,386 @@
+//! Startup preflight checks of the host the validator is about to run on.
+//!
+//! Every check reports `Pass`, `Warn` or `Fail`. Warnings flag settings that degrade the
+//! validator, failures flag settings it is known not to run on. Checks that do not apply to
+//! the platform are skipped.
+
+use {
+    log::*,
+    std::{
+        fmt,
+        fs::{self, OpenOptions},
+        io::Write,
+        path::Path,
+        time::Instant,
+    },
+};
+
+// Matches the limit the blockstore tries to raise RLIMIT_NOFILE to
+const DESIRED_NOFILE: u64 = 500_000;
+
+// Matches the values `solana-sys-tuner` sets
+const DESIRED_UDP_BUFFER_SIZE: u64 = 134_217_728;
+
+const MIN_FREE_DISK_SPACE: u64 = 10 * 1024 * 1024 * 1024;
+const RECOMMENDED_FREE_DISK_SPACE: u64 = 500 * 1024 * 1024 * 1024;
+
+const DISK_SYNC_TEST_WRITES: usize = 32;
+const DISK_SYNC_TEST_WRITE_SIZE: usize = 4096;
+const MIN_DISK_SYNC_WRITES_PER_SECOND: f64 = 100.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PreflightStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for PreflightStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PreflightStatus::Pass => write!(f, "PASS"),
+            PreflightStatus::Warn => write!(f, "WARN"),
+            PreflightStatus::Fail => write!(f, "FAIL"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PreflightCheck {
+    pub name: &'static str,
+    pub status: PreflightStatus,
+    pub detail: String,
+}
+
+impl PreflightCheck {
+    fn new(name: &'static str, status: PreflightStatus, detail: String) -> Self {
+        Self {
+            name,
+            status,
+            detail,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PreflightReport {
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    pub fn has_failures(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.status == PreflightStatus::Fail)
+    }
+
+    /// Logs each check at a level matching its status
+    pub fn log(&self) {
+        for check in &self.checks {
+            let line = format!(
+                "Preflight check {:<16} {}: {}",
+                check.name, check.status, check.detail
+            );
+            match check.status {
+                PreflightStatus::Pass => info!("{}", line),
+                PreflightStatus::Warn => warn!("{}", line),
+                PreflightStatus::Fail => error!("{}", line),
+            }
+        }
+    }
+}
+
+impl fmt::Display for PreflightReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{:<16} {}  {}", check.name, check.status, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs all the checks against the host, measuring the disk backing `ledger_path`
+pub fn run_preflight_checks(ledger_path: &Path) -> PreflightReport {
+    let mut checks = vec![];
+    checks.extend(check_nofile_limit());
+    checks.extend(check_udp_buffers());
+    checks.extend(check_disk_space(ledger_path));
+    checks.push(check_disk_sync_rate(ledger_path));
+    checks.extend(check_clock_sync());
+    checks.extend(check_cpu_features());
+    PreflightReport { checks }
+}
+
+#[cfg(unix)]
+fn check_nofile_limit() -> Option<PreflightCheck> {
+    let mut nofile = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut nofile) } != 0 {
+        return Some(PreflightCheck::new(
+            "nofile-limit",
+            PreflightStatus::Warn,
+            "getrlimit(RLIMIT_NOFILE) failed".to_string(),
+        ));
+    }
+    let (soft, hard) = (nofile.rlim_cur as u64, nofile.rlim_max as u64);
+    // The blockstore raises the soft limit itself, up to the hard limit
+    let status = if hard >= DESIRED_NOFILE {
+        PreflightStatus::Pass
+    } else {
+        PreflightStatus::Fail
+    };
+    Some(PreflightCheck::new(
+        "nofile-limit",
+        status,
+        format!(
+            "open file descriptor limit {} (hard {}), {} required",
+            soft, hard, DESIRED_NOFILE
+        ),
+    ))
+}
+
+#[cfg(not(unix))]
+fn check_nofile_limit() -> Option<PreflightCheck> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn check_udp_buffers() -> Vec<PreflightCheck> {
+    ["net.core.rmem_max", "net.core.wmem_max"]
+        .iter()
+        .map(|name| {
+            let path = format!("/proc/sys/{}", name.replace('.', "/"));
+            match fs::read_to_string(&path)
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+            {
+                Some(value) if value >= DESIRED_UDP_BUFFER_SIZE => PreflightCheck::new(
+                    "udp-buffers",
+                    PreflightStatus::Pass,
+                    format!("{} = {}", name, value),
+                ),
+                Some(value) => PreflightCheck::new(
+                    "udp-buffers",
+                    PreflightStatus::Warn,
+                    format!(
+                        "{} = {}, {} recommended; run solana-sys-tuner",
+                        name, value, DESIRED_UDP_BUFFER_SIZE
+                    ),
+                ),
+                None => PreflightCheck::new(
+                    "udp-buffers",
+                    PreflightStatus::Warn,
+                    format!("unable to read {}", path),
+                ),
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_udp_buffers() -> Vec<PreflightCheck> {
+    vec![]
+}
+
+#[cfg(unix)]
+fn check_disk_space(ledger_path: &Path) -> Option<PreflightCheck> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(ledger_path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Some(PreflightCheck::new(
+            "disk-space",
+            PreflightStatus::Warn,
+            format!("statvfs({:?}) failed", ledger_path),
+        ));
+    }
+    let free = stat.f_bavail as u64 * stat.f_frsize as u64;
+    Some(PreflightCheck::new(
+        "disk-space",
+        disk_space_status(free),
+        format!(
+            "{} GiB free at {:?}, {} GiB recommended",
+            free / (1024 * 1024 * 1024),
+            ledger_path,
+            RECOMMENDED_FREE_DISK_SPACE / (1024 * 1024 * 1024)
+        ),
+    ))
+}
+
+#[cfg(not(unix))]
+fn check_disk_space(_ledger_path: &Path) -> Option<PreflightCheck> {
+    None
+}
+
+fn disk_space_status(free: u64) -> PreflightStatus {
+    if free < MIN_FREE_DISK_SPACE {
+        PreflightStatus::Fail
+    } else if free < RECOMMENDED_FREE_DISK_SPACE {
+        PreflightStatus::Warn
+    } else {
+        PreflightStatus::Pass
+    }
+}
+
+/// Times synchronous writes to the ledger disk, a rough proxy for the IOPS RocksDB will get
+fn check_disk_sync_rate(ledger_path: &Path) -> PreflightCheck {
+    let path = ledger_path.join(".preflight-disk-sync-test");
+    let result = (|| {
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        let block = [0xa5u8; DISK_SYNC_TEST_WRITE_SIZE];
+        let start = Instant::now();
+        for _ in 0..DISK_SYNC_TEST_WRITES {
+            file.write_all(&block)?;
+            file.sync_data()?;
+        }
+        Ok::<_, std::io::Error>(start.elapsed())
+    })();
+    let _ = fs::remove_file(&path);
+
+    match result {
+        Ok(elapsed) => {
+            let writes_per_second =
+                DISK_SYNC_TEST_WRITES as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+            let status = if writes_per_second >= MIN_DISK_SYNC_WRITES_PER_SECOND {
+                PreflightStatus::Pass
+            } else {
+                PreflightStatus::Warn
+            };
+            PreflightCheck::new(
+                "disk-sync-rate",
+                status,
+                format!(
+                    "{:.0} synchronous writes/s, {:.0} recommended",
+                    writes_per_second, MIN_DISK_SYNC_WRITES_PER_SECOND
+                ),
+            )
+        }
+        Err(err) => PreflightCheck::new(
+            "disk-sync-rate",
+            PreflightStatus::Fail,
+            format!("unable to write to {:?}: {}", ledger_path, err),
+        ),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn check_clock_sync() -> Option<PreflightCheck> {
+    // adjtimex() returns TIME_ERROR while the kernel clock is not synchronized by NTP or PTP
+    const TIME_ERROR: libc::c_int = 5;
+
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+    Some(if state < 0 {
+        PreflightCheck::new(
+            "clock-sync",
+            PreflightStatus::Warn,
+            "adjtimex() failed".to_string(),
+        )
+    } else if state == TIME_ERROR {
+        PreflightCheck::new(
+            "clock-sync",
+            PreflightStatus::Warn,
+            "system clock is not synchronized; enable NTP".to_string(),
+        )
+    } else {
+        PreflightCheck::new(
+            "clock-sync",
+            PreflightStatus::Pass,
+            format!("system clock synchronized, max error {}us", timex.maxerror),
+        )
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_clock_sync() -> Option<PreflightCheck> {
+    None
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn check_cpu_features() -> Vec<PreflightCheck> {
+    let features = [
+        (
+            "avx",
+            is_x86_feature_detected!("avx"),
+            cfg!(target_feature = "avx"),
+        ),
+        (
+            "avx2",
+            is_x86_feature_detected!("avx2"),
+            cfg!(target_feature = "avx2"),
+        ),
+    ];
+    features
+        .iter()
+        .map(|(feature, detected, required_by_build)| {
+            if *detected {
+                PreflightCheck::new(
+                    "cpu-features",
+                    PreflightStatus::Pass,
+                    format!("{} supported", feature),
+                )
+            } else if *required_by_build {
+                PreflightCheck::new(
+                    "cpu-features",
+                    PreflightStatus::Fail,
+                    format!("{} required by this build but not supported", feature),
+                )
+            } else {
+                PreflightCheck::new(
+                    "cpu-features",
+                    PreflightStatus::Warn,
+                    format!(
+                        "{} not supported, signature verification will be slower",
+                        feature
+                    ),
+                )
+            }
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn check_cpu_features() -> Vec<PreflightCheck> {
+    vec![]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disk_space_status() {
+        assert_eq!(disk_space_status(0), PreflightStatus::Fail);
+        assert_eq!(
+            disk_space_status(MIN_FREE_DISK_SPACE),
+            PreflightStatus::Warn
+        );
+        assert_eq!(
+            disk_space_status(RECOMMENDED_FREE_DISK_SPACE),
+            PreflightStatus::Pass
+        );
+    }
+
+    #[test]
+    fn test_run_preflight_checks() {
+        let ledger_path = tempfile::tempdir().unwrap();
+        let report = run_preflight_checks(ledger_path.path());
+        let disk_sync_rate = report
+            .checks
+            .iter()
+            .find(|check| check.name == "disk-sync-rate")
+            .unwrap();
+        assert_ne!(disk_sync_rate.status, PreflightStatus::Fail);
+        assert_eq!(fs::read_dir(ledger_path.path()).unwrap().count(), 0);
+
+        let report = run_preflight_checks(&ledger_path.path().join("missing"));
+        assert!(report.has_failures());
+    }
+}
@@ -0,0 +1,271 @@
+//! Stake concentration checks, grouping the stake of the cluster's validators by software
+//! version or by the network their gossip address belongs to
+
+use {
+    solana_client::rpc_response::{RpcContactInfo, RpcVoteAccountStatus},
+    std::{
+        collections::HashMap,
+        fs, io,
+        net::{IpAddr, Ipv6Addr},
+        path::Path,
+    },
+};
+
+/// Maps an IP address to the network (datacenter, ASN, ...) it belongs to
+pub trait NetworkProvider {
+    fn network(&self, ip: &IpAddr) -> Option<String>;
+}
+
+/// Groups addresses by their /24 (IPv4) or /48 (IPv6) subnet, a rough approximation of the
+/// datacenter when no address range database is available
+#[derive(Default)]
+pub struct SubnetProvider;
+
+impl NetworkProvider for SubnetProvider {
+    fn network(&self, ip: &IpAddr) -> Option<String> {
+        Some(match ip {
+            IpAddr::V4(ip) => {
+                let octets = ip.octets();
+                format!("{}.{}.{}.0/24", octets[0], octets[1], octets[2])
+            }
+            IpAddr::V6(ip) => {
+                let segments = ip.segments();
+                format!("{:x}:{:x}:{:x}::/48", segments[0], segments[1], segments[2])
+            }
+        })
+    }
+}
+
+/// Looks addresses up in a file of address ranges, one `<first address>,<last address>,<network>`
+/// entry per line, such as an IP to ASN database export.  Empty lines and lines starting with
+/// `#` are ignored
+pub struct AddressRangeProvider {
+    ranges: Vec<(u128, u128, String)>,
+}
+
+impl AddressRangeProvider {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    fn parse(contents: &str) -> io::Result<Self> {
+        let invalid_line = |line_number: usize, line: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "invalid address range on line {}: {}",
+                    line_number + 1,
+                    line
+                ),
+            )
+        };
+
+        let mut ranges = vec![];
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut fields = line.splitn(3, ',').map(str::trim);
+            let first = fields.next().and_then(|first| first.parse::<IpAddr>().ok());
+            let last = fields.next().and_then(|last| last.parse::<IpAddr>().ok());
+            let network = fields.next().filter(|network| !network.is_empty());
+            match (first, last, network) {
+                (Some(first), Some(last), Some(network)) => {
+                    let (first, last) = (address_key(&first), address_key(&last));
+                    if first > last {
+                        return Err(invalid_line(line_number, line));
+                    }
+                    ranges.push((first, last, network.to_string()));
+                }
+                _ => return Err(invalid_line(line_number, line)),
+            }
+        }
+        ranges.sort_by_key(|(first, _, _)| *first);
+        Ok(Self { ranges })
+    }
+}
+
+impl NetworkProvider for AddressRangeProvider {
+    fn network(&self, ip: &IpAddr) -> Option<String> {
+        let key = address_key(ip);
+        // Last range starting at or before the address
+        let index = match self
+            .ranges
+            .binary_search_by_key(&key, |(first, _, _)| *first)
+        {
+            Ok(index) => index,
+            Err(0) => return None,
+            Err(index) => index - 1,
+        };
+        let (_, last, network) = &self.ranges[index];
+        if key <= *last {
+            Some(network.clone())
+        } else {
+            None
+        }
+    }
+}
+
+// IPv4 addresses are mapped into the IPv6 space so both can share the same ranges
+fn address_key(ip: &IpAddr) -> u128 {
+    let ip: Ipv6Addr = match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => *ip,
+    };
+    u128::from(ip)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Concentration {
+    /// The group holding the most stake
+    pub group: String,
+    pub stake: u64,
+    pub total_stake: u64,
+}
+
+impl Concentration {
+    pub fn percent(&self) -> f64 {
+        if self.total_stake == 0 {
+            0.
+        } else {
+            self.stake as f64 * 100. / self.total_stake as f64
+        }
+    }
+}
+
+/// Returns the group holding the most stake, using `group_of` to assign each gossip node to a
+/// group.  The stake of validators that cannot be grouped, for instance because they are not
+/// visible in gossip, only counts towards the total stake
+pub fn max_concentration<F>(
+    cluster_nodes: &[RpcContactInfo],
+    vote_accounts: &RpcVoteAccountStatus,
+    group_of: F,
+) -> Option<Concentration>
+where
+    F: Fn(&RpcContactInfo) -> Option<String>,
+{
+    let mut node_stakes = HashMap::new();
+    for vote_account in vote_accounts
+        .current
+        .iter()
+        .chain(vote_accounts.delinquent.iter())
+    {
+        *node_stakes
+            .entry(vote_account.node_pubkey.as_str())
+            .or_insert(0) += vote_account.activated_stake;
+    }
+    let total_stake = node_stakes.values().sum();
+
+    let mut group_stakes = HashMap::new();
+    for node in cluster_nodes {
+        if let Some(stake) = node_stakes.get(node.pubkey.as_str()) {
+            if let Some(group) = group_of(node) {
+                *group_stakes.entry(group).or_insert(0) += stake;
+            }
+        }
+    }
+
+    group_stakes
+        .into_iter()
+        .max_by(|(group_a, stake_a), (group_b, stake_b)| {
+            // Ties go to the first group by name, to keep notifications stable
+            stake_a.cmp(stake_b).then_with(|| group_b.cmp(group_a))
+        })
+        .map(|(group, stake)| Concentration {
+            group,
+            stake,
+            total_stake,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, solana_client::rpc_response::RpcVoteAccountInfo, std::net::SocketAddr};
+
+    fn contact_info(pubkey: &str, gossip: Option<&str>, version: Option<&str>) -> RpcContactInfo {
+        RpcContactInfo {
+            pubkey: pubkey.to_string(),
+            gossip: gossip.map(|gossip| gossip.parse::<SocketAddr>().unwrap()),
+            tpu: None,
+            rpc: None,
+            pubsub: None,
+            version: version.map(str::to_string),
+            feature_set: None,
+        }
+    }
+
+    fn vote_account(node_pubkey: &str, activated_stake: u64) -> RpcVoteAccountInfo {
+        RpcVoteAccountInfo {
+            vote_pubkey: String::default(),
+            node_pubkey: node_pubkey.to_string(),
+            activated_stake,
+            commission: 0,
+            epoch_vote_account: true,
+            epoch_credits: vec![],
+            last_vote: 0,
+            root_slot: 0,
+        }
+    }
+
+    #[test]
+    fn test_max_concentration() {
+        let cluster_nodes = vec![
+            contact_info("a", Some("10.0.0.1:8001"), Some("1.6.0")),
+            contact_info("b", Some("10.0.0.2:8001"), Some("1.5.0")),
+            contact_info("c", Some("10.0.1.1:8001"), Some("1.5.0")),
+            contact_info("d", None, None),
+        ];
+        let vote_accounts = RpcVoteAccountStatus {
+            current: vec![vote_account("a", 40), vote_account("b", 20)],
+            delinquent: vec![vote_account("c", 30), vote_account("e", 10)],
+        };
+
+        let by_version =
+            max_concentration(&cluster_nodes, &vote_accounts, |node| node.version.clone()).unwrap();
+        assert_eq!(
+            by_version,
+            Concentration {
+                group: "1.5.0".to_string(),
+                stake: 50,
+                total_stake: 100,
+            }
+        );
+        assert!((by_version.percent() - 50.).abs() < f64::EPSILON);
+
+        let subnet_provider = SubnetProvider::default();
+        let by_network = max_concentration(&cluster_nodes, &vote_accounts, |node| {
+            node.gossip
+                .and_then(|gossip| subnet_provider.network(&gossip.ip()))
+        })
+        .unwrap();
+        assert_eq!(by_network.group, "10.0.0.0/24");
+        assert_eq!(by_network.stake, 60);
+
+        assert_eq!(
+            max_concentration(&cluster_nodes, &vote_accounts, |_| None),
+            None
+        );
+    }
+
+    #[test]
+    fn test_address_range_provider() {
+        let provider = AddressRangeProvider::parse(
+            "# first,last,network\n\
+             10.0.1.0,10.0.1.255,AS2 Example\n\
+             \n\
+             10.0.0.0,10.0.0.255,AS1\n\
+             2001:db8::,2001:db8::ffff,AS3\n",
+        )
+        .unwrap();
+        let network = |ip: &str| provider.network(&ip.parse().unwrap());
+        assert_eq!(network("10.0.0.0"), Some("AS1".to_string()));
+        assert_eq!(network("10.0.1.42"), Some("AS2 Example".to_string()));
+        assert_eq!(network("10.0.2.1"), None);
+        assert_eq!(network("9.255.255.255"), None);
+        assert_eq!(network("2001:db8::1"), Some("AS3".to_string()));
+
+        assert!(AddressRangeProvider::parse("10.0.0.0,AS1").is_err());
+        assert!(AddressRangeProvider::parse("10.0.0.255,10.0.0.0,AS1").is_err());
+    }
+}
@@ -2,13 +2,19 @@
 
 use {
     clap::{crate_description, crate_name, value_t, value_t_or_exit, App, Arg},
+    concentration::{max_concentration, AddressRangeProvider, NetworkProvider, SubnetProvider},
     log::*,
     solana_clap_utils::{
         input_parsers::pubkeys_of,
-        input_validators::{is_parsable, is_pubkey_or_keypair, is_url},
+        input_validators::{is_parsable, is_pubkey_or_keypair, is_url, is_valid_percentage},
     },
     solana_cli_output::display::format_labeled_address,
-    solana_client::{client_error, rpc_client::RpcClient, rpc_response::RpcVoteAccountStatus},
+    solana_client::{
+        client_error,
+        pubsub_client::PubsubClient,
+        rpc_client::RpcClient,
+        rpc_response::{EpochBoundaryInfo, RpcContactInfo, RpcVoteAccountStatus},
+    },
     solana_metrics::{datapoint_error, datapoint_info},
     solana_notifier::Notifier,
     solana_sdk::{
@@ -19,11 +25,15 @@ use {
     std::{
         collections::HashMap,
         error,
-        thread::sleep,
+        path::Path,
+        process::exit,
+        thread::{self, sleep},
         time::{Duration, Instant},
     },
 };
 
+mod concentration;
+
 struct Config {
     address_labels: HashMap<String, String>,
     ignore_http_bad_gateway: bool,
@@ -31,10 +41,20 @@ struct Config {
     json_rpc_url: String,
     minimum_validator_identity_balance: u64,
     monitor_active_stake: bool,
+    monitor_epoch_boundaries: bool,
+    max_version_stake_percent: Option<u8>,
+    max_network_stake_percent: Option<u8>,
+    network_provider: Box<dyn NetworkProvider>,
     unhealthy_threshold: usize,
     validator_identity_pubkeys: Vec<Pubkey>,
 }
 
+impl Config {
+    fn monitor_concentration(&self) -> bool {
+        self.max_version_stake_percent.is_some() || self.max_network_stake_percent.is_some()
+    }
+}
+
 fn get_config() -> Config {
     let matches = App::new(crate_name!())
         .about(crate_description!())
@@ -124,6 +144,41 @@ fn get_config() -> Config {
                 .takes_value(false)
                 .help("Alert when the current stake for the cluster drops below 80%"),
         )
+        .arg(
+            Arg::with_name("monitor_epoch_boundaries")
+                .long("monitor-epoch-boundaries")
+                .takes_value(false)
+                .help("Notify when the cluster roots a new epoch, with its stake changes and \
+                    feature activations. Requires the RPC PubSub websocket of the JSON RPC URL"),
+        )
+        .arg(
+            Arg::with_name("max_version_stake_percent")
+                .long("monitor-version-concentration")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_valid_percentage)
+                .help("Alert when the validators running a single software version \
+                    hold more than this percentage of the stake"),
+        )
+        .arg(
+            Arg::with_name("max_network_stake_percent")
+                .long("monitor-datacenter-concentration")
+                .value_name("PERCENT")
+                .takes_value(true)
+                .validator(is_valid_percentage)
+                .help("Alert when the validators hosted in a single network hold more than \
+                    this percentage of the stake. Networks are /24 subnets unless \
+                    --address-ranges is provided"),
+        )
+        .arg(
+            Arg::with_name("address_ranges")
+                .long("address-ranges")
+                .value_name("PATH")
+                .takes_value(true)
+                .requires("max_network_stake_percent")
+                .help("File mapping address ranges to networks, such as ASNs or datacenters, \
+                    one \"<first address>,<last address>,<network>\" entry per line"),
+        )
         .arg(
             Arg::with_name("ignore_http_bad_gateway")
                 .long("ignore-http-bad-gateway")
@@ -156,7 +211,20 @@ fn get_config() -> Config {
         .collect();
 
     let monitor_active_stake = matches.is_present("monitor_active_stake");
+    let monitor_epoch_boundaries = matches.is_present("monitor_epoch_boundaries");
     let ignore_http_bad_gateway = matches.is_present("ignore_http_bad_gateway");
+    let max_version_stake_percent = value_t!(matches, "max_version_stake_percent", u8).ok();
+    let max_network_stake_percent = value_t!(matches, "max_network_stake_percent", u8).ok();
+    let network_provider: Box<dyn NetworkProvider> = match matches.value_of("address_ranges") {
+        Some(address_ranges) => match AddressRangeProvider::load(Path::new(address_ranges)) {
+            Ok(provider) => Box::new(provider),
+            Err(err) => {
+                eprintln!("Unable to load {}: {}", address_ranges, err);
+                exit(1);
+            }
+        },
+        None => Box::new(SubnetProvider::default()),
+    };
 
     let config = Config {
         address_labels: config.address_labels,
@@ -165,6 +233,10 @@ fn get_config() -> Config {
         json_rpc_url,
         minimum_validator_identity_balance,
         monitor_active_stake,
+        monitor_epoch_boundaries,
+        max_version_stake_percent,
+        max_network_stake_percent,
+        network_provider,
         unhealthy_threshold,
         validator_identity_pubkeys,
     };
@@ -177,13 +249,23 @@ fn get_config() -> Config {
     config
 }
 
-fn get_cluster_info(
-    config: &Config,
-    rpc_client: &RpcClient,
-) -> client_error::Result<(u64, Hash, RpcVoteAccountStatus, HashMap<Pubkey, u64>)> {
+struct ClusterInfo {
+    transaction_count: u64,
+    recent_blockhash: Hash,
+    vote_accounts: RpcVoteAccountStatus,
+    validator_balances: HashMap<Pubkey, u64>,
+    cluster_nodes: Vec<RpcContactInfo>,
+}
+
+fn get_cluster_info(config: &Config, rpc_client: &RpcClient) -> client_error::Result<ClusterInfo> {
     let transaction_count = rpc_client.get_transaction_count()?;
     let recent_blockhash = rpc_client.get_recent_blockhash()?.0;
     let vote_accounts = rpc_client.get_vote_accounts()?;
+    let cluster_nodes = if config.monitor_concentration() {
+        rpc_client.get_cluster_nodes()?
+    } else {
+        vec![]
+    };
 
     let mut validator_balances = HashMap::new();
     for validator_identity in &config.validator_identity_pubkeys {
@@ -193,12 +275,131 @@ fn get_cluster_info(
         );
     }
 
-    Ok((
+    Ok(ClusterInfo {
         transaction_count,
         recent_blockhash,
         vote_accounts,
         validator_balances,
-    ))
+        cluster_nodes,
+    })
+}
+
+// Returns a failure message for each stake concentration above its configured maximum
+fn check_concentration(
+    config: &Config,
+    cluster_nodes: &[RpcContactInfo],
+    vote_accounts: &RpcVoteAccountStatus,
+) -> Vec<(&'static str, String)> {
+    let mut failures = vec![];
+
+    if let Some(max_percent) = config.max_version_stake_percent {
+        if let Some(concentration) =
+            max_concentration(cluster_nodes, vote_accounts, |node| node.version.clone())
+        {
+            info!(
+                "Largest version stake: {} with {:.1}%",
+                concentration.group,
+                concentration.percent()
+            );
+            datapoint_info!(
+                "watchtower-concentration",
+                ("kind", "version", String),
+                ("group", concentration.group, String),
+                ("percent", concentration.percent(), f64),
+            );
+            if concentration.percent() > max_percent as f64 {
+                failures.push((
+                    "version-concentration",
+                    format!(
+                        "Version {} holds {:.1}% of the stake",
+                        concentration.group,
+                        concentration.percent()
+                    ),
+                ));
+            }
+        }
+    }
+
+    if let Some(max_percent) = config.max_network_stake_percent {
+        if let Some(concentration) = max_concentration(cluster_nodes, vote_accounts, |node| {
+            node.gossip
+                .and_then(|gossip| config.network_provider.network(&gossip.ip()))
+        }) {
+            info!(
+                "Largest network stake: {} with {:.1}%",
+                concentration.group,
+                concentration.percent()
+            );
+            datapoint_info!(
+                "watchtower-concentration",
+                ("kind", "network", String),
+                ("group", concentration.group, String),
+                ("percent", concentration.percent(), f64),
+            );
+            if concentration.percent() > max_percent as f64 {
+                failures.push((
+                    "datacenter-concentration",
+                    format!(
+                        "Network {} holds {:.1}% of the stake",
+                        concentration.group,
+                        concentration.percent()
+                    ),
+                ));
+            }
+        }
+    }
+
+    failures
+}
+
+fn epoch_boundary_message(epoch_boundary: &EpochBoundaryInfo) -> String {
+    let mut message = format!(
+        "Epoch {} rooted at slot {}: total stake {} (was {}), {} vote accounts changed stake",
+        epoch_boundary.epoch,
+        epoch_boundary.root,
+        Sol(epoch_boundary.total_active_stake),
+        Sol(epoch_boundary.prior_total_active_stake),
+        epoch_boundary.stake_changes.len(),
+    );
+    for feature in &epoch_boundary.activated_features {
+        message.push_str(&format!(
+            "\nFeature activated at slot {}: {} ({})",
+            feature.activation_slot, feature.name, feature.id
+        ));
+    }
+    message
+}
+
+// Follows the epoch boundaries of the cluster over the RPC PubSub websocket, resubscribing
+// whenever the connection is lost
+fn monitor_epoch_boundaries(websocket_url: String, interval: Duration) {
+    let notifier = Notifier::default();
+    loop {
+        match PubsubClient::epoch_subscribe(&websocket_url) {
+            Ok((_subscription, receiver)) => {
+                info!("Subscribed to epoch boundaries at {}", websocket_url);
+                for epoch_boundary in receiver.iter() {
+                    let message = epoch_boundary_message(&epoch_boundary);
+                    info!("{}", message);
+                    datapoint_info!(
+                        "watchtower-epoch-boundary",
+                        ("epoch", epoch_boundary.epoch, i64),
+                        ("total_active_stake", epoch_boundary.total_active_stake, i64),
+                        ("stake_changes", epoch_boundary.stake_changes.len(), i64),
+                        (
+                            "activated_features",
+                            epoch_boundary.activated_features.len(),
+                            i64
+                        ),
+                    );
+                    notifier.send(&format!("solana-watchtower: {}", message));
+                }
+                warn!("Epoch boundary subscription closed");
+            }
+            Err(err) => warn!("Unable to subscribe to epoch boundaries: {}", err),
+        }
+        sleep(interval);
+    }
 }
 
 fn main() -> Result<(), Box<dyn error::Error>> {
@@ -208,6 +409,13 @@ fn main() -> Result<(), Box<dyn error::Error>> {
     let config = get_config();
 
     let rpc_client = RpcClient::new(config.json_rpc_url.clone());
+    if config.monitor_epoch_boundaries {
+        let websocket_url = solana_cli_config::Config::compute_websocket_url(&config.json_rpc_url);
+        let interval = config.interval;
+        thread::Builder::new()
+            .name("solana-watchtower-epoch".to_string())
+            .spawn(move || monitor_epoch_boundaries(websocket_url, interval))?;
+    }
     let notifier = Notifier::default();
     let mut last_transaction_count = 0;
     let mut last_recent_blockhash = Hash::default();
@@ -217,7 +425,13 @@ fn main() -> Result<(), Box<dyn error::Error>> {
 
     loop {
         let failure = match get_cluster_info(&config, &rpc_client) {
-            Ok((transaction_count, recent_blockhash, vote_accounts, validator_balances)) => {
+            Ok(ClusterInfo {
+                transaction_count,
+                recent_blockhash,
+                vote_accounts,
+                validator_balances,
+                cluster_nodes,
+            }) => {
                 info!("Current transaction count: {}", transaction_count);
                 info!("Recent blockhash: {}", recent_blockhash);
                 info!("Current validator count: {}", vote_accounts.current.len());
@@ -312,6 +526,8 @@ fn main() -> Result<(), Box<dyn error::Error>> {
                     failures.push(("delinquent", validator_errors.join(",")));
                 }
 
+                failures.extend(check_concentration(&config, &cluster_nodes, &vote_accounts));
+
                 for failure in failures.iter() {
                     error!("{} sanity failure: {}", failure.0, failure.1);
                 }